@@ -0,0 +1,75 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A hook for code which embeds autocxx to drop or adjust the APIs which
+//! it would otherwise generate, according to rules which can't be
+//! expressed as directives. See `Builder::api_filter`.
+
+use crate::api_report::ApiKind;
+
+/// What the [`ApiFilter`] is told about each API. The shape of this may
+/// change between versions of autocxx.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiSummary {
+    /// The qualified C++ name, e.g. `ns::Widget::frob`.
+    pub name: String,
+    pub kind: ApiKind,
+    /// The path of the binding within the generated mod, e.g.
+    /// `ns::Widget::frob`.
+    pub rust_name: String,
+    /// For functions and methods, roughly their Rust signature, e.g.
+    /// `fn frob(self: &Widget, count: u32) -> bool`.
+    pub signature: Option<String>,
+    /// The directive which asked for this API (or the type to which it
+    /// belongs), if any.
+    pub requested_by: Option<String>,
+}
+
+/// What to do with an API. The default is to generate it unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiDecision {
+    /// Don't generate the API. Anything which depends on it isn't
+    /// generated either, as if it had been on the blocklist.
+    pub exclude: bool,
+    /// Lines to add to the end of the API's documentation. These are
+    /// added to functions, methods, types and enums.
+    pub extra_docs: Vec<String>,
+    /// Mark a function or method `#[must_use]`.
+    pub must_use: bool,
+    /// Mark a function, method or type `#[deprecated]`, with this note.
+    /// This replaces any note from a C++ `[[deprecated]]` attribute.
+    pub deprecated: Option<String>,
+}
+
+impl ApiDecision {
+    /// Generate the API unchanged.
+    pub fn keep() -> Self {
+        Self::default()
+    }
+
+    /// Don't generate the API.
+    pub fn exclude() -> Self {
+        Self {
+            exclude: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Function to decide what to do with each API which autocxx would
+/// otherwise generate. It's called once for each API, after all analysis.
+/// Newtype wrapper so that [`crate::CodegenOptions`] can hold it.
+pub struct ApiFilter<'a>(pub Box<ApiFilterFn<'a>>);
+
+type ApiFilterFn<'a> = dyn 'a + Fn(&ApiSummary) -> ApiDecision;
+
+impl ApiFilter<'_> {
+    pub(crate) fn decide(&self, summary: &ApiSummary) -> ApiDecision {
+        self.0(summary)
+    }
+}
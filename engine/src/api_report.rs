@@ -0,0 +1,354 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A machine-readable report of every C++ API which autocxx discovered,
+//! whether it generated bindings for each, and if not, why not. This is
+//! intended to be diffed between versions of a C++ library, so everything
+//! is sorted by name.
+
+use std::{fmt::Display, path::Path};
+
+use serde_json::{json, Value};
+use syn::{ForeignItem, Item, ItemMod, ItemType};
+
+use crate::{conversion::ConvertErrorFromCpp, types::QualifiedName, IncludeCppEngine, ParsedFile};
+
+/// What sort of C++ API something is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiKind {
+    Function,
+    Method,
+    Type,
+    Typedef,
+    Enum,
+    Constant,
+    TemplateInstantiation,
+    /// Something we couldn't generate bindings for, and so didn't get as
+    /// far as classifying.
+    Item,
+}
+
+impl ApiKind {
+    const ALL: [Self; 8] = [
+        Self::Function,
+        Self::Method,
+        Self::Type,
+        Self::Typedef,
+        Self::Enum,
+        Self::Constant,
+        Self::TemplateInstantiation,
+        Self::Item,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Method => "method",
+            Self::Type => "type",
+            Self::Typedef => "typedef",
+            Self::Enum => "enum",
+            Self::Constant => "constant",
+            Self::TemplateInstantiation => "template instantiation",
+            Self::Item => "item",
+        }
+    }
+
+    fn from_str(kind: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == kind)
+    }
+}
+
+impl Display for ApiKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One C++ API which autocxx discovered, and what became of it.
+#[derive(Debug, Clone)]
+pub struct ReportedApi {
+    /// The qualified C++ name, e.g. `ns::Widget::frob`.
+    pub name: String,
+    /// The name by which autocxx knows this API internally. For methods
+    /// and other items which bindgen names after their type, this differs
+    /// from [`Self::name`].
+    pub qualified_name: QualifiedName,
+    pub kind: ApiKind,
+    /// The path of the binding within the generated mod, e.g.
+    /// `ns::Widget::frob`, if one was generated.
+    pub rust_name: Option<String>,
+    /// Why bindings weren't generated, if they weren't.
+    pub ignored_reason: Option<String>,
+    /// The error behind [`Self::ignored_reason`]. This isn't kept when
+    /// bindings are cached, so is missing if they came from the cache.
+    pub error: Option<ConvertErrorFromCpp>,
+    /// The directive which asked for this API (or the type to which it
+    /// belongs), if any.
+    pub requested_by: Option<String>,
+}
+
+/// Ignores the [`ReportedApi::error`], which has the same information as
+/// the `ignored_reason` but can't be cached.
+impl PartialEq for ReportedApi {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.qualified_name == other.qualified_name
+            && self.kind == other.kind
+            && self.rust_name == other.rust_name
+            && self.ignored_reason == other.ignored_reason
+            && self.requested_by == other.requested_by
+    }
+}
+
+impl Eq for ReportedApi {}
+
+impl ReportedApi {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "qualified_name": self.qualified_name.to_cpp_name(),
+            "kind": self.kind.as_str(),
+            "rust_name": self.rust_name,
+            "generated": self.ignored_reason.is_none(),
+            "reason": self.ignored_reason,
+            "requested_by": self.requested_by,
+        })
+    }
+
+    /// The reverse of [`Self::to_json`], except that there's no
+    /// [`Self::error`].
+    pub(crate) fn from_json(value: &Value) -> Option<Self> {
+        let optional_string = |field: &str| match value.get(field)? {
+            Value::Null => Some(None),
+            value => Some(Some(value.as_str()?.to_string())),
+        };
+        Some(Self {
+            name: value.get("name")?.as_str()?.to_string(),
+            qualified_name: QualifiedName::new_from_cpp_name(
+                value.get("qualified_name")?.as_str()?,
+            ),
+            kind: ApiKind::from_str(value.get("kind")?.as_str()?)?,
+            rust_name: optional_string("rust_name")?,
+            ignored_reason: optional_string("reason")?,
+            error: None,
+            requested_by: optional_string("requested_by")?,
+        })
+    }
+}
+
+/// Sorts the APIs by name, then kind, dropping any duplicates.
+pub(crate) fn sort_reported_apis(apis: &mut Vec<ReportedApi>) {
+    apis.sort_by(|a, b| (&a.name, a.kind).cmp(&(&b.name, b.kind)));
+    apis.dedup_by(|a, b| a.name == b.name && a.kind == b.kind);
+}
+
+/// What autocxx made of one `include_cpp!`, for tools which want to know
+/// programmatically. Get one from
+/// [`IncludeCppEngine::conversion_summary`]. The shape of this may change
+/// between versions of autocxx.
+#[derive(Debug, Clone)]
+pub struct ConversionSummary {
+    pub mod_name: String,
+    /// Every API which was discovered in the C++, sorted by name.
+    pub apis: Vec<ReportedApi>,
+    /// The names of the C++ headers which were generated. The first is
+    /// that generated by cxx.
+    pub cpp_headers: Vec<String>,
+    /// The names of the functions and types declared in the `cxx::bridge`.
+    pub cxx_bridge_items: Vec<String>,
+}
+
+impl ConversionSummary {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "mod": self.mod_name,
+            "apis": self.apis.iter().map(ReportedApi::to_json).collect::<Vec<_>>(),
+            "cpp_headers": self.cpp_headers,
+            "cxx_bridge_items": self.cxx_bridge_items,
+        })
+    }
+}
+
+/// The names of the functions and types declared within the
+/// `#[cxx::bridge] mod cxxbridge` inside the generated mod.
+pub(crate) fn cxx_bridge_items(item_mod: &ItemMod) -> Vec<String> {
+    let bridge_items = item_mod
+        .content
+        .iter()
+        .flat_map(|(_, items)| items)
+        .filter_map(|item| match item {
+            Item::Mod(bridge) if bridge.ident == "cxxbridge" => bridge.content.as_ref(),
+            _ => None,
+        })
+        .flat_map(|(_, items)| items);
+    bridge_items
+        .filter_map(|item| match item {
+            Item::ForeignMod(foreign_mod) => Some(&foreign_mod.items),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|item| match item {
+            ForeignItem::Fn(fun) => Some(fun.sig.ident.to_string()),
+            ForeignItem::Type(typ) => Some(typ.ident.to_string()),
+            // cxx's `type Foo = path::to::Foo;` isn't valid Rust in an
+            // extern block, so syn can't parse it as a type.
+            ForeignItem::Verbatim(tokens) => syn::parse2::<ItemType>(tokens.clone())
+                .ok()
+                .map(|typ| typ.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn include_cpp_report(include_cpp: &IncludeCppEngine) -> Value {
+    json!({
+        "mod": include_cpp.get_mod_name(),
+        "apis": include_cpp
+            .reported_apis()
+            .iter()
+            .map(ReportedApi::to_json)
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Writes a JSON report of the APIs discovered by every `include_cpp!` in
+/// these files, in order. Call `resolve_all` on each file first.
+pub fn write_api_report<'a>(
+    path: &Path,
+    parsed_files: impl IntoIterator<Item = &'a ParsedFile>,
+) -> std::io::Result<()> {
+    let include_cpps: Vec<_> = parsed_files
+        .into_iter()
+        .flat_map(|parsed_file| parsed_file.get_autocxxes())
+        .map(include_cpp_report)
+        .collect();
+    let report = json!({ "include_cpps": include_cpps });
+    let mut contents = serde_json::to_string_pretty(&report)?;
+    contents.push('\n');
+    std::fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cxx_bridge_items, sort_reported_apis, ApiKind, ConversionSummary, ReportedApi};
+    use crate::types::QualifiedName;
+    use serde_json::json;
+    use syn::parse_quote;
+
+    fn reported(name: &str, kind: ApiKind, ignored_reason: Option<&str>) -> ReportedApi {
+        ReportedApi {
+            name: name.to_string(),
+            qualified_name: QualifiedName::new_from_cpp_name(&name.replace("::frob", "_frob")),
+            kind,
+            rust_name: ignored_reason.is_none().then(|| name.to_string()),
+            ignored_reason: ignored_reason.map(str::to_string),
+            error: None,
+            requested_by: Some("generate!(\"ns::*\")".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_reported_apis() {
+        let mut apis = vec![
+            reported(
+                "ns::Widget::frob",
+                ApiKind::Method,
+                Some("it was too frobby"),
+            ),
+            reported("ns::Widget", ApiKind::Type, None),
+            reported(
+                "ns::Widget::frob",
+                ApiKind::Method,
+                Some("it was too frobby"),
+            ),
+            reported("ns::Gadget", ApiKind::Enum, None),
+        ];
+        sort_reported_apis(&mut apis);
+        let names: Vec<_> = apis.iter().map(|api| api.name.as_str()).collect();
+        assert_eq!(names, ["ns::Gadget", "ns::Widget", "ns::Widget::frob"]);
+        let json = apis[2].to_json();
+        assert_eq!(json["generated"], false);
+        assert_eq!(json["reason"], "it was too frobby");
+        assert_eq!(apis[0].to_json()["reason"], serde_json::Value::Null);
+        for api in apis {
+            assert_eq!(ReportedApi::from_json(&api.to_json()), Some(api));
+        }
+    }
+
+    #[test]
+    fn test_conversion_summary() {
+        let summary = ConversionSummary {
+            mod_name: "ffi".to_string(),
+            apis: vec![
+                reported("ns::Widget", ApiKind::Type, None),
+                reported(
+                    "ns::Widget::frob",
+                    ApiKind::Method,
+                    Some("it was too frobby"),
+                ),
+            ],
+            cpp_headers: vec!["cxxgen.h".to_string(), "autocxxgen_ffi.h".to_string()],
+            cxx_bridge_items: vec!["Widget".to_string()],
+        };
+        assert_eq!(
+            summary.to_json(),
+            json!({
+                "mod": "ffi",
+                "apis": [
+                    {
+                        "name": "ns::Widget",
+                        "qualified_name": "ns::Widget",
+                        "kind": "type",
+                        "rust_name": "ns::Widget",
+                        "generated": true,
+                        "reason": null,
+                        "requested_by": "generate!(\"ns::*\")",
+                    },
+                    {
+                        "name": "ns::Widget::frob",
+                        "qualified_name": "ns::Widget_frob",
+                        "kind": "method",
+                        "rust_name": null,
+                        "generated": false,
+                        "reason": "it was too frobby",
+                        "requested_by": "generate!(\"ns::*\")",
+                    },
+                ],
+                "cpp_headers": ["cxxgen.h", "autocxxgen_ffi.h"],
+                "cxx_bridge_items": ["Widget"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_cxx_bridge_items() {
+        let item_mod: syn::ItemMod = parse_quote! {
+            mod ffi {
+                #[cxx::bridge]
+                mod cxxbridge {
+                    impl UniquePtr<Widget> {}
+                    unsafe extern "C++" {
+                        fn give_int() -> u32;
+                        type Widget = super::bindgen::root::ns::Widget;
+                        type Gadget;
+                    }
+                    extern "Rust" {
+                        fn call_me();
+                    }
+                }
+                pub use cxxbridge::give_int;
+            }
+        };
+        assert_eq!(
+            cxx_bridge_items(&item_mod),
+            ["give_int", "Widget", "Gadget", "call_me"]
+        );
+    }
+}
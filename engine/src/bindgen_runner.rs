@@ -0,0 +1,281 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Running bindgen over the headers of an `include_cpp!`. This is usually
+//! by far the slowest part of generating bindings, since clang must parse
+//! every header, but the bindgen runs for the different `include_cpp!`s
+//! in a file are independent of one another. So everything bindgen needs
+//! is gathered here, away from the [`crate::IncludeCppEngine`] (whose syn
+//! types can't be sent between threads), such that those runs can happen
+//! in parallel.
+
+use std::{cell::RefCell, rc::Rc, sync::Mutex};
+
+use autocxx_bindgen::BindgenError;
+use itertools::Itertools;
+
+use crate::{
+    bindgen,
+    header_recovery::{attribute_errors, SynthesizedHeader},
+    known_types::known_types,
+    libclang::{examine_header, CONCRETE_MEMBERS_HEADER, CONCRETE_MEMBERS_NAMESPACE},
+    parse_callbacks::AutocxxParseCallbacks,
+    DroppedHeader, RebuildDependencyRecorder,
+};
+
+/// Everything bindgen needs to know about a single `include_cpp!`.
+pub(crate) struct BindgenInputs {
+    pub(crate) inclusions: Vec<String>,
+    pub(crate) clang_args: Vec<String>,
+    pub(crate) keep_inline_namespaces: bool,
+    pub(crate) allowlist: Option<Vec<String>>,
+    pub(crate) opaque_types: Vec<String>,
+    /// The C++ definition and Rust name of each `concrete!` type.
+    pub(crate) concretes: Vec<(String, String)>,
+}
+
+/// What bindgen made of a single `include_cpp!`.
+pub(crate) struct BindgenOutput {
+    /// The bindings, as a string, because bindgen's own representation
+    /// can't be sent between threads.
+    pub(crate) bindings: String,
+    /// The headers which made it into the bindings.
+    pub(crate) inclusions: Vec<String>,
+    pub(crate) dropped_headers: Vec<DroppedHeader>,
+    /// Every header which clang read along the way.
+    pub(crate) dependencies: Vec<String>,
+}
+
+/// Collects the headers which clang reads.
+#[derive(Debug, Default)]
+struct DependencyList(RefCell<Vec<String>>);
+
+impl RebuildDependencyRecorder for DependencyList {
+    fn record_header_file_dependency(&self, filename: &str) {
+        self.0.borrow_mut().push(filename.to_string());
+    }
+}
+
+impl BindgenInputs {
+    fn synthesize_header(inclusions: &[String]) -> SynthesizedHeader<'static> {
+        SynthesizedHeader::new(
+            "example.hpp",
+            &known_types().get_prelude(),
+            &crate::IncludeCppEngine::build_header(inclusions),
+        )
+    }
+
+    fn make_bindgen_builder(&self) -> bindgen::Builder {
+        let mut builder = bindgen::builder()
+            .clang_args(&self.clang_args)
+            .derive_copy(false)
+            .derive_debug(false)
+            .default_enum_style(bindgen::EnumVariation::Rust {
+                non_exhaustive: false,
+            })
+            .formatter(if log::log_enabled!(log::Level::Info) {
+                bindgen::Formatter::Rustfmt
+            } else {
+                bindgen::Formatter::None
+            })
+            .size_t_is_usize(true)
+            .enable_cxx_namespaces()
+            .generate_inline_functions(true)
+            .respect_cxx_access_specs(true)
+            .use_specific_virtual_function_receiver(true)
+            .cpp_semantic_attributes(true)
+            .represent_cxx_operators(true)
+            .use_distinct_char16_t(true)
+            .array_pointers_in_arguments(true)
+            // We don't keep the layout tests, but they're where bindgen
+            // tells us the offset of each field.
+            .layout_tests(true);
+        if self.keep_inline_namespaces {
+            builder = builder.conservative_inline_namespaces();
+        }
+        for item in known_types().get_initial_blocklist() {
+            builder = builder.blocklist_item(item);
+        }
+
+        // 3. Passes allowlist and other options to the bindgen::Builder equivalent
+        //    to --output-style=cxx --allowlist=<as passed in>
+        if let Some(allowlist) = &self.allowlist {
+            for a in allowlist {
+                // TODO - allowlist type/functions/separately
+                builder = builder
+                    .allowlist_type(a)
+                    .allowlist_function(a)
+                    .allowlist_function(within_anonymous_namespaces(a))
+                    .allowlist_var(a);
+            }
+            builder =
+                builder.allowlist_function(format!("(.*::)?{CONCRETE_MEMBERS_NAMESPACE}::.*"));
+        }
+        // Excluded namespaces: bindgen need only know the size and
+        // alignment of their types, and we don't want their functions at all.
+        for excluded in &self.opaque_types {
+            builder = builder.opaque_type(excluded).blocklist_function(excluded);
+        }
+
+        log::info!(
+            "Bindgen flags would be: {}",
+            builder
+                .command_line_flags()
+                .into_iter()
+                .map(|f| format!("\"{f}\""))
+                .join(" ")
+        );
+        builder
+    }
+
+    /// Runs bindgen over `header`, and over `concrete_members` (see
+    /// [`examine_header`]) if given.
+    fn run_bindgen(
+        &self,
+        header: &SynthesizedHeader,
+        concrete_members: Option<&str>,
+        dep_recorder: Option<&Rc<DependencyList>>,
+    ) -> Result<bindgen::Bindings, BindgenError> {
+        let mut builder = self.make_bindgen_builder();
+        if let Some(dep_recorder) = dep_recorder {
+            builder =
+                builder.parse_callbacks(Box::new(AutocxxParseCallbacks(dep_recorder.clone())));
+        }
+        log::info!("Header and prelude for bindgen:\n{}", header.contents);
+        // bindgen `-include`s all but the first header, so this one goes
+        // first for ours to come before it.
+        if let Some(concrete_members) = concrete_members {
+            log::info!("Concrete members for bindgen:\n{concrete_members}");
+            builder = builder.header_contents(CONCRETE_MEMBERS_HEADER, concrete_members);
+        }
+        builder
+            .header_contents(header.name, &header.contents)
+            .generate()
+    }
+
+    /// Runs bindgen over all our headers. If clang can't parse some of
+    /// them, we try again without them, so long as we can tell which
+    /// headers are at fault and some others remain.
+    pub(crate) fn run(&self) -> Result<BindgenOutput, BindgenError> {
+        let dependencies = Rc::new(DependencyList::default());
+        let mut inclusions = self.inclusions.clone();
+        let mut dropped_headers = Vec::new();
+        loop {
+            let header = Self::synthesize_header(&inclusions);
+            let facts = examine_header(&header, &self.clang_args, &self.concretes);
+            let message =
+                match self.run_bindgen(&header, facts.concrete_members(), Some(&dependencies)) {
+                    Ok(bindings) => {
+                        return Ok(BindgenOutput {
+                            bindings: facts.annotate_bindings(bindings.to_string()),
+                            inclusions,
+                            dropped_headers,
+                            dependencies: dependencies.0.take(),
+                        })
+                    }
+                    Err(BindgenError::ClangDiagnostic(message)) => message,
+                    Err(e) => return Err(e),
+                };
+            let (mut culprits, _) = attribute_errors(&message, &inclusions, &header);
+            if culprits.is_empty() {
+                // The errors are somewhere else, perhaps in a header which
+                // one of ours includes. Parse successively more of our
+                // headers (since later ones may rely on earlier ones)
+                // until we find the first which fails.
+                for idx in 0..inclusions.len() {
+                    let header = Self::synthesize_header(&inclusions[..=idx]);
+                    if let Err(BindgenError::ClangDiagnostic(message)) =
+                        self.run_bindgen(&header, None, None)
+                    {
+                        culprits.insert(
+                            idx,
+                            message
+                                .lines()
+                                .filter(|line| !line.trim().is_empty())
+                                .map(str::to_string)
+                                .collect(),
+                        );
+                        break;
+                    }
+                }
+            }
+            if culprits.is_empty() || culprits.len() == inclusions.len() {
+                return Err(BindgenError::ClangDiagnostic(message));
+            }
+            let mut remaining = Vec::new();
+            for (idx, inclusion) in inclusions.into_iter().enumerate() {
+                match culprits.remove(&idx) {
+                    Some(diagnostics) => {
+                        let dropped = DroppedHeader {
+                            header: inclusion,
+                            diagnostics,
+                        };
+                        log::warn!("{}", dropped);
+                        dropped_headers.push(dropped);
+                    }
+                    None => remaining.push(inclusion),
+                }
+            }
+            inclusions = remaining;
+        }
+    }
+}
+
+/// Runs bindgen for each of the given `include_cpp!`s, using up to `jobs`
+/// threads. The results are in the same order as the inputs, whichever
+/// order the runs finish in.
+pub(crate) fn run_in_parallel(
+    inputs: Vec<BindgenInputs>,
+    jobs: usize,
+) -> Vec<Result<BindgenOutput, BindgenError>> {
+    let threads = jobs.clamp(1, inputs.len().max(1));
+    let queue = Mutex::new(inputs.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((idx, inputs)) => {
+                        let output = inputs.run();
+                        results.lock().unwrap().push((idx, output));
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, output)| output).collect()
+}
+
+/// The bindgen allowlist pattern for a function which may be declared
+/// within anonymous namespaces. bindgen names those `_bindgen_mod_...`
+/// when matching its allowlist, but we expose their functions by the names
+/// C++ callers use, so that's what people will ask for.
+fn within_anonymous_namespaces(item: &str) -> String {
+    const ANONYMOUS_NAMESPACES: &str = "(_bindgen_mod_[0-9]+::)*";
+    format!(
+        "{ANONYMOUS_NAMESPACES}{}",
+        item.split("::").join(&format!("::{ANONYMOUS_NAMESPACES}"))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::within_anonymous_namespaces;
+
+    #[test]
+    fn test_within_anonymous_namespaces() {
+        assert_eq!(
+            within_anonymous_namespaces("outer::triple"),
+            "(_bindgen_mod_[0-9]+::)*outer::(_bindgen_mod_[0-9]+::)*triple"
+        );
+    }
+}
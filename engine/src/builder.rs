@@ -10,14 +10,23 @@ use autocxx_parser::file_locations::FileLocationStrategy;
 use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::{generate_rs_single, CodegenOptions};
-use crate::{get_cxx_header_bytes, CppCodegenOptions, ParseError, RebuildDependencyRecorder};
+use crate::target_args::target_clang_args;
+use crate::{
+    generate_rs_single, generate_rs_single_pretty, ApiDecision, ApiFilter, ApiSummary,
+    CodegenOptions, DroppedHeader, IgnoredApi,
+};
+use crate::{
+    get_cxx_header_bytes, CppCodegenOptions, CppStandard, HeaderInclusion, HeaderInclusionMapper,
+    ParseError, RebuildDependencyRecorder, TargetArgsError,
+};
+use std::cell::Cell;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs::File;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 /// Errors returned during creation of a [`cc::Build`] from an include_cxx
 /// macro.
@@ -35,6 +44,12 @@ pub enum BuilderError {
     NoIncludeCxxMacrosFound,
     #[error("could not create a directory {1}: {0}")]
     UnableToCreateDirectory(std::io::Error, PathBuf),
+    #[error("clang couldn't parse some headers: {}", .0.iter().map(|dropped| dropped.to_string()).collect::<Vec<_>>().join("; "))]
+    DroppedHeaders(Vec<DroppedHeader>),
+    #[error("bindings couldn't be generated for some requested APIs: {}", .0.iter().map(|ignored| ignored.to_string()).collect::<Vec<_>>().join("; "))]
+    IgnoredApis(Vec<IgnoredApi>),
+    #[error(transparent)]
+    TargetArgs(TargetArgsError),
 }
 
 #[cfg_attr(feature = "nightly", doc(cfg(feature = "build")))]
@@ -59,6 +74,11 @@ pub trait BuilderContext {
 
     /// Create a dependency recorder, if any.
     fn get_dependency_recorder() -> Option<Box<dyn RebuildDependencyRecorder>>;
+
+    /// Report a problem which doesn't prevent the build from succeeding.
+    fn warn(message: &str) {
+        log::warn!("{}", message);
+    }
 }
 
 /// An object to allow building of bindings from a `build.rs` file.
@@ -78,9 +98,14 @@ pub struct Builder<'a, BuilderContext> {
     rs_file: PathBuf,
     autocxx_incs: Vec<OsString>,
     extra_clang_args: Vec<String>,
+    derive_target_clang_args: bool,
     dependency_recorder: Option<Box<dyn RebuildDependencyRecorder>>,
     custom_gendir: Option<PathBuf>,
     auto_allowlist: bool,
+    fail_on_dropped_headers: bool,
+    fail_on_ignored: bool,
+    pretty_print_rs: bool,
+    api_report: Option<PathBuf>,
     codegen_options: CodegenOptions<'a>,
     // This member is to ensure that this type is parameterized
     // by a BuilderContext. The goal is to balance three needs:
@@ -113,10 +138,28 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
                 .map(|s| s.as_ref().to_os_string())
                 .collect(),
             extra_clang_args: Vec::new(),
+            derive_target_clang_args: true,
             dependency_recorder: CTX::get_dependency_recorder(),
             custom_gendir: None,
             auto_allowlist: false,
-            codegen_options: CodegenOptions::default(),
+            fail_on_dropped_headers: false,
+            fail_on_ignored: false,
+            pretty_print_rs: false,
+            api_report: None,
+            codegen_options: CodegenOptions {
+                cpp_codegen_options: CppCodegenOptions {
+                    cpp_shards: std::env::var("AUTOCXX_CC_SHARDS")
+                        .ok()
+                        .and_then(|shards| shards.parse().ok())
+                        .unwrap_or_default(),
+                    ..Default::default()
+                },
+                bindgen_jobs: ["AUTOCXX_JOBS", "NUM_JOBS"]
+                    .into_iter()
+                    .find_map(|var| std::env::var(var).ok()?.parse().ok())
+                    .unwrap_or(1),
+                ..Default::default()
+            },
             ctx: PhantomData,
         }
     }
@@ -129,6 +172,35 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         self
     }
 
+    /// When cross-compiling, headers are by default parsed for Cargo's
+    /// target, not the host: autocxx tells clang the `--target`, and the
+    /// `--sysroot` if there's a `SYSROOT_<target>` environment variable or
+    /// the target is Android (in which case the sysroot is found within
+    /// `ANDROID_NDK_HOME`). Any `BINDGEN_EXTRA_CLANG_ARGS` are used too.
+    /// If you give your own `--target` or `--sysroot` using
+    /// [`Self::extra_clang_args`], those are used instead. Pass `false` to
+    /// derive nothing from the target.
+    pub fn derive_target_clang_args(mut self, do_it: bool) -> Self {
+        self.derive_target_clang_args = do_it;
+        self
+    }
+
+    /// Specify extra arguments for clang to use only when parsing the
+    /// headers of one `include_cpp!`, identified by its mod name (`ffi`
+    /// unless it was changed using `name!`). These come after those given
+    /// to [`Self::extra_clang_args`], so where the two conflict (for
+    /// instance, two `-std` options) these win. Call this more than once
+    /// to add arguments for several `include_cpp!`s. Note that the C++
+    /// which autocxx generates is all compiled by the one `cc::Build`;
+    /// these arguments only affect how the headers are parsed.
+    pub fn extra_clang_args_for_block(mut self, mod_name: &str, extra_clang_args: &[&str]) -> Self {
+        self.codegen_options.block_clang_args.push((
+            mod_name.to_string(),
+            extra_clang_args.iter().map(|s| s.to_string()).collect(),
+        ));
+        self
+    }
+
     /// Where to generate the code.
     pub fn custom_gendir(mut self, custom_gendir: PathBuf) -> Self {
         self.custom_gendir = Some(custom_gendir);
@@ -162,6 +234,197 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         self
     }
 
+    /// If clang can't parse some of the headers listed in an `include_cpp!`,
+    /// autocxx normally leaves them out, warns, and generates bindings for
+    /// the rest. Set this to instead fail the build.
+    pub fn fail_on_dropped_headers(mut self, do_it: bool) -> Self {
+        self.fail_on_dropped_headers = do_it;
+        self
+    }
+
+    /// If bindings can't be generated for APIs named in `generate!`,
+    /// `generate_pod!` or `subclass!` directives (or for members of types
+    /// so named), autocxx normally warns about each and carries on. Set
+    /// this to instead fail the build, listing all of them.
+    pub fn fail_on_ignored(mut self, do_it: bool) -> Self {
+        self.fail_on_ignored = do_it;
+        self
+    }
+
+    /// Write a JSON report to this path listing every C++ API which
+    /// autocxx discovered, sorted by name. For each it gives the kind of
+    /// API, whether bindings were generated, and if not, why not; plus the
+    /// directive which asked for it, if any. This is useful to see what
+    /// changed when you upgrade a C++ library. The report is written even
+    /// if [`Self::fail_on_ignored`] then fails the build.
+    pub fn api_report(mut self, path: impl AsRef<Path>) -> Self {
+        self.api_report = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Split the C++ which autocxx generates (beyond that generated by
+    /// cxx) into up to this many files, so that they can be compiled in
+    /// parallel. This can also be set using the `AUTOCXX_CC_SHARDS`
+    /// environment variable. The default is a single file.
+    pub fn cpp_shards(mut self, shards: usize) -> Self {
+        self.codegen_options.cpp_codegen_options.cpp_shards = shards;
+        self
+    }
+
+    /// Run bindgen for up to this many of the `include_cpp!`s in the file
+    /// at once. Each needs clang to parse its headers, which is usually the
+    /// slowest part of generating bindings; the rest happens one
+    /// `include_cpp!` at a time, in order, so the output is the same
+    /// however many jobs are used. This can also be set using the
+    /// `AUTOCXX_JOBS` environment variable. The default is the number of
+    /// jobs Cargo is running (`NUM_JOBS`), or one outside of Cargo.
+    pub fn bindgen_jobs(mut self, jobs: usize) -> Self {
+        self.codegen_options.bindgen_jobs = jobs;
+        self
+    }
+
+    /// Tell Cargo to rerun the build only if headers within the include
+    /// directories given to this builder (or given to clang using `-I` or
+    /// `-iquote`) change. Normally every header which clang reads is
+    /// watched, including the many system headers which those include.
+    /// Either way, headers which are only included indirectly are watched
+    /// too.
+    pub fn skip_system_header_dependencies(mut self, do_it: bool) -> Self {
+        self.codegen_options.skip_system_header_dependencies = do_it;
+        self
+    }
+
+    /// Precede each piece of C++ which autocxx generates with a comment
+    /// saying which C++ API it's for. If the generated C++ fails to compile,
+    /// this tells you which declaration in your headers, and therefore
+    /// which `generate!` or similar directive, is responsible.
+    pub fn annotate_generated_cpp(mut self, do_it: bool) -> Self {
+        self.codegen_options.cpp_codegen_options.annotate_cpp = do_it;
+        self
+    }
+
+    /// Prefix the path to each header listed in the `include_cpp!` with
+    /// this, within `#include` statements in the generated C++. This
+    /// doesn't affect how autocxx itself finds the headers. The prefix
+    /// must end in `/`.
+    pub fn path_to_original_headers(mut self, prefix: impl Into<String>) -> Self {
+        self.codegen_options
+            .cpp_codegen_options
+            .path_to_original_headers = Some(prefix.into());
+        self
+    }
+
+    /// Include the headers listed in the `include_cpp!` from the generated
+    /// C++ using `#include <...>` rather than `#include "..."`.
+    pub fn angle_bracket_includes(mut self, do_it: bool) -> Self {
+        self.codegen_options
+            .cpp_codegen_options
+            .angle_bracket_includes = do_it;
+        self
+    }
+
+    /// Decide exactly how the generated C++ includes particular headers
+    /// listed in the `include_cpp!`. The function is passed each header as
+    /// written in the `include_cpp!`; where it returns `None`, the header is
+    /// included according to [`path_to_original_headers`] and
+    /// [`angle_bracket_includes`].
+    pub fn map_header_inclusions<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(&str) -> Option<HeaderInclusion> + 'static,
+    {
+        self.codegen_options
+            .cpp_codegen_options
+            .header_inclusion_mapper = HeaderInclusionMapper(Box::new(mapper));
+        self
+    }
+
+    /// Don't let the generated C++ rely on anything newer than this
+    /// C++ standard. Bindings aren't generated for functions which would
+    /// need it (for instance, those taking a `std::string_view` if this is
+    /// [`CppStandard::Cpp14`]) and it's an error if you asked for them
+    /// specifically.
+    pub fn max_cpp_standard(mut self, standard: CppStandard) -> Self {
+        self.codegen_options.cpp_codegen_options.max_cpp_standard = Some(standard);
+        self
+    }
+
+    /// Pretty-print the generated Rust files, which `include_cpp!` then
+    /// `include!`s from `OUT_DIR` (or wherever you asked). This makes it
+    /// easier for you, and for IDEs such as rust-analyzer, to read the
+    /// generated bindings, and the formatting is stable so you can diff
+    /// one version against another. It's a little slower.
+    pub fn pretty_print_rs(mut self, do_it: bool) -> Self {
+        self.pretty_print_rs = do_it;
+        self
+    }
+
+    /// Whether to return any C++ exception thrown by a generated function,
+    /// method or constructor as a Rust `Result`, rather than letting it
+    /// terminate the program. This applies to everything which could be
+    /// listed in a `generate_fallible!` directive. It adds some overhead to
+    /// every call, so prefer `generate_fallible!` for just the functions
+    /// which can actually throw.
+    pub fn fallible_by_default(mut self, do_it: bool) -> Self {
+        self.codegen_options.fallible_by_default = do_it;
+        self
+    }
+
+    /// Don't generate the stubs which otherwise stand in for anything
+    /// autocxx couldn't generate bindings for. Each such stub has the same
+    /// name as the missing function, method or type, and is deprecated with
+    /// the reason it couldn't be generated, so that any attempt to use it
+    /// says why. Suppressing them makes the generated code smaller.
+    pub fn suppress_ignored_api_stubs(mut self, do_it: bool) -> Self {
+        self.codegen_options.suppress_ignored_api_stubs = do_it;
+        self
+    }
+
+    /// Fail the whole build if autocxx fails unexpectedly while processing
+    /// any one declaration. By default such a declaration is ignored, with
+    /// the failure given as the reason, so that one declaration autocxx
+    /// can't cope with doesn't prevent bindings for everything else.
+    pub fn strict(mut self, do_it: bool) -> Self {
+        self.codegen_options.strict = do_it;
+        self
+    }
+
+    /// Decide, according to rules which can't be expressed as directives,
+    /// what to do with each API which autocxx would otherwise generate.
+    /// The function is called once for each API, after all analysis, and
+    /// may exclude it (along with anything which depends on it, as if it
+    /// were on the blocklist), add to its documentation, or mark it
+    /// `#[must_use]` or `#[deprecated]`. See [`ApiDecision`]. Bindings
+    /// aren't cached while there's a filter.
+    pub fn api_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&ApiSummary) -> ApiDecision + 'static,
+    {
+        self.codegen_options.api_filter = Some(ApiFilter(Box::new(filter)));
+        self
+    }
+
+    /// Cache generated bindings in the given directory. If neither the
+    /// `include_cpp!` configuration, the options given to this builder, nor
+    /// the contents of any header which contributed to the bindings have
+    /// changed, the cached bindings are reused rather than parsing the
+    /// headers again. The cache is keyed on the autocxx version too, and
+    /// any problem reading it results in the bindings being generated in
+    /// full. Set the `AUTOCXX_REGENERATE` environment variable to ignore
+    /// the cache and always generate everything.
+    pub fn with_cache_dir(mut self, cache_dir: impl AsRef<Path>) -> Self {
+        self.codegen_options.cache_dir = Some(cache_dir.as_ref().to_path_buf());
+        self
+    }
+
+    #[doc(hidden)]
+    /// Counts how many times the bindings are actually generated rather
+    /// than being found in the cache. Only used by the integration test
+    /// suite.
+    pub fn conversion_counter(mut self, conversion_counter: Rc<Cell<usize>>) -> Self {
+        self.codegen_options.conversion_counter = Some(conversion_counter);
+        self
+    }
+
     #[doc(hidden)]
     /// Whether to force autocxx always to generate extra Rust and C++
     /// side shims. This is only used by the integration test suite to
@@ -183,8 +446,11 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         self
     }
 
-    /// An annotation optionally to include on each C++ function.
-    /// For example to export the symbol from a library.
+    /// An annotation optionally to include on each C++ function, whether
+    /// generated by cxx or by autocxx, and on the classes generated for
+    /// `subclass!`. For example to export the symbol from a library, using
+    /// `__attribute__((visibility("default")))` or a macro defined in your
+    /// headers.
     pub fn cxx_impl_annotations(mut self, cxx_impl_annotations: Option<String>) -> Self {
         self.codegen_options
             .cpp_codegen_options
@@ -218,11 +484,13 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
     /// about the files generated which can subsequently be examined for correctness.
     /// In production, please use simply [`build`].
     pub fn build_listing_files(self) -> Result<BuilderSuccess, BuilderError> {
-        let clang_args = &self
-            .extra_clang_args
-            .iter()
-            .map(|s| &s[..])
-            .collect::<Vec<_>>();
+        let mut clang_args = Vec::new();
+        if self.derive_target_clang_args {
+            clang_args = target_clang_args(|var| std::env::var(var).ok(), &self.extra_clang_args)
+                .map_err(BuilderError::TargetArgs)?;
+        }
+        clang_args.extend(self.extra_clang_args.iter().cloned());
+        let clang_args = &clang_args.iter().map(|s| &s[..]).collect::<Vec<_>>();
         rust_version_check();
         let gen_location_strategy = match self.custom_gendir {
             None => FileLocationStrategy::new(),
@@ -252,6 +520,14 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         let autocxx_inc = build_autocxx_inc(self.autocxx_incs, &incdir);
         gen_location_strategy.set_cargo_env_vars_for_build();
 
+        // The generated file names depend on the directives within the
+        // `include_cpp!`, so we need to be rerun if they change, or
+        // else the macro will look for files which don't exist.
+        if let Some(dependency_recorder) = &self.dependency_recorder {
+            if let Some(rs_file) = self.rs_file.to_str() {
+                dependency_recorder.record_header_file_dependency(rs_file);
+            }
+        }
         let mut parsed_file = crate::parse_file(self.rs_file, self.auto_allowlist)
             .map_err(BuilderError::ParseError)?;
         parsed_file
@@ -262,6 +538,35 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
                 &self.codegen_options,
             )
             .map_err(BuilderError::ParseError)?;
+        if let Some(api_report) = self.api_report {
+            crate::write_api_report(&api_report, [&parsed_file])
+                .map_err(|e| BuilderError::FileWriteFail(e, api_report))?;
+        }
+        if self.fail_on_dropped_headers {
+            let dropped_headers: Vec<_> = parsed_file
+                .get_autocxxes()
+                .flat_map(|autocxx| autocxx.dropped_headers())
+                .cloned()
+                .collect();
+            if !dropped_headers.is_empty() {
+                return Err(BuilderError::DroppedHeaders(dropped_headers));
+            }
+        }
+        if self.fail_on_ignored {
+            let ignored_apis: Vec<_> = parsed_file
+                .get_autocxxes()
+                .flat_map(|autocxx| autocxx.ignored_apis())
+                .cloned()
+                .collect();
+            if !ignored_apis.is_empty() {
+                return Err(BuilderError::IgnoredApis(ignored_apis));
+            }
+        }
+        for autocxx in parsed_file.get_autocxxes() {
+            for warning in autocxx.ignored_api_warnings() {
+                CTX::warn(&warning);
+            }
+        }
         let mut counter = 0;
         let mut builder = cc::Build::new();
         builder.cpp(true);
@@ -289,7 +594,11 @@ impl<CTX: BuilderContext> Builder<'_, CTX> {
         }
 
         for rs_output in parsed_file.get_rs_outputs() {
-            let rs = generate_rs_single(rs_output);
+            let rs = if self.pretty_print_rs {
+                generate_rs_single_pretty(rs_output)
+            } else {
+                generate_rs_single(rs_output)
+            };
             generated_rs.push(write_to_file(&rsdir, &rs.filename, rs.code.as_bytes())?);
         }
         if counter == 0 {
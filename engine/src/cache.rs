@@ -0,0 +1,573 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An on-disk cache of generated bindings, so that build systems which
+//! rerun autocxx frequently needn't rerun bindgen and the conversion
+//! unless the C++ headers (or the autocxx configuration) actually changed.
+//!
+//! Each entry lives in its own directory, named after a hash of the
+//! cache key. The key covers everything which is known before bindgen
+//! runs: the autocxx version, the `include_cpp!` configuration, the
+//! include paths, clang arguments and codegen options. The headers which
+//! bindgen actually read aren't known until it has run, so each entry
+//! also records those headers and a hash of their contents; the entry is
+//! only used if all of them are unchanged. Any problem reading an entry
+//! simply results in full generation, as does setting `AUTOCXX_REGENERATE`.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use autocxx_parser::IncludeCppConfig;
+use quote::ToTokens;
+use syn::ItemMod;
+
+use crate::api_report::ReportedApi;
+use crate::generated_types::{GeneratedType, ImportedType};
+use crate::{
+    ApiFilter, AutocxxgenHeaderNamer, CodegenOptions, CppCodegenOptions, CppFilePair,
+    CxxgenHeaderNamer, HeaderInclusionMapper, IgnoredApi,
+};
+
+const KEY_FILE: &str = "key";
+const DEPENDENCIES_FILE: &str = "dependencies";
+const RS_FILE: &str = "bindings.rs";
+const CXXGEN_HEADER_NAME_FILE: &str = "cxxgen_header_name";
+const CPP_HEADER_NAME_FILE: &str = "cpp_header_name";
+const CPP_HEADER_FILE: &str = "cpp_header";
+const CPP_IMPLEMENTATION_FILE: &str = "cpp_implementation";
+const IGNORED_APIS_FILE: &str = "ignored_apis";
+const GENERATED_TYPES_FILE: &str = "generated_types";
+const REPORTED_APIS_FILE: &str = "reported_apis.json";
+
+/// If this environment variable is set, cache entries are never used,
+/// though they're still written. This is an escape hatch for when the
+/// cache is (somehow) wrong.
+pub(crate) const REGENERATE_ENV_VAR: &str = "AUTOCXX_REGENERATE";
+
+/// Everything which [`crate::IncludeCppEngine::generate`] produces,
+/// as read back from the cache.
+pub(crate) struct CachedGeneration {
+    pub(crate) item_mod: ItemMod,
+    pub(crate) cpp: Option<CppFilePair>,
+    pub(crate) cxxgen_header_name: String,
+    pub(crate) ignored_apis: Vec<IgnoredApi>,
+    pub(crate) generated_types: Vec<GeneratedType>,
+    pub(crate) reported_apis: Vec<ReportedApi>,
+    pub(crate) dependencies: Vec<String>,
+}
+
+/// A single cache entry, which may or may not yet exist on disk.
+pub(crate) struct GenerationCache {
+    cache_dir: PathBuf,
+    entry_dir: PathBuf,
+    key: String,
+}
+
+impl GenerationCache {
+    pub(crate) fn new(
+        cache_dir: &Path,
+        config: &IncludeCppConfig,
+        imported_types: &[ImportedType],
+        header_contents: &str,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+        codegen_options: &CodegenOptions,
+    ) -> Self {
+        let key = make_key(
+            config,
+            imported_types,
+            header_contents,
+            inc_dirs,
+            extra_clang_args,
+            codegen_options,
+        );
+        let entry_dir = cache_dir.join(format!("{:016x}", hash(key.as_bytes())));
+        Self {
+            cache_dir: cache_dir.to_path_buf(),
+            entry_dir,
+            key,
+        }
+    }
+
+    /// Returns the cached results, if there are any and the headers
+    /// from which they were generated are unchanged.
+    pub(crate) fn load(&self) -> Option<CachedGeneration> {
+        let r = self
+            .fresh_dependencies()
+            .and_then(|dependencies| self.try_load(dependencies));
+        if r.is_none() {
+            log::info!("No usable cache entry in {}", self.entry_dir.display());
+        }
+        r
+    }
+
+    /// Whether there's an entry which [`Self::load`] would use, without
+    /// reading all of it.
+    pub(crate) fn is_fresh(&self) -> bool {
+        self.fresh_dependencies().is_some()
+    }
+
+    /// The headers recorded in the entry, if it exists and none of them
+    /// has changed.
+    fn fresh_dependencies(&self) -> Option<Vec<String>> {
+        if std::env::var_os(REGENERATE_ENV_VAR).is_some() {
+            log::info!("Ignoring cached bindings because {REGENERATE_ENV_VAR} is set");
+            return None;
+        }
+        if self.read_string(KEY_FILE)? != self.key {
+            return None;
+        }
+        let mut dependencies = Vec::new();
+        for line in self.read_string(DEPENDENCIES_FILE)?.lines() {
+            let (content_hash, filename) = line.split_once(' ')?;
+            if content_hash != hash_file(filename) {
+                log::info!("Cached bindings are stale because {filename} changed");
+                return None;
+            }
+            dependencies.push(filename.to_string());
+        }
+        Some(dependencies)
+    }
+
+    fn try_load(&self, dependencies: Vec<String>) -> Option<CachedGeneration> {
+        let item_mod = syn::parse_str::<ItemMod>(&self.read_string(RS_FILE)?).ok()?;
+        let cxxgen_header_name = self.read_string(CXXGEN_HEADER_NAME_FILE)?;
+        let ignored_apis = self
+            .read_string(IGNORED_APIS_FILE)?
+            .lines()
+            .map(|line| {
+                let mut fields = line.split('\t');
+                Some(IgnoredApi {
+                    name: fields.next()?.to_string(),
+                    reason: fields.next()?.to_string(),
+                    suggestion: fields.next().map(str::to_string),
+                })
+            })
+            .collect::<Option<_>>()?;
+        let generated_types = self
+            .read_string(GENERATED_TYPES_FILE)?
+            .lines()
+            .map(GeneratedType::from_line)
+            .collect::<Option<_>>()?;
+        let reported_apis =
+            match serde_json::from_str(&self.read_string(REPORTED_APIS_FILE)?).ok()? {
+                serde_json::Value::Array(apis) => apis
+                    .iter()
+                    .map(ReportedApi::from_json)
+                    .collect::<Option<_>>()?,
+                _ => return None,
+            };
+        let cpp = match self.read_string(CPP_HEADER_NAME_FILE) {
+            None => None,
+            Some(header_name) => Some(CppFilePair {
+                header: fs::read(self.entry_dir.join(CPP_HEADER_FILE)).ok()?,
+                implementation: fs::read(self.entry_dir.join(CPP_IMPLEMENTATION_FILE)).ok(),
+                header_name,
+                extra_implementations: (1..)
+                    .map_while(|shard| {
+                        fs::read(
+                            self.entry_dir
+                                .join(format!("{CPP_IMPLEMENTATION_FILE}_{shard}")),
+                        )
+                        .ok()
+                    })
+                    .collect(),
+            }),
+        };
+        Some(CachedGeneration {
+            item_mod,
+            cpp,
+            cxxgen_header_name,
+            ignored_apis,
+            generated_types,
+            reported_apis,
+            dependencies,
+        })
+    }
+
+    fn read_string(&self, filename: &str) -> Option<String> {
+        fs::read_to_string(self.entry_dir.join(filename)).ok()
+    }
+
+    /// Stores newly generated results. Failure to do so isn't fatal:
+    /// we'll just have to generate everything again next time.
+    #[allow(clippy::too_many_arguments)] // one for each part of the entry
+    pub(crate) fn store(
+        &self,
+        item_mod: &ItemMod,
+        cpp: Option<&CppFilePair>,
+        cxxgen_header_name: &str,
+        ignored_apis: &[IgnoredApi],
+        generated_types: &[GeneratedType],
+        reported_apis: &[ReportedApi],
+        dependencies: &[String],
+    ) {
+        if let Err(e) = self.try_store(
+            item_mod,
+            cpp,
+            cxxgen_header_name,
+            ignored_apis,
+            generated_types,
+            reported_apis,
+            dependencies,
+        ) {
+            log::info!(
+                "Unable to store bindings in cache {}: {}",
+                self.cache_dir.display(),
+                e
+            );
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)] // one for each part of the entry
+    fn try_store(
+        &self,
+        item_mod: &ItemMod,
+        cpp: Option<&CppFilePair>,
+        cxxgen_header_name: &str,
+        ignored_apis: &[IgnoredApi],
+        generated_types: &[GeneratedType],
+        reported_apis: &[ReportedApi],
+        dependencies: &[String],
+    ) -> std::io::Result<()> {
+        // Write everything into a temporary directory and then move it into
+        // place, so that nobody sees a partially written entry.
+        fs::create_dir_all(&self.cache_dir)?;
+        let temp_dir = tempfile::tempdir_in(&self.cache_dir)?;
+        let write =
+            |filename: &str, contents: &[u8]| fs::write(temp_dir.path().join(filename), contents);
+        let dependencies: String = dependencies
+            .iter()
+            .map(|filename| format!("{} {}\n", hash_file(filename), filename))
+            .collect();
+        write(DEPENDENCIES_FILE, dependencies.as_bytes())?;
+        write(RS_FILE, item_mod.to_token_stream().to_string().as_bytes())?;
+        write(CXXGEN_HEADER_NAME_FILE, cxxgen_header_name.as_bytes())?;
+        let ignored_apis: String = ignored_apis
+            .iter()
+            .map(|ignored| {
+                let suggestion = ignored
+                    .suggestion
+                    .as_ref()
+                    .map(|suggestion| format!("\t{}", suggestion.replace('\n', " ")))
+                    .unwrap_or_default();
+                format!(
+                    "{}\t{}{suggestion}\n",
+                    ignored.name,
+                    ignored.reason.replace('\n', " ")
+                )
+            })
+            .collect();
+        write(IGNORED_APIS_FILE, ignored_apis.as_bytes())?;
+        let generated_types: String = generated_types
+            .iter()
+            .map(|generated| format!("{}\n", generated.to_line()))
+            .collect();
+        write(GENERATED_TYPES_FILE, generated_types.as_bytes())?;
+        let reported_apis: Vec<_> = reported_apis.iter().map(ReportedApi::to_json).collect();
+        write(
+            REPORTED_APIS_FILE,
+            serde_json::Value::Array(reported_apis)
+                .to_string()
+                .as_bytes(),
+        )?;
+        if let Some(cpp) = cpp {
+            write(CPP_HEADER_NAME_FILE, cpp.header_name.as_bytes())?;
+            write(CPP_HEADER_FILE, &cpp.header)?;
+            if let Some(implementation) = &cpp.implementation {
+                write(CPP_IMPLEMENTATION_FILE, implementation)?;
+            }
+            for (shard, implementation) in cpp.extra_implementations.iter().enumerate() {
+                write(
+                    &format!("{CPP_IMPLEMENTATION_FILE}_{}", shard + 1),
+                    implementation,
+                )?;
+            }
+        }
+        // Written last, since an entry without a key is never used.
+        write(KEY_FILE, self.key.as_bytes())?;
+        if self.entry_dir.exists() {
+            fs::remove_dir_all(&self.entry_dir)?;
+        }
+        // Dropping `temp_dir` afterwards finds nothing left to clean up.
+        fs::rename(temp_dir.path(), &self.entry_dir)
+    }
+}
+
+fn make_key(
+    config: &IncludeCppConfig,
+    imported_types: &[ImportedType],
+    header_contents: &str,
+    inc_dirs: &[PathBuf],
+    extra_clang_args: &[&str],
+    codegen_options: &CodegenOptions,
+) -> String {
+    let CodegenOptions {
+        force_wrapper_gen,
+        cpp_codegen_options,
+        stable_output,
+        cfg_features,
+        fallible_by_default,
+        cache_dir: _,
+        bindgen_jobs: _,
+        skip_system_header_dependencies: _,
+        block_clang_args: _,
+        suppress_ignored_api_stubs,
+        strict,
+        // We don't use the cache at all if there's one of these.
+        api_filter: _,
+        conversion_counter: _,
+    } = codegen_options;
+    // The header namers can't be part of the key; instead the names
+    // they produced are checked when the entry is used.
+    let CppCodegenOptions {
+        suppress_system_headers,
+        path_to_cxx_h,
+        path_to_cxxgen_h,
+        autocxxgen_header_namer: _,
+        cxxgen_header_namer: _,
+        cxx_impl_annotations,
+        cpp_shards,
+        annotate_cpp,
+        path_to_original_headers: _,
+        angle_bracket_includes: _,
+        header_inclusion_mapper: _,
+        path_to_autocxxgen_h,
+        max_cpp_standard,
+    } = cpp_codegen_options;
+    // Likewise the header inclusion mapper, so instead we record how it
+    // (and the related options) say each header should be included.
+    let header_inclusions: Vec<_> = config
+        .inclusions
+        .iter()
+        .map(|inclusion| cpp_codegen_options.original_header_inclusion(inclusion))
+        .collect();
+    // Which types the other mods generated can change even if our own
+    // headers don't, e.g. if they `generate!` something different.
+    let imported_types: Vec<_> = imported_types
+        .iter()
+        .map(|imported| {
+            (
+                &imported.cpp_name,
+                imported.rust_path.to_token_stream().to_string(),
+                imported.pod,
+            )
+        })
+        .collect();
+    format!(
+        "autocxx {}\nconfig: {}\nimported_types: {:?}\nheader: {:?}\ninc_dirs: {:?}\nclang_args: {:?}\n\
+        force_wrapper_gen: {}\nstable_output: {}\ncfg_features: {:?}\n\
+        fallible_by_default: {}\nsuppress_system_headers: {}\npath_to_cxx_h: {:?}\n\
+        path_to_cxxgen_h: {:?}\ncxx_impl_annotations: {:?}\ncpp_shards: {}\nannotate_cpp: {}\n\
+        header_inclusions: {:?}\npath_to_autocxxgen_h: {:?}\nmax_cpp_standard: {:?}\n\
+        suppress_ignored_api_stubs: {}\nstrict: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        config.to_token_stream(),
+        imported_types,
+        header_contents,
+        inc_dirs,
+        extra_clang_args,
+        force_wrapper_gen,
+        stable_output,
+        cfg_features,
+        fallible_by_default,
+        suppress_system_headers,
+        path_to_cxx_h,
+        path_to_cxxgen_h,
+        cxx_impl_annotations,
+        cpp_shards,
+        annotate_cpp,
+        header_inclusions,
+        path_to_autocxxgen_h,
+        max_cpp_standard,
+        suppress_ignored_api_stubs,
+        strict,
+    )
+}
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the contents of a header, or `-` if it can't be read
+/// (in which case it must remain unreadable for the entry to be used).
+fn hash_file(filename: &str) -> String {
+    match fs::read(filename) {
+        Ok(contents) => format!("{:016x}", hash(&contents)),
+        Err(_) => "-".to_string(),
+    }
+}
+
+/// Returns codegen options which are identical to those given, except
+/// that they use header names we've already obtained from the header
+/// namers. This is used if we called the namers for a cache entry which
+/// then turned out to be unusable; the namers may be stateful, so we
+/// mustn't call them again.
+pub(crate) fn with_header_names<'a>(
+    codegen_options: &'a CodegenOptions,
+    cxxgen_header_name: String,
+    autocxxgen_header_name: Option<String>,
+) -> CodegenOptions<'a> {
+    let cpp_codegen_options = &codegen_options.cpp_codegen_options;
+    CodegenOptions {
+        force_wrapper_gen: codegen_options.force_wrapper_gen,
+        cpp_codegen_options: CppCodegenOptions {
+            suppress_system_headers: cpp_codegen_options.suppress_system_headers,
+            path_to_cxx_h: cpp_codegen_options.path_to_cxx_h.clone(),
+            path_to_cxxgen_h: cpp_codegen_options.path_to_cxxgen_h.clone(),
+            autocxxgen_header_namer: AutocxxgenHeaderNamer(Box::new(move |mod_name| {
+                match &autocxxgen_header_name {
+                    Some(name) => name.clone(),
+                    None => cpp_codegen_options
+                        .autocxxgen_header_namer
+                        .name_header(mod_name),
+                }
+            })),
+            cxxgen_header_namer: CxxgenHeaderNamer(Box::new(move || cxxgen_header_name.clone())),
+            cxx_impl_annotations: cpp_codegen_options.cxx_impl_annotations.clone(),
+            cpp_shards: cpp_codegen_options.cpp_shards,
+            annotate_cpp: cpp_codegen_options.annotate_cpp,
+            path_to_original_headers: cpp_codegen_options.path_to_original_headers.clone(),
+            angle_bracket_includes: cpp_codegen_options.angle_bracket_includes,
+            header_inclusion_mapper: HeaderInclusionMapper(Box::new(|header| {
+                cpp_codegen_options
+                    .header_inclusion_mapper
+                    .map_header(header)
+            })),
+            path_to_autocxxgen_h: cpp_codegen_options.path_to_autocxxgen_h.clone(),
+            max_cpp_standard: cpp_codegen_options.max_cpp_standard,
+        },
+        stable_output: codegen_options.stable_output,
+        cfg_features: codegen_options.cfg_features.clone(),
+        fallible_by_default: codegen_options.fallible_by_default,
+        cache_dir: codegen_options.cache_dir.clone(),
+        bindgen_jobs: codegen_options.bindgen_jobs,
+        skip_system_header_dependencies: codegen_options.skip_system_header_dependencies,
+        block_clang_args: codegen_options.block_clang_args.clone(),
+        suppress_ignored_api_stubs: codegen_options.suppress_ignored_api_stubs,
+        strict: codegen_options.strict,
+        api_filter: codegen_options
+            .api_filter
+            .as_ref()
+            .map(|api_filter| ApiFilter(Box::new(|summary| api_filter.decide(summary)))),
+        conversion_counter: codegen_options.conversion_counter.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GenerationCache;
+    use crate::api_report::{ApiKind, ReportedApi};
+    use crate::generated_types::GeneratedType;
+    use crate::types::QualifiedName;
+    use crate::{CodegenOptions, CppFilePair, IgnoredApi};
+    use autocxx_parser::IncludeCppConfig;
+    use syn::{parse_quote, ItemMod};
+
+    #[test]
+    fn test_entry_invalidated_by_header_change() {
+        let tdir = tempfile::tempdir().unwrap();
+        let header = tdir.path().join("a.h");
+        std::fs::write(&header, "struct A {};").unwrap();
+        let header = header.to_str().unwrap().to_string();
+        let config: IncludeCppConfig = parse_quote! {
+            safety!(unsafe_ffi)
+            generate!("A")
+        };
+        let cache = GenerationCache::new(
+            &tdir.path().join("cache"),
+            &config,
+            &[],
+            "#include \"a.h\"\n",
+            &[tdir.path().to_path_buf()],
+            &[],
+            &CodegenOptions::default(),
+        );
+        assert!(cache.load().is_none());
+        let item_mod: ItemMod = parse_quote! {
+            mod ffi {
+                pub struct A;
+            }
+        };
+        let ignored_apis = vec![IgnoredApi {
+            name: "A::frob".to_string(),
+            reason: "it was too frobby".to_string(),
+            suggestion: Some("Try defrob!(\"A\").".to_string()),
+        }];
+        let generated_types = vec![GeneratedType {
+            cpp_name: "A".to_string(),
+            rust_path: "A".to_string(),
+            pod: false,
+        }];
+        let reported_apis = vec![ReportedApi {
+            name: "A::frob".to_string(),
+            qualified_name: QualifiedName::new_from_cpp_name("A_frob"),
+            kind: ApiKind::Method,
+            rust_name: None,
+            ignored_reason: Some("it was too frobby".to_string()),
+            error: None,
+            requested_by: Some("generate!(\"A\")".to_string()),
+        }];
+        cache.store(
+            &item_mod,
+            None,
+            "cxxgen.h",
+            &ignored_apis,
+            &generated_types,
+            &reported_apis,
+            std::slice::from_ref(&header),
+        );
+        let cached = cache.load().unwrap();
+        assert_eq!(cached.item_mod, item_mod);
+        assert_eq!(cached.cxxgen_header_name, "cxxgen.h");
+        assert_eq!(cached.ignored_apis, ignored_apis);
+        assert_eq!(cached.generated_types, generated_types);
+        assert_eq!(cached.reported_apis, reported_apis);
+        assert!(cached.cpp.is_none());
+        assert_eq!(cached.dependencies, vec![header.clone()]);
+        std::fs::write(&header, "struct A { int a; };").unwrap();
+        assert!(cache.load().is_none());
+    }
+
+    #[test]
+    fn test_entry_with_several_cpp_files() {
+        let tdir = tempfile::tempdir().unwrap();
+        let config: IncludeCppConfig = parse_quote! {
+            safety!(unsafe_ffi)
+            generate_all!()
+        };
+        let cache = GenerationCache::new(
+            &tdir.path().join("cache"),
+            &config,
+            &[],
+            "",
+            &[],
+            &[],
+            &CodegenOptions::default(),
+        );
+        let item_mod: ItemMod = parse_quote! { mod ffi {} };
+        let cpp = CppFilePair {
+            header: b"void a(); void b();".to_vec(),
+            implementation: Some(b"void a() {}".to_vec()),
+            header_name: "autocxxgen_ffi.h".to_string(),
+            extra_implementations: vec![b"void b() {}".to_vec()],
+        };
+        cache.store(&item_mod, Some(&cpp), "cxxgen.h", &[], &[], &[], &[]);
+        let cached = cache.load().unwrap().cpp.unwrap();
+        assert_eq!(cached.header, cpp.header);
+        assert_eq!(cached.implementation, cpp.implementation);
+        assert_eq!(cached.header_name, cpp.header_name);
+        assert_eq!(cached.extra_implementations, cpp.extra_implementations);
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create a `cpp_addr` method for each non-POD type, so that Rust
+//! can tell whether two references are to the same C++ object.
+//!
+//! Non-POD types never move once C++ has created them, so their address
+//! identifies them. We use the address of the most-derived object, since
+//! C++ may hand back a reference to a different base class subobject.
+
+use indexmap::set::IndexSet as HashSet;
+
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TypeKind,
+        },
+        apivec::ApiVec,
+    },
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+const ADDRESS_METHOD: &str = "cpp_addr";
+
+pub(crate) fn create_address_methods(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    // Leave alone any type which has its own method of this name.
+    let clashes: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Function { fun, .. }
+                if fun
+                    .original_name
+                    .as_deref()
+                    .unwrap_or(&fun.ident.to_string())
+                    == ADDRESS_METHOD =>
+            {
+                fun.self_ty.clone()
+            }
+            _ => None,
+        })
+        .collect();
+    let types: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. }
+                if matches!(analysis.kind, TypeKind::NonPod)
+                    && !analysis.is_generic
+                    && !analysis.in_anonymous_namespace
+                    && !clashes.contains(&name.name) =>
+            {
+                Some(name.name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut apis = apis;
+    apis.extend(types.iter().map(create_address_method));
+    apis
+}
+
+fn create_address_method(ty_name: &QualifiedName) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let name = QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!(
+            "{}_autocxx_{ADDRESS_METHOD}",
+            ty_name.get_final_item()
+        )),
+    );
+    let inputs: Punctuated<FnArg, Comma> = parse_quote! {
+        this: *const #typ
+    };
+    let output: ReturnType = parse_quote! {
+        -> usize
+    };
+    let doc_attr: syn::Attribute = parse_quote! {
+        #[doc = "Returns the address of this C++ object, which is the same for any two references to it, even via different base classes. It's only meaningful while the object exists."]
+    };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: Some(ADDRESS_METHOD.to_string()),
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((CppFunctionBody::ObjectAddress, CppFunctionKind::Method)),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: true,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
@@ -93,6 +93,13 @@ fn create_alloc_and_free(ty_name: QualifiedName) -> impl Iterator<Item = Api<Pod
                     is_deleted: DeletedOrDefaulted::Neither,
                     provenance: Provenance::SynthesizedOther,
                     variadic: false,
+                    noexcept: false,
+                    explicit: false,
+                    pinned_rust_name: None,
+                    safety_override: None,
+                    deprecated: None,
+                    must_use: false,
+                    is_final: false,
                 }),
                 analysis: (),
             }
@@ -7,42 +7,52 @@
 // except according to those terms.
 
 use crate::minisyn::FnArg;
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
 use itertools::Itertools;
 use quote::quote;
 use syn::parse_quote;
 
 use crate::{
     conversion::{
-        api::{
-            Api, ApiName, CastMutability, DeletedOrDefaulted, Provenance, References,
-            TraitSynthesis,
-        },
+        api::{Api, ApiName, DeletedOrDefaulted, Provenance, References, TraitSynthesis},
         apivec::ApiVec,
     },
     types::{make_ident, QualifiedName},
 };
 
-/// If A is a base of B, we might want to be able to cast from
-/// &B to &A, or from Pin<&mut A> to &B, or from Pin<&mut A> to &B.
-/// The first is OK; the others turn out to be hard due to all
-/// the Pin stuff. For now therefore, we simply don't allow them.
-/// But the related code may be useful in future so I'm keeping it around.
-const SUPPORT_MUTABLE_CASTS: bool = false;
-
 use super::{
     fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
-    pod::{PodAnalysis, PodPhase},
+    pod::PodPhase,
 };
 
+/// If A is a base of B, we can cast from &B to &A, which we do by
+/// implementing `AsRef<A>` for B, and from `Pin<&mut B>` to `Pin<&mut A>`,
+/// which we do with a method `as_A_mut`. (An `AsMut`-like trait would
+/// need implementing for `Pin<&mut B>`, since that's all anyone has.)
+#[derive(Clone, Copy)]
+enum CastMutability {
+    ConstToConst,
+    MutToMut,
+}
+
 pub(crate) fn add_casts(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let bases_by_type: HashMap<QualifiedName, (HashSet<QualifiedName>, HashSet<QualifiedName>)> =
+        apis.iter()
+            .filter_map(|api| match api {
+                Api::Struct { name, analysis, .. } => Some((
+                    name.name.clone(),
+                    (analysis.bases.clone(), analysis.castable_bases.clone()),
+                )),
+                _ => None,
+            })
+            .collect();
     apis.into_iter()
         .flat_map(|api| {
             let mut resultant_apis = match api {
-                Api::Struct {
-                    ref name,
-                    details: _,
-                    ref analysis,
-                } => create_casts(&name.name, analysis).collect_vec(),
+                Api::Struct { ref name, .. } => {
+                    create_casts(&name.name, &bases_by_type).collect_vec()
+                }
                 _ => Vec::new(),
             };
             resultant_apis.push(api);
@@ -53,54 +63,90 @@ pub(crate) fn add_casts(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
 
 fn create_casts<'a>(
     name: &'a QualifiedName,
-    analysis: &'a PodAnalysis,
+    bases_by_type: &HashMap<QualifiedName, (HashSet<QualifiedName>, HashSet<QualifiedName>)>,
 ) -> impl Iterator<Item = Api<PodPhase>> + 'a {
-    // Create casts only to base classes which are on the allowlist
-    // because otherwise we won't know for sure whether they're abstract or not.
-    analysis
-        .castable_bases
-        .iter()
-        .flat_map(move |base| cast_types().map(|mutable| create_cast(name, base, mutable)))
+    unambiguous_castable_ancestors(name, bases_by_type)
+        .into_iter()
+        .flat_map(move |base| {
+            [CastMutability::ConstToConst, CastMutability::MutToMut]
+                .map(|mutable| create_cast(name, &base, mutable))
+        })
 }
 
-/// Iterate through the types of cast we should make.
-fn cast_types() -> impl Iterator<Item = CastMutability> {
-    if SUPPORT_MUTABLE_CASTS {
-        vec![
-            CastMutability::ConstToConst,
-            CastMutability::MutToConst,
-            CastMutability::MutToMut,
-        ]
-        .into_iter()
-    } else {
-        vec![CastMutability::ConstToConst].into_iter()
+/// The classes to which we can cast this one. That's its castable bases
+/// (see [`super::pod::PodAnalysis::castable_bases`]), their castable bases,
+/// and so on, except for any which it inherits more than once, since C++
+/// wouldn't know which we meant. bindgen doesn't tell us about virtual
+/// bases, so we don't cast to those either.
+fn unambiguous_castable_ancestors(
+    name: &QualifiedName,
+    bases_by_type: &HashMap<QualifiedName, (HashSet<QualifiedName>, HashSet<QualifiedName>)>,
+) -> Vec<QualifiedName> {
+    // How many times we inherit each ancestor, however we do so.
+    let mut inheritance_counts: HashMap<&QualifiedName, usize> = HashMap::new();
+    let mut todo = vec![name];
+    while let Some(ty) = todo.pop() {
+        if let Some((bases, _)) = bases_by_type.get(ty) {
+            for base in bases {
+                *inheritance_counts.entry(base).or_default() += 1;
+                todo.push(base);
+            }
+        }
     }
+    let mut castable = HashSet::new();
+    let mut todo = vec![name];
+    while let Some(ty) = todo.pop() {
+        if let Some((_, castable_bases)) = bases_by_type.get(ty) {
+            for base in castable_bases {
+                if castable.insert(base.clone()) {
+                    todo.push(base);
+                }
+            }
+        }
+    }
+    castable
+        .into_iter()
+        .filter(|base| inheritance_counts.get(base) == Some(&1))
+        .collect()
 }
 
 fn create_cast(from: &QualifiedName, to: &QualifiedName, mutable: CastMutability) -> Api<PodPhase> {
     let name = name_for_cast(from, to, mutable);
-    let ident = name.get_final_ident();
     let from_typ = from.to_type_path();
     let to_typ = to.to_type_path();
-    let return_mutability = match mutable {
-        CastMutability::ConstToConst | CastMutability::MutToConst => quote! { const },
-        CastMutability::MutToMut => quote! { mut },
-    };
-    let param_mutability = match mutable {
-        CastMutability::ConstToConst => quote! { const },
-        CastMutability::MutToConst | CastMutability::MutToMut => quote! { mut },
+    let (ident, doc_attrs, mutability, add_to_trait) = match mutable {
+        CastMutability::ConstToConst => (
+            name.get_final_ident(),
+            Vec::new(),
+            quote! { const },
+            Some(TraitSynthesis::Cast {
+                to_type: to.clone(),
+            }),
+        ),
+        CastMutability::MutToMut => {
+            let doc = format!("Casts to the base class `{}`.", to.to_cpp_name());
+            let doc_attr: syn::Attribute = parse_quote! {
+                #[doc = #doc]
+            };
+            (
+                make_ident(format!("as_{}_mut", to.get_final_item())),
+                vec![doc_attr.into()],
+                quote! { mut },
+                None,
+            )
+        }
     };
     let fnarg: FnArg = parse_quote! {
-        this: * #param_mutability #from_typ
+        this: * #mutability #from_typ
     };
     Api::Function {
         name: ApiName::new_from_qualified_name(name),
         fun: Box::new(crate::conversion::api::FuncToConvert {
             ident,
-            doc_attrs: Vec::new(),
+            doc_attrs,
             inputs: [fnarg].into_iter().collect(),
             output: parse_quote! {
-                -> * #return_mutability #to_typ
+                -> * #mutability #to_typ
             },
             vis: parse_quote! { pub },
             virtualness: crate::conversion::api::Virtualness::None,
@@ -111,14 +157,18 @@ fn create_cast(from: &QualifiedName, to: &QualifiedName, mutable: CastMutability
             original_name: None,
             self_ty: Some(from.clone()),
             synthesized_this_type: None,
-            add_to_trait: Some(TraitSynthesis::Cast {
-                to_type: to.clone(),
-                mutable,
-            }),
+            add_to_trait,
             synthetic_cpp: Some((CppFunctionBody::Cast, CppFunctionKind::Function)),
             is_deleted: DeletedOrDefaulted::Neither,
             provenance: Provenance::SynthesizedOther,
             variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
         }),
         analysis: (),
     }
@@ -131,7 +181,6 @@ fn name_for_cast(
 ) -> QualifiedName {
     let suffix = match mutable {
         CastMutability::ConstToConst => "",
-        CastMutability::MutToConst => "_to_const",
         CastMutability::MutToMut => "_mut",
     };
     let name = format!(
@@ -0,0 +1,105 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Works out which Cargo features, if any, should gate the Rust bindings
+//! generated for each API.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
+
+use crate::{conversion::apivec::ApiVec, types::QualifiedName};
+
+use super::{deps::HasDependencies, fun::FnPhase};
+
+/// For each API which should only be generated if some Cargo feature
+/// is enabled, returns the list of features (any of which) enable it.
+/// APIs which are not in the map are needed unconditionally.
+///
+/// This follows the same edges as garbage collection: each API which is
+/// on the allowlist is labelled with the feature requested for it, if any,
+/// and everything it depends upon is needed whenever that feature is
+/// enabled. Anything reachable from an ungated API is itself ungated.
+pub(crate) fn find_cfg_features(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+    cfg_features: &[(String, String)],
+) -> HashMap<QualifiedName, Vec<String>> {
+    let mut results: HashMap<QualifiedName, Vec<String>> = HashMap::new();
+    if cfg_features.is_empty() {
+        return results;
+    }
+    let deps_by_name: HashMap<&QualifiedName, Vec<&QualifiedName>> = apis
+        .iter()
+        .map(|api| (api.name(), api.deps().collect()))
+        .collect();
+    let mut roots_by_feature: HashMap<Option<&str>, Vec<&QualifiedName>> = HashMap::new();
+    for api in apis.iter() {
+        let cpp_name = api.name_for_allowlist().to_cpp_name();
+        if config.is_on_allowlist(&cpp_name) {
+            roots_by_feature
+                .entry(feature_for_root(&cpp_name, config, cfg_features))
+                .or_default()
+                .push(api.name());
+        }
+    }
+    let ungated = reachable(
+        &deps_by_name,
+        roots_by_feature.remove(&None).unwrap_or_default(),
+    );
+    for (feature, roots) in roots_by_feature {
+        let feature = feature.unwrap();
+        for name in reachable(&deps_by_name, roots) {
+            if !ungated.contains(name) {
+                results
+                    .entry(name.clone())
+                    .or_default()
+                    .push(feature.to_string());
+            }
+        }
+    }
+    results
+}
+
+/// Which feature was requested for an API on the allowlist. For
+/// `subclass!` directives, this includes the various supporting types we
+/// synthesize for the subclass.
+fn feature_for_root<'a>(
+    cpp_name: &str,
+    config: &IncludeCppConfig,
+    cfg_features: &'a [(String, String)],
+) -> Option<&'a str> {
+    cfg_features
+        .iter()
+        .find(|(item, _)| {
+            item == cpp_name
+                || config.subclasses.iter().any(|sc| {
+                    sc.subclass == item
+                        && [format!("{item}Holder"), format!("{item}Cpp")]
+                            .iter()
+                            .any(|synthesized| synthesized == cpp_name)
+                })
+        })
+        .map(|(_, feature)| feature.as_str())
+}
+
+fn reachable<'a>(
+    deps_by_name: &HashMap<&'a QualifiedName, Vec<&'a QualifiedName>>,
+    mut todos: Vec<&'a QualifiedName>,
+) -> HashSet<&'a QualifiedName> {
+    let mut done = HashSet::new();
+    while let Some(todo) = todos.pop() {
+        if done.insert(todo) {
+            if let Some(deps) = deps_by_name.get(todo) {
+                todos.extend(deps.iter().copied());
+            }
+        }
+    }
+    done
+}
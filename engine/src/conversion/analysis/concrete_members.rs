@@ -0,0 +1,195 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to turn the functions which we declared to stand for the member
+//! functions of `concrete!` types back into methods of those types.
+//!
+//! bindgen doesn't tell us about the members of class templates, so
+//! before running it we declare a function for each member of each
+//! `concrete!` instantiation, taking the object as its first parameter
+//! (see [`crate::libclang::examine_header`]). Here we make each
+//! a method which calls the real member. Constructors and the destructor
+//! are declared too, for the sake of subclasses, which can derive from
+//! the instantiation.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use syn::{parse_quote, FnArg, Pat, Type};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, FuncToConvert, Provenance, SpecialMemberKind, Virtualness,
+        },
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    libclang::{ConcreteMemberKind, CONCRETE_MEMBERS_NAMESPACE},
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+pub(crate) fn create_concrete_members(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let concretes: HashMap<String, (QualifiedName, String)> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::ConcreteType {
+                name,
+                cpp_definition,
+                rs_definition: None,
+            } => Some((
+                name.name.get_final_item().to_string(),
+                (name.name.clone(), cpp_definition.clone()),
+            )),
+            _ => None,
+        })
+        .collect();
+    let superclasses: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Subclass { superclass, .. } => Some(superclass.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut results = ApiVec::new();
+    for api in apis.into_iter() {
+        match api {
+            Api::Function { name, fun, .. } if is_concrete_member(&name.name) => {
+                // Anything which we made from these functions, such as a
+                // variant taking `&str`, would have the wrong shape.
+                if !matches!(fun.provenance, Provenance::Bindgen) {
+                    continue;
+                }
+                let function_name = fun
+                    .original_name
+                    .clone()
+                    .unwrap_or_else(|| fun.ident.to_string());
+                let Some((concrete, kind, member)) =
+                    ConcreteMemberKind::parse_function_name(&function_name)
+                else {
+                    continue;
+                };
+                let Some((ty_name, cpp_definition)) = concretes.get(concrete) else {
+                    continue;
+                };
+                // Only a subclass can construct or destroy an instantiation
+                // through these; otherwise it's done as for any other type.
+                if matches!(
+                    kind,
+                    ConcreteMemberKind::Constructor | ConcreteMemberKind::Destructor
+                ) && !superclasses.contains(ty_name)
+                {
+                    continue;
+                }
+                results.push(create_member(*fun, ty_name, cpp_definition, kind, member));
+            }
+            _ => results.push(api),
+        }
+    }
+    results
+}
+
+fn is_concrete_member(name: &QualifiedName) -> bool {
+    name.get_namespace()
+        .iter()
+        .last()
+        .is_some_and(|ns| ns == CONCRETE_MEMBERS_NAMESPACE)
+}
+
+fn create_member(
+    mut fun: FuncToConvert,
+    ty_name: &QualifiedName,
+    cpp_definition: &str,
+    kind: ConcreteMemberKind,
+    member: &str,
+) -> Api<PodPhase> {
+    // The member's own name is the one to use in Rust. Constructors are
+    // recognized by being named after the type.
+    let cpp_name = match kind {
+        ConcreteMemberKind::Constructor => ty_name.get_final_item(),
+        _ => member,
+    };
+    let name = ApiName::new_with_cpp_name(
+        ty_name.get_namespace(),
+        fun.ident.clone(),
+        Some(cpp_name.to_string()),
+    );
+    let payload = match kind {
+        ConcreteMemberKind::Unsupported => {
+            return Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_method(
+                    ty_name.get_final_ident(),
+                    make_ident(member),
+                )),
+                name,
+                err: ConvertErrorFromCpp::UnsupportedConcreteMember(cpp_definition.to_string()),
+            }
+        }
+        ConcreteMemberKind::StaticMethod => Some((
+            CppFunctionBody::Call(format!("{cpp_definition}::{member}")),
+            CppFunctionKind::Function,
+        )),
+        _ => {
+            // The object comes first, by reference, as the `this` which a
+            // method would have.
+            if let Some(arg) = fun.inputs.iter_mut().next() {
+                if let FnArg::Typed(pt) = &**arg {
+                    if let (Pat::Ident(pi), Type::Ptr(ptr)) = (&*pt.pat, &*pt.ty) {
+                        fun.references
+                            .ref_params
+                            .remove(&make_ident(pi.ident.to_string()));
+                        let typ = ty_name.to_type_path();
+                        let this: FnArg = match ptr.mutability {
+                            Some(_) => parse_quote! { this: *mut #typ },
+                            None => parse_quote! { this: *const #typ },
+                        };
+                        *arg = this.into();
+                    }
+                }
+            }
+            match kind {
+                ConcreteMemberKind::VirtualMethod => fun.virtualness = Virtualness::Virtual,
+                ConcreteMemberKind::PureVirtualMethod => fun.virtualness = Virtualness::PureVirtual,
+                // These are used only by subclasses, which call them by way
+                // of the typedef for the instantiation, so they need no
+                // payload. The instantiation may be abstract, so nobody
+                // else may use them.
+                ConcreteMemberKind::Constructor | ConcreteMemberKind::Destructor => {
+                    fun.cpp_vis = CppVisibility::Protected;
+                    fun.special_member = match kind {
+                        ConcreteMemberKind::Destructor => Some(SpecialMemberKind::Destructor),
+                        _ if fun.inputs.len() == 1 => Some(SpecialMemberKind::DefaultConstructor),
+                        _ => None,
+                    };
+                }
+                _ => {}
+            }
+            match kind {
+                ConcreteMemberKind::Constructor | ConcreteMemberKind::Destructor => None,
+                _ => Some((
+                    CppFunctionBody::Call(member.to_string()),
+                    CppFunctionKind::Method,
+                )),
+            }
+        }
+    };
+    fun.self_ty = Some(ty_name.clone());
+    fun.original_name = Some(cpp_name.to_string());
+    fun.synthetic_cpp = payload;
+    fun.provenance = Provenance::SynthesizedOther;
+    Api::Function {
+        name,
+        fun: Box::new(fun),
+        analysis: (),
+    }
+}
@@ -84,6 +84,8 @@ fn find_important_constructors(
                             kind:
                                 TraitMethodKind::Alloc
                                 | TraitMethodKind::Dealloc
+                                | TraitMethodKind::ExplicitDestructor
+                                | TraitMethodKind::SharedPtrFromUniquePtr
                                 | TraitMethodKind::CopyConstructor
                                 | TraitMethodKind::MoveConstructor,
                             impl_for,
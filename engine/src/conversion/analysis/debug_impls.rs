@@ -0,0 +1,196 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create functions which stringify types, such that we can
+//! implement `Debug` and `Display` for them.
+//!
+//! We use a type's `to_string` or `str` method if it has one. Otherwise,
+//! if the user asked for it with `derive_debug!`, we use its
+//! `operator<<(std::ostream&, const T&)`; bindgen doesn't tell us whether
+//! there is one, so the generated C++ finds out, and if not describes the
+//! object by its type and address.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, Pat, ReturnType, Type};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TraitSynthesis,
+        },
+        apivec::ApiVec,
+    },
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+/// C++ methods which we'll use to stringify an object, in order
+/// of preference.
+const STRINGIFY_METHODS: &[&str] = &["to_string", "str"];
+
+pub(crate) fn create_debug_impls(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let mut stringify_methods: HashMap<QualifiedName, &str> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Function { fun, .. } = api {
+            if let Some((ty_name, method)) = find_stringify_method(fun) {
+                if config.is_on_debug_blocklist(&ty_name.to_cpp_name()) {
+                    continue;
+                }
+                match stringify_methods.get(&ty_name) {
+                    Some(existing) if preference(existing) <= preference(method) => {}
+                    _ => {
+                        stringify_methods.insert(ty_name, method);
+                    }
+                }
+            }
+        }
+    }
+    let struct_names: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let stream_requests: Vec<_> = config
+        .get_debug_requests()
+        .iter()
+        .filter(|ty| !config.is_on_debug_blocklist(ty))
+        .map(|ty| QualifiedName::new_from_cpp_name(ty))
+        .filter(|ty_name| {
+            struct_names.contains(ty_name) && !stringify_methods.contains_key(ty_name)
+        })
+        .collect();
+    let mut apis = apis;
+    for (ty_name, method) in stringify_methods {
+        apis.push(create_stringify_function(
+            ty_name,
+            CppFunctionBody::Stringify(make_ident(method)),
+        ));
+    }
+    for ty_name in stream_requests {
+        apis.push(create_stringify_function(
+            ty_name.clone(),
+            CppFunctionBody::StreamOut(ty_name),
+        ));
+    }
+    apis
+}
+
+fn preference(method: &str) -> usize {
+    STRINGIFY_METHODS
+        .iter()
+        .position(|candidate| *candidate == method)
+        .unwrap()
+}
+
+/// Determines whether this is a public, const method taking no
+/// arguments and returning a `std::string` by value, with a name we
+/// recognize. If so, returns the type and the method name.
+fn find_stringify_method(fun: &FuncToConvert) -> Option<(QualifiedName, &'static str)> {
+    if fun.synthetic_cpp.is_some()
+        || !matches!(fun.cpp_vis, CppVisibility::Public)
+        || !matches!(fun.is_deleted, DeletedOrDefaulted::Neither)
+    {
+        return None;
+    }
+    let name = fun
+        .original_name
+        .clone()
+        .unwrap_or_else(|| fun.ident.to_string());
+    let method = STRINGIFY_METHODS
+        .iter()
+        .find(|candidate| **candidate == name)?;
+    let returns_string = match &*fun.output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(typ) => QualifiedName::from_type_path(typ).to_cpp_name() == "std::string",
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+    if !returns_string || fun.references.ref_return || fun.references.rvalue_ref_return {
+        return None;
+    }
+    let mut inputs = fun.inputs.iter();
+    let this = inputs.next()?;
+    if inputs.next().is_some() {
+        return None;
+    }
+    match &**this {
+        FnArg::Typed(pt) => match (pt.pat.as_ref(), pt.ty.as_ref()) {
+            (Pat::Ident(pp), Type::Ptr(ptr)) if pp.ident == "this" && ptr.mutability.is_none() => {
+                match ptr.elem.as_ref() {
+                    Type::Path(typ) => Some((QualifiedName::from_type_path(typ), *method)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    }
+}
+
+fn create_stringify_function(ty_name: QualifiedName, body: CppFunctionBody) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let name = get_stringify_function_name(&ty_name);
+    let ident = name.get_final_ident();
+    let inputs: Punctuated<FnArg, Comma> = parse_quote! {
+        arg0: *const #typ
+    };
+    let output: ReturnType = parse_quote! {
+        -> root::std::string
+    };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            ident,
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((body, CppFunctionKind::Function)),
+            add_to_trait: Some(TraitSynthesis::Debug(ty_name)),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        analysis: (),
+    }
+}
+
+fn get_stringify_function_name(ty_name: &QualifiedName) -> QualifiedName {
+    let name = format!("{}_autocxx_to_string", ty_name.get_final_item());
+    QualifiedName::new(ty_name.get_namespace(), make_ident(name))
+}
@@ -0,0 +1,447 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create functions which call friend functions defined within
+//! classes, user-defined literal operators, instantiations of function
+//! templates, and call operators.
+//!
+//! bindgen tells us about none of these, so we rely on the user giving their
+//! signatures in `friend_function!`, `literal_operator!`, `instantiate!` and
+//! `call_operator!` directives. A friend function defined within a class can only be found by
+//! argument-dependent lookup, so we call it unqualified.
+
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::{DeclaredFunction, IncludeCppConfig};
+use quote::ToTokens;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType, Type, TypePtr,
+    TypeReference,
+};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+        },
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    known_types::known_types,
+    minisyn::minisynize_punctuated,
+    types::{make_ident, Namespace, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+/// The Rust name of a call operator.
+const CALL_OPERATOR_METHOD: &str = "call";
+
+/// How a function described by a directive relates to a class.
+#[derive(Clone, Copy)]
+enum Membership<'a> {
+    None,
+    /// A friend function, which must take the class as a parameter.
+    FriendOf(&'a QualifiedName),
+    /// A method, whose first parameter is the object.
+    Method(&'a QualifiedName),
+    /// A static method.
+    StaticMethod(&'a QualifiedName),
+}
+
+impl<'a> Membership<'a> {
+    fn self_ty(&self) -> Option<&'a QualifiedName> {
+        match self {
+            Self::Method(class) | Self::StaticMethod(class) => Some(class),
+            Self::None | Self::FriendOf(_) => None,
+        }
+    }
+}
+
+pub(crate) fn create_declared_functions(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let structs: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut apis = apis;
+    for friend in config.get_friend_functions() {
+        let (class, function) = friend.split_name();
+        let class = QualifiedName::new_from_cpp_name(class);
+        // If we didn't generate the class, for whatever reason, the user
+        // will already have been told why.
+        if !structs.contains(&class) {
+            continue;
+        }
+        // The friend is a member of the namespace enclosing the class.
+        let name = QualifiedName::new(class.get_namespace(), make_ident(function));
+        apis.push(create_declared_function(
+            friend,
+            &name,
+            None,
+            Membership::FriendOf(&class),
+            config
+                .get_rust_rename(&name.to_cpp_name())
+                .map(str::to_string),
+            CppFunctionBody::Call(function.to_string()),
+            Provenance::SynthesizedFriend(class.clone()),
+        ));
+    }
+    for literal in config.get_literal_operators() {
+        let literal_name = QualifiedName::new_from_cpp_name(&literal.name);
+        let ns = literal_name.get_namespace();
+        let suffix = literal_name.get_final_item();
+        // `operator""_px` isn't a valid identifier, so we call it
+        // `operator__px`. There are no arguments of class type by which
+        // C++ could find a literal operator, so we qualify the call.
+        let cpp_name = qualify(ns, &format!("operator\"\"{suffix}"));
+        let name = QualifiedName::new(ns, make_ident(format!("operator_{suffix}")));
+        // The user may choose a Rust name using rename!, keyed on the C++
+        // name, such as `geo::operator""_px`.
+        apis.push(create_declared_function(
+            literal,
+            &name,
+            None,
+            Membership::None,
+            config.get_rust_rename(&cpp_name).map(str::to_string),
+            CppFunctionBody::Call(cpp_name.clone()),
+            Provenance::SynthesizedLiteralOperator(literal.name.clone()),
+        ));
+    }
+    for instantiation in config.get_instantiations() {
+        let (scope_name, function) = instantiation.split_name();
+        let rust_name = instantiation.rust_name.clone().unwrap();
+        let scope = QualifiedName::new_from_cpp_name(scope_name);
+        let provenance = Provenance::SynthesizedInstantiation {
+            name: instantiation.name.clone(),
+            signature: describe_signature(instantiation),
+        };
+        let api = if structs.contains(&scope) {
+            // A member function template. It's called on an object if the
+            // first parameter is that object.
+            let is_method = matches!(
+                instantiation.inputs.first().map(|ty| convert_declared_type(ty, scope.get_namespace())),
+                Some(Ok((Type::Ptr(_), ref pointee))) if *pointee == scope
+            );
+            let name = QualifiedName::new(
+                scope.get_namespace(),
+                make_ident(format!("{}_{rust_name}", scope.get_final_item())),
+            );
+            let (membership, payload) = if is_method {
+                (
+                    Membership::Method(&scope),
+                    CppFunctionBody::Call(function.to_string()),
+                )
+            } else {
+                (
+                    Membership::StaticMethod(&scope),
+                    CppFunctionBody::Call(instantiation.name.clone()),
+                )
+            };
+            create_declared_function(
+                instantiation,
+                &name,
+                Some(rust_name.clone()),
+                membership,
+                Some(rust_name),
+                payload,
+                provenance,
+            )
+        } else {
+            // A free function template in the namespace `scope`.
+            let ns = if scope_name.is_empty() {
+                Namespace::new()
+            } else {
+                scope
+                    .get_namespace()
+                    .push(scope.get_final_item().to_string())
+            };
+            let name = QualifiedName::new(&ns, make_ident(&rust_name));
+            create_declared_function(
+                instantiation,
+                &name,
+                None,
+                Membership::None,
+                Some(rust_name),
+                CppFunctionBody::Call(instantiation.name.clone()),
+                provenance,
+            )
+        };
+        apis.push(api);
+    }
+    for (i, call) in config.get_call_operators().iter().enumerate() {
+        let class = QualifiedName::new_from_cpp_name(&call.name);
+        if !structs.contains(&class) {
+            continue;
+        }
+        // `operator()` isn't a valid identifier, so we make up a name for
+        // the API and call the method `call`. Overloads are then numbered
+        // as for any other method.
+        let name = QualifiedName::new(
+            class.get_namespace(),
+            make_ident(format!(
+                "{}_autocxx_call_operator{i}",
+                class.get_final_item()
+            )),
+        );
+        let has_object = matches!(
+            call.inputs.first().map(|ty| (ty, convert_declared_type(ty, class.get_namespace()))),
+            Some((Type::Reference(_), Ok((_, ref pointee)))) if *pointee == class
+        );
+        if !has_object {
+            apis.push(Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_method(
+                    class.get_final_ident(),
+                    make_ident(CALL_OPERATOR_METHOD),
+                )),
+                name: ApiName::new_from_qualified_name(name),
+                err: ConvertErrorFromCpp::CallOperatorWithoutObject(call.name.clone()),
+            });
+            continue;
+        }
+        apis.push(create_declared_function(
+            call,
+            &name,
+            Some(CALL_OPERATOR_METHOD.to_string()),
+            Membership::Method(&class),
+            None,
+            CppFunctionBody::Call("operator()".to_string()),
+            Provenance::SynthesizedOther,
+        ));
+    }
+    apis
+}
+
+fn qualify(ns: &Namespace, item: &str) -> String {
+    if ns.is_empty() {
+        item.to_string()
+    } else {
+        format!("{}::{item}", ns.to_cpp_path())
+    }
+}
+
+fn create_declared_function(
+    declared: &DeclaredFunction,
+    name: &QualifiedName,
+    original_name: Option<String>,
+    membership: Membership,
+    pinned_rust_name: Option<String>,
+    payload: CppFunctionBody,
+    provenance: Provenance,
+) -> Api<PodPhase> {
+    let ns = name.get_namespace();
+    let api_name = ApiName::new_from_qualified_name(name.clone());
+    let signature = convert_signature(declared, ns, membership).map_err(|err| match &provenance {
+        Provenance::SynthesizedInstantiation { signature, .. } => {
+            ConvertErrorFromCpp::Instantiation {
+                signature: signature.clone(),
+                err: Box::new(err),
+            }
+        }
+        _ => err,
+    });
+    let (inputs, output, references) = match signature {
+        Ok(signature) => signature,
+        Err(err) => {
+            let ctx = match membership.self_ty() {
+                Some(class) => ErrorContext::new_for_method(
+                    class.get_final_ident(),
+                    make_ident(original_name.as_ref().unwrap()),
+                ),
+                None => ErrorContext::new_for_item(name.get_final_ident()),
+            };
+            return Api::IgnoredItem {
+                ctx: Some(ctx),
+                name: api_name,
+                err,
+            };
+        }
+    };
+    let kind = match membership {
+        Membership::Method(_) => CppFunctionKind::Method,
+        _ => CppFunctionKind::Function,
+    };
+    Api::Function {
+        name: api_name,
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references,
+            original_name,
+            self_ty: membership.self_ty().cloned(),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((payload, kind)),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        analysis: (),
+    }
+}
+
+/// Converts the signature given in the directive into the form bindgen
+/// would have given us, with references as pointers.
+fn convert_signature(
+    declared: &DeclaredFunction,
+    ns: &Namespace,
+    membership: Membership,
+) -> Result<(Punctuated<FnArg, Comma>, ReturnType, References), ConvertErrorFromCpp> {
+    let mut references = References::default();
+    let mut inputs = Punctuated::new();
+    let mut mentions_class = false;
+    for (i, ty) in declared.inputs.iter().enumerate() {
+        // Like bindgen, we call the object on which a method is called
+        // `this`, and always pass it as a pointer.
+        let arg = match membership {
+            Membership::Method(_) if i == 0 => make_ident("this"),
+            _ => make_ident(format!("arg{i}")),
+        };
+        if matches!(ty, Type::Reference(_)) && arg != "this" {
+            references.ref_params.insert(arg.clone());
+        }
+        let (ty, pointee) = convert_declared_type(ty, ns)?;
+        if let Membership::FriendOf(class) = membership {
+            mentions_class |= *class == pointee;
+        }
+        inputs.push(parse_quote! { #arg: #ty });
+    }
+    if let Membership::FriendOf(class) = membership {
+        if !mentions_class {
+            return Err(ConvertErrorFromCpp::FriendWithoutClassParameter(
+                class.to_cpp_name(),
+            ));
+        }
+    }
+    let output = match &declared.output {
+        None => ReturnType::Default,
+        Some(ty) => {
+            references.ref_return = matches!(ty, Type::Reference(_));
+            let (ty, _) = convert_declared_type(ty, ns)?;
+            parse_quote! { -> #ty }
+        }
+    };
+    Ok((inputs, output, references))
+}
+
+/// Converts a type from a directive into the form bindgen would have
+/// given us, and also returns the name of the type to which it refers.
+/// A single identifier names a primitive, or a type in the namespace `ns`;
+/// otherwise a path is the fully-qualified name of a C++ type.
+fn convert_declared_type(
+    ty: &Type,
+    ns: &Namespace,
+) -> Result<(Type, QualifiedName), ConvertErrorFromCpp> {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability, elem, ..
+        })
+        | Type::Ptr(TypePtr {
+            mutability, elem, ..
+        }) => {
+            let (elem, pointee) = convert_declared_type(elem, ns)?;
+            let ty = match mutability {
+                Some(_) => parse_quote! { *mut #elem },
+                None => parse_quote! { *const #elem },
+            };
+            Ok((ty, pointee))
+        }
+        Type::Path(typ)
+            if typ.qself.is_none()
+                && typ.path.segments.iter().all(|seg| seg.arguments.is_empty()) =>
+        {
+            let segments: Vec<_> = typ
+                .path
+                .segments
+                .iter()
+                .map(|seg| seg.ident.to_string())
+                .collect();
+            let name = match segments.as_slice() {
+                [id] if typ.path.leading_colon.is_none() => {
+                    let primitive = QualifiedName::new_from_cpp_name(id);
+                    let ctype = QualifiedName::new_from_cpp_name(&format!("std::os::raw::{id}"));
+                    if known_types().is_known_type(&primitive) {
+                        primitive
+                    } else if known_types().is_ctype(&ctype) {
+                        ctype
+                    } else {
+                        QualifiedName::new(ns, make_ident(id))
+                    }
+                }
+                _ => QualifiedName::new_from_cpp_name(&segments.join("::")),
+            };
+            Ok((Type::Path(name.to_type_path()), name))
+        }
+        _ => Err(ConvertErrorFromCpp::UnsupportedDeclaredFunctionType(
+            ty.to_token_stream().to_string(),
+        )),
+    }
+}
+
+/// Describes the signature of an instantiation as the user gave it, such
+/// as `Config::get<int>(&Config, &std::string) -> i32`.
+fn describe_signature(declared: &DeclaredFunction) -> String {
+    let inputs: Vec<_> = declared.inputs.iter().map(describe_type).collect();
+    let output = declared
+        .output
+        .as_ref()
+        .map(|ty| format!(" -> {}", describe_type(ty)))
+        .unwrap_or_default();
+    format!("{}({}){output}", declared.name, inputs.join(", "))
+}
+
+fn describe_type(ty: &Type) -> String {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability, elem, ..
+        }) => format!(
+            "&{}{}",
+            if mutability.is_some() { "mut " } else { "" },
+            describe_type(elem)
+        ),
+        Type::Ptr(TypePtr {
+            mutability, elem, ..
+        }) => format!(
+            "*{} {}",
+            if mutability.is_some() { "mut" } else { "const" },
+            describe_type(elem)
+        ),
+        Type::Path(typ) if typ.qself.is_none() => {
+            let segments: Vec<_> = typ
+                .path
+                .segments
+                .iter()
+                .map(|seg| seg.ident.to_string())
+                .collect();
+            segments.join("::")
+        }
+        _ => ty.to_token_stream().to_string(),
+    }
+}
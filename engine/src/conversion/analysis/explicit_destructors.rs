@@ -0,0 +1,97 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create functions which explicitly destroy types listed in
+//! `explicit_destructor!` directives.
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TraitSynthesis,
+        },
+        apivec::ApiVec,
+    },
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+pub(crate) fn create_explicit_destructors(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    apis.into_iter()
+        .flat_map(|api| -> Box<dyn Iterator<Item = Api<PodPhase>>> {
+            match &api {
+                Api::Struct { name, .. }
+                    if config.has_explicit_destructor(&name.name.to_cpp_name()) =>
+                {
+                    let destroy = create_explicit_destructor(name.name.clone());
+                    Box::new(std::iter::once(destroy).chain(std::iter::once(api)))
+                }
+                _ => Box::new(std::iter::once(api)),
+            }
+        })
+        .collect()
+}
+
+fn create_explicit_destructor(ty_name: QualifiedName) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let name = get_explicit_destructor_name(&ty_name);
+    let ident = name.get_final_ident();
+    let inputs: Punctuated<FnArg, Comma> = parse_quote! {
+        arg0: *mut #typ
+    };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            ident,
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: ReturnType::Default.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::DestroyAndFree(ty_name.clone()),
+                CppFunctionKind::Function,
+            )),
+            add_to_trait: Some(TraitSynthesis::ExplicitDestructor(ty_name)),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        analysis: (),
+    }
+}
+
+fn get_explicit_destructor_name(ty_name: &QualifiedName) -> QualifiedName {
+    let name = format!("{}_autocxx_destroy", ty_name.get_final_item());
+    QualifiedName::new(ty_name.get_namespace(), make_ident(name))
+}
@@ -7,8 +7,8 @@
 // except according to those terms.
 
 use crate::types::Namespace;
+use indexmap::map::IndexMap as HashMap;
 use itertools::Itertools;
-use std::collections::HashMap;
 
 /// Type to generate unique names for entries in the [cxx::bridge]
 /// mod which is flat.
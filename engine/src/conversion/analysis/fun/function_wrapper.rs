@@ -8,10 +8,19 @@
 
 use crate::minisyn::Ident;
 use crate::{
-    conversion::{api::SubclassName, type_helpers::extract_pinned_mutable_reference_type},
+    conversion::{
+        analysis::{
+            iterators::IteratorShim, maps::MapAccessor, vectors::VectorAccessor, ChronoType,
+            WideCharUnit,
+        },
+        api::SubclassName,
+        type_helpers::extract_pinned_mutable_reference_type,
+    },
     types::{Namespace, QualifiedName},
+    CppStandard,
 };
-use quote::ToTokens;
+use autocxx_parser::{ClosureParam, CppOperator};
+use quote::{quote, ToTokens};
 use syn::{parse_quote, Type, TypeReference};
 
 #[derive(Clone, Debug)]
@@ -22,11 +31,121 @@ pub(crate) enum CppConversionType {
     FromPtrToValue,
     FromValueToUniquePtr,
     FromPtrToMove,
+    /// A `T&&` parameter of a function used by a subclass, received from
+    /// Rust as a `std::unique_ptr<T>` from which we move.
+    FromUniquePtrToRValue,
+    /// The opposite of [`Self::FromUniquePtrToRValue`]: a `T&&` received
+    /// by a subclass override is moved into a new `std::unique_ptr<T>` to
+    /// pass to Rust.
+    FromRValueToUniquePtr,
     /// Ignored in the sense that it isn't passed into the C++ function.
     IgnoredPlacementPtrParameter,
     FromReturnValueToPlacementPtr,
     FromPointerToReference, // unwrapped_type is always Type::Ptr
     FromReferenceToPointer, // unwrapped_type is always Type::Ptr
+    /// A `std::optional<T>` parameter, received from Rust as a possibly-null
+    /// `const T*`. unwrapped_type is always Type::Ptr.
+    FromPtrToOptional,
+    /// A returned `std::optional<T>`, whose contents are moved into a
+    /// `std::unique_ptr<T>` which is null if the optional was empty.
+    FromOptionalToUniquePtr,
+    /// A returned `std::optional<T>`, whose contents are moved into a placement
+    /// parameter. The function instead returns whether there was any value.
+    FromOptionalToPlacementPtr,
+    /// A C function pointer parameter. cxx can't represent function
+    /// pointers, so it's received from Rust as a `void*` and cast back to
+    /// this type, which is the `Option<unsafe extern "C" fn(...)>` we use
+    /// for it in Rust. unwrapped_type is always `*mut c_void`.
+    FromVoidPtrToFunctionPointer(Box<crate::minisyn::Type>),
+    /// A pointer to a pointer, such as a `char**`, which cxx also can't
+    /// represent. It's received from Rust as a `void*` and cast back to
+    /// this type, which is the same raw pointer type in Rust.
+    /// unwrapped_type is always `*mut c_void`.
+    FromVoidPtrToIndirectPointer(Box<crate::minisyn::Type>),
+    /// The opposite, for a returned pointer to a pointer.
+    /// unwrapped_type is always `*mut c_void`.
+    FromIndirectPointerToVoidPtr(Box<crate::minisyn::Type>),
+    /// A function which returns nothing, but whose out parameters are
+    /// returned to Rust as an array. unwrapped_type is always `()`.
+    FromVoidToOutArray,
+    /// Somewhere for C++ to put the function (of type `void(*)(void*)`)
+    /// with which Rust should free an array described by an `out_array!`
+    /// directive. It calls `free_with` on the array, whose elements are of
+    /// the given type. This isn't passed to the C++ function.
+    /// unwrapped_type is always `*mut c_void`.
+    ToOutArrayFreeFunction {
+        free_with: String,
+        elem: Box<crate::minisyn::Type>,
+    },
+    /// A fixed-size array parameter, which cxx represents as a reference
+    /// to a `std::array`. It's cast to a reference to the equivalent C
+    /// array, which also decays to a pointer if that's what the C++
+    /// function takes. unwrapped_type is always a reference to an array.
+    FromStdArrayToCArray,
+    /// A `const std::string&` parameter, received from Rust as a
+    /// `rust::Str` from which we construct the `std::string`.
+    /// unwrapped_type is always `&str`.
+    FromStrToString,
+    /// A `std::string_view` parameter, received from Rust as a
+    /// `rust::Slice<const uint8_t>` from which we construct the view.
+    /// unwrapped_type is always `&[u8]`.
+    FromSliceToStringView,
+    /// A `std::span<T>` parameter, received from Rust as a `rust::Slice<T>`.
+    /// unwrapped_type is always a slice reference.
+    FromSliceToSpan,
+    /// A `std::initializer_list<T>` parameter, received from Rust as a
+    /// `rust::Slice<const T>`. There's no portable way to make an
+    /// initializer list of a length known only at runtime, so the C++
+    /// wrapper calls the function with a braced list of the slice's
+    /// elements, for each length up to a limit; see the initializer list
+    /// prelude. unwrapped_type is always a slice reference.
+    FromSliceToInitializerList,
+    /// A pointer and length parameter pair, received from Rust as a single
+    /// `rust::Slice<T>` which we split back into both arguments.
+    /// unwrapped_type is always a slice reference.
+    FromSliceToPointerAndLength,
+    /// A returned `std::pair` or `std::tuple`, whose elements are moved into
+    /// placement parameters. The function itself returns nothing.
+    FromTupleToPlacementPtrs,
+    /// One element of a `std::pair` or `std::tuple` parameter, received from
+    /// Rust separately. The C++ wrapper brace-initializes the pair or tuple
+    /// from all its elements, so the first and last add the braces.
+    FromTupleElement {
+        first: bool,
+        last: bool,
+    },
+    /// A `std::function` parameter, received from Rust as a
+    /// `rust::Box` owning a closure. We wrap it in a lambda which calls
+    /// the closure using the given extern "Rust" function.
+    FromBoxToStdFunction(crate::minisyn::Ident),
+    /// A `std::chrono::duration` or `time_point` parameter, received from
+    /// Rust as a count of nanoseconds which we convert to whatever
+    /// representation and period the C++ function takes.
+    /// unwrapped_type is always `i64`.
+    FromNanosToChrono,
+    /// A returned `std::chrono::duration` or `time_point`, converted to a
+    /// saturated count of nanoseconds. unwrapped_type is always `i64`.
+    FromChronoToNanos,
+    /// A `std::filesystem::path` parameter, received from Rust as the bytes
+    /// of the path. unwrapped_type is always `&[u8]`.
+    FromSliceToPath,
+    /// A returned `std::filesystem::path`, whose bytes are copied into a
+    /// `std::unique_ptr<std::string>`. unwrapped_type is always `CxxString`.
+    FromPathToUniquePtrString,
+    /// A `std::u8string`, `std::u16string`, `std::u32string` or
+    /// `std::wstring` parameter (of the given C++ type), received from Rust
+    /// as a slice of code units.
+    /// If `c_str`, the C++ function instead takes a pointer to a
+    /// NUL-terminated string, for which we make a terminated copy.
+    /// unwrapped_type is always a slice reference.
+    FromSliceToWideString {
+        string_type: String,
+        c_str: bool,
+    },
+    /// A returned `std::u8string`, `std::u16string`, `std::u32string` or
+    /// `std::wstring`, whose code units are copied into a `rust::Vec`.
+    /// unwrapped_type is always a `Vec`.
+    FromWideStringToVec(WideCharUnit),
 }
 
 impl CppConversionType {
@@ -36,10 +155,13 @@ impl CppConversionType {
     fn inverse(&self) -> Self {
         match self {
             CppConversionType::None => CppConversionType::None,
+            CppConversionType::Move => CppConversionType::Move,
             CppConversionType::FromUniquePtrToValue | CppConversionType::FromPtrToValue => {
                 CppConversionType::FromValueToUniquePtr
             }
             CppConversionType::FromValueToUniquePtr => CppConversionType::FromUniquePtrToValue,
+            CppConversionType::FromUniquePtrToRValue => CppConversionType::FromRValueToUniquePtr,
+            CppConversionType::FromRValueToUniquePtr => CppConversionType::FromUniquePtrToRValue,
             CppConversionType::FromPointerToReference => CppConversionType::FromReferenceToPointer,
             CppConversionType::FromReferenceToPointer => CppConversionType::FromPointerToReference,
             _ => panic!("Did not expect to have to invert this conversion"),
@@ -60,6 +182,70 @@ pub(crate) enum RustConversionType {
     FromRValueParamToPtr,
     FromReferenceWrapperToPointer, // unwrapped_type is always Type::Ptr
     FromPointerToReferenceWrapper, // unwrapped_type is always Type::Ptr
+    FromOptionToPtr,               // unwrapped_type is always Type::Ptr
+    FromOptionRefToPtr,            // unwrapped_type is always Type::Ptr
+    /// The space into which part of a return value is moved, such as the
+    /// contents of a `std::optional<T>` or one element of a `std::tuple`.
+    /// This doesn't appear in the wrapper function's parameters.
+    /// unwrapped_type is always Type::Ptr.
+    FromOutParam,
+    /// A returned `std::pair` or `std::tuple`, whose elements have been moved
+    /// into the out parameters created by the previous case.
+    /// unwrapped_type is always a Rust tuple.
+    FromOutParamsToTuple,
+    /// The first element of a `std::pair` or `std::tuple` parameter. Rust
+    /// callers pass the whole tuple, which we destructure into these local
+    /// variables, one per element; each is a separate cxx::bridge parameter.
+    FromTupleToElements(Box<crate::minisyn::Type>, Vec<crate::minisyn::Ident>),
+    /// Any subsequent element of a `std::pair` or `std::tuple` parameter,
+    /// taken from the local variables created by the previous case.
+    FromTupleElement,
+    FromBoolToOption,
+    FromUniquePtrToOption,
+    FromFunctionPointerToVoidPtr, // unwrapped_type is always Type::Ptr
+    FromAsRefStrToStr,            // unwrapped_type is always &str
+    FromAsRefBytesToSlice,        // unwrapped_type is always &[u8]
+    /// A closure, which we box up into the given holder type. The
+    /// type is the `impl FnMut` which Rust callers pass.
+    FromClosureToBox(crate::minisyn::Ident, Box<crate::minisyn::Type>),
+    /// A `std::time::Duration` or `autocxx::chrono::TimePoint` parameter,
+    /// passed to the cxx::bridge as nanoseconds. unwrapped_type is always `i64`.
+    FromChronoToNanos(ChronoType),
+    /// The opposite, for return values. unwrapped_type is always `i64`.
+    FromNanosToChrono(ChronoType),
+    FromAsRefPathToSlice, // unwrapped_type is always &[u8]
+    FromUniquePtrStringToPathBuf,
+    /// A pointer to a pointer parameter, which Rust callers pass as the
+    /// raw pointer type itself. unwrapped_type is always Type::Ptr.
+    FromIndirectPointerToVoidPtr,
+    /// The opposite, for return values. unwrapped_type is always Type::Ptr.
+    FromVoidPtrToIndirectPointer,
+    /// One of the out parameters described by an `out_array!` directive,
+    /// pointing to the given type. Like [`Self::FromOutParam`], this
+    /// doesn't appear in the wrapper function's parameters; Rust
+    /// zero-initializes it before the call.
+    FromArrayOutParam(Box<crate::minisyn::Type>),
+    /// A return value which Rust callers receive as an
+    /// `autocxx::CppArray`, built from the out parameters created by the
+    /// previous case. If the function returned a primitive as well as the
+    /// array, callers receive both in a tuple.
+    FromOutArray(Box<OutArrayReturn>),
+}
+
+/// Where to find an array described by an `out_array!` directive once the
+/// C++ function has returned.
+#[derive(Clone, Debug)]
+pub(crate) struct OutArrayReturn {
+    /// The out parameter holding the pointer to the array, or `None` if
+    /// the function returns that pointer.
+    pub(crate) data: Option<crate::minisyn::Ident>,
+    /// The out parameter holding the number of elements.
+    pub(crate) len: crate::minisyn::Ident,
+    pub(crate) elem: crate::minisyn::Type,
+    /// Whether there's an out parameter, called `autocxx_free`, holding
+    /// the function with which to free the array. If not, C++ retains
+    /// ownership of the array.
+    pub(crate) free: bool,
 }
 
 impl RustConversionType {
@@ -147,6 +333,320 @@ impl TypeConversionPolicy {
         }
     }
 
+    /// A `std::optional<T>` parameter, where `payload` is the `T`. If
+    /// `payload_by_value`, Rust callers pass an `Option<T>`, otherwise
+    /// an `Option<&T>`.
+    pub(crate) fn new_from_optional(payload: Type, payload_by_value: bool) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *const #payload },
+            cpp_conversion: CppConversionType::FromPtrToOptional,
+            rust_conversion: if payload_by_value {
+                RustConversionType::FromOptionToPtr
+            } else {
+                RustConversionType::FromOptionRefToPtr
+            },
+        }
+    }
+
+    /// A returned `std::optional<T>` where `T` can't be held by value
+    /// in Rust, so is returned as an `Option<UniquePtr<T>>`.
+    pub(crate) fn new_to_optional_unique_ptr(payload: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: payload.into(),
+            cpp_conversion: CppConversionType::FromOptionalToUniquePtr,
+            rust_conversion: RustConversionType::FromUniquePtrToOption,
+        }
+    }
+
+    /// A returned `std::optional<T>` where `T` can be held by value in
+    /// Rust. This must be paired with a parameter created by
+    /// [`Self::new_for_out_param`].
+    pub(crate) fn new_for_optional_return(payload: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: payload.into(),
+            cpp_conversion: CppConversionType::FromOptionalToPlacementPtr,
+            rust_conversion: RustConversionType::FromBoolToOption,
+        }
+    }
+
+    /// A returned `std::pair` or `std::tuple`, where `ty` is the Rust tuple.
+    /// This must be paired with a parameter created by
+    /// [`Self::new_for_out_param`] for each element.
+    pub(crate) fn new_for_tuple_return(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromTupleToPlacementPtrs,
+            rust_conversion: RustConversionType::FromOutParamsToTuple,
+        }
+    }
+
+    /// One element, of type `ty`, of a `std::pair` or `std::tuple` parameter.
+    /// `rust_conversion` must be [`RustConversionType::FromTupleToElements`]
+    /// for the first element and [`RustConversionType::FromTupleElement`]
+    /// for the others.
+    pub(crate) fn new_for_tuple_element(
+        ty: Type,
+        first: bool,
+        last: bool,
+        rust_conversion: RustConversionType,
+    ) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromTupleElement { first, last },
+            rust_conversion,
+        }
+    }
+
+    pub(crate) fn new_for_out_param(payload: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut #payload },
+            cpp_conversion: CppConversionType::IgnoredPlacementPtrParameter,
+            rust_conversion: RustConversionType::FromOutParam,
+        }
+    }
+
+    /// A C function pointer parameter, where `ty` is the
+    /// `Option<unsafe extern "C" fn(...)>` which Rust callers pass.
+    pub(crate) fn new_from_function_pointer(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut autocxx::c_void },
+            cpp_conversion: CppConversionType::FromVoidPtrToFunctionPointer(Box::new(ty.into())),
+            rust_conversion: RustConversionType::FromFunctionPointerToVoidPtr,
+        }
+    }
+
+    /// A fixed-size array parameter, where `ty` is the reference to the
+    /// array which Rust callers pass.
+    pub(crate) fn new_from_array(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromStdArrayToCArray,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `const std::string&` parameter which Rust callers may pass
+    /// as any `impl AsRef<str>`.
+    pub(crate) fn new_from_str() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &str },
+            cpp_conversion: CppConversionType::FromStrToString,
+            rust_conversion: RustConversionType::FromAsRefStrToStr,
+        }
+    }
+
+    /// A `std::string_view` parameter which Rust callers may pass as
+    /// any `impl AsRef<[u8]>`, including `&str`.
+    pub(crate) fn new_from_string_view() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[u8] },
+            cpp_conversion: CppConversionType::FromSliceToStringView,
+            rust_conversion: RustConversionType::FromAsRefBytesToSlice,
+        }
+    }
+
+    /// A `std::span<T>` parameter, where `ty` is the slice reference which
+    /// Rust callers pass.
+    pub(crate) fn new_from_span(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromSliceToSpan,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::initializer_list<T>` parameter, where `ty` is the slice
+    /// reference which Rust callers pass.
+    pub(crate) fn new_from_initializer_list(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromSliceToInitializerList,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A pointer and length parameter pair, described by a `slice_params!`
+    /// directive, where `ty` is the slice reference which Rust callers pass.
+    pub(crate) fn new_from_pointer_and_length(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: ty.into(),
+            cpp_conversion: CppConversionType::FromSliceToPointerAndLength,
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::chrono::duration` or `time_point` parameter which Rust
+    /// callers pass as the equivalent `std::time` type.
+    pub(crate) fn new_from_chrono(ty: ChronoType) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { i64 },
+            cpp_conversion: CppConversionType::FromNanosToChrono,
+            rust_conversion: RustConversionType::FromChronoToNanos(ty),
+        }
+    }
+
+    /// A returned `std::chrono::duration` or `time_point`.
+    pub(crate) fn new_to_chrono(ty: ChronoType) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { i64 },
+            cpp_conversion: CppConversionType::FromChronoToNanos,
+            rust_conversion: RustConversionType::FromNanosToChrono(ty),
+        }
+    }
+
+    /// A `std::filesystem::path` parameter which Rust callers may pass as
+    /// any `impl AsRef<Path>`.
+    pub(crate) fn new_from_path() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[u8] },
+            cpp_conversion: CppConversionType::FromSliceToPath,
+            rust_conversion: RustConversionType::FromAsRefPathToSlice,
+        }
+    }
+
+    /// A returned `std::filesystem::path`, which Rust callers receive as a
+    /// `PathBuf`.
+    pub(crate) fn new_to_path() -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { cxx::CxxString },
+            cpp_conversion: CppConversionType::FromPathToUniquePtrString,
+            rust_conversion: RustConversionType::FromUniquePtrStringToPathBuf,
+        }
+    }
+
+    /// A wide string parameter, which Rust callers pass as a slice of
+    /// code units.
+    pub(crate) fn new_from_wide_string(
+        string_type: String,
+        unit: WideCharUnit,
+        c_str: bool,
+    ) -> Self {
+        let unit = unit.rust_type();
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { &[#unit] },
+            cpp_conversion: CppConversionType::FromSliceToWideString { string_type, c_str },
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A returned wide string, which Rust callers receive as a `Vec` of
+    /// code units.
+    pub(crate) fn new_to_wide_string_vec(unit: WideCharUnit) -> Self {
+        let ty = unit.rust_type();
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { Vec<#ty> },
+            cpp_conversion: CppConversionType::FromWideStringToVec(unit),
+            rust_conversion: RustConversionType::None,
+        }
+    }
+
+    /// A `std::function` parameter which Rust callers may pass as a
+    /// closure, described by a `closure_param!` directive.
+    pub(crate) fn new_from_closure(closure: &ClosureParam) -> Self {
+        let holder = closure.holder();
+        let inputs = closure.inputs.iter().map(|ty| match ty {
+            Type::Reference(_) => parse_quote! { &::cxx::CxxString },
+            _ => ty.clone(),
+        });
+        let ret = closure.output.as_ref().map(|ty| quote! { -> #ty });
+        let closure_type: Type = parse_quote! {
+            impl FnMut(#(#inputs),*) #ret + 'static
+        };
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { rust::Box<#holder> },
+            cpp_conversion: CppConversionType::FromBoxToStdFunction(closure.call_fn().into()),
+            rust_conversion: RustConversionType::FromClosureToBox(
+                holder.into(),
+                Box::new(closure_type.into()),
+            ),
+        }
+    }
+
+    /// The `Option<unsafe extern "C" fn(...)>` for a conversion involving
+    /// function pointers.
+    pub(crate) fn function_pointer_type(&self) -> Option<&Type> {
+        match &self.cpp_conversion {
+            CppConversionType::FromVoidPtrToFunctionPointer(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// A pointer to a pointer parameter, where `ty` is the raw pointer
+    /// type which Rust callers pass.
+    pub(crate) fn new_from_indirect_pointer(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut autocxx::c_void },
+            cpp_conversion: CppConversionType::FromVoidPtrToIndirectPointer(Box::new(ty.into())),
+            rust_conversion: RustConversionType::FromIndirectPointerToVoidPtr,
+        }
+    }
+
+    /// A returned pointer to a pointer, where `ty` is the raw pointer type
+    /// which Rust callers receive.
+    pub(crate) fn new_to_indirect_pointer(ty: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut autocxx::c_void },
+            cpp_conversion: CppConversionType::FromIndirectPointerToVoidPtr(Box::new(ty.into())),
+            rust_conversion: RustConversionType::FromVoidPtrToIndirectPointer,
+        }
+    }
+
+    /// The raw pointer type for a conversion involving pointers to
+    /// pointers.
+    pub(crate) fn indirect_pointer_type(&self) -> Option<&Type> {
+        match &self.cpp_conversion {
+            CppConversionType::FromVoidPtrToIndirectPointer(ty)
+            | CppConversionType::FromIndirectPointerToVoidPtr(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Turns this parameter into one of the out parameters described by an
+    /// `out_array!` directive, which Rust supplies itself. `pointee` is the
+    /// type to which it points.
+    pub(crate) fn into_array_out_param(self, pointee: Type) -> Self {
+        TypeConversionPolicy {
+            rust_conversion: RustConversionType::FromArrayOutParam(Box::new(pointee.into())),
+            ..self
+        }
+    }
+
+    /// The out parameter into which C++ puts the function with which to
+    /// free an array described by an `out_array!` directive, whose
+    /// elements are of type `elem`.
+    pub(crate) fn new_for_out_array_free_function(free_with: String, elem: Type) -> Self {
+        TypeConversionPolicy {
+            unwrapped_type: parse_quote! { *mut autocxx::c_void },
+            cpp_conversion: CppConversionType::ToOutArrayFreeFunction {
+                free_with,
+                elem: Box::new(elem.into()),
+            },
+            rust_conversion: RustConversionType::FromArrayOutParam(Box::new(
+                parse_quote! { *mut autocxx::c_void },
+            )),
+        }
+    }
+
+    /// Turns this return value into an array described by an `out_array!`
+    /// directive. If there's no return value, `this` is `None`.
+    pub(crate) fn new_for_out_array_return(this: Option<Self>, out_array: OutArrayReturn) -> Self {
+        let this = this.unwrap_or_else(|| TypeConversionPolicy {
+            unwrapped_type: parse_quote! { () },
+            cpp_conversion: CppConversionType::FromVoidToOutArray,
+            rust_conversion: RustConversionType::None,
+        });
+        TypeConversionPolicy {
+            rust_conversion: RustConversionType::FromOutArray(Box::new(out_array)),
+            ..this
+        }
+    }
+
+    /// Whether this is a return value converted by
+    /// [`Self::new_for_out_array_return`].
+    pub(crate) fn is_out_array_return(&self) -> bool {
+        matches!(self.rust_conversion, RustConversionType::FromOutArray(_))
+    }
+
     pub(crate) fn new_for_placement_return(ty: Type) -> Self {
         TypeConversionPolicy {
             unwrapped_type: ty.into(),
@@ -164,14 +664,19 @@ impl TypeConversionPolicy {
 
     pub(crate) fn unconverted_rust_type(&self) -> Type {
         match self.cpp_conversion {
-            CppConversionType::FromValueToUniquePtr => self.make_unique_ptr_type(),
+            CppConversionType::FromValueToUniquePtr
+            | CppConversionType::FromOptionalToUniquePtr
+            | CppConversionType::FromPathToUniquePtrString => self.make_unique_ptr_type(),
+            CppConversionType::FromOptionalToPlacementPtr => parse_quote! { bool },
             _ => self.unwrapped_type.clone().into(),
         }
     }
 
     pub(crate) fn converted_rust_type(&self) -> Type {
         match self.cpp_conversion {
-            CppConversionType::FromUniquePtrToValue => self.make_unique_ptr_type(),
+            CppConversionType::FromUniquePtrToValue | CppConversionType::FromUniquePtrToRValue => {
+                self.make_unique_ptr_type()
+            }
             CppConversionType::FromPtrToValue => {
                 let innerty = &self.unwrapped_type;
                 parse_quote! {
@@ -212,6 +717,12 @@ impl TypeConversionPolicy {
                 | RustConversionType::FromPlacementParamToNewReturn
                 | RustConversionType::FromPointerToReferenceWrapper { .. }
                 | RustConversionType::FromReferenceWrapperToPointer { .. }
+                | RustConversionType::FromOptionToPtr
+                | RustConversionType::FromOptionRefToPtr
+                | RustConversionType::FromOutParam
+                | RustConversionType::FromFunctionPointerToVoidPtr
+                | RustConversionType::FromIndirectPointerToVoidPtr
+                | RustConversionType::FromArrayOutParam(_)
         )
     }
 
@@ -222,6 +733,125 @@ impl TypeConversionPolicy {
         )
     }
 
+    /// If this is a parameter which C++ receives by value, initialized by
+    /// moving from an object supplied by Rust, the type of that parameter.
+    pub(crate) fn type_moved_into_param(&self) -> Option<QualifiedName> {
+        match (
+            &self.cpp_conversion,
+            &self.rust_conversion,
+            self.cxxbridge_type(),
+        ) {
+            (
+                CppConversionType::FromPtrToValue,
+                RustConversionType::FromValueParamToPtr,
+                Type::Path(tp),
+            )
+            | (CppConversionType::FromUniquePtrToValue, RustConversionType::None, Type::Path(tp)) => {
+                Some(QualifiedName::from_type_path(tp))
+            }
+            _ => None,
+        }
+    }
+
+    /// If this is a value which C++ returns, and which we construct in its
+    /// final location (a placement parameter or the heap) from the returned
+    /// prvalue, the type of that value.
+    pub(crate) fn type_constructed_from_return(&self) -> Option<QualifiedName> {
+        match (&self.cpp_conversion, self.cxxbridge_type()) {
+            (
+                CppConversionType::FromReturnValueToPlacementPtr
+                | CppConversionType::FromValueToUniquePtr,
+                Type::Path(tp),
+            ) => Some(QualifiedName::from_type_path(tp)),
+            _ => None,
+        }
+    }
+
+    /// Whether converting this return value in Rust requires unsafe code,
+    /// in which case the call to the cxx::bridge function will be within
+    /// the same unsafe block.
+    pub(crate) fn rust_return_conversion_requires_unsafe(&self) -> bool {
+        matches!(
+            self.rust_conversion,
+            RustConversionType::FromBoolToOption
+                | RustConversionType::FromOutParamsToTuple
+                | RustConversionType::FromOutArray(_)
+        )
+    }
+
+    pub(crate) fn is_closure(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromBoxToStdFunction(_)
+        )
+    }
+
+    pub(crate) fn involves_optional(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromPtrToOptional
+                | CppConversionType::FromOptionalToUniquePtr
+                | CppConversionType::FromOptionalToPlacementPtr
+        )
+    }
+
+    pub(crate) fn involves_chrono(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromNanosToChrono | CppConversionType::FromChronoToNanos
+        )
+    }
+
+    pub(crate) fn involves_path(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromSliceToPath | CppConversionType::FromPathToUniquePtrString
+        )
+    }
+
+    /// The C++ standard library feature, if any, which our C++ wrapper
+    /// needs for this conversion, and the standard which introduced it.
+    pub(crate) fn required_cpp_standard(&self) -> Option<(&'static str, CppStandard)> {
+        match &self.cpp_conversion {
+            CppConversionType::FromPtrToOptional
+            | CppConversionType::FromOptionalToUniquePtr
+            | CppConversionType::FromOptionalToPlacementPtr => {
+                Some(("std::optional", CppStandard::Cpp17))
+            }
+            CppConversionType::FromSliceToStringView => {
+                Some(("std::string_view", CppStandard::Cpp17))
+            }
+            CppConversionType::FromSliceToPath | CppConversionType::FromPathToUniquePtrString => {
+                Some(("std::filesystem::path", CppStandard::Cpp17))
+            }
+            CppConversionType::FromSliceToSpan => Some(("std::span", CppStandard::Cpp20)),
+            CppConversionType::FromSliceToWideString { string_type, .. }
+                if string_type == "std::u8string" =>
+            {
+                Some(("std::u8string", CppStandard::Cpp20))
+            }
+            CppConversionType::FromWideStringToVec(WideCharUnit::U8) => {
+                Some(("std::u8string", CppStandard::Cpp20))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_initializer_list(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromSliceToInitializerList
+        )
+    }
+
+    pub(crate) fn involves_wide_string(&self) -> bool {
+        matches!(
+            self.cpp_conversion,
+            CppConversionType::FromSliceToWideString { .. }
+                | CppConversionType::FromWideStringToVec(_)
+        )
+    }
+
     pub(crate) fn populate_return_value(&self) -> bool {
         !matches!(
             self.cpp_conversion,
@@ -240,6 +870,48 @@ pub(crate) enum CppFunctionBody {
     Destructor(Namespace, Ident),
     AllocUninitialized(QualifiedName),
     FreeUninitialized(QualifiedName),
+    /// Run the destructor of the object of the given type, then free
+    /// storage from `AllocUninitialized`.
+    DestroyAndFree(QualifiedName),
+    /// Call the given const method to turn the object into a string.
+    Stringify(Ident),
+    /// Turn the object of the given type into a string using its
+    /// `operator<<`, or describe it if it has none.
+    StreamOut(QualifiedName),
+    /// Apply the given C++ operator to the two arguments.
+    Operator(CppOperator),
+    /// Hash the argument using `std::hash` for the given type.
+    Hash(QualifiedName),
+    /// Return the value of the given (fully-qualified) static data.
+    StaticDataGetter(String),
+    /// Assign the argument to the given (fully-qualified) static data.
+    StaticDataSetter(String),
+    /// One of the accessor methods we provide for maps.
+    MapAccessor(MapAccessor),
+    /// Find the address of the most-derived object of which the receiver
+    /// is part.
+    ObjectAddress,
+    /// Return `sizeof` the given type, checking at compile time that it's
+    /// the size we baked into the Rust code, if any.
+    SizeOf(QualifiedName, Option<usize>),
+    /// Return `alignof` the given type, checking at compile time that it's
+    /// the alignment we baked into the Rust code, if any.
+    AlignOf(QualifiedName, Option<usize>),
+    /// One of the accessor methods we provide for vectors of
+    /// `std::unique_ptr`s.
+    VectorAccessor(VectorAccessor),
+    /// One of the functions with which Rust iterates over a container.
+    Iterator(IteratorShim),
+    /// Move the object owned by the given `std::unique_ptr` into a
+    /// `std::shared_ptr`.
+    SharedPtrFromUniquePtr(QualifiedName),
+    /// Call the given `&&`-qualified method on the first argument, which
+    /// has been moved from.
+    ConsumingMethodCall(Ident),
+    /// Call the function of the given name, which is left unqualified if
+    /// it must be found by argument-dependent lookup, or the method of that
+    /// name on the receiver.
+    Call(String),
 }
 
 #[derive(Clone, Debug)]
@@ -261,4 +933,10 @@ pub(crate) struct CppFunction {
     pub(crate) kind: CppFunctionKind,
     pub(crate) pass_obs_field: bool,
     pub(crate) qualification: Option<QualifiedName>,
+    /// Whether to catch any C++ exception and rethrow it in a form
+    /// which the Rust side can return as a `Result`.
+    pub(crate) fallible: bool,
+    /// Whether the wrapped function is `noexcept`, in which case the
+    /// wrapper can be too.
+    pub(crate) noexcept: bool,
 }
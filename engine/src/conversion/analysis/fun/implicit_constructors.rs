@@ -9,7 +9,7 @@
 use indexmap::map::IndexMap as HashMap;
 use indexmap::{map::Entry, set::IndexSet as HashSet};
 
-use syn::{Type, TypeArray};
+use syn::Type;
 
 use crate::conversion::api::DeletedOrDefaulted;
 use crate::{
@@ -20,6 +20,7 @@ use crate::{
         api::{Api, ApiName, CppVisibility, FuncToConvert, SpecialMemberKind},
         apivec::ApiVec,
         convert_error::ConvertErrorWithContext,
+        type_helpers::array_element_type,
         ConvertErrorFromCpp,
     },
     known_types::{known_types, KnownTypeConstructorDetails},
@@ -91,6 +92,16 @@ pub(super) struct ItemsFound {
     /// Remember that [`const_copy_constructor`] may be used in place of this if it exists.
     pub(super) non_const_copy_constructor: SpecialMemberFound,
     pub(super) move_constructor: SpecialMemberFound,
+    /// Whether copying, moving and destroying this type are all trivial,
+    /// such that it can be `Copy` in Rust. We're conservative here: anything
+    /// we don't fully understand is assumed not to be.
+    pub(super) is_trivially_copyable: bool,
+    /// Whether we know that this type can be neither moved nor copied, so
+    /// `T(std::move(t))` won't compile. Move-only types are fine.
+    pub(super) immovable: bool,
+    /// Whether the type has any user-declared constructor, even a deleted
+    /// one, which means that it can't be aggregate-initialized.
+    pub(super) user_declared_constructor: bool,
 
     /// The full name of the type. We identify instances by [`QualifiedName`], because that's
     /// the only thing which [`FnKind::Method`] has to tie it to, and that's unique enough for
@@ -228,11 +239,18 @@ pub(super) fn find_constructors_present(
                         const_copy_constructor: SpecialMemberFound::Implicit,
                         non_const_copy_constructor: SpecialMemberFound::NotPresent,
                         move_constructor: SpecialMemberFound::Implicit,
+                        // bindgen doesn't derive Copy for enums.
+                        is_trivially_copyable: false,
+                        immovable: false,
+                        user_declared_constructor: false,
                         name: Some(name.clone()),
                     })
                 } else if let Some(constructor_details) = known_types().get_constructor_details(qn)
                 {
-                    Some(known_type_items_found(constructor_details))
+                    Some(ItemsFound {
+                        is_trivially_copyable: known_types().is_c_abi_by_value(qn),
+                        ..known_type_items_found(constructor_details)
+                    })
                 } else {
                     all_items_found.get(qn).cloned()
                 }
@@ -241,20 +259,19 @@ pub(super) fn find_constructors_present(
             let fields_items_found: Vec<_> = field_info
                 .iter()
                 .filter_map(|field_info| match field_info.type_kind {
-                    TypeKind::Regular | TypeKind::SubclassHolder(_) => match field_info.ty {
-                        Type::Path(ref qn) => get_items_found(&QualifiedName::from_type_path(qn)),
-                        Type::Array(TypeArray { ref elem, .. }) => match elem.as_ref() {
+                    TypeKind::Regular | TypeKind::SubclassHolder(_) => {
+                        match array_element_type(&field_info.ty) {
                             Type::Path(ref qn) => {
                                 get_items_found(&QualifiedName::from_type_path(qn))
                             }
                             _ => None,
-                        },
-                        _ => None,
-                    },
+                        }
+                    }
                     // TODO: https://github.com/google/autocxx/issues/865 Figure out how to
                     // differentiate between pointers and references coming from C++. Pointers
                     // have a default constructor.
                     TypeKind::Pointer
+                    | TypeKind::FunctionPointer
                     | TypeKind::Reference
                     | TypeKind::MutableReference
                     | TypeKind::RValueReference => Some(ItemsFound {
@@ -263,12 +280,42 @@ pub(super) fn find_constructors_present(
                         const_copy_constructor: SpecialMemberFound::Implicit,
                         non_const_copy_constructor: SpecialMemberFound::NotPresent,
                         move_constructor: SpecialMemberFound::Implicit,
+                        is_trivially_copyable: true,
+                        immovable: false,
+                        user_declared_constructor: false,
                         name: Some(name.clone()),
                     }),
                 })
                 .collect();
             let has_rvalue_reference_fields = details.has_rvalue_reference_fields;
 
+            // Moving from a `T&&` selects the move constructor if one is
+            // declared (even if it's deleted) and otherwise the const copy
+            // constructor.
+            let unusable = |kind: ExplicitKind| {
+                matches!(
+                    find_explicit(kind),
+                    Some(
+                        ExplicitFound::Deleted
+                            | ExplicitFound::UserDefined(
+                                CppVisibility::Private | CppVisibility::Protected
+                            )
+                    )
+                )
+            };
+            let explicitly_immovable = unusable(ExplicitKind::MoveConstructor)
+                || (find_explicit(ExplicitKind::MoveConstructor).is_none()
+                    && unusable(ExplicitKind::ConstCopyConstructor));
+            let user_declared_constructor = [
+                ExplicitKind::DefaultConstructor,
+                ExplicitKind::ConstCopyConstructor,
+                ExplicitKind::NonConstCopyConstructor,
+                ExplicitKind::MoveConstructor,
+                ExplicitKind::OtherConstructor,
+            ]
+            .into_iter()
+            .any(|kind| find_explicit(kind).is_some());
+
             // Check that all the bases and field types are known first. This combined with
             // iterating via [`depth_first`] means we can safely search in `items_found` for all of
             // them.
@@ -324,6 +371,9 @@ pub(super) fn find_constructors_present(
                     const_copy_constructor: is_explicit(ExplicitKind::ConstCopyConstructor),
                     non_const_copy_constructor: is_explicit(ExplicitKind::NonConstCopyConstructor),
                     move_constructor: is_explicit(ExplicitKind::MoveConstructor),
+                    is_trivially_copyable: false,
+                    immovable: explicitly_immovable,
+                    user_declared_constructor,
                     name: Some(name.clone()),
                 };
                 log::info!(
@@ -540,12 +590,41 @@ pub(super) fn find_constructors_present(
                     }
                 };
 
+                // A trivially copyable class has no user-provided copy or move
+                // operations nor destructor, and no virtual functions. (We
+                // don't check the latter here, but such types can't be POD anyway.)
+                // All bases and members must in turn be trivially copyable.
+                let is_trivially_copyable = destructor.exists_implicit()
+                    && const_copy_constructor.exists_implicit()
+                    && move_constructor.exists_implicit()
+                    && find_explicit(ExplicitKind::ConstCopyAssignmentOperator).is_none()
+                    && find_explicit(ExplicitKind::NonConstCopyAssignmentOperator).is_none()
+                    && find_explicit(ExplicitKind::MoveAssignmentOperator).is_none()
+                    && bases_items_found
+                        .iter()
+                        .chain(fields_items_found.iter())
+                        .all(|items_found| items_found.is_trivially_copyable);
+
+                // If there are several candidates, we don't know which
+                // overload resolution would pick.
+                let ambiguous = |kind: ExplicitKind| {
+                    matches!(find_explicit(kind), Some(ExplicitFound::Multiple))
+                };
+                let immovable = explicitly_immovable
+                    || (!move_constructor.callable_any()
+                        && !const_copy_constructor.callable_any()
+                        && !ambiguous(ExplicitKind::MoveConstructor)
+                        && !ambiguous(ExplicitKind::ConstCopyConstructor));
+
                 let items_found = ItemsFound {
                     default_constructor,
                     destructor,
                     const_copy_constructor,
                     non_const_copy_constructor,
                     move_constructor,
+                    is_trivially_copyable,
+                    immovable,
+                    user_declared_constructor,
                     name: Some(name.clone()),
                 };
                 log::info!(
@@ -710,6 +789,10 @@ fn known_type_items_found(constructor_details: KnownTypeConstructorDetails) -> I
         const_copy_constructor: exists_public_if(constructor_details.has_const_copy_constructor),
         non_const_copy_constructor: SpecialMemberFound::NotPresent,
         move_constructor: exists_public_if(constructor_details.has_move_constructor),
+        is_trivially_copyable: false,
+        immovable: !(constructor_details.has_move_constructor
+            || constructor_details.has_const_copy_constructor),
+        user_declared_constructor: true,
         name: None,
     }
 }
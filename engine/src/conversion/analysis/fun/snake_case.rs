@@ -0,0 +1,151 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use crate::types::{Namespace, QualifiedName};
+
+/// Where a function lives: its namespace, plus its type if it's a method.
+/// Names must be unique within each of these.
+type Owner = (Namespace, Option<QualifiedName>);
+
+/// Decides the snake_case Rust names of functions and methods, if the
+/// user asked for `rename_snake_case!`. We need to know all the names up
+/// front: if both `getFoo` and `get_foo` exist on the same type, only the
+/// latter can be called `get_foo`, so `getFoo` keeps its C++ name. If
+/// several camelCase names would collide, the first one declared wins.
+/// Overloads share a single C++ name, so they don't collide here; they'll
+/// be numbered later in the usual way.
+pub(crate) struct SnakeCaseRenamer {
+    unrenamable: HashSet<(Owner, String)>,
+}
+
+impl SnakeCaseRenamer {
+    pub(crate) fn new(functions: impl Iterator<Item = (Owner, String)>) -> Self {
+        let mut by_snake_name: HashMap<(Owner, String), HashSet<String>> = HashMap::new();
+        for (owner, cpp_name) in functions {
+            by_snake_name
+                .entry((owner, to_snake_case(&cpp_name)))
+                .or_default()
+                .insert(cpp_name);
+        }
+        let mut unrenamable = HashSet::new();
+        for ((owner, snake_name), cpp_names) in by_snake_name {
+            if cpp_names.len() < 2 {
+                continue;
+            }
+            let winner = if cpp_names.contains(&snake_name) {
+                snake_name.clone()
+            } else {
+                cpp_names[0].clone()
+            };
+            for cpp_name in cpp_names {
+                if cpp_name != winner {
+                    log::warn!(
+                        "Not renaming {cpp_name} to {snake_name}, because {winner} already has that name"
+                    );
+                    unrenamable.insert((owner.clone(), cpp_name));
+                }
+            }
+        }
+        Self { unrenamable }
+    }
+
+    /// The snake_case version of this name, unless that would collide
+    /// with another function.
+    pub(crate) fn get_rust_name(&self, owner: Owner, cpp_name: &str) -> Option<String> {
+        if self.unrenamable.contains(&(owner, cpp_name.to_string())) {
+            None
+        } else {
+            Some(to_snake_case(cpp_name))
+        }
+    }
+}
+
+/// Converts `camelCase` or `PascalCase` to `snake_case`. Runs of capitals
+/// are treated as a single word (`parseHTTPHeader` becomes
+/// `parse_http_header`) and digits stay attached to the word before them
+/// (`setValue1` becomes `set_value1`). Names without capitals are
+/// returned unchanged.
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let starts_word = match (i.checked_sub(1).map(|i| chars[i]), chars.get(i + 1)) {
+                (None | Some('_'), _) => false,
+                (Some(prev), _) if prev.is_ascii_lowercase() || prev.is_ascii_digit() => true,
+                (Some(prev), Some(next)) => prev.is_ascii_uppercase() && next.is_ascii_lowercase(),
+                (Some(_), None) => false,
+            };
+            if starts_word {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(*c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_snake_case, SnakeCaseRenamer};
+    use crate::types::{make_ident, Namespace, QualifiedName};
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("getFooBar"), "get_foo_bar");
+        assert_eq!(to_snake_case("GetFooBar"), "get_foo_bar");
+        assert_eq!(to_snake_case("setValue1"), "set_value1");
+        assert_eq!(to_snake_case("parseHTTPHeader"), "parse_http_header");
+        assert_eq!(to_snake_case("toURL"), "to_url");
+        assert_eq!(to_snake_case("already_snake_case"), "already_snake_case");
+        assert_eq!(to_snake_case("_privateThing"), "_private_thing");
+        assert_eq!(to_snake_case("move_"), "move_");
+    }
+
+    #[test]
+    fn test_collisions() {
+        let ns = Namespace::new();
+        let ty = Some(QualifiedName::new(&ns, make_ident("Widget")));
+        let renamer = SnakeCaseRenamer::new(
+            [
+                ((ns.clone(), ty.clone()), "getFoo".to_string()),
+                ((ns.clone(), ty.clone()), "get_foo".to_string()),
+                ((ns.clone(), ty.clone()), "setBar".to_string()),
+                ((ns.clone(), ty.clone()), "SetBar".to_string()),
+                ((ns.clone(), ty.clone()), "setBaz".to_string()),
+                ((ns.clone(), ty.clone()), "setBaz".to_string()),
+                ((ns.clone(), None), "getFoo".to_string()),
+            ]
+            .into_iter(),
+        );
+        let owner = || (ns.clone(), ty.clone());
+        assert_eq!(renamer.get_rust_name(owner(), "getFoo"), None);
+        assert_eq!(
+            renamer.get_rust_name(owner(), "get_foo"),
+            Some("get_foo".to_string())
+        );
+        assert_eq!(
+            renamer.get_rust_name(owner(), "setBar"),
+            Some("set_bar".to_string())
+        );
+        assert_eq!(renamer.get_rust_name(owner(), "SetBar"), None);
+        assert_eq!(
+            renamer.get_rust_name(owner(), "setBaz"),
+            Some("set_baz".to_string())
+        );
+        assert_eq!(
+            renamer.get_rust_name((ns.clone(), None), "getFoo"),
+            Some("get_foo".to_string())
+        );
+    }
+}
@@ -9,16 +9,19 @@
 use std::ops::DerefMut;
 
 use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
 
 use syn::{parse_quote, FnArg, PatType, Type, TypePtr};
 
 use crate::conversion::analysis::fun::{FnKind, MethodKind, ReceiverMutability, UnsafePolicy};
 use crate::conversion::analysis::pod::PodPhase;
 use crate::conversion::api::{
-    CppVisibility, FuncToConvert, Provenance, RustSubclassFnDetails, SubclassConstructorDetails,
-    SubclassName, SuperclassMethod, UnsafetyNeeded, Virtualness,
+    CppVisibility, FuncToConvert, Provenance, RustSubclassFnDetails, SpecialMemberKind,
+    SubclassConstructorDetails, SubclassName, SubclassProtectedMethodDetails, SuperclassMethod,
+    UnsafetyNeeded, Virtualness,
 };
 use crate::conversion::apivec::ApiVec;
+use crate::conversion::convert_error::{ConvertErrorFromCpp, ErrorContext};
 use crate::minisyn::minisynize_punctuated;
 use crate::{
     conversion::{
@@ -30,7 +33,7 @@ use crate::{
     types::{make_ident, Namespace, QualifiedName},
 };
 
-use super::{FnAnalysis, FnPrePhase1};
+use super::{FnAnalysis, FnPrePhase1, FnPrePhase2};
 
 pub(super) fn subclasses_by_superclass(
     apis: &ApiVec<PodPhase>,
@@ -48,6 +51,207 @@ pub(super) fn subclasses_by_superclass(
     subclasses_per_superclass
 }
 
+/// Like [`subclasses_by_superclass`], but each subclass is also listed against
+/// the public bases (direct or indirect) of its superclass. Only allowlisted
+/// bases are considered, as for casts.
+pub(super) fn subclasses_by_ancestor(
+    apis: &ApiVec<PodPhase>,
+) -> HashMap<QualifiedName, Vec<SubclassName>> {
+    let bases_by_type: HashMap<&QualifiedName, &HashSet<QualifiedName>> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. } => Some((&name.name, &analysis.castable_bases)),
+            _ => None,
+        })
+        .collect();
+    let mut subclasses_per_ancestor: HashMap<QualifiedName, Vec<SubclassName>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Subclass { name, superclass } = api {
+            let mut todo = vec![superclass];
+            let mut done = HashSet::new();
+            while let Some(ancestor) = todo.pop() {
+                if !done.insert(ancestor) {
+                    continue;
+                }
+                subclasses_per_ancestor
+                    .entry(ancestor.clone())
+                    .or_default()
+                    .push(name.clone());
+                if let Some(bases) = bases_by_type.get(ancestor) {
+                    todo.extend(bases.iter());
+                }
+            }
+        }
+    }
+    subclasses_per_ancestor
+}
+
+/// A subclass which may override virtual methods declared in some
+/// class: either its superclass, or an (allowlisted) indirect base.
+#[derive(Clone)]
+pub(super) struct OverridingSubclass {
+    pub(super) sub: SubclassName,
+    /// The superclass named in `subclass!`.
+    pub(super) superclass: QualifiedName,
+    /// Virtual methods which are redeclared by a class between the
+    /// superclass and the class in question, and therefore hide its
+    /// declarations.
+    pub(super) hidden_methods: HashSet<String>,
+}
+
+/// For each class which declares virtual methods, the subclasses which
+/// should be able to override them. This covers classes which are
+/// indirect bases of the subclass's superclass, so long as they're
+/// allowlisted.
+pub(super) fn subclasses_by_virtual_method_owner(
+    apis: &ApiVec<PodPhase>,
+) -> HashMap<QualifiedName, Vec<OverridingSubclass>> {
+    let bases_by_type: HashMap<&QualifiedName, &HashSet<QualifiedName>> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. } => Some((&name.name, &analysis.castable_bases)),
+            _ => None,
+        })
+        .collect();
+    let mut virtuals_by_type: HashMap<&QualifiedName, HashSet<String>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Function { name, fun, .. } = api {
+            if let (Some(self_ty), false) =
+                (&fun.self_ty, matches!(fun.virtualness, Virtualness::None))
+            {
+                virtuals_by_type
+                    .entry(self_ty)
+                    .or_default()
+                    .insert(name.cpp_name());
+            }
+        }
+    }
+    let ancestors_of = |ty: &QualifiedName| {
+        let mut todo: Vec<&QualifiedName> = bases_by_type
+            .get(ty)
+            .map(|bases| bases.iter().collect())
+            .unwrap_or_default();
+        let mut done = HashSet::new();
+        while let Some(ancestor) = todo.pop() {
+            if done.insert(ancestor.clone()) {
+                if let Some(bases) = bases_by_type.get(ancestor) {
+                    todo.extend(bases.iter());
+                }
+            }
+        }
+        done
+    };
+    let mut results: HashMap<QualifiedName, Vec<OverridingSubclass>> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Subclass { name, superclass } = api {
+            let classes: Vec<(QualifiedName, HashSet<QualifiedName>)> =
+                std::iter::once(superclass.clone())
+                    .chain(ancestors_of(superclass))
+                    .map(|class| {
+                        let ancestors = ancestors_of(&class);
+                        (class, ancestors)
+                    })
+                    .collect();
+            for (class, _) in &classes {
+                let hidden_methods = classes
+                    .iter()
+                    .filter(|(_, ancestors)| ancestors.contains(class))
+                    .filter_map(|(intermediate, _)| virtuals_by_type.get(intermediate))
+                    .flatten()
+                    .cloned()
+                    .collect();
+                results
+                    .entry(class.clone())
+                    .or_default()
+                    .push(OverridingSubclass {
+                        sub: name.clone(),
+                        superclass: superclass.clone(),
+                        hidden_methods,
+                    });
+            }
+        }
+    }
+    results
+}
+/// Types whose destructors are private. Even a subclass can't destroy these.
+pub(super) fn types_with_private_destructors(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Function { fun, .. }
+                if matches!(fun.special_member, Some(SpecialMemberKind::Destructor))
+                    && matches!(fun.cpp_vis, CppVisibility::Private) =>
+            {
+                fun.self_ty.clone()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The C++ peer of a subclass needs to destroy its superclass, so we can't
+/// generate subclasses of types with private destructors.
+pub(super) fn reject_subclasses_of_private_destructors(
+    apis: ApiVec<FnPrePhase2>,
+    private_destructors: &HashSet<QualifiedName>,
+) -> ApiVec<FnPrePhase2> {
+    let mut results = ApiVec::new();
+    results.extend(apis.into_iter().map(|api| match api {
+        Api::Subclass { name, superclass } if private_destructors.contains(&superclass) => {
+            Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_item(name.id())),
+                name: name.0,
+                err: ConvertErrorFromCpp::SubclassOfPrivateDestructor(superclass.to_cpp_name()),
+            }
+        }
+        _ => api,
+    }));
+    results
+}
+
+/// Types declared `final`, which can't be subclassed.
+pub(super) fn final_types(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, details, .. } if details.is_final => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A subclass's C++ peer would fail to compile if it derived from a
+/// `final` class, or overrode a `final` method, so we reject such
+/// subclasses. `final_overrides` gives, for each subclass which would
+/// override a `final` method, the first such method.
+pub(super) fn reject_subclasses_of_finals(
+    apis: ApiVec<FnPrePhase2>,
+    final_types: &HashSet<QualifiedName>,
+    final_overrides: &HashMap<SubclassName, String>,
+) -> ApiVec<FnPrePhase2> {
+    let mut results = ApiVec::new();
+    results.extend(apis.into_iter().map(|api| match api {
+        Api::Subclass { name, superclass } if final_types.contains(&superclass) => {
+            Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_item(name.id())),
+                name: name.0,
+                err: ConvertErrorFromCpp::SubclassOfFinal(superclass.to_cpp_name()),
+            }
+        }
+        Api::Subclass { name, superclass } => match final_overrides.get(&name) {
+            Some(method) => Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_item(name.id())),
+                err: ConvertErrorFromCpp::SubclassOverridingFinal(
+                    name.0.name.to_cpp_name(),
+                    method.clone(),
+                ),
+                name: name.0,
+            },
+            None => Api::Subclass { name, superclass },
+        },
+        _ => api,
+    }));
+    results
+}
+
 pub(super) fn create_subclass_fn_wrapper(
     sub: &SubclassName,
     super_fn_name: &QualifiedName,
@@ -73,11 +277,72 @@ pub(super) fn create_subclass_fn_wrapper(
         synthetic_cpp: None,
         provenance: Provenance::SynthesizedOther,
         variadic: fun.variadic,
+        noexcept: false,
+        explicit: false,
+        pinned_rust_name: None,
+        safety_override: None,
+        deprecated: fun.deprecated.clone(),
+        must_use: fun.must_use,
+        is_final: false,
     })
 }
 
+/// Creates a public method on the subclass which calls a protected
+/// method of the given ancestor class.
+pub(super) fn create_subclass_protected_fn_wrapper(
+    sub: &SubclassName,
+    forwarder_name: &QualifiedName,
+    ancestor: &QualifiedName,
+    name: &ApiName,
+    analysis: &FnAnalysis,
+    receiver_mutability: &ReceiverMutability,
+    fun: &FuncToConvert,
+) -> Box<FuncToConvert> {
+    let kind = if matches!(receiver_mutability, ReceiverMutability::Mutable) {
+        CppFunctionKind::Method
+    } else {
+        CppFunctionKind::ConstMethod
+    };
+    let argument_conversion = analysis
+        .param_details
+        .iter()
+        .skip(1)
+        .map(|p| p.conversion.clone())
+        .collect();
+    // Functions we've made up, such as accessors for protected fields,
+    // already say what to do.
+    let payload = match &fun.synthetic_cpp {
+        Some((payload, _)) => payload.clone(),
+        None => CppFunctionBody::StaticMethodCall(
+            ancestor.get_namespace().clone(),
+            ancestor.get_final_ident(),
+            make_ident(name.cpp_name()),
+        ),
+    };
+    let cpp_impl = CppFunction {
+        payload,
+        wrapper_function_name: forwarder_name.get_final_ident(),
+        original_cpp_name: name.cpp_name(),
+        return_conversion: analysis.ret_conversion.clone(),
+        argument_conversion,
+        kind,
+        pass_obs_field: false,
+        qualification: Some(sub.cpp()),
+        fallible: false,
+        noexcept: false,
+    };
+    let mut wrapper = create_subclass_fn_wrapper(sub, forwarder_name, fun);
+    wrapper.provenance =
+        Provenance::SynthesizedSubclassProtectedMethod(Box::new(SubclassProtectedMethodDetails {
+            subclass: sub.clone(),
+            cpp_impl,
+        }));
+    wrapper
+}
+
 pub(super) fn create_subclass_trait_item(
     name: ApiName,
+    fun: &FuncToConvert,
     analysis: &FnAnalysis,
     receiver_mutability: &ReceiverMutability,
     receiver: QualifiedName,
@@ -99,6 +364,7 @@ pub(super) fn create_subclass_trait_item(
         name,
         details: SuperclassMethod {
             name: make_ident(&analysis.rust_name),
+            doc_attrs: fun.doc_attrs.clone(),
             params: minisynize_punctuated(analysis.params.clone()),
             ret_type: analysis.ret_type.clone(),
             param_names,
@@ -110,10 +376,23 @@ pub(super) fn create_subclass_trait_item(
     }
 }
 
+/// The name of the Rust function which C++ calls to invoke the subclass's
+/// implementation of a virtual method. This lives in the flat namespace of
+/// the `cxx::bridge`, so it must be made unique by the caller.
+pub(super) fn subclass_function_call_name(sub: &SubclassName, name: &ApiName) -> String {
+    format!(
+        "{}_{}",
+        sub.0.name.get_final_item(),
+        name.name.get_final_item()
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(super) fn create_subclass_function(
     sub: &SubclassName,
     analysis: &super::FnAnalysis,
     name: &ApiName,
+    rust_call_name: crate::minisyn::Ident,
     receiver_mutability: &ReceiverMutability,
     superclass: &QualifiedName,
     dependencies: Vec<QualifiedName>,
@@ -121,11 +400,6 @@ pub(super) fn create_subclass_function(
 ) -> Api<FnPrePhase1> {
     let cpp = sub.cpp();
     let holder_name = sub.holder();
-    let rust_call_name = make_ident(format!(
-        "{}_{}",
-        sub.0.name.get_final_item(),
-        name.name.get_final_item()
-    ));
     let params = std::iter::once(crate::minisyn::FnArg(parse_quote! {
         me: & #holder_name
     }))
@@ -163,6 +437,8 @@ pub(super) fn create_subclass_function(
                 kind,
                 pass_obs_field: true,
                 qualification: Some(cpp),
+                fallible: false,
+                noexcept: false,
             },
             superclass: superclass.clone(),
             receiver_mutability: *receiver_mutability,
@@ -207,6 +483,8 @@ pub(super) fn create_subclass_constructor(
         pass_obs_field: false,
         qualification: Some(cpp.clone()),
         original_cpp_name: cpp.to_cpp_name(),
+        fallible: false,
+        noexcept: false,
     };
     let subclass_constructor_details = Box::new(SubclassConstructorDetails {
         subclass: sub.clone(),
@@ -257,6 +535,13 @@ pub(super) fn create_subclass_constructor(
         synthetic_cpp: None,
         provenance: Provenance::SynthesizedSubclassConstructor(subclass_constructor_details),
         variadic: fun.variadic,
+        noexcept: false,
+        explicit: false,
+        pinned_rust_name: None,
+        safety_override: None,
+        deprecated: fun.deprecated.clone(),
+        must_use: fun.must_use,
+        is_final: false,
     });
     let subclass_constructor_name = ApiName::new_with_cpp_name(
         &Namespace::new(),
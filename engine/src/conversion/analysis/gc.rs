@@ -6,17 +6,25 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::collections::VecDeque;
+
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
 use autocxx_parser::IncludeCppConfig;
 
 use crate::{
-    conversion::{api::Api, apivec::ApiVec},
-    types::QualifiedName,
+    conversion::{
+        api::{Api, Provenance},
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
 };
 
-use super::{deps::HasDependencies, fun::FnPhase};
+use super::{
+    deps::HasDependencies,
+    fun::{FnKind, FnPhase},
+};
 
 /// This is essentially mark-and-sweep garbage collection of the
 /// [Api]s that we've discovered. Why do we do this, you might wonder?
@@ -35,36 +43,66 @@ use super::{deps::HasDependencies, fun::FnPhase};
 ///    some methods from a given struct/class. In which case, we
 ///    don't care about the other parameter types passed into those
 ///    APIs either.
+///
+/// Anything named in `keep!` (or everything, with `keep_all!`) is kept
+/// as if it were on the allowlist. Returns the APIs we keep, then those
+/// we collect.
 pub(crate) fn filter_apis_by_following_edges_from_allowlist(
-    apis: ApiVec<FnPhase>,
+    mut apis: ApiVec<FnPhase>,
     config: &IncludeCppConfig,
-) -> ApiVec<FnPhase> {
-    let mut todos: Vec<QualifiedName> = apis
+) -> (ApiVec<FnPhase>, ApiVec<FnPhase>) {
+    let mut todos: VecDeque<QualifiedName> = apis
         .iter()
         .filter(|api| {
-            let tnforal = api.name_for_allowlist();
-            config.is_on_allowlist(&tnforal.to_cpp_name())
+            let tnforal = api.name_for_allowlist().to_cpp_name();
+            config.is_on_allowlist(&tnforal) || config.is_kept(&tnforal)
         })
         .map(Api::name)
         .cloned()
         .collect();
-    let mut by_typename: HashMap<QualifiedName, ApiVec<FnPhase>> = HashMap::new();
-    for api in apis.into_iter() {
-        let tn = api.name().clone();
-        by_typename.entry(tn).or_default().push(api);
+    // Accessors for a std::map or similar, the means to iterate over a container,
+    // and enums declared within a class are wanted whenever the type is, even
+    // though the type itself doesn't depend upon them.
+    let mut companions: HashMap<QualifiedName, Vec<QualifiedName>> = HashMap::new();
+    for api in apis.iter() {
+        let owner = match api {
+            Api::Function { fun, analysis, .. } => match (&fun.provenance, &analysis.kind) {
+                (Provenance::SynthesizedContainerAccessor, FnKind::Method { impl_for, .. }) => {
+                    impl_for.clone()
+                }
+                (Provenance::SynthesizedIterator { container, .. }, _) => container.clone(),
+                _ => continue,
+            },
+            Api::Enum { name, .. } => match name.cpp_name().rsplit_once("::") {
+                // bindgen names the enclosing class Outer_Inner if it's
+                // itself nested.
+                Some((outer, _)) => QualifiedName::new(
+                    name.name.get_namespace(),
+                    make_ident(outer.replace("::", "_")),
+                ),
+                None => continue,
+            },
+            _ => continue,
+        };
+        companions
+            .entry(owner)
+            .or_default()
+            .push(api.name().clone());
     }
     let mut done = HashSet::new();
     let mut output = ApiVec::new();
-    while !todos.is_empty() {
-        let todo = todos.remove(0);
+    while let Some(todo) = todos.pop_front() {
         if done.contains(&todo) {
             continue;
         }
-        if let Some(mut these_apis) = by_typename.remove(&todo) {
-            todos.extend(these_apis.iter().flat_map(|api| api.deps().cloned()));
-            output.append(&mut these_apis);
+        if let Some(api) = apis.take(&todo) {
+            todos.extend(api.deps().cloned());
+            output.push(api);
         } // otherwise, probably an intrinsic e.g. uint32_t.
+        if let Some(companions) = companions.get(&todo) {
+            todos.extend(companions.iter().cloned());
+        }
         done.insert(todo);
     }
-    output
+    (output, apis)
 }
@@ -0,0 +1,346 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to let Rust iterate over C++ containers which have `begin()` and
+//! `end()` methods.
+//!
+//! For each such container we make a concrete type from the C++
+//! `autocxx_iterator` template, which holds the current and end iterators,
+//! and two functions: one to start iterating over a container, and one to
+//! step an `autocxx_iterator` along, returning a pointer to the element it
+//! passed (or null once it reaches the end). The Rust code generator wraps
+//! those up as an `Iterator`.
+
+use indexmap::map::IndexMap as HashMap;
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType, Type};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TypedefKind,
+        },
+        apivec::ApiVec,
+    },
+    known_types::known_types,
+    minisyn::minisynize_punctuated,
+    types::{make_ident, Namespace, QualifiedName},
+};
+
+use super::{
+    fun::{
+        function_wrapper::{CppFunctionBody, CppFunctionKind},
+        FnAnalysis, FnPhase,
+    },
+    pod::PodPhase,
+};
+
+/// One of the functions we generate to iterate over a container.
+#[derive(Clone, Debug)]
+pub(crate) enum IteratorShim {
+    /// Make an `autocxx_iterator` positioned at the container's `begin()`.
+    Begin,
+    /// Return a pointer to the current element and advance, or return
+    /// null if we've reached `end()`.
+    Next,
+}
+
+impl IteratorShim {
+    fn name_suffix(&self) -> &'static str {
+        match self {
+            Self::Begin => "begin",
+            Self::Next => "next",
+        }
+    }
+}
+
+/// What we've found out about the `begin()` and `end()` methods of a type.
+#[derive(Default)]
+struct ContainerMethods {
+    /// The type which `begin() const` returns.
+    begin: Option<Type>,
+    has_end: bool,
+    /// Whether the type already has something called `iter`, in which case
+    /// we'd better not add another.
+    has_iter: bool,
+}
+
+pub(crate) fn create_iterator_impls(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    // Our Rust iterator hands out plain references to the elements, which
+    // won't do if we're supposed to be wrapping them instead.
+    if config.unsafe_policy.requires_cpprefs() {
+        return apis;
+    }
+    let mut containers: HashMap<QualifiedName, ContainerMethods> = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Function { name, fun, .. } = api {
+            let self_ty = match &fun.self_ty {
+                Some(self_ty) if matches!(fun.provenance, Provenance::Bindgen) => self_ty,
+                _ => continue,
+            };
+            let usable = matches!(fun.cpp_vis, CppVisibility::Public)
+                && !matches!(fun.is_deleted, DeletedOrDefaulted::Deleted)
+                && fun.inputs.len() == 1
+                && has_const_this(fun);
+            let methods = containers.entry(self_ty.clone()).or_default();
+            match name.cpp_name().as_str() {
+                "begin" if usable => {
+                    if let ReturnType::Type(_, ty) = &*fun.output {
+                        methods.begin = Some(ty.as_ref().clone());
+                    }
+                }
+                "end" if usable => methods.has_end = true,
+                "iter" => methods.has_iter = true,
+                _ => {}
+            }
+        }
+    }
+    let mut new_apis = ApiVec::new();
+    for api in apis.iter() {
+        if let Api::Struct { name, analysis, .. } = api {
+            if analysis.is_generic {
+                continue;
+            }
+            let found = match containers.get(&name.name) {
+                Some(ContainerMethods {
+                    begin: Some(begin),
+                    has_end: true,
+                    has_iter: false,
+                }) => begin,
+                _ => continue,
+            };
+            if apis.contains_key(&get_rust_iterator_name(&name.name)) {
+                continue;
+            }
+            if let Some((element, yields_values)) = find_element_type(&name.name, found, &apis) {
+                create_iterator(name, element, yields_values, &mut new_apis);
+            }
+        }
+    }
+    let mut apis = apis;
+    apis.append(&mut new_apis);
+    apis
+}
+
+fn has_const_this(fun: &FuncToConvert) -> bool {
+    fun.inputs.iter().any(|arg| match &**arg {
+        FnArg::Typed(pt) => {
+            matches!(&*pt.pat, syn::Pat::Ident(pi) if pi.ident == "this")
+                && matches!(&*pt.ty, Type::Ptr(ptr) if ptr.const_token.is_some())
+        }
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// Work out what the container holds, and whether our Rust iterator should
+/// yield copies of the elements rather than references to them. If
+/// `begin()` returns a pointer, the elements are whatever that points to;
+/// otherwise we rely on the container having a `value_type`, as standard
+/// containers do.
+fn find_element_type(
+    container: &QualifiedName,
+    begin: &Type,
+    apis: &ApiVec<PodPhase>,
+) -> Option<(Type, bool)> {
+    let element = match begin {
+        Type::Ptr(ptr) => ptr.elem.as_ref().clone(),
+        _ => {
+            let value_type = QualifiedName::new(
+                container.get_namespace(),
+                make_ident(format!("{}_value_type", container.get_final_item())),
+            );
+            let target = match apis.get(&value_type)? {
+                Api::Typedef { analysis, .. } => match &analysis.kind {
+                    TypedefKind::Type(ity) => ity.ty.as_ref().clone(),
+                    TypedefKind::Use(_, ty) => Type::clone(ty),
+                },
+                _ => return None,
+            };
+            return Some((
+                Type::Path(value_type.to_type_path()),
+                is_copied_by_value(&target),
+            ));
+        }
+    };
+    let yields_values = is_copied_by_value(&element);
+    match &element {
+        // There's nothing to be done with a `void*`.
+        Type::Path(typ)
+            if !QualifiedName::from_type_path(typ)
+                .to_cpp_name()
+                .ends_with("c_void") =>
+        {
+            Some((element, yields_values))
+        }
+        _ => None,
+    }
+}
+
+fn is_copied_by_value(ty: &Type) -> bool {
+    match ty {
+        Type::Path(typ) => known_types().is_c_abi_by_value(&QualifiedName::from_type_path(typ)),
+        _ => false,
+    }
+}
+
+fn create_iterator(
+    container: &ApiName,
+    element: Type,
+    yields_values: bool,
+    apis: &mut ApiVec<PodPhase>,
+) {
+    let iterator_name = get_iterator_state_name(&container.name);
+    let container_typ = container.name.to_type_path();
+    let iterator_typ = iterator_name.to_type_path();
+    apis.push(Api::ConcreteType {
+        name: ApiName::new_from_qualified_name(iterator_name),
+        cpp_definition: format!("autocxx_iterator<{}>", container.qualified_cpp_name()),
+        rs_definition: None,
+    });
+    apis.push(create_shim(
+        &container.name,
+        IteratorShim::Begin,
+        parse_quote! { arg0: *const #container_typ },
+        parse_quote! { -> #iterator_typ },
+        yields_values,
+    ));
+    apis.push(create_shim(
+        &container.name,
+        IteratorShim::Next,
+        parse_quote! { arg0: *mut #iterator_typ },
+        parse_quote! { -> *const #element },
+        yields_values,
+    ));
+}
+
+fn create_shim(
+    container: &QualifiedName,
+    shim: IteratorShim,
+    inputs: Punctuated<FnArg, Comma>,
+    output: ReturnType,
+    yields_values: bool,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        container.get_namespace(),
+        make_ident(format!(
+            "{}_autocxx_iterator_{}",
+            container.get_final_item(),
+            shim.name_suffix()
+        )),
+    );
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References {
+                ref_params: [make_ident("arg0")].into_iter().collect(),
+                ..Default::default()
+            },
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((CppFunctionBody::Iterator(shim), CppFunctionKind::Function)),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedIterator {
+                container: container.clone(),
+                yields_values,
+            },
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
+
+/// The concrete `autocxx_iterator` type for this container. These all live
+/// in the root namespace, as do other concrete types.
+fn get_iterator_state_name(container: &QualifiedName) -> QualifiedName {
+    QualifiedName::new(
+        &Namespace::new(),
+        make_ident(format!(
+            "{}_AutocxxIterator",
+            container.to_cpp_name().replace("::", "_")
+        )),
+    )
+}
+
+/// The Rust type which we generate to implement `Iterator`.
+pub(crate) fn get_rust_iterator_name(container: &QualifiedName) -> QualifiedName {
+    QualifiedName::new(
+        container.get_namespace(),
+        make_ident(format!("{}Iter", container.get_final_item())),
+    )
+}
+
+/// Everything we need to generate Rust code for iterating over one
+/// container.
+pub(crate) struct IteratorFunctions<'a> {
+    pub(crate) container: &'a QualifiedName,
+    pub(crate) yields_values: bool,
+    pub(crate) begin: &'a FnAnalysis,
+    pub(crate) next: &'a FnAnalysis,
+}
+
+/// Pair up the functions which we generated for each container, skipping
+/// any container if either of them couldn't be generated after all.
+pub(crate) fn find_iterator_functions(apis: &ApiVec<FnPhase>) -> Vec<IteratorFunctions<'_>> {
+    let mut begins = HashMap::new();
+    let mut nexts = HashMap::new();
+    for api in apis.iter() {
+        if let Api::Function { fun, analysis, .. } = api {
+            if let (
+                Provenance::SynthesizedIterator {
+                    container,
+                    yields_values,
+                },
+                Some((CppFunctionBody::Iterator(shim), _)),
+            ) = (&fun.provenance, &fun.synthetic_cpp)
+            {
+                if analysis.ignore_reason.is_err() || !analysis.externally_callable {
+                    continue;
+                }
+                let found = match shim {
+                    IteratorShim::Begin => &mut begins,
+                    IteratorShim::Next => &mut nexts,
+                };
+                found.insert(container, (*yields_values, analysis));
+            }
+        }
+    }
+    begins
+        .into_iter()
+        .filter_map(|(container, (yields_values, begin))| {
+            nexts.get(container).map(|(_, next)| IteratorFunctions {
+                container,
+                yields_values,
+                begin,
+                next,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,155 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create `cpp_size` and `cpp_align` static methods for the types
+//! listed in `layout_shims!`, which ask C++ itself for the size and
+//! alignment of the type.
+//!
+//! For non-POD types we also bake the layout reported by bindgen into the
+//! Rust code as `CPP_SIZE` and `CPP_ALIGN` constants; the C++ shims check
+//! at compile time that they agree with those constants.
+
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, punctuated::Punctuated, ReturnType};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TypeKind,
+        },
+        apivec::ApiVec,
+    },
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+const SIZE_METHOD: &str = "cpp_size";
+const ALIGN_METHOD: &str = "cpp_align";
+
+pub(crate) fn create_layout_shims(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let requested = config.get_layout_shims();
+    if requested.is_empty() {
+        return apis;
+    }
+    // Leave alone any type which has its own methods of these names.
+    let clashes: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Function { fun, .. }
+                if [SIZE_METHOD, ALIGN_METHOD].contains(
+                    &fun.original_name
+                        .as_deref()
+                        .unwrap_or(&fun.ident.to_string()),
+                ) =>
+            {
+                fun.self_ty.clone()
+            }
+            _ => None,
+        })
+        .collect();
+    let types: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                details,
+                analysis,
+            } if requested.contains(&name.name.to_cpp_name())
+                && !analysis.is_generic
+                && !analysis.in_anonymous_namespace
+                && !clashes.contains(&name.name) =>
+            {
+                // Only non-POD types get the constants to check against.
+                let layout = match analysis.kind {
+                    TypeKind::NonPod => details.layout.clone(),
+                    _ => None,
+                };
+                Some((name.name.clone(), layout))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut apis = apis;
+    for (ty_name, layout) in types {
+        apis.push(create_layout_shim(
+            &ty_name,
+            SIZE_METHOD,
+            "Returns the size of this type, according to the C++ compiler.",
+            CppFunctionBody::SizeOf(ty_name.clone(), layout.as_ref().map(|l| l.size)),
+        ));
+        // Packing may leave the alignment C++ reports smaller than the
+        // one we use for Rust, so there's nothing to check against.
+        let align = layout.filter(|l| !l.packed).map(|l| l.align);
+        apis.push(create_layout_shim(
+            &ty_name,
+            ALIGN_METHOD,
+            "Returns the alignment of this type, according to the C++ compiler.",
+            CppFunctionBody::AlignOf(ty_name.clone(), align),
+        ));
+    }
+    apis
+}
+
+fn create_layout_shim(
+    ty_name: &QualifiedName,
+    method: &str,
+    doc: &str,
+    payload: CppFunctionBody,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!("{}_autocxx_{method}", ty_name.get_final_item())),
+    );
+    let output: ReturnType = parse_quote! {
+        -> usize
+    };
+    let doc_attr: syn::Attribute = parse_quote! {
+        #[doc = #doc]
+    };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: Punctuated::new(),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: Some(method.to_string()),
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((payload, CppFunctionKind::Function)),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: true,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::fun::FnPhase;
+use crate::conversion::api::Api;
+use crate::conversion::apivec::ApiVec;
+use crate::conversion::convert_error::ErrorContextType;
+use crate::conversion::ConvertErrorFromCpp;
+use crate::source_locations::SourceLocator;
+
+/// Says where in the headers each item we couldn't generate bindings for
+/// was declared, if we can find it, so that the errors are actionable.
+/// Errors for a method (including those we synthesized, such as
+/// constructors) point at its declaration, or failing that at its type.
+pub(crate) fn add_source_locations(
+    apis: ApiVec<FnPhase>,
+    locator: &SourceLocator,
+) -> ApiVec<FnPhase> {
+    apis.into_iter()
+        .map(|api| match api {
+            Api::IgnoredItem { name, err, ctx } => {
+                let location = match ctx.as_ref().map(|ctx| ctx.get_type()) {
+                    _ if matches!(err, ConvertErrorFromCpp::Located { .. }) => None,
+                    Some(ErrorContextType::Method { self_ty, method }) => {
+                        locator.locate_method(&self_ty.to_string(), &method.to_string())
+                    }
+                    _ => {
+                        let cpp_name = name.cpp_name();
+                        let final_item = cpp_name.rsplit("::").next().unwrap_or_default();
+                        locator
+                            .locate_type(final_item)
+                            .or_else(|| locator.locate_function(final_item))
+                    }
+                };
+                let err = match location {
+                    Some(location) => ConvertErrorFromCpp::Located {
+                        location,
+                        err: Box::new(err),
+                    },
+                    None => err,
+                };
+                Api::IgnoredItem { name, err, ctx }
+            }
+            _ => api,
+        })
+        .collect()
+}
@@ -0,0 +1,348 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create accessor methods for instantiations of `std::map` and
+//! `std::unordered_map`.
+//!
+//! cxx can only represent these as opaque types, so without some help
+//! there's nothing Rust code can do with one except pass it back to C++.
+//! We add a few methods to each such concrete type: `len`, `contains`,
+//! `get`, `insert` and (where cxx can return a vector of the keys) `keys`.
+
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType, Type, TypePath};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+        },
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    known_types::known_types,
+    minisyn::minisynize_punctuated,
+    types::{make_ident, Namespace, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+    type_converter::{add_analysis, TypeConversionContext, TypeConverter},
+};
+
+/// An accessor method which we generate for a map.
+#[derive(Clone, Debug)]
+pub(crate) enum MapAccessor {
+    Len,
+    Contains,
+    Get,
+    Insert,
+    /// Copy the keys into a `std::vector` of the given C++ key type.
+    Keys(String),
+}
+
+impl MapAccessor {
+    fn method_name(&self) -> &'static str {
+        match self {
+            Self::Len => "len",
+            Self::Contains => "contains",
+            Self::Get => "get",
+            Self::Insert => "insert",
+            Self::Keys(_) => "keys",
+        }
+    }
+}
+
+pub(crate) fn create_map_accessors(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let mut apis = materialize_in_signatures(apis, config, is_map);
+    let all_names: HashSet<QualifiedName> = apis.iter().map(|api| api.name().clone()).collect();
+    let maps: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::ConcreteType {
+                name,
+                cpp_definition,
+                rs_definition,
+            } => parse_map_definition(cpp_definition)
+                .map(|(key, value)| (name.name.clone(), key, value, rs_definition.is_none())),
+            _ => None,
+        })
+        .collect();
+    for (ty_name, key, value, declared) in maps {
+        match create_accessors(&ty_name, &key, &value, &all_names) {
+            Ok(accessors) => apis.extend(accessors.into_iter()),
+            // Only complain about maps which the user asked for by name
+            // using concrete!; others are just types which we happened
+            // to come across.
+            Err(err) if declared => apis.push(Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_method(
+                    ty_name.get_final_ident(),
+                    make_ident(MapAccessor::Len.method_name()),
+                )),
+                name: ApiName::new_from_qualified_name(get_accessor_name(
+                    &ty_name,
+                    &MapAccessor::Len,
+                )),
+                err,
+            }),
+            Err(_) => {}
+        }
+    }
+    apis
+}
+
+/// Maps and other containers which only appear in function signatures won't
+/// get a concrete type until we analyze those functions, by which time it's
+/// too late to add methods. So make concrete types now for those which
+/// `wanted` picks out. The type converter used during function analysis
+/// will find them and use them.
+pub(super) fn materialize_in_signatures(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+    wanted: fn(&TypePath) -> bool,
+) -> ApiVec<PodPhase> {
+    let mut found = Vec::new();
+    for api in apis.iter() {
+        if let Api::Function { name, fun, .. } = api {
+            let ns = name.name.get_namespace();
+            let output = match &*fun.output {
+                ReturnType::Type(_, ty) => Some(ty.as_ref()),
+                ReturnType::Default => None,
+            };
+            let inputs = fun.inputs.iter().filter_map(|arg| match &**arg {
+                FnArg::Typed(pt) => Some(pt.ty.as_ref()),
+                FnArg::Receiver(_) => None,
+            });
+            for ty in inputs.chain(output) {
+                find_types(ty, ns, wanted, &mut found);
+            }
+        }
+    }
+    if found.is_empty() {
+        return apis;
+    }
+    let mut extra_apis = ApiVec::new();
+    let mut type_converter = TypeConverter::new(config, &apis);
+    for (ns, tp) in found {
+        // Any problem here will be reported when we analyze the function.
+        if let Ok(mut annotated) = type_converter.convert_type(
+            Type::Path(tp),
+            &ns,
+            &TypeConversionContext::WithinReference,
+        ) {
+            extra_apis.append(&mut annotated.extra_apis);
+        }
+    }
+    let mut apis = apis;
+    apis.extend(extra_apis.into_iter().map(add_analysis));
+    apis
+}
+
+fn find_types(
+    ty: &Type,
+    ns: &Namespace,
+    wanted: fn(&TypePath) -> bool,
+    found: &mut Vec<(Namespace, TypePath)>,
+) {
+    match ty {
+        Type::Path(tp) if wanted(tp) => found.push((ns.clone(), tp.clone())),
+        Type::Ptr(ptr) => find_types(&ptr.elem, ns, wanted, found),
+        Type::Reference(reference) => find_types(&reference.elem, ns, wanted, found),
+        _ => {}
+    }
+}
+
+fn is_map(tp: &TypePath) -> bool {
+    let tn = QualifiedName::from_type_path(tp);
+    tn == QualifiedName::new_from_cpp_name("std::map")
+        || tn == QualifiedName::new_from_cpp_name("std::unordered_map")
+}
+
+/// If this C++ type is an instantiation of `std::map` or
+/// `std::unordered_map`, return the C++ key and value types.
+fn parse_map_definition(cpp_definition: &str) -> Option<(String, String)> {
+    let cpp_definition = cpp_definition.trim().trim_start_matches("::");
+    let args = ["std::map<", "std::unordered_map<"]
+        .iter()
+        .find_map(|prefix| cpp_definition.strip_prefix(prefix))?
+        .strip_suffix('>')?;
+    // Split on top-level commas only, since the key or value type may
+    // themselves be templates.
+    let mut depth = 0;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(args[start..].trim());
+    // Ignore any comparator, hasher or allocator.
+    match split.as_slice() {
+        [key, value, ..] => Some((key.to_string(), value.to_string())),
+        _ => None,
+    }
+}
+
+fn create_accessors(
+    ty_name: &QualifiedName,
+    key: &str,
+    value: &str,
+    all_names: &HashSet<QualifiedName>,
+) -> Result<Vec<Api<PodPhase>>, ConvertErrorFromCpp> {
+    let key_name = QualifiedName::new_from_cpp_name(key);
+    if !known_types().convertible_from_strs(&key_name) && !known_types().is_integer(&key_name) {
+        return Err(ConvertErrorFromCpp::UnsupportedMap(format!(
+            "its key type is {key}"
+        )));
+    }
+    let value_name = QualifiedName::new_from_cpp_name(value);
+    if value.contains('<')
+        || !(all_names.contains(&value_name) || known_types().is_known_type(&value_name))
+    {
+        return Err(ConvertErrorFromCpp::UnsupportedMap(format!(
+            "its value type is {value}"
+        )));
+    }
+    let typ = ty_name.to_type_path();
+    let key_typ = key_name.to_type_path();
+    let value_typ = value_name.to_type_path();
+    let mut accessors = vec![
+        create_accessor(
+            ty_name,
+            MapAccessor::Len,
+            parse_quote! { this: *const #typ },
+            parse_quote! { -> usize },
+            "Returns the number of entries in the map.",
+        ),
+        create_accessor(
+            ty_name,
+            MapAccessor::Contains,
+            parse_quote! { this: *const #typ, key: #key_typ },
+            parse_quote! { -> bool },
+            "Whether the map has an entry for this key.",
+        ),
+        create_accessor(
+            ty_name,
+            MapAccessor::Get,
+            parse_quote! { this: *const #typ, key: #key_typ },
+            parse_quote! { -> *const #value_typ },
+            "Returns a pointer to the value for this key, or a null pointer if there's no such entry. The pointer remains valid until the entry is removed or the map is destroyed.",
+        ),
+        create_accessor(
+            ty_name,
+            MapAccessor::Insert,
+            parse_quote! { this: *mut #typ, key: #key_typ, value: #value_typ },
+            ReturnType::Default,
+            "Inserts this entry, replacing any existing value for the key.",
+        ),
+    ];
+    // cxx can't make a CxxVector of variable-length integers such as
+    // c_int, so there's no way to return those keys.
+    if known_types().permissible_within_vector(&key_name) {
+        accessors.push(create_accessor(
+            ty_name,
+            MapAccessor::Keys(key.to_string()),
+            parse_quote! { this: *const #typ },
+            parse_quote! { -> cxx::CxxVector<#key_typ> },
+            "Returns a copy of the keys, in the map's iteration order.",
+        ));
+    }
+    Ok(accessors)
+}
+
+fn create_accessor(
+    ty_name: &QualifiedName,
+    accessor: MapAccessor,
+    inputs: Punctuated<FnArg, Comma>,
+    output: ReturnType,
+    doc: &str,
+) -> Api<PodPhase> {
+    let name = get_accessor_name(ty_name, &accessor);
+    let doc_attr: syn::Attribute = parse_quote! {
+        #[doc = #doc]
+    };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: Some(accessor.method_name().to_string()),
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::MapAccessor(accessor),
+                CppFunctionKind::Method,
+            )),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedContainerAccessor,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
+
+fn get_accessor_name(ty_name: &QualifiedName, accessor: &MapAccessor) -> QualifiedName {
+    QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!(
+            "{}_autocxx_map_{}",
+            ty_name.get_final_item(),
+            accessor.method_name()
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_map_definition;
+
+    #[test]
+    fn test_parse_map_definition() {
+        assert_eq!(
+            parse_map_definition("std::map<std::string, Config>"),
+            Some(("std::string".to_string(), "Config".to_string()))
+        );
+        assert_eq!(
+            parse_map_definition(
+                "std::unordered_map<int32_t, ns::Thing, std::hash<int32_t>, std::equal_to<int32_t>>"
+            ),
+            Some(("int32_t".to_string(), "ns::Thing".to_string()))
+        );
+        assert_eq!(parse_map_definition("std::vector<int>"), None);
+        assert_eq!(parse_map_definition("Container<B>"), None);
+    }
+}
@@ -7,22 +7,38 @@
 // except according to those terms.
 
 pub(crate) mod abstract_types;
+pub(crate) mod addresses;
 pub(crate) mod allocators;
 pub(crate) mod casts;
+pub(crate) mod cfg_features;
+pub(crate) mod concrete_members;
 pub(crate) mod constructor_deps;
 pub(crate) mod ctypes;
+pub(crate) mod debug_impls;
+pub(crate) mod declared_functions;
 pub(crate) mod deps;
 mod depth_first;
 mod doc_label;
+pub(crate) mod explicit_destructors;
 pub(crate) mod fun;
 pub(crate) mod gc;
+pub(crate) mod iterators;
+pub(crate) mod layouts;
+pub(crate) mod locations;
+pub(crate) mod maps;
 mod name_check;
+pub(crate) mod operators;
 pub(crate) mod pod; // hey, that rhymes
+pub(crate) mod protected_fields;
 pub(crate) mod remove_ignored;
 mod replace_hopeless_typedef_targets;
+pub(crate) mod shared_ptrs;
 pub(crate) mod tdef;
 mod type_converter;
+pub(crate) mod vectors;
 
-pub(crate) use name_check::check_names;
+pub(crate) use name_check::{check_names, check_renames};
 pub(crate) use replace_hopeless_typedef_targets::replace_hopeless_typedef_targets;
+pub(crate) use type_converter::ChronoType;
 pub(crate) use type_converter::PointerTreatment;
+pub(crate) use type_converter::WideCharUnit;
@@ -7,6 +7,9 @@
 // except according to those terms.
 
 use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
 
 use crate::minisyn::Ident;
 
@@ -20,7 +23,7 @@ use crate::{
     types::{validate_ident_ok_for_cxx, QualifiedName},
 };
 
-use super::fun::FnPhase;
+use super::fun::{FnKind, FnPhase};
 
 /// Do some final checks that the names we've come up with can be represented
 /// within cxx.
@@ -57,7 +60,7 @@ pub(crate) fn check_names(apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
             validate_all_segments_ok_for_cxx(superclass.segment_iter())?;
             Ok(Box::new(std::iter::once(api)))
         }
-        Api::Function { ref name, .. } => {
+        Api::Function { ref name, .. } | Api::RawFunction { ref name, .. } => {
             // we don't handle function names here because
             // the function analysis does an equivalent check. Instead of just rejecting
             // the function, it creates a wrapper function instead with a more
@@ -68,9 +71,11 @@ pub(crate) fn check_names(apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
         Api::ConcreteType { .. }
         | Api::CType { .. }
         | Api::StringConstructor { .. }
+        | Api::BitfieldUnit { .. }
         | Api::RustType { .. }
         | Api::RustSubclassFn { .. }
         | Api::RustFn { .. }
+        | Api::ClosureHolder { .. }
         | Api::SubclassTraitItem { .. }
         | Api::ExternCppType { .. }
         | Api::IgnoredItem { .. } => Ok(Box::new(std::iter::once(api))),
@@ -105,6 +110,170 @@ pub(crate) fn check_names(apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
     results
 }
 
+/// Where a name lives in the generated Rust, for the purposes of
+/// [check_renames].
+#[derive(PartialEq, Eq, Hash)]
+enum RustScope {
+    /// Types and modules within a mod.
+    Types(String),
+    /// Functions and constants within a mod.
+    Values(String),
+    /// Methods of a type.
+    Methods(QualifiedName),
+}
+
+/// An item in the generated Rust, for the purposes of [check_renames].
+struct RustItem {
+    scope: RustScope,
+    rust_name: String,
+    cpp_name: String,
+    /// The C++ namespace or type within which this item lives.
+    cpp_scope: String,
+    is_mod: bool,
+}
+
+/// Check that no `rename!` directive has given an item the same Rust name
+/// as something else alongside it, and that no `map_namespace!` directive
+/// has put two items with the same name in the same mod. This has to wait
+/// until we know the final Rust names of all the functions.
+pub(crate) fn check_renames(
+    apis: &ApiVec<FnPhase>,
+    config: &IncludeCppConfig,
+) -> Result<(), ConvertErrorFromCpp> {
+    if config.get_name_renames().is_empty() && config.get_namespace_mappings().is_empty() {
+        return Ok(());
+    }
+    let rust_mod = |ns: &[String]| config.get_rust_mod_path(ns).join("::");
+    let mut items = Vec::new();
+    let mut namespaces = HashSet::new();
+    for api in apis.iter() {
+        let name = api.name_info();
+        let ns = name.name.get_namespace();
+        namespaces.insert(ns.clone());
+        let ns_segments: Vec<_> = ns.iter().cloned().collect();
+        match api {
+            Api::Struct { .. }
+            | Api::Enum { .. }
+            | Api::Typedef { .. }
+            | Api::OpaqueTypedef { .. }
+            | Api::ForwardDeclaration { .. } => {
+                let cpp_name = name.qualified_cpp_name();
+                let rust_name = config
+                    .get_rust_rename(&cpp_name)
+                    .unwrap_or_else(|| name.name.get_final_item())
+                    .to_string();
+                items.push(RustItem {
+                    scope: RustScope::Types(rust_mod(&ns_segments)),
+                    rust_name,
+                    cpp_name,
+                    cpp_scope: ns.to_cpp_path(),
+                    is_mod: false,
+                });
+            }
+            Api::Function { analysis, .. } => match &analysis.kind {
+                FnKind::Function => items.push(RustItem {
+                    scope: RustScope::Values(rust_mod(&ns_segments)),
+                    rust_name: analysis.rust_name.clone(),
+                    cpp_name: name.qualified_cpp_name(),
+                    cpp_scope: ns.to_cpp_path(),
+                    is_mod: false,
+                }),
+                FnKind::Method { impl_for, .. } => items.push(RustItem {
+                    scope: RustScope::Methods(impl_for.clone()),
+                    rust_name: analysis.rust_name.clone(),
+                    cpp_name: format!("{}::{}", impl_for.to_cpp_name(), name.cpp_name()),
+                    cpp_scope: impl_for.to_cpp_name(),
+                    is_mod: false,
+                }),
+                FnKind::TraitMethod { .. } => {}
+            },
+            _ => {}
+        }
+    }
+    // Each namespace, and each namespace enclosing one, is a mod, unless
+    // map_namespace! has put its contents somewhere else.
+    let rust_mods: HashSet<_> = namespaces
+        .iter()
+        .map(|ns| config.get_rust_mod_path(&ns.iter().cloned().collect::<Vec<_>>()))
+        .collect();
+    let mut all_namespaces = HashSet::new();
+    for ns in namespaces {
+        let segments: Vec<_> = ns.iter().cloned().collect();
+        for depth in 1..=segments.len() {
+            all_namespaces.insert(segments[..depth].to_vec());
+        }
+    }
+    for segments in all_namespaces {
+        let rust_path = config.get_rust_mod_path(&segments);
+        let (rust_name, parent) = match rust_path.split_last() {
+            Some(split) => split,
+            None => continue,
+        };
+        if !rust_mods
+            .iter()
+            .any(|rust_mod| rust_mod.starts_with(&rust_path))
+        {
+            continue;
+        }
+        items.push(RustItem {
+            scope: RustScope::Types(parent.join("::")),
+            rust_name: rust_name.clone(),
+            cpp_name: segments.join("::"),
+            cpp_scope: segments[..segments.len() - 1].join("::"),
+            is_mod: true,
+        });
+    }
+    for rename in config.get_name_renames() {
+        for (idx, item) in items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.cpp_name == rename.cpp_name)
+        {
+            let clash = items.iter().enumerate().find(|(other_idx, other)| {
+                *other_idx != idx
+                    && other.scope == item.scope
+                    && other.rust_name == item.rust_name
+                    && !(other.is_mod && item.is_mod)
+            });
+            if let Some((_, other)) = clash {
+                return Err(ConvertErrorFromCpp::RenameConflict(
+                    rename.cpp_name.clone(),
+                    rename.rust_name.clone(),
+                    other.cpp_name.clone(),
+                ));
+            }
+        }
+    }
+    // Items from different C++ namespaces (other than mods, which are
+    // merged) may now have been put alongside each other.
+    let mut by_rust_name: HashMap<(&RustScope, &str), &RustItem> = HashMap::new();
+    for item in &items {
+        match by_rust_name.get(&(&item.scope, item.rust_name.as_str())) {
+            Some(other) if other.cpp_scope != item.cpp_scope && !(other.is_mod && item.is_mod) => {
+                let rust_mod = match &item.scope {
+                    RustScope::Types(rust_mod) | RustScope::Values(rust_mod)
+                        if !rust_mod.is_empty() =>
+                    {
+                        rust_mod.clone()
+                    }
+                    RustScope::Methods(impl_for) => impl_for.to_cpp_name(),
+                    _ => config.get_mod_name().to_string(),
+                };
+                return Err(ConvertErrorFromCpp::NamespaceMappingConflict(
+                    other.cpp_name.clone(),
+                    item.cpp_name.clone(),
+                    rust_mod,
+                ));
+            }
+            Some(_) => {}
+            None => {
+                by_rust_name.insert((&item.scope, &item.rust_name), item);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn validate_all_segments_ok_for_cxx(
     items: impl Iterator<Item = String>,
 ) -> Result<(), ConvertErrorFromCpp> {
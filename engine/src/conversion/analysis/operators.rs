@@ -0,0 +1,344 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create functions which apply C++ operators, such that we can
+//! implement `PartialEq`, `PartialOrd`, `Add`, `Index` and friends.
+//!
+//! bindgen doesn't tell us about operator overloads, so we rely on the
+//! user listing them in an `operators!` directive.
+
+use indexmap::map::IndexMap as HashMap;
+
+use autocxx_parser::{CppOperator, IncludeCppConfig};
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType, Type};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TraitSynthesis, TypeKind,
+        },
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    known_types::known_types,
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::{PodAnalysis, PodPhase},
+};
+
+pub(crate) fn create_operator_impls(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let struct_kinds: HashMap<QualifiedName, TypeKind> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct {
+                name,
+                analysis: PodAnalysis { kind, .. },
+                ..
+            } => Some((name.name.clone(), *kind)),
+            _ => None,
+        })
+        .collect();
+    let mut apis = apis;
+    for type_operators in config.get_operators() {
+        let ty_name = QualifiedName::new_from_cpp_name(&type_operators.ty);
+        // If we didn't generate the type, for whatever reason, the user
+        // will already have been told why.
+        let kind = match struct_kinds.get(&ty_name) {
+            Some(kind) => *kind,
+            None => continue,
+        };
+        for op in &type_operators.operators {
+            if let (CppOperator::Index, Some(output)) = (op, &type_operators.index_output) {
+                let output_name = output;
+                let output = QualifiedName::new_from_cpp_name(output);
+                if !apis.contains_key(&output) && !known_types().is_known_type(&output) {
+                    let name = get_operator_function_name(&ty_name, *op);
+                    apis.push(Api::IgnoredItem {
+                        ctx: Some(ErrorContext::new_for_method(
+                            ty_name.get_final_ident(),
+                            name.get_final_ident(),
+                        )),
+                        name: ApiName::new_from_qualified_name(name),
+                        err: ConvertErrorFromCpp::UnknownIndexOutput(output_name.clone()),
+                    });
+                    continue;
+                }
+                // IndexMut needs a plain `&mut self`, and to return a plain
+                // `&mut Output`, so that's only possible for POD types;
+                // otherwise provide a method instead.
+                let mutable_fn = if matches!(kind, TypeKind::Pod)
+                    && !matches!(struct_kinds.get(&output), Some(TypeKind::NonPod))
+                {
+                    create_index_function(&ty_name, &output, true)
+                } else {
+                    create_index_mut_method(&ty_name, &output)
+                };
+                apis.push(create_index_function(&ty_name, &output, false));
+                apis.push(mutable_fn);
+                continue;
+            }
+            let name = get_operator_function_name(&ty_name, *op);
+            // Anything other than a comparison, a hash or streaming out must
+            // pass or return the type by value.
+            if !op.is_comparison()
+                && !matches!(op, CppOperator::Hash | CppOperator::StreamOut)
+                && !matches!(kind, TypeKind::Pod)
+            {
+                apis.push(Api::IgnoredItem {
+                    ctx: Some(ErrorContext::new_for_method(
+                        ty_name.get_final_ident(),
+                        name.get_final_ident(),
+                    )),
+                    name: ApiName::new_from_qualified_name(name),
+                    err: ConvertErrorFromCpp::OperatorOnNonPod(op.symbol().to_string()),
+                });
+                continue;
+            }
+            apis.push(create_operator_function(ty_name.clone(), name, *op));
+        }
+    }
+    apis
+}
+
+/// The Rust trait which corresponds to this C++ operator, and the
+/// trait method which we implement.
+pub(crate) fn get_operator_trait(op: CppOperator) -> (Type, &'static str) {
+    match op {
+        CppOperator::Eq => (parse_quote! { ::core::cmp::PartialEq }, "eq"),
+        CppOperator::Lt => (parse_quote! { ::core::cmp::PartialOrd }, "partial_cmp"),
+        CppOperator::Add => (parse_quote! { ::core::ops::Add }, "add"),
+        CppOperator::Sub => (parse_quote! { ::core::ops::Sub }, "sub"),
+        CppOperator::Mul => (parse_quote! { ::core::ops::Mul }, "mul"),
+        CppOperator::Div => (parse_quote! { ::core::ops::Div }, "div"),
+        CppOperator::Rem => (parse_quote! { ::core::ops::Rem }, "rem"),
+        CppOperator::AddAssign => (parse_quote! { ::core::ops::AddAssign }, "add_assign"),
+        CppOperator::SubAssign => (parse_quote! { ::core::ops::SubAssign }, "sub_assign"),
+        CppOperator::MulAssign => (parse_quote! { ::core::ops::MulAssign }, "mul_assign"),
+        CppOperator::DivAssign => (parse_quote! { ::core::ops::DivAssign }, "div_assign"),
+        CppOperator::RemAssign => (parse_quote! { ::core::ops::RemAssign }, "rem_assign"),
+        CppOperator::Index => (parse_quote! { ::core::ops::Index<usize> }, "index"),
+        CppOperator::Hash => (parse_quote! { ::core::hash::Hash }, "hash"),
+        CppOperator::StreamOut => (parse_quote! { ::core::fmt::Display }, "fmt"),
+    }
+}
+
+fn create_operator_function(
+    ty_name: QualifiedName,
+    name: QualifiedName,
+    op: CppOperator,
+) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let ident = name.get_final_ident();
+    let payload = match op {
+        CppOperator::Hash => CppFunctionBody::Hash(ty_name.clone()),
+        _ => CppFunctionBody::Operator(op),
+    };
+    let inputs: Punctuated<FnArg, Comma> = if op.is_assignment() {
+        parse_quote! {
+            arg0: *mut #typ, arg1: *const #typ
+        }
+    } else if matches!(op, CppOperator::Hash | CppOperator::StreamOut) {
+        parse_quote! {
+            arg0: *const #typ
+        }
+    } else {
+        parse_quote! {
+            arg0: *const #typ, arg1: *const #typ
+        }
+    };
+    let output: ReturnType = if op.is_comparison() {
+        parse_quote! { -> bool }
+    } else if op == CppOperator::Hash {
+        parse_quote! { -> usize }
+    } else if op == CppOperator::StreamOut {
+        parse_quote! { -> root::std::string }
+    } else if op.is_assignment() {
+        ReturnType::Default
+    } else {
+        parse_quote! { -> #typ }
+    };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            ident,
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((payload, CppFunctionKind::Function)),
+            add_to_trait: Some(TraitSynthesis::Operator(ty_name, op)),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        analysis: (),
+    }
+}
+
+/// Creates a function applying `operator[]`, for use by `Index` or
+/// `IndexMut`.
+fn create_index_function(
+    ty_name: &QualifiedName,
+    output: &QualifiedName,
+    mutable: bool,
+) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let output_typ = output.to_type_path();
+    let (name, inputs, output, add_to_trait): (_, Punctuated<FnArg, Comma>, ReturnType, _) =
+        if mutable {
+            (
+                QualifiedName::new(
+                    ty_name.get_namespace(),
+                    make_ident(format!(
+                        "{}_autocxx_operator_index_mut",
+                        ty_name.get_final_item()
+                    )),
+                ),
+                parse_quote! { arg0: *mut #typ, arg1: usize },
+                parse_quote! { -> *mut #output_typ },
+                TraitSynthesis::IndexMut(ty_name.clone()),
+            )
+        } else {
+            (
+                get_operator_function_name(ty_name, CppOperator::Index),
+                parse_quote! { arg0: *const #typ, arg1: usize },
+                parse_quote! { -> *const #output_typ },
+                TraitSynthesis::Operator(ty_name.clone(), CppOperator::Index),
+            )
+        };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References {
+                ref_params: [make_ident("arg0")].into_iter().collect(),
+                ref_return: true,
+                ..Default::default()
+            },
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::Operator(CppOperator::Index),
+                CppFunctionKind::Function,
+            )),
+            add_to_trait: Some(add_to_trait),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
+
+/// Creates an `at_mut` method applying `operator[]`, for types where
+/// `IndexMut` can't be implemented because we need a `Pin<&mut Self>`.
+fn create_index_mut_method(ty_name: &QualifiedName, output: &QualifiedName) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let output_typ = output.to_type_path();
+    let inputs: Punctuated<FnArg, Comma> = parse_quote! {
+        this: *mut #typ, arg1: usize
+    };
+    let output: ReturnType = parse_quote! {
+        -> *mut #output_typ
+    };
+    let name = QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!("{}_at_mut", ty_name.get_final_item())),
+    );
+    let doc_attr: syn::Attribute = parse_quote! {
+        #[doc = "Calls the C++ `operator[]`. As in C++, the index isn't checked."]
+    };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References {
+                ref_return: true,
+                ..Default::default()
+            },
+            original_name: Some("at_mut".to_string()),
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::Operator(CppOperator::Index),
+                CppFunctionKind::Method,
+            )),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
+
+fn get_operator_function_name(ty_name: &QualifiedName, op: CppOperator) -> QualifiedName {
+    let (_, method) = get_operator_trait(op);
+    let suffix = match op {
+        CppOperator::Eq => "eq",
+        CppOperator::Lt => "lt",
+        CppOperator::StreamOut => "stream_out",
+        _ => method,
+    };
+    let name = format!("{}_autocxx_operator_{suffix}", ty_name.get_final_item());
+    QualifiedName::new(ty_name.get_namespace(), make_ident(name))
+}
@@ -6,19 +6,25 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use super::union_member_type_name;
 use crate::conversion::apivec::ApiVec;
+use crate::conversion::type_helpers::{array_element_type, extract_function_pointer_signature};
 use crate::{conversion::ConvertErrorFromCpp, known_types::known_types};
 use crate::{
     conversion::{
         analysis::tdef::TypedefPhase,
         api::{Api, TypedefKind},
     },
-    types::{Namespace, QualifiedName},
+    types::{Namespace, QualifiedName, BITFIELD_UNIT},
 };
 use autocxx_parser::IncludeCppConfig;
-use std::collections::HashMap;
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
 use syn::{ItemStruct, Type};
 
+/// The type bindgen uses for flexible and zero-length arrays.
+const INCOMPLETE_ARRAY_FIELD: &str = "__IncompleteArrayField";
+
 #[derive(Clone)]
 enum PodState {
     UnsafeToBePod(String),
@@ -54,6 +60,9 @@ impl StructDetails {
 pub struct ByValueChecker {
     // Mapping from type name to whether it is safe to be POD
     results: HashMap<QualifiedName, StructDetails>,
+    // Structs which have an alignment attribute, or contain a field
+    // which does. Rust won't let a packed struct contain one.
+    aligned: HashSet<QualifiedName>,
 }
 
 impl ByValueChecker {
@@ -67,7 +76,15 @@ impl ByValueChecker {
             };
             results.insert(tn.clone(), StructDetails::new(safety));
         }
-        ByValueChecker { results }
+        // bindgen's bit-field storage is just an array of bytes.
+        results.insert(
+            QualifiedName::new_from_cpp_name(BITFIELD_UNIT),
+            StructDetails::new(PodState::IsPod),
+        );
+        ByValueChecker {
+            results,
+            aligned: HashSet::new(),
+        }
     }
 
     /// Scan APIs to work out which are by-value safe. Constructs a [ByValueChecker]
@@ -78,7 +95,7 @@ impl ByValueChecker {
     ) -> Result<ByValueChecker, ConvertErrorFromCpp> {
         let mut byvalue_checker = ByValueChecker::new();
         for blocklisted in config.get_blocklist() {
-            let tn = QualifiedName::new_from_cpp_name(blocklisted);
+            let tn = QualifiedName::new_from_cpp_name(&blocklisted);
             let safety = PodState::UnsafeToBePod(format!("type {} is on the blocklist", &tn));
             byvalue_checker
                 .results
@@ -89,27 +106,37 @@ impl ByValueChecker {
         // but that's awkward given that our ApiPhase does not yet have a fixed
         // list of field/base types. Instead, we'll iterate first over non-struct
         // types and then over structs.
+        let enums: HashSet<&QualifiedName> = apis
+            .iter()
+            .filter(|api| matches!(api, Api::Enum { .. }))
+            .map(|api| api.name())
+            .collect();
         for api in apis.iter() {
             match api {
                 Api::Typedef { analysis, .. } => {
                     let name = api.name();
-                    let typedef_type = match analysis.kind {
+                    let target_tn = match analysis.kind {
                         TypedefKind::Type(ref type_item) => match type_item.ty.as_ref() {
-                            Type::Path(typ) => {
-                                let target_tn = QualifiedName::from_type_path(typ);
-                                known_types().consider_substitution(&target_tn)
-                            }
+                            Type::Path(typ) => Some(QualifiedName::from_type_path(typ)),
                             _ => None,
                         },
                         TypedefKind::Use(_, ref ty) => match **ty {
                             crate::minisyn::Type(Type::Path(ref typ)) => {
-                                let target_tn = QualifiedName::from_type_path(typ);
-                                known_types().consider_substitution(&target_tn)
+                                Some(QualifiedName::from_type_path(typ))
                             }
                             _ => None,
                         },
                     };
+                    let typedef_type = target_tn
+                        .as_ref()
+                        .and_then(|target_tn| known_types().consider_substitution(target_tn));
                     match &typedef_type {
+                        // A C function pointer is just an address.
+                        _ if Self::is_function_pointer_typedef(&analysis.kind) => {
+                            byvalue_checker
+                                .results
+                                .insert(name.clone(), StructDetails::new(PodState::IsPod));
+                        }
                         Some(typ) => {
                             byvalue_checker.results.insert(
                                 name.clone(),
@@ -118,6 +145,17 @@ impl ByValueChecker {
                                 )),
                             );
                         }
+                        // A typedef to an enum is as POD as the enum.
+                        None if target_tn
+                            .as_ref()
+                            .map(|target_tn| enums.contains(target_tn))
+                            .unwrap_or_default() =>
+                        {
+                            byvalue_checker.results.insert(
+                                name.clone(),
+                                StructDetails::new(PodState::IsAlias(target_tn.unwrap())),
+                            );
+                        }
                         None => byvalue_checker.ingest_nonpod_type(name.clone()),
                     }
                 }
@@ -131,17 +169,86 @@ impl ByValueChecker {
         }
         for api in apis.iter() {
             if let Api::Struct { details, .. } = api {
-                byvalue_checker.ingest_struct(&details.item, api.name().get_namespace())
+                match config.excluded_namespace_of(&api.name().to_cpp_name()) {
+                    Some(excluded) => {
+                        let reason = format!(
+                            "type {} is in namespace {excluded}, which was excluded from generate_ns!",
+                            api.name()
+                        );
+                        byvalue_checker.results.insert(
+                            api.name().clone(),
+                            StructDetails::new(PodState::UnsafeToBePod(reason)),
+                        );
+                    }
+                    None if config.is_opaque(&api.name().to_cpp_name()) => {
+                        let reason =
+                            format!("type {} was named in opaque!, so can't be POD", api.name());
+                        byvalue_checker.results.insert(
+                            api.name().clone(),
+                            StructDetails::new(PodState::UnsafeToBePod(reason)),
+                        );
+                    }
+                    None => {
+                        byvalue_checker.ingest_struct(&details.item, api.name().get_namespace())
+                    }
+                }
             }
         }
         let pod_requests = config
-            .get_pod_requests()
-            .iter()
-            .map(|ty| QualifiedName::new_from_cpp_name(ty))
+            .get_exact_pod_requests()
+            .map(|ty| QualifiedName::new_from_cpp_name(&ty))
+            .chain(
+                apis.iter()
+                    .filter(|api| {
+                        matches!(api, Api::Struct { .. })
+                            && config.is_pod_request(&api.name().to_cpp_name())
+                    })
+                    .map(|api| api.name().clone()),
+            )
+            .collect::<HashSet<_>>()
+            .into_iter()
             .collect();
         byvalue_checker
             .satisfy_requests(pod_requests)
             .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+        // Types in a generate_ns_pod! namespace are POD only if they can
+        // be; the others remain non-POD rather than causing an error.
+        for api in apis.iter() {
+            if let Api::Struct { details, .. } = api {
+                let name = api.name();
+                if config.is_pod_by_default(&name.to_cpp_name())
+                    && !details.has_rvalue_reference_fields
+                    && byvalue_checker.could_be_pod(name)
+                {
+                    byvalue_checker
+                        .satisfy_requests(vec![name.clone()])
+                        .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+                }
+            }
+        }
+        // Members of the unions within POD types only get accessors if
+        // they're POD too, so make them POD if we can, as we would the
+        // types of ordinary fields. They may contain unions in turn.
+        loop {
+            let members: Vec<_> = apis
+                .iter()
+                .filter_map(|api| match api {
+                    Api::Struct { details, .. } if byvalue_checker.is_pod(api.name()) => {
+                        Some(details.union_members.iter())
+                    }
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|member| union_member_type_name(&member.ty))
+                .filter(|tn| !byvalue_checker.is_pod(tn) && byvalue_checker.could_be_pod(tn))
+                .collect();
+            if members.is_empty() {
+                break;
+            }
+            byvalue_checker
+                .satisfy_requests(members)
+                .map_err(ConvertErrorFromCpp::UnsafePodType)?;
+        }
         Ok(byvalue_checker)
     }
 
@@ -158,12 +265,6 @@ impl ByValueChecker {
                     ));
                     break;
                 }
-                None if ty_id.get_final_item() == "__BindgenBitfieldUnit" => {
-                    field_safety_problem = PodState::UnsafeToBePod(format!(
-                        "Type {tyname} could not be POD because it is a bitfield"
-                    ));
-                    break;
-                }
                 None => {
                     field_safety_problem = PodState::UnsafeToBePod(format!(
                         "Type {tyname} could not be POD because its dependent type {ty_id} isn't known"
@@ -179,11 +280,31 @@ impl ByValueChecker {
                 }
             }
         }
+        if let Some(field) = Self::find_flexible_array_member(def) {
+            field_safety_problem = PodState::UnsafeToBePod(format!(
+                "Type {tyname} could not be POD because its field {field} is a flexible or zero-length array, so its size isn't known"
+            ));
+        }
         if Self::has_vtable(def) {
             let reason =
                 format!("Type {tyname} could not be POD because it has virtual functions.");
             field_safety_problem = PodState::UnsafeToBePod(reason);
         }
+        let (packed, aligned) = Self::get_repr(def);
+        if packed && aligned {
+            field_safety_problem = PodState::UnsafeToBePod(format!(
+                "Type {tyname} could not be POD because it's both packed and given an alignment, which Rust can't represent"
+            ));
+        } else if packed {
+            if let Some((field, ty_id)) = self.find_aligned_field(def) {
+                field_safety_problem = PodState::UnsafeToBePod(format!(
+                    "Type {tyname} could not be POD because it's packed, but its field {field} is of type {ty_id}, which is given an alignment; Rust can't represent that"
+                ));
+            }
+        }
+        if aligned || self.find_aligned_field(def).is_some() {
+            self.aligned.insert(tyname.clone());
+        }
         let mut my_details = StructDetails::new(field_safety_problem);
         my_details.dependent_structs = fieldlist;
         self.results.insert(tyname, my_details);
@@ -234,6 +355,27 @@ impl ByValueChecker {
         Ok(())
     }
 
+    /// Whether [Self::satisfy_requests] would succeed in making this
+    /// type POD. Unlike that function, this changes nothing.
+    fn could_be_pod(&self, ty_id: &QualifiedName) -> bool {
+        let mut todo = vec![ty_id.clone()];
+        let mut done = HashSet::new();
+        while let Some(ty_id) = todo.pop() {
+            if !done.insert(ty_id.clone()) {
+                continue;
+            }
+            match self.results.get(&ty_id).map(|deets| &deets.state) {
+                None | Some(PodState::UnsafeToBePod(_)) => return false,
+                Some(PodState::IsPod) => {}
+                Some(PodState::SafeToBePod) => {
+                    todo.extend_from_slice(&self.results[&ty_id].dependent_structs)
+                }
+                Some(PodState::IsAlias(target_type)) => todo.push(target_type.clone()),
+            }
+        }
+        true
+    }
+
     /// Return whether a given type is POD (i.e. can be represented by value in Rust) or not.
     /// Unless we've got a definite record that it _is_, we return false.
     /// Some types won't be in our `results` map. For example: (a) AutocxxConcrete types
@@ -256,15 +398,79 @@ impl ByValueChecker {
     fn get_field_types(def: &ItemStruct) -> Vec<QualifiedName> {
         let mut results = Vec::new();
         for f in &def.fields {
-            let fty = &f.ty;
-            if let Type::Path(p) = fty {
-                results.push(QualifiedName::from_type_path(p));
+            // Arrays (including multi-dimensional arrays) are POD if
+            // their elements are. C function pointers always are.
+            match array_element_type(&f.ty) {
+                Type::Path(p) if extract_function_pointer_signature(p).is_some() => {}
+                Type::Path(p) => results.push(QualifiedName::from_type_path(p)),
+                _ => {}
             }
-            // TODO handle anything else which bindgen might spit out, e.g. arrays?
         }
         results
     }
 
+    fn is_function_pointer_typedef(kind: &TypedefKind) -> bool {
+        let ty = match kind {
+            TypedefKind::Type(type_item) => type_item.ty.as_ref(),
+            TypedefKind::Use(_, ty) => &ty.0,
+        };
+        matches!(ty, Type::Path(typ) if extract_function_pointer_signature(typ).is_some())
+    }
+
+    /// bindgen represents a flexible array member, such as `int data[]`,
+    /// or a zero-length array, as an `__IncompleteArrayField`. Rust can't hold such a struct by
+    /// value since its real size isn't known.
+    fn find_flexible_array_member(def: &ItemStruct) -> Option<String> {
+        def.fields.iter().find_map(|f| match (&f.ty, &f.ident) {
+            (Type::Path(p), Some(id))
+                if p.path
+                    .segments
+                    .last()
+                    .map(|seg| seg.ident == INCOMPLETE_ARRAY_FIELD)
+                    .unwrap_or(false) =>
+            {
+                Some(id.to_string())
+            }
+            _ => None,
+        })
+    }
+
+    /// Whether bindgen made this struct packed (for `#pragma pack` or
+    /// `__attribute__((packed))`), and whether it gave it an alignment
+    /// (for `alignas` or `__attribute__((aligned))`).
+    fn get_repr(def: &ItemStruct) -> (bool, bool) {
+        let mut packed = false;
+        let mut aligned = false;
+        for attr in def.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+            let _ = attr.parse_nested_meta(|meta| {
+                packed |= meta.path.is_ident("packed");
+                aligned |= meta.path.is_ident("align");
+                // Skip any (N) argument.
+                if meta.input.peek(syn::token::Paren) {
+                    let _ = meta.input.parse::<proc_macro2::Group>();
+                }
+                Ok(())
+            });
+        }
+        (packed, aligned)
+    }
+
+    /// Finds a field of a type which has an alignment attribute, or
+    /// contains one.
+    fn find_aligned_field(&self, def: &ItemStruct) -> Option<(String, QualifiedName)> {
+        def.fields
+            .iter()
+            .find_map(|f| match (array_element_type(&f.ty), &f.ident) {
+                (Type::Path(p), Some(id)) => {
+                    let ty_id = QualifiedName::from_type_path(p);
+                    self.aligned
+                        .contains(&ty_id)
+                        .then(|| (id.to_string(), ty_id))
+                }
+                _ => None,
+            })
+    }
+
     fn has_vtable(def: &ItemStruct) -> bool {
         for f in &def.fields {
             if f.ident.as_ref().map(|id| id == "vtable_").unwrap_or(false) {
@@ -330,6 +536,21 @@ mod tests {
         assert!(bvc.is_pod(&t_id));
     }
 
+    #[test]
+    fn test_function_pointer() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Foo {
+                a: i32,
+                callback: ::std::option::Option<unsafe extern "C" fn(arg1: i32)>,
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        bvc.satisfy_requests(vec![t_id.clone()]).unwrap();
+        assert!(bvc.is_pod(&t_id));
+    }
+
     #[test]
     fn test_with_up() {
         let mut bvc = ByValueChecker::new();
@@ -358,4 +579,134 @@ mod tests {
         bvc.ingest_struct(&t, &Namespace::new());
         assert!(bvc.satisfy_requests(vec![t_id]).is_err());
     }
+
+    #[test]
+    fn test_with_arrays() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Vec3 {
+                a: [f32; 3],
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            struct Box {
+                corners: [Vec3; 8],
+                m: [[f32; 4]; 4],
+                ptrs: [*mut Vec3; 2],
+                empty: [i32; 0],
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        bvc.satisfy_requests(vec![t_id.clone()]).unwrap();
+        assert!(bvc.is_pod(&t_id));
+        assert!(bvc.is_pod(&QualifiedName::new_from_cpp_name("Vec3")));
+    }
+
+    #[test]
+    fn test_with_array_of_cxxstring() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Bar {
+                a: [CxxString; 2],
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        assert!(bvc.satisfy_requests(vec![t_id]).is_err());
+    }
+
+    #[test]
+    fn test_with_flexible_array_member() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Packet {
+                len: u32,
+                data: root::__IncompleteArrayField<u8>,
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        let err = bvc.satisfy_requests(vec![t_id]).unwrap_err();
+        assert!(err.contains("field data is a flexible or zero-length array"));
+    }
+
+    #[test]
+    fn test_could_be_pod() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Foo {
+                a: i32,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            struct Bar {
+                a: Foo,
+                b: CxxString,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let foo_id = QualifiedName::new_from_cpp_name("Foo");
+        let bar_id = QualifiedName::new_from_cpp_name("Bar");
+        assert!(bvc.could_be_pod(&foo_id));
+        assert!(!bvc.could_be_pod(&bar_id));
+        // Asking changes nothing.
+        assert!(!bvc.is_pod(&foo_id));
+    }
+
+    #[test]
+    fn test_packed_with_aligned_field() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            #[repr(C)]
+            #[repr(align(64))]
+            struct Line {
+                a: u8,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            #[repr(C, packed)]
+            struct Wire {
+                a: u8,
+                b: u32,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let t: ItemStruct = parse_quote! {
+            #[repr(C, packed)]
+            struct Bad {
+                a: u8,
+                line: Line,
+            }
+        };
+        bvc.ingest_struct(&t, &Namespace::new());
+        let line_id = QualifiedName::new_from_cpp_name("Line");
+        let wire_id = QualifiedName::new_from_cpp_name("Wire");
+        let bad_id = QualifiedName::new_from_cpp_name("Bad");
+        bvc.satisfy_requests(vec![line_id.clone(), wire_id.clone()])
+            .unwrap();
+        assert!(bvc.is_pod(&line_id));
+        assert!(bvc.is_pod(&wire_id));
+        let err = bvc.satisfy_requests(vec![bad_id]).unwrap_err();
+        assert!(err.contains("field line is of type Line"));
+    }
+
+    #[test]
+    fn test_with_bitfields() {
+        let mut bvc = ByValueChecker::new();
+        let t: ItemStruct = parse_quote! {
+            struct Flags {
+                a: i32,
+                _bitfield_align_1: [u8; 0],
+                _bitfield_1: root::__BindgenBitfieldUnit<[u8; 1usize]>,
+            }
+        };
+        let t_id = ty_from_ident(&t.ident);
+        bvc.ingest_struct(&t, &Namespace::new());
+        bvc.satisfy_requests(vec![t_id.clone()]).unwrap();
+        assert!(bvc.is_pod(&t_id));
+    }
 }
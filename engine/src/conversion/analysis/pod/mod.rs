@@ -13,19 +13,26 @@ use indexmap::set::IndexSet as HashSet;
 
 use autocxx_parser::IncludeCppConfig;
 use byvalue_checker::ByValueChecker;
-use syn::{ItemStruct, Type, Visibility};
+use syn::{
+    parse_quote, Attribute, Ident, ImplItem, Item, ItemStruct, ReturnType, Type, Visibility,
+};
 
 use crate::{
     conversion::{
         analysis::type_converter::{self, add_analysis, TypeConversionContext, TypeConverter},
-        api::{AnalysisPhase, Api, ApiName, NullPhase, StructDetails, TypeKind},
+        api::{
+            AnalysisPhase, Api, ApiName, CppVisibility, EnumAlias, NullPhase, StructDetails,
+            TypeKind, UnionMember,
+        },
         apivec::ApiVec,
         convert_error::{ConvertErrorWithContext, ErrorContext},
+        doc_attr::get_doc_attrs,
         error_reporter::convert_apis,
-        parse::BindgenSemanticAttributes,
+        parse::{BindgenSemanticAttributes, ALIGN_MARKER_PREFIX},
+        type_helpers::array_element_type,
         ConvertErrorFromCpp,
     },
-    types::{Namespace, QualifiedName},
+    types::{Namespace, QualifiedName, BITFIELD_UNIT},
 };
 
 use super::tdef::{TypedefAnalysis, TypedefPhase};
@@ -35,6 +42,24 @@ use super::tdef::{TypedefAnalysis, TypedefPhase};
 pub(crate) struct FieldInfo {
     pub(crate) ty: Type,
     pub(crate) type_kind: type_converter::TypeKind,
+    /// The name by which C++ code outside the type can refer to this field,
+    /// if it's public. This excludes any fields which bindgen might have
+    /// renamed to avoid Rust keywords, which it does by appending `_`.
+    pub(crate) accessible_name: Option<String>,
+}
+
+/// A protected field, which subclasses can reach through accessors on
+/// their C++ peers.
+#[derive(std::fmt::Debug)]
+pub(crate) struct ProtectedField {
+    /// The name by which C++ code refers to the field.
+    pub(crate) cpp_name: String,
+    /// The field's type, as bindgen gave it.
+    pub(crate) ty: Type,
+    pub(crate) doc_attrs: Vec<Attribute>,
+    /// Whether the field can't be assigned: it's `const` or a reference.
+    pub(crate) is_const: bool,
+    pub(crate) is_reference: bool,
 }
 
 #[derive(std::fmt::Debug)]
@@ -53,6 +78,7 @@ pub(crate) struct PodAnalysis {
     /// std::unique_ptr<A> it would just be std::unique_ptr.
     pub(crate) field_definition_deps: HashSet<QualifiedName>,
     pub(crate) field_info: Vec<FieldInfo>,
+    pub(crate) protected_fields: Vec<ProtectedField>,
     pub(crate) is_generic: bool,
     pub(crate) in_anonymous_namespace: bool,
 }
@@ -128,10 +154,15 @@ pub(crate) fn analyze_pod_apis(
 fn analyze_enum(
     name: ApiName,
     mut item: crate::minisyn::ItemEnum,
+    aliases: Vec<EnumAlias>,
 ) -> Result<Box<dyn Iterator<Item = Api<PodPhase>>>, ConvertErrorWithContext> {
     let metadata = BindgenSemanticAttributes::new_retaining_others(&mut item.attrs);
     metadata.check_for_fatal_attrs(&name.name.get_final_ident())?;
-    Ok(Box::new(std::iter::once(Api::Enum { name, item })))
+    Ok(Box::new(std::iter::once(Api::Enum {
+        name,
+        item,
+        aliases,
+    })))
 }
 
 fn analyze_struct(
@@ -149,7 +180,7 @@ fn analyze_struct(
     let mut field_deps = HashSet::new();
     let mut field_definition_deps = HashSet::new();
     let mut field_info = Vec::new();
-    let field_conversion_errors = get_struct_field_types(
+    let mut field_conversion_errors = get_struct_field_types(
         type_converter,
         name.name.get_namespace(),
         &details.item,
@@ -158,6 +189,23 @@ fn analyze_struct(
         &mut field_info,
         extra_apis,
     );
+    field_conversion_errors.extend(get_bitfield_types(
+        type_converter,
+        name.name.get_namespace(),
+        &details.bitfield_impls,
+        &mut field_deps,
+        extra_apis,
+    ));
+    let union_members = std::mem::take(&mut details.union_members);
+    details.union_members = get_union_member_types(
+        byvalue_checker,
+        type_converter,
+        name.name.get_namespace(),
+        union_members,
+        &mut field_deps,
+        extra_apis,
+    );
+    let protected_fields = get_protected_fields(&mut details.item);
     let type_kind = if byvalue_checker.is_pod(&name.name) {
         // It's POD so any errors encountered parsing its fields are important.
         // Let's not allow anything to be POD if it's got rvalue reference fields.
@@ -173,6 +221,11 @@ fn analyze_struct(
                 Some(ErrorContext::new_for_item(id)),
             ));
         }
+        let cpp_name = name.name.to_cpp_name();
+        if config.exposes_private_fields(&cpp_name) {
+            let blocked: Vec<_> = config.get_blocked_fields(&cpp_name).collect();
+            expose_private_fields(&mut details.item, &blocked);
+        }
         TypeKind::Pod
     } else {
         TypeKind::NonPod
@@ -199,6 +252,7 @@ fn analyze_struct(
             field_deps,
             field_definition_deps,
             field_info,
+            protected_fields,
             is_generic,
             in_anonymous_namespace,
         },
@@ -222,6 +276,20 @@ fn get_struct_field_types(
         .collect();
     let type_conversion_context = TypeConversionContext::WithinStructField { struct_type_params };
     for f in &s.fields {
+        // bindgen stores bit-fields in fields it calls _bitfield_N, alongside
+        // zero-sized _bitfield_align_N fields which give them the right
+        // alignment. Neither exists in C++; the bit-fields themselves are
+        // handled by get_bitfield_types.
+        if let Some(id) = f
+            .ident
+            .as_ref()
+            .filter(|id| id.to_string().starts_with("_bitfield_"))
+        {
+            if !id.to_string().starts_with("_bitfield_align_") {
+                field_deps.insert(QualifiedName::new_from_cpp_name(BITFIELD_UNIT));
+            }
+            continue;
+        }
         let annotated = type_converter.convert_type(f.ty.clone(), ns, &type_conversion_context);
         match annotated {
             Ok(mut r) => {
@@ -238,26 +306,189 @@ fn get_struct_field_types(
                     .unwrap_or(false)
                 {
                     field_deps.extend(r.types_encountered);
-                    if let Type::Path(typ) = &r.ty {
+                    if let Type::Path(typ) = array_element_type(&r.ty) {
                         // Later analyses need to know about the field
                         // types where we need full definitions, as opposed
                         // to just declarations. That means just the outermost
-                        // type path.
-                        // TODO: consider arrays.
+                        // type path, or the elements of an array.
                         field_definition_deps.insert(QualifiedName::from_type_path(typ));
                     }
+                    // bindgen gives private and protected fields Rust's
+                    // default visibility.
+                    let accessible_name = match (&f.vis, &f.ident) {
+                        (Visibility::Public(_), Some(id)) if !id.to_string().ends_with('_') => {
+                            Some(id.to_string())
+                        }
+                        _ => None,
+                    };
                     field_info.push(FieldInfo {
                         ty: r.ty,
                         type_kind: r.kind,
+                        accessible_name,
                     });
                 }
             }
-            Err(e) => convert_errors.push(e),
+            Err(err) => convert_errors.push(match (&f.ty, &f.ident) {
+                (Type::Array(_), Some(id)) => ConvertErrorFromCpp::UnsupportedArrayField {
+                    field: id.to_string(),
+                    err: Box::new(err),
+                },
+                _ => err,
+            }),
         };
     }
     convert_errors
 }
 
+/// Finds the protected fields of a struct, and removes the annotations
+/// which bindgen (and autocxx) made on all its fields, since rustc won't
+/// understand them.
+fn get_protected_fields(item: &mut ItemStruct) -> Vec<ProtectedField> {
+    item.fields
+        .iter_mut()
+        .filter_map(|f| {
+            let annotations = BindgenSemanticAttributes::new_retaining_others(&mut f.attrs);
+            if annotations.get_cpp_visibility() != CppVisibility::Protected {
+                return None;
+            }
+            let ident = f.ident.as_ref()?;
+            Some(ProtectedField {
+                cpp_name: annotations
+                    .get_original_name()
+                    .unwrap_or_else(|| ident.to_string()),
+                ty: f.ty.clone(),
+                doc_attrs: get_doc_attrs(&f.attrs),
+                is_const: annotations.is_const_field(),
+                is_reference: annotations.is_reference_field(),
+            })
+        })
+        .collect()
+}
+
+/// Gives the private and protected fields of a POD type which the user
+/// has named in `expose_private_fields!` the same visibility as its public
+/// ones. Fields which bindgen adds for its own purposes, base classes,
+/// and the opaque storage of blocked fields keep theirs.
+fn expose_private_fields(item: &mut ItemStruct, blocked: &[&str]) {
+    for f in item.fields.iter_mut() {
+        let is_own_field = f.ident.as_ref().is_some_and(|id| {
+            !is_bindgen_housekeeping_field(id)
+                && !id.to_string().starts_with("_base")
+                && !blocked.iter().any(|name| id == name)
+        });
+        if is_own_field {
+            f.vis = parse_quote! { pub };
+        }
+    }
+}
+
+/// Whether this is a field which bindgen adds for its own purposes, rather
+/// than one from the C++: padding, the storage behind bit-fields (which
+/// we get at through their getters instead), or the `_address` field it
+/// gives empty types so that they aren't zero-sized, as in C++. We treat
+/// the markers we add to align the storage of blocked fields the same way.
+pub(crate) fn is_bindgen_housekeeping_field(id: &Ident) -> bool {
+    let id = id.to_string();
+    id == "_address"
+        || id.starts_with("_bitfield_")
+        || id.starts_with("__bindgen_padding")
+        || id.starts_with(ALIGN_MARKER_PREFIX)
+}
+
+/// The getter which bindgen generates for each bit-field, and the type
+/// which it returns.
+pub(crate) fn bitfield_getters(
+    bitfield_impls: &[crate::minisyn::Item],
+) -> impl Iterator<Item = (&Ident, &Type)> {
+    bitfield_impls
+        .iter()
+        .filter_map(|item| match &**item {
+            Item::Impl(imp) => Some(imp.items.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|item| match item {
+            ImplItem::Fn(f) if f.sig.receiver().is_some_and(|r| r.mutability.is_none()) => {
+                match &f.sig.output {
+                    ReturnType::Type(_, ty) => Some((&f.sig.ident, ty.as_ref())),
+                    ReturnType::Default => None,
+                }
+            }
+            _ => None,
+        })
+}
+
+/// Finds the types of any bit-fields from the getters which bindgen
+/// generates for them.
+fn get_bitfield_types(
+    type_converter: &mut TypeConverter,
+    ns: &Namespace,
+    bitfield_impls: &[crate::minisyn::Item],
+    field_deps: &mut HashSet<QualifiedName>,
+    extra_apis: &mut ApiVec<NullPhase>,
+) -> Vec<ConvertErrorFromCpp> {
+    let mut convert_errors = Vec::new();
+    let type_conversion_context = TypeConversionContext::WithinStructField {
+        struct_type_params: HashSet::new(),
+    };
+    for (field, ty) in bitfield_getters(bitfield_impls) {
+        match type_converter.convert_type(ty.clone(), ns, &type_conversion_context) {
+            Ok(mut r) => {
+                extra_apis.append(&mut r.extra_apis);
+                field_deps.extend(r.types_encountered);
+            }
+            Err(err) => convert_errors.push(ConvertErrorFromCpp::UnsupportedBitfield {
+                field: field.to_string(),
+                err: Box::new(err),
+            }),
+        }
+    }
+    convert_errors
+}
+
+/// The union members which can have accessors: those which are
+/// pointers or of POD type.
+fn get_union_member_types(
+    byvalue_checker: &ByValueChecker,
+    type_converter: &mut TypeConverter,
+    ns: &Namespace,
+    union_members: Vec<UnionMember>,
+    field_deps: &mut HashSet<QualifiedName>,
+    extra_apis: &mut ApiVec<NullPhase>,
+) -> Vec<UnionMember> {
+    let type_conversion_context = TypeConversionContext::WithinStructField {
+        struct_type_params: HashSet::new(),
+    };
+    union_members
+        .into_iter()
+        .filter(|member| match union_member_type_name(&member.ty) {
+            Some(tn) => byvalue_checker.is_pod(&tn),
+            None => matches!(array_element_type(&member.ty), Type::Ptr(_)),
+        })
+        .filter(|member| {
+            // Anything we can't convert just doesn't get accessors, rather
+            // than preventing the use of the rest of the struct.
+            match type_converter.convert_type(member.ty.0.clone(), ns, &type_conversion_context) {
+                Ok(mut r) => {
+                    extra_apis.append(&mut r.extra_apis);
+                    field_deps.extend(r.types_encountered);
+                    true
+                }
+                Err(_) => false,
+            }
+        })
+        .collect()
+}
+
+/// The type of a union member, or of its elements if it's an array, if
+/// that's a named type.
+fn union_member_type_name(ty: &Type) -> Option<QualifiedName> {
+    match array_element_type(ty) {
+        Type::Path(typ) => Some(QualifiedName::from_type_path(typ)),
+        _ => None,
+    }
+}
+
 /// Map to whether the bases are public.
 fn get_bases(item: &ItemStruct) -> HashMap<QualifiedName, bool> {
     item.fields
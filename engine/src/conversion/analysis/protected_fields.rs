@@ -0,0 +1,203 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create accessors for the protected fields of the superclasses
+//! of subclasses.
+//!
+//! A C++ subclass can read and write the protected fields of its
+//! superclasses, so we give each Rust subclass's C++ peer a getter, and a
+//! setter unless the field is `const` or a reference, for each such field.
+//! Here we describe those accessors as protected methods of the class
+//! which declares the field; they're then forwarded to the peer just as
+//! protected methods are.
+
+use indexmap::set::IndexSet as HashSet;
+
+use syn::{parse_quote, punctuated::Punctuated, token::Comma, FnArg, ReturnType, Type};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TypeKind,
+        },
+        apivec::ApiVec,
+    },
+    minisyn::{minisynize_punctuated, minisynize_vec},
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::{PodPhase, ProtectedField},
+};
+
+pub(crate) fn create_protected_field_accessors(mut apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let ancestors = subclass_ancestors(&apis);
+    if ancestors.is_empty() {
+        return apis;
+    }
+    let non_pod_types: HashSet<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, analysis, .. } if !matches!(analysis.kind, TypeKind::Pod) => {
+                Some(name.name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut accessors = ApiVec::new();
+    for api in apis.iter() {
+        if let Api::Struct { name, analysis, .. } = api {
+            if ancestors.contains(&name.name) {
+                for field in &analysis.protected_fields {
+                    accessors.extend(create_accessors(name, field, &non_pod_types).into_iter());
+                }
+            }
+        }
+    }
+    apis.append(&mut accessors);
+    apis
+}
+
+/// Every superclass of a subclass, and every class from which those
+/// (publicly and visibly) derive.
+fn subclass_ancestors(apis: &ApiVec<PodPhase>) -> HashSet<QualifiedName> {
+    let mut todo: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Subclass { superclass, .. } => Some(superclass.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut ancestors = HashSet::new();
+    while let Some(ancestor) = todo.pop() {
+        if !ancestors.insert(ancestor.clone()) {
+            continue;
+        }
+        for api in apis.iter() {
+            if let Api::Struct { name, analysis, .. } = api {
+                if name.name == ancestor {
+                    todo.extend(analysis.castable_bases.iter().cloned());
+                }
+            }
+        }
+    }
+    ancestors
+}
+
+fn create_accessors(
+    class: &ApiName,
+    field: &ProtectedField,
+    non_pod_types: &HashSet<QualifiedName>,
+) -> Vec<Api<PodPhase>> {
+    // Rust can't be given an array by value.
+    if matches!(field.ty, Type::Array(_)) {
+        return Vec::new();
+    }
+    let typ = class.name.to_type_path();
+    let ty = &field.ty;
+    let cpp_name = format!("{}::{}", class.qualified_cpp_name(), field.cpp_name);
+    // A field of a non-POD type is returned by reference, rather than
+    // copied, and can't be assigned from Rust.
+    let is_non_pod =
+        matches!(ty, Type::Path(tp) if non_pod_types.contains(&QualifiedName::from_type_path(tp)));
+    let (getter_output, ref_return): (ReturnType, _) = match ty {
+        Type::Ptr(ptr) if field.is_reference => {
+            let elem = &ptr.elem;
+            (parse_quote! { -> *const #elem }, true)
+        }
+        _ if is_non_pod => (parse_quote! { -> *const #ty }, true),
+        _ => (parse_quote! { -> #ty }, false),
+    };
+    let mut accessors = vec![create_accessor(
+        class,
+        field,
+        "get",
+        parse_quote! { this: *const #typ },
+        getter_output,
+        References {
+            ref_return,
+            ..Default::default()
+        },
+        CppFunctionBody::StaticDataGetter(cpp_name.clone()),
+        format!(" Returns the value of the protected field `{cpp_name}`."),
+    )];
+    if !field.is_const && !field.is_reference && !is_non_pod {
+        accessors.push(create_accessor(
+            class,
+            field,
+            "set",
+            parse_quote! { this: *mut #typ, value: #ty },
+            ReturnType::Default,
+            References::default(),
+            CppFunctionBody::StaticDataSetter(cpp_name.clone()),
+            format!(" Sets the value of the protected field `{cpp_name}`."),
+        ));
+    }
+    accessors
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_accessor(
+    class: &ApiName,
+    field: &ProtectedField,
+    verb: &str,
+    inputs: Punctuated<FnArg, Comma>,
+    output: ReturnType,
+    references: References,
+    body: CppFunctionBody,
+    doc: String,
+) -> Api<PodPhase> {
+    let name = QualifiedName::new(
+        class.name.get_namespace(),
+        make_ident(format!(
+            "{}_autocxx_field_{}_{verb}",
+            class.name.get_final_item(),
+            field.cpp_name
+        )),
+    );
+    let mut doc_attrs = field.doc_attrs.clone();
+    if !doc_attrs.is_empty() {
+        doc_attrs.push(parse_quote! { #[doc = ""] });
+    }
+    doc_attrs.push(parse_quote! { #[doc = #doc] });
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: minisynize_vec(doc_attrs),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Protected,
+            special_member: None,
+            unused_template_param: false,
+            references,
+            // Fields are commonly named with a trailing underscore, which
+            // would look odd before ours.
+            original_name: Some(format!("{}_{verb}", field.cpp_name.trim_end_matches('_'))),
+            self_ty: Some(class.name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((body, CppFunctionKind::Method)),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedProtectedFieldAccessor,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
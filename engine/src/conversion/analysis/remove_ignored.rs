@@ -9,7 +9,7 @@
 use indexmap::set::IndexSet as HashSet;
 
 use super::deps::HasDependencies;
-use super::fun::{FnAnalysis, FnKind, FnPhase};
+use super::fun::{FnAnalysis, FnKind, FnPhase, PodAndDepAnalysis};
 use crate::conversion::apivec::ApiVec;
 use crate::conversion::{convert_error::ErrorContext, ConvertErrorFromCpp};
 use crate::{conversion::api::Api, known_types};
@@ -18,6 +18,10 @@ use crate::{conversion::api::Api, known_types};
 /// We also eliminate any APIs that depend on some type that we just don't
 /// know about at all. In either case, we don't simply remove the type, but instead
 /// replace it with an error marker.
+/// A type isn't ignored merely because one of its constructors or allocators
+/// was: those are only its dependencies to keep them alive through garbage
+/// collection, and the type (and its static methods) remains usable without
+/// them.
 pub(crate) fn filter_apis_by_ignored_dependents(mut apis: ApiVec<FnPhase>) -> ApiVec<FnPhase> {
     let (ignored_items, valid_items): (Vec<&Api<_>>, Vec<&Api<_>>) = apis
         .iter()
@@ -35,7 +39,18 @@ pub(crate) fn filter_apis_by_ignored_dependents(mut apis: ApiVec<FnPhase>) -> Ap
         iterate_again = false;
         apis = apis
             .into_iter()
-            .map(|api| {
+            .map(|mut api| {
+                if let Api::Struct {
+                    analysis:
+                        PodAndDepAnalysis {
+                            constructor_and_allocator_deps,
+                            ..
+                        },
+                    ..
+                } = &mut api
+                {
+                    constructor_and_allocator_deps.retain(|dep| !ignored_items.contains(dep));
+                }
                 let ignored_dependents: HashSet<_> = api
                     .deps()
                     .filter(|dep| ignored_items.contains(*dep))
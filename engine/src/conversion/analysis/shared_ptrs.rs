@@ -0,0 +1,155 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create functions which turn a `std::unique_ptr` into a
+//! `std::shared_ptr`, so that Rust can create a `SharedPtr` using any of
+//! a type's constructors. We do this for any type which appears within a
+//! `std::shared_ptr` or `std::weak_ptr` in the APIs we're given.
+
+use indexmap::set::IndexSet as HashSet;
+
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, PathArguments,
+    ReturnType, Type,
+};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+            TraitSynthesis, TypedefKind,
+        },
+        apivec::ApiVec,
+    },
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    pod::PodPhase,
+};
+
+/// Shared pointer types, as bindgen and as cxx name them.
+const SHARED_PTR_TYPES: &[&str] = &[
+    "std::shared_ptr",
+    "std::weak_ptr",
+    "cxx::SharedPtr",
+    "cxx::WeakPtr",
+];
+
+pub(crate) fn create_shared_ptr_conversions(apis: ApiVec<PodPhase>) -> ApiVec<PodPhase> {
+    let mut pointees = HashSet::new();
+    for api in apis.iter() {
+        match api {
+            Api::Function { fun, .. } => {
+                for input in fun.inputs.iter() {
+                    if let FnArg::Typed(pt) = &**input {
+                        find_shared_ptr_pointees(&pt.ty, &mut pointees);
+                    }
+                }
+                if let ReturnType::Type(_, ty) = &*fun.output {
+                    find_shared_ptr_pointees(ty, &mut pointees);
+                }
+            }
+            Api::Typedef { item, .. } => match item {
+                TypedefKind::Type(ity) => find_shared_ptr_pointees(&ity.ty, &mut pointees),
+                TypedefKind::Use(_, ty) => find_shared_ptr_pointees(ty, &mut pointees),
+            },
+            _ => {}
+        }
+    }
+    let struct_names: Vec<QualifiedName> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::Struct { name, .. } if pointees.contains(&name.name) => Some(name.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut apis = apis;
+    for ty_name in struct_names {
+        apis.push(create_shared_ptr_conversion(ty_name));
+    }
+    apis
+}
+
+/// Notes the type within any shared or weak pointer in this type.
+fn find_shared_ptr_pointees(ty: &Type, pointees: &mut HashSet<QualifiedName>) {
+    match ty {
+        Type::Path(typ) => {
+            let is_shared_ptr = SHARED_PTR_TYPES
+                .contains(&QualifiedName::from_type_path(typ).to_cpp_name().as_str());
+            for seg in typ.path.segments.iter() {
+                if let PathArguments::AngleBracketed(args) = &seg.arguments {
+                    for arg in args.args.iter() {
+                        if let GenericArgument::Type(inner) = arg {
+                            if let (true, Type::Path(inner_typ)) = (is_shared_ptr, inner) {
+                                pointees.insert(QualifiedName::from_type_path(inner_typ));
+                            }
+                            find_shared_ptr_pointees(inner, pointees);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Reference(typr) => find_shared_ptr_pointees(&typr.elem, pointees),
+        Type::Ptr(typp) => find_shared_ptr_pointees(&typp.elem, pointees),
+        _ => {}
+    }
+}
+
+fn create_shared_ptr_conversion(ty_name: QualifiedName) -> Api<PodPhase> {
+    let typ = ty_name.to_type_path();
+    let name = get_shared_ptr_conversion_name(&ty_name);
+    let ident = name.get_final_ident();
+    let inputs: Punctuated<FnArg, Comma> = parse_quote! {
+        arg0: root::std::unique_ptr<#typ>
+    };
+    let output: ReturnType = parse_quote! {
+        -> root::std::shared_ptr<#typ>
+    };
+    Api::Function {
+        name: ApiName::new_from_qualified_name(name),
+        fun: Box::new(FuncToConvert {
+            ident,
+            doc_attrs: Vec::new(),
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references: References::default(),
+            original_name: None,
+            self_ty: None,
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::SharedPtrFromUniquePtr(ty_name.clone()),
+                CppFunctionKind::Function,
+            )),
+            add_to_trait: Some(TraitSynthesis::SharedPtrFromUniquePtr(ty_name)),
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedOther,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        analysis: (),
+    }
+}
+
+fn get_shared_ptr_conversion_name(ty_name: &QualifiedName) -> QualifiedName {
+    let name = format!("{}_autocxx_make_shared", ty_name.get_final_item());
+    QualifiedName::new(ty_name.get_namespace(), make_ident(name))
+}
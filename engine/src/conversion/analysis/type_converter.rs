@@ -11,6 +11,7 @@ use crate::{
         api::{AnalysisPhase, Api, ApiName, NullPhase, TypedefKind, UnanalyzedApi},
         apivec::ApiVec,
         codegen_cpp::type_to_cpp::CppNameMap,
+        type_helpers::extract_function_pointer_signature,
         ConvertErrorFromCpp,
     },
     known_types::{known_types, CxxGenericType},
@@ -24,10 +25,11 @@ use proc_macro2::Ident;
 use quote::ToTokens;
 use syn::{
     parse_quote, punctuated::Punctuated, token::Comma, GenericArgument, PathArguments, PathSegment,
-    Type, TypePath, TypePtr,
+    ReturnType, Type, TypeBareFn, TypePath, TypePtr,
 };
 
 use super::tdef::TypedefAnalysis;
+use super::vectors::is_vector_of_unique_ptrs;
 
 /// Certain kinds of type may require special handling by callers.
 #[derive(Debug)]
@@ -38,6 +40,7 @@ pub(crate) enum TypeKind {
     Reference,
     RValueReference,
     MutableReference,
+    FunctionPointer,
 }
 
 /// Results of some type conversion, annotated with a list of every type encountered,
@@ -82,6 +85,78 @@ pub(crate) enum PointerTreatment {
     RValueReference,
 }
 
+/// A `std::chrono` type which we represent in Rust using `std::time`.
+#[derive(Clone, Debug)]
+pub(crate) enum ChronoType {
+    Duration,
+    /// A `time_point` of the clock with this marker type name in
+    /// `autocxx::chrono`.
+    TimePoint(&'static str),
+}
+
+impl ChronoType {
+    /// The type which Rust callers use.
+    pub(crate) fn rust_type(&self) -> Type {
+        match self {
+            Self::Duration => parse_quote! { ::std::time::Duration },
+            Self::TimePoint(clock) => {
+                let clock = make_ident(clock);
+                parse_quote! { autocxx::chrono::TimePoint<autocxx::chrono::#clock> }
+            }
+        }
+    }
+}
+
+/// The code units of a `std::u8string`, `std::u16string`, `std::u32string`
+/// or `std::wstring`, which Rust deals in as slices or vectors of integers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum WideCharUnit {
+    U8,
+    U16,
+    U32,
+}
+
+impl WideCharUnit {
+    pub(crate) fn rust_type(&self) -> Type {
+        match self {
+            Self::U8 => parse_quote! { u8 },
+            Self::U16 => parse_quote! { u16 },
+            Self::U32 => parse_quote! { u32 },
+        }
+    }
+
+    pub(crate) fn cpp_type(&self) -> &'static str {
+        match self {
+            Self::U8 => "uint8_t",
+            Self::U16 => "uint16_t",
+            Self::U32 => "uint32_t",
+        }
+    }
+}
+
+/// The standard aliases for `std::chrono::duration`. bindgen tells us the
+/// alias rather than the `duration` itself, whose `std::ratio` period it
+/// can't represent.
+const CHRONO_DURATION_ALIASES: &[&str] = &[
+    "nanoseconds",
+    "microseconds",
+    "milliseconds",
+    "seconds",
+    "minutes",
+    "hours",
+    "days",
+    "weeks",
+    "months",
+    "years",
+];
+
+/// Standard clocks, and the marker types for them in `autocxx::chrono`.
+const CHRONO_CLOCKS: &[(&str, &str)] = &[
+    ("system_clock", "SystemClock"),
+    ("steady_clock", "SteadyClock"),
+    ("high_resolution_clock", "HighResolutionClock"),
+];
+
 /// Options when converting a type.
 /// It's possible we could add more policies here in future.
 /// For example, Rust in general allows type names containing
@@ -105,6 +180,13 @@ impl TypeConversionContext {
             Self::OuterType { pointer_treatment } => *pointer_treatment,
         }
     }
+    /// Whether a pointer (or array) may contain `elem` if it's another
+    /// pointer. Struct fields keep the types bindgen gave them, which cxx
+    /// never sees, so they may, whereas cxx can't pass a pointer to a
+    /// pointer.
+    fn allows_indirect_pointer(&self, elem: &Type) -> bool {
+        matches!(self, Self::WithinStructField { .. }) && matches!(elem, Type::Ptr(_))
+    }
     fn allow_instantiation_of_forward_declaration(&self) -> bool {
         matches!(self, Self::WithinReference)
     }
@@ -166,13 +248,21 @@ impl<'a> TypeConverter<'a> {
     ) -> Result<Annotated<Type>, ConvertErrorFromCpp> {
         let result = match ty {
             Type::Path(p) => {
+                if let Some(bare_fn) = extract_function_pointer_signature(&p) {
+                    return self.convert_function_pointer(bare_fn.clone(), ns);
+                }
                 let newp = self.convert_type_path(p, ns, ctx)?;
                 if let Type::Path(newpp) = &newp.ty {
                     let qn = QualifiedName::from_type_path(newpp);
                     if !ctx.allow_instantiation_of_forward_declaration()
                         && self.forward_declarations.contains(&qn)
                     {
-                        return Err(ConvertErrorFromCpp::TypeContainingForwardDeclaration(qn));
+                        return Err(match ctx {
+                            TypeConversionContext::WithinContainer => {
+                                ConvertErrorFromCpp::TypeContainingForwardDeclaration(qn)
+                            }
+                            _ => ConvertErrorFromCpp::ForwardDeclarationByValue(qn),
+                        });
                     }
                     // Special handling because rust_Str (as emitted by bindgen)
                     // doesn't simply get renamed to a different type _identifier_.
@@ -197,6 +287,7 @@ impl<'a> TypeConverter<'a> {
             Type::Reference(mut r) => {
                 let innerty =
                     self.convert_boxed_type(r.elem, ns, &TypeConversionContext::WithinReference)?;
+                Self::ensure_pointee_is_not_function_pointer(&innerty)?;
                 r.elem = innerty.ty;
                 Annotated::new(
                     Type::Reference(r),
@@ -206,8 +297,12 @@ impl<'a> TypeConverter<'a> {
                 )
             }
             Type::Array(mut arr) => {
-                let innerty =
-                    self.convert_type(*arr.elem, ns, &TypeConversionContext::WithinReference)?;
+                let elem_ctx = if ctx.allows_indirect_pointer(&arr.elem) {
+                    ctx
+                } else {
+                    &TypeConversionContext::WithinReference
+                };
+                let innerty = self.convert_type(*arr.elem, ns, elem_ctx)?;
                 arr.elem = Box::new(innerty.ty);
                 Annotated::new(
                     Type::Array(arr),
@@ -216,7 +311,7 @@ impl<'a> TypeConverter<'a> {
                     TypeKind::Regular,
                 )
             }
-            Type::Ptr(ptr) => self.convert_ptr(ptr, ns, ctx.pointer_treatment())?,
+            Type::Ptr(ptr) => self.convert_ptr(ptr, ns, ctx)?,
             _ => {
                 return Err(ConvertErrorFromCpp::UnknownType(
                     ty.to_token_stream().to_string(),
@@ -261,14 +356,25 @@ impl<'a> TypeConverter<'a> {
         original_tn
             .validate_ok_for_cxx()
             .map_err(ConvertErrorFromCpp::InvalidIdent)?;
-        if self.config.is_on_blocklist(&original_tn.to_cpp_name()) {
-            return Err(ConvertErrorFromCpp::Blocked(original_tn));
+        if let Some(directive) = self.config.blocked_by(&original_tn.to_cpp_name()) {
+            return Err(ConvertErrorFromCpp::Blocked(original_tn, directive));
         }
         let mut deps = HashSet::new();
 
         // Now convert this type itself.
         deps.insert(original_tn.clone());
         // First let's see if this is a typedef.
+        let resolved_function_pointer = match self.resolve_typedef(&original_tn)? {
+            Some(Type::Path(resolved_tp)) => {
+                extract_function_pointer_signature(resolved_tp).cloned()
+            }
+            _ => None,
+        };
+        if let Some(bare_fn) = resolved_function_pointer {
+            let mut annotated = self.convert_function_pointer(bare_fn, ns)?;
+            annotated.types_encountered.extend(deps);
+            return Ok(annotated);
+        }
         let (typ, tn) = match self.resolve_typedef(&original_tn)? {
             None => (typ, original_tn),
             Some(Type::Path(resolved_tp)) => {
@@ -294,6 +400,41 @@ impl<'a> TypeConverter<'a> {
             }
         };
 
+        // If this is an instantiation which was named in concrete!, use
+        // that, even if it's one which cxx could otherwise represent.
+        if let Some(concrete) = self.find_declared_concrete(&typ) {
+            deps.remove(&tn);
+            deps.insert(concrete.clone());
+            return Ok(Annotated::new(
+                Type::Path(concrete.to_type_path()),
+                deps,
+                ApiVec::new(),
+                TypeKind::Regular,
+            ));
+        }
+
+        // How the user would spell this type in a concrete! directive, in
+        // case we can't represent it as it is.
+        let cpp_definition = self
+            .original_name_map
+            .type_to_cpp(&Type::Path(typ.clone()))
+            .ok();
+
+        // cxx can't put a UniquePtr in a CxxVector, so a vector of
+        // std::unique_ptrs becomes an opaque concrete type instead, to which
+        // we add accessors for the elements.
+        if is_vector_of_unique_ptrs(&typ) {
+            let (new_tn, api) = self.get_templated_typename(&Type::Path(typ))?;
+            deps.remove(&tn);
+            deps.insert(new_tn.clone());
+            return Ok(Annotated::new(
+                Type::Path(new_tn.to_type_path()),
+                deps,
+                api.into_iter().collect(),
+                TypeKind::Regular,
+            ));
+        }
+
         // Now let's see if it's a known type.
         // (We may entirely reject some types at this point too.)
         let mut typ = match known_types().consider_substitution(&tn) {
@@ -326,12 +467,24 @@ impl<'a> TypeConverter<'a> {
                         &TypeConversionContext::WithinContainer,
                     )?;
                     ab.args = innerty.ty;
-                    kind = self.confirm_inner_type_is_acceptable_generic_payload(
-                        &ab.args,
-                        &tn,
-                        generic_behavior,
-                        forward_declarations_ok,
-                    )?;
+                    kind = self
+                        .confirm_inner_type_is_acceptable_generic_payload(
+                            &ab.args,
+                            &tn,
+                            generic_behavior,
+                            forward_declarations_ok,
+                        )
+                        .map_err(|err| match cpp_definition {
+                            // A concrete! type for this instantiation would
+                            // be opaque, so cxx wouldn't mind its payload.
+                            Some(cpp_definition) => {
+                                ConvertErrorFromCpp::UnsupportedTemplateInstantiation {
+                                    cpp_definition,
+                                    err: Box::new(err),
+                                }
+                            }
+                            None => err,
+                        })?;
                     deps.extend(innerty.types_encountered.drain(..));
                 } else {
                     return Err(ConvertErrorFromCpp::TemplatedTypeContainingNonPathArg(
@@ -380,6 +533,200 @@ impl<'a> TypeConverter<'a> {
         Ok(Annotated::new(Type::Path(typ), deps, extra_apis, kind))
     }
 
+    /// Whether this type is a `std::string_view`, perhaps via some typedefs.
+    pub(crate) fn is_std_string_view(&self, typ: &TypePath) -> bool {
+        let tn = QualifiedName::from_type_path(typ);
+        if tn == QualifiedName::new_from_cpp_name("std::string_view") {
+            return true;
+        }
+        let typ = match self.resolve_typedef(&tn) {
+            Ok(None) => typ,
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return false,
+        };
+        let tn = QualifiedName::from_type_path(typ);
+        if tn != QualifiedName::new_from_cpp_name("std::basic_string_view") {
+            return false;
+        }
+        match &typ.path.segments.last().map(|s| &s.arguments) {
+            Some(PathArguments::AngleBracketed(ab)) => matches!(
+                ab.args.first(),
+                Some(GenericArgument::Type(Type::Path(char_type)))
+                    if char_type.path.segments.last().is_some_and(|s| s.ident == "c_char")
+            ),
+            _ => false,
+        }
+    }
+
+    /// If this type is a `std::u8string`, `std::u16string`, `std::u32string`
+    /// or `std::wstring`, returns the size of its code units. For `wstring`
+    /// that's the size of `wchar_t` on the target, which bindgen tells us
+    /// by representing it as a 16- or 32-bit integer.
+    pub(crate) fn std_wide_string_unit(&self, typ: &TypePath) -> Option<WideCharUnit> {
+        let tn = QualifiedName::from_type_path(typ);
+        if tn == QualifiedName::new_from_cpp_name("std::u8string") {
+            return Some(WideCharUnit::U8);
+        }
+        if tn == QualifiedName::new_from_cpp_name("std::u16string") {
+            return Some(WideCharUnit::U16);
+        }
+        if tn == QualifiedName::new_from_cpp_name("std::u32string") {
+            return Some(WideCharUnit::U32);
+        }
+        if tn != QualifiedName::new_from_cpp_name("std::wstring") {
+            return None;
+        }
+        let typ = match self.resolve_typedef(&tn) {
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return None,
+        };
+        match &typ.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                Some(GenericArgument::Type(Type::Path(wchar))) => {
+                    match wchar.path.segments.last()?.ident.to_string().as_str() {
+                        "u16" | "i16" => Some(WideCharUnit::U16),
+                        "u32" | "i32" => Some(WideCharUnit::U32),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether this type is a `std::filesystem::path`, perhaps via some
+    /// typedefs.
+    pub(crate) fn is_std_filesystem_path(&self, typ: &TypePath) -> bool {
+        let path_name = QualifiedName::new_from_cpp_name("std::filesystem::path");
+        let tn = QualifiedName::from_type_path(typ);
+        if tn == path_name {
+            return true;
+        }
+        matches!(
+            self.resolve_typedef(&tn),
+            Ok(Some(Type::Path(resolved_tp))) if QualifiedName::from_type_path(resolved_tp) == path_name
+        )
+    }
+
+    /// If this type is a `std::optional<T>`, perhaps via some typedefs,
+    /// returns the (unconverted) `T`.
+    pub(crate) fn std_optional_payload(&self, typ: &TypePath) -> Option<Type> {
+        let typ = match self.resolve_typedef(&QualifiedName::from_type_path(typ)) {
+            Ok(None) => typ,
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return None,
+        };
+        let tn = QualifiedName::from_type_path(typ);
+        if tn.get_final_item() != "optional"
+            || tn.get_namespace().iter().next().map(String::as_str) != Some("std")
+        {
+            return None;
+        }
+        match &typ.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(ab) => match ab.args.iter().exactly_one() {
+                Ok(GenericArgument::Type(payload)) => Some(payload.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// If this type is a `std::pair` or `std::tuple`, perhaps via some
+    /// typedefs, returns its (unconverted) element types.
+    pub(crate) fn std_tuple_elements(&self, typ: &TypePath) -> Option<Vec<Type>> {
+        let typ = match self.resolve_typedef(&QualifiedName::from_type_path(typ)) {
+            Ok(None) => typ,
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return None,
+        };
+        let tn = QualifiedName::from_type_path(typ);
+        if tn != QualifiedName::new_from_cpp_name("std::pair")
+            && tn != QualifiedName::new_from_cpp_name("std::tuple")
+        {
+            return None;
+        }
+        match &typ.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(ab) => ab
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    GenericArgument::Type(elem) => Some(elem.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+                .filter(|elements| !elements.is_empty()),
+            _ => None,
+        }
+    }
+
+    /// If this type is a `std::chrono::duration` or a `time_point` of one
+    /// of the standard clocks, perhaps via some typedefs, say which.
+    pub(crate) fn std_chrono_type(&self, typ: &TypePath) -> Option<ChronoType> {
+        let typ = match self.resolve_typedef(&QualifiedName::from_type_path(typ)) {
+            Ok(None) => typ,
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return None,
+        };
+        let cpp_name = QualifiedName::from_type_path(typ).to_cpp_name();
+        let item = cpp_name.strip_prefix("std::chrono::")?;
+        if item == "duration" || CHRONO_DURATION_ALIASES.contains(&item) {
+            return Some(ChronoType::Duration);
+        }
+        // bindgen calls a nested typedef such as steady_clock::time_point
+        // steady_clock_time_point.
+        let clock = match item.strip_suffix("_time_point") {
+            Some(clock) => clock.to_string(),
+            None if item == "time_point" => match &typ.path.segments.last()?.arguments {
+                PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                    Some(GenericArgument::Type(Type::Path(clock))) => {
+                        QualifiedName::from_type_path(clock)
+                            .get_final_item()
+                            .to_string()
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            None => return None,
+        };
+        CHRONO_CLOCKS
+            .iter()
+            .find(|(cpp_clock, _)| *cpp_clock == clock)
+            .map(|(_, marker)| ChronoType::TimePoint(marker))
+    }
+
+    /// If this type is a `std::span<T>`, perhaps via some typedefs,
+    /// returns the (unconverted) `T`.
+    pub(crate) fn std_span_element(&self, typ: &TypePath) -> Option<Type> {
+        self.std_template_element(typ, "std::span")
+    }
+
+    /// If this type is a `std::initializer_list<T>`, perhaps via some
+    /// typedefs, returns the (unconverted) `T`.
+    pub(crate) fn std_initializer_list_element(&self, typ: &TypePath) -> Option<Type> {
+        self.std_template_element(typ, "std::initializer_list")
+    }
+
+    fn std_template_element(&self, typ: &TypePath, template: &str) -> Option<Type> {
+        let typ = match self.resolve_typedef(&QualifiedName::from_type_path(typ)) {
+            Ok(None) => typ,
+            Ok(Some(Type::Path(resolved_tp))) => resolved_tp,
+            _ => return None,
+        };
+        if QualifiedName::from_type_path(typ) != QualifiedName::new_from_cpp_name(template) {
+            return None;
+        }
+        // Any extent is a non-type template parameter, which bindgen omits.
+        match &typ.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                Some(GenericArgument::Type(elem)) => Some(elem.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     fn get_generic_args(typ: &mut TypePath) -> Option<&mut PathSegment> {
         match typ.path.segments.last_mut() {
             Some(s) if !s.arguments.is_empty() => Some(s),
@@ -455,13 +802,18 @@ impl<'a> TypeConverter<'a> {
         &mut self,
         mut ptr: TypePtr,
         ns: &Namespace,
-        pointer_treatment: PointerTreatment,
+        ctx: &TypeConversionContext,
     ) -> Result<Annotated<Type>, ConvertErrorFromCpp> {
-        match pointer_treatment {
+        match ctx.pointer_treatment() {
             PointerTreatment::Pointer => {
-                Self::ensure_pointee_is_valid(&ptr)?;
-                let innerty =
-                    self.convert_boxed_type(ptr.elem, ns, &TypeConversionContext::WithinReference)?;
+                // Each level keeps its own constness, as bindgen gave it.
+                let innerty = if ctx.allows_indirect_pointer(&ptr.elem) {
+                    self.convert_boxed_type(ptr.elem, ns, ctx)?
+                } else {
+                    Self::ensure_pointee_is_valid(&ptr)?;
+                    self.convert_boxed_type(ptr.elem, ns, &TypeConversionContext::WithinReference)?
+                };
+                Self::ensure_pointee_is_not_function_pointer(&innerty)?;
                 ptr.elem = innerty.ty;
                 Ok(Annotated::new(
                     Type::Ptr(ptr),
@@ -474,6 +826,7 @@ impl<'a> TypeConverter<'a> {
                 let mutability = ptr.mutability;
                 let elem =
                     self.convert_boxed_type(ptr.elem, ns, &TypeConversionContext::WithinReference)?;
+                Self::ensure_pointee_is_not_function_pointer(&elem)?;
                 // TODO - in the future, we should check if this is a rust::Str and throw
                 // a wobbler if not. rust::Str should only be seen _by value_ in C++
                 // headers; it manifests as &str in Rust but on the C++ side it must
@@ -497,6 +850,7 @@ impl<'a> TypeConverter<'a> {
                 Self::ensure_pointee_is_valid(&ptr)?;
                 let innerty =
                     self.convert_boxed_type(ptr.elem, ns, &TypeConversionContext::WithinReference)?;
+                Self::ensure_pointee_is_not_function_pointer(&innerty)?;
                 ptr.elem = innerty.ty;
                 Ok(Annotated::new(
                     Type::Ptr(ptr),
@@ -519,6 +873,97 @@ impl<'a> TypeConverter<'a> {
         }
     }
 
+    fn ensure_pointee_is_not_function_pointer(
+        pointee: &Annotated<Box<Type>>,
+    ) -> Result<(), ConvertErrorFromCpp> {
+        match pointee.kind {
+            TypeKind::FunctionPointer => Err(ConvertErrorFromCpp::UnsupportedFunctionPointer(
+                "it is used via a pointer or reference".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Converts the parameters and return type of a C function pointer.
+    /// We accept only those which can cross a C ABI boundary unchanged:
+    /// primitives, raw pointers and other function pointers.
+    fn convert_function_pointer(
+        &mut self,
+        mut bare_fn: TypeBareFn,
+        ns: &Namespace,
+    ) -> Result<Annotated<Type>, ConvertErrorFromCpp> {
+        if bare_fn.variadic.is_some() {
+            return Err(ConvertErrorFromCpp::UnsupportedFunctionPointer(
+                "it is variadic".to_string(),
+            ));
+        }
+        let mut types_encountered = HashSet::new();
+        let mut extra_apis = ApiVec::new();
+        let component_types =
+            bare_fn
+                .inputs
+                .iter_mut()
+                .map(|input| &mut input.ty)
+                .chain(match &mut bare_fn.output {
+                    ReturnType::Type(_, ty) => Some(ty.as_mut()),
+                    ReturnType::Default => None,
+                });
+        for ty in component_types {
+            let mut annotated =
+                self.convert_type(ty.clone(), ns, &TypeConversionContext::WithinReference)?;
+            let acceptable = match (&annotated.kind, &annotated.ty) {
+                (TypeKind::Pointer | TypeKind::FunctionPointer, _) => true,
+                (TypeKind::Regular, Type::Path(typ)) => {
+                    known_types().is_c_abi_by_value(&QualifiedName::from_type_path(typ))
+                }
+                _ => false,
+            };
+            if !acceptable {
+                return Err(ConvertErrorFromCpp::UnsupportedFunctionPointer(format!(
+                    "it uses {}, which is not a primitive or raw pointer",
+                    ty.to_token_stream()
+                )));
+            }
+            *ty = annotated.ty;
+            types_encountered.extend(annotated.types_encountered);
+            extra_apis.append(&mut annotated.extra_apis);
+        }
+        if let ReturnType::Type(_, ty) = &bare_fn.output {
+            if matches!(ty.as_ref(), Type::Path(typ) if extract_function_pointer_signature(typ).is_some())
+            {
+                return Err(ConvertErrorFromCpp::UnsupportedFunctionPointer(
+                    "it returns another function pointer".to_string(),
+                ));
+            }
+        }
+        Ok(Annotated::new(
+            Type::Path(parse_quote! {
+                ::std::option::Option< #bare_fn >
+            }),
+            types_encountered,
+            extra_apis,
+            TypeKind::FunctionPointer,
+        ))
+    }
+
+    /// The `concrete!` type for this template instantiation, if there is one.
+    fn find_declared_concrete(&self, typ: &TypePath) -> Option<QualifiedName> {
+        if !matches!(
+            typ.path.segments.last().map(|ps| &ps.arguments),
+            Some(PathArguments::AngleBracketed(_))
+        ) || self
+            .ignored_types
+            .contains(&QualifiedName::from_type_path(typ))
+        {
+            return None;
+        }
+        let cpp_definition = self
+            .original_name_map
+            .type_to_cpp(&Type::Path(typ.clone()))
+            .ok()?;
+        self.concrete_templates.get(&cpp_definition).cloned()
+    }
+
     fn get_templated_typename(
         &mut self,
         rs_definition: &Type,
@@ -735,10 +1180,13 @@ pub(crate) fn find_types<A: AnalysisPhase>(apis: &ApiVec<A>) -> HashSet<Qualifie
             | Api::Struct { .. }
             | Api::Subclass { .. }
             | Api::ExternCppType { .. }
-            | Api::RustType { .. } => Some(api.name()),
+            | Api::RustType { .. }
+            | Api::ClosureHolder { .. } => Some(api.name()),
             Api::StringConstructor { .. }
             | Api::Function { .. }
             | Api::Const { .. }
+            | Api::RawFunction { .. }
+            | Api::BitfieldUnit { .. }
             | Api::CType { .. }
             | Api::RustSubclassFn { .. }
             | Api::IgnoredItem { .. }
@@ -0,0 +1,260 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Code to create accessor methods for instantiations of `std::vector`
+//! whose elements are `std::unique_ptr`s.
+//!
+//! cxx can't put a `UniquePtr` inside a `CxxVector`, so such vectors become
+//! opaque concrete types, which factory functions commonly return. We add
+//! `len`, `get` and `get_mut` methods to each, so that Rust can at least
+//! reach the objects they own, for instance to call virtual methods on an
+//! abstract base class.
+
+use indexmap::set::IndexSet as HashSet;
+
+use autocxx_parser::IncludeCppConfig;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, PathArguments,
+    ReturnType, Type, TypePath,
+};
+
+use crate::{
+    conversion::{
+        api::{
+            Api, ApiName, CppVisibility, DeletedOrDefaulted, FuncToConvert, Provenance, References,
+        },
+        apivec::ApiVec,
+        convert_error::{ConvertErrorFromCpp, ErrorContext},
+    },
+    minisyn::minisynize_punctuated,
+    types::{make_ident, QualifiedName},
+};
+
+use super::{
+    fun::function_wrapper::{CppFunctionBody, CppFunctionKind},
+    maps::materialize_in_signatures,
+    pod::PodPhase,
+};
+
+/// An accessor method which we generate for a vector of `std::unique_ptr`s.
+#[derive(Clone, Debug)]
+pub(crate) enum VectorAccessor {
+    Len,
+    Get,
+    GetMut,
+}
+
+impl VectorAccessor {
+    fn method_name(&self) -> &'static str {
+        match self {
+            Self::Len => "len",
+            Self::Get => "get",
+            Self::GetMut => "get_mut",
+        }
+    }
+}
+
+pub(crate) fn create_vector_accessors(
+    apis: ApiVec<PodPhase>,
+    config: &IncludeCppConfig,
+) -> ApiVec<PodPhase> {
+    let mut apis = materialize_in_signatures(apis, config, is_vector_of_unique_ptrs);
+    let all_names: HashSet<QualifiedName> = apis.iter().map(|api| api.name().clone()).collect();
+    let vectors: Vec<_> = apis
+        .iter()
+        .filter_map(|api| match api {
+            Api::ConcreteType {
+                name,
+                cpp_definition,
+                rs_definition,
+            } => parse_vector_definition(cpp_definition)
+                .map(|element| (name.name.clone(), element, rs_definition.is_none())),
+            _ => None,
+        })
+        .collect();
+    for (ty_name, element, declared) in vectors {
+        let element_name = QualifiedName::new_from_cpp_name(&element);
+        if !element.contains('<') && all_names.contains(&element_name) {
+            apis.extend(create_accessors(&ty_name, &element_name).into_iter());
+        } else if declared {
+            // Only complain about vectors which the user asked for by name
+            // using concrete!, as with maps.
+            apis.push(Api::IgnoredItem {
+                ctx: Some(ErrorContext::new_for_method(
+                    ty_name.get_final_ident(),
+                    make_ident(VectorAccessor::Len.method_name()),
+                )),
+                name: ApiName::new_from_qualified_name(get_accessor_name(
+                    &ty_name,
+                    &VectorAccessor::Len,
+                )),
+                err: ConvertErrorFromCpp::UnsupportedVectorOfUniquePtrs(element),
+            });
+        }
+    }
+    apis
+}
+
+/// Whether this is a `std::vector` of `std::unique_ptr`s, which cxx can't
+/// represent as a `CxxVector`.
+pub(crate) fn is_vector_of_unique_ptrs(tp: &TypePath) -> bool {
+    if QualifiedName::from_type_path(tp) != QualifiedName::new_from_cpp_name("std::vector") {
+        return false;
+    }
+    match tp.path.segments.last().map(|seg| &seg.arguments) {
+        Some(PathArguments::AngleBracketed(ab)) => matches!(
+            ab.args.first(),
+            Some(GenericArgument::Type(Type::Path(element)))
+                if QualifiedName::from_type_path(element)
+                    == QualifiedName::new_from_cpp_name("std::unique_ptr")
+        ),
+        _ => false,
+    }
+}
+
+/// If this C++ type is a `std::vector` of `std::unique_ptr`s, return the
+/// C++ type which they point to.
+fn parse_vector_definition(cpp_definition: &str) -> Option<String> {
+    let element = cpp_definition
+        .trim()
+        .trim_start_matches("::")
+        .strip_prefix("std::vector<")?
+        .strip_suffix('>')?
+        .trim()
+        .trim_start_matches("::")
+        .strip_prefix("std::unique_ptr<")?
+        .strip_suffix('>')?
+        .trim();
+    // A custom deleter or allocator would need more care than we take.
+    if element.contains(',') {
+        return None;
+    }
+    Some(element.to_string())
+}
+
+fn create_accessors(ty_name: &QualifiedName, element: &QualifiedName) -> Vec<Api<PodPhase>> {
+    let typ = ty_name.to_type_path();
+    let element_typ = element.to_type_path();
+    vec![
+        create_accessor(
+            ty_name,
+            VectorAccessor::Len,
+            parse_quote! { this: *const #typ },
+            parse_quote! { -> usize },
+            References::default(),
+            "Returns the number of elements in the vector.",
+        ),
+        create_accessor(
+            ty_name,
+            VectorAccessor::Get,
+            parse_quote! { this: *const #typ, index: usize },
+            parse_quote! { -> *const #element_typ },
+            References {
+                ref_return: true,
+                ..Default::default()
+            },
+            "Returns the object owned by the element at this index. Aborts if the index is out of range or the element is null.",
+        ),
+        create_accessor(
+            ty_name,
+            VectorAccessor::GetMut,
+            parse_quote! { this: *mut #typ, index: usize },
+            parse_quote! { -> *mut #element_typ },
+            References {
+                ref_return: true,
+                ..Default::default()
+            },
+            "Returns the object owned by the element at this index, mutably. Aborts if the index is out of range or the element is null.",
+        ),
+    ]
+}
+
+fn create_accessor(
+    ty_name: &QualifiedName,
+    accessor: VectorAccessor,
+    inputs: Punctuated<FnArg, Comma>,
+    output: ReturnType,
+    references: References,
+    doc: &str,
+) -> Api<PodPhase> {
+    let name = get_accessor_name(ty_name, &accessor);
+    let doc_attr: syn::Attribute = parse_quote! {
+        #[doc = #doc]
+    };
+    Api::Function {
+        fun: Box::new(FuncToConvert {
+            ident: name.get_final_ident(),
+            doc_attrs: vec![doc_attr.into()],
+            inputs: minisynize_punctuated(inputs),
+            output: output.into(),
+            vis: parse_quote! { pub },
+            virtualness: crate::conversion::api::Virtualness::None,
+            cpp_vis: CppVisibility::Public,
+            special_member: None,
+            unused_template_param: false,
+            references,
+            original_name: Some(accessor.method_name().to_string()),
+            self_ty: Some(ty_name.clone()),
+            synthesized_this_type: None,
+            synthetic_cpp: Some((
+                CppFunctionBody::VectorAccessor(accessor),
+                CppFunctionKind::Method,
+            )),
+            add_to_trait: None,
+            is_deleted: DeletedOrDefaulted::Neither,
+            provenance: Provenance::SynthesizedContainerAccessor,
+            variadic: false,
+            noexcept: false,
+            explicit: false,
+            pinned_rust_name: None,
+            safety_override: None,
+            deprecated: None,
+            must_use: false,
+            is_final: false,
+        }),
+        name: ApiName::new_from_qualified_name(name),
+        analysis: (),
+    }
+}
+
+fn get_accessor_name(ty_name: &QualifiedName, accessor: &VectorAccessor) -> QualifiedName {
+    QualifiedName::new(
+        ty_name.get_namespace(),
+        make_ident(format!(
+            "{}_autocxx_vector_{}",
+            ty_name.get_final_item(),
+            accessor.method_name()
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_vector_definition;
+
+    #[test]
+    fn test_parse_vector_definition() {
+        assert_eq!(
+            parse_vector_definition("std::vector<std::unique_ptr<Shape>>"),
+            Some("Shape".to_string())
+        );
+        assert_eq!(
+            parse_vector_definition("std::vector<std::unique_ptr<geo::Shape> >"),
+            Some("geo::Shape".to_string())
+        );
+        assert_eq!(
+            parse_vector_definition("std::vector<std::unique_ptr<Shape, ShapeDeleter>>"),
+            None
+        );
+        assert_eq!(
+            parse_vector_definition("std::vector<std::shared_ptr<Shape>>"),
+            None
+        );
+        assert_eq!(parse_vector_definition("std::vector<int>"), None);
+    }
+}
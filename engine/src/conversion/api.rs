@@ -15,12 +15,13 @@ use syn::{
     token::{Comma, Unsafe},
 };
 
+use crate::known_types::known_types;
 use crate::minisyn::{
-    Attribute, FnArg, Ident, ItemConst, ItemEnum, ItemStruct, ItemType, ItemUse, LitBool, LitInt,
-    Pat, ReturnType, Type, Visibility,
+    Attribute, FnArg, ForeignItem, Ident, ItemConst, ItemEnum, ItemStruct, ItemType, ItemUse,
+    LitBool, LitInt, Pat, ReturnType, Type, Visibility,
 };
 use crate::types::{make_ident, Namespace, QualifiedName};
-use autocxx_parser::{ExternCppType, RustFun, RustPath};
+use autocxx_parser::{ClosureParam, CppOperator, ExternCppType, FnSafety, RustFun, RustPath};
 use itertools::Itertools;
 use quote::ToTokens;
 
@@ -61,6 +62,55 @@ pub(crate) struct StructDetails {
     pub(crate) item: ItemStruct,
     pub(crate) layout: Option<Layout>,
     pub(crate) has_rvalue_reference_fields: bool,
+    /// bindgen's accessor methods for any bit-fields, which we pass on
+    /// only if the struct turns out to be POD.
+    pub(crate) bitfield_impls: Vec<crate::minisyn::Item>,
+    /// Union members for which we generate accessor methods, if the struct
+    /// turns out to be POD.
+    pub(crate) union_members: Vec<UnionMember>,
+    /// The class's `[[deprecated]]` attribute, if any.
+    pub(crate) deprecated: Option<Deprecation>,
+    /// Whether the class is declared `final`, so can't be subclassed.
+    pub(crate) is_final: bool,
+    /// The offset clang computed for each field, where bindgen told us.
+    pub(crate) field_offsets: Vec<FieldOffset>,
+}
+
+/// The offset in bytes of a field from the start of its struct.
+#[derive(Clone, Debug)]
+pub(crate) struct FieldOffset {
+    pub(crate) field: Ident,
+    pub(crate) offset: usize,
+}
+
+/// A C++ `[[deprecated]]` attribute, which we pass on to Rust.
+#[derive(Clone, Debug)]
+pub(crate) struct Deprecation {
+    /// The reason given in the attribute, if any.
+    pub(crate) message: Option<String>,
+}
+
+impl Deprecation {
+    /// The equivalent Rust attribute.
+    pub(crate) fn to_attr(&self) -> syn::Attribute {
+        match &self.message {
+            Some(note) => syn::parse_quote! { #[deprecated(note = #note)] },
+            None => syn::parse_quote! { #[deprecated] },
+        }
+    }
+}
+
+/// A member of a union which is either this struct itself or one of its
+/// anonymous union fields. The union's storage is opaque, so Rust code
+/// gets at the member using a pair of accessor methods.
+#[derive(Clone, Debug)]
+pub(crate) struct UnionMember {
+    /// The name of the getter; the other accessor has `_mut` appended.
+    pub(crate) accessor: Ident,
+    /// The field holding an anonymous union, or `None` if this struct is
+    /// itself the union.
+    pub(crate) storage_field: Option<Ident>,
+    pub(crate) ty: Type,
 }
 
 /// Layout of a type, equivalent to the same type in ir/layout.rs in bindgen
@@ -96,23 +146,22 @@ pub(crate) enum Virtualness {
     PureVirtual,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub(crate) enum CastMutability {
-    ConstToConst,
-    MutToConst,
-    MutToMut,
-}
-
 /// Indicates that this function (which is synthetic) should
 /// be a trait implementation rather than a method or free function.
 #[derive(Clone, Debug)]
 pub(crate) enum TraitSynthesis {
     Cast {
         to_type: QualifiedName,
-        mutable: CastMutability,
     },
     AllocUninitialized(QualifiedName),
     FreeUninitialized(QualifiedName),
+    ExplicitDestructor(QualifiedName),
+    Debug(QualifiedName),
+    Operator(QualifiedName, CppOperator),
+    /// The mutable counterpart of `operator[]`, which C++ spells the
+    /// same way as the immutable one.
+    IndexMut(QualifiedName),
+    SharedPtrFromUniquePtr(QualifiedName),
 }
 
 /// Details of a subclass constructor.
@@ -126,11 +175,22 @@ pub(crate) struct SubclassConstructorDetails {
     pub(crate) cpp_impl: CppFunction,
 }
 
+/// Details of a public method on a subclass which forwards to a protected,
+/// non-virtual method of one of its superclasses.
+#[derive(Clone, Debug)]
+pub(crate) struct SubclassProtectedMethodDetails {
+    pub(crate) subclass: SubclassName,
+    /// Implementation of the forwarding method _itself_ as distinct
+    /// from any wrapper function we create to call it.
+    pub(crate) cpp_impl: CppFunction,
+}
+
 /// Contributions to traits representing C++ superclasses that
 /// we may implement as Rust subclasses.
 #[derive(Clone, Debug)]
 pub(crate) struct SuperclassMethod {
     pub(crate) name: Ident,
+    pub(crate) doc_attrs: Vec<Attribute>,
     pub(crate) receiver: QualifiedName,
     pub(crate) params: Punctuated<FnArg, Comma>,
     pub(crate) param_names: Vec<Pat>,
@@ -149,6 +209,12 @@ pub(crate) struct References {
     pub(crate) ref_params: HashSet<Ident>,
     pub(crate) ref_return: bool,
     pub(crate) rvalue_ref_return: bool,
+    /// `std::span<const T>` parameters. bindgen loses the `const` from
+    /// template arguments, so it tells us about these separately.
+    pub(crate) const_span_params: HashSet<Ident>,
+    /// Pointers to member functions, which bindgen gives the same type as
+    /// pointers to plain functions.
+    pub(crate) member_function_pointer_params: HashSet<Ident>,
 }
 
 impl References {
@@ -248,6 +314,49 @@ pub(crate) enum Provenance {
     Bindgen,
     SynthesizedOther,
     SynthesizedSubclassConstructor(Box<SubclassConstructorDetails>),
+    SynthesizedSubclassProtectedMethod(Box<SubclassProtectedMethodDetails>),
+    /// A copy of a bindgen function with some trailing defaulted
+    /// parameters omitted, leaving `supplied` parameters (not
+    /// counting any `this`).
+    SynthesizedDefaultArguments {
+        supplied: usize,
+    },
+    /// A copy of a bindgen function whose `const std::string&`
+    /// parameters instead accept Rust strings.
+    SynthesizedStrParameters,
+    /// A getter or setter for some static data, which cxx can't access
+    /// directly: either a global variable or a static data member. Contains
+    /// the fully-qualified C++ name of the data.
+    SynthesizedStaticDataAccessor(String),
+    /// A getter or setter for a protected field of a superclass of a
+    /// subclass, to be made a member of the subclass's C++ peer.
+    SynthesizedProtectedFieldAccessor,
+    /// An accessor method for a `std::map` or `std::unordered_map`
+    /// instantiation, or for a `std::vector` of `std::unique_ptr`s, such
+    /// as `len` or `get`.
+    SynthesizedContainerAccessor,
+    /// One of the functions with which Rust iterates over a C++ container
+    /// which has `begin()` and `end()`.
+    SynthesizedIterator {
+        container: QualifiedName,
+        /// Whether Rust should copy the elements rather than referring to
+        /// them.
+        yields_values: bool,
+    },
+    /// A friend function defined within the given class, which the user
+    /// described in a `friend_function!` directive.
+    SynthesizedFriend(QualifiedName),
+    /// A user-defined literal operator, which the user described in a
+    /// `literal_operator!` directive. Contains the name from the directive.
+    SynthesizedLiteralOperator(String),
+    /// An instantiation of a function template, which the user described
+    /// in an `instantiate!` directive.
+    SynthesizedInstantiation {
+        /// The name from the directive, such as `Config::get<int>`.
+        name: String,
+        /// A description of the signature, for use in error messages.
+        signature: String,
+    },
 }
 
 /// Whether a function has =delete or =default
@@ -297,6 +406,56 @@ pub(crate) struct FuncToConvert {
     pub(crate) synthetic_cpp: Option<(CppFunctionBody, CppFunctionKind)>,
     /// =delete
     pub(crate) is_deleted: DeletedOrDefaulted,
+    /// Whether the C++ function is declared `noexcept`, so can't throw.
+    pub(crate) noexcept: bool,
+    /// Whether this is a constructor declared `explicit`, so not to be
+    /// used for implicit conversions.
+    pub(crate) explicit: bool,
+    /// The Rust name which the user chose for this overload using
+    /// `rename!`, if any.
+    pub(crate) pinned_rust_name: Option<String>,
+    /// Whether the user marked this function safe or unsafe using
+    /// `safe_fn!` or `unsafe_fn!`, overriding the `safety!` policy.
+    pub(crate) safety_override: Option<FnSafety>,
+    /// The C++ `[[deprecated]]` attribute, if any.
+    pub(crate) deprecated: Option<Deprecation>,
+    /// Whether the C++ function is declared `[[nodiscard]]`, so its result
+    /// is `#[must_use]` in Rust.
+    pub(crate) must_use: bool,
+    /// Whether this is a virtual method declared `final`, which subclasses
+    /// can't override.
+    pub(crate) is_final: bool,
+}
+
+impl FuncToConvert {
+    /// Whether this parameter (which must be one of our `inputs`) is a
+    /// `const std::string&`, or a reference to some other type which
+    /// cxx represents as a `CxxString`.
+    pub(crate) fn is_const_string_ref_param(&self, arg: &syn::FnArg) -> bool {
+        let pt = match arg {
+            syn::FnArg::Typed(pt) => pt,
+            syn::FnArg::Receiver(_) => return false,
+        };
+        let is_ref_param = match &*pt.pat {
+            syn::Pat::Ident(pi) => {
+                pi.ident != "this"
+                    && self
+                        .references
+                        .ref_params
+                        .contains(&Ident::from(pi.ident.clone()))
+            }
+            _ => false,
+        };
+        match &*pt.ty {
+            syn::Type::Ptr(ptr) if is_ref_param && ptr.mutability.is_none() => match &*ptr.elem {
+                syn::Type::Path(tp) => {
+                    known_types().convertible_from_strs(&QualifiedName::from_type_path(tp))
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 /// Layers of analysis which may be applied to decorate each API.
@@ -417,6 +576,14 @@ impl SubclassName {
     pub(crate) fn remove_ownership(&self) -> Ident {
         self.with_suffix("_remove_ownership")
     }
+    pub(crate) fn peer_destroyed(&self) -> Ident {
+        self.with_suffix("_peer_destroyed")
+    }
+    /// The type of the value with which the subclass behind a
+    /// `future_wrapper!` resolves its future.
+    pub(crate) fn future_output(&self) -> Ident {
+        self.with_suffix("Output")
+    }
     fn with_suffix(&self, suffix: &str) -> Ident {
         make_ident(format!("{}{}", self.0.name.get_final_item(), suffix))
     }
@@ -435,6 +602,13 @@ impl SubclassName {
         let id = make_ident(format!("{id}_super"));
         QualifiedName::new(superclass_namespace, id)
     }
+    pub(crate) fn get_protected_fn_name(
+        superclass_namespace: &Namespace,
+        id: &str,
+    ) -> QualifiedName {
+        let id = make_ident(format!("protected_{id}"));
+        QualifiedName::new(superclass_namespace, id)
+    }
     pub(crate) fn get_methods_trait_name(superclass_name: &QualifiedName) -> QualifiedName {
         Self::with_qualified_name_suffix(superclass_name, "methods")
     }
@@ -448,6 +622,15 @@ impl SubclassName {
     }
 }
 
+/// An enumerator which has the same value as an earlier one. Rust enums
+/// can't have duplicate discriminants, so bindgen gives us these as
+/// associated constants instead of variants.
+#[derive(Clone, Debug)]
+pub(crate) struct EnumAlias {
+    pub(crate) name: crate::minisyn::Ident,
+    pub(crate) aliased: crate::minisyn::Ident,
+}
+
 #[derive(std::fmt::Debug)]
 /// Different types of API we might encounter.
 ///
@@ -504,6 +687,24 @@ pub(crate) enum Api<T: AnalysisPhase> {
     Const {
         name: ApiName,
         const_item: ItemConst,
+        /// If this is a static data member, the type to which it belongs
+        /// and the member's own name.
+        member_of: Option<(QualifiedName, crate::minisyn::Ident)>,
+    },
+    /// A function which cxx can't call, but which the user asked to be
+    /// exposed just as bindgen declares it, in an `extern "C"` block.
+    /// Currently only used for C-style variadic functions.
+    RawFunction {
+        name: ApiName,
+        item: ForeignItem,
+        deps: Vec<QualifiedName>,
+    },
+    /// bindgen's storage type for bit-fields, `__BindgenBitfieldUnit`,
+    /// and its methods. These are passed on verbatim so that the
+    /// bit-field accessors of POD structs work.
+    BitfieldUnit {
+        name: ApiName,
+        items: Vec<crate::minisyn::Item>,
     },
     /// A typedef found in the bindgen output which we wish
     /// to pass on in our output
@@ -515,7 +716,11 @@ pub(crate) enum Api<T: AnalysisPhase> {
     },
     /// An enum encountered in the
     /// `bindgen` output.
-    Enum { name: ApiName, item: ItemEnum },
+    Enum {
+        name: ApiName,
+        item: ItemEnum,
+        aliases: Vec<EnumAlias>,
+    },
     /// A struct encountered in the
     /// `bindgen` output.
     Struct {
@@ -545,6 +750,12 @@ pub(crate) enum Api<T: AnalysisPhase> {
         details: RustFun,
         deps: Vec<QualifiedName>,
     },
+    /// The Rust type which owns a closure passed to a C++ function in
+    /// place of a `std::function`.
+    ClosureHolder {
+        name: ApiName,
+        details: Box<ClosureParam>,
+    },
     /// Some function for the extern "Rust" block.
     RustSubclassFn {
         name: ApiName,
@@ -600,6 +811,8 @@ impl<T: AnalysisPhase> Api<T> {
             Api::StringConstructor { name } => name,
             Api::Function { name, .. } => name,
             Api::Const { name, .. } => name,
+            Api::RawFunction { name, .. } => name,
+            Api::BitfieldUnit { name, .. } => name,
             Api::Typedef { name, .. } => name,
             Api::Enum { name, .. } => name,
             Api::Struct { name, .. } => name,
@@ -607,6 +820,7 @@ impl<T: AnalysisPhase> Api<T> {
             Api::IgnoredItem { name, .. } => name,
             Api::RustType { name, .. } => name,
             Api::RustFn { name, .. } => name,
+            Api::ClosureHolder { name, .. } => name,
             Api::RustSubclassFn { name, .. } => name,
             Api::Subclass { name, .. } => &name.0,
             Api::SubclassTraitItem { name, .. } => name,
@@ -638,6 +852,30 @@ impl<T: AnalysisPhase> Api<T> {
             .unwrap_or_else(|| self.name().get_final_item())
     }
 
+    /// What sort of thing this is, for diagnostics.
+    pub(crate) fn kind_description(&self) -> &'static str {
+        match self {
+            Api::ForwardDeclaration { .. } => "forward declaration",
+            Api::OpaqueTypedef { .. } | Api::Typedef { .. } => "typedef",
+            Api::ConcreteType { .. } => "template instantiation",
+            Api::StringConstructor { .. } => "string constructor",
+            Api::Function { .. } | Api::RawFunction { .. } => "function",
+            Api::Const { .. } => "constant",
+            Api::BitfieldUnit { .. } => "bit-field storage",
+            Api::Enum { .. } => "enum",
+            Api::Struct { .. } => "struct",
+            Api::CType { .. } => "C type",
+            Api::IgnoredItem { .. } => "ignored item",
+            Api::RustType { .. } => "Rust type",
+            Api::RustFn { .. } => "Rust function",
+            Api::ClosureHolder { .. } => "closure holder",
+            Api::RustSubclassFn { .. } => "Rust subclass function",
+            Api::Subclass { .. } => "subclass",
+            Api::SubclassTraitItem { .. } => "superclass method",
+            Api::ExternCppType { .. } => "extern C++ type",
+        }
+    }
+
     /// If this API turns out to have the same QualifiedName as another,
     /// whether it's OK to just discard it?
     pub(crate) fn discard_duplicates(&self) -> bool {
@@ -712,11 +950,16 @@ impl<T: AnalysisPhase> Api<T> {
     pub(crate) fn enum_unchanged(
         name: ApiName,
         item: ItemEnum,
+        aliases: Vec<EnumAlias>,
     ) -> Result<Box<dyn Iterator<Item = Api<T>>>, ConvertErrorWithContext>
     where
         T: 'static,
     {
-        Ok(Box::new(std::iter::once(Api::Enum { name, item })))
+        Ok(Box::new(std::iter::once(Api::Enum {
+            name,
+            item,
+            aliases,
+        })))
     }
 }
 
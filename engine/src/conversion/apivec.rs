@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use indexmap::set::IndexSet as HashSet;
+use indexmap::map::IndexMap as HashMap;
 
 use crate::{
     conversion::{api::ApiName, convert_error::ErrorContext, ConvertErrorFromCpp},
@@ -27,7 +27,11 @@ use super::api::{AnalysisPhase, Api};
 /// Specifically, each API should have a unique [`QualifiedName`] which is kept
 /// within an [`ApiName`]. The [`QualifiedName`] is used to refer to this API
 /// from others, e.g. to represent edges in the graph used for garbage collection,
-/// so that's why this uniqueness is so important.
+/// so that's why this uniqueness is so important. It also means we can look
+/// APIs up by name, rather than searching for them.
+///
+/// APIs are kept in the order in which they were added, since the order of
+/// our output depends upon it.
 ///
 /// At present, this type also refuses to allow mutation of an API once it
 /// has been added to a set. This is because the autocxx engine is
@@ -43,39 +47,69 @@ use super::api::{AnalysisPhase, Api};
 /// the naming-uniqueness-invariant remains, so any such mutation should
 /// allow mutation only of other fields, not the name.
 pub(crate) struct ApiVec<P: AnalysisPhase> {
-    apis: Vec<Api<P>>,
-    names: HashSet<QualifiedName>,
+    apis: HashMap<QualifiedName, Api<P>>,
 }
 
 impl<P: AnalysisPhase> ApiVec<P> {
     pub(crate) fn push(&mut self, api: Api<P>) {
         let name = api.name();
-        let already_contains = self.already_contains(name);
-        if already_contains {
-            if api.discard_duplicates() {
+        match self.apis.get(name) {
+            None => {
+                self.apis.insert(name.clone(), api);
+            }
+            Some(_) if api.discard_duplicates() => {
                 // This is already an IgnoredItem or something else where
                 // we can silently drop it.
                 log::info!("Discarding duplicate API for {}", name);
-            } else {
+            }
+            Some(existing) => {
                 log::info!(
                     "Duplicate API for {} - removing all of them and replacing with an IgnoredItem.",
                     name
                 );
-                self.retain(|api| api.name() != name);
-                self.push(Api::IgnoredItem {
-                    name: ApiName::new_from_qualified_name(name.clone()),
-                    err: ConvertErrorFromCpp::DuplicateItemsFoundInParsing,
-                    ctx: Some(ErrorContext::new_for_item(name.get_final_ident())),
-                })
+                // Say what all the clashing APIs were, including any
+                // which earlier clashed.
+                let mut kinds = match existing {
+                    Api::IgnoredItem {
+                        err: ConvertErrorFromCpp::DuplicateItemsFoundInParsing(_, kinds),
+                        ..
+                    } => kinds.clone(),
+                    _ => vec![existing.kind_description()],
+                };
+                kinds.push(api.kind_description());
+                let name = name.clone();
+                // Replacing the entry keeps its place in the order.
+                self.apis.insert(
+                    name.clone(),
+                    Api::IgnoredItem {
+                        name: ApiName::new_from_qualified_name(name.clone()),
+                        err: ConvertErrorFromCpp::DuplicateItemsFoundInParsing(name.clone(), kinds),
+                        ctx: Some(ErrorContext::new_for_item(name.get_final_ident())),
+                    },
+                );
             }
-        } else {
-            self.names.insert(name.clone());
-            self.apis.push(api)
         }
     }
 
-    fn already_contains(&self, name: &QualifiedName) -> bool {
-        self.names.contains(name)
+    /// Replaces the API with the same name as this one, keeping its place
+    /// in the order, or adds this one if there's none.
+    pub(crate) fn replace(&mut self, api: Api<P>) {
+        self.apis.insert(api.name().clone(), api);
+    }
+
+    /// The API with the given name, if there is one.
+    pub(crate) fn get(&self, name: &QualifiedName) -> Option<&Api<P>> {
+        self.apis.get(name)
+    }
+
+    pub(crate) fn contains_key(&self, name: &QualifiedName) -> bool {
+        self.apis.contains_key(name)
+    }
+
+    /// Takes out the API with the given name, if there is one. This doesn't
+    /// preserve the order of the remaining APIs.
+    pub(crate) fn take(&mut self, name: &QualifiedName) -> Option<Api<P>> {
+        self.apis.swap_remove(name)
     }
 
     pub(crate) fn new() -> Self {
@@ -83,7 +117,7 @@ impl<P: AnalysisPhase> ApiVec<P> {
     }
 
     pub(crate) fn append(&mut self, more: &mut ApiVec<P>) {
-        self.extend(more.apis.drain(..))
+        self.extend(more.apis.drain(..).map(|(_, api)| api))
     }
 
     pub(crate) fn extend(&mut self, it: impl Iterator<Item = Api<P>>) {
@@ -94,25 +128,28 @@ impl<P: AnalysisPhase> ApiVec<P> {
     }
 
     pub(crate) fn iter(&self) -> impl Iterator<Item = &Api<P>> {
-        self.apis.iter()
+        self.apis.values()
     }
 
     pub(crate) fn into_iter(self) -> impl Iterator<Item = Api<P>> {
-        self.apis.into_iter()
+        self.apis.into_values()
     }
 
     pub(crate) fn is_empty(&self) -> bool {
         self.apis.is_empty()
     }
 
-    pub fn retain<F>(&mut self, f: F)
+    /// Sort the APIs by name, so that their order no longer depends upon
+    /// the order in which they were discovered or analyzed.
+    pub(crate) fn sort_by_name(&mut self) {
+        self.apis.sort_keys()
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
     where
         F: FnMut(&Api<P>) -> bool,
     {
-        self.apis.retain(f);
-        self.names.clear();
-        self.names
-            .extend(self.apis.iter().map(|api| api.name()).cloned());
+        self.apis.retain(|_, api| f(api));
     }
 }
 
@@ -120,7 +157,6 @@ impl<P: AnalysisPhase> Default for ApiVec<P> {
     fn default() -> Self {
         Self {
             apis: Default::default(),
-            names: Default::default(),
         }
     }
 }
@@ -135,3 +171,86 @@ impl<P: AnalysisPhase> FromIterator<Api<P>> for ApiVec<P> {
         this
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ApiVec;
+    use crate::{
+        conversion::{
+            api::{ApiName, NullPhase, UnanalyzedApi},
+            ConvertErrorFromCpp,
+        },
+        types::QualifiedName,
+    };
+
+    #[test]
+    fn test_duplicate_names() {
+        let name = QualifiedName::new_from_cpp_name("foo::bar");
+        let api_name = || ApiName::new_from_qualified_name(name.clone());
+        let mut apis = ApiVec::<NullPhase>::new();
+        apis.push(UnanalyzedApi::StringConstructor {
+            name: ApiName::new_from_qualified_name(QualifiedName::new_from_cpp_name("baz")),
+        });
+        apis.push(UnanalyzedApi::ForwardDeclaration {
+            name: api_name(),
+            err: None,
+        });
+        apis.push(UnanalyzedApi::OpaqueTypedef {
+            name: api_name(),
+            forward_declaration: false,
+        });
+        apis.push(UnanalyzedApi::StringConstructor { name: api_name() });
+        let clashes: Vec<_> = apis
+            .iter()
+            .filter_map(|api| match api {
+                UnanalyzedApi::IgnoredItem { name, err, .. } => Some((name.name.clone(), err)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(apis.iter().count(), 2);
+        assert_eq!(clashes.len(), 1);
+        assert_eq!(clashes[0].0, name);
+        assert!(matches!(
+            clashes[0].1,
+            ConvertErrorFromCpp::DuplicateItemsFoundInParsing(clash, kinds)
+                if clash == &name
+                    && kinds == &["forward declaration", "typedef", "string constructor"]
+        ));
+        assert!(clashes[0]
+            .1
+            .to_string()
+            .contains("named foo::bar (forward declaration vs typedef vs string constructor)"));
+    }
+
+    #[test]
+    fn test_lookup_by_name() {
+        let names: Vec<_> = ["c", "a", "b"]
+            .into_iter()
+            .map(QualifiedName::new_from_cpp_name)
+            .collect();
+        let mut apis: ApiVec<NullPhase> = names
+            .iter()
+            .map(|name| UnanalyzedApi::StringConstructor {
+                name: ApiName::new_from_qualified_name(name.clone()),
+            })
+            .collect();
+        assert!(apis.contains_key(&names[1]));
+        assert_eq!(apis.get(&names[2]).unwrap().name(), &names[2]);
+        assert!(apis.get(&QualifiedName::new_from_cpp_name("d")).is_none());
+        // A clash doesn't move the API.
+        apis.push(UnanalyzedApi::ForwardDeclaration {
+            name: ApiName::new_from_qualified_name(names[1].clone()),
+            err: None,
+        });
+        let order = |apis: &ApiVec<NullPhase>| -> Vec<_> {
+            apis.iter().map(|api| api.name().to_cpp_name()).collect()
+        };
+        assert_eq!(order(&apis), ["c", "a", "b"]);
+        apis.retain(|api| api.name() != &names[0]);
+        assert_eq!(order(&apis), ["a", "b"]);
+        assert!(!apis.contains_key(&names[0]));
+        assert_eq!(apis.take(&names[1]).unwrap().name(), &names[1]);
+        assert!(!apis.contains_key(&names[1]));
+        assert_eq!(order(&apis), ["b"]);
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Finds the address of the most-derived object of which `obj` is a part,
+/// so that references to different base subobjects of the same object give
+/// the same answer. `dynamic_cast<const void*>` doesn't need RTTI on GCC
+/// or Clang, but MSVC does, so without it we fall back to the address of
+/// `obj` itself.
+pub(super) static ADDRESS_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_ADDRESS_PRELUDE
+    #define AUTOCXX_ADDRESS_PRELUDE
+    // Mechanics to compare the identity of C++ objects from Rust
+    #if defined(_MSC_VER) && !defined(_CPPRTTI)
+    #define AUTOCXX_MOST_DERIVED_ADDRESS 0
+    #else
+    #define AUTOCXX_MOST_DERIVED_ADDRESS 1
+    #endif
+    template <typename T>
+    typename std::enable_if<AUTOCXX_MOST_DERIVED_ADDRESS && std::is_polymorphic<T>::value, std::size_t>::type
+    autocxx_object_address(const T& obj) {
+      return reinterpret_cast<std::uintptr_t>(dynamic_cast<const void*>(std::addressof(obj)));
+    }
+    template <typename T>
+    typename std::enable_if<!(AUTOCXX_MOST_DERIVED_ADDRESS && std::is_polymorphic<T>::value), std::size_t>::type
+    autocxx_object_address(const T& obj) {
+      return reinterpret_cast<std::uintptr_t>(std::addressof(obj));
+    }
+    #endif // AUTOCXX_ADDRESS_PRELUDE
+"};
@@ -0,0 +1,54 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Conversions between `std::chrono` types and the counts of nanoseconds
+/// which we pass to and from Rust. Rust doesn't know the representation or
+/// period of each duration (bindgen can't express `std::ratio`) so all the
+/// arithmetic happens here.
+/// `autocxx_chrono_from_nanos` converts itself to whatever duration or
+/// time_point it's passed as, so the C++ compiler deduces those for us.
+/// Returned values are saturated if they're out of range of the nanoseconds
+/// we can pass back to Rust.
+pub(super) static CHRONO_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_CHRONO_PRELUDE
+    #define AUTOCXX_CHRONO_PRELUDE
+    // Mechanics to convert std::chrono types to and from nanoseconds
+    struct autocxx_chrono_from_nanos {
+      int64_t nanos;
+      template <typename Rep, typename Period>
+      operator std::chrono::duration<Rep, Period>() const {
+        return std::chrono::duration_cast<std::chrono::duration<Rep, Period>>(
+            std::chrono::nanoseconds(nanos));
+      }
+      template <typename Clock, typename Duration>
+      operator std::chrono::time_point<Clock, Duration>() const {
+        return std::chrono::time_point<Clock, Duration>(
+            std::chrono::duration_cast<Duration>(std::chrono::nanoseconds(nanos)));
+      }
+    };
+    template <typename Rep, typename Period>
+    int64_t autocxx_chrono_to_nanos(std::chrono::duration<Rep, Period> d) {
+      // Find out whether it fits without overflowing on the way.
+      auto wide = std::chrono::duration_cast<
+          std::chrono::duration<long double, std::nano>>(d).count();
+      if (wide >= static_cast<long double>(std::numeric_limits<int64_t>::max())) {
+        return std::numeric_limits<int64_t>::max();
+      }
+      if (wide <= static_cast<long double>(std::numeric_limits<int64_t>::min())) {
+        return std::numeric_limits<int64_t>::min();
+      }
+      return std::chrono::duration_cast<std::chrono::nanoseconds>(d).count();
+    }
+    template <typename Clock, typename Duration>
+    int64_t autocxx_chrono_to_nanos(std::chrono::time_point<Clock, Duration> t) {
+      return autocxx_chrono_to_nanos(t.time_since_epoch());
+    }
+    #endif // AUTOCXX_CHRONO_PRELUDE
+"};
@@ -0,0 +1,29 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Writes an object to a stream for its `Debug` implementation. The first
+/// overload is viable only if the type has an `operator<<`, and is
+/// preferred because `0` is an `int`; otherwise we fall back to describing
+/// the object by its type name and address.
+pub(super) static DEBUG_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_DEBUG_PRELUDE
+    #define AUTOCXX_DEBUG_PRELUDE
+    // Mechanics to stringify types for Rust's Debug trait
+    template <typename T>
+    auto autocxx_debug_stream(std::ostream& stream, const T& value, const char*, int)
+        -> decltype(void(stream << value)) {
+      stream << value;
+    }
+    template <typename T>
+    void autocxx_debug_stream(std::ostream& stream, const T& value, const char* type_name, long) {
+      stream << type_name << \" at \" << static_cast<const void*>(&value);
+    }
+    #endif // AUTOCXX_DEBUG_PRELUDE
+"};
@@ -6,12 +6,15 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use syn::{Type, TypePtr};
+use syn::{GenericArgument, PathArguments, Type, TypePtr};
 
-use crate::conversion::{
-    analysis::fun::function_wrapper::{CppConversionType, TypeConversionPolicy},
-    api::Pointerness,
-    ConvertErrorFromCpp,
+use crate::{
+    conversion::{
+        analysis::fun::function_wrapper::{CppConversionType, TypeConversionPolicy},
+        api::Pointerness,
+        ConvertErrorFromCpp,
+    },
+    types::QualifiedName,
 };
 
 use super::type_to_cpp::CppNameMap;
@@ -22,10 +25,15 @@ impl TypeConversionPolicy {
         cpp_name_map: &CppNameMap,
     ) -> Result<String, ConvertErrorFromCpp> {
         match self.cpp_conversion {
-            CppConversionType::FromUniquePtrToValue => self.unique_ptr_wrapped_type(cpp_name_map),
+            CppConversionType::FromUniquePtrToValue | CppConversionType::FromUniquePtrToRValue => {
+                self.unique_ptr_wrapped_type(cpp_name_map)
+            }
             CppConversionType::FromPtrToValue => {
                 Ok(format!("{}*", self.unwrapped_type_as_string(cpp_name_map)?))
             }
+            CppConversionType::FromRValueToUniquePtr => self.rvalue_reference_type(cpp_name_map),
+            CppConversionType::FromOptionalToUniquePtr
+            | CppConversionType::FromOptionalToPlacementPtr => self.optional_type(cpp_name_map),
             _ => self.unwrapped_type_as_string(cpp_name_map),
         }
     }
@@ -35,7 +43,25 @@ impl TypeConversionPolicy {
         cpp_name_map: &CppNameMap,
     ) -> Result<String, ConvertErrorFromCpp> {
         match self.cpp_conversion {
-            CppConversionType::FromValueToUniquePtr => self.unique_ptr_wrapped_type(cpp_name_map),
+            CppConversionType::FromValueToUniquePtr
+            | CppConversionType::FromOptionalToUniquePtr
+            | CppConversionType::FromRValueToUniquePtr
+            | CppConversionType::FromPathToUniquePtrString => {
+                self.unique_ptr_wrapped_type(cpp_name_map)
+            }
+            CppConversionType::FromUniquePtrToRValue => self.rvalue_reference_type(cpp_name_map),
+            CppConversionType::FromOptionalToPlacementPtr => Ok("bool".to_string()),
+            CppConversionType::FromTupleToPlacementPtrs | CppConversionType::FromVoidToOutArray => {
+                Ok("void".to_string())
+            }
+            CppConversionType::FromWideStringToVec(unit) => {
+                Ok(format!("rust::Vec<{}>", unit.cpp_type()))
+            }
+            CppConversionType::FromPtrToOptional => self.optional_type(cpp_name_map),
+            CppConversionType::FromVoidPtrToFunctionPointer(ref ty)
+            | CppConversionType::FromVoidPtrToIndirectPointer(ref ty) => {
+                cpp_name_map.type_to_cpp(ty)
+            }
             CppConversionType::FromReferenceToPointer => {
                 let (const_string, ty) = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr {
@@ -84,6 +110,72 @@ impl TypeConversionPolicy {
         ))
     }
 
+    fn rvalue_reference_type(
+        &self,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        Ok(format!(
+            "{}&&",
+            self.unwrapped_type_as_string(cpp_name_map)?
+        ))
+    }
+
+    /// Moves element `element` of the returned tuple, `autocxx_tuple`, into
+    /// this out parameter.
+    pub(super) fn tuple_element_placement(
+        &self,
+        element: usize,
+        var_name: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let dest_type = match self.cxxbridge_type() {
+            Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+            _ => panic!("Not a pointer"),
+        };
+        let value = format!("std::move(std::get<{element}>(autocxx_tuple))");
+        let value = match unique_ptr_payload(dest_type) {
+            Some(payload) => format!(
+                "std::make_unique<{}>({value})",
+                cpp_name_map.type_to_cpp(payload)?
+            ),
+            None => value,
+        };
+        Ok(format!(
+            "new({var_name}) {}({value})",
+            cpp_name_map.type_to_cpp(dest_type)?
+        ))
+    }
+
+    /// Stores the function with which Rust should free an array described
+    /// by an `out_array!` directive in this out parameter, if that's what
+    /// it is. A capture-free lambda converts to a plain function pointer,
+    /// so `free_with` may be anything callable with the array.
+    pub(super) fn out_array_free_function(
+        &self,
+        var_name: &str,
+        cpp_name_map: &CppNameMap,
+    ) -> Result<Option<String>, ConvertErrorFromCpp> {
+        Ok(match &self.cpp_conversion {
+            CppConversionType::ToOutArrayFreeFunction { free_with, elem } => Some(format!(
+                "*static_cast<void**>({var_name}) = reinterpret_cast<void*>(static_cast<void (*)(void*)>([](void* autocxx_array) {{ {free_with}(static_cast<{}*>(autocxx_array)); }}));",
+                cpp_name_map.type_to_cpp(elem)?
+            )),
+            _ => None,
+        })
+    }
+
+    /// The `std::optional<T>` for a conversion involving optionals.
+    fn optional_type(&self, cpp_name_map: &CppNameMap) -> Result<String, ConvertErrorFromCpp> {
+        let payload = match self.cxxbridge_type() {
+            Type::Ptr(TypePtr { elem, .. }) => elem.as_ref(),
+            ty => ty,
+        };
+        Ok(format!(
+            "std::optional<{}>",
+            cpp_name_map.type_to_cpp(payload)?
+        ))
+    }
+
     pub(super) fn cpp_conversion(
         &self,
         var_name: &str,
@@ -93,18 +185,33 @@ impl TypeConversionPolicy {
         // If is_return we want to avoid unnecessary std::moves because they
         // make RVO less effective
         Ok(match self.cpp_conversion {
-            CppConversionType::None | CppConversionType::FromReturnValueToPlacementPtr => {
-                Some(var_name.to_string())
-            }
+            CppConversionType::None
+            | CppConversionType::FromReturnValueToPlacementPtr
+            | CppConversionType::FromOptionalToUniquePtr
+            | CppConversionType::FromOptionalToPlacementPtr
+            | CppConversionType::FromTupleToPlacementPtrs
+            | CppConversionType::FromVoidToOutArray => Some(var_name.to_string()),
             CppConversionType::FromPointerToReference { .. } => Some(format!("(*{var_name})")),
             CppConversionType::Move => Some(format!("std::move({var_name})")),
-            CppConversionType::FromUniquePtrToValue | CppConversionType::FromPtrToMove => {
-                Some(format!("std::move(*{var_name})"))
+            CppConversionType::FromUniquePtrToValue
+            | CppConversionType::FromPtrToMove
+            | CppConversionType::FromUniquePtrToRValue => Some(format!("std::move(*{var_name})")),
+            // A returned value is constructed directly on the heap, rather
+            // than forwarded through std::make_unique, so that C++17's
+            // guaranteed copy elision works even for types which can be
+            // neither moved nor copied.
+            CppConversionType::FromValueToUniquePtr if is_return => {
+                let ty = self.unconverted_type(cpp_name_map)?;
+                Some(format!("std::unique_ptr<{ty}>(new {ty}({var_name}))"))
             }
+            // A by-value parameter belongs to us, so we may move from it.
             CppConversionType::FromValueToUniquePtr => Some(format!(
-                "std::make_unique<{}>({})",
+                "std::make_unique<{}>(std::move({var_name}))",
                 self.unconverted_type(cpp_name_map)?,
-                var_name
+            )),
+            CppConversionType::FromRValueToUniquePtr => Some(format!(
+                "std::make_unique<{}>(std::move({var_name}))",
+                self.unwrapped_type_as_string(cpp_name_map)?,
             )),
             CppConversionType::FromPtrToValue => {
                 let dereference = format!("*{var_name}");
@@ -114,8 +221,117 @@ impl TypeConversionPolicy {
                     format!("std::move({dereference})")
                 })
             }
-            CppConversionType::IgnoredPlacementPtrParameter => None,
+            CppConversionType::IgnoredPlacementPtrParameter
+            | CppConversionType::ToOutArrayFreeFunction { .. } => None,
             CppConversionType::FromReferenceToPointer { .. } => Some(format!("&{var_name}")),
+            CppConversionType::FromPtrToOptional => {
+                let optional_type = self.optional_type(cpp_name_map)?;
+                Some(format!(
+                    "({var_name} ? {optional_type}(*{var_name}) : {optional_type}())"
+                ))
+            }
+            CppConversionType::FromVoidPtrToFunctionPointer(ref ty) => Some(format!(
+                "reinterpret_cast<{}>({var_name})",
+                cpp_name_map.type_to_cpp(ty)?
+            )),
+            CppConversionType::FromVoidPtrToIndirectPointer(ref ty) => Some(format!(
+                "reinterpret_cast<{}>({var_name})",
+                cpp_name_map.type_to_cpp(ty)?
+            )),
+            CppConversionType::FromIndirectPointerToVoidPtr(_) => Some(format!(
+                "const_cast<void*>(static_cast<const void*>({var_name}))"
+            )),
+            CppConversionType::FromStdArrayToCArray => Some(format!(
+                "reinterpret_cast<{}>({var_name})",
+                cpp_name_map.c_array_reference_to_cpp(self.cxxbridge_type())?
+            )),
+            CppConversionType::FromStrToString => {
+                Some(format!("std::string({var_name}.data(), {var_name}.size())"))
+            }
+            CppConversionType::FromSliceToStringView => Some(format!(
+                "std::string_view(reinterpret_cast<const char*>({var_name}.data()), {var_name}.size())"
+            )),
+            // Class template argument deduction picks `std::span<const T>`
+            // or `std::span<T>` to match the slice.
+            CppConversionType::FromSliceToSpan => Some(format!(
+                "std::span({var_name}.data(), {var_name}.size())"
+            )),
+            // The elements are passed to a lambda which makes the call; see
+            // the initializer list prelude.
+            CppConversionType::FromSliceToInitializerList => {
+                Some(format!("{{{var_name}_elements...}}"))
+            }
+            CppConversionType::FromSliceToPointerAndLength => {
+                Some(format!("{var_name}.data(), {var_name}.size()"))
+            }
+            // This converts itself to whichever duration or time_point
+            // the function takes; see the chrono prelude.
+            CppConversionType::FromNanosToChrono => {
+                Some(format!("autocxx_chrono_from_nanos{{{var_name}}}"))
+            }
+            CppConversionType::FromChronoToNanos => {
+                Some(format!("autocxx_chrono_to_nanos({var_name})"))
+            }
+            CppConversionType::FromSliceToWideString {
+                ref string_type,
+                c_str,
+            } => {
+                let string = format!("{string_type}(reinterpret_cast<const {string_type}::value_type*>({var_name}.data()), {var_name}.size())");
+                // The temporary lasts until the function returns.
+                Some(if c_str {
+                    format!("{string}.c_str()")
+                } else {
+                    string
+                })
+            }
+            CppConversionType::FromWideStringToVec(unit) => {
+                let unit = unit.cpp_type();
+                Some(format!("[&] {{ auto autocxx_string = {var_name}; rust::Vec<{unit}> autocxx_units; autocxx_units.reserve(autocxx_string.size()); for (auto autocxx_unit : autocxx_string) {{ autocxx_units.push_back(static_cast<{unit}>(autocxx_unit)); }} return autocxx_units; }}()"))
+            }
+            // See the path prelude.
+            CppConversionType::FromSliceToPath => Some(format!(
+                "autocxx_path_from_bytes({var_name}.data(), {var_name}.size())"
+            )),
+            CppConversionType::FromPathToUniquePtrString => Some(format!(
+                "std::make_unique<std::string>(autocxx_path_to_bytes({var_name}))"
+            )),
+            CppConversionType::FromTupleElement { first, last } => {
+                let element = if unique_ptr_payload(self.cxxbridge_type()).is_some() {
+                    format!("std::move(*{var_name})")
+                } else {
+                    var_name.to_string()
+                };
+                Some(format!(
+                    "{}{element}{}",
+                    if first { "{" } else { "" },
+                    if last { "}" } else { "" }
+                ))
+            }
+            // std::function must be copyable, so the copies share
+            // ownership of the box.
+            CppConversionType::FromBoxToStdFunction(ref call_fn) => Some(format!(
+                "[autocxx_closure = std::make_shared<{}>(std::move({var_name}))](auto&&... autocxx_args) {{ return {call_fn}(**autocxx_closure, std::forward<decltype(autocxx_args)>(autocxx_args)...); }}",
+                self.unwrapped_type_as_string(cpp_name_map)?
+            )),
         })
     }
 }
+
+/// If this type is a `cxx::UniquePtr<T>`, returns `T`.
+fn unique_ptr_payload(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Path(typ)
+            if QualifiedName::from_type_path(typ)
+                == QualifiedName::new_from_cpp_name("cxx::UniquePtr") =>
+        {
+            match &typ.path.segments.last()?.arguments {
+                PathArguments::AngleBracketed(ab) => match ab.args.first() {
+                    Some(GenericArgument::Type(payload)) => Some(payload),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
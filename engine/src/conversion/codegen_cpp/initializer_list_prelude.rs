@@ -0,0 +1,41 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Calls `f` with the elements of a slice as separate arguments, so that
+/// it can pass them on as a braced list to a function taking a
+/// `std::initializer_list`. Standard C++ can only make an initializer list
+/// whose length is known at compile time, so we instantiate a call for
+/// each length up to `AUTOCXX_INITIALIZER_LIST_MAX_LEN`, which may be
+/// overridden when compiling the generated code. Longer slices abort.
+pub(super) static INITIALIZER_LIST_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_INITIALIZER_LIST_PRELUDE
+    #define AUTOCXX_INITIALIZER_LIST_PRELUDE
+    #ifndef AUTOCXX_INITIALIZER_LIST_MAX_LEN
+    #define AUTOCXX_INITIALIZER_LIST_MAX_LEN 16
+    #endif
+    // Mechanics to pass Rust slices as std::initializer_lists
+    template <typename T, typename F, std::size_t... I>
+    decltype(auto) autocxx_call_with_elements(rust::Slice<const T> slice, F&& f, std::index_sequence<I...>) {
+      return f(slice[I]...);
+    }
+    template <typename T, typename F>
+    [[noreturn]] auto autocxx_with_initializer_list(rust::Slice<const T> slice, F&& f, std::integral_constant<std::size_t, AUTOCXX_INITIALIZER_LIST_MAX_LEN + 1>) -> decltype(f()) {
+      std::fprintf(stderr, \"autocxx: a slice of %zu elements is too long to pass as a std::initializer_list; the limit is AUTOCXX_INITIALIZER_LIST_MAX_LEN (%d)\\n\", slice.size(), AUTOCXX_INITIALIZER_LIST_MAX_LEN);
+      std::abort();
+    }
+    template <typename T, typename F, std::size_t N = 0>
+    decltype(auto) autocxx_with_initializer_list(rust::Slice<const T> slice, F&& f, std::integral_constant<std::size_t, N> = {}) {
+      if (slice.size() == N) {
+        return autocxx_call_with_elements(slice, f, std::make_index_sequence<N>());
+      }
+      return autocxx_with_initializer_list(slice, f, std::integral_constant<std::size_t, N + 1>());
+    }
+    #endif // AUTOCXX_INITIALIZER_LIST_PRELUDE
+"};
@@ -0,0 +1,41 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Holds the position of a Rust iterator over a C++ container: the
+/// container's current and end iterators. `next` hands Rust a pointer to
+/// the current element, then advances. The end iterator may be of a
+/// different type (a sentinel), so long as it compares with the other.
+pub(super) static ITERATOR_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_ITERATOR_PRELUDE
+    #define AUTOCXX_ITERATOR_PRELUDE
+    // Mechanics to iterate over C++ containers from Rust
+    template <typename Container> class autocxx_iterator {
+    public:
+      using iterator = decltype(std::declval<const Container&>().begin());
+      using sentinel = decltype(std::declval<const Container&>().end());
+      using pointer = decltype(&*std::declval<iterator&>());
+      explicit autocxx_iterator(const Container& container)
+          : current(container.begin()), end(container.end()) {}
+      pointer next() {
+        if (current == end) {
+          return nullptr;
+        }
+        pointer item = deref();
+        advance();
+        return item;
+      }
+    private:
+      pointer deref() const { return &*current; }
+      void advance() { ++current; }
+      iterator current;
+      sentinel end;
+    };
+    #endif // AUTOCXX_ITERATOR_PRELUDE
+"};
@@ -6,32 +6,47 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod address_prelude;
+mod chrono_prelude;
+mod debug_prelude;
 mod function_wrapper_cpp;
+mod initializer_list_prelude;
+mod iterator_prelude;
 mod new_and_delete_prelude;
+mod path_prelude;
 pub(crate) mod type_to_cpp;
 
 use crate::{
-    conversion::analysis::fun::{function_wrapper::CppFunctionKind, FnAnalysis},
+    conversion::analysis::fun::{
+        function_wrapper::{CppConversionType, CppFunctionKind},
+        FnAnalysis,
+    },
     types::{make_ident, QualifiedName},
     CppCodegenOptions, CppFilePair,
 };
-use autocxx_parser::IncludeCppConfig;
+use autocxx_parser::{CppOperator, IncludeCppConfig};
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 use itertools::Itertools;
 use std::borrow::Cow;
+use syn::Type;
 use type_to_cpp::CppNameMap;
 
 use super::{
     analysis::{
         fun::{
+            find_types_without_public_destructors,
             function_wrapper::{CppFunction, CppFunctionBody},
-            FnPhase, PodAndDepAnalysis,
+            FnKind, FnPhase, PodAndDepAnalysis,
         },
-        pod::PodAnalysis,
+        iterators::IteratorShim,
+        maps::MapAccessor,
+        pod::{FieldInfo, PodAnalysis},
+        vectors::VectorAccessor,
     },
-    api::{Api, Provenance, SubclassName, TypeKind},
+    api::{Api, Layout, Provenance, SubclassName, TypeKind},
     apivec::ApiVec,
+    convert_error::{ConvertErrorWithContext, ErrorContext},
     ConvertErrorFromCpp,
 };
 
@@ -41,6 +56,12 @@ enum Header {
     CxxH,
     CxxgenH,
     NewDeletePrelude,
+    ChronoPrelude,
+    PathPrelude,
+    DebugPrelude,
+    IteratorPrelude,
+    InitializerListPrelude,
+    AddressPrelude,
 }
 
 impl Header {
@@ -64,6 +85,14 @@ impl Header {
                 format!("#include \"{prefix}{cxxgen_header_name}\"")
             }
             Header::NewDeletePrelude => new_and_delete_prelude::NEW_AND_DELETE_PRELUDE.to_string(),
+            Header::ChronoPrelude => chrono_prelude::CHRONO_PRELUDE.to_string(),
+            Header::PathPrelude => path_prelude::PATH_PRELUDE.to_string(),
+            Header::DebugPrelude => debug_prelude::DEBUG_PRELUDE.to_string(),
+            Header::IteratorPrelude => iterator_prelude::ITERATOR_PRELUDE.to_string(),
+            Header::InitializerListPrelude => {
+                initializer_list_prelude::INITIALIZER_LIST_PRELUDE.to_string()
+            }
+            Header::AddressPrelude => address_prelude::ADDRESS_PRELUDE.to_string(),
         }
     }
 
@@ -87,6 +116,9 @@ struct ExtraCpp {
     definition: Option<String>,
     headers: Vec<Header>,
     cpp_headers: Vec<Header>,
+    /// Snippets generated for the same API have the same group, and are
+    /// kept in the same file if we split the definitions across several.
+    group: usize,
 }
 
 /// Generates additional C++ glue functions needed by autocxx.
@@ -99,6 +131,7 @@ pub(crate) struct CppCodeGenerator<'a> {
     additional_functions: Vec<ExtraCpp>,
     inclusions: String,
     original_name_map: CppNameMap,
+    types_without_public_destructors: HashSet<QualifiedName>,
     config: &'a IncludeCppConfig,
     cpp_codegen_options: &'a CppCodegenOptions<'a>,
     cxxgen_header_name: &'a str,
@@ -121,6 +154,7 @@ impl<'a> CppCodeGenerator<'a> {
             additional_functions: Vec::new(),
             inclusions,
             original_name_map: CppNameMap::new_from_apis(apis),
+            types_without_public_destructors: find_types_without_public_destructors(apis),
             config,
             cpp_codegen_options,
             cxxgen_header_name,
@@ -131,6 +165,96 @@ impl<'a> CppCodeGenerator<'a> {
         Ok(gen.generate())
     }
 
+    /// Marks as ignored any functions whose C++ wrappers we'd fail to
+    /// generate, so that one function we can't handle becomes an ignored
+    /// item rather than stopping us from generating everything else.
+    pub(crate) fn ignore_functions_without_cpp(
+        apis: ApiVec<FnPhase>,
+        config: &'a IncludeCppConfig,
+        cpp_codegen_options: &'a CppCodegenOptions<'a>,
+    ) -> ApiVec<FnPhase> {
+        let gen = CppCodeGenerator {
+            additional_functions: Vec::new(),
+            inclusions: String::new(),
+            original_name_map: CppNameMap::new_from_apis(&apis),
+            types_without_public_destructors: find_types_without_public_destructors(&apis),
+            config,
+            cpp_codegen_options,
+            cxxgen_header_name: "",
+        };
+        let mut failures: HashMap<QualifiedName, ConvertErrorFromCpp> = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Function {
+                    name,
+                    analysis:
+                        FnAnalysis {
+                            cpp_wrapper: Some(cpp_wrapper),
+                            ignore_reason: Ok(_),
+                            externally_callable: true,
+                            ..
+                        },
+                    ..
+                } => gen
+                    .cpp_function_impl(cpp_wrapper)
+                    .err()
+                    .map(|err| (name.name.clone(), err)),
+                _ => None,
+            })
+            .collect();
+        let generic_types: HashSet<QualifiedName> = apis
+            .iter()
+            .filter_map(|api| match api {
+                Api::Struct {
+                    name,
+                    analysis:
+                        PodAndDepAnalysis {
+                            pod:
+                                PodAnalysis {
+                                    is_generic: true, ..
+                                },
+                            ..
+                        },
+                    ..
+                } => Some(name.name.clone()),
+                _ => None,
+            })
+            .collect();
+        apis.into_iter()
+            .map(|api| match api {
+                Api::Function {
+                    name,
+                    fun,
+                    mut analysis,
+                } => {
+                    if let Some(err) = failures.swap_remove(&name.name) {
+                        // Our name may by now be that of a wrapper function,
+                        // so report the error against the name users know.
+                        let ctx = match &analysis.kind {
+                            FnKind::TraitMethod { .. } => None,
+                            FnKind::Method { impl_for, .. }
+                                if !generic_types.contains(impl_for) =>
+                            {
+                                Some(ErrorContext::new_for_method(
+                                    impl_for.get_final_ident(),
+                                    make_ident(&analysis.rust_name),
+                                ))
+                            }
+                            _ => Some(ErrorContext::new_for_item(make_ident(&analysis.rust_name))),
+                        };
+                        analysis.ignore_reason = Err(ConvertErrorWithContext(err, ctx));
+                    }
+                    Api::Function {
+                        name,
+                        fun,
+                        analysis,
+                    }
+                }
+                _ => api,
+            })
+            .collect()
+    }
+
     // It's important to keep this in sync with Api::needs_cpp_codegen.
     fn add_needs<'b>(
         &mut self,
@@ -138,14 +262,17 @@ impl<'a> CppCodeGenerator<'a> {
     ) -> Result<(), ConvertErrorFromCpp> {
         let mut constructors_by_subclass: HashMap<SubclassName, Vec<&CppFunction>> = HashMap::new();
         let mut methods_by_subclass: HashMap<SubclassName, Vec<SubclassFunction>> = HashMap::new();
+        let mut protected_methods_by_subclass: HashMap<SubclassName, Vec<&CppFunction>> =
+            HashMap::new();
         let mut deferred_apis = Vec::new();
         for api in apis {
+            let group = self.additional_functions.len();
             match &api {
                 Api::StringConstructor { .. } => self.generate_string_constructor(),
                 Api::Function {
                     analysis:
                         FnAnalysis {
-                            cpp_wrapper: Some(cpp_wrapper),
+                            cpp_wrapper,
                             ignore_reason: Ok(_),
                             externally_callable: true,
                             ..
@@ -153,13 +280,24 @@ impl<'a> CppCodeGenerator<'a> {
                     fun,
                     ..
                 } => {
-                    if let Provenance::SynthesizedSubclassConstructor(details) = &fun.provenance {
-                        constructors_by_subclass
-                            .entry(details.subclass.clone())
-                            .or_default()
-                            .push(&details.cpp_impl);
+                    match &fun.provenance {
+                        Provenance::SynthesizedSubclassConstructor(details) => {
+                            constructors_by_subclass
+                                .entry(details.subclass.clone())
+                                .or_default()
+                                .push(&details.cpp_impl);
+                        }
+                        Provenance::SynthesizedSubclassProtectedMethod(details) => {
+                            protected_methods_by_subclass
+                                .entry(details.subclass.clone())
+                                .or_default()
+                                .push(&details.cpp_impl);
+                        }
+                        _ => {}
+                    }
+                    if let Some(cpp_wrapper) = cpp_wrapper {
+                        self.generate_cpp_function(cpp_wrapper)?
                     }
-                    self.generate_cpp_function(cpp_wrapper)?
                 }
                 Api::ConcreteType {
                     rs_definition,
@@ -176,6 +314,10 @@ impl<'a> CppCodeGenerator<'a> {
                     self.generate_typedef(api.name(), &effective_cpp_definition)
                 }
                 Api::CType { typename, .. } => self.generate_ctype_typedef(typename),
+                Api::ClosureHolder { name, .. } => self.additional_functions.push(ExtraCpp {
+                    type_definition: Some(format!("struct {};", name.name.get_final_item())),
+                    ..Default::default()
+                }),
                 Api::Subclass { .. } => deferred_apis.push(api),
                 Api::RustSubclassFn {
                     subclass, details, ..
@@ -195,32 +337,68 @@ impl<'a> CppCodeGenerator<'a> {
                             pod:
                                 PodAnalysis {
                                     kind: TypeKind::Pod,
+                                    field_info,
                                     ..
                                 },
                             ..
                         },
+                    details,
                     ..
                 } => {
-                    self.generate_pod_assertion(name.qualified_cpp_name());
+                    self.generate_pod_assertion(
+                        name.qualified_cpp_name(),
+                        field_info,
+                        details.layout.as_ref(),
+                    )?;
                 }
                 _ => panic!("Should have filtered on needs_cpp_codegen"),
             }
+            self.set_group(group, api);
         }
 
         for api in deferred_apis.into_iter() {
+            let group = self.additional_functions.len();
             match api {
                 Api::Subclass { name, superclass } => self.generate_subclass(
                     superclass,
                     name,
                     constructors_by_subclass.remove(name).unwrap_or_default(),
                     methods_by_subclass.remove(name).unwrap_or_default(),
+                    protected_methods_by_subclass
+                        .remove(name)
+                        .unwrap_or_default(),
                 )?,
                 _ => panic!("Unexpected deferred API"),
             }
+            self.set_group(group, api);
         }
         Ok(())
     }
 
+    /// Marks all the snippets generated since `group` as belonging together,
+    /// and, if asked, says which API they came from.
+    fn set_group(&mut self, group: usize, api: &Api<FnPhase>) {
+        let annotation = self
+            .cpp_codegen_options
+            .annotate_cpp
+            .then(|| format!("// autocxx: generated for {}\n", describe_api(api)));
+        for additional_function in &mut self.additional_functions[group..] {
+            additional_function.group = group;
+            if let Some(annotation) = &annotation {
+                for snippet in [
+                    &mut additional_function.type_definition,
+                    &mut additional_function.declaration,
+                    &mut additional_function.definition,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    snippet.insert_str(0, annotation);
+                }
+            }
+        }
+    }
+
     fn generate(&self) -> Option<CppFilePair> {
         if self.additional_functions.is_empty() {
             None
@@ -238,25 +416,73 @@ impl<'a> CppCodeGenerator<'a> {
                 .cpp_codegen_options
                 .autocxxgen_header_namer
                 .name_header(self.config.get_mod_name().to_string());
-            let implementation = if self
+            let implementations: Vec<_> = if self
                 .additional_functions
                 .iter()
                 .any(|x| x.definition.is_some())
             {
-                let definitions = self.concat_additional_items(|x| x.definition.as_ref());
-                let definitions =
-                    format!("#include \"{header_name}\"\n{cpp_headers}\n{definitions}");
-                log::info!("Additional C++ defs:\n{}", definitions);
-                Some(definitions.into_bytes())
+                let prefix = self
+                    .cpp_codegen_options
+                    .path_to_autocxxgen_h
+                    .as_deref()
+                    .unwrap_or_default();
+                self.shard_definitions()
+                    .into_iter()
+                    .map(|definitions| {
+                        let definitions = format!(
+                            "#include \"{prefix}{header_name}\"\n{cpp_headers}\n{definitions}"
+                        );
+                        log::info!("Additional C++ defs:\n{}", definitions);
+                        definitions.into_bytes()
+                    })
+                    .collect()
             } else {
-                None
+                Vec::new()
             };
+            let mut implementations = implementations.into_iter();
             Some(CppFilePair {
                 header: declarations.into_bytes(),
-                implementation,
+                implementation: implementations.next(),
                 header_name,
+                extra_implementations: implementations.collect(),
+            })
+        }
+    }
+
+    /// Splits the definitions into as many files as requested (though
+    /// there may be fewer), of roughly equal size, without splitting
+    /// up the definitions generated for any one API.
+    fn shard_definitions(&self) -> Vec<String> {
+        let groups: Vec<String> = self
+            .additional_functions
+            .iter()
+            .filter_map(|x| {
+                x.definition
+                    .as_ref()
+                    .map(|definition| (x.group, definition))
+            })
+            .group_by(|(group, _)| *group)
+            .into_iter()
+            .map(|(_, definitions)| {
+                definitions
+                    .map(|(_, definition)| definition.as_str())
+                    .join("\n")
             })
+            .collect();
+        let shards = self.cpp_codegen_options.cpp_shards.max(1);
+        let total_len: usize = groups.iter().map(String::len).sum();
+        let target_len = total_len.div_ceil(shards);
+        let mut result: Vec<String> = vec![String::new()];
+        for group in groups {
+            let current = result.last_mut().unwrap();
+            if !current.is_empty() && current.len() + group.len() > target_len {
+                result.push(String::new());
+            }
+            let current = result.last_mut().unwrap();
+            current.push_str(&group);
+            current.push('\n');
         }
+        result
     }
 
     fn collect_headers<F>(&self, filter: F) -> String
@@ -288,7 +514,12 @@ impl<'a> CppCodeGenerator<'a> {
         s
     }
 
-    fn generate_pod_assertion(&mut self, name: String) {
+    fn generate_pod_assertion(
+        &mut self,
+        name: String,
+        field_info: &[FieldInfo],
+        layout: Option<&Layout>,
+    ) -> Result<(), ConvertErrorFromCpp> {
         // These assertions are generated by cxx for trivial ExternTypes but
         // *only if* such types are used as trivial types in the cxx::bridge.
         // It's possible for types which we generate to be used even without
@@ -296,18 +527,32 @@ impl<'a> CppCodeGenerator<'a> {
         // can result in destructors for nested types being called multiple times
         // if we represent them as trivial types. So generate an extra
         // assertion to make sure.
-        let declaration = Some(format!("static_assert(::rust::IsRelocatable<{name}>::value, \"type {name} should be trivially move constructible and trivially destructible to be used with generate_pod! in autocxx\");"));
+        let mut declaration = format!("static_assert(::rust::IsRelocatable<{name}>::value, \"type {name} should be trivially move constructible and trivially destructible to be used with generate_pod! in autocxx\");");
+        // Array bounds are quite often macros, which might not be defined
+        // the same way when bindgen parses the header as when it's compiled,
+        // so check that each array field has the size the Rust side expects.
+        // Likewise the size and alignment which the Rust side was given.
+        if let Some(Layout { size, align, .. }) = layout {
+            declaration.push_str(&format!("\nstatic_assert(sizeof({name}) == {size} && alignof({name}) == {align}, \"type {name} should have size {size} and alignment {align}, as it had when autocxx generated bindings for it\");"));
+        }
+        for field in field_info {
+            if let (Type::Array(_), Some(field_name)) = (&field.ty, &field.accessible_name) {
+                let c_array = self.original_name_map.c_array_to_cpp(&field.ty)?;
+                declaration.push_str(&format!("\nstatic_assert(sizeof({name}::{field_name}) == sizeof({c_array}), \"field {name}::{field_name} should have the same size as {c_array}, as it had when autocxx generated bindings for it\");"));
+            }
+        }
         self.additional_functions.push(ExtraCpp {
-            declaration,
+            declaration: Some(declaration),
             headers: vec![Header::CxxH],
             ..Default::default()
-        })
+        });
+        Ok(())
     }
 
     fn generate_string_constructor(&mut self) {
         let makestring_name = self.config.get_makestring_name();
         let declaration = Some(format!("inline std::unique_ptr<std::string> {makestring_name}(::rust::Str str) {{ return std::make_unique<std::string>(std::string(str)); }}"));
-        self.additional_functions.push(ExtraCpp {
+        let mut fn_impl = ExtraCpp {
             declaration,
             headers: vec![
                 Header::System("memory"),
@@ -315,21 +560,58 @@ impl<'a> CppCodeGenerator<'a> {
                 Header::CxxH,
             ],
             ..Default::default()
-        })
+        };
+        self.annotate_free_function(&mut fn_impl);
+        self.additional_functions.push(fn_impl)
+    }
+
+    /// Puts the annotations which the user asked for (for instance, to
+    /// export the symbol from a shared library) on a free function.
+    fn annotate_free_function(&self, fn_impl: &mut ExtraCpp) {
+        if let Some(annotations) = &self.cpp_codegen_options.cxx_impl_annotations {
+            for snippet in [&mut fn_impl.declaration, &mut fn_impl.definition]
+                .into_iter()
+                .flatten()
+            {
+                snippet.insert_str(0, &format!("{annotations} "));
+            }
+        }
     }
 
     fn generate_cpp_function(&mut self, details: &CppFunction) -> Result<(), ConvertErrorFromCpp> {
-        self.additional_functions
-            .push(self.generate_cpp_function_inner(
-                details,
-                false,
-                ConversionDirection::RustCallsCpp,
-                false,
-                None,
-            )?);
+        let fn_impl = self.cpp_function_impl(details)?;
+        self.additional_functions.push(fn_impl);
         Ok(())
     }
 
+    fn cpp_function_impl(&self, details: &CppFunction) -> Result<ExtraCpp, ConvertErrorFromCpp> {
+        // Calling a Rust closure needs the declarations which cxx generates,
+        // which in turn need ours, so such functions can't be inline.
+        let calls_closures = details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_closure());
+        // If the C++ is to be split across several files, the wrappers
+        // can't be inline in the header, else they'd all be compiled as
+        // part of every file.
+        let out_of_line = calls_closures || self.cpp_codegen_options.cpp_shards > 1;
+        let mut fn_impl = self.generate_cpp_function_inner(
+            details,
+            false,
+            ConversionDirection::RustCallsCpp,
+            out_of_line,
+            None,
+        )?;
+        if calls_closures {
+            fn_impl.headers.push(Header::CxxH);
+            fn_impl.cpp_headers.push(Header::CxxgenH);
+            fn_impl.cpp_headers.push(Header::System("functional"));
+            fn_impl.cpp_headers.push(Header::System("utility"));
+        }
+        self.annotate_free_function(&mut fn_impl);
+        Ok(fn_impl)
+    }
+
     fn generate_cpp_function_inner(
         &self,
         details: &CppFunction,
@@ -419,13 +701,24 @@ impl<'a> CppCodeGenerator<'a> {
             CppFunctionKind::ConstMethod => " const",
             _ => "",
         };
-        let declaration = format!("{ret_type} {name}({args}){constness}");
+        // A noexcept function can't throw, so neither can our wrapper: saying
+        // so spares the C++ compiler from generating unwinding paths.
+        let noexcept = match conversion_direction {
+            ConversionDirection::RustCallsCpp | ConversionDirection::CppCallsCpp
+                if details.noexcept =>
+            {
+                " noexcept"
+            }
+            _ => "",
+        };
+        let declaration = format!("{ret_type} {name}({args}){constness}{noexcept}");
         let qualification = if let Some(qualification) = &details.qualification {
             format!("{}::", qualification.to_cpp_name())
         } else {
             "".to_string()
         };
-        let qualified_declaration = format!("{ret_type} {qualification}{name}({args}){constness}");
+        let qualified_declaration =
+            format!("{ret_type} {qualification}{name}({args}){constness}{noexcept}");
         // Whether there's a placement param in which to put the return value
         let placement_param = details
             .argument_conversion
@@ -448,7 +741,17 @@ impl<'a> CppCodeGenerator<'a> {
                 ConversionDirection::RustCallsCpp => {
                     conv.cpp_conversion(&get_arg_name(counter), &self.original_name_map, false)
                 }
-                ConversionDirection::CppCallsCpp => Ok(Some(get_arg_name(counter))),
+                ConversionDirection::CppCallsCpp => Ok(Some(match conv.cpp_conversion {
+                    // Received by value or by rvalue reference, and
+                    // passed on in the same way.
+                    CppConversionType::Move
+                    | CppConversionType::FromUniquePtrToValue
+                    | CppConversionType::FromPtrToValue
+                    | CppConversionType::FromUniquePtrToRValue => {
+                        format!("std::move({})", get_arg_name(counter))
+                    }
+                    _ => get_arg_name(counter),
+                })),
                 ConversionDirection::CppCallsRust => conv.inverse().cpp_conversion(
                     &get_arg_name(counter),
                     &self.original_name_map,
@@ -461,12 +764,13 @@ impl<'a> CppCodeGenerator<'a> {
         if matches!(&details.payload, CppFunctionBody::ConstructSuperclass(_)) {
             arg_list.next();
         }
+        let args: Vec<_> = arg_list.collect();
         let arg_list = if details.pass_obs_field {
             std::iter::once("*obs".to_string())
-                .chain(arg_list)
+                .chain(args.iter().cloned())
                 .join(",")
         } else {
-            arg_list.join(", ")
+            args.join(", ")
         };
         let (mut underlying_function_call, field_assignments, need_allocators) = match &details
             .payload
@@ -556,7 +860,148 @@ impl<'a> CppCodeGenerator<'a> {
                 "".to_string(),
                 true,
             ),
+            CppFunctionBody::DestroyAndFree(ty) => (
+                format!(
+                    "std::destroy_at(arg0); delete_appropriately<{}>(arg0);",
+                    self.namespaced_name(ty)
+                ),
+                "".to_string(),
+                true,
+            ),
+            CppFunctionBody::SharedPtrFromUniquePtr(ty) => (
+                format!("std::shared_ptr<{}>({arg_list})", self.namespaced_name(ty)),
+                "".to_string(),
+                false,
+            ),
+            // A `&&`-qualified method can only be called on an rvalue,
+            // which the object may not yet be, depending on how it reached us.
+            CppFunctionBody::ConsumingMethodCall(id) => (
+                format!("std::move({}).{id}({})", args[0], args[1..].join(", ")),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::Call(function) => match receiver {
+                Some(receiver) => (
+                    format!("{receiver}.{function}({arg_list})"),
+                    "".to_string(),
+                    false,
+                ),
+                None => (format!("{function}({arg_list})"), "".to_string(), false),
+            },
+            CppFunctionBody::Stringify(id) => {
+                (format!("{arg_list}->{id}()"), "".to_string(), false)
+            }
+            CppFunctionBody::StreamOut(ty) => (
+                format!(
+                    "[&] {{ std::ostringstream autocxx_stream; autocxx_debug_stream(autocxx_stream, *{arg_list}, \"{}\", 0); return autocxx_stream.str(); }}()",
+                    self.namespaced_name(ty)
+                ),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::Operator(CppOperator::Index) => {
+                let subscript = match receiver {
+                    Some(receiver) => format!("{receiver}[{}]", args[0]),
+                    None => format!("{}[{}]", args[0], args[1]),
+                };
+                (subscript, "".to_string(), false)
+            }
+            CppFunctionBody::Hash(ty) => (
+                format!("std::hash<{}>{{}}(*{arg_list})", self.namespaced_name(ty)),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::Operator(CppOperator::StreamOut) => (
+                format!(
+                    "[&] {{ std::ostringstream autocxx_stream; autocxx_stream << *{arg_list}; return autocxx_stream.str(); }}()"
+                ),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::Operator(op) => (
+                format!("*{} {} *{}", args[0], op.symbol(), args[1]),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::StaticDataGetter(cpp_name) => {
+                (cpp_name.clone(), "".to_string(), false)
+            }
+            CppFunctionBody::StaticDataSetter(cpp_name) => {
+                (format!("{cpp_name} = {arg_list}"), "".to_string(), false)
+            }
+            CppFunctionBody::MapAccessor(accessor) => {
+                let map = receiver.unwrap();
+                let call = match accessor {
+                    MapAccessor::Len => format!("{map}.size()"),
+                    MapAccessor::Contains => format!("({map}.count({}) != 0)", args[0]),
+                    MapAccessor::Get => format!(
+                        "[&] {{ auto autocxx_it = {map}.find({}); return autocxx_it == {map}.end() ? nullptr : &autocxx_it->second; }}()",
+                        args[0]
+                    ),
+                    // Avoiding insert_or_assign, which needs C++17.
+                    MapAccessor::Insert => format!(
+                        "[&](auto&& autocxx_key, auto&& autocxx_value) {{ auto autocxx_it = {map}.find(autocxx_key); if (autocxx_it == {map}.end()) {{ {map}.emplace(std::move(autocxx_key), std::move(autocxx_value)); }} else {{ autocxx_it->second = std::move(autocxx_value); }} }}({}, {})",
+                        args[0], args[1]
+                    ),
+                    MapAccessor::Keys(key) => format!(
+                        "[&] {{ std::vector<{key}> autocxx_keys; for (const auto& autocxx_entry : {map}) {{ autocxx_keys.push_back(autocxx_entry.first); }} return autocxx_keys; }}()"
+                    ),
+                };
+                (call, "".to_string(), false)
+            }
+            CppFunctionBody::ObjectAddress => (
+                format!("autocxx_object_address({})", receiver.unwrap()),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::SizeOf(ty, expected) => (
+                self.layout_query("sizeof", ty, *expected),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::AlignOf(ty, expected) => (
+                self.layout_query("alignof", ty, *expected),
+                "".to_string(),
+                false,
+            ),
+            CppFunctionBody::VectorAccessor(accessor) => {
+                let vector = receiver.unwrap();
+                let call = match accessor {
+                    VectorAccessor::Len => format!("{vector}.size()"),
+                    // Throwing from here aborts, as cxx doesn't let
+                    // exceptions escape functions which don't return a
+                    // Result.
+                    VectorAccessor::Get | VectorAccessor::GetMut => format!(
+                        "[&]() -> decltype(*{vector}.at({0})) {{ auto& autocxx_element = {vector}.at({0}); if (!autocxx_element) {{ throw std::out_of_range(\"null element\"); }} return *autocxx_element; }}()",
+                        args[0]
+                    ),
+                };
+                (call, "".to_string(), false)
+            }
+            // The return conversion wraps this in a std::make_unique, which
+            // does the work of constructing the autocxx_iterator.
+            CppFunctionBody::Iterator(IteratorShim::Begin) => {
+                (args[0].clone(), "".to_string(), false)
+            }
+            CppFunctionBody::Iterator(IteratorShim::Next) => {
+                (format!("{}.next()", args[0]), "".to_string(), false)
+            }
         };
+        // Each initializer list's elements are passed to a lambda
+        // which makes the call.
+        if matches!(conversion_direction, ConversionDirection::RustCallsCpp) {
+            for (counter, _) in details
+                .argument_conversion
+                .iter()
+                .enumerate()
+                .filter(|(_, conv)| conv.is_initializer_list())
+            {
+                let arg_name = get_arg_name(counter);
+                underlying_function_call = format!(
+                    "autocxx_with_initializer_list({arg_name}, [&](const auto&... {arg_name}_elements) -> decltype(auto) {{ return {underlying_function_call}; }})"
+                );
+            }
+        }
         if let Some(ret) = &details.return_conversion {
             let call_itself = match conversion_direction {
                 ConversionDirection::RustCallsCpp => {
@@ -573,17 +1018,65 @@ impl<'a> CppCodeGenerator<'a> {
                 "Expected some conversion type for return value which resulted in a parameter name",
             );
 
-            underlying_function_call = match placement_param {
-                Some(placement_param) => {
+            underlying_function_call = match (placement_param, &ret.cpp_conversion) {
+                (_, CppConversionType::FromTupleToPlacementPtrs) => {
+                    let elements: Result<Vec<_>, _> = details
+                        .argument_conversion
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, conv)| conv.is_placement_parameter())
+                        .enumerate()
+                        .map(|(element, (counter, conv))| {
+                            conv.tuple_element_placement(
+                                element,
+                                &get_arg_name(counter),
+                                &self.original_name_map,
+                            )
+                        })
+                        .collect();
+                    format!(
+                        "auto autocxx_tuple = {call_itself}; {}",
+                        elements?.join("; ")
+                    )
+                }
+                (Some(placement_param), CppConversionType::FromOptionalToPlacementPtr) => {
+                    let tyname = self.original_name_map.type_to_cpp(ret.cxxbridge_type())?;
+                    format!("auto autocxx_optional = {call_itself}; if (!autocxx_optional) return false; new({placement_param}) {tyname}(std::move(*autocxx_optional)); return true")
+                }
+                (None, CppConversionType::FromOptionalToUniquePtr) => {
+                    let tyname = self.original_name_map.type_to_cpp(ret.cxxbridge_type())?;
+                    format!("auto autocxx_optional = {call_itself}; return autocxx_optional ? std::make_unique<{tyname}>(std::move(*autocxx_optional)) : nullptr")
+                }
+                (Some(placement_param), _) => {
                     let tyname = self.original_name_map.type_to_cpp(ret.cxxbridge_type())?;
                     format!("new({placement_param}) {tyname}({call_itself})")
                 }
-                None => format!("return {call_itself}"),
+                (None, _) => format!("return {call_itself}"),
             };
         };
         if !underlying_function_call.is_empty() {
             underlying_function_call = format!("{underlying_function_call};");
         }
+        if matches!(conversion_direction, ConversionDirection::RustCallsCpp) {
+            for (counter, conv) in details.argument_conversion.iter().enumerate() {
+                if let Some(free_function) =
+                    conv.out_array_free_function(&get_arg_name(counter), &self.original_name_map)?
+                {
+                    underlying_function_call =
+                        format!("{free_function} {underlying_function_call}");
+                }
+            }
+        }
+        let catch_exceptions =
+            details.fallible && matches!(conversion_direction, ConversionDirection::RustCallsCpp);
+        if catch_exceptions {
+            // cxx passes only the what() string of a std::exception to Rust,
+            // and terminates for anything else. So we rethrow everything as a
+            // std::runtime_error, prefixing the type name (if we know it).
+            // The separator must match the one expected by
+            // autocxx::CppException.
+            underlying_function_call = format!("try {{ {underlying_function_call} }} catch (const std::exception& autocxx_exception) {{ throw std::runtime_error(std::string(typeid(autocxx_exception).name()) + '\\x1f' + autocxx_exception.what()); }} catch (...) {{ throw std::runtime_error(\"unknown C++ exception\"); }}");
+        }
         let field_assignments =
             if let CppFunctionBody::ConstructSuperclass(superclass_name) = &details.payload {
                 let superclass_assignments = if field_assignments.is_empty() {
@@ -608,6 +1101,94 @@ impl<'a> CppCodeGenerator<'a> {
             )
         };
         let mut headers = vec![Header::System("memory")];
+        if details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| conv.involves_optional())
+        {
+            headers.push(Header::System("optional"));
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| conv.involves_chrono())
+        {
+            headers.push(Header::System("chrono"));
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::System("limits"));
+            headers.push(Header::ChronoPrelude);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| conv.involves_path())
+        {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::System("filesystem"));
+            headers.push(Header::System("string"));
+            headers.push(Header::PathPrelude);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .chain(details.return_conversion.iter())
+            .any(|conv| conv.involves_wide_string())
+        {
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::System("string"));
+            headers.push(Header::CxxH);
+        }
+        if details
+            .argument_conversion
+            .iter()
+            .any(|conv| conv.is_initializer_list())
+        {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("cstdio"));
+            headers.push(Header::System("cstdlib"));
+            headers.push(Header::System("initializer_list"));
+            headers.push(Header::System("type_traits"));
+            headers.push(Header::System("utility"));
+            headers.push(Header::CxxH);
+            headers.push(Header::InitializerListPrelude);
+        }
+        if matches!(details.payload, CppFunctionBody::StreamOut(_)) {
+            headers.push(Header::System("ostream"));
+            headers.push(Header::System("sstream"));
+            headers.push(Header::DebugPrelude);
+        }
+        if matches!(
+            details.payload,
+            CppFunctionBody::Operator(CppOperator::StreamOut)
+        ) {
+            headers.push(Header::System("ostream"));
+            headers.push(Header::System("sstream"));
+        }
+        if matches!(details.payload, CppFunctionBody::Hash(_)) {
+            headers.push(Header::System("functional"));
+        }
+        if matches!(details.payload, CppFunctionBody::ObjectAddress) {
+            headers.push(Header::System("cstddef"));
+            headers.push(Header::System("cstdint"));
+            headers.push(Header::System("type_traits"));
+            headers.push(Header::AddressPrelude);
+        }
+        if matches!(details.payload, CppFunctionBody::VectorAccessor(_)) {
+            headers.push(Header::System("stdexcept"));
+        }
+        if matches!(details.payload, CppFunctionBody::Iterator(_)) {
+            headers.push(Header::System("utility"));
+            headers.push(Header::IteratorPrelude);
+        }
+        if catch_exceptions {
+            headers.push(Header::System("stdexcept"));
+            headers.push(Header::System("string"));
+            headers.push(Header::System("typeinfo"));
+        }
         if need_allocators {
             headers.push(Header::System("stddef.h"));
             headers.push(Header::NewDeletePrelude);
@@ -624,6 +1205,20 @@ impl<'a> CppCodeGenerator<'a> {
         self.original_name_map.map(name)
     }
 
+    /// Applies `sizeof` or `alignof` to the type. If we told Rust what to
+    /// expect, we check that here, so any disagreement between the layout
+    /// we got from bindgen and the compiler's own fails the build.
+    fn layout_query(&self, operator: &str, ty: &QualifiedName, expected: Option<usize>) -> String {
+        let query = format!("{operator}({})", self.namespaced_name(ty));
+        match expected {
+            Some(expected) => format!(
+                "[] {{ static_assert({query} == {expected}, \"autocxx: {operator}({}) differs from the value generated for Rust\"); return {query}; }}()",
+                ty.to_cpp_name()
+            ),
+            None => query,
+        }
+    }
+
     fn generate_ctype_typedef(&mut self, tn: &QualifiedName) {
         let cpp_name = tn.to_cpp_name();
         self.generate_typedef(tn, &cpp_name)
@@ -643,6 +1238,7 @@ impl<'a> CppCodeGenerator<'a> {
         subclass: &SubclassName,
         constructors: Vec<&CppFunction>,
         methods: Vec<SubclassFunction>,
+        protected_methods: Vec<&CppFunction>,
     ) -> Result<(), ConvertErrorFromCpp> {
         let holder = subclass.holder();
         self.additional_functions.push(ExtraCpp {
@@ -686,6 +1282,18 @@ impl<'a> CppCodeGenerator<'a> {
                 self.additional_functions.push(super_fn_impl);
             }
         }
+        // Public methods to call protected superclass methods from Rust.
+        for protected_method in protected_methods {
+            let mut fn_impl = self.generate_cpp_function_inner(
+                protected_method,
+                true,
+                ConversionDirection::CppCallsCpp,
+                false,
+                None,
+            )?;
+            method_decls.push(fn_impl.declaration.take().unwrap());
+            self.additional_functions.push(fn_impl);
+        }
         // In future, for each superclass..
         let super_name = superclass.get_final_item();
         method_decls.push(format!(
@@ -694,13 +1302,25 @@ impl<'a> CppCodeGenerator<'a> {
         method_decls.push(format!(
             "{super_name}& As_{super_name}_mut() {{ return *this; }}"
         ));
-        self.additional_functions.push(ExtraCpp {
-            declaration: Some(format!(
-                "inline std::unique_ptr<{}> {}_As_{}_UniquePtr(std::unique_ptr<{}> u) {{ return std::unique_ptr<{}>(u.release()); }}",
-                superclass.to_cpp_name(), subclass.cpp(), super_name, subclass.cpp(), superclass.to_cpp_name(),
-                )),
-                ..Default::default()
-        });
+        // A std::unique_ptr can't own the superclass if its destructor is protected.
+        if !self.types_without_public_destructors.contains(superclass) {
+            let mut fn_impl = ExtraCpp {
+                declaration: Some(format!(
+                    "inline std::unique_ptr<{}> {}_As_{}_UniquePtr(std::unique_ptr<{}> u) {{ return std::unique_ptr<{}>(u.release()); }}",
+                    superclass.to_cpp_name(), subclass.cpp(), super_name, subclass.cpp(), superclass.to_cpp_name(),
+                    )),
+                    ..Default::default()
+            };
+            self.annotate_free_function(&mut fn_impl);
+            self.additional_functions.push(fn_impl);
+        }
+        // Annotations go on the class as a whole rather than its members.
+        let class_annotations = self
+            .cpp_codegen_options
+            .cxx_impl_annotations
+            .as_ref()
+            .map(|annotations| format!("{annotations} "))
+            .unwrap_or_default();
         // And now constructors
         let mut constructor_decls: Vec<String> = Vec::new();
         for constructor in constructors {
@@ -715,27 +1335,70 @@ impl<'a> CppCodeGenerator<'a> {
             constructor_decls.push(decl);
             self.additional_functions.push(fn_impl);
         }
+        // Anything the user asked us to add, verbatim, using
+        // subclass_extra_cpp!. This goes last, in a public section of its
+        // own, so it can't change the access of anything we generate.
+        let (extra_decls, extra_defs): (Vec<_>, Vec<_>) = self
+            .config
+            .get_subclass_extra_cpp(subclass.0.name.get_final_item())
+            .map(|extra| (extra.declarations.as_str(), extra.definitions.as_deref()))
+            .unzip();
+        let extra_decls = if extra_decls.is_empty() {
+            String::new()
+        } else {
+            format!("public:\n{}\n", extra_decls.join("\n"))
+        };
+        let extra_defs: String = extra_defs
+            .into_iter()
+            .flatten()
+            .map(|defs| format!("{defs}\n"))
+            .collect();
         self.additional_functions.push(ExtraCpp {
             type_definition: Some(format!(
-                "class {} : public {}\n{{\npublic:\n{}\n{}\nvoid {}() const;\nprivate:rust::Box<{}> obs;\nvoid really_remove_ownership();\n\n}};",
+                "static_assert(!std::is_final<{}>::value, \"subclass!({}, {}) can't be generated because {} is marked final\");\nclass {}{} : public {}\n{{\npublic:\n{}\n{}\n~{}();\nvoid {}() const;\nprivate:rust::Box<{}> obs;\nvoid really_remove_ownership();\n\n{}}};",
+                superclass.to_cpp_name(),
+                superclass.to_cpp_name(),
+                subclass.0.name.get_final_item(),
+                superclass.to_cpp_name(),
+                class_annotations,
                 subclass.cpp(),
                 superclass.to_cpp_name(),
                 constructor_decls.join("\n"),
                 method_decls.join("\n"),
+                subclass.cpp().get_final_item(),
                 subclass.cpp_remove_ownership(),
-                holder
+                holder,
+                extra_decls
             )),
             definition: Some(format!(
-                "void {}::{}() const {{\nconst_cast<{}*>(this)->really_remove_ownership();\n}}\nvoid {}::really_remove_ownership() {{\nauto new_obs = {}(std::move(obs));\nobs = std::move(new_obs);\n}}\n",
+                "{}::~{}() {{\n{}(*obs);\n}}\nvoid {}::{}() const {{\nconst_cast<{}*>(this)->really_remove_ownership();\n}}\nvoid {}::really_remove_ownership() {{\nauto new_obs = {}(std::move(obs));\nobs = std::move(new_obs);\n}}\n{}",
+                subclass.cpp(),
+                subclass.cpp().get_final_item(),
+                subclass.peer_destroyed(),
                 subclass.cpp(),
                 subclass.cpp_remove_ownership(),
                 subclass.cpp(),
                 subclass.cpp(),
-                subclass.remove_ownership()
+                subclass.remove_ownership(),
+                extra_defs
             )),
-            cpp_headers: vec![Header::CxxgenH],
+            cpp_headers: vec![Header::CxxgenH, Header::System("type_traits")],
             ..Default::default()
         });
         Ok(())
     }
 }
+
+/// The C++ name of the API from which we generated some C++.
+fn describe_api(api: &Api<FnPhase>) -> String {
+    match api {
+        Api::Function { fun, name, .. } => match &fun.self_ty {
+            Some(self_ty) => format!("{}::{}", self_ty.to_cpp_name(), name.cpp_name()),
+            None => name.qualified_cpp_name(),
+        },
+        Api::Subclass { name, superclass } => {
+            format!("subclass {} of {}", name.cpp(), superclass.to_cpp_name())
+        }
+        _ => api.name_info().qualified_cpp_name(),
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use indoc::indoc;
+
+/// Conversions between `std::filesystem::path` and the bytes which we pass
+/// to and from Rust. Elsewhere than Windows, those are the bytes of the
+/// path's native encoding, just as Rust's `OsStr` holds on Unix. On Windows,
+/// where the native encoding is UTF-16, they're UTF-8 instead.
+/// `u8string()` returns a `std::u8string` from C++20, which is why we copy
+/// it character by character.
+pub(super) static PATH_PRELUDE: &str = indoc! {"
+    #ifndef AUTOCXX_PATH_PRELUDE
+    #define AUTOCXX_PATH_PRELUDE
+    // Mechanics to convert std::filesystem::path to and from bytes
+    inline std::filesystem::path autocxx_path_from_bytes(const uint8_t* data, size_t len) {
+      auto begin = reinterpret_cast<const char*>(data);
+    #ifdef _WIN32
+    #ifdef __cpp_char8_t
+      return std::filesystem::path(std::u8string(begin, begin + len));
+    #else
+      return std::filesystem::u8path(begin, begin + len);
+    #endif
+    #else
+      return std::filesystem::path(std::string(begin, len));
+    #endif
+    }
+    inline std::string autocxx_path_to_bytes(const std::filesystem::path& path) {
+    #ifdef _WIN32
+      auto utf8 = path.u8string();
+      return std::string(utf8.begin(), utf8.end());
+    #else
+      return path.native();
+    #endif
+    }
+    #endif // AUTOCXX_PATH_PRELUDE
+"};
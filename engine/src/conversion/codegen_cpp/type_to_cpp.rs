@@ -7,14 +7,18 @@
 // except according to those terms.
 
 use crate::{
-    conversion::{apivec::ApiVec, AnalysisPhase, ConvertErrorFromCpp},
+    conversion::{
+        apivec::ApiVec,
+        type_helpers::{extract_function_pointer_signature, extract_pinned_mutable_reference_type},
+        AnalysisPhase, ConvertErrorFromCpp,
+    },
     types::QualifiedName,
 };
 use indexmap::map::IndexMap as HashMap;
 use itertools::Itertools;
 use quote::ToTokens;
 use std::iter::once;
-use syn::{Token, Type};
+use syn::{Expr, ExprLit, Lit, ReturnType, Token, Type, TypeArray, TypeBareFn, TypeReference};
 
 /// Map from QualifiedName to original C++ name. Original C++ name does not
 /// include the namespace; this can be assumed to be the same as the namespace
@@ -71,6 +75,9 @@ impl CppNameMap {
     pub(crate) fn type_to_cpp(&self, ty: &Type) -> Result<String, ConvertErrorFromCpp> {
         match ty {
             Type::Path(typ) => {
+                if let Some(bare_fn) = extract_function_pointer_signature(typ) {
+                    return self.function_pointer_to_cpp(bare_fn);
+                }
                 // If this is a std::unique_ptr we do need to pass
                 // its argument through.
                 let qual_name = QualifiedName::from_type_path(typ);
@@ -107,19 +114,44 @@ impl CppNameMap {
             }
             Type::Reference(typr) => match &*typr.elem {
                 Type::Path(typ) if typ.path.is_ident("str") => Ok("rust::Str".into()),
+                Type::Slice(slice) => Ok(format!(
+                    "rust::Slice<{}{}>",
+                    if typr.mutability.is_some() {
+                        ""
+                    } else {
+                        "const "
+                    },
+                    self.type_to_cpp(&slice.elem)?
+                )),
                 _ => Ok(format!(
                     "{}{}&",
                     get_mut_string(&typr.mutability),
                     self.type_to_cpp(typr.elem.as_ref())?
                 )),
             },
+            // The constness of a pointer to a pointer, e.g. `char* const*`,
+            // must follow the pointee.
+            Type::Ptr(typp) if matches!(typp.elem.as_ref(), Type::Ptr(_)) => Ok(format!(
+                "{}{}*",
+                self.type_to_cpp(typp.elem.as_ref())?,
+                if typp.mutability.is_some() {
+                    ""
+                } else {
+                    " const"
+                }
+            )),
             Type::Ptr(typp) => Ok(format!(
                 "{}{}*",
                 get_mut_string(&typp.mutability),
                 self.type_to_cpp(typp.elem.as_ref())?
             )),
-            Type::Array(_)
-            | Type::BareFn(_)
+            // This is how cxx represents arrays.
+            Type::Array(arr) => Ok(format!(
+                "std::array<{}, {}>",
+                self.type_to_cpp(&arr.elem)?,
+                array_len(arr)?
+            )),
+            Type::BareFn(_)
             | Type::Group(_)
             | Type::ImplTrait(_)
             | Type::Infer(_)
@@ -138,6 +170,67 @@ impl CppNameMap {
         }
     }
 
+    /// Spell the C array equivalent to a Rust array, e.g. `c_int[2][3]`
+    /// for `[[c_int; 3]; 2]`.
+    pub(crate) fn c_array_to_cpp(&self, ty: &Type) -> Result<String, ConvertErrorFromCpp> {
+        let (elem, dimensions) = self.c_array_elem_and_dimensions(ty)?;
+        Ok(format!("{elem}{dimensions}"))
+    }
+
+    fn c_array_elem_and_dimensions(
+        &self,
+        mut ty: &Type,
+    ) -> Result<(String, String), ConvertErrorFromCpp> {
+        let mut dimensions = String::new();
+        while let Type::Array(arr) = ty {
+            dimensions.push_str(&format!("[{}]", array_len(arr)?));
+            ty = &arr.elem;
+        }
+        Ok((self.type_to_cpp(ty)?, dimensions))
+    }
+
+    /// Spell a reference to the C array equivalent to a Rust reference to
+    /// an array, e.g. `const c_int(&)[3]` for `&[c_int; 3]`.
+    pub(crate) fn c_array_reference_to_cpp(
+        &self,
+        ty: &Type,
+    ) -> Result<String, ConvertErrorFromCpp> {
+        let (const_string, arr) = match ty {
+            Type::Reference(TypeReference {
+                mutability, elem, ..
+            }) => (get_mut_string(mutability), elem.as_ref()),
+            Type::Path(typ) => match extract_pinned_mutable_reference_type(typ) {
+                Some(arr) => ("", arr),
+                None => {
+                    return Err(ConvertErrorFromCpp::UnsupportedType(
+                        ty.to_token_stream().to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(ConvertErrorFromCpp::UnsupportedType(
+                    ty.to_token_stream().to_string(),
+                ))
+            }
+        };
+        let (elem, dimensions) = self.c_array_elem_and_dimensions(arr)?;
+        Ok(format!("{const_string}{elem}(&){dimensions}"))
+    }
+
+    /// Spell a C function pointer type, e.g. `void (*)(int)`.
+    fn function_pointer_to_cpp(&self, bare_fn: &TypeBareFn) -> Result<String, ConvertErrorFromCpp> {
+        let params: Result<Vec<_>, _> = bare_fn
+            .inputs
+            .iter()
+            .map(|input| self.type_to_cpp(&input.ty))
+            .collect();
+        let ret = match &bare_fn.output {
+            ReturnType::Type(_, ty) => self.type_to_cpp(ty)?,
+            ReturnType::Default => "void".to_string(),
+        };
+        Ok(format!("{ret} (*)({})", params?.join(", ")))
+    }
+
     /// Check an individual item in the name map. Returns a thing if
     /// it's an inner type, otherwise returns none.
     pub(crate) fn get(&self, name: &QualifiedName) -> Option<&String> {
@@ -145,6 +238,17 @@ impl CppNameMap {
     }
 }
 
+fn array_len(arr: &TypeArray) -> Result<String, ConvertErrorFromCpp> {
+    match &arr.len {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(len), ..
+        }) => Ok(len.base10_digits().to_string()),
+        _ => Err(ConvertErrorFromCpp::UnsupportedType(
+            arr.to_token_stream().to_string(),
+        )),
+    }
+}
+
 fn get_mut_string(mutability: &Option<Token![mut]>) -> &'static str {
     match mutability {
         None => "const ",
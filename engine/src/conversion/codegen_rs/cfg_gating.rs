@@ -0,0 +1,139 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers to gate generated items behind Cargo features.
+
+use quote::quote;
+use syn::{parse_quote, Attribute, ForeignItem, ImplItem, Item};
+
+/// The `#[cfg]` attribute enabling an item if any of the given
+/// features is enabled, or `None` if the item is unconditional.
+pub(super) fn cfg_attr(features: &[String]) -> Option<Attribute> {
+    match features {
+        [] => None,
+        [feature] => Some(parse_quote! { #[cfg(feature = #feature)] }),
+        _ => Some(parse_quote! { #[cfg(any(#(feature = #features),*))] }),
+    }
+}
+
+/// Combine the features for several items which are to be emitted
+/// together, e.g. within a single `impl` block. If any of them is
+/// unconditional, so is the combination.
+pub(super) fn union_of_features<'a>(
+    mut feature_lists: impl Iterator<Item = &'a [String]>,
+) -> Vec<String> {
+    let mut union = Vec::new();
+    let any_unconditional = feature_lists.any(|features| {
+        union.extend(
+            features
+                .iter()
+                .filter(|feature| !union.contains(*feature))
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        features.is_empty()
+    });
+    if any_unconditional {
+        Vec::new()
+    } else {
+        union
+    }
+}
+
+pub(super) fn add_attr_to_item(item: &mut Item, attr: &Attribute) {
+    let attrs = match item {
+        Item::Const(i) => &mut i.attrs,
+        Item::Enum(i) => &mut i.attrs,
+        Item::ExternCrate(i) => &mut i.attrs,
+        Item::Fn(i) => &mut i.attrs,
+        Item::ForeignMod(i) => &mut i.attrs,
+        Item::Impl(i) => &mut i.attrs,
+        Item::Macro(i) => &mut i.attrs,
+        Item::Mod(i) => &mut i.attrs,
+        Item::Static(i) => &mut i.attrs,
+        Item::Struct(i) => &mut i.attrs,
+        Item::Trait(i) => &mut i.attrs,
+        Item::TraitAlias(i) => &mut i.attrs,
+        Item::Type(i) => &mut i.attrs,
+        Item::Union(i) => &mut i.attrs,
+        Item::Use(i) => &mut i.attrs,
+        Item::Verbatim(ts) => {
+            *ts = quote! { #attr #ts };
+            return;
+        }
+        _ => return,
+    };
+    attrs.insert(0, attr.clone());
+}
+
+pub(super) fn add_attr_to_foreign_item(item: &mut ForeignItem, attr: &Attribute) {
+    let attrs = match item {
+        ForeignItem::Fn(i) => &mut i.attrs,
+        ForeignItem::Static(i) => &mut i.attrs,
+        ForeignItem::Type(i) => &mut i.attrs,
+        ForeignItem::Macro(i) => &mut i.attrs,
+        ForeignItem::Verbatim(ts) => {
+            *ts = quote! { #attr #ts };
+            return;
+        }
+        _ => return,
+    };
+    attrs.insert(0, attr.clone());
+}
+
+pub(super) fn add_attr_to_impl_item(item: &mut ImplItem, attr: &Attribute) {
+    let attrs = match item {
+        ImplItem::Const(i) => &mut i.attrs,
+        ImplItem::Fn(i) => &mut i.attrs,
+        ImplItem::Type(i) => &mut i.attrs,
+        ImplItem::Macro(i) => &mut i.attrs,
+        ImplItem::Verbatim(ts) => {
+            *ts = quote! { #attr #ts };
+            return;
+        }
+        _ => return,
+    };
+    attrs.insert(0, attr.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cfg_attr, union_of_features};
+    use quote::ToTokens;
+
+    #[test]
+    fn test_cfg_attr() {
+        assert!(cfg_attr(&[]).is_none());
+        assert_eq!(
+            cfg_attr(&["a".into()])
+                .unwrap()
+                .to_token_stream()
+                .to_string(),
+            "# [cfg (feature = \"a\")]"
+        );
+        assert_eq!(
+            cfg_attr(&["a".into(), "b".into()])
+                .unwrap()
+                .to_token_stream()
+                .to_string(),
+            "# [cfg (any (feature = \"a\" , feature = \"b\"))]"
+        );
+    }
+
+    #[test]
+    fn test_union_of_features() {
+        let a = vec!["a".to_string()];
+        let ab = vec!["a".to_string(), "b".to_string()];
+        let none = Vec::new();
+        assert_eq!(
+            union_of_features([a.as_slice(), ab.as_slice()].into_iter()),
+            ab
+        );
+        assert!(union_of_features([a.as_slice(), none.as_slice()].into_iter()).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use quote::quote;
+use syn::{parse_quote, Expr, Ident, ImplItem, Item, ItemEnum, ItemStruct, Type};
+
+use crate::{conversion::api::EnumAlias, types::make_ident};
+
+/// Turns a Rust enum into a newtype over its underlying integer, with an
+/// associated constant for each enumerator, including any aliases.
+/// Unlike the enum, this can represent several enumerators with the same
+/// value, and indeed any value at all. We keep the enum's other
+/// attributes (docs, derives); returns `None` if bindgen didn't tell us
+/// the underlying integer type. If the enumerators are flags, we also
+/// implement the bitwise operators.
+pub(crate) fn make_enum_constants(
+    item: &ItemEnum,
+    aliases: &[EnumAlias],
+    is_bitflags: bool,
+) -> Option<(Item, Vec<Item>)> {
+    let repr = get_repr(item)?;
+    let id = &item.ident;
+    let vis = &item.vis;
+    let attrs = item
+        .attrs
+        .iter()
+        .filter(|attr| !attr.path().is_ident("repr"));
+    let newtype: ItemStruct = parse_quote! {
+        #(#attrs)*
+        #[repr(transparent)]
+        #vis struct #id(pub #repr);
+    };
+    let mut constants: Vec<ImplItem> = Vec::new();
+    let mut previous = None;
+    for variant in &item.variants {
+        let variant_id = &variant.ident;
+        let variant_attrs = &variant.attrs;
+        // bindgen normally spells out every value, but in case it
+        // doesn't, we count on from the previous enumerator as C++ does.
+        let value: Expr = match (&variant.discriminant, &previous) {
+            (Some((_, value)), _) => value.clone(),
+            (None, Some(previous)) => parse_quote! { Self::#previous.0 + 1 },
+            (None, None) => parse_quote! { 0 },
+        };
+        constants.push(parse_quote! {
+            #(#variant_attrs)*
+            pub const #variant_id: Self = Self(#value);
+        });
+        previous = Some(variant_id);
+    }
+    for EnumAlias { name, aliased } in aliases {
+        constants.push(parse_quote! {
+            pub const #name: Self = Self::#aliased;
+        });
+    }
+    let mut impls = vec![parse_quote! {
+        impl #id {
+            #(#constants)*
+        }
+    }];
+    if is_bitflags {
+        impls.extend(make_bitflag_impls(id));
+    }
+    // The newtype can hold any value, so both conversions are infallible.
+    impls.push(parse_quote! {
+        impl ::core::convert::From<#repr> for #id {
+            fn from(value: #repr) -> Self {
+                Self(value)
+            }
+        }
+    });
+    impls.push(parse_quote! {
+        impl ::core::convert::From<#id> for #repr {
+            fn from(value: #id) -> Self {
+                value.0
+            }
+        }
+    });
+    Some((Item::Struct(newtype), impls))
+}
+
+/// Implements conversions between a Rust enum and its underlying integer.
+/// Converting an integer is fallible, since only the values of the
+/// enumerators are allowed. Returns nothing if bindgen didn't tell us the
+/// underlying integer type.
+pub(crate) fn make_enum_conversions(item: &ItemEnum, cpp_name: &str) -> Vec<Item> {
+    let repr = match get_repr(item) {
+        Some(repr) => repr,
+        None => return Vec::new(),
+    };
+    let id = &item.ident;
+    let variants = item.variants.iter().map(|variant| &variant.ident);
+    let values = variants.clone();
+    vec![
+        parse_quote! {
+            impl ::core::convert::TryFrom<#repr> for #id {
+                type Error = autocxx::InvalidEnumValue<#repr>;
+                fn try_from(value: #repr) -> ::core::result::Result<Self, Self::Error> {
+                    #(
+                        if value == Self::#values as #repr {
+                            return ::core::result::Result::Ok(Self::#variants);
+                        }
+                    )*
+                    ::core::result::Result::Err(autocxx::InvalidEnumValue::new(value, #cpp_name))
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::convert::From<#id> for #repr {
+                fn from(value: #id) -> Self {
+                    value as #repr
+                }
+            }
+        },
+    ]
+}
+
+fn get_repr(item: &ItemEnum) -> Option<Type> {
+    item.attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("repr"))
+        .and_then(|attr| attr.parse_args().ok())
+}
+
+fn make_bitflag_impls(id: &Ident) -> Vec<Item> {
+    let mut impls: Vec<Item> = [
+        (
+            "BitOr",
+            "bitor",
+            "BitOrAssign",
+            "bitor_assign",
+            quote! { | },
+        ),
+        (
+            "BitAnd",
+            "bitand",
+            "BitAndAssign",
+            "bitand_assign",
+            quote! { & },
+        ),
+        (
+            "BitXor",
+            "bitxor",
+            "BitXorAssign",
+            "bitxor_assign",
+            quote! { ^ },
+        ),
+    ]
+    .into_iter()
+    .flat_map(|(op_trait, op_fn, assign_trait, assign_fn, op)| {
+        let (op_trait, op_fn) = (make_ident(op_trait), make_ident(op_fn));
+        let (assign_trait, assign_fn) = (make_ident(assign_trait), make_ident(assign_fn));
+        [
+            parse_quote! {
+                impl ::core::ops::#op_trait for #id {
+                    type Output = Self;
+                    fn #op_fn(self, other: Self) -> Self {
+                        Self(self.0 #op other.0)
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::ops::#assign_trait for #id {
+                    fn #assign_fn(&mut self, other: Self) {
+                        self.0 = self.0 #op other.0;
+                    }
+                }
+            },
+        ]
+    })
+    .collect();
+    impls.push(parse_quote! {
+        impl ::core::ops::Not for #id {
+            type Output = Self;
+            fn not(self) -> Self {
+                Self(!self.0)
+            }
+        }
+    });
+    impls.push(parse_quote! {
+        impl #id {
+            /// Whether all the flags set in `other` are also set in `self`.
+            pub fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+            /// Whether no flags at all are set.
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+        }
+    });
+    impls
+}
@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use autocxx_parser::IncludeCppConfig;
+use autocxx_parser::{CppOperator, IncludeCppConfig};
 use indexmap::set::IndexSet as HashSet;
 use std::borrow::Cow;
 
@@ -17,22 +17,28 @@ use syn::{
     parse_quote,
     punctuated::Punctuated,
     token::{Comma, Unsafe},
-    Attribute, FnArg, ForeignItem, Ident, ImplItem, Item, ReturnType,
+    Attribute, FnArg, ForeignItem, GenericArgument, Ident, ImplItem, ImplItemFn, Item, Pat,
+    PathArguments, ReturnType, TraitItemFn, Type, TypeReference,
 };
 
 use super::{
     function_wrapper_rs::RustParamConversion,
     maybe_unsafes_to_tokens,
     unqualify::{unqualify_params, unqualify_ret_type},
-    ImplBlockDetails, ImplBlockKey, MaybeUnsafeStmt, RsCodegenResult, TraitImplBlockDetails, Use,
+    ImplBlockDetails, ImplBlockKey, MaybeUnsafeStmt, RsCodegenResult, TraitImplBlockDetails,
+    UniquePtrExtDetails, Use,
 };
 use crate::{
     conversion::{
-        analysis::fun::{
-            function_wrapper::TypeConversionPolicy, ArgumentAnalysis, FnAnalysis, FnKind,
-            MethodKind, RustRenameStrategy, TraitMethodDetails,
+        analysis::{
+            fun::{
+                function_wrapper::TypeConversionPolicy, ArgumentAnalysis, FnAnalysis, FnKind,
+                FutureWrapperAnalysis, MethodKind, RustRenameStrategy, TraitMethodDetails,
+                TraitMethodKind,
+            },
+            iterators::{get_rust_iterator_name, IteratorFunctions},
         },
-        api::{Pointerness, UnsafetyNeeded},
+        api::{Pointerness, Provenance, SpecialMemberKind, SubclassName, UnsafetyNeeded},
     },
     minisyn::minisynize_vec,
     types::{Namespace, QualifiedName},
@@ -85,6 +91,30 @@ impl UnsafetyNeeded {
     }
 }
 
+/// Notes for the documentation of a function whose parameters or return
+/// value undergo a conversion which may not preserve every value.
+fn conversion_doc_attrs(
+    param_details: &[ArgumentAnalysis],
+    ret_conversion: Option<&TypeConversionPolicy>,
+) -> Vec<Attribute> {
+    let conversions: Vec<_> = param_details
+        .iter()
+        .map(|pd| &pd.conversion)
+        .chain(ret_conversion)
+        .collect();
+    let mut notes = Vec::new();
+    if conversions.iter().any(|conv| conv.involves_chrono()) {
+        notes.push(" `std::chrono` values are converted via a count of nanoseconds. Values which don't fit (including negative durations) saturate, and panic in debug builds.");
+    }
+    if conversions.iter().any(|conv| conv.involves_path()) {
+        notes.push(" `std::filesystem::path` values are converted via their bytes. On Unix these are the native bytes, so nothing is lost. Elsewhere they are UTF-8 from `u8string()`, and any part of a Rust path which isn't valid Unicode is replaced with U+FFFD.");
+    }
+    notes
+        .into_iter()
+        .map(|note| parse_quote! { #[doc = #note] })
+        .collect()
+}
+
 pub(super) fn gen_function(
     ns: &Namespace,
     fun: FuncToConvert,
@@ -105,7 +135,23 @@ pub(super) fn gen_function(
     let params = analysis.params;
     let vis = analysis.vis;
     let kind = analysis.kind;
-    let doc_attrs = minisynize_vec(fun.doc_attrs);
+    let fallible = analysis.fallible;
+    let future_wrapper = analysis.future_wrapper;
+    let mut doc_attrs = minisynize_vec(fun.doc_attrs);
+    doc_attrs.extend(conversion_doc_attrs(
+        &param_details,
+        ret_conversion.as_ref(),
+    ));
+    // These only make sense on functions and inherent methods, not on the
+    // implementations of traits.
+    let mut lint_attrs: Vec<Attribute> = fun
+        .deprecated
+        .iter()
+        .map(|deprecation| deprecation.to_attr())
+        .collect();
+    if fun.must_use {
+        lint_attrs.push(parse_quote! { #[must_use] });
+    }
 
     let mut cpp_name_attr = Vec::new();
     let mut impl_entry = None;
@@ -122,10 +168,12 @@ pub(super) fn gen_function(
         unsafety: &analysis.requires_unsafe,
         always_unsafe_due_to_trait_definition,
         doc_attrs: &doc_attrs,
+        lint_attrs: &lint_attrs,
         non_pod_types,
         ret_type: &ret_type,
         ret_conversion: &ret_conversion,
         reference_wrappers: config.unsafe_policy.requires_cpprefs(),
+        fallible,
     };
     // In rare occasions, we might need to give an explicit lifetime.
     let (lifetime_tokens, params, ret_type) = add_explicit_lifetime_if_necessary(
@@ -135,15 +183,49 @@ pub(super) fn gen_function(
         non_pod_types,
     );
 
-    if analysis.rust_wrapper_needed {
+    // The functions with which we iterate over a container aren't for
+    // direct use: see `gen_iterator`.
+    let hidden = matches!(fun.provenance, Provenance::SynthesizedIterator { .. });
+
+    if analysis.rust_wrapper_needed && !hidden {
         match kind {
             FnKind::Method {
                 ref impl_for,
-                method_kind: MethodKind::Constructor { .. },
+                method_kind: MethodKind::Constructor { is_default },
                 ..
             } => {
                 // Constructor.
                 impl_entry = Some(fn_generator.generate_constructor_impl(impl_for));
+                if is_default {
+                    bindgen_mod_items.extend(
+                        fn_generator
+                            .generate_default_impl(impl_for, !non_pod_types.contains(impl_for)),
+                    );
+                }
+                // C++ can use non-explicit single-argument constructors for
+                // implicit conversions, so offer them as conversions in Rust.
+                if !fun.explicit
+                    && matches!(
+                        fun.provenance,
+                        Provenance::Bindgen | Provenance::SynthesizedDefaultArguments { .. }
+                    )
+                    && !matches!(
+                        fun.special_member,
+                        Some(
+                            SpecialMemberKind::CopyConstructor | SpecialMemberKind::MoveConstructor
+                        )
+                    )
+                    && !config.is_on_implicit_conversion_blocklist(&impl_for.to_cpp_name())
+                {
+                    let constructor_prefix = config
+                        .get_constructor_name(&impl_for.to_cpp_name())
+                        .unwrap_or("new");
+                    bindgen_mod_items.extend(fn_generator.generate_conversion_impl(
+                        impl_for,
+                        !non_pod_types.contains(impl_for),
+                        constructor_prefix,
+                    ));
+                }
             }
             FnKind::Method {
                 ref impl_for,
@@ -156,6 +238,58 @@ pub(super) fn gen_function(
                     impl_for,
                 ));
             }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::Debug,
+                ref impl_for,
+                ref details,
+            } => {
+                let (debug_impl, display_impl) = fn_generator.generate_debug_impls(details);
+                trait_impl_entry = Some(debug_impl);
+                // A listed operator<< takes precedence for Display.
+                if !config.has_operator(&impl_for.to_cpp_name(), CppOperator::StreamOut) {
+                    bindgen_mod_items.push(display_impl);
+                }
+            }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::Operator(CppOperator::StreamOut),
+                ref details,
+                ..
+            } => {
+                let (display_impl, to_cpp_string_impl) =
+                    fn_generator.generate_display_impls(details);
+                bindgen_mod_items.push(display_impl);
+                bindgen_mod_items.push(to_cpp_string_impl);
+            }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::Operator(CppOperator::Index),
+                ref details,
+                ..
+            } => {
+                bindgen_mod_items.push(fn_generator.generate_index_impl(details, false));
+            }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::Operator(CppOperator::Hash),
+                ref details,
+                ..
+            } => {
+                let (hash_impl, eq_impl) = fn_generator.generate_hash_impls(details);
+                bindgen_mod_items.push(hash_impl);
+                bindgen_mod_items.push(eq_impl);
+            }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::IndexMut,
+                ref details,
+                ..
+            } => {
+                bindgen_mod_items.push(fn_generator.generate_index_impl(details, true));
+            }
+            FnKind::TraitMethod {
+                kind: TraitMethodKind::Operator(op),
+                ref details,
+                ..
+            } => {
+                bindgen_mod_items.push(fn_generator.generate_operator_impl(op, details));
+            }
             FnKind::TraitMethod { ref details, .. } => {
                 trait_impl_entry = Some(fn_generator.generate_trait_impl(details));
             }
@@ -166,8 +300,20 @@ pub(super) fn gen_function(
         }
     }
 
+    let unique_ptr_ext_entry = match kind {
+        FnKind::Method {
+            ref impl_for,
+            method_kind: MethodKind::Normal(_) | MethodKind::Virtual(_) | MethodKind::PureVirtual(_),
+            ..
+        } if !hidden && config.has_unique_ptr_ext(&impl_for.to_cpp_name()) => {
+            fn_generator.generate_unique_ptr_ext(impl_for, analysis.rust_wrapper_needed)
+        }
+        _ => None,
+    };
+
     let materialization = match kind {
         FnKind::Method { .. } | FnKind::TraitMethod { .. } => None,
+        FnKind::Function if hidden => None,
         FnKind::Function => match analysis.rust_rename_strategy {
             _ if analysis.rust_wrapper_needed => {
                 Some(Use::SpecificNameFromBindgen(make_ident(rust_name).into()))
@@ -178,6 +324,13 @@ pub(super) fn gen_function(
             _ => Some(Use::UsedFromCxxBridge),
         },
     };
+    let mut materializations: Vec<_> = materialization.into_iter().collect();
+    if let Some(future_wrapper) = future_wrapper.filter(|_| !hidden) {
+        let (future_wrapper_fn, future_wrapper_name) =
+            fn_generator.generate_future_wrapper_impl(&future_wrapper);
+        bindgen_mod_items.push(future_wrapper_fn);
+        materializations.push(Use::SpecificNameFromBindgen(future_wrapper_name));
+    }
     if cxxbridge_name != cpp_call_name && !wrapper_function_needed {
         cpp_name_attr = Attribute::parse_outer
             .parse2(quote!(
@@ -196,6 +349,13 @@ pub(super) fn gen_function(
     // which the user has declared.
     let params = unqualify_params(params);
     let ret_type = unqualify_ret_type(ret_type.into_owned());
+    // cxx converts any C++ exception into an Err so long as the function
+    // returns a Result.
+    let ret_type = if fallible {
+        wrap_in_result(&ret_type, None)
+    } else {
+        ret_type
+    };
     // And we need to make an attribute for the namespace that the function
     // itself is in.
     let namespace_attr = if ns.is_empty() || wrapper_function_needed {
@@ -214,6 +374,7 @@ pub(super) fn gen_function(
         #(#namespace_attr)*
         #(#cpp_name_attr)*
         #(#doc_attrs)*
+        #(#lint_attrs)*
         #vis #bridge_unsafety fn #cxxbridge_name #lifetime_tokens ( #params ) #ret_type;
     ));
     RsCodegenResult {
@@ -221,11 +382,107 @@ pub(super) fn gen_function(
         bindgen_mod_items,
         impl_entry,
         trait_impl_entry,
-        materializations: materialization.into_iter().collect(),
+        unique_ptr_ext_entry,
+        materializations,
         ..Default::default()
     }
 }
 
+/// Generate the Rust side of iterating over a C++ container: a type which
+/// owns the C++ `autocxx_iterator` and implements `Iterator`, an `iter`
+/// method on the container to make one, and `IntoIterator` for a
+/// reference to the container.
+pub(super) fn gen_iterator(functions: IteratorFunctions) -> (QualifiedName, RsCodegenResult) {
+    let name = get_rust_iterator_name(functions.container);
+    let iterator_ty = name.get_final_ident();
+    let container_ty = functions.container.get_final_ident();
+    let state_ty = match &*functions.begin.ret_type {
+        ReturnType::Type(_, ty) => ty.as_ref(),
+        ReturnType::Default => panic!("iterator creation function should return the iterator"),
+    };
+    let element_ty = match &*functions.next.ret_type {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            syn::Type::Ptr(ptr) => ptr.elem.as_ref(),
+            _ => panic!("iterator advancing function should return a pointer"),
+        },
+        ReturnType::Default => panic!("iterator advancing function should return a pointer"),
+    };
+    let call_within_unsafe_if_needed =
+        |analysis: &FnAnalysis, call: TokenStream| match analysis.requires_unsafe {
+            UnsafetyNeeded::None => call,
+            _ => quote! { unsafe { #call } },
+        };
+    let begin_name = &functions.begin.cxxbridge_name;
+    let begin_call =
+        call_within_unsafe_if_needed(functions.begin, quote! { cxxbridge::#begin_name(self) });
+    let next_name = &functions.next.cxxbridge_name;
+    let next_call = call_within_unsafe_if_needed(
+        functions.next,
+        quote! { cxxbridge::#next_name(self.state.pin_mut()) },
+    );
+    let (item_ty, item) = if functions.yields_values {
+        (quote! { #element_ty }, quote! { *item })
+    } else {
+        (quote! { &'a #element_ty }, quote! { &*item })
+    };
+    let doc = format!("An iterator over a [`{container_ty}`], made by [`{container_ty}::iter`].");
+    let bindgen_mod_items = vec![
+        parse_quote! {
+            #[doc = #doc]
+            pub struct #iterator_ty<'a> {
+                state: #state_ty,
+                container: ::core::marker::PhantomData<&'a #container_ty>,
+            }
+        },
+        parse_quote! {
+            impl<'a> Iterator for #iterator_ty<'a> {
+                type Item = #item_ty;
+                fn next(&mut self) -> Option<Self::Item> {
+                    let item = #next_call;
+                    if item.is_null() {
+                        None
+                    } else {
+                        Some(unsafe { #item })
+                    }
+                }
+            }
+        },
+        parse_quote! {
+            impl<'a> IntoIterator for &'a #container_ty {
+                type Item = #item_ty;
+                type IntoIter = #iterator_ty<'a>;
+                fn into_iter(self) -> Self::IntoIter {
+                    self.iter()
+                }
+            }
+        },
+    ];
+    let impl_entry = Box::new(ImplBlockDetails {
+        item: ImplItem::Fn(parse_quote! {
+            /// Returns an iterator over the elements from `begin()` to `end()`.
+            pub fn iter(&self) -> #iterator_ty<'_> {
+                #iterator_ty {
+                    state: #begin_call,
+                    container: ::core::marker::PhantomData,
+                }
+            }
+        }),
+        ty: ImplBlockKey {
+            ty: parse_quote! { #container_ty },
+            lifetime: None,
+        },
+    });
+    (
+        name,
+        RsCodegenResult {
+            bindgen_mod_items,
+            impl_entry: Some(impl_entry),
+            materializations: vec![Use::UsedFromBindgen],
+            ..Default::default()
+        },
+    )
+}
+
 /// Knows how to generate a given function.
 #[derive(Clone)]
 struct FnGenerator<'a> {
@@ -237,8 +494,10 @@ struct FnGenerator<'a> {
     unsafety: &'a UnsafetyNeeded,
     always_unsafe_due_to_trait_definition: bool,
     doc_attrs: &'a Vec<Attribute>,
+    lint_attrs: &'a Vec<Attribute>,
     non_pod_types: &'a HashSet<QualifiedName>,
     reference_wrappers: bool,
+    fallible: bool,
 }
 
 impl<'a> FnGenerator<'a> {
@@ -293,11 +552,16 @@ impl<'a> FnGenerator<'a> {
                 }
                 RustParamConversion::ReturnValue { ty } => {
                     ptr_arg_name = Some(pd.name.to_token_stream());
-                    ret_type = Cow::Owned(parse_quote! {
-                        -> impl autocxx::moveit::new::New<Output = #ty>
-                    });
+                    ret_type = Cow::Owned(self.placement_ret_type(ty));
                     arg_list.push(pd.name.to_token_stream());
                 }
+                RustParamConversion::OutParam {
+                    conversion,
+                    local_variables: mut these_local_variables,
+                } => {
+                    arg_list.push(conversion);
+                    local_variables.append(&mut these_local_variables);
+                }
             }
         }
         if let Some(parameter_reordering) = &parameter_reordering {
@@ -311,28 +575,43 @@ impl<'a> FnGenerator<'a> {
         );
 
         let cxxbridge_name = self.cxxbridge_name;
+        let call = quote! {
+            cxxbridge::#cxxbridge_name ( #(#arg_list),* )
+        };
+        // For fallible functions, we need to convert the cxx::Exception.
+        // If there's a return value conversion, we instead propagate any
+        // error straight from the call, so the conversion works as usual,
+        // and wrap the converted value in Ok later.
+        let return_value_converted = matches!(
+            self.ret_conversion,
+            Some(ret_conversion) if ret_conversion.rust_work_needed()
+        );
+        let ok_needed = self.fallible && return_value_converted;
+        let call = if ok_needed {
+            quote! { #call? }
+        } else if self.fallible {
+            quote! { #call.map_err(autocxx::CppException::from) }
+        } else {
+            call
+        };
         let call_body = MaybeUnsafeStmt::maybe_unsafe(
-            quote! {
-                cxxbridge::#cxxbridge_name ( #(#arg_list),* )
-            },
+            call,
             any_conversion_requires_unsafe || matches!(self.unsafety, UnsafetyNeeded::JustBridge),
         );
         let context_is_unsafe = matches!(self.unsafety, UnsafetyNeeded::Always)
             || self.always_unsafe_due_to_trait_definition;
-        let (call_body, ret_type) = match self.ret_conversion {
+        let (call_body, mut ret_type) = match self.ret_conversion {
             Some(ret_conversion) if ret_conversion.rust_work_needed() => {
-                // There's a potential lurking bug below. If the return type conversion requires
-                // unsafe, then we'll end up doing something like
+                // If the return type conversion requires unsafe, we'd otherwise end up
+                // doing something like
                 //   unsafe { do_return_conversion( unsafe { call_body() })}
-                // and the generated code will get warnings about nested unsafe blocks.
-                // That's because we convert the call body to tokens in the following
-                // line without considering the fact it's embedded in another expression.
-                // At the moment this is OK because no return type conversions require
-                // unsafe, but if this happens in future, we should do:
-                //   let temp_ret_val = unsafe { call_body() };
-                //   do_return_conversion(temp_ret_val)
-                // by returning a vector of MaybeUnsafes within call_body.
-                let expr = maybe_unsafes_to_tokens(vec![call_body], context_is_unsafe);
+                // and the generated code would get warnings about nested unsafe blocks.
+                // So in that case, we generate the call body as if it's already
+                // in an unsafe context, since it will be.
+                let expr = maybe_unsafes_to_tokens(
+                    vec![call_body],
+                    context_is_unsafe || ret_conversion.rust_return_conversion_requires_unsafe(),
+                );
                 let conv =
                     ret_conversion.rust_conversion(parse_quote! { #expr }, &mut variable_counter);
                 let (conversion, requires_unsafe, ty) = match conv {
@@ -369,14 +648,34 @@ impl<'a> FnGenerator<'a> {
             ));
             closure_stmts.push(call_body);
             let closure_stmts = maybe_unsafes_to_tokens(closure_stmts, true);
-            vec![MaybeUnsafeStmt::needs_unsafe(parse_quote! {
-                autocxx::moveit::new::by_raw(move |#ptr_arg_name| {
-                    #closure_stmts
-                })
+            vec![MaybeUnsafeStmt::needs_unsafe(if self.fallible {
+                let ok = ok_needed.then(|| quote! { ; Ok(()) });
+                quote! {
+                    autocxx::moveit::new::try_by_raw(move |#ptr_arg_name| {
+                        #closure_stmts #ok
+                    })
+                }
+            } else {
+                quote! {
+                    autocxx::moveit::new::by_raw(move |#ptr_arg_name| {
+                        #closure_stmts
+                    })
+                }
             })]
         } else {
             let mut call_stmts = local_variables;
-            call_stmts.push(call_body);
+            if ok_needed {
+                let call_body = maybe_unsafes_to_tokens(vec![call_body], context_is_unsafe);
+                call_stmts.push(MaybeUnsafeStmt::new(quote! { Ok(#call_body) }));
+            } else {
+                call_stmts.push(call_body);
+            }
+            if self.fallible {
+                ret_type = Cow::Owned(wrap_in_result(
+                    &ret_type,
+                    Some(quote! { autocxx::CppException }),
+                ));
+            }
             call_stmts
         };
         let call_body = maybe_unsafes_to_tokens(call_stmts, context_is_unsafe);
@@ -394,6 +693,7 @@ impl<'a> FnGenerator<'a> {
         let rust_name = make_ident(self.rust_name);
         let unsafety = self.unsafety.wrapper_token();
         let doc_attrs = self.doc_attrs;
+        let lint_attrs = self.lint_attrs;
         let receiver_pointerness = self
             .param_details
             .iter()
@@ -422,6 +722,7 @@ impl<'a> FnGenerator<'a> {
         Box::new(ImplBlockDetails {
             item: ImplItem::Fn(parse_quote! {
                 #(#doc_attrs)*
+                #(#lint_attrs)*
                 pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
                     #call_body
                 }
@@ -430,6 +731,80 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// If this method takes `self: Pin<&mut Self>`, generate a declaration
+    /// of it for the type's `unique_ptr_ext!` trait, and an implementation
+    /// for `UniquePtr` which forwards to it through `pin_mut`.
+    /// `via_wrapper` says whether callers use our Rust wrapper function,
+    /// or the cxx::bridge function directly.
+    fn generate_unique_ptr_ext(
+        &self,
+        impl_for: &QualifiedName,
+        via_wrapper: bool,
+    ) -> Option<Box<UniquePtrExtDetails>> {
+        let (lifetime_tokens, wrapper_params, ret_type, _) = self.common_parts(false, &None, None);
+        let (method_name, unsafety): (Ident, _) = if via_wrapper {
+            (
+                make_ident(self.rust_name).into(),
+                self.unsafety.wrapper_token(),
+            )
+        } else {
+            (self.cxxbridge_name.clone(), self.unsafety.bridge_token())
+        };
+        let mut params = wrapper_params.into_iter();
+        let receiver_lifetime = match params.next()? {
+            FnArg::Receiver(receiver) => match receiver.ty.as_ref() {
+                Type::Path(tp) => {
+                    let seg = tp.path.segments.last()?;
+                    match &seg.arguments {
+                        PathArguments::AngleBracketed(args) if seg.ident == "Pin" => {
+                            match args.args.first()? {
+                                GenericArgument::Type(Type::Reference(TypeReference {
+                                    lifetime,
+                                    mutability: Some(_),
+                                    ..
+                                })) => lifetime.clone(),
+                                _ => return None,
+                            }
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            },
+            FnArg::Typed(_) => return None,
+        };
+        let mut param_names = Vec::new();
+        let mut other_params: Punctuated<FnArg, Comma> = Punctuated::new();
+        for param in params {
+            match param {
+                FnArg::Typed(pt) => match pt.pat.as_ref() {
+                    Pat::Ident(id) => {
+                        let name = &id.ident;
+                        let ty = &pt.ty;
+                        other_params.push(parse_quote! { #name: #ty });
+                        param_names.push(name.clone());
+                    }
+                    _ => return None,
+                },
+                FnArg::Receiver(_) => return None,
+            }
+        }
+        let doc_attrs = self.doc_attrs;
+        let decl: TraitItemFn = parse_quote! {
+            #(#doc_attrs)*
+            #unsafety fn #method_name #lifetime_tokens (&#receiver_lifetime mut self, #other_params) #ret_type;
+        };
+        let imp: ImplItemFn = parse_quote! {
+            #unsafety fn #method_name #lifetime_tokens (&#receiver_lifetime mut self, #other_params) #ret_type {
+                self.pin_mut().#method_name(#(#param_names),*)
+            }
+        };
+        Some(Box::new(UniquePtrExtDetails {
+            ty: impl_for.get_final_ident().into(),
+            method: Some((decl, imp)),
+        }))
+    }
+
     /// Generate an 'impl Trait for Type { methods-go-here }' in its entrety.
     fn generate_trait_impl(&self, details: &TraitMethodDetails) -> Box<TraitImplBlockDetails> {
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
@@ -447,22 +822,201 @@ impl<'a> FnGenerator<'a> {
         Box::new(TraitImplBlockDetails { item, key })
     }
 
+    /// Generate an implementation of `Debug` which calls our stringification
+    /// function, plus an implementation of `Display` which does the same.
+    fn generate_debug_impls(
+        &self,
+        details: &TraitMethodDetails,
+    ) -> (Box<TraitImplBlockDetails>, Item) {
+        let cxxbridge_name = self.cxxbridge_name;
+        let method_name = &details.method_name;
+        let item = parse_quote! {
+            fn #method_name(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                let stringified = unsafe { cxxbridge::#cxxbridge_name(self) };
+                f.write_str(&stringified.to_string_lossy())
+            }
+        };
+        let ty = &details.trt.ty;
+        let display_impl = Item::Impl(parse_quote! {
+            impl ::core::fmt::Display for #ty {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    ::core::fmt::Debug::fmt(self, f)
+                }
+            }
+        });
+        (
+            Box::new(TraitImplBlockDetails {
+                item,
+                key: details.trt.clone(),
+            }),
+            display_impl,
+        )
+    }
+
+    /// Generate an implementation of `Display` which writes whatever the
+    /// type's `operator<<` streams out, and a `to_cpp_string` method which
+    /// returns that as a C++ string.
+    fn generate_display_impls(&self, details: &TraitMethodDetails) -> (Item, Item) {
+        let cxxbridge_name = self.cxxbridge_name;
+        let method_name = &details.method_name;
+        let ty = &details.trt.ty;
+        let trt = &details.trt.trait_signature;
+        (
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        let stringified = unsafe { cxxbridge::#cxxbridge_name(self) };
+                        f.write_str(&stringified.to_string_lossy())
+                    }
+                }
+            },
+            parse_quote! {
+                impl #ty {
+                    /// Returns what `operator<<` writes to a `std::ostream`.
+                    pub fn to_cpp_string(&self) -> cxx::UniquePtr<cxx::CxxString> {
+                        unsafe { cxxbridge::#cxxbridge_name(self) }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Generate an implementation of the Rust trait corresponding to a C++
+    /// operator, calling our function which applies that operator. This is
+    /// a whole `impl` block, since some of these traits need an associated
+    /// `Output` type as well as the method.
+    fn generate_operator_impl(&self, op: CppOperator, details: &TraitMethodDetails) -> Item {
+        let cxxbridge_name = self.cxxbridge_name;
+        let method_name = &details.method_name;
+        let ty = &details.trt.ty;
+        let trt = &details.trt.trait_signature;
+        Item::Impl(if op == CppOperator::Eq {
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name(&self, other: &Self) -> bool {
+                        unsafe { cxxbridge::#cxxbridge_name(self, other) }
+                    }
+                }
+            }
+        } else if op == CppOperator::Lt {
+            // C++ types commonly define just operator<, so we derive the
+            // other orderings from it and from operator==.
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                        if unsafe { cxxbridge::#cxxbridge_name(self, other) } {
+                            Some(::core::cmp::Ordering::Less)
+                        } else if unsafe { cxxbridge::#cxxbridge_name(other, self) } {
+                            Some(::core::cmp::Ordering::Greater)
+                        } else if self == other {
+                            Some(::core::cmp::Ordering::Equal)
+                        } else {
+                            None
+                        }
+                    }
+                }
+            }
+        } else if op.is_assignment() {
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name(&mut self, rhs: Self) {
+                        unsafe { cxxbridge::#cxxbridge_name(self, &rhs) }
+                    }
+                }
+            }
+        } else {
+            parse_quote! {
+                impl #trt for #ty {
+                    type Output = #ty;
+                    fn #method_name(self, rhs: Self) -> Self::Output {
+                        unsafe { cxxbridge::#cxxbridge_name(&self, &rhs) }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Generate an implementation of `Hash` which feeds the result of the
+    /// type's `std::hash` into the `Hasher`. Anything hashable in C++ must
+    /// have an `operator==` which is an equivalence relation, and the
+    /// user has to have listed that, so also implement `Eq`.
+    fn generate_hash_impls(&self, details: &TraitMethodDetails) -> (Item, Item) {
+        let cxxbridge_name = self.cxxbridge_name;
+        let method_name = &details.method_name;
+        let ty = &details.trt.ty;
+        let trt = &details.trt.trait_signature;
+        (
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                        state.write_usize(unsafe { cxxbridge::#cxxbridge_name(self) })
+                    }
+                }
+            },
+            parse_quote! {
+                impl ::core::cmp::Eq for #ty {}
+            },
+        )
+    }
+
+    /// Generate an implementation of `Index` or `IndexMut` which calls our
+    /// function applying `operator[]`. The trait's `Output` is whatever
+    /// that function returns a reference to.
+    fn generate_index_impl(&self, details: &TraitMethodDetails, mutable: bool) -> Item {
+        let cxxbridge_name = self.cxxbridge_name;
+        let method_name = &details.method_name;
+        let ty = &details.trt.ty;
+        let trt = &details.trt.trait_signature;
+        let call = quote! { cxxbridge::#cxxbridge_name(self, index) };
+        let call = match self.unsafety {
+            UnsafetyNeeded::None => call,
+            _ => quote! { unsafe { #call } },
+        };
+        if mutable {
+            parse_quote! {
+                impl #trt for #ty {
+                    fn #method_name(&mut self, index: usize) -> &mut Self::Output {
+                        #call
+                    }
+                }
+            }
+        } else {
+            let output = match self.ret_type {
+                ReturnType::Type(_, ty) => match ty.as_ref() {
+                    syn::Type::Reference(reference) => &reference.elem,
+                    _ => panic!("operator[] should return a reference"),
+                },
+                ReturnType::Default => panic!("operator[] should return a reference"),
+            };
+            parse_quote! {
+                impl #trt for #ty {
+                    type Output = #output;
+                    fn #method_name(&self, index: usize) -> &Self::Output {
+                        #call
+                    }
+                }
+            }
+        }
+    }
+
     /// Generate a 'impl Type { methods-go-here }' item which is a constructor
     /// for use with moveit traits.
     fn generate_constructor_impl(
         &self,
         impl_block_type_name: &QualifiedName,
     ) -> Box<ImplBlockDetails> {
-        let ret_type: ReturnType = parse_quote! { -> impl autocxx::moveit::new::New<Output=Self> };
+        let ret_type = self.placement_ret_type(quote! { Self });
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
             self.common_parts(true, &None, Some(ret_type));
         let rust_name = make_ident(self.rust_name);
         let doc_attrs = self.doc_attrs;
+        let lint_attrs = self.lint_attrs;
         let unsafety = self.unsafety.wrapper_token();
         let ty = impl_block_type_name.get_final_ident();
         let ty = parse_quote! { #ty };
         let stuff = quote! {
                 #(#doc_attrs)*
+                #(#lint_attrs)*
                 pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
                     #call_body
                 }
@@ -473,21 +1027,217 @@ impl<'a> FnGenerator<'a> {
         })
     }
 
+    /// Generate a conversion from the single parameter of a constructor:
+    /// an `impl From` for POD types, or else a `from` function which
+    /// returns a `UniquePtr`. Returns `None` if this constructor isn't
+    /// suitable. `constructor_prefix` is the name of the type's constructors
+    /// without any suffix distinguishing overloads, normally `new`.
+    fn generate_conversion_impl(
+        &self,
+        impl_block_type_name: &QualifiedName,
+        is_pod: bool,
+        constructor_prefix: &str,
+    ) -> Option<Item> {
+        if self.fallible {
+            return None;
+        }
+        let (lifetime_tokens, wrapper_params, _, _) = self.common_parts(true, &None, None);
+        if wrapper_params.len() != 1 {
+            return None;
+        }
+        let (arg_name, arg_ty) = match wrapper_params.first() {
+            Some(FnArg::Typed(pt)) => (&pt.pat, &pt.ty),
+            _ => return None,
+        };
+        let constructor_name = make_ident(self.rust_name);
+        let ty = impl_block_type_name.get_final_ident();
+        if is_pod {
+            // From::from can't be unsafe, nor can it have lifetimes other
+            // than those in the trait parameters, so stick to simple types.
+            if self.unsafety.wrapper_token().is_some()
+                || lifetime_tokens.is_some()
+                || self.param_details.iter().any(|pd| pd.has_lifetime)
+                || !matches!(arg_ty.as_ref(), syn::Type::Path(_))
+            {
+                return None;
+            }
+            Some(parse_quote! {
+                impl From<#arg_ty> for #ty {
+                    fn from(#arg_name: #arg_ty) -> Self {
+                        let mut this = ::core::mem::MaybeUninit::uninit();
+                        unsafe {
+                            autocxx::moveit::new::New::new(
+                                Self::#constructor_name(#arg_name),
+                                ::core::pin::Pin::new_unchecked(&mut this),
+                            );
+                            this.assume_init()
+                        }
+                    }
+                }
+            })
+        } else {
+            let rust_name = make_ident(format!(
+                "from{}",
+                self.rust_name
+                    .strip_prefix(constructor_prefix)
+                    .unwrap_or_default()
+            ));
+            let unsafety = self.unsafety.wrapper_token();
+            let doc =
+                format!("Constructs using [`Self::{constructor_name}`] within a `UniquePtr`.");
+            // If the constructor is unsafe, so is this function, so there's
+            // no need for an unsafe block.
+            Some(parse_quote! {
+                impl #ty {
+                    #[doc = #doc]
+                    #[allow(clippy::should_implement_trait)]
+                    pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) -> cxx::UniquePtr<Self> {
+                        autocxx::WithinUniquePtr::within_unique_ptr(Self::#constructor_name(#arg_name))
+                    }
+                }
+            })
+        }
+    }
+
+    /// Generate a way to default-construct the type in one call: an
+    /// `impl Default` for POD types, or else a `default_unique_ptr`
+    /// function. Returns `None` if this constructor isn't suitable.
+    fn generate_default_impl(
+        &self,
+        impl_block_type_name: &QualifiedName,
+        is_pod: bool,
+    ) -> Option<Item> {
+        if self.fallible {
+            return None;
+        }
+        let (lifetime_tokens, wrapper_params, _, _) = self.common_parts(true, &None, None);
+        if !wrapper_params.is_empty() || lifetime_tokens.is_some() {
+            return None;
+        }
+        let constructor_name = make_ident(self.rust_name);
+        let ty = impl_block_type_name.get_final_ident();
+        if is_pod {
+            // Default::default can't be unsafe.
+            if self.unsafety.wrapper_token().is_some() {
+                return None;
+            }
+            Some(parse_quote! {
+                impl Default for #ty {
+                    fn default() -> Self {
+                        let mut this = ::core::mem::MaybeUninit::uninit();
+                        unsafe {
+                            autocxx::moveit::new::New::new(
+                                Self::#constructor_name(),
+                                ::core::pin::Pin::new_unchecked(&mut this),
+                            );
+                            this.assume_init()
+                        }
+                    }
+                }
+            })
+        } else {
+            let unsafety = self.unsafety.wrapper_token();
+            let doc =
+                format!("Constructs using [`Self::{constructor_name}`] within a `UniquePtr`.");
+            Some(parse_quote! {
+                impl #ty {
+                    #[doc = #doc]
+                    pub #unsafety fn default_unique_ptr() -> cxx::UniquePtr<Self> {
+                        autocxx::WithinUniquePtr::within_unique_ptr(Self::#constructor_name())
+                    }
+                }
+            })
+        }
+    }
+
     /// Generate a function call wrapper
     fn generate_function_impl(&self) -> Item {
         let (lifetime_tokens, wrapper_params, ret_type, call_body) =
             self.common_parts(false, &None, None);
         let rust_name = make_ident(self.rust_name);
         let doc_attrs = self.doc_attrs;
+        let lint_attrs = self.lint_attrs;
         let unsafety = self.unsafety.wrapper_token();
         Item::Fn(parse_quote! {
             #(#doc_attrs)*
+            #(#lint_attrs)*
             pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) #ret_type {
                 #call_body
             }
         })
     }
 
+    /// Generate a wrapper which passes a callback object of its own in
+    /// place of the callback parameter, and returns a future which that
+    /// object resolves when C++ calls its completion method.
+    fn generate_future_wrapper_impl(
+        &self,
+        future_wrapper: &FutureWrapperAnalysis,
+    ) -> (Item, Ident) {
+        let (lifetime_tokens, wrapper_params, _, call_body) = self.common_parts(false, &None, None);
+        let wrapper_params: Punctuated<FnArg, Comma> = wrapper_params
+            .into_iter()
+            .filter(|param| {
+                !matches!(param, FnArg::Typed(pt) if future_wrapper.is_callback_param(&pt.pat))
+            })
+            .collect();
+        let subclass = SubclassName::new(future_wrapper.details.subclass().into());
+        let subclass_id = subclass.id();
+        let output = subclass.future_output();
+        let callback_param = &future_wrapper.details.callback_param;
+        let completion_ty = quote! { autocxx::completion::Completion<root::#output> };
+        // If the call throws, we don't expect C++ to call back, so the
+        // callback object is leaked.
+        let (ret_type, call_and_return) = if self.fallible {
+            (
+                quote! { ::core::result::Result<#completion_ty, autocxx::CppException> },
+                quote! {
+                    let result = unsafe { #call_body };
+                    result.map(|_| completion)
+                },
+            )
+        } else {
+            (
+                completion_ty,
+                quote! {
+                    unsafe { #call_body };
+                    completion
+                },
+            )
+        };
+        let rust_name = make_ident(format!("{}_async", self.rust_name));
+        let doc = format!(
+            " Like `{}`, but returns a future which resolves when C++ calls `{}` on the `{}` callback.",
+            self.rust_name, future_wrapper.details.method, callback_param
+        );
+        let lint_attrs = self.lint_attrs;
+        let unsafety = future_wrapper.requires_unsafe.wrapper_token();
+        let item = Item::Fn(parse_quote! {
+            #[doc = #doc]
+            #(#lint_attrs)*
+            #[allow(unused_unsafe)]
+            pub #unsafety fn #rust_name #lifetime_tokens ( #wrapper_params ) -> #ret_type {
+                let (#callback_param, completion) = root::#subclass_id::start();
+                #call_and_return
+            }
+        });
+        (item, rust_name.into())
+    }
+
+    /// The return type for a function which constructs its result into
+    /// memory provided by the caller.
+    fn placement_ret_type(&self, ty: impl ToTokens) -> ReturnType {
+        if self.fallible {
+            parse_quote! {
+                -> impl autocxx::moveit::new::TryNew<Output = #ty, Error = autocxx::CppException>
+            }
+        } else {
+            parse_quote! {
+                -> impl autocxx::moveit::new::New<Output = #ty>
+            }
+        }
+    }
+
     fn reorder_parameters(
         params: Punctuated<FnArg, Comma>,
         parameter_ordering: &[usize],
@@ -499,3 +1249,17 @@ impl<'a> FnGenerator<'a> {
             .collect()
     }
 }
+
+/// Wraps a return type in a `Result`, for functions which return C++
+/// exceptions as errors. If no error type is given, this is the `Result`
+/// understood by cxx.
+fn wrap_in_result(ret_type: &ReturnType, error: Option<TokenStream>) -> ReturnType {
+    let ok: TokenStream = match ret_type {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+    match error {
+        Some(error) => parse_quote! { -> ::core::result::Result<#ok, #error> },
+        None => parse_quote! { -> Result<#ok> },
+    }
+}
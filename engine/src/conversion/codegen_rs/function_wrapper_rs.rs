@@ -10,10 +10,13 @@ use proc_macro2::TokenStream;
 use syn::{Expr, Type, TypePtr};
 
 use crate::{
-    conversion::analysis::fun::function_wrapper::{RustConversionType, TypeConversionPolicy},
+    conversion::analysis::{
+        fun::function_wrapper::{RustConversionType, TypeConversionPolicy},
+        ChronoType,
+    },
     types::make_ident,
 };
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::parse_quote;
 
 use super::MaybeUnsafeStmt;
@@ -29,6 +32,12 @@ pub(super) enum RustParamConversion {
     ReturnValue {
         ty: Type,
     },
+    /// A parameter to the cxx::bridge function which the wrapper function
+    /// supplies itself, rather than taking from its caller.
+    OutParam {
+        local_variables: Vec<MaybeUnsafeStmt>,
+        conversion: TokenStream,
+    },
 }
 
 impl TypeConversionPolicy {
@@ -46,12 +55,54 @@ impl TypeConversionPolicy {
                 conversion: quote! ( #var .into_cpp() ),
                 conversion_requires_unsafe: false,
             },
+            RustConversionType::FromAsRefStrToStr => RustParamConversion::Param {
+                ty: parse_quote! { impl AsRef<str> },
+                local_variables: Vec::new(),
+                conversion: quote! ( #var .as_ref() ),
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromAsRefBytesToSlice => RustParamConversion::Param {
+                ty: parse_quote! { impl AsRef<[u8]> },
+                local_variables: Vec::new(),
+                conversion: quote! ( #var .as_ref() ),
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromAsRefPathToSlice => RustParamConversion::Param {
+                ty: parse_quote! { impl AsRef<::std::path::Path> },
+                local_variables: Vec::new(),
+                conversion: quote! ( &*autocxx::path::path_to_bytes(#var.as_ref()) ),
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromUniquePtrStringToPathBuf => RustParamConversion::Param {
+                ty: parse_quote! { ::std::path::PathBuf },
+                local_variables: Vec::new(),
+                conversion: quote! ( autocxx::path::path_from_bytes(#var.as_bytes()) ),
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromChronoToNanos(ref chrono) => RustParamConversion::Param {
+                ty: chrono.rust_type(),
+                local_variables: Vec::new(),
+                conversion: match chrono {
+                    ChronoType::Duration => quote! { autocxx::chrono::duration_to_nanos(#var) },
+                    ChronoType::TimePoint(_) => quote! { #var.nanos_since_epoch() },
+                },
+                conversion_requires_unsafe: false,
+            },
+            RustConversionType::FromNanosToChrono(ref chrono) => RustParamConversion::Param {
+                ty: chrono.rust_type(),
+                local_variables: Vec::new(),
+                conversion: match chrono {
+                    ChronoType::Duration => quote! { autocxx::chrono::duration_from_nanos(#var) },
+                    ChronoType::TimePoint(_) => {
+                        quote! { autocxx::chrono::TimePoint::from_nanos_since_epoch(#var) }
+                    }
+                },
+                conversion_requires_unsafe: false,
+            },
             RustConversionType::ToBoxedUpHolder(ref sub) => {
                 let holder_type = sub.holder();
                 let id = sub.id();
-                let ty = parse_quote! { autocxx::subclass::CppSubclassRustPeerHolder<
-                    super::super::super:: #id>
-                };
+                let ty = parse_quote! { autocxx::subclass::CppSubclassRustPeerHolder<#id> };
                 RustParamConversion::Param {
                     ty,
                     local_variables: Vec::new(),
@@ -61,6 +112,16 @@ impl TypeConversionPolicy {
                     conversion_requires_unsafe: false,
                 }
             }
+            RustConversionType::FromClosureToBox(ref holder, ref ty) => {
+                RustParamConversion::Param {
+                    ty: ty.as_ref().clone().into(),
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        Box::new(root::#holder(Box::new(#var)))
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
             RustConversionType::FromPinMaybeUninitToPtr => {
                 let ty = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr { elem, .. }) => elem,
@@ -186,6 +247,212 @@ impl TypeConversionPolicy {
                     conversion_requires_unsafe: false,
                 }
             }
+            RustConversionType::FromOptionToPtr | RustConversionType::FromOptionRefToPtr => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem,
+                    _ => panic!("Not a ptr"),
+                };
+                let (ty, var) = match self.rust_conversion {
+                    RustConversionType::FromOptionToPtr => {
+                        (parse_quote! { Option<#ty> }, quote! { #var.as_ref() })
+                    }
+                    _ => (parse_quote! { Option<&#ty> }, quote! { #var }),
+                };
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        #var.map_or(::core::ptr::null(), |payload| payload as *const _)
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromFunctionPointerToVoidPtr => {
+                let ty = self
+                    .function_pointer_type()
+                    .expect("Function pointer conversion without a function pointer type")
+                    .clone();
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        #var.map_or(::core::ptr::null_mut(), |function| function as *mut _)
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromIndirectPointerToVoidPtr => {
+                let ty = self
+                    .indirect_pointer_type()
+                    .expect("Indirect pointer conversion without a pointer type")
+                    .clone();
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        #var as *mut autocxx::c_void
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromVoidPtrToIndirectPointer => {
+                let ty = self
+                    .indirect_pointer_type()
+                    .expect("Indirect pointer conversion without a pointer type")
+                    .clone();
+                RustParamConversion::Param {
+                    conversion: quote! {
+                        #var as #ty
+                    },
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion_requires_unsafe: false,
+                }
+            }
+            // Zeroed, so that if C++ doesn't populate the array, Rust sees
+            // an empty one.
+            RustConversionType::FromArrayOutParam(ref pointee) => RustParamConversion::OutParam {
+                local_variables: vec![MaybeUnsafeStmt::new(quote! {
+                    let mut #var = ::core::mem::MaybeUninit::< #pointee >::zeroed();
+                })],
+                conversion: quote! {
+                    #var.as_mut_ptr() as _
+                },
+            },
+            // The cxx::bridge function has populated the out parameters
+            // created by the previous case.
+            RustConversionType::FromOutArray(ref out_array) => {
+                let elem = &out_array.elem;
+                let len = &out_array.len;
+                let free = if out_array.free {
+                    quote! { autocxx_free.assume_init() }
+                } else {
+                    quote! { ::core::ptr::null_mut() }
+                };
+                let array_type: Type = parse_quote! { autocxx::CppArray<#elem> };
+                let array = |data: TokenStream| {
+                    quote! {
+                        autocxx::CppArray::<#elem>::from_raw_parts(#data as *mut #elem, #len.assume_init(), #free)
+                    }
+                };
+                let (ty, conversion) = match (&out_array.data, self.cxxbridge_type()) {
+                    (None, _) => (array_type, array(quote! { #var })),
+                    (Some(data), Type::Tuple(tuple)) if tuple.elems.is_empty() => {
+                        let array = array(quote! { #data.assume_init() });
+                        (
+                            array_type,
+                            quote! {
+                                {
+                                    #var;
+                                    #array
+                                }
+                            },
+                        )
+                    }
+                    (Some(data), ret_type) => {
+                        let array = array(quote! { #data.assume_init() });
+                        (
+                            parse_quote! { (#ret_type, #array_type) },
+                            quote! {
+                                {
+                                    let autocxx_result = #var;
+                                    (autocxx_result, #array)
+                                }
+                            },
+                        )
+                    }
+                };
+                RustParamConversion::Param {
+                    ty,
+                    local_variables: Vec::new(),
+                    conversion,
+                    conversion_requires_unsafe: true,
+                }
+            }
+            RustConversionType::FromOutParam => {
+                let ty = match self.cxxbridge_type() {
+                    Type::Ptr(TypePtr { elem, .. }) => elem,
+                    _ => panic!("Not a ptr"),
+                };
+                RustParamConversion::OutParam {
+                    local_variables: vec![MaybeUnsafeStmt::new(quote! {
+                        let mut #var = ::core::mem::MaybeUninit::< #ty >::uninit();
+                    })],
+                    conversion: quote! {
+                        #var.as_mut_ptr()
+                    },
+                }
+            }
+            // The cxx::bridge function returns whether it populated the
+            // out parameter created by the previous case.
+            RustConversionType::FromBoolToOption => {
+                let ty = self.cxxbridge_type();
+                RustParamConversion::Param {
+                    ty: parse_quote! { Option<#ty> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        if #var {
+                            Some(autocxx_optional.assume_init())
+                        } else {
+                            None
+                        }
+                    },
+                    conversion_requires_unsafe: true,
+                }
+            }
+            // The cxx::bridge function has populated the out parameters
+            // created for each element.
+            RustConversionType::FromOutParamsToTuple => {
+                let ty = self.cxxbridge_type();
+                let arity = match ty {
+                    Type::Tuple(tuple) => tuple.elems.len(),
+                    _ => panic!("Not a tuple"),
+                };
+                let elements = (0..arity).map(|i| make_ident(format!("autocxx_tuple{i}")));
+                RustParamConversion::Param {
+                    ty: ty.clone(),
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            #var;
+                            ( #(#elements.assume_init(),)* )
+                        }
+                    },
+                    conversion_requires_unsafe: true,
+                }
+            }
+            RustConversionType::FromTupleToElements(ref tuple_type, ref elements) => {
+                RustParamConversion::Param {
+                    ty: tuple_type.as_ref().clone().into(),
+                    local_variables: vec![MaybeUnsafeStmt::new(quote! {
+                        let ( #(#elements,)* ) = #var;
+                    })],
+                    conversion: elements[0].to_token_stream(),
+                    conversion_requires_unsafe: false,
+                }
+            }
+            RustConversionType::FromTupleElement => RustParamConversion::OutParam {
+                local_variables: Vec::new(),
+                conversion: quote! { #var },
+            },
+            RustConversionType::FromUniquePtrToOption => {
+                let ty = self.cxxbridge_type();
+                RustParamConversion::Param {
+                    ty: parse_quote! { Option<cxx::UniquePtr<#ty>> },
+                    local_variables: Vec::new(),
+                    conversion: quote! {
+                        {
+                            let autocxx_optional = #var;
+                            if autocxx_optional.is_null() {
+                                None
+                            } else {
+                                Some(autocxx_optional)
+                            }
+                        }
+                    },
+                    conversion_requires_unsafe: false,
+                }
+            }
             RustConversionType::FromReferenceWrapperToPointer => {
                 let (is_mut, ty) = match self.cxxbridge_type() {
                     Type::Ptr(TypePtr {
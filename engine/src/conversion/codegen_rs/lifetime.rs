@@ -7,7 +7,8 @@
 // except according to those terms.
 use crate::{
     conversion::analysis::fun::{
-        function_wrapper::RustConversionType, ArgumentAnalysis, ReceiverMutability,
+        function_wrapper::RustConversionType, returns_reference_to_receiver, ArgumentAnalysis,
+        ReceiverMutability,
     },
     types::QualifiedName,
 };
@@ -16,8 +17,8 @@ use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use std::borrow::Cow;
 use syn::{
-    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, PatType, Path,
-    PathSegment, ReturnType, Type, TypePath, TypeReference,
+    parse_quote, punctuated::Punctuated, token::Comma, FnArg, GenericArgument, Pat, PatIdent,
+    PatType, Path, PathSegment, ReturnType, Type, TypePath, TypeReference,
 };
 
 /// Function which can add explicit lifetime parameters to function signatures
@@ -91,9 +92,15 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     match new_return_type {
         None => (None, params, ret_type),
         Some(new_return_type) => {
-            for FnArg::Typed(PatType { ty, .. }) | FnArg::Receiver(syn::Receiver { ty, .. }) in
-                params.iter_mut()
-            {
+            // A method returning a reference to its own class borrows from
+            // the receiver alone; see `returns_reference_to_receiver`.
+            let returns_receiver = returns_reference_to_receiver(param_details, &ret_type);
+            for param in params.iter_mut() {
+                if returns_receiver && !is_receiver(param) {
+                    continue;
+                }
+                let (FnArg::Typed(PatType { ty, .. }) | FnArg::Receiver(syn::Receiver { ty, .. })) =
+                    param;
                 match ty.as_mut() {
                     Type::Path(TypePath {
                         path: Path { segments, .. },
@@ -110,6 +117,15 @@ pub(crate) fn add_explicit_lifetime_if_necessary<'r>(
     }
 }
 
+fn is_receiver(param: &FnArg) -> bool {
+    match param {
+        FnArg::Receiver(_) => true,
+        FnArg::Typed(PatType { pat, .. }) => {
+            matches!(pat.as_ref(), Pat::Ident(PatIdent { ident, .. }) if ident == "self")
+        }
+    }
+}
+
 fn reference_parameter_is_non_pod_reference(
     params: &Punctuated<FnArg, Comma>,
     non_pod_types: &HashSet<QualifiedName>,
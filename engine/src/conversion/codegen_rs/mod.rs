@@ -6,6 +6,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod cfg_gating;
+mod enum_constants;
 mod fun_codegen;
 mod function_wrapper_rs;
 mod impl_item_creator;
@@ -17,47 +19,63 @@ pub(crate) mod unqualify;
 use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 
-use autocxx_parser::{ExternCppType, IncludeCppConfig, RustFun, UnsafePolicy};
+use autocxx_parser::{
+    ClosureParam, ExternCppType, FutureWrapper, IncludeCppConfig, RustFun, UnsafePolicy,
+};
 
 use itertools::Itertools;
 use proc_macro2::{Span, TokenStream};
 use syn::{
-    parse_quote, punctuated::Punctuated, token::Comma, Attribute, Expr, FnArg, ForeignItem,
-    ForeignItemFn, Ident, ImplItem, Item, ItemForeignMod, ItemMod, Lifetime, TraitItem, Type,
-    TypePath,
+    parse_quote, punctuated::Punctuated, token::Comma, Attribute, Expr, ExprLit, FnArg,
+    ForeignItem, ForeignItemFn, Ident, ImplItem, ImplItemFn, Item, ItemConst, ItemForeignMod,
+    ItemMod, ItemStruct, Lifetime, Lit, TraitItem, TraitItemFn, Type, TypePath,
 };
 
 use crate::{
     conversion::codegen_rs::{
+        enum_constants::{make_enum_constants, make_enum_conversions},
         non_pod_struct::{make_non_pod, new_non_pod_struct},
         unqualify::{unqualify_params, unqualify_ret_type},
     },
     minisyn::minisynize_punctuated,
     types::{make_ident, Namespace, QualifiedName},
+    HeaderInclusion,
 };
 use impl_item_creator::create_impl_items;
 
 use self::{
-    fun_codegen::gen_function,
+    cfg_gating::{
+        add_attr_to_foreign_item, add_attr_to_impl_item, add_attr_to_item, cfg_attr,
+        union_of_features,
+    },
+    fun_codegen::{gen_function, gen_iterator},
     namespace_organizer::{HasNs, NamespaceEntries},
 };
 
 use super::{
     analysis::{
-        fun::{FnPhase, PodAndDepAnalysis, ReceiverMutability},
-        pod::PodAnalysis,
+        fun::{
+            find_types_with_cpp_debug_impls, find_types_with_identity_eq,
+            find_types_with_structural_eq, find_types_without_public_destructors, FnKind, FnPhase,
+            PodAndDepAnalysis, ReceiverMutability,
+        },
+        iterators::find_iterator_functions,
+        pod::{bitfield_getters, is_bindgen_housekeeping_field, PodAnalysis},
     },
-    api::{AnalysisPhase, Api, SubclassName, TypeKind, TypedefKind},
+    api::{AnalysisPhase, Api, StructDetails, SubclassName, TypeKind, TypedefKind},
     convert_error::ErrorContextType,
     doc_attr::get_doc_attrs,
 };
 use super::{
-    api::{Layout, Provenance, RustSubclassFnDetails, SuperclassMethod, TraitImplSignature},
+    api::{
+        FieldOffset, Layout, Provenance, RustSubclassFnDetails, SuperclassMethod,
+        TraitImplSignature, UnionMember,
+    },
     apivec::ApiVec,
     codegen_cpp::type_to_cpp::CppNameMap,
 };
 use super::{convert_error::ErrorContext, ConvertErrorFromCpp};
-use quote::quote;
+use quote::{quote, ToTokens};
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 struct ImplBlockKey {
@@ -76,6 +94,16 @@ struct TraitImplBlockDetails {
     key: TraitImplSignature,
 }
 
+/// An entry for the trait which makes a type's `Pin<&mut Self>` methods
+/// callable on a `UniquePtr` of it, for types named in `unique_ptr_ext!`.
+struct UniquePtrExtDetails {
+    ty: Ident,
+    /// The declaration of a method in the trait, and its implementation
+    /// for `UniquePtr`. This is `None` for the entry from the type itself,
+    /// since the trait must exist even if there are no such methods.
+    method: Option<(TraitItemFn, ImplItemFn)>,
+}
+
 /// Whether and how this item should be exposed in the mods constructed
 /// for actual end-user use.
 #[derive(Clone)]
@@ -88,10 +116,302 @@ enum Use {
     UsedFromBindgen,
     /// 'use' a specific name from bindgen.
     SpecificNameFromBindgen(Ident),
+    /// 'use' a specific name from bindgen, which is the deprecated stub
+    /// for something we couldn't generate.
+    ErrorEntryFromBindgen(Ident),
+    /// 'use' directive points to bindgen, but the user has chosen a
+    /// different name using `rename!`.
+    UsedFromBindgenWithAlias(Ident),
     /// Some kind of custom item
     Custom(Box<Item>),
 }
 
+/// Generates a getter and a mutable getter for each union member. These are
+/// unsafe because only one member of the union is active at a time.
+fn generate_union_accessors(id: &Ident, members: &[UnionMember]) -> Item {
+    let accessors = members.iter().map(|member| {
+        let getter = &member.accessor;
+        let mut_getter = make_ident(format!("{getter}_mut"));
+        let ty = &member.ty;
+        let (storage, storage_mut) = match &member.storage_field {
+            Some(field) => (
+                quote! { ::std::ptr::addr_of!(self.#field) },
+                quote! { ::std::ptr::addr_of_mut!(self.#field) },
+            ),
+            None => (
+                quote! { (self as *const Self) },
+                quote! { (self as *mut Self) },
+            ),
+        };
+        let doc = format!(
+            "Returns a reference to the union member `{getter}`.\n\n# Safety\n\nThis must be the member which was last written."
+        );
+        quote! {
+            #[doc = #doc]
+            pub unsafe fn #getter(&self) -> &#ty {
+                &*(#storage as *const #ty)
+            }
+            #[doc = #doc]
+            pub unsafe fn #mut_getter(&mut self) -> &mut #ty {
+                &mut *(#storage_mut as *mut #ty)
+            }
+        }
+    });
+    parse_quote! {
+        impl #id {
+            #(#accessors)*
+        }
+    }
+}
+
+/// Implements `Debug` for a POD type by printing each of its public fields,
+/// including bit-fields. Private fields are skipped, as are union members
+/// since we can't tell which is active.
+fn generate_pod_debug_impl(id: &Ident, details: &StructDetails) -> Item {
+    let union_storage: HashSet<_> = details
+        .union_members
+        .iter()
+        .filter_map(|member| member.storage_field.as_ref())
+        .collect();
+    let (fields, skipped): (Vec<_>, Vec<_>) = details
+        .item
+        .fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(|id| (id, &f.vis)))
+        .filter(|(id, _)| !is_bindgen_housekeeping_field(id))
+        .partition(|(id, vis)| {
+            matches!(vis, syn::Visibility::Public(_))
+                && *id != "bindgen_union_field"
+                && !id.to_string().starts_with("__bindgen_anon_")
+                && !union_storage.contains(&crate::minisyn::Ident::from((*id).clone()))
+        });
+    let packed = is_packed(&details.item);
+    let fields = fields.into_iter().map(|(field, _)| {
+        let label = field.to_string();
+        let value = field_value(quote! { self }, field, packed);
+        quote! { .field(#label, &(&&autocxx::debug::DebugField(&#value)).debug_field()) }
+    });
+    let bitfields = bitfield_getters(&details.bitfield_impls).map(|(getter, _)| {
+        let label = getter.to_string();
+        quote! { .field(#label, &self.#getter()) }
+    });
+    let finish = if skipped.is_empty() {
+        quote! { finish() }
+    } else {
+        quote! { finish_non_exhaustive() }
+    };
+    let label = id.to_string();
+    parse_quote! {
+        impl ::core::fmt::Debug for #id {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #[allow(unused_imports)]
+                use autocxx::debug::{ViaDebug, ViaTypeName};
+                f.debug_struct(#label)
+                    #(#fields)*
+                    #(#bitfields)*
+                    .#finish
+            }
+        }
+    }
+}
+
+/// Implements `PartialEq` for a POD type which has no C++ `operator==`
+/// by comparing each of its fields, including bit-fields.
+fn generate_pod_eq_impl(id: &Ident, details: &StructDetails) -> Item {
+    let packed = is_packed(&details.item);
+    let fields = details
+        .item
+        .fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref())
+        .filter(|id| !is_bindgen_housekeeping_field(id))
+        .map(|field| {
+            let ours = field_value(quote! { self }, field, packed);
+            let theirs = field_value(quote! { other }, field, packed);
+            quote! { #ours == #theirs }
+        });
+    let bitfields = bitfield_getters(&details.bitfield_impls)
+        .map(|(getter, _)| quote! { self.#getter() == other.#getter() });
+    let comparisons: Vec<_> = fields.chain(bitfields).collect();
+    if comparisons.is_empty() {
+        parse_quote! {
+            impl ::core::cmp::PartialEq for #id {
+                fn eq(&self, _other: &Self) -> bool {
+                    true
+                }
+            }
+        }
+    } else {
+        parse_quote! {
+            impl ::core::cmp::PartialEq for #id {
+                fn eq(&self, other: &Self) -> bool {
+                    #(#comparisons)&&*
+                }
+            }
+        }
+    }
+}
+
+/// Whether bindgen made this struct `#[repr(packed)]`, in which case its
+/// fields may not be aligned.
+fn is_packed(item: &crate::minisyn::ItemStruct) -> bool {
+    item.attrs.iter().any(|attr| {
+        attr.path().is_ident("repr") && attr.to_token_stream().to_string().contains("packed")
+    })
+}
+
+/// The value of a field of a POD struct. We can't take a reference to a
+/// field of a packed struct, since it may be misaligned, so instead we
+/// read a copy, which we mustn't drop.
+fn field_value(receiver: TokenStream, field: &syn::Ident, packed: bool) -> TokenStream {
+    if packed {
+        quote! {
+            (*::core::mem::ManuallyDrop::new(unsafe {
+                ::core::ptr::read_unaligned(::core::ptr::addr_of!(#receiver.#field))
+            }))
+        }
+    } else {
+        quote! { #receiver.#field }
+    }
+}
+
+/// Checks, when the Rust is compiled, that a POD struct has the layout
+/// which clang computed for the C++ struct. They can differ if Rust can't
+/// express some attribute of the C++ struct, and then passing it between
+/// the languages would corrupt it.
+fn generate_pod_layout_assertions(id: &Ident, details: &StructDetails) -> Option<Item> {
+    let mut assertions: Vec<TokenStream> = Vec::new();
+    if let Some(Layout { size, align, .. }) = &details.layout {
+        let size_msg = format!("autocxx: {id} should have size {size}, as it does in C++");
+        let align_msg = format!("autocxx: {id} should have alignment {align}, as it does in C++");
+        assertions.push(quote! {
+            assert!(::core::mem::size_of::<#id>() == #size, #size_msg);
+        });
+        assertions.push(quote! {
+            assert!(::core::mem::align_of::<#id>() == #align, #align_msg);
+        });
+    }
+    // Only fields which have survived our changes to bindgen's struct.
+    let fields: HashSet<_> = details
+        .item
+        .fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref())
+        .collect();
+    for FieldOffset { field, offset } in &details.field_offsets {
+        if fields.contains(&&field.0) {
+            let msg = format!(
+                "autocxx: field {id}::{field} should be at offset {offset}, as it is in C++"
+            );
+            assertions.push(quote! {
+                assert!(::core::mem::offset_of!(#id, #field) == #offset, #msg);
+            });
+        }
+    }
+    (!assertions.is_empty()).then(|| {
+        parse_quote! {
+            const _: () = {
+                #(#assertions)*
+            };
+        }
+    })
+}
+
+/// Bakes the size and alignment which bindgen found for the target into
+/// constants, since the Rust type is opaque.
+fn generate_layout_consts(id: &Ident, layout: &Layout) -> Item {
+    let size = layout.size;
+    let align = layout.align;
+    parse_quote! {
+        impl #id {
+            #[doc = "The size of this C++ type, as `sizeof` would give it."]
+            pub const CPP_SIZE: usize = #size;
+            #[doc = "The alignment of this C++ type, as `alignof` would give it."]
+            pub const CPP_ALIGN: usize = #align;
+        }
+    }
+}
+
+/// Implements `PartialEq` and `Eq` for a non-POD type by comparing the
+/// addresses of the most-derived C++ objects.
+fn generate_identity_eq_impls(id: &Ident) -> Vec<Item> {
+    vec![
+        parse_quote! {
+            #[allow(unused_unsafe)]
+            impl ::core::cmp::PartialEq for #id {
+                fn eq(&self, other: &Self) -> bool {
+                    // Safety: this only reads the objects' addresses.
+                    unsafe { self.cpp_addr() == other.cpp_addr() }
+                }
+            }
+        },
+        parse_quote! {
+            impl ::core::cmp::Eq for #id {}
+        },
+    ]
+}
+
+/// Implements `From` a tuple of all the fields of an aggregate POD type,
+/// in declaration order (starting with any bases), which is the Rust
+/// equivalent of aggregate initialization. Returns `None` if some fields
+/// are private, or are bit-fields or unions, which can't be initialized
+/// like this.
+fn generate_aggregate_from_impl(id: &Ident, details: &StructDetails) -> Option<Item> {
+    if !details.union_members.is_empty() || details.item.fields.is_empty() {
+        return None;
+    }
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for field in &details.item.fields {
+        match (&field.ident, &field.vis) {
+            (Some(name), syn::Visibility::Public(_)) if !is_bindgen_housekeeping_field(name) => {
+                names.push(name);
+                types.push(&field.ty);
+            }
+            _ => return None,
+        }
+    }
+    let indices = (0..names.len()).map(syn::Index::from);
+    Some(parse_quote! {
+        impl From<(#(#types,)*)> for #id {
+            fn from(fields: (#(#types,)*)) -> Self {
+                Self {
+                    #(#names: fields.#indices,)*
+                }
+            }
+        }
+    })
+}
+
+/// bindgen gives us C++ string literals as byte strings, complete with
+/// their nul terminators, because C++ doesn't promise that they're UTF-8;
+/// and it gives us `char`s as numbers. Say so in the docs of the constant.
+fn document_const_encoding(const_item: &mut ItemConst) {
+    let note = match (&*const_item.expr, &*const_item.ty) {
+        (
+            Expr::Lit(ExprLit {
+                lit: Lit::ByteStr(_),
+                ..
+            }),
+            _,
+        ) => " The C++ string literal, as bytes including its nul terminator.",
+        (_, Type::Path(typ))
+            if typ
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "c_char") =>
+        {
+            " The C++ `char`, as a number."
+        }
+        _ => return,
+    };
+    if !get_doc_attrs(&const_item.attrs).is_empty() {
+        const_item.attrs.push(parse_quote! { #[doc = ""] });
+    }
+    const_item.attrs.push(parse_quote! { #[doc = #note] });
+}
+
 fn get_string_items() -> Vec<Item> {
     [
         Item::Trait(parse_quote! {
@@ -139,22 +459,27 @@ fn get_string_items() -> Vec<Item> {
 /// existing lumps of code within the Api structures.
 pub(crate) struct RsCodeGenerator<'a> {
     unsafe_policy: &'a UnsafePolicy,
-    include_list: &'a [String],
+    include_list: &'a [HeaderInclusion],
     bindgen_mod: ItemMod,
     original_name_map: CppNameMap,
     config: &'a IncludeCppConfig,
     header_name: Option<String>,
+    cfg_features: HashMap<QualifiedName, Vec<String>>,
+    suppress_ignored_api_stubs: bool,
 }
 
 impl<'a> RsCodeGenerator<'a> {
     /// Generate code for a set of APIs that was discovered during parsing.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn generate_rs_code(
         all_apis: ApiVec<FnPhase>,
         unsafe_policy: &'a UnsafePolicy,
-        include_list: &'a [String],
+        include_list: &'a [HeaderInclusion],
         bindgen_mod: ItemMod,
         config: &'a IncludeCppConfig,
         header_name: Option<String>,
+        cfg_features: HashMap<QualifiedName, Vec<String>>,
+        suppress_ignored_api_stubs: bool,
     ) -> Vec<Item> {
         let c = Self {
             unsafe_policy,
@@ -163,6 +488,8 @@ impl<'a> RsCodeGenerator<'a> {
             original_name_map: CppNameMap::new_from_apis(&all_apis),
             config,
             header_name,
+            cfg_features,
+            suppress_ignored_api_stubs,
         };
         c.rs_codegen(all_apis)
     }
@@ -175,25 +502,66 @@ impl<'a> RsCodeGenerator<'a> {
         let subclasses_with_a_single_trivial_constructor =
             find_trivially_constructed_subclasses(&all_apis);
         let non_pod_types = find_non_pod_types(&all_apis);
-        // Now let's generate the Rust code.
-        let (rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) = all_apis
+        let types_without_public_destructors = find_types_without_public_destructors(&all_apis);
+        let types_with_cpp_debug_impls = find_types_with_cpp_debug_impls(&all_apis);
+        let types_with_structural_eq = find_types_with_structural_eq(&all_apis, self.config);
+        let types_with_identity_eq = find_types_with_identity_eq(&all_apis, self.config);
+        let generated_functions = find_generated_functions(&all_apis);
+        let iterators: Vec<_> = find_iterator_functions(&all_apis)
             .into_iter()
-            .map(|api| {
-                let more_cpp_needed = api.needs_cpp_codegen();
-                let name = api.name().clone();
-                let gen = self.generate_rs_for_api(
-                    api,
-                    &methods_by_superclass,
-                    &subclasses_with_a_single_trivial_constructor,
-                    &non_pod_types,
-                );
-                ((name, gen), more_cpp_needed)
+            .map(|functions| {
+                let features = self.cfg_features.get(functions.container).cloned();
+                let (name, mut gen) = gen_iterator(functions);
+                if let Some(features) = features {
+                    gen.gate_behind_features(features);
+                }
+                (name, gen)
             })
-            .unzip();
+            .collect();
+        // Now let's generate the Rust code.
+        let (mut rs_codegen_results_and_namespaces, additional_cpp_needs): (Vec<_>, Vec<_>) =
+            all_apis
+                .into_iter()
+                .filter(|api| self.wants_error_entry(api, &generated_functions))
+                .map(|api| {
+                    let more_cpp_needed = api.needs_cpp_codegen();
+                    let name = api.name().clone();
+                    let mut gen = self.generate_rs_for_api(
+                        api,
+                        &methods_by_superclass,
+                        &subclasses_with_a_single_trivial_constructor,
+                        &non_pod_types,
+                        &types_without_public_destructors,
+                        &types_with_cpp_debug_impls,
+                        &types_with_structural_eq,
+                        &types_with_identity_eq,
+                    );
+                    if let Some(features) = self.cfg_features.get(&name) {
+                        gen.gate_behind_features(features.clone());
+                    }
+                    ((name, gen), more_cpp_needed)
+                })
+                .unzip();
+        rs_codegen_results_and_namespaces.extend(iterators);
+        // Types renamed using rename! keep their C++ names everywhere except
+        // in the final 'use' statements.
+        for (name, gen) in rs_codegen_results_and_namespaces.iter_mut() {
+            if let Some(rust_name) = self
+                .config
+                .get_rust_rename(&self.original_name_map.map(name))
+            {
+                for materialization in gen.materializations.iter_mut() {
+                    if matches!(materialization, Use::UsedFromBindgen) {
+                        *materialization =
+                            Use::UsedFromBindgenWithAlias(make_ident(rust_name).into());
+                    }
+                }
+            }
+        }
         // First, the hierarchy of mods containing lots of 'use' statements
         // which is the final API exposed as 'ffi'.
         let mut use_statements =
-            Self::generate_final_use_statements(&rs_codegen_results_and_namespaces);
+            Self::generate_final_use_statements(&rs_codegen_results_and_namespaces, self.config);
         // And work out what we need for the bindgen mod.
         let bindgen_root_items =
             self.generate_final_bindgen_mods(&rs_codegen_results_and_namespaces);
@@ -249,6 +617,9 @@ impl<'a> RsCodeGenerator<'a> {
         if !bindgen_root_items.is_empty() {
             self.bindgen_mod.vis = parse_quote! {};
             self.bindgen_mod.content.as_mut().unwrap().1 = vec![Item::Mod(parse_quote! {
+                // Methods may be attached to the deprecated stubs for
+                // types we couldn't generate.
+                #[allow(deprecated)]
                 pub(super) mod root {
                     #(#bindgen_root_items)*
                 }
@@ -256,6 +627,8 @@ impl<'a> RsCodeGenerator<'a> {
             all_items.push(Item::Mod(self.bindgen_mod));
         }
         all_items.push(Item::Mod(parse_quote! {
+            // C++ APIs may be deprecated, but that's for our users to heed.
+            #[allow(deprecated)]
             #[cxx::bridge]
             mod cxxbridge {
                 #(#bridge_items)*
@@ -275,11 +648,12 @@ impl<'a> RsCodeGenerator<'a> {
         apis: &ApiVec<FnPhase>,
     ) -> HashMap<QualifiedName, Vec<SuperclassMethod>> {
         let mut results = HashMap::new();
-        results.extend(
-            self.config
-                .superclasses()
-                .map(|sc| (QualifiedName::new_from_cpp_name(sc), Vec::new())),
-        );
+        // The superclass may be a concrete! type, however the subclass!
+        // directive named it.
+        results.extend(apis.iter().filter_map(|api| match api {
+            Api::Subclass { superclass, .. } => Some((superclass.clone(), Vec::new())),
+            _ => None,
+        }));
         for api in apis.iter() {
             if let Api::SubclassTraitItem { details, .. } = api {
                 let list = results.get_mut(&details.receiver);
@@ -301,13 +675,23 @@ impl<'a> RsCodeGenerator<'a> {
 
     fn build_include_foreign_items(&self, has_additional_cpp_needs: bool) -> Vec<ForeignItem> {
         let extra_inclusion = if has_additional_cpp_needs {
-            Some(self.header_name.clone().unwrap())
+            Some(HeaderInclusion::Quoted(self.header_name.clone().unwrap()))
         } else {
             None
         };
         let chained = self.include_list.iter().chain(extra_inclusion.iter());
         chained
             .map(|inc| {
+                let inc = match inc {
+                    HeaderInclusion::Quoted(path) => quote! { #path },
+                    // cxx reassembles the path from the tokens between the
+                    // angle brackets. A path which isn't made of Rust tokens
+                    // will have to be quoted instead.
+                    HeaderInclusion::Angled(path) => match path.parse::<TokenStream>() {
+                        Ok(path) => quote! { <#path> },
+                        Err(_) => quote! { #path },
+                    },
+                };
                 ForeignItem::Macro(parse_quote! {
                     include!(#inc);
                 })
@@ -316,34 +700,68 @@ impl<'a> RsCodeGenerator<'a> {
     }
 
     /// Generate lots of 'use' statements to pull cxxbridge items into the output
-    /// mod hierarchy according to C++ namespaces.
+    /// mod hierarchy according to C++ namespaces, or wherever `map_namespace!`
+    /// or `rename!` put them instead.
     fn generate_final_use_statements(
         input_items: &[(QualifiedName, RsCodegenResult)],
+        config: &IncludeCppConfig,
     ) -> Vec<Item> {
         let mut output_items = Vec::new();
-        let ns_entries = NamespaceEntries::new(input_items);
-        Self::append_child_use_namespace(&ns_entries, &mut output_items);
+        let placed_items: Vec<_> = input_items
+            .iter()
+            .map(|item| {
+                let cpp_namespace: Vec<_> = item.0.get_namespace().iter().cloned().collect();
+                PlacedItem {
+                    rust_mod: config
+                        .get_rust_mod_path(&cpp_namespace)
+                        .into_iter()
+                        .fold(Namespace::new(), |ns, segment| ns.push(segment)),
+                    item,
+                }
+            })
+            .collect();
+        let ns_entries = NamespaceEntries::new(&placed_items);
+        Self::append_child_use_namespace(&ns_entries, &mut output_items, 0);
         output_items
     }
 
     fn append_child_use_namespace(
-        ns_entries: &NamespaceEntries<(QualifiedName, RsCodegenResult)>,
+        ns_entries: &NamespaceEntries<PlacedItem>,
         output_items: &mut Vec<Item>,
+        depth: usize,
     ) {
-        for (name, codegen) in ns_entries.entries() {
+        for PlacedItem {
+            item: (name, codegen),
+            ..
+        } in ns_entries.entries()
+        {
+            let cfg = cfg_attr(&codegen.cfg_features);
             output_items.extend(codegen.materializations.iter().map(|materialization| {
-                match materialization {
+                let mut item = match materialization {
                     Use::UsedFromCxxBridgeWithAlias(ref alias) => {
-                        Self::generate_cxx_use_stmt(name, Some(alias))
+                        Self::generate_cxx_use_stmt(name, Some(alias), depth)
+                    }
+                    Use::UsedFromCxxBridge => Self::generate_cxx_use_stmt(name, None, depth),
+                    Use::UsedFromBindgen => Self::generate_bindgen_use_stmt(name, None, depth),
+                    Use::UsedFromBindgenWithAlias(ref alias) => {
+                        Self::generate_bindgen_use_stmt(name, Some(alias), depth)
                     }
-                    Use::UsedFromCxxBridge => Self::generate_cxx_use_stmt(name, None),
-                    Use::UsedFromBindgen => Self::generate_bindgen_use_stmt(name),
-                    Use::SpecificNameFromBindgen(id) => {
+                    Use::SpecificNameFromBindgen(id) | Use::ErrorEntryFromBindgen(id) => {
                         let name = QualifiedName::new(name.get_namespace(), id.clone().into());
-                        Self::generate_bindgen_use_stmt(&name)
+                        Self::generate_bindgen_use_stmt(&name, None, depth)
                     }
                     Use::Custom(item) => *item.clone(),
+                };
+                // Both the stubs for things we couldn't generate and APIs
+                // which are deprecated in C++ are deprecated in Rust, but
+                // only their users should be warned.
+                if !matches!(materialization, Use::Custom(_)) {
+                    add_attr_to_item(&mut item, &parse_quote! { #[allow(deprecated)] });
+                }
+                if let Some(cfg) = &cfg {
+                    add_attr_to_item(&mut item, cfg);
                 }
+                item
             }));
         }
         for (child_name, child_ns_entries) in ns_entries.children() {
@@ -358,6 +776,7 @@ impl<'a> RsCodeGenerator<'a> {
             Self::append_child_use_namespace(
                 child_ns_entries,
                 &mut new_mod.content.as_mut().unwrap().1,
+                depth + 1,
             );
             output_items.push(Item::Mod(new_mod));
         }
@@ -396,25 +815,40 @@ impl<'a> RsCodeGenerator<'a> {
     ) {
         let mut impl_entries_by_type: HashMap<_, Vec<_>> = HashMap::new();
         let mut trait_impl_entries_by_trait_and_ty: HashMap<_, Vec<_>> = HashMap::new();
+        let mut unique_ptr_ext_entries_by_type: HashMap<_, Vec<_>> = HashMap::new();
         for item in ns_entries.entries() {
             output_items.extend(item.1.bindgen_mod_items.iter().cloned());
             if let Some(impl_entry) = &item.1.impl_entry {
                 impl_entries_by_type
                     .entry(impl_entry.ty.clone())
                     .or_default()
-                    .push(&impl_entry.item);
+                    .push((&impl_entry.item, item.1.cfg_features.as_slice()));
             }
             if let Some(trait_impl_entry) = &item.1.trait_impl_entry {
                 trait_impl_entries_by_trait_and_ty
                     .entry(trait_impl_entry.key.clone())
                     .or_default()
-                    .push(&trait_impl_entry.item);
+                    .push((&trait_impl_entry.item, item.1.cfg_features.as_slice()));
+            }
+            if let Some(unique_ptr_ext_entry) = &item.1.unique_ptr_ext_entry {
+                unique_ptr_ext_entries_by_type
+                    .entry(unique_ptr_ext_entry.ty.clone())
+                    .or_default()
+                    .push((&unique_ptr_ext_entry.method, item.1.cfg_features.as_slice()));
             }
         }
+        // Each impl block is needed if any of its items is needed.
+        // Items within inherent impls are gated individually, whereas
+        // trait impls must be all-or-nothing.
         for (ty, entries) in impl_entries_by_type.into_iter() {
             let lt = ty.lifetime.map(|lt| quote! { < #lt > });
             let ty = ty.ty;
+            let cfg = cfg_attr(&union_of_features(
+                entries.iter().map(|(_, features)| *features),
+            ));
+            let entries = entries.into_iter().map(|(entry, _)| entry);
             output_items.push(Item::Impl(parse_quote! {
+                #cfg
                 impl #lt #ty {
                     #(#entries)*
                 }
@@ -424,12 +858,42 @@ impl<'a> RsCodeGenerator<'a> {
             let unsafety = key.unsafety;
             let ty = key.ty;
             let trt = key.trait_signature;
+            let cfg = cfg_attr(&union_of_features(
+                entries.iter().map(|(_, features)| *features),
+            ));
+            let entries = entries.into_iter().map(|(entry, _)| entry);
             output_items.push(Item::Impl(parse_quote! {
+                #cfg
                 #unsafety impl #trt for #ty {
                     #(#entries)*
                 }
             }))
         }
+        for (ty, entries) in unique_ptr_ext_entries_by_type.into_iter() {
+            let trait_name = get_unique_ptr_ext_name(&ty);
+            let cfg = cfg_attr(&union_of_features(
+                entries.iter().map(|(_, features)| *features),
+            ));
+            let (decls, impls): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .filter_map(|(method, _)| method.as_ref())
+                .map(|(decl, imp)| (decl, imp))
+                .unzip();
+            let doc = format!("The methods of [`{ty}`] which take `Pin<&mut Self>`, callable directly on a `UniquePtr<{ty}>`. Like `UniquePtr::pin_mut`, each panics if the `UniquePtr` is null.");
+            output_items.push(Item::Trait(parse_quote! {
+                #cfg
+                #[doc = #doc]
+                pub trait #trait_name {
+                    #(#decls)*
+                }
+            }));
+            output_items.push(Item::Impl(parse_quote! {
+                #cfg
+                impl #trait_name for cxx::UniquePtr<#ty> {
+                    #(#impls)*
+                }
+            }));
+        }
         for (child_name, child_ns_entries) in ns_entries.children() {
             let new_ns = ns.push((*child_name).clone());
             let child_id = make_ident(child_name);
@@ -464,12 +928,17 @@ impl<'a> RsCodeGenerator<'a> {
         output_items
     }
 
+    #[allow(clippy::too_many_arguments)] // currently the least unclear way
     fn generate_rs_for_api(
         &self,
         api: Api<FnPhase>,
         associated_methods: &HashMap<QualifiedName, Vec<SuperclassMethod>>,
         subclasses_with_a_single_trivial_constructor: &HashSet<QualifiedName>,
         non_pod_types: &HashSet<QualifiedName>,
+        types_without_public_destructors: &HashSet<QualifiedName>,
+        types_with_cpp_debug_impls: &HashSet<QualifiedName>,
+        types_with_structural_eq: &HashSet<QualifiedName>,
+        types_with_identity_eq: &HashSet<QualifiedName>,
     ) -> RsCodegenResult {
         let name = api.name().clone();
         let id = name.get_final_ident();
@@ -496,11 +965,36 @@ impl<'a> RsCodeGenerator<'a> {
                 non_pod_types,
                 self.config,
             ),
-            Api::Const { const_item, .. } => RsCodegenResult {
-                bindgen_mod_items: vec![Item::Const(const_item.into())],
+            Api::Const {
+                const_item,
+                member_of,
+                ..
+            } => {
+                let impl_entry = member_of.map(|(ty, member)| {
+                    Box::new(Self::generate_associated_const(&const_item, ty, member))
+                });
+                let mut const_item: ItemConst = const_item.into();
+                document_const_encoding(&mut const_item);
+                RsCodegenResult {
+                    impl_entry,
+                    bindgen_mod_items: vec![Item::Const(const_item)],
+                    materializations: vec![Use::UsedFromBindgen],
+                    ..Default::default()
+                }
+            }
+            Api::RawFunction { item, .. } => RsCodegenResult {
+                bindgen_mod_items: vec![parse_quote! {
+                    extern "C" {
+                        #item
+                    }
+                }],
                 materializations: vec![Use::UsedFromBindgen],
                 ..Default::default()
             },
+            Api::BitfieldUnit { items, .. } => RsCodegenResult {
+                bindgen_mod_items: items.into_iter().map(Into::into).collect(),
+                ..Default::default()
+            },
             Api::Typedef { analysis, .. } => RsCodegenResult {
                 bindgen_mod_items: vec![match analysis.kind {
                     TypedefKind::Type(type_item) => Item::Type(type_item.into()),
@@ -522,33 +1016,110 @@ impl<'a> RsCodeGenerator<'a> {
                     },
                 ..
             } => {
-                let doc_attrs = get_doc_attrs(&details.item.attrs);
+                let mut doc_attrs = get_doc_attrs(&details.item.attrs);
+                doc_attrs.extend(details.deprecated.iter().map(|d| d.to_attr()));
                 let layout = details.layout.clone();
-                self.generate_type(
+                let layout_consts = layout
+                    .as_ref()
+                    .filter(|_| matches!(kind, TypeKind::NonPod) && !is_generic)
+                    .map(|layout| generate_layout_consts(&id, layout));
+                // Types which C++ can stringify use that instead.
+                let debug_impl = (matches!(kind, TypeKind::Pod)
+                    && !is_generic
+                    && !types_with_cpp_debug_impls.contains(&name)
+                    && !self.config.is_on_debug_blocklist(&name.to_cpp_name()))
+                .then(|| generate_pod_debug_impl(&id, &details));
+                let layout_assertions = (matches!(kind, TypeKind::Pod) && !is_generic)
+                    .then(|| generate_pod_layout_assertions(&id, &details))
+                    .flatten();
+                let eq_impls = if types_with_structural_eq.contains(&name) {
+                    vec![generate_pod_eq_impl(&id, &details)]
+                } else if types_with_identity_eq.contains(&name) {
+                    generate_identity_eq_impls(&id)
+                } else {
+                    Vec::new()
+                };
+                let aggregate_from_impl = if constructors.aggregate_pod && !is_generic {
+                    generate_aggregate_from_impl(&id, &details)
+                } else {
+                    None
+                };
+                let mut item: ItemStruct = details.item.into();
+                if constructors.trivially_copyable_pod {
+                    item.attrs.push(parse_quote! { #[derive(Clone, Copy)] });
+                }
+                // A type which must be destroyed explicitly mustn't be
+                // destroyed whenever a UniquePtr is dropped.
+                let destroyable = constructors.destructor
+                    && !self.config.has_explicit_destructor(&name.to_cpp_name());
+                let mut result = self.generate_type(
                     &name,
                     id,
                     kind,
                     constructors.move_constructor,
-                    constructors.destructor,
-                    || Some((Item::Struct(details.item.into()), doc_attrs)),
+                    destroyable,
+                    || Some((Item::Struct(item), doc_attrs)),
                     associated_methods,
                     layout,
                     is_generic,
-                )
+                );
+                // Field accessors only make sense if the fields are there.
+                if matches!(kind, TypeKind::Pod) {
+                    result
+                        .bindgen_mod_items
+                        .extend(details.bitfield_impls.into_iter().map(Into::into));
+                    if !details.union_members.is_empty() {
+                        result.bindgen_mod_items.push(generate_union_accessors(
+                            &name.get_final_ident(),
+                            &details.union_members,
+                        ));
+                    }
+                }
+                result.bindgen_mod_items.extend(debug_impl);
+                result.bindgen_mod_items.extend(layout_consts);
+                result.bindgen_mod_items.extend(layout_assertions);
+                result.bindgen_mod_items.extend(eq_impls);
+                result.bindgen_mod_items.extend(aggregate_from_impl);
+                result
             }
-            Api::Enum { item, .. } => {
+            Api::Enum { item, aliases, .. } => {
                 let doc_attrs = get_doc_attrs(&item.attrs);
-                self.generate_type(
+                let mut item: syn::ItemEnum = item.into();
+                if !self.config.is_on_debug_blocklist(&name.to_cpp_name()) {
+                    item.attrs.push(parse_quote! { #[derive(Debug)] });
+                }
+                // A Rust enum can't have two variants with the same value,
+                // so if the C++ enum does, we use constants instead. Nor can
+                // it hold a combination of flags.
+                let is_bitflags = self.config.is_bitflags_enum(&name.to_cpp_name());
+                let as_constants = !aliases.is_empty()
+                    || is_bitflags
+                    || self.config.is_enum_as_constants(&name.to_cpp_name());
+                let newtype = if as_constants {
+                    make_enum_constants(&item, &aliases, is_bitflags)
+                } else {
+                    None
+                };
+                let (item, constants) = match newtype {
+                    Some((newtype, constants)) => (newtype, constants),
+                    None => {
+                        let conversions = make_enum_conversions(&item, &name.to_cpp_name());
+                        (Item::Enum(item), conversions)
+                    }
+                };
+                let mut result = self.generate_type(
                     &name,
                     id,
                     TypeKind::Pod,
                     true,
                     true,
-                    || Some((Item::Enum(item.into()), doc_attrs)),
+                    || Some((item, doc_attrs)),
                     associated_methods,
                     None,
                     false,
-                )
+                );
+                result.bindgen_mod_items.extend(constants);
+                result
             }
             Api::ConcreteType { .. } => self.generate_type(
                 &name,
@@ -594,6 +1165,7 @@ impl<'a> RsCodeGenerator<'a> {
                     ..Default::default()
                 }
             }
+            Api::ClosureHolder { details, .. } => Self::generate_closure_holder(&details),
             Api::RustFn {
                 details:
                     RustFun {
@@ -630,9 +1202,19 @@ impl<'a> RsCodeGenerator<'a> {
                 let generate_peer_constructor = subclasses_with_a_single_trivial_constructor.contains(&name.0.name) &&
                     // TODO: Create an UnsafeCppPeerConstructor trait for calling an unsafe
                     // constructor instead? Need to create unsafe versions of everything that uses
-                    // it too.
-                    matches!(self.unsafe_policy, UnsafePolicy::AllFunctionsSafe);
-                self.generate_subclass(name, &superclass, methods, generate_peer_constructor)
+                    // it too. We construct the subclasses for future_wrapper! ourselves, so
+                    // they're fine.
+                    (matches!(self.unsafe_policy, UnsafePolicy::AllFunctionsSafe)
+                        || self.config.is_future_wrapper_subclass(name.0.name.get_final_item()));
+                let superclass_destructible =
+                    !types_without_public_destructors.contains(&superclass);
+                self.generate_subclass(
+                    name,
+                    &superclass,
+                    methods,
+                    generate_peer_constructor,
+                    superclass_destructible,
+                )
             }
             Api::ExternCppType {
                 details: ExternCppType { rust_path, .. },
@@ -653,6 +1235,7 @@ impl<'a> RsCodeGenerator<'a> {
         superclass: &QualifiedName,
         methods: Option<&Vec<SuperclassMethod>>,
         generate_peer_constructor: bool,
+        superclass_destructible: bool,
     ) -> RsCodegenResult {
         let super_name = superclass.get_final_item();
         let super_path = superclass.to_type_path();
@@ -667,12 +1250,17 @@ impl<'a> RsCodeGenerator<'a> {
             pub use bindgen::root::#holder;
         });
         let relinquish_ownership_call = sub.cpp_remove_ownership();
+        let future_wrapper = self
+            .config
+            .get_future_wrappers()
+            .iter()
+            .find(|fw| fw.subclass() == id.0);
         let mut bindgen_mod_items = vec![
             parse_quote! {
                 pub use cxxbridge::#cpp_id;
             },
             parse_quote! {
-                pub struct #holder(pub autocxx::subclass::CppSubclassRustPeerHolder<super::super::super::#id>);
+                pub struct #holder(pub autocxx::subclass::CppSubclassRustPeerHolder<#id>);
             },
             parse_quote! {
                 impl autocxx::subclass::CppSubclassCppPeer for #cpp_id {
@@ -682,6 +1270,34 @@ impl<'a> RsCodeGenerator<'a> {
                 }
             },
         ];
+        if self.config.is_threadsafe_subclass(&id.to_string()) {
+            // The user has promised that the object pair is only ever used
+            // from one thread at a time. We don't make anything Sync, since
+            // two threads could then borrow the Rust object at once.
+            // The Rust subclass itself must still be Send on its own merits.
+            bindgen_mod_items.extend([
+                parse_quote! {
+                    unsafe impl Send for #cpp_id {}
+                },
+                parse_quote! {
+                    unsafe impl Send for #holder {}
+                },
+                parse_quote! {
+                    const _: fn() = || {
+                        fn assert_send<T: Send>() {}
+                        assert_send::<#id>();
+                    };
+                },
+            ]);
+        }
+        match future_wrapper {
+            Some(future_wrapper) => bindgen_mod_items.extend(
+                Self::generate_future_wrapper_subclass(future_wrapper, &sub, superclass, methods),
+            ),
+            None => bindgen_mod_items.push(parse_quote! {
+                use super::super::super::#id;
+            }),
+        }
         let mut extern_c_mod_items = vec![
             self.generate_cxxbridge_type(&full_cpp, false, Vec::new()),
             parse_quote! {
@@ -718,18 +1334,28 @@ impl<'a> RsCodeGenerator<'a> {
             if !methods_impls.is_empty() {
                 bindgen_mod_items.push(parse_quote! {
                     #[allow(non_snake_case)]
-                    impl #supers for super::super::super::#id {
+                    impl #supers for #id {
                         #(#methods_impls)*
                     }
                 });
             }
         }
         if generate_peer_constructor {
+            let new_call = quote! { #cpp_id :: new(peer_holder) };
+            let (allow_unused_unsafe, new_call) = if future_wrapper.is_some() {
+                (
+                    Some(quote! { #[allow(unused_unsafe)] }),
+                    quote! { unsafe { #new_call } },
+                )
+            } else {
+                (None, new_call)
+            };
             bindgen_mod_items.push(parse_quote! {
-                impl autocxx::subclass::CppPeerConstructor<#cpp_id> for super::super::super::#id {
+                impl autocxx::subclass::CppPeerConstructor<#cpp_id> for #id {
+                    #allow_unused_unsafe
                     fn make_peer(&mut self, peer_holder: autocxx::subclass::CppSubclassRustPeerHolder<Self>) -> cxx::UniquePtr<#cpp_path> {
                         use autocxx::moveit::Emplace;
-                        cxx::UniquePtr::emplace(#cpp_id :: new(peer_holder))
+                        cxx::UniquePtr::emplace(#new_call)
                     }
                 }
             })
@@ -744,12 +1370,8 @@ impl<'a> RsCodeGenerator<'a> {
         extern_c_mod_items.push(parse_quote! {
             fn #as_mut_id(self: Pin<&mut #cpp_id>) -> Pin<&mut #super_cxxxbridge_id>;
         });
-        let as_unique_ptr_id = make_ident(format!("{cpp_id}_As_{super_name}_UniquePtr"));
-        extern_c_mod_items.push(parse_quote! {
-            fn #as_unique_ptr_id(u: UniquePtr<#cpp_id>) -> UniquePtr<#super_cxxxbridge_id>;
-        });
         bindgen_mod_items.push(parse_quote! {
-            impl AsRef<#super_path> for super::super::super::#id {
+            impl AsRef<#super_path> for #id {
                 fn as_ref(&self) -> &cxxbridge::#super_cxxxbridge_id {
                     use autocxx::subclass::CppSubclass;
                     self.peer().#as_id()
@@ -758,21 +1380,28 @@ impl<'a> RsCodeGenerator<'a> {
         });
         // TODO it would be nice to impl AsMut here but pin prevents us
         bindgen_mod_items.push(parse_quote! {
-            impl super::super::super::#id {
+            impl #id {
                 pub fn pin_mut(&mut self) -> ::core::pin::Pin<&mut cxxbridge::#super_cxxxbridge_id> {
                     use autocxx::subclass::CppSubclass;
                     self.peer_mut().#as_mut_id()
                 }
             }
         });
-        let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
-        bindgen_mod_items.push(parse_quote! {
-            impl super::super::super::#id {
-                pub fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
-                    cxxbridge::#as_unique_ptr_id(u)
+        // A UniquePtr can't own the superclass if its destructor is protected.
+        if superclass_destructible {
+            let as_unique_ptr_id = make_ident(format!("{cpp_id}_As_{super_name}_UniquePtr"));
+            extern_c_mod_items.push(parse_quote! {
+                fn #as_unique_ptr_id(u: UniquePtr<#cpp_id>) -> UniquePtr<#super_cxxxbridge_id>;
+            });
+            let rs_as_unique_ptr_id = make_ident(format!("as_{super_name}_unique_ptr"));
+            bindgen_mod_items.push(parse_quote! {
+                impl #id {
+                    pub fn #rs_as_unique_ptr_id(u: cxx::UniquePtr<#cpp_id>) -> cxx::UniquePtr<cxxbridge::#super_cxxxbridge_id> {
+                        cxxbridge::#as_unique_ptr_id(u)
+                    }
                 }
-            }
-        });
+            });
+        }
         let remove_ownership = sub.remove_ownership();
         global_items.push(parse_quote! {
             #[allow(non_snake_case)]
@@ -780,6 +1409,13 @@ impl<'a> RsCodeGenerator<'a> {
                 Box::new(#holder(me.0.relinquish_ownership()))
             }
         });
+        let peer_destroyed = sub.peer_destroyed();
+        global_items.push(parse_quote! {
+            #[allow(non_snake_case)]
+            pub fn #peer_destroyed(me: &#holder) {
+                me.0.peer_destroyed::<bindgen::root::#cpp_id>()
+            }
+        });
         RsCodegenResult {
             extern_c_mod_items,
             // For now we just assume we can't keep subclasses in vectors, but we can put them in
@@ -798,6 +1434,162 @@ impl<'a> RsCodeGenerator<'a> {
                 parse_quote! {
                     fn #remove_ownership(me: Box<#holder>) -> Box<#holder>;
                 },
+                parse_quote! {
+                    fn #peer_destroyed(me: &#holder);
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Generates the Rust side of the subclass behind a `future_wrapper!`,
+    /// which users never see, so it lives here rather than alongside the
+    /// `include_cpp!`. Its override of the completion method resolves the
+    /// future, then deletes the subclass. If the completion method doesn't
+    /// take exactly one parameter we can't do that, and the wrapper
+    /// function won't compile.
+    fn generate_future_wrapper_subclass(
+        future_wrapper: &FutureWrapper,
+        sub: &SubclassName,
+        superclass: &QualifiedName,
+        methods: Option<&Vec<SuperclassMethod>>,
+    ) -> Vec<Item> {
+        let id = sub.id();
+        let cpp_id = sub.cpp().get_final_ident();
+        let super_cxxbridge_id = superclass.get_final_ident();
+        let method_and_output_ty = methods
+            .into_iter()
+            .flatten()
+            .find(|m| m.name.0 == future_wrapper.method)
+            .and_then(
+                |m| match (m.params.iter().nth(1).map(|arg| &arg.0), &m.ret_type.0) {
+                    (Some(FnArg::Typed(pt)), syn::ReturnType::Default) if m.params.len() == 2 => {
+                        Some((m, pt.ty.as_ref().clone()))
+                    }
+                    _ => None,
+                },
+            );
+        let (method, output_ty) = match method_and_output_ty {
+            Some(method_and_output_ty) => method_and_output_ty,
+            None => {
+                return vec![parse_quote! {
+                    pub struct #id {
+                        cpp_peer: autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id>,
+                    }
+                }]
+            }
+        };
+        let output = sub.future_output();
+        let method_name = &method.name;
+        let receiver: FnArg = match method.receiver_mutability {
+            ReceiverMutability::Const => parse_quote!(&self),
+            ReceiverMutability::Mutable => parse_quote!(&mut self),
+        };
+        let unsafe_token = method.requires_unsafe.wrapper_token();
+        let methods_trait = SubclassName::get_methods_trait_name(superclass).to_type_path();
+        vec![
+            parse_quote! {
+                pub type #output = #output_ty;
+            },
+            parse_quote! {
+                pub struct #id {
+                    completer: ::core::cell::Cell<Option<autocxx::completion::Completer<#output>>>,
+                    cpp_peer: autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id>,
+                }
+            },
+            parse_quote! {
+                impl autocxx::subclass::CppSubclass<#cpp_id> for #id {
+                    fn peer_holder_mut(&mut self) -> &mut autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id> {
+                        &mut self.cpp_peer
+                    }
+                    fn peer_holder(&self) -> &autocxx::subclass::CppSubclassCppPeerHolder<#cpp_id> {
+                        &self.cpp_peer
+                    }
+                }
+            },
+            parse_quote! {
+                impl autocxx::subclass::CppSubclassSelfOwned<#cpp_id> for #id {}
+            },
+            parse_quote! {
+                #[allow(non_snake_case)]
+                impl #methods_trait for #id {
+                    #unsafe_token fn #method_name(#receiver, value: #output) {
+                        use autocxx::subclass::CppSubclassSelfOwned;
+                        // Any second call is ignored, though C++ shouldn't
+                        // make one, since we're about to be deleted.
+                        if let Some(completer) = self.completer.take() {
+                            completer.complete(value);
+                        }
+                        self.delete_self();
+                    }
+                }
+            },
+            parse_quote! {
+                impl #id {
+                    /// Creates the callback object, which owns itself,
+                    /// and the future it will resolve.
+                    pub fn start() -> (*mut cxxbridge::#super_cxxbridge_id, autocxx::completion::Completion<#output>) {
+                        use autocxx::subclass::CppSubclassSelfOwned;
+                        let (completer, completion) = autocxx::completion::completion();
+                        let me = Self::new_self_owned(Self {
+                            completer: ::core::cell::Cell::new(Some(completer)),
+                            cpp_peer: Default::default(),
+                        });
+                        let callback = unsafe {
+                            ::core::pin::Pin::into_inner_unchecked(me.borrow_mut().pin_mut())
+                                as *mut cxxbridge::#super_cxxbridge_id
+                        };
+                        // C++ may call back on another thread as soon as it
+                        // has the pointer, by which time we must have
+                        // dropped our reference to the Rust object.
+                        drop(me);
+                        (callback, completion)
+                    }
+                }
+            },
+        ]
+    }
+
+    /// Generates the type which owns a closure passed to C++ in place of a
+    /// `std::function`, and the function by which C++ calls that closure.
+    fn generate_closure_holder(details: &ClosureParam) -> RsCodegenResult {
+        let holder = details.holder();
+        let call_fn = details.call_fn();
+        let bridge_inputs = &details.inputs;
+        // Outside the cxx::bridge mod, CxxString needs qualifying.
+        let inputs: Vec<Type> = bridge_inputs
+            .iter()
+            .map(|ty| match ty {
+                Type::Reference(_) => parse_quote! { &::cxx::CxxString },
+                _ => ty.clone(),
+            })
+            .collect();
+        let arg_names: Vec<Ident> = (0..inputs.len())
+            .map(|counter| make_ident(format!("arg{counter}")).into())
+            .collect();
+        let ret = details.output.as_ref().map(|ty| quote! { -> #ty });
+        RsCodegenResult {
+            bindgen_mod_items: vec![parse_quote! {
+                pub struct #holder(pub Box<dyn FnMut(#(#inputs),*) #ret>);
+            }],
+            global_items: vec![
+                parse_quote! {
+                    pub use bindgen::root::#holder;
+                },
+                parse_quote! {
+                    #[allow(non_snake_case)]
+                    fn #call_fn(holder: &mut #holder, #(#arg_names: #inputs),*) #ret {
+                        (holder.0)(#(#arg_names),*)
+                    }
+                },
+            ],
+            extern_rust_mod_items: vec![
+                parse_quote! {
+                    type #holder;
+                },
+                parse_quote! {
+                    fn #call_fn(holder: &mut #holder, #(#arg_names: #bridge_inputs),*) #ret;
+                },
             ],
             ..Default::default()
         }
@@ -896,6 +1688,16 @@ impl<'a> RsCodeGenerator<'a> {
             .as_ref()
             .map(|maybe_item| maybe_item.1.clone())
             .unwrap_or_default();
+        let unique_ptr_ext_entry =
+            if !is_generic && self.config.has_unique_ptr_ext(&name.to_cpp_name()) {
+                materializations.push(Use::SpecificNameFromBindgen(get_unique_ptr_ext_name(&id)));
+                Some(Box::new(UniquePtrExtDetails {
+                    ty: id.clone().into(),
+                    method: None,
+                }))
+            } else {
+                None
+            };
         // We have a choice here to either:
         // a) tell cxx to generate an opaque type using 'type A;'
         // b) generate a concrete type definition, e.g. by using bindgen's
@@ -941,6 +1743,7 @@ impl<'a> RsCodeGenerator<'a> {
                             self.generate_cxxbridge_type(name, true, doc_attrs)
                         ],
                         bindgen_mod_items,
+                        unique_ptr_ext_entry,
                         materializations,
                         ..Default::default()
                     }
@@ -960,6 +1763,7 @@ impl<'a> RsCodeGenerator<'a> {
                         ],
                         bridge_items: create_impl_items(&id, movable, destroyable, self.config),
                         bindgen_mod_items,
+                        unique_ptr_ext_entry,
                         materializations,
                         ..Default::default()
                     }
@@ -992,18 +1796,22 @@ impl<'a> RsCodeGenerator<'a> {
                     };
                     let ret_type = &method.ret_type;
                     let unsafe_token = method.requires_unsafe.wrapper_token();
+                    let doc_attrs = &method.doc_attrs;
                     if method.is_pure_virtual {
                         (
                             None,
                             parse_quote!(
+                                #(#doc_attrs)*
                                 #unsafe_token fn #id(#params) #ret_type;
                             ),
                         )
                     } else {
                         let a: Option<TraitItem> = Some(parse_quote!(
+                            #(#doc_attrs)*
                             #unsafe_token fn #super_id(#params) #ret_type;
                         ));
                         let b: TraitItem = parse_quote!(
+                            #(#doc_attrs)*
                             #unsafe_token fn #id(#params) #ret_type {
                                 self.#super_id(#param_names)
                             }
@@ -1067,17 +1875,96 @@ impl<'a> RsCodeGenerator<'a> {
     /// Generates something in the output mod that will carry a docstring
     /// explaining why a given type or function couldn't have bindings
     /// generated.
+    /// Makes a static data member available as an associated constant,
+    /// as well as the `Type_member` constant which bindgen gives us.
+    /// bindgen represents strings as null-terminated byte strings, but
+    /// here we can offer a `&str`.
+    fn generate_associated_const(
+        const_item: &crate::minisyn::ItemConst,
+        ty: QualifiedName,
+        member: crate::minisyn::Ident,
+    ) -> ImplBlockDetails {
+        let id = &const_item.ident;
+        let doc_attrs = get_doc_attrs(&const_item.attrs);
+        let string_value = match &*const_item.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::ByteStr(bytes),
+                ..
+            }) => bytes
+                .value()
+                .strip_suffix(&[0])
+                .and_then(|bytes| String::from_utf8(bytes.to_vec()).ok()),
+            _ => None,
+        };
+        let item = match string_value {
+            Some(value) => {
+                let separator = (!doc_attrs.is_empty()).then(|| quote! { #[doc = ""] });
+                parse_quote! {
+                    #(#doc_attrs)*
+                    #separator
+                    #[doc = " The C++ string literal, as a Rust string without its nul terminator."]
+                    pub const #member: &'static str = #value;
+                }
+            }
+            None => {
+                let const_ty = &const_item.ty;
+                parse_quote! {
+                    #(#doc_attrs)*
+                    pub const #member: #const_ty = #id;
+                }
+            }
+        };
+        let ty = ty.get_final_ident();
+        ImplBlockDetails {
+            item,
+            ty: ImplBlockKey {
+                ty: parse_quote! { #ty },
+                lifetime: None,
+            },
+        }
+    }
+
+    /// Whether to generate anything for this API: we don't generate stubs
+    /// for ignored APIs if asked not to, if they'd clash with something
+    /// we did generate, or if the API filter excluded them on purpose.
+    fn wants_error_entry(
+        &self,
+        api: &Api<FnPhase>,
+        generated_functions: &HashSet<(Namespace, Option<String>, String)>,
+    ) -> bool {
+        match api {
+            Api::IgnoredItem {
+                name,
+                err,
+                ctx: Some(ctx),
+            } => {
+                !self.suppress_ignored_api_stubs
+                    && !matches!(err, ConvertErrorFromCpp::FilteredByUser)
+                    && !generated_functions.contains(&error_entry_key(&name.name, ctx))
+            }
+            _ => true,
+        }
+    }
+
+    /// A stub for something we couldn't generate, deprecated so that any use
+    /// of it says why.
     fn generate_error_entry(err: ConvertErrorFromCpp, ctx: ErrorContext) -> RsCodegenResult {
-        let err = format!("autocxx bindings couldn't be generated: {err}");
+        let err = match err.suggestion() {
+            Some(suggestion) => {
+                format!("autocxx bindings couldn't be generated: {err}\n\n{suggestion}")
+            }
+            None => format!("autocxx bindings couldn't be generated: {err}"),
+        };
         let (impl_entry, bindgen_mod_item, materialization) = match ctx.into_type() {
             ErrorContextType::Item(id) => (
                 // Populate within bindgen mod because impl blocks may attach.
                 None,
                 Some(parse_quote! {
                     #[doc = #err]
+                    #[deprecated(note = #err)]
                     pub struct #id;
                 }),
-                Some(Use::SpecificNameFromBindgen(id.into())),
+                Some(Use::ErrorEntryFromBindgen(id.into())),
             ),
             ErrorContextType::SanitizedItem(id) => (
                 // Guaranteed to be no impl blocks - populate directly in output mod.
@@ -1085,14 +1972,17 @@ impl<'a> RsCodeGenerator<'a> {
                 None,
                 Some(Use::Custom(Box::new(parse_quote! {
                     #[doc = #err]
+                    #[deprecated(note = #err)]
                     pub struct #id;
                 }))),
             ),
             ErrorContextType::Method { self_ty, method } => (
+                // Taking `&self` means method call syntax finds this too.
                 Some(Box::new(ImplBlockDetails {
                     item: parse_quote! {
                         #[doc = #err]
-                        fn #method(_uhoh: autocxx::BindingGenerationFailure) {
+                        #[deprecated(note = #err)]
+                        pub fn #method(&self, _uhoh: autocxx::BindingGenerationFailure) {
                         }
                     },
                     ty: ImplBlockKey {
@@ -1112,8 +2002,8 @@ impl<'a> RsCodeGenerator<'a> {
         }
     }
 
-    fn generate_cxx_use_stmt(name: &QualifiedName, alias: Option<&Ident>) -> Item {
-        let segs = Self::find_output_mod_root(name.get_namespace())
+    fn generate_cxx_use_stmt(name: &QualifiedName, alias: Option<&Ident>, depth: usize) -> Item {
+        let segs = Self::find_output_mod_root(depth)
             .chain(std::iter::once(make_ident("cxxbridge")))
             .chain(std::iter::once(name.get_final_ident()));
         Item::Use(match alias {
@@ -1126,11 +2016,19 @@ impl<'a> RsCodeGenerator<'a> {
         })
     }
 
-    fn generate_bindgen_use_stmt(name: &QualifiedName) -> Item {
-        let segs =
-            Self::find_output_mod_root(name.get_namespace()).chain(name.get_bindgen_path_idents());
-        Item::Use(parse_quote! {
-            pub use #(#segs)::*;
+    fn generate_bindgen_use_stmt(
+        name: &QualifiedName,
+        alias: Option<&Ident>,
+        depth: usize,
+    ) -> Item {
+        let segs = Self::find_output_mod_root(depth).chain(name.get_bindgen_path_idents());
+        Item::Use(match alias {
+            None => parse_quote! {
+                pub use #(#segs)::*;
+            },
+            Some(alias) => parse_quote! {
+                pub use #(#segs)::* as #alias;
+            },
         })
     }
 
@@ -1209,8 +2107,10 @@ impl<'a> RsCodeGenerator<'a> {
         ForeignItem::Verbatim(for_extern_c_ts)
     }
 
-    fn find_output_mod_root(ns: &Namespace) -> impl Iterator<Item = crate::minisyn::Ident> {
-        std::iter::repeat(make_ident("super")).take(ns.depth())
+    /// The path from a mod in the output hierarchy, `depth` mods deep,
+    /// back to the output mod itself.
+    fn find_output_mod_root(depth: usize) -> impl Iterator<Item = crate::minisyn::Ident> {
+        std::iter::repeat(make_ident("super")).take(depth)
     }
 }
 
@@ -1237,6 +2137,54 @@ fn find_trivially_constructed_subclasses(apis: &ApiVec<FnPhase>) -> HashSet<Qual
         .collect()
 }
 
+/// The functions and methods we generated, as (namespace, type for
+/// methods, name) in the same form as [`error_entry_key`].
+fn find_generated_functions(
+    apis: &ApiVec<FnPhase>,
+) -> HashSet<(Namespace, Option<String>, String)> {
+    apis.iter()
+        .filter_map(|api| match api {
+            Api::Function { name, analysis, .. } => match &analysis.kind {
+                FnKind::Function => Some((
+                    name.name.get_namespace().clone(),
+                    None,
+                    analysis.rust_name.clone(),
+                )),
+                FnKind::Method { impl_for, .. } => Some((
+                    impl_for.get_namespace().clone(),
+                    Some(impl_for.get_final_item().to_string()),
+                    analysis.rust_name.clone(),
+                )),
+                // Our stubs would shadow trait methods, too.
+                FnKind::TraitMethod {
+                    impl_for, details, ..
+                } => Some((
+                    impl_for.get_namespace().clone(),
+                    Some(impl_for.get_final_item().to_string()),
+                    details.method_name.to_string(),
+                )),
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Where the stub for an ignored API would go.
+fn error_entry_key(
+    name: &QualifiedName,
+    ctx: &ErrorContext,
+) -> (Namespace, Option<String>, String) {
+    let ns = name.get_namespace().clone();
+    match ctx.get_type() {
+        ErrorContextType::Item(id) | ErrorContextType::SanitizedItem(id) => {
+            (ns, None, id.to_string())
+        }
+        ErrorContextType::Method { self_ty, method } => {
+            (ns, Some(self_ty.to_string()), method.to_string())
+        }
+    }
+}
+
 fn find_non_pod_types(apis: &ApiVec<FnPhase>) -> HashSet<QualifiedName> {
     apis.iter()
         .filter_map(|api| match api {
@@ -1264,12 +2212,31 @@ impl HasNs for (QualifiedName, RsCodegenResult) {
     }
 }
 
+/// An item together with the Rust mod in which it appears in the output
+/// hierarchy, which isn't always the same as its C++ namespace.
+struct PlacedItem<'a> {
+    rust_mod: Namespace,
+    item: &'a (QualifiedName, RsCodegenResult),
+}
+
+impl HasNs for PlacedItem<'_> {
+    fn get_namespace(&self) -> &Namespace {
+        &self.rust_mod
+    }
+}
+
 impl<T: AnalysisPhase> HasNs for Api<T> {
     fn get_namespace(&self) -> &Namespace {
         self.name().get_namespace()
     }
 }
 
+/// The name of the trait which makes a type's `Pin<&mut Self>` methods
+/// callable on a `UniquePtr` of it.
+fn get_unique_ptr_ext_name(ty: &Ident) -> Ident {
+    make_ident(format!("{ty}Ext")).into()
+}
+
 /// Snippets of code generated from a particular API.
 /// These are then concatenated together into the final generated code.
 #[derive(Default)]
@@ -1281,7 +2248,49 @@ struct RsCodegenResult {
     bindgen_mod_items: Vec<Item>,
     impl_entry: Option<Box<ImplBlockDetails>>,
     trait_impl_entry: Option<Box<TraitImplBlockDetails>>,
+    unique_ptr_ext_entry: Option<Box<UniquePtrExtDetails>>,
     materializations: Vec<Use>,
+    /// Cargo features, any of which enables these items. If empty,
+    /// they're unconditional.
+    cfg_features: Vec<String>,
+}
+
+impl RsCodegenResult {
+    /// Mark all these items as being needed only if one of the given
+    /// features is enabled. Items which are later combined with those
+    /// from other APIs (such as `impl` blocks and `use` statements)
+    /// are gated when that happens.
+    fn gate_behind_features(&mut self, features: Vec<String>) {
+        if let Some(cfg) = cfg_attr(&features) {
+            for item in self
+                .extern_c_mod_items
+                .iter_mut()
+                .chain(self.extern_rust_mod_items.iter_mut())
+            {
+                add_attr_to_foreign_item(item, &cfg);
+            }
+            for item in self
+                .bridge_items
+                .iter_mut()
+                .chain(self.global_items.iter_mut())
+                .chain(self.bindgen_mod_items.iter_mut())
+            {
+                add_attr_to_item(item, &cfg);
+            }
+            if let Some(impl_entry) = &mut self.impl_entry {
+                add_attr_to_impl_item(&mut impl_entry.item, &cfg);
+            }
+            if let Some((decl, imp)) = self
+                .unique_ptr_ext_entry
+                .as_mut()
+                .and_then(|entry| entry.method.as_mut())
+            {
+                decl.attrs.insert(0, cfg.clone());
+                imp.attrs.insert(0, cfg.clone());
+            }
+        }
+        self.cfg_features = features;
+    }
 }
 
 /// An [`Item`] that always needs to be in an unsafe block.
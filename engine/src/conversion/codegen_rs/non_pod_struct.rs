@@ -57,16 +57,13 @@ pub(crate) fn make_non_pod(s: &mut ItemStruct, layout: Option<Layout>) {
         .iter()
         .filter(|a| a.path().get_ident().iter().any(|p| *p == "doc"))
         .cloned();
+    // Rust doesn't allow a type to be both packed and aligned, but our
+    // opaque type has no fields to pack, so the alignment is all that
+    // matters even for a packed C++ type.
     let repr_attr = if let Some(layout) = &layout {
         let align = make_lit_int(layout.align);
-        if layout.packed {
-            parse_quote! {
-                #[repr(C,align(#align),packed)]
-            }
-        } else {
-            parse_quote! {
-                #[repr(C,align(#align))]
-            }
+        parse_quote! {
+            #[repr(C,align(#align))]
         }
     } else {
         parse_quote! {
@@ -6,11 +6,14 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cell::Cell;
+
 use autocxx_parser::UnsafePolicy;
-#[allow(unused_imports)]
+use quote::ToTokens;
 use syn::parse_quote;
 use syn::ItemMod;
 
+use crate::source_locations::SourceLocator;
 use crate::CodegenOptions;
 
 use super::BridgeConverter;
@@ -22,23 +25,175 @@ use super::BridgeConverter;
 // the original C++ in integration_tests.rs if possible.
 // Also, if you're pasting in code from github issues, it's
 // important to make sure that the underlying code has an
-// acceptable license. That's why this file is currently blank.
+// acceptable license. That's why this file contains only tests
+// of situations which we can't (or no longer) provoke from C++.
 
 #[allow(dead_code)]
 fn do_test(input: ItemMod) {
     let tc = parse_quote! {};
-    let bc = BridgeConverter::new(&[], &tc);
-    let inclusions = "".into();
+    let bc = BridgeConverter::new(&[], &tc, &[]);
     bc.convert(
-        input,
+        || input.clone(),
         UnsafePolicy::AllFunctionsSafe,
-        inclusions,
         &CodegenOptions::default(),
         "",
+        &SourceLocator::new(&[], &[], &[]),
     )
     .unwrap();
 }
 
+/// bindgen output with a function whose annotation makes us panic,
+/// amid ordinary functions.
+fn make_pathological_input() -> ItemMod {
+    parse_quote! {
+        mod bindgen {
+            pub mod root {
+                extern "C" {
+                    pub fn before() -> u32;
+                    #[cpp_semantics(special_member("unheard_of"))]
+                    pub fn pathological();
+                    pub fn after(a: u32) -> u32;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_panic_in_one_item_is_contained() {
+    let tc = parse_quote! {
+        generate!("before")
+        generate!("pathological")
+        generate!("after")
+    };
+    let bc = BridgeConverter::new(&[], &tc, &[]);
+    let results = bc
+        .convert(
+            make_pathological_input,
+            UnsafePolicy::AllFunctionsSafe,
+            &CodegenOptions::default(),
+            "",
+            &SourceLocator::new(&[], &[], &[]),
+        )
+        .unwrap();
+    assert_eq!(results.ignored_apis.len(), 1);
+    let ignored = &results.ignored_apis[0];
+    assert_eq!(ignored.name, "pathological");
+    assert!(ignored.reason.contains("unexpected special_member_kind"));
+    // The panic isn't printed, so its location is part of the error.
+    assert!(ignored.reason.contains(".rs:"));
+    let rs = results
+        .rs
+        .iter()
+        .map(|item| item.to_token_stream().to_string())
+        .collect::<String>();
+    assert!(rs.contains("fn before"));
+    assert!(rs.contains("fn after"));
+}
+
+#[test]
+fn test_panics_in_several_items_are_contained() {
+    let input: ItemMod = parse_quote! {
+        mod bindgen {
+            pub mod root {
+                extern "C" {
+                    #[cpp_semantics(special_member("unheard_of"))]
+                    pub fn first_pathological();
+                    pub fn ordinary() -> u32;
+                    #[cpp_semantics(special_member("unheard_of"))]
+                    pub fn second_pathological();
+                }
+            }
+        }
+    };
+    let tc = parse_quote! {
+        generate!("first_pathological")
+        generate!("ordinary")
+        generate!("second_pathological")
+    };
+    let bc = BridgeConverter::new(&[], &tc, &[]);
+    let attempts = Cell::new(0);
+    let results = bc
+        .convert(
+            || {
+                attempts.set(attempts.get() + 1);
+                input.clone()
+            },
+            UnsafePolicy::AllFunctionsSafe,
+            &CodegenOptions::default(),
+            "",
+            &SourceLocator::new(&[], &[], &[]),
+        )
+        .unwrap();
+    // Both panics are found in the first attempt, so one more suffices.
+    assert_eq!(attempts.get(), 2);
+    let ignored: Vec<_> = results
+        .ignored_apis
+        .iter()
+        .map(|ignored| ignored.name.as_str())
+        .collect();
+    assert_eq!(ignored, ["first_pathological", "second_pathological"]);
+    let rs = results
+        .rs
+        .iter()
+        .map(|item| item.to_token_stream().to_string())
+        .collect::<String>();
+    assert!(rs.contains("fn ordinary"));
+}
+
+#[test]
+#[should_panic(expected = "unexpected special_member_kind")]
+fn test_panic_in_one_item_is_fatal_when_strict() {
+    let tc = parse_quote! {
+        generate!("before")
+        generate!("pathological")
+    };
+    let bc = BridgeConverter::new(&[], &tc, &[]);
+    let _ = bc.convert(
+        make_pathological_input,
+        UnsafePolicy::AllFunctionsSafe,
+        &CodegenOptions {
+            strict: true,
+            ..Default::default()
+        },
+        "",
+        &SourceLocator::new(&[], &[], &[]),
+    );
+}
+
+#[test]
+fn test_member_function_pointer_param_is_ignored() {
+    let input: ItemMod = parse_quote! {
+        mod bindgen {
+            pub mod root {
+                extern "C" {
+                    #[cpp_semantics(arg_type_member_function_pointer(handler))]
+                    pub fn on_click(handler: *mut ::std::os::raw::c_void);
+                }
+            }
+        }
+    };
+    let tc = parse_quote! {
+        generate!("on_click")
+    };
+    let bc = BridgeConverter::new(&[], &tc, &[]);
+    let results = bc
+        .convert(
+            || input.clone(),
+            UnsafePolicy::AllFunctionsSafe,
+            &CodegenOptions::default(),
+            "",
+            &SourceLocator::new(&[], &[], &[]),
+        )
+        .unwrap();
+    assert_eq!(results.ignored_apis.len(), 1);
+    let ignored = &results.ignored_apis[0];
+    assert_eq!(ignored.name, "on_click");
+    assert!(ignored
+        .reason
+        .contains("The parameter handler is a pointer to a member function"));
+}
+
 // How to add a test here
 //
 // #[test]
@@ -16,7 +16,9 @@ use thiserror::Error;
 
 use crate::{
     known_types, proc_macro_span_to_miette_span,
+    source_locations::SourceLocation,
     types::{make_ident, InvalidIdentError, Namespace, QualifiedName},
+    CppStandard,
 };
 
 /// Errors which can occur during conversion
@@ -45,6 +47,8 @@ pub enum ConvertErrorFromCpp {
     UnexpectedOuterItem,
     #[error("Bindgen generated some unexpected code in an inner namespace mod. You may have specified something in a 'generate' directive which is not currently compatible with autocxx.")]
     UnexpectedItemInMod,
+    #[error("autocxx failed unexpectedly while processing this item, so it's been ignored: {0}. This is a bug in autocxx; please report it. To fail the whole conversion in such cases instead, use strict mode.")]
+    InternalError(String),
     #[error("autocxx was unable to produce a typdef pointing to the complex type {0}.")]
     ComplexTypedefTarget(String),
     #[error("Unexpected type for 'this' in the function {}.", .0.to_cpp_name())]
@@ -67,8 +71,10 @@ pub enum ConvertErrorFromCpp {
     UnsupportedType(String),
     #[error("Encountered type not yet known by autocxx: {0}")]
     UnknownType(String),
-    #[error("Encountered mutable static data, not yet supported: {0}")]
+    #[error("Static data {0} has no value known at compile time. Name it in a generate! directive to get functions to access it.")]
     StaticData(String),
+    #[error("Static data {0} is declared static outside any class, so each C++ file has its own copy. autocxx can't give Rust access to the one the rest of your C++ uses; declare it extern instead.")]
+    InternalStaticData(String),
     #[error("Encountered typedef to itself - this is a known bindgen bug: {0}")]
     InfinitelyRecursiveTypedef(QualifiedName),
     #[error("Unexpected 'use' statement encountered: {}", .0.as_ref().map(|s| s.as_str()).unwrap_or("<unknown>"))]
@@ -83,16 +89,34 @@ pub enum ConvertErrorFromCpp {
     InvalidPointee(String),
     #[error("The 'generate' or 'generate_pod' directive for '{0}' did not result in any code being generated. Perhaps this was mis-spelled or you didn't qualify the name with any namespaces? Otherwise please report a bug.")]
     DidNotGenerateAnything(String),
+    #[error("The keep! directive for '{0}' didn't match anything autocxx found in the C++. Perhaps this was mis-spelled or you didn't qualify the name with any namespaces?")]
+    KeepDidNotMatchAnything(String),
+    #[error("The rename! directive for '{0}' matched {1} functions, but it must match exactly one. Spell each parameter type as in the C++ declaration, for instance 'const std::string&'.")]
+    OverloadRenameMismatch(String, usize),
+    #[error("The rename! directive for '{0}' gives it the Rust name '{1}', but '{2}' already has that name. If '{0}' is overloaded, rename each overload separately by giving its parameter types, for instance rename!(\"{0}(double)\", \"{1}_f64\").")]
+    RenameConflict(String, String, String),
+    #[error("The map_namespace! directives put both '{0}' and '{1}' in the Rust mod '{2}', where they'd have the same name. Use rename! to give one of them a different Rust name.")]
+    NamespaceMappingConflict(String, String, String),
     #[error("Found an attempt at using a forward declaration ({}) inside a templated cxx type such as UniquePtr or CxxVector. If the forward declaration is a typedef, perhaps autocxx wasn't sure whether or not it involved a forward declaration. If you're sure it didn't, then you may be able to solve this by using instantiable!.", .0.to_cpp_name())]
     TypeContainingForwardDeclaration(QualifiedName),
+    #[error("{} is only forward declared, so its size isn't known and it can't be passed, returned or stored by value. It can still be used behind a pointer or reference.", .0.to_cpp_name())]
+    ForwardDeclarationByValue(QualifiedName),
     #[error("Found an attempt at using a type marked as blocked! ({})", .0.to_cpp_name())]
-    Blocked(QualifiedName),
+    Blocked(QualifiedName, String),
+    #[error("{} is named in suppress_constructor!, so its constructors aren't offered to Rust.", .0.to_cpp_name())]
+    ConstructorSuppressed(QualifiedName),
     #[error("This function or method uses a type where one of the template parameters was incomprehensible to bindgen/autocxx - probably because it uses template specialization.")]
     UnusedTemplateParam,
     #[error("This item relies on a type not known to autocxx ({})", .0.to_cpp_name())]
     UnknownDependentType(QualifiedName),
     #[error("This item depends on some other type(s) which autocxx could not generate, some of them are: {}", .0.iter().join(", "))]
     IgnoredDependent(HashSet<QualifiedName>),
+    #[error("Nothing which was requested depends on this, so it was garbage collected. Use keep! or keep_all! to retain it.")]
+    GarbageCollected,
+    #[error("The API filter given to the autocxx Builder excluded this.")]
+    FilteredByUser,
+    #[error("Both {0} and {1} would be called {2} in Rust, so bindings aren't generated for either. Use block! to exclude one of them.")]
+    ConstantNameClash(String, String, String),
     #[error(transparent)]
     InvalidIdent(InvalidIdentError),
     #[error("This item name is used in multiple namespaces. At present, autocxx and cxx allow only one type of a given name. This limitation will be fixed in future. (Items found with this name: {})", .0.iter().join(", "))]
@@ -121,12 +145,24 @@ pub enum ConvertErrorFromCpp {
     Deleted,
     #[error("This structure has an rvalue reference field (&&) which is not yet supported.")]
     RValueReferenceField,
+    #[error("The array field {field} has elements of a type which autocxx can't represent: {err}")]
+    UnsupportedArrayField {
+        field: String,
+        #[source]
+        err: Box<ConvertErrorFromCpp>,
+    },
+    #[error("The bit-field {field} has a type which autocxx can't represent: {err}")]
+    UnsupportedBitfield {
+        field: String,
+        #[source]
+        err: Box<ConvertErrorFromCpp>,
+    },
     #[error("This type was not on the allowlist, so we are not generating methods for it.")]
     MethodOfNonAllowlistedType,
     #[error("This type is templated, so we can't generate bindings. We will instead generate bindings for each instantiation.")]
     MethodOfGenericType,
-    #[error("bindgen generated multiple different APIs (functions/types) with this name. autocxx doesn't know how to disambiguate them, so we won't generate bindings for any of them.")]
-    DuplicateItemsFoundInParsing,
+    #[error("bindgen generated multiple different APIs named {} ({}). autocxx doesn't know how to disambiguate them, so we won't generate bindings for any of them.", .0.to_cpp_name(), .1.join(" vs "))]
+    DuplicateItemsFoundInParsing(QualifiedName, Vec<&'static str>),
     #[error(
         "bindgen generated a move or copy constructor with an unexpected number of parameters."
     )]
@@ -137,7 +173,7 @@ pub enum ConvertErrorFromCpp {
     InvalidTypeForCppPtr(QualifiedName),
     #[error("A C++ std::vector was found containing some type that cxx can't accommodate as a vector element ({})", .0.to_cpp_name())]
     InvalidTypeForCppVector(QualifiedName),
-    #[error("Variadic functions are not supported by cxx or autocxx.")]
+    #[error("Variadic functions are not supported by cxx or autocxx. Free functions may be exposed as raw unsafe declarations using generate_variadic!.")]
     Variadic,
     #[error("A type had a template inside a std::vector, which is not supported.")]
     GenericsWithinVector,
@@ -153,12 +189,168 @@ pub enum ConvertErrorFromCpp {
     ReferringToGenericTypeParam,
     #[error("This forward declaration was nested within another struct/class. autocxx is unable to represent inner types if they are forward declarations.")]
     ForwardDeclaredNestedType,
+    #[error("Type {} was listed in explicit_destructor! but its destructor is private or deleted", .0.to_cpp_name())]
+    ExplicitDestructorNotCallable(QualifiedName),
+    #[error("This function takes {} by value, but that type can be neither moved nor copied, so there's no way to pass it from Rust. (Move-only types are fine: pass a UniquePtr, which is moved into the parameter.)", .0.to_cpp_name())]
+    ImmovableValueParam(QualifiedName),
+    #[error("This std::optional can't be represented in Rust: {0}. autocxx supports std::optional<T> only by value or by const reference, where T is a type that could itself be passed by value.")]
+    UnsupportedOptional(String),
+    #[error("This function pointer can't be represented in Rust: {0}. autocxx supports plain C function pointers passed by value as function parameters or held in struct fields, where the function pointer itself takes and returns only primitives and raw pointers.")]
+    UnsupportedFunctionPointer(String),
+    #[error("The function pointer parameter {0} can't be represented in Rust: {1}. autocxx supports plain C function pointers passed by value as function parameters, where the function pointer itself takes and returns only primitives and raw pointers.")]
+    UnsupportedFunctionPointerParam(String, String),
+    #[error("The parameter {0} is a pointer to a member function, which autocxx can't pass between Rust and C++. Consider adding a wrapper function which takes the object and calls the member function itself.")]
+    MemberFunctionPointerParam(String),
+    #[error("This array can't be represented in Rust: {0}. autocxx supports fixed-size arrays of primitives as function parameters, passed by reference or by pointer.")]
+    UnsupportedArray(String),
+    #[error("This function uses std::array, which autocxx can't yet represent because bindgen doesn't tell us its size. Consider adding a wrapper function which takes a C array such as const T (&)[N], or T out[N] for results; autocxx represents those as Rust arrays.")]
+    UnsupportedStdArray,
+    #[error("This std::span can't be represented in Rust: {0}. autocxx supports std::span<T> parameters, by value or by const reference, where T is a primitive type.")]
+    UnsupportedSpan(String),
+    #[error("This std::initializer_list can't be represented in Rust: {0}. autocxx supports std::initializer_list<T> parameters, by value or by const reference, where T is a primitive type; otherwise, use an overload taking a std::vector if there is one.")]
+    UnsupportedInitializerList(String),
+    #[error("This function was listed in a slice_params! directive, but {0}. The data parameter must be a pointer to a primitive type, immediately followed by an integer length parameter.")]
+    UnsupportedSliceParams(String),
+    #[error("This function was listed in an out_array! directive, but {0}. The array must be returned through a pointer to a pointer parameter, or as the function's return value, and its length through a pointer to an integer parameter. If the array is returned through a parameter, the function may return only nothing or a primitive.")]
+    UnsupportedOutArray(String),
+    #[error("This std::pair or std::tuple can't be represented in Rust, because one of its elements is {0}. autocxx supports pairs and tuples of primitives, POD types and types which can be held in a UniquePtr, passed by value or by const reference or returned by value. They may not contain references, pointers, or further pairs or tuples.")]
+    UnsupportedTupleElement(String),
+    #[error("The superclass of this subclass, {0}, is an instantiation of a C++ template which isn't named in a concrete! directive. Add concrete!(\"{0}\", SomeName), so that autocxx looks for its virtual methods, and then subclass either.")]
+    SubclassOfTemplate(String),
+    #[error("The superclass of this subclass, {0}, has a private destructor. The C++ peer of a subclass has to be able to destroy its superclass, so this isn't possible. (A protected destructor is fine.)")]
+    SubclassOfPrivateDestructor(String),
+    #[error("The superclass of this subclass, {0}, is declared final, so it can't be subclassed.")]
+    SubclassOfFinal(String),
+    #[error("The subclass {0} can't be generated, because it would override {1}, which is declared final.")]
+    SubclassOverridingFinal(String, String),
+    #[error("operator{0} was listed in an operators! directive, but the type isn't POD. Only comparison operators are supported for non-POD types, since other operators take or return the type by value.")]
+    OperatorOnNonPod(String),
+    #[error("operator[] was listed in an operators! directive as returning a reference to {0}, but that isn't a type which autocxx knows about.")]
+    UnknownIndexOutput(String),
+    #[error("This operator() was listed in a call_operator! directive, but its first parameter isn't a reference to {0}. That parameter is the object on which it's called.")]
+    CallOperatorWithoutObject(String),
+    #[error("This function was listed in a friend_function! directive, but none of its parameters are of the class {0}, or references or pointers to it. C++ can find a friend function defined within a class only by argument-dependent lookup, which needs such a parameter.")]
+    FriendWithoutClassParameter(String),
+    #[error("This function was listed in a friend_function!, literal_operator!, instantiate! or call_operator! directive, but its signature uses {0}. The directive may use primitive types, and the names of C++ types, optionally behind references or pointers.")]
+    UnsupportedDeclaredFunctionType(String),
+    #[error("autocxx can't generate accessor methods for this map, because {0}. It supports std::map and std::unordered_map keyed by std::string or by an integer type, whose values are of a type which autocxx knows about.")]
+    UnsupportedMap(String),
+    #[error("autocxx can't generate accessor methods for this vector, because its elements point to {0}. It supports a std::vector of std::unique_ptrs, with the default deleter, to a type which autocxx knows about.")]
+    UnsupportedVectorOfUniquePtrs(String),
+    #[error("The field {0} was listed in a block_field! directive, but {1} has no such field.")]
+    BlockedFieldNotFound(String, String),
+    #[error("The field {0} of {1} was listed in a block_field! directive, but bindgen didn't tell autocxx where it is within the struct, or where the next field starts, so it can't be replaced with storage of the same size. Bit-fields can't be blocked.")]
+    BlockedFieldLayoutUnknown(String, String),
+    #[error("This function uses {0}, which needs {1}, but the generated C++ may only use {2}. Allow a later C++ standard, or write a C++ function which avoids {0} and generate bindings to that instead.")]
+    RequiresNewerCppStandard(&'static str, CppStandard, CppStandard),
+    #[error("{err}")]
+    UnsupportedTemplateInstantiation {
+        cpp_definition: String,
+        #[source]
+        err: Box<ConvertErrorFromCpp>,
+    },
+    #[error(
+        "The instantiation {signature} from an instantiate! directive can't be generated: {err}"
+    )]
+    Instantiation {
+        signature: String,
+        #[source]
+        err: Box<ConvertErrorFromCpp>,
+    },
+    #[error("This member function of {0} can't be called from Rust, because autocxx couldn't spell out its signature with the template arguments of this instantiation, or because it's variadic or &&-qualified. Consider adding a free function which calls it.")]
+    UnsupportedConcreteMember(String),
     #[error("Problem handling function argument {arg}: {err}")]
     Argument {
         arg: String,
         #[source]
         err: Box<ConvertErrorFromCpp>,
     },
+    #[error("{location}: {err}")]
+    Located {
+        location: SourceLocation,
+        #[source]
+        err: Box<ConvertErrorFromCpp>,
+    },
+}
+
+impl ConvertErrorFromCpp {
+    /// What the user might do about this error, naming the directive to
+    /// try where there is one.
+    pub(crate) fn suggestion(&self) -> Option<String> {
+        match self {
+            Self::Argument { err, .. }
+            | Self::Located { err, .. }
+            | Self::Instantiation { err, .. }
+            | Self::UnsupportedArrayField { err, .. }
+            | Self::UnsupportedBitfield { err, .. } => err.suggestion(),
+            Self::UnsupportedTemplateInstantiation { cpp_definition, .. } => Some(format!(
+                "Try concrete!(\"{cpp_definition}\", {}) to make an opaque Rust type for this instantiation, which functions using it will then take instead.",
+                concrete_type_name(cpp_definition)
+            )),
+            Self::Blocked(_, directive) => Some(format!(
+                "Remove the {directive} directive if you need this."
+            )),
+            Self::UnknownDependentType(qn) => Some(format!(
+                "Add generate!(\"{}\") so that autocxx knows about this type.",
+                qn.to_cpp_name()
+            )),
+            Self::IgnoredDependent(deps) => deps.iter().next().map(|dep| {
+                format!(
+                    "See the documentation for {} to find out why autocxx couldn't generate it.",
+                    dep.to_cpp_name()
+                )
+            }),
+            Self::ForwardDeclarationByValue(qn) => Some(format!(
+                "Take or return {} by reference or pointer instead, or #include the header which defines it before the header using it.",
+                qn.to_cpp_name()
+            )),
+            Self::TypeContainingForwardDeclaration(qn) => Some(format!(
+                "#include the header which defines {0} before the header using it, or, if {0} is a typedef to a complete type, try instantiable!(\"{0}\").",
+                qn.to_cpp_name()
+            )),
+            Self::UnsafePodType(_) => Some(
+                "Use generate! rather than generate_pod! for this type. It will be opaque to Rust, but can still be passed to and from C++ by value, held in a UniquePtr.".to_string(),
+            ),
+            Self::ImmovableValueParam(qn) => Some(format!(
+                "Write a C++ function which takes {} by reference instead, and generate! that.",
+                qn.to_cpp_name()
+            )),
+            Self::NoInputReference(_)
+            | Self::MultipleInputReferences(_)
+            | Self::NoMutableInputReference(_)
+            | Self::MultipleMutableInputReferences(_) => Some(
+                "Write a C++ function which returns a value or a pointer rather than a reference, or which takes exactly one reference, and generate! that.".to_string(),
+            ),
+            Self::RValueReturn => Some(
+                "Write a C++ function which returns by value instead, and generate! that."
+                    .to_string(),
+            ),
+            Self::StaticData(name) => Some(format!("Add generate!(\"{name}\").")),
+            _ => None,
+        }
+    }
+}
+
+/// A Rust name to suggest for a `concrete!` type, such as `VectorOfWidget`
+/// for `std::vector<Widget>`.
+fn concrete_type_name(cpp_definition: &str) -> String {
+    let mut words = cpp_definition
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty() && *word != "std")
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        });
+    let first = words.next().unwrap_or_default();
+    let rest: Vec<_> = words.collect();
+    if rest.is_empty() {
+        first
+    } else {
+        format!("{first}Of{}", rest.join(""))
+    }
 }
 
 /// Error types derived from Rust code. This is separate from [`ConvertError`] because these
@@ -6,7 +6,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use syn::Attribute;
+use indexmap::set::IndexSet as HashSet;
+use syn::{
+    visit_mut::{visit_attribute_mut, visit_item_mod_mut, VisitMut},
+    Attribute, Expr, ExprLit, Item, ItemMod, Lit, LitStr, Meta,
+};
 
 /// Returns the attribute (if any) which contains a doc comment.
 pub(super) fn get_doc_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
@@ -16,3 +20,475 @@ pub(super) fn get_doc_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
         .cloned()
         .collect()
 }
+
+/// Rewrites the doc comments which bindgen extracted from the C++
+/// so that common Doxygen markup reads reasonably in rustdoc.
+pub(super) fn translate_doxygen_in_items(items: &mut [Item]) {
+    let mut translator = DoxygenTranslator {
+        linkable: vec![linkable_names(items)],
+    };
+    for item in items {
+        translator.visit_item_mut(item);
+    }
+}
+
+/// The names of the types in a mod, to which a `@see` or `@ref` in the
+/// same mod can become an intra-doc link.
+fn linkable_names(items: &[Item]) -> HashSet<String> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) => Some(s.ident.to_string()),
+            Item::Enum(e) => Some(e.ident.to_string()),
+            Item::Union(u) => Some(u.ident.to_string()),
+            Item::Type(t) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+struct DoxygenTranslator {
+    /// Linkable names for each mod we're within, innermost last.
+    linkable: Vec<HashSet<String>>,
+}
+
+impl VisitMut for DoxygenTranslator {
+    fn visit_item_mod_mut(&mut self, m: &mut ItemMod) {
+        let names = m
+            .content
+            .as_ref()
+            .map(|(_, items)| linkable_names(items))
+            .unwrap_or_default();
+        self.linkable.push(names);
+        visit_item_mod_mut(self, m);
+        self.linkable.pop();
+    }
+
+    fn visit_attribute_mut(&mut self, attr: &mut Attribute) {
+        if let Meta::NameValue(nv) = &mut attr.meta {
+            if nv.path.is_ident("doc") {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) = &mut nv.value
+                {
+                    let linkable = self.linkable.last().cloned().unwrap_or_default();
+                    *s = LitStr::new(&translate_doxygen(&s.value(), &linkable), s.span());
+                }
+            }
+        }
+        visit_attribute_mut(self, attr)
+    }
+}
+
+/// The parts of a Doxygen comment which rustdoc convention puts into
+/// their own sections, in the order we emit them.
+#[derive(Default)]
+struct Sections {
+    body: Vec<String>,
+    arguments: Vec<String>,
+    returns: Vec<String>,
+    throws: Vec<String>,
+    see_also: Vec<String>,
+}
+
+/// Where continuation lines of a command go.
+enum Continuation {
+    Body,
+    LastArgument,
+    LastReturn,
+    LastThrow,
+}
+
+/// Translates Doxygen (or Javadoc) markup into Markdown. We strip comment
+/// decorations which bindgen left behind, gather `\param`, `\return`,
+/// `\throws` and `\see` into the usual rustdoc sections, turn
+/// `\code` blocks into fenced C++ blocks, and translate common inline
+/// commands. Anything we don't recognize is left alone.
+fn translate_doxygen(doc: &str, linkable: &HashSet<String>) -> String {
+    let lines = strip_decorations(doc);
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .min_by_key(|indent| indent.len())
+        .unwrap_or("")
+        .to_string();
+    let mut sections = Sections::default();
+    let mut continuation = Continuation::Body;
+    let mut in_code = false;
+    for line in &lines {
+        let line = line.strip_prefix(indent.as_str()).unwrap_or(line);
+        let content = line.trim();
+        if in_code {
+            if is_command(content, &["endcode", "endverbatim"]) {
+                sections.body.push("```".to_string());
+                in_code = false;
+            } else {
+                sections.body.push(line.to_string());
+            }
+            continue;
+        }
+        let (command, rest) = split_command(content);
+        match command {
+            Some("code") => {
+                // Doxygen allows `\code{.py}` to choose the language.
+                let language = rest
+                    .trim_start_matches(['{', '.'])
+                    .trim_end_matches('}')
+                    .trim();
+                let language = if language.is_empty() { "cpp" } else { language };
+                sections.body.push(format!("```{language}"));
+                in_code = true;
+                continuation = Continuation::Body;
+            }
+            Some("verbatim") => {
+                sections.body.push("```text".to_string());
+                in_code = true;
+                continuation = Continuation::Body;
+            }
+            Some(command) if command == "param" || command.starts_with("param[") => {
+                let (name, desc) = split_word(rest);
+                if name.is_empty() {
+                    sections.body.push(line.to_string());
+                    continue;
+                }
+                let direction = match command.trim_start_matches("param") {
+                    "[out]" => " (out)",
+                    "[in,out]" | "[out,in]" => " (in, out)",
+                    _ => "",
+                };
+                sections.arguments.push(list_entry(
+                    &format!("`{name}`{direction}"),
+                    &translate_inline(desc, linkable),
+                ));
+                continuation = Continuation::LastArgument;
+            }
+            Some("return" | "returns" | "result") => {
+                sections.returns.push(translate_inline(rest, linkable));
+                continuation = Continuation::LastReturn;
+            }
+            Some("retval") => {
+                let (value, desc) = split_word(rest);
+                sections.returns.push(list_entry(
+                    &format!("`{value}`"),
+                    &translate_inline(desc, linkable),
+                ));
+                continuation = Continuation::LastReturn;
+            }
+            Some("throws" | "throw" | "exception") => {
+                let (exception, desc) = split_word(rest);
+                sections.throws.push(list_entry(
+                    &format!("`{exception}`"),
+                    &translate_inline(desc, linkable),
+                ));
+                continuation = Continuation::LastThrow;
+            }
+            Some("see" | "sa") => {
+                sections.see_also.extend(
+                    rest.split(',')
+                        .map(str::trim)
+                        .filter(|target| !target.is_empty())
+                        .map(|target| format!("* {}", reference(target, linkable))),
+                );
+                continuation = Continuation::Body;
+            }
+            Some("brief" | "short") => {
+                sections.body.push(translate_inline(rest, linkable));
+                continuation = Continuation::Body;
+            }
+            Some("details") => {
+                if sections.body.last().is_some_and(|line| !line.is_empty()) {
+                    sections.body.push(String::new());
+                }
+                sections.body.push(translate_inline(rest, linkable));
+                continuation = Continuation::Body;
+            }
+            Some(
+                command @ ("note" | "warning" | "attention" | "deprecated" | "todo" | "pre"
+                | "post"),
+            ) => {
+                let label = match command {
+                    "note" => "Note",
+                    "warning" | "attention" => "Warning",
+                    "deprecated" => "Deprecated",
+                    "todo" => "To do",
+                    "pre" => "Precondition",
+                    _ => "Postcondition",
+                };
+                sections
+                    .body
+                    .push(format!("**{label}:** {}", translate_inline(rest, linkable)));
+                continuation = Continuation::Body;
+            }
+            Some(_) => {
+                sections.body.push(translate_inline(line, linkable));
+                continuation = Continuation::Body;
+            }
+            None if content.is_empty() => {
+                sections.body.push(String::new());
+                continuation = Continuation::Body;
+            }
+            None => {
+                let text = translate_inline(content, linkable);
+                let entry = match continuation {
+                    Continuation::Body => None,
+                    Continuation::LastArgument => sections.arguments.last_mut(),
+                    Continuation::LastReturn => sections.returns.last_mut(),
+                    Continuation::LastThrow => sections.throws.last_mut(),
+                };
+                match entry {
+                    Some(entry) => {
+                        entry.push(' ');
+                        entry.push_str(&text);
+                    }
+                    None => sections.body.push(translate_inline(line, linkable)),
+                }
+            }
+        }
+    }
+    if in_code {
+        sections.body.push("```".to_string());
+    }
+    sections.into_lines(&indent).join("\n")
+}
+
+impl Sections {
+    fn into_lines(mut self, indent: &str) -> Vec<String> {
+        while self.body.last().is_some_and(|line| line.is_empty()) {
+            self.body.pop();
+        }
+        let leading_blanks = self.body.iter().take_while(|line| line.is_empty()).count();
+        let mut lines = self.body.split_off(leading_blanks);
+        for (heading, entries) in [
+            ("Arguments", self.arguments),
+            ("Returns", self.returns),
+            ("Throws", self.throws),
+            ("See also", self.see_also),
+        ] {
+            if entries.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("# {heading}"));
+            lines.push(String::new());
+            lines.extend(entries);
+        }
+        lines
+            .into_iter()
+            .map(|line| {
+                if line.is_empty() {
+                    line
+                } else {
+                    format!("{indent}{line}")
+                }
+            })
+            .collect()
+    }
+}
+
+/// Removes the comment decorations which bindgen sometimes leaves behind.
+fn strip_decorations(doc: &str) -> Vec<String> {
+    let lines: Vec<&str> = doc.split('\n').collect();
+    // Only treat a leading '*' as decoration if every line has one;
+    // otherwise it's probably a Markdown list.
+    let all_starred = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.trim_start().starts_with('*'));
+    lines
+        .into_iter()
+        .map(|line| {
+            let content = line.trim_start();
+            let indent = &line[..line.len() - content.len()];
+            let content = strip_prefix_and_space(content, "///")
+                .or_else(|| strip_prefix_and_space(content, "//!"))
+                .or_else(|| {
+                    if all_starred {
+                        strip_prefix_and_space(content, "*")
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(content);
+            format!("{indent}{content}")
+        })
+        .collect()
+}
+
+fn strip_prefix_and_space<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.strip_prefix(prefix)
+        .map(|rest| rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+/// Splits a line starting with a `\command` or `@command` into the
+/// command and the rest.
+fn split_command(line: &str) -> (Option<&str>, &str) {
+    match line.strip_prefix('\\').or_else(|| line.strip_prefix('@')) {
+        Some(command_and_rest) if command_and_rest.starts_with(|c: char| c.is_alphabetic()) => {
+            let (command, rest) = split_word(command_and_rest);
+            // Allow `\code{.cpp}` with no space.
+            match command.split_once('{') {
+                Some((command, language)) if command == "code" => (Some(command), language),
+                _ => (Some(command), rest),
+            }
+        }
+        _ => (None, line),
+    }
+}
+
+fn is_command(line: &str, commands: &[&str]) -> bool {
+    matches!(split_command(line), (Some(command), _) if commands.contains(&command))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    (word, rest.trim_start())
+}
+
+fn list_entry(item: &str, desc: &str) -> String {
+    if desc.is_empty() {
+        format!("* {item}")
+    } else {
+        format!("* {item} - {desc}")
+    }
+}
+
+/// A reference to something else in the C++: an intra-doc link if it's a
+/// type in the same namespace, and otherwise just its name.
+fn reference(target: &str, linkable: &HashSet<String>) -> String {
+    if linkable.contains(target) {
+        format!("[`{target}`]")
+    } else {
+        format!("`{target}`")
+    }
+}
+
+/// Translates the inline commands which take the following word as their
+/// argument, such as `\p name` or `@ref Type`.
+fn translate_inline(text: &str, linkable: &HashSet<String>) -> String {
+    let mut words = text.split(' ').peekable();
+    let mut output = Vec::new();
+    while let Some(word) = words.next() {
+        let format: Option<fn(&str, &HashSet<String>) -> String> = match word {
+            "\\p" | "@p" | "\\c" | "@c" => Some(|arg, _| format!("`{arg}`")),
+            "\\a" | "@a" | "\\e" | "@e" | "\\em" | "@em" => Some(|arg, _| format!("*{arg}*")),
+            "\\b" | "@b" => Some(|arg, _| format!("**{arg}**")),
+            "\\ref" | "@ref" | "\\link" | "@link" => Some(reference),
+            _ => None,
+        };
+        match (format, words.peek()) {
+            (Some(format), Some(arg)) if !arg.is_empty() => {
+                // Keep trailing punctuation outside the markup.
+                let trimmed = arg.trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+                output.push(format!(
+                    "{}{}",
+                    format(trimmed, linkable),
+                    &arg[trimmed.len()..]
+                ));
+                words.next();
+            }
+            _ if matches!(word, "\\endlink" | "@endlink") => {}
+            _ => output.push(word.to_string()),
+        }
+    }
+    output.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::set::IndexSet as HashSet;
+
+    use super::translate_doxygen;
+
+    fn translate(doc: &str) -> String {
+        translate_doxygen(doc, &["Widget".to_string()].into_iter().collect())
+    }
+
+    #[test]
+    fn test_translate_params_and_return() {
+        assert_eq!(
+            translate(
+                " \\brief Adds things.\n \\param a first\n @param[out] b second\n @returns the sum"
+            ),
+            " Adds things.\n\n # Arguments\n\n * `a` - first\n * `b` (out) - second\n\n # Returns\n\n the sum"
+        );
+    }
+
+    #[test]
+    fn test_strip_decorations() {
+        assert_eq!(translate("* One\n* Two"), "One\nTwo");
+        assert_eq!(translate("/// One"), "One");
+        assert_eq!(
+            translate(" A list:\n * One\n * Two"),
+            " A list:\n * One\n * Two"
+        );
+    }
+
+    #[test]
+    fn test_unknown_commands_untouched() {
+        assert_eq!(
+            translate(" No markup.\n @frobnicate this\n Email me@example.com"),
+            " No markup.\n @frobnicate this\n Email me@example.com"
+        );
+        assert_eq!(translate_doxygen("", &HashSet::new()), "");
+    }
+
+    #[test]
+    fn test_translate_representative_comment() {
+        let doc = [
+            "*",
+            " * @brief Resizes a widget.",
+            " *",
+            " * Scales the widget by @p factor, like @ref Widget::scale does,",
+            " * keeping its \\b aspect ratio.",
+            " * \\code{.cpp}",
+            " * Widget w;",
+            " * w.resize(2.0);",
+            " * \\endcode",
+            " * @param factor how much to scale by,",
+            " *     which must be positive.",
+            " * @param[in,out] hint a size hint.",
+            " * @return whether it worked.",
+            " * @retval false if the widget was \\c frozen.",
+            " * @throws std::invalid_argument if @p factor is negative.",
+            " * @note This is slow.",
+            " * @see Widget, reset()",
+            " ",
+        ]
+        .join("\n");
+        let expected = [
+            " Resizes a widget.",
+            "",
+            " Scales the widget by `factor`, like `Widget::scale` does,",
+            " keeping its **aspect** ratio.",
+            " ```cpp",
+            " Widget w;",
+            " w.resize(2.0);",
+            " ```",
+            " **Note:** This is slow.",
+            "",
+            " # Arguments",
+            "",
+            " * `factor` - how much to scale by, which must be positive.",
+            " * `hint` (in, out) - a size hint.",
+            "",
+            " # Returns",
+            "",
+            " whether it worked.",
+            " * `false` - if the widget was `frozen`.",
+            "",
+            " # Throws",
+            "",
+            " * `std::invalid_argument` - if `factor` is negative.",
+            "",
+            " # See also",
+            "",
+            " * [`Widget`]",
+            " * `reset()`",
+        ]
+        .join("\n");
+        assert_eq!(translate(&doc), expected);
+    }
+}
@@ -6,10 +6,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    sync::Once,
+};
+
 use crate::minisyn::ItemEnum;
 
 use super::{
-    api::{AnalysisPhase, Api, ApiName, FuncToConvert, StructDetails, TypedefKind},
+    api::{AnalysisPhase, Api, ApiName, EnumAlias, FuncToConvert, StructDetails, TypedefKind},
     apivec::ApiVec,
     convert_error::{ConvertErrorWithContext, ErrorContext},
     ConvertErrorFromCpp,
@@ -19,18 +27,147 @@ use crate::{
     types::{Namespace, QualifiedName},
 };
 
+thread_local! {
+    /// Whether a panic while converting one item should be turned into an
+    /// error against that item, rather than abort the whole conversion.
+    static CONTAIN: Cell<bool> = const { Cell::new(false) };
+    /// The items whose conversion has panicked during this conversion,
+    /// and the panic messages.
+    static PANICKED: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+    /// Whether any item has panicked during this attempt at the conversion.
+    static PANICKED_THIS_ATTEMPT: Cell<bool> = const { Cell::new(false) };
+    /// How many items we're converting whose panics we'll contain. The
+    /// panic hook leaves their panics to us to report.
+    static CONTAINING: Cell<usize> = const { Cell::new(0) };
+    /// Where the latest contained panic happened.
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run a whole conversion. Unless `strict`, a panic while converting
+/// any one item is turned into an error against that item, and the
+/// conversion carries on, so that one declaration we can't cope with
+/// doesn't take down everything else.
+///
+/// The panic may have left whatever state the conversion had built up
+/// half-updated, so if any item panicked we start again from a fresh
+/// `input`, this time ignoring all such items. Usually that means one
+/// more attempt, however many items panicked.
+pub(crate) fn contain_item_panics<I, T>(
+    strict: bool,
+    mut input: impl FnMut() -> I,
+    mut convert: impl FnMut(I) -> T,
+) -> T {
+    let _reset = ResetPanicState(CONTAIN.with(|c| c.replace(!strict)));
+    if !strict {
+        install_panic_hook();
+    }
+    loop {
+        PANICKED_THIS_ATTEMPT.with(|p| p.set(false));
+        let input = input();
+        let result = catch_unwind(AssertUnwindSafe(|| convert(input)));
+        match (result, PANICKED_THIS_ATTEMPT.with(Cell::get)) {
+            (Ok(result), false) => return result,
+            // Nothing survives from an attempt which we abandon, even a
+            // panic which the half-updated state might have caused.
+            (_, true) => {}
+            (Err(payload), false) => resume_unwind(payload),
+        }
+    }
+}
+
+struct ResetPanicState(bool);
+
+impl Drop for ResetPanicState {
+    fn drop(&mut self) {
+        CONTAIN.with(|c| c.set(self.0));
+        PANICKED.with(|p| p.borrow_mut().clear());
+    }
+}
+
+/// Keeps the default panic hook from printing panics which we contain,
+/// since we report them as errors against the items instead. Other
+/// panics, including those on other threads, are printed as usual.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if CONTAINING.with(Cell::get) == 0 {
+                default_hook(info)
+            } else {
+                let location = info.location().map(ToString::to_string);
+                PANIC_LOCATION.with(|l| *l.borrow_mut() = location);
+            }
+        }));
+    });
+}
+
+/// Run some code which converts a single item, within
+/// [`contain_item_panics`]. If converting this item panicked in an earlier
+/// attempt, it's not run again; either way, the panic is turned into an
+/// error against the item.
+fn contain_panics<F, T>(
+    ns: &Namespace,
+    ctx: ErrorContext,
+    fun: F,
+) -> Result<T, ConvertErrorWithContext>
+where
+    F: FnOnce() -> Result<T, ConvertErrorWithContext>,
+{
+    if !CONTAIN.with(Cell::get) {
+        // In strict mode, a panic aborts the whole conversion.
+        return fun();
+    }
+    let key = format!("{ns}::{ctx}");
+    if let Some(msg) = PANICKED.with(|p| p.borrow().get(&key).cloned()) {
+        return Err(ConvertErrorWithContext(
+            ConvertErrorFromCpp::InternalError(msg),
+            Some(ctx),
+        ));
+    }
+    CONTAINING.with(|c| c.set(c.get() + 1));
+    let result = catch_unwind(AssertUnwindSafe(fun));
+    CONTAINING.with(|c| c.set(c.get() - 1));
+    result.unwrap_or_else(|payload| {
+        let msg = panic_message(payload);
+        let msg = match PANIC_LOCATION.with(|l| l.borrow_mut().take()) {
+            Some(location) => format!("{msg} at {location}"),
+            None => msg,
+        };
+        PANICKED.with(|p| p.borrow_mut().insert(key, msg.clone()));
+        PANICKED_THIS_ATTEMPT.with(|p| p.set(true));
+        Err(ConvertErrorWithContext(
+            ConvertErrorFromCpp::InternalError(msg),
+            Some(ctx),
+        ))
+    })
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
 /// Run some code which may generate a ConvertError.
 /// If it does, try to note the problem in our output APIs
 /// such that users will see documentation of the error.
+/// `panic_ctx` identifies the item being converted, should
+/// the code panic.
 pub(crate) fn report_any_error<F, T>(
     ns: &Namespace,
     apis: &mut ApiVec<impl AnalysisPhase>,
+    panic_ctx: ErrorContext,
     fun: F,
 ) -> Option<T>
 where
     F: FnOnce() -> Result<T, ConvertErrorWithContext>,
 {
-    match fun() {
+    match contain_panics(ns, panic_ctx, fun) {
         Ok(result) => Some(result),
         Err(ConvertErrorWithContext(err, None)) => {
             eprintln!("Ignored item: {err}");
@@ -78,6 +215,7 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
     EF: FnMut(
         ApiName,
         ItemEnum,
+        Vec<EnumAlias>,
     ) -> Result<Box<dyn Iterator<Item = Api<B>>>, ConvertErrorWithContext>,
     TF: FnMut(
         ApiName,
@@ -88,6 +226,8 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
 {
     out_apis.extend(in_apis.into_iter().flat_map(|api| {
         let fullname = api.name_info().clone();
+        let ns = fullname.name.get_namespace().clone();
+        let panic_ctx = ErrorContext::new_for_item(fullname.name.get_final_ident());
         let result: Result<Box<dyn Iterator<Item = Api<B>>>, ConvertErrorWithContext> = match api {
             // No changes to any of these...
             Api::ConcreteType {
@@ -115,8 +255,24 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
             Api::StringConstructor { name } => {
                 Ok(Box::new(std::iter::once(Api::StringConstructor { name })))
             }
-            Api::Const { name, const_item } => {
-                Ok(Box::new(std::iter::once(Api::Const { name, const_item })))
+            Api::Const {
+                name,
+                const_item,
+                member_of,
+            } => Ok(Box::new(std::iter::once(Api::Const {
+                name,
+                const_item,
+                member_of,
+            }))),
+            Api::RawFunction { name, item, deps } => {
+                Ok(Box::new(std::iter::once(Api::RawFunction {
+                    name,
+                    item,
+                    deps,
+                })))
+            }
+            Api::BitfieldUnit { name, items } => {
+                Ok(Box::new(std::iter::once(Api::BitfieldUnit { name, items })))
             }
             Api::CType { name, typename } => {
                 Ok(Box::new(std::iter::once(Api::CType { name, typename })))
@@ -133,6 +289,12 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
                 details,
                 deps,
             }))),
+            Api::ClosureHolder { name, details } => {
+                Ok(Box::new(std::iter::once(Api::ClosureHolder {
+                    name,
+                    details,
+                })))
+            }
             Api::RustSubclassFn {
                 name,
                 subclass,
@@ -167,23 +329,31 @@ pub(crate) fn convert_apis<FF, SF, EF, TF, A, B: 'static>(
                 })))
             }
             // Apply a mapping to the following
-            Api::Enum { name, item } => enum_conversion(name, item),
+            Api::Enum {
+                name,
+                item,
+                aliases,
+            } => contain_panics(&ns, panic_ctx, || enum_conversion(name, item, aliases)),
             Api::Typedef {
                 name,
                 item,
                 old_tyname,
                 analysis,
-            } => typedef_conversion(name, item, old_tyname, analysis),
+            } => contain_panics(&ns, panic_ctx, || {
+                typedef_conversion(name, item, old_tyname, analysis)
+            }),
             Api::Function {
                 name,
                 fun,
                 analysis,
-            } => func_conversion(name, fun, analysis),
+            } => contain_panics(&ns, panic_ctx, || func_conversion(name, fun, analysis)),
             Api::Struct {
                 name,
                 details,
                 analysis,
-            } => struct_conversion(name, details, analysis),
+            } => contain_panics(&ns, panic_ctx, || {
+                struct_conversion(name, details, analysis)
+            }),
         };
         api_or_error(fullname, result)
     }))
@@ -216,9 +386,9 @@ pub(crate) fn convert_item_apis<F, A, B: 'static>(
 {
     out_apis.extend(in_apis.into_iter().flat_map(|api| {
         let fullname = api.name_info().clone();
-        let tn = api.name().clone();
-        let result = fun(api).map_err(|e| {
-            ConvertErrorWithContext(e, Some(ErrorContext::new_for_item(tn.get_final_ident())))
+        let ctx = ErrorContext::new_for_item(api.name().get_final_ident());
+        let result = contain_panics(fullname.name.get_namespace(), ctx.clone(), || {
+            fun(api).map_err(|e| ConvertErrorWithContext(e, Some(ctx)))
         });
         api_or_error(fullname, result)
     }))
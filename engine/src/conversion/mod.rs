@@ -24,28 +24,52 @@ use analysis::fun::FnAnalyzer;
 use autocxx_parser::IncludeCppConfig;
 pub(crate) use codegen_cpp::CppCodeGenerator;
 pub(crate) use convert_error::ConvertError;
-use convert_error::ConvertErrorFromCpp;
+pub use convert_error::ConvertErrorFromCpp;
+use convert_error::{ErrorContext, ErrorContextType};
 use itertools::Itertools;
-use syn::{Item, ItemMod};
+pub(crate) use parse::find_nested_type_names;
+use quote::quote;
+use syn::{parse_quote, Item, ItemMod};
 
-use crate::{CodegenOptions, CppFilePair, UnsafePolicy};
+use crate::api_filter::{ApiFilter, ApiSummary};
+use crate::api_report::{sort_reported_apis, ApiKind, ReportedApi};
+use crate::generated_types::{GeneratedType, ImportedType};
+use crate::source_locations::SourceLocator;
+use crate::types::make_ident;
+use crate::{CodegenOptions, CppFilePair, HeaderInclusion, IgnoredApi, UnsafePolicy};
 
 use self::{
+    analysis::fun::{FnKind, FnPhase},
     analysis::{
         abstract_types::{discard_ignored_functions, mark_types_abstract},
+        addresses::create_address_methods,
         allocators::create_alloc_and_frees,
         casts::add_casts,
-        check_names,
+        cfg_features::find_cfg_features,
+        check_names, check_renames,
+        concrete_members::create_concrete_members,
         constructor_deps::decorate_types_with_constructor_deps,
+        debug_impls::create_debug_impls,
+        declared_functions::create_declared_functions,
+        explicit_destructors::create_explicit_destructors,
         gc::filter_apis_by_following_edges_from_allowlist,
+        iterators::create_iterator_impls,
+        layouts::create_layout_shims,
+        locations::add_source_locations,
+        maps::create_map_accessors,
+        operators::create_operator_impls,
         pod::analyze_pod_apis,
+        protected_fields::create_protected_field_accessors,
         remove_ignored::filter_apis_by_ignored_dependents,
         replace_hopeless_typedef_targets,
+        shared_ptrs::create_shared_ptr_conversions,
         tdef::convert_typedef_targets,
+        vectors::create_vector_accessors,
     },
-    api::AnalysisPhase,
+    api::{AnalysisPhase, Api, Deprecation, Provenance, TypeKind},
     apivec::ApiVec,
     codegen_rs::RsCodeGenerator,
+    error_reporter::contain_item_panics,
     parse::ParseBindgen,
 };
 
@@ -64,8 +88,9 @@ const LOG_APIS: bool = true;
 /// if the bindgen output is not as expected. It may be in future that
 /// we need to be a bit more graceful, but for now, that's OK.
 pub(crate) struct BridgeConverter<'a> {
-    include_list: &'a [String],
+    include_list: &'a [HeaderInclusion],
     config: &'a IncludeCppConfig,
+    imported_types: &'a [ImportedType],
 }
 
 /// C++ and Rust code generation output.
@@ -73,13 +98,21 @@ pub(crate) struct CodegenResults {
     pub(crate) rs: Vec<Item>,
     pub(crate) cpp: Option<CppFilePair>,
     pub(crate) cxxgen_header_name: String,
+    pub(crate) ignored_apis: Vec<IgnoredApi>,
+    pub(crate) generated_types: Vec<GeneratedType>,
+    pub(crate) reported_apis: Vec<ReportedApi>,
 }
 
 impl<'a> BridgeConverter<'a> {
-    pub fn new(include_list: &'a [String], config: &'a IncludeCppConfig) -> Self {
+    pub fn new(
+        include_list: &'a [HeaderInclusion],
+        config: &'a IncludeCppConfig,
+        imported_types: &'a [ImportedType],
+    ) -> Self {
         Self {
             include_list,
             config,
+            imported_types,
         }
     }
 
@@ -102,20 +135,43 @@ impl<'a> BridgeConverter<'a> {
     /// This is really the heart of autocxx. It parses the output of `bindgen`
     /// (although really by "parse" we mean to interpret the structures already built
     /// up by the `syn` crate).
+    ///
+    /// `bindgen_mod` provides those bindings, afresh if the conversion
+    /// has to be attempted again after an item panicked.
     pub(crate) fn convert(
+        &self,
+        bindgen_mod: impl FnMut() -> ItemMod,
+        unsafe_policy: UnsafePolicy,
+        codegen_options: &CodegenOptions,
+        source_file_contents: &str,
+        source_locator: &SourceLocator,
+    ) -> Result<CodegenResults, ConvertError> {
+        contain_item_panics(codegen_options.strict, bindgen_mod, |bindgen_mod| {
+            self.convert_once(
+                bindgen_mod,
+                unsafe_policy.clone(),
+                codegen_options,
+                source_file_contents,
+                source_locator,
+            )
+        })
+    }
+
+    /// A single attempt at [`Self::convert`].
+    fn convert_once(
         &self,
         mut bindgen_mod: ItemMod,
         unsafe_policy: UnsafePolicy,
-        inclusions: String,
         codegen_options: &CodegenOptions,
         source_file_contents: &str,
+        source_locator: &SourceLocator,
     ) -> Result<CodegenResults, ConvertError> {
         match &mut bindgen_mod.content {
             None => Err(ConvertError::NoContent),
             Some((_, items)) => {
                 // Parse the bindgen mod.
                 let items_to_process = std::mem::take(items);
-                let parser = ParseBindgen::new(self.config);
+                let parser = ParseBindgen::new(self.config, self.imported_types);
                 let apis = parser.parse_items(items_to_process, source_file_contents)?;
                 Self::dump_apis("parsing", &apis);
                 // Inside parse_results, we now have a list of APIs.
@@ -137,6 +193,18 @@ impl<'a> BridgeConverter<'a> {
                 let analyzed_apis = replace_hopeless_typedef_targets(self.config, analyzed_apis);
                 let analyzed_apis = add_casts(analyzed_apis);
                 let analyzed_apis = create_alloc_and_frees(analyzed_apis);
+                let analyzed_apis = create_explicit_destructors(analyzed_apis, self.config);
+                let analyzed_apis = create_shared_ptr_conversions(analyzed_apis);
+                let analyzed_apis = create_debug_impls(analyzed_apis, self.config);
+                let analyzed_apis = create_address_methods(analyzed_apis);
+                let analyzed_apis = create_layout_shims(analyzed_apis, self.config);
+                let analyzed_apis = create_operator_impls(analyzed_apis, self.config);
+                let analyzed_apis = create_declared_functions(analyzed_apis, self.config);
+                let analyzed_apis = create_concrete_members(analyzed_apis);
+                let analyzed_apis = create_protected_field_accessors(analyzed_apis);
+                let analyzed_apis = create_map_accessors(analyzed_apis, self.config);
+                let analyzed_apis = create_vector_accessors(analyzed_apis, self.config);
+                let analyzed_apis = create_iterator_impls(analyzed_apis, self.config);
                 // Next, figure out how we materialize different functions.
                 // Some will be simple entries in the cxx::bridge module; others will
                 // require C++ wrapper functions. This is probably the most complex
@@ -148,6 +216,8 @@ impl<'a> BridgeConverter<'a> {
                     &unsafe_policy,
                     self.config,
                     codegen_options.force_wrapper_gen,
+                    codegen_options.fallible_by_default,
+                    codegen_options.cpp_codegen_options.max_cpp_standard,
                 );
                 // If any of those functions turned out to be pure virtual, don't attempt
                 // to generate UniquePtr implementations for the type, since it can't
@@ -159,8 +229,21 @@ impl<'a> BridgeConverter<'a> {
                 // we may want to retain to avoid garbage collecting them later.
                 let analyzed_apis = decorate_types_with_constructor_deps(analyzed_apis);
                 Self::dump_apis("adding constructor deps", &analyzed_apis);
+                // Any function whose C++ wrapper we'd fail to generate is
+                // ignored now, rather than failing the whole conversion later.
+                let analyzed_apis = CppCodeGenerator::ignore_functions_without_cpp(
+                    analyzed_apis,
+                    self.config,
+                    &codegen_options.cpp_codegen_options,
+                );
                 let analyzed_apis = discard_ignored_functions(analyzed_apis);
                 Self::dump_apis("ignoring ignorable fns", &analyzed_apis);
+                // Let whoever is embedding us drop or adjust APIs, before
+                // we work out what depended on any which were dropped.
+                let analyzed_apis = match &codegen_options.api_filter {
+                    Some(api_filter) => self.apply_api_filter(analyzed_apis, api_filter),
+                    None => analyzed_apis,
+                };
                 // Remove any APIs whose names are not compatible with cxx.
                 let analyzed_apis = check_names(analyzed_apis);
                 // During parsing or subsequent processing we might have encountered
@@ -169,19 +252,64 @@ impl<'a> BridgeConverter<'a> {
                 // too.
                 let analyzed_apis = filter_apis_by_ignored_dependents(analyzed_apis);
                 Self::dump_apis("removing ignored dependents", &analyzed_apis);
+                let analyzed_apis = add_source_locations(analyzed_apis, source_locator);
+                // Note what we're ignoring before garbage collection throws
+                // away what nothing needs, so that we can report it all.
+                let ignored_before_gc: Vec<_> = analyzed_apis
+                    .iter()
+                    .filter(|api| matches!(api, Api::IgnoredItem { .. }))
+                    .filter_map(|api| self.reported_api(api))
+                    .collect();
 
                 // We now garbage collect the ones we don't need...
-                let mut analyzed_apis =
+                let (mut analyzed_apis, garbage) =
                     filter_apis_by_following_edges_from_allowlist(analyzed_apis, self.config);
+                // ... and report those we found in the C++ but threw away,
+                // so that they can be told apart from those we couldn't
+                // generate.
+                let garbage_collected = garbage
+                    .iter()
+                    .filter(|api| !matches!(api, Api::IgnoredItem { .. }))
+                    .filter_map(|api| self.reported_api(api))
+                    .map(|report| {
+                        let err = ConvertErrorFromCpp::GarbageCollected;
+                        ReportedApi {
+                            rust_name: None,
+                            ignored_reason: Some(err.to_string()),
+                            error: Some(err),
+                            ..report
+                        }
+                    });
+                check_renames(&analyzed_apis, self.config).map_err(ConvertError::Cpp)?;
+                let ignored_apis = self.find_requested_ignored_apis(&analyzed_apis);
+                let generated_types = self.find_generated_types(&analyzed_apis);
+                let mut reported_apis: Vec<_> = analyzed_apis
+                    .iter()
+                    .filter_map(|api| self.reported_api(api))
+                    .chain(ignored_before_gc)
+                    .chain(garbage_collected)
+                    .collect();
+                sort_reported_apis(&mut reported_apis);
                 // Determine what variably-sized C types (e.g. int) we need to include
                 analysis::ctypes::append_ctype_information(&mut analyzed_apis);
                 Self::dump_apis("GC", &analyzed_apis);
+                if codegen_options.stable_output {
+                    analyzed_apis.sort_by_name();
+                }
+                // Work out which bindings should be gated behind Cargo features.
+                let cfg_features =
+                    find_cfg_features(&analyzed_apis, self.config, &codegen_options.cfg_features);
                 // And finally pass them to the code gen phases, which outputs
                 // code suitable for cxx to consume.
                 let cxxgen_header_name = codegen_options
                     .cpp_codegen_options
                     .cxxgen_header_namer
                     .name_header();
+                let inclusions = self
+                    .include_list
+                    .iter()
+                    .map(|inclusion| format!("{}\n", inclusion.include_stmt()))
+                    .join("");
                 let cpp = CppCodeGenerator::generate_cpp_code(
                     inclusions,
                     &analyzed_apis,
@@ -196,14 +324,254 @@ impl<'a> BridgeConverter<'a> {
                     self.include_list,
                     bindgen_mod,
                     self.config,
-                    cpp.as_ref().map(|file_pair| file_pair.header_name.clone()),
+                    cpp.as_ref().map(|file_pair| {
+                        format!(
+                            "{}{}",
+                            codegen_options
+                                .cpp_codegen_options
+                                .path_to_autocxxgen_h
+                                .as_deref()
+                                .unwrap_or_default(),
+                            file_pair.header_name
+                        )
+                    }),
+                    cfg_features,
+                    codegen_options.suppress_ignored_api_stubs,
                 );
                 Ok(CodegenResults {
                     rs,
                     cpp,
                     cxxgen_header_name,
+                    ignored_apis,
+                    generated_types,
+                    reported_apis,
                 })
             }
         }
     }
+
+    /// Finds the APIs which the user specifically asked for (or which
+    /// belong to types they asked for) but which we couldn't generate.
+    fn find_requested_ignored_apis(&self, apis: &ApiVec<FnPhase>) -> Vec<IgnoredApi> {
+        apis.iter()
+            .filter_map(|api| match api {
+                Api::IgnoredItem {
+                    name,
+                    err,
+                    ctx: Some(ctx),
+                } if !matches!(err, ConvertErrorFromCpp::FilteredByUser)
+                    && self
+                        .config
+                        .is_explicitly_requested(&api.name_for_allowlist().to_cpp_name()) =>
+                {
+                    let ns = name.name.get_namespace();
+                    Some(IgnoredApi {
+                        name: if ns.is_empty() {
+                            ctx.to_string()
+                        } else {
+                            format!("{ns}::{ctx}")
+                        },
+                        reason: err.to_string(),
+                        suggestion: err.suggestion(),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Asks the [`ApiFilter`] what to do with each API which we'd report,
+    /// and does it. Excluded APIs become [`Api::IgnoredItem`]s, so that
+    /// anything which depends on them is ignored too.
+    fn apply_api_filter(&self, apis: ApiVec<FnPhase>, api_filter: &ApiFilter) -> ApiVec<FnPhase> {
+        apis.into_iter()
+            .map(|mut api| {
+                let report = match &api {
+                    Api::IgnoredItem { .. } => None,
+                    _ => self.reported_api(&api),
+                };
+                let report = match report {
+                    Some(report) => report,
+                    None => return api,
+                };
+                let signature = match &api {
+                    Api::Function { analysis, .. } => {
+                        let ident = make_ident(&analysis.rust_name);
+                        let params = &analysis.params;
+                        let ret_type = &analysis.ret_type;
+                        Some(quote! { fn #ident(#params) #ret_type }.to_string())
+                    }
+                    _ => None,
+                };
+                let decision = api_filter.decide(&ApiSummary {
+                    name: report.name,
+                    kind: report.kind,
+                    rust_name: report.rust_name.unwrap_or_default(),
+                    signature,
+                    requested_by: report.requested_by,
+                });
+                if decision.exclude {
+                    let ctx = match &api {
+                        Api::Function { analysis, .. } => match &analysis.kind {
+                            FnKind::TraitMethod { .. } => None,
+                            FnKind::Method { impl_for, .. } => Some(ErrorContext::new_for_method(
+                                impl_for.get_final_ident(),
+                                make_ident(&analysis.rust_name),
+                            )),
+                            FnKind::Function => {
+                                Some(ErrorContext::new_for_item(make_ident(&analysis.rust_name)))
+                            }
+                        },
+                        _ => Some(ErrorContext::new_for_item(api.name().get_final_ident())),
+                    };
+                    return Api::IgnoredItem {
+                        name: api.name_info().clone(),
+                        err: ConvertErrorFromCpp::FilteredByUser,
+                        ctx,
+                    };
+                }
+                let docs = decision.extra_docs.iter().map(|doc| -> syn::Attribute {
+                    parse_quote! { #[doc = #doc] }
+                });
+                let deprecation = decision.deprecated.map(|note| Deprecation {
+                    message: Some(note),
+                });
+                match &mut api {
+                    Api::Function { fun, .. } => {
+                        fun.doc_attrs.extend(docs.map(Into::into));
+                        fun.must_use |= decision.must_use;
+                        if deprecation.is_some() {
+                            fun.deprecated = deprecation;
+                        }
+                    }
+                    Api::Struct { details, .. } => {
+                        details.item.attrs.extend(docs);
+                        if deprecation.is_some() {
+                            details.deprecated = deprecation;
+                        }
+                    }
+                    Api::Enum { item, .. } => item.attrs.extend(docs),
+                    _ => {}
+                }
+                api
+            })
+            .collect()
+    }
+
+    /// Describes an API for the API report, if it's one which the user
+    /// might have expected us to find in the C++, rather than something
+    /// we synthesized.
+    fn reported_api(&self, api: &Api<FnPhase>) -> Option<ReportedApi> {
+        let name = api.name();
+        let qualify = |item: &str| {
+            let ns = name.get_namespace();
+            if ns.is_empty() {
+                item.to_string()
+            } else {
+                format!("{ns}::{item}")
+            }
+        };
+        let (reported_name, kind, rust_name, error) = match api {
+            Api::Function { fun, analysis, .. } => {
+                if !matches!(fun.provenance, Provenance::Bindgen) {
+                    return None;
+                }
+                let fn_name = fun
+                    .original_name
+                    .clone()
+                    .unwrap_or_else(|| fun.ident.to_string());
+                match &analysis.kind {
+                    FnKind::Method { impl_for, .. } | FnKind::TraitMethod { impl_for, .. } => (
+                        format!("{}::{fn_name}", impl_for.to_cpp_name()),
+                        ApiKind::Method,
+                        Some(format!(
+                            "{}::{}",
+                            impl_for.to_cpp_name(),
+                            analysis.rust_name
+                        )),
+                        None,
+                    ),
+                    FnKind::Function => (
+                        qualify(&fn_name),
+                        ApiKind::Function,
+                        Some(qualify(&analysis.rust_name)),
+                        None,
+                    ),
+                }
+            }
+            Api::IgnoredItem { err, ctx, .. } => match ctx {
+                Some(ctx) => (
+                    qualify(&ctx.to_string()),
+                    match ctx.get_type() {
+                        ErrorContextType::Method { .. } => ApiKind::Method,
+                        _ => ApiKind::Item,
+                    },
+                    None,
+                    Some(err.clone()),
+                ),
+                None => (name.to_cpp_name(), ApiKind::Item, None, Some(err.clone())),
+            },
+            _ => {
+                let (reported_name, kind) = match api {
+                    Api::RawFunction { .. } => (name.to_cpp_name(), ApiKind::Function),
+                    Api::Struct { .. } | Api::ForwardDeclaration { .. } => {
+                        (name.to_cpp_name(), ApiKind::Type)
+                    }
+                    Api::Typedef { .. } | Api::OpaqueTypedef { .. } => {
+                        (name.to_cpp_name(), ApiKind::Typedef)
+                    }
+                    Api::Enum { .. } => (name.to_cpp_name(), ApiKind::Enum),
+                    Api::Const { .. } => (name.to_cpp_name(), ApiKind::Constant),
+                    Api::ConcreteType { cpp_definition, .. } => {
+                        (cpp_definition.clone(), ApiKind::TemplateInstantiation)
+                    }
+                    _ => return None,
+                };
+                (reported_name, kind, Some(name.to_cpp_name()), None)
+            }
+        };
+        Some(ReportedApi {
+            name: reported_name,
+            qualified_name: name.clone(),
+            kind,
+            rust_name,
+            ignored_reason: error.as_ref().map(|err| err.to_string()),
+            error,
+            requested_by: self
+                .config
+                .requested_by(&api.name_for_allowlist().to_cpp_name()),
+        })
+    }
+
+    /// Finds the types for which we're generating bindings, so that
+    /// other `include_cpp!`s can use them.
+    fn find_generated_types(&self, apis: &ApiVec<FnPhase>) -> Vec<GeneratedType> {
+        apis.iter()
+            .filter_map(|api| {
+                let pod = match api {
+                    Api::Struct { analysis, .. } => matches!(analysis.pod.kind, TypeKind::Pod),
+                    Api::Enum { .. } => true,
+                    _ => return None,
+                };
+                let name = api.name();
+                // Any `rename!` of the type, or `rename!` or `map_namespace!`
+                // of the namespaces it's in, changes where it ends up in the
+                // generated mod.
+                let cpp_namespace: Vec<_> = name.ns_segment_iter().cloned().collect();
+                let mut rust_path = self.config.get_rust_mod_path(&cpp_namespace);
+                let cpp_name = name.to_cpp_name();
+                rust_path.push(
+                    self.config
+                        .get_rust_rename(&cpp_name)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| name.get_final_item().to_string()),
+                );
+                Some(GeneratedType {
+                    cpp_name,
+                    rust_path: rust_path.join("::"),
+                    pod,
+                })
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,208 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen names anonymous types `_bindgen_ty_N` (or `Outer__bindgen_ty_N`
+//! when nested) and cxx can't cope with either. Here we rewrite the bindgen
+//! output before we parse it so that, where possible, such types end up
+//! with usable names.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use syn::{
+    parse_quote,
+    visit_mut::{visit_ident_mut, VisitMut},
+    Attribute, Expr, Ident, Item, ItemEnum, ItemType, Type,
+};
+
+use super::bindgen_semantic_attributes::BindgenSemanticAttributes;
+
+/// Rewrites the items found in the bindgen `root` mod:
+/// * an anonymous struct or enum which is named by a typedef
+///   (`typedef struct { .. } Point;`) takes the name of the typedef,
+///   and the typedef is removed;
+/// * constants generated for the values of anonymous enums are given
+///   the underlying integer type of the enum.
+pub(super) fn resolve_anonymous_types(items: &mut Vec<Item>) {
+    let mut anonymous_types = AnonymousTypes::default();
+    anonymous_types.find_in_mod(items);
+    anonymous_types.rewrite_mod(items);
+    let mut renamer = Renamer(
+        anonymous_types
+            .typedef_names
+            .into_iter()
+            .map(|(anon_id, typedef)| (anon_id, typedef.ident))
+            .collect(),
+    );
+    for item in items {
+        renamer.visit_item_mut(item);
+    }
+}
+
+fn is_anonymous(id: &Ident) -> bool {
+    let id = id.to_string();
+    id.starts_with("_bindgen_ty_") || id.contains("__bindgen_ty_")
+}
+
+/// The final segment of a plain type path, if that's what this is.
+fn final_ident(ty: &Type) -> Option<&Ident> {
+    match ty {
+        Type::Path(tp) => tp.path.segments.last().map(|seg| &seg.ident),
+        _ => None,
+    }
+}
+
+struct AnonymousEnum {
+    repr: Type,
+    values: HashMap<Ident, Expr>,
+}
+
+#[derive(Default)]
+struct AnonymousTypes {
+    /// Anonymous structs and enums, keyed by their bindgen name, mapped
+    /// to the typedef which names them.
+    typedef_names: HashMap<Ident, ItemType>,
+    /// Anonymous enums which no typedef names.
+    enums: HashMap<Ident, AnonymousEnum>,
+}
+
+impl AnonymousTypes {
+    fn find_in_mod(&mut self, items: &[Item]) {
+        let mut anonymous_structs_and_enums = HashSet::new();
+        let mut candidate_typedefs = Vec::new();
+        for item in items {
+            match item {
+                Item::Mod(itm) => {
+                    if let Some((_, items)) = &itm.content {
+                        self.find_in_mod(items);
+                    }
+                }
+                Item::Struct(s) if is_anonymous(&s.ident) => {
+                    anonymous_structs_and_enums.insert(s.ident.clone());
+                }
+                Item::Enum(e) if is_anonymous(&e.ident) => {
+                    anonymous_structs_and_enums.insert(e.ident.clone());
+                    if let Some(anon_enum) = Self::enum_values(e) {
+                        self.enums.insert(e.ident.clone(), anon_enum);
+                    }
+                }
+                Item::Type(ity) if !is_anonymous(&ity.ident) && ity.generics.params.is_empty() => {
+                    if let Some(target) = final_ident(&ity.ty) {
+                        if is_anonymous(target) {
+                            candidate_typedefs.push((target.clone(), ity));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // If several typedefs name the same anonymous type, the first wins
+        // and the others remain typedefs to it.
+        for (target, ity) in candidate_typedefs {
+            if anonymous_structs_and_enums.contains(&target)
+                && !self.typedef_names.contains_key(&target)
+            {
+                self.enums.remove(&target);
+                self.typedef_names.insert(target, ity.clone());
+            }
+        }
+    }
+
+    fn enum_values(e: &ItemEnum) -> Option<AnonymousEnum> {
+        let repr = e
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("repr"))
+            .and_then(|attr| attr.parse_args::<Ident>().ok())?;
+        let values = e
+            .variants
+            .iter()
+            .filter_map(|v| {
+                v.discriminant
+                    .as_ref()
+                    .map(|(_, value)| (v.ident.clone(), value.clone()))
+            })
+            .collect();
+        Some(AnonymousEnum {
+            repr: parse_quote! { #repr },
+            values,
+        })
+    }
+
+    fn rewrite_mod(&self, items: &mut Vec<Item>) {
+        items.retain(|item| match item {
+            Item::Type(ity) => !final_ident(&ity.ty)
+                .and_then(|target| self.typedef_names.get(target))
+                .map(|typedef| typedef.ident == ity.ident)
+                .unwrap_or_default(),
+            _ => true,
+        });
+        for item in items {
+            match item {
+                Item::Mod(itm) => {
+                    if let Some((_, items)) = &mut itm.content {
+                        self.rewrite_mod(items);
+                    }
+                }
+                Item::Struct(s) => {
+                    if let Some(typedef) = self.typedef_names.get(&s.ident) {
+                        s.attrs.extend(Self::attrs_to_inherit(typedef));
+                    }
+                }
+                Item::Enum(e) => {
+                    if let Some(typedef) = self.typedef_names.get(&e.ident) {
+                        e.attrs.extend(Self::attrs_to_inherit(typedef));
+                    }
+                }
+                Item::Const(const_item) => {
+                    let anon_enum = final_ident(&const_item.ty).and_then(|ty| self.enums.get(ty));
+                    let value = match (anon_enum, &*const_item.expr) {
+                        (Some(anon_enum), Expr::Path(value)) => value
+                            .path
+                            .segments
+                            .last()
+                            .and_then(|seg| anon_enum.values.get(&seg.ident))
+                            .map(|value| (anon_enum.repr.clone(), value.clone())),
+                        _ => None,
+                    };
+                    if let Some((ty, value)) = value {
+                        *const_item.ty = ty;
+                        *const_item.expr = value;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The typedef knows the C++ name and documentation of the type
+    /// it names, which the anonymous type itself does not.
+    fn attrs_to_inherit(typedef: &ItemType) -> impl Iterator<Item = Attribute> + '_ {
+        typedef
+            .attrs
+            .iter()
+            .filter(|attr| {
+                attr.path().is_ident("doc")
+                    || BindgenSemanticAttributes::new(std::slice::from_ref(attr))
+                        .get_original_name()
+                        .is_some()
+            })
+            .cloned()
+    }
+}
+
+struct Renamer(HashMap<Ident, Ident>);
+
+impl VisitMut for Renamer {
+    fn visit_ident_mut(&mut self, id: &mut Ident) {
+        if let Some(new_id) = self.0.get(id) {
+            *id = new_id.clone();
+        }
+        visit_ident_mut(self, id)
+    }
+}
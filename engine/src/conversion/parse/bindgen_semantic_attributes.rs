@@ -6,15 +6,20 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use indexmap::map::IndexMap as HashMap;
+
 use proc_macro2::{Ident, TokenStream};
 use syn::{
     parenthesized,
-    parse::{Parse, Parser},
-    Attribute, LitStr,
+    parse::{Parse, ParseStream, Parser},
+    Attribute, LitStr, Token,
 };
 
 use crate::conversion::{
-    api::{CppVisibility, DeletedOrDefaulted, Layout, References, SpecialMemberKind, Virtualness},
+    api::{
+        CppVisibility, DeletedOrDefaulted, Deprecation, Layout, References, SpecialMemberKind,
+        Virtualness,
+    },
     convert_error::{ConvertErrorWithContext, ErrorContext},
     ConvertErrorFromCpp,
 };
@@ -76,8 +81,63 @@ impl BindgenSemanticAttributes {
         self.0.iter().any(|a| a.is_ident(attr_name))
     }
 
+    /// Whether the function is declared `noexcept`.
+    pub(super) fn is_noexcept(&self) -> bool {
+        self.has_attr("noexcept")
+    }
+
+    /// Whether the method is `&&` qualified, so may only be called on an
+    /// rvalue.
+    pub(super) fn is_rvalue_ref_qualified(&self) -> bool {
+        self.has_attr("rvalue_ref_qualified")
+    }
+
+    /// The `[[deprecated]]` attribute, with its reason if it gives one.
+    pub(super) fn get_deprecation(&self) -> Option<Deprecation> {
+        self.0
+            .iter()
+            .find(|a| a.is_ident("deprecated"))
+            .map(|a| Deprecation {
+                message: a
+                    .body
+                    .as_ref()
+                    .and_then(|_| a.parse_args::<LitStr>().ok())
+                    .map(|ls| ls.value()),
+            })
+    }
+
+    /// Whether the function is declared `[[nodiscard]]`.
+    pub(super) fn is_nodiscard(&self) -> bool {
+        self.has_attr("nodiscard")
+    }
+
+    /// Whether the class or virtual method is declared `final`.
+    pub(crate) fn is_final(&self) -> bool {
+        self.has_attr("final")
+    }
+
+    /// Whether each C++ file has its own copy of this (mutable) variable.
+    pub(super) fn has_internal_linkage(&self) -> bool {
+        self.has_attr("internal_linkage")
+    }
+
+    /// Whether the field is declared `const`.
+    pub(crate) fn is_const_field(&self) -> bool {
+        self.has_attr("const_field")
+    }
+
+    /// Whether the field is a reference.
+    pub(crate) fn is_reference_field(&self) -> bool {
+        self.has_attr("reference")
+    }
+
+    /// Whether the constructor is declared `explicit`.
+    pub(super) fn is_explicit(&self) -> bool {
+        self.has_attr("explicit")
+    }
+
     /// The C++ visibility of the item.
-    pub(super) fn get_cpp_visibility(&self) -> CppVisibility {
+    pub(crate) fn get_cpp_visibility(&self) -> CppVisibility {
         if self.has_attr("visibility_private") {
             CppVisibility::Private
         } else if self.has_attr("visibility_protected") {
@@ -126,7 +186,7 @@ impl BindgenSemanticAttributes {
     }
 
     /// The original C++ name, which bindgen may have changed.
-    pub(super) fn get_original_name(&self) -> Option<String> {
+    pub(crate) fn get_original_name(&self) -> Option<String> {
         self.string_if_present("original_name")
     }
 
@@ -161,10 +221,46 @@ impl BindgenSemanticAttributes {
                 if let Ok(ls) = r {
                     results.rvalue_ref_params.insert(ls.into());
                 }
+            } else if a.is_ident("arg_type_const_span") {
+                let r: Result<Ident, syn::Error> = a.parse_args();
+                if let Ok(ls) = r {
+                    results.const_span_params.insert(ls.into());
+                }
+            } else if a.is_ident("arg_type_member_function_pointer") {
+                let r: Result<Ident, syn::Error> = a.parse_args();
+                if let Ok(ls) = r {
+                    results.member_function_pointer_params.insert(ls.into());
+                }
             }
         }
         results
     }
+
+    /// Any parameters which have default values, mapped to the C++
+    /// expression used as the default.
+    pub(super) fn get_default_arguments(&self) -> HashMap<String, String> {
+        self.0
+            .iter()
+            .filter(|a| a.is_ident("arg_default"))
+            .filter_map(|a| a.parse_args::<DefaultArgument>().ok())
+            .map(|da| (da.param.to_string(), da.expr.value()))
+            .collect()
+    }
+}
+
+/// The body of an `arg_default(param, "expression")` annotation.
+struct DefaultArgument {
+    param: Ident,
+    expr: LitStr,
+}
+
+impl Parse for DefaultArgument {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let param = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let expr = input.parse()?;
+        Ok(Self { param, expr })
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,196 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A struct with a single field of a type we can't represent, such as a
+//! mutex from some library we don't want to generate bindings for, can't
+//! be POD. The user can ask us with `block_field!` to replace such a
+//! field with a private byte array of the same size at the same offset,
+//! which is enough for Rust to hold and move the struct, since C++
+//! checks that it's trivially relocatable anyway. The offsets come from
+//! bindgen's layout tests; the layout assertions we generate for POD
+//! structs check the result.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse::Parser, parse_quote, Attribute, Field, Fields, ItemStruct};
+
+use crate::conversion::{
+    api::{FieldOffset, Layout},
+    ConvertErrorFromCpp,
+};
+
+/// The prefix of the zero-sized fields which we insert to align the
+/// storage replacing a blocked field.
+pub(crate) const ALIGN_MARKER_PREFIX: &str = "_autocxx_align_";
+
+/// Replaces the fields named in `blocked` with opaque storage.
+pub(super) fn block_fields(
+    s: &mut ItemStruct,
+    blocked: &[&str],
+    layout: Option<&Layout>,
+    offsets: &[FieldOffset],
+) -> Result<(), ConvertErrorFromCpp> {
+    if blocked.is_empty() {
+        return Ok(());
+    }
+    let struct_name = s.ident.to_string();
+    let fields = match &mut s.fields {
+        Fields::Named(fields) => fields,
+        _ => {
+            return Err(ConvertErrorFromCpp::BlockedFieldNotFound(
+                blocked[0].to_string(),
+                struct_name,
+            ))
+        }
+    };
+    for name in blocked {
+        if !fields
+            .named
+            .iter()
+            .any(|f| f.ident.as_ref().unwrap() == name)
+        {
+            return Err(ConvertErrorFromCpp::BlockedFieldNotFound(
+                name.to_string(),
+                struct_name,
+            ));
+        }
+    }
+    let layout = layout.ok_or_else(|| {
+        ConvertErrorFromCpp::BlockedFieldLayoutUnknown(blocked[0].to_string(), struct_name.clone())
+    })?;
+    let offset_of = |field: &Field| {
+        let ident = field.ident.as_ref().unwrap();
+        offsets
+            .iter()
+            .find(|o| o.field.0 == *ident)
+            .map(|o| o.offset)
+    };
+    let old_fields: Vec<Field> = fields.named.iter().cloned().collect();
+    let mut new_fields = Vec::new();
+    for (i, field) in old_fields.iter().enumerate() {
+        let ident = field.ident.as_ref().unwrap();
+        if !blocked.iter().any(|name| ident == name) {
+            new_fields.push(field.clone());
+            continue;
+        }
+        let unknown = || {
+            ConvertErrorFromCpp::BlockedFieldLayoutUnknown(ident.to_string(), struct_name.clone())
+        };
+        let offset = offset_of(field).ok_or_else(unknown)?;
+        let end = match old_fields.get(i + 1) {
+            Some(next) => offset_of(next).ok_or_else(unknown)?,
+            None => layout.size,
+        };
+        let size = end.checked_sub(offset).ok_or_else(unknown)?;
+        if offset != 0 && !layout.packed {
+            // Rust would otherwise put the storage straight after the
+            // previous field, where C++ may have left padding. The largest
+            // power of two which divides the C++ offset is at least the
+            // alignment of the C++ field, so aligning to it reproduces
+            // that padding.
+            let align = (1 << offset.trailing_zeros()).min(layout.align);
+            let marker_ty: syn::Type = match align {
+                1 => parse_quote! { [u8; 0] },
+                2 => parse_quote! { [u16; 0] },
+                4 => parse_quote! { [u32; 0] },
+                _ => parse_quote! { [u64; 0] },
+            };
+            let marker = crate::types::make_ident(format!("{ALIGN_MARKER_PREFIX}{ident}"));
+            new_fields.push(named_field(quote! { #marker: #marker_ty }));
+        }
+        // Keep the documentation, but not bindgen's annotations of the
+        // original type.
+        let docs = field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        new_fields.push(named_field(quote! {
+            #(#docs)*
+            #ident: [u8; #size]
+        }));
+    }
+    fields.named = new_fields.into_iter().collect();
+    // The blocked field may have been the one which gave the struct its
+    // alignment.
+    if !layout.packed {
+        add_align_repr(&mut s.attrs, layout.align);
+    }
+    Ok(())
+}
+
+fn named_field(tokens: TokenStream) -> Field {
+    Field::parse_named.parse2(tokens).unwrap()
+}
+
+fn add_align_repr(attrs: &mut [Attribute], align: usize) {
+    let align = proc_macro2::Literal::usize_unsuffixed(align);
+    for attr in attrs.iter_mut() {
+        if attr.path().is_ident("repr") {
+            let existing = attr.meta.require_list().map(|list| list.tokens.clone());
+            if let Ok(existing) = existing {
+                if !existing.to_string().contains("align") {
+                    *attr = parse_quote! { #[repr(#existing, align(#align))] };
+                }
+            }
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::block_fields;
+    use crate::conversion::api::{FieldOffset, Layout};
+    use quote::ToTokens;
+    use syn::{parse_quote, ItemStruct};
+
+    #[test]
+    fn test_block_fields() {
+        let mut s: ItemStruct = parse_quote! {
+            #[repr(C)]
+            pub struct Config {
+                pub id: u16,
+                pub lock: root::Mutex,
+                pub count: u32,
+                pub last: root::Mutex,
+            }
+        };
+        let offsets = [("id", 0), ("lock", 8), ("count", 24), ("last", 28)]
+            .into_iter()
+            .map(|(field, offset)| FieldOffset {
+                field: syn::parse_str::<syn::Ident>(field).unwrap().into(),
+                offset,
+            })
+            .collect::<Vec<_>>();
+        let layout = Layout {
+            size: 40,
+            align: 8,
+            packed: false,
+        };
+        block_fields(&mut s, &["lock", "last"], Some(&layout), &offsets).unwrap();
+        let expected: ItemStruct = parse_quote! {
+            #[repr(C, align(8))]
+            pub struct Config {
+                pub id: u16,
+                _autocxx_align_lock: [u64; 0],
+                lock: [u8; 16usize],
+                pub count: u32,
+                _autocxx_align_last: [u32; 0],
+                last: [u8; 12usize],
+            }
+        };
+        let tokens = |s: &ItemStruct| {
+            s.attrs
+                .iter()
+                .map(|a| a.to_token_stream().to_string())
+                .chain(s.fields.iter().map(|f| f.to_token_stream().to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tokens(&s), tokens(&expected));
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen represents a typedef within a class template, such as
+//! `Traits<T>::value_type`, as a generic type alias like
+//! `pub type Traits_value_type<T> = T;`, and a use of it, such as
+//! `Traits<unsigned long>::value_type`, as `Traits_value_type<c_ulong>`.
+//! We can't generate generic typedefs, so anything using one would be
+//! ignored. Here we rewrite the bindgen output before we parse it so
+//! that each such use is instead the aliased type with the generic
+//! arguments substituted, which is the canonical type as far as clang
+//! is concerned. If that type isn't one we can represent either, the
+//! item is ignored later for that reason instead.
+
+use indexmap::map::IndexMap as HashMap;
+
+use syn::{
+    visit_mut::{visit_type_mut, VisitMut},
+    GenericArgument, GenericParam, Ident, Item, PathArguments, Type,
+};
+
+/// Rewrites uses of generic typedefs found in the bindgen `root` mod.
+pub(super) fn resolve_generic_typedefs(items: &mut [Item]) {
+    let mut typedefs = HashMap::new();
+    find_generic_typedefs(items, &mut typedefs);
+    if typedefs.is_empty() {
+        return;
+    }
+    let mut resolver = Resolver(typedefs);
+    for item in items {
+        resolver.visit_item_mut(item);
+    }
+}
+
+struct GenericTypedef {
+    params: Vec<Ident>,
+    target: Type,
+}
+
+fn find_generic_typedefs(items: &[Item], typedefs: &mut HashMap<Ident, GenericTypedef>) {
+    for item in items {
+        match item {
+            Item::Mod(itm) => {
+                if let Some((_, items)) = &itm.content {
+                    find_generic_typedefs(items, typedefs);
+                }
+            }
+            Item::Type(ity) if !ity.generics.params.is_empty() => {
+                // Only type parameters can be substituted like this.
+                let params: Option<Vec<Ident>> = ity
+                    .generics
+                    .params
+                    .iter()
+                    .map(|param| match param {
+                        GenericParam::Type(tp) => Some(tp.ident.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                if let Some(params) = params {
+                    typedefs.insert(
+                        ity.ident.clone(),
+                        GenericTypedef {
+                            params,
+                            target: (*ity.ty).clone(),
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Resolver(HashMap<Ident, GenericTypedef>);
+
+impl Resolver {
+    /// If this is a use of a generic typedef, returns the aliased type
+    /// with the arguments substituted for the parameters.
+    fn resolve(&self, ty: &Type) -> Option<Type> {
+        let seg = match ty {
+            Type::Path(tp) if tp.qself.is_none() => tp.path.segments.last()?,
+            _ => return None,
+        };
+        let typedef = self.0.get(&seg.ident)?;
+        let args: Vec<Type> = match &seg.arguments {
+            PathArguments::AngleBracketed(ab) => ab
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty.clone()),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+            _ => return None,
+        };
+        if args.len() != typedef.params.len() {
+            return None;
+        }
+        let mut target = typedef.target.clone();
+        Substituter(typedef.params.iter().cloned().zip(args).collect()).visit_type_mut(&mut target);
+        Some(target)
+    }
+}
+
+impl VisitMut for Resolver {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        // Arguments first, in case they too use generic typedefs.
+        visit_type_mut(self, ty);
+        if let Some(resolved) = self.resolve(ty) {
+            *ty = resolved;
+            // The aliased type may itself use a generic typedef.
+            self.visit_type_mut(ty);
+        }
+    }
+}
+
+/// Replaces generic parameters with the corresponding arguments.
+struct Substituter(HashMap<Ident, Type>);
+
+impl VisitMut for Substituter {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        let replacement = match ty {
+            Type::Path(tp) if tp.qself.is_none() => {
+                tp.path.get_ident().and_then(|id| self.0.get(id)).cloned()
+            }
+            _ => None,
+        };
+        match replacement {
+            Some(replacement) => *ty = replacement,
+            None => visit_type_mut(self, ty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_generic_typedefs;
+    use quote::ToTokens;
+    use syn::{parse_quote, Item};
+
+    #[test]
+    fn test_resolve_generic_typedefs() {
+        let mut items: Vec<Item> = vec![
+            parse_quote! {
+                pub type Traits_value_type<T> = T;
+            },
+            parse_quote! {
+                pub type Traits_pointer<T> = *mut T;
+            },
+            parse_quote! {
+                extern "C" {
+                    pub fn take(
+                        a: root::Traits_value_type<::std::os::raw::c_ulong>,
+                        b: root::Traits_pointer<root::Traits_value_type<u8>>
+                    );
+                }
+            },
+        ];
+        resolve_generic_typedefs(&mut items);
+        let expected: Item = parse_quote! {
+            extern "C" {
+                pub fn take(a: ::std::os::raw::c_ulong, b: *mut u8);
+            }
+        };
+        assert_eq!(
+            items[2].to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+    }
+}
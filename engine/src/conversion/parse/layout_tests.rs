@@ -0,0 +1,147 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen's layout tests are the only place it tells us the offset which
+//! clang computed for each field of a struct, for instance
+//! `assert_eq!(unsafe { ... addr_of!((*ptr).b) ... }, 4usize,
+//! concat!("Offset of field: ", stringify!(A), "::", stringify!(b)));`
+//! within `fn bindgen_test_layout_A()`. We take those offsets, so that
+//! we can check that the Rust layout of a POD struct matches, and throw
+//! away the tests themselves.
+
+use indexmap::map::IndexMap as HashMap;
+
+use syn::{punctuated::Punctuated, token::Comma, Expr, ExprLit, Ident, Item, Lit, Macro, Stmt};
+
+use crate::conversion::api::FieldOffset;
+
+const LAYOUT_TEST_PREFIX: &str = "bindgen_test_layout_";
+
+/// Removes bindgen's layout tests from `items`, returning the field
+/// offsets of each struct which had one.
+pub(super) fn take_field_offsets(items: &mut Vec<Item>) -> HashMap<Ident, Vec<FieldOffset>> {
+    let mut offsets = HashMap::new();
+    items.retain(|item| match item {
+        Item::Fn(f) => {
+            // Tests of template instantiations are prefixed with `__`, and
+            // don't correspond to any struct we generate.
+            let fn_name = f.sig.ident.to_string();
+            if let Some(struct_name) = fn_name.strip_prefix(LAYOUT_TEST_PREFIX) {
+                let field_offsets: Vec<_> = f.block.stmts.iter().filter_map(field_offset).collect();
+                offsets.insert(Ident::new(struct_name, f.sig.ident.span()), field_offsets);
+            }
+            !fn_name.contains(LAYOUT_TEST_PREFIX)
+        }
+        _ => true,
+    });
+    offsets
+}
+
+/// Interprets one of the assertions of a layout test, if it's about the
+/// offset of a field.
+fn field_offset(stmt: &Stmt) -> Option<FieldOffset> {
+    let mac = match stmt {
+        Stmt::Macro(stmt) => &stmt.mac,
+        Stmt::Expr(Expr::Macro(expr), _) => &expr.mac,
+        _ => return None,
+    };
+    if !mac.path.is_ident("assert_eq") {
+        return None;
+    }
+    let args = macro_args(mac)?;
+    let (offset, message) = match (args.get(1)?, args.get(2)?) {
+        (
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(offset),
+                ..
+            }),
+            Expr::Macro(message),
+        ) if message.mac.path.is_ident("concat") => (offset.base10_parse().ok()?, message),
+        _ => return None,
+    };
+    let parts = macro_args(&message.mac)?;
+    match parts.first()? {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) if s.value().starts_with("Offset of field") => {}
+        _ => return None,
+    }
+    let field = match parts.last()? {
+        Expr::Macro(field) if field.mac.path.is_ident("stringify") => {
+            field.mac.parse_body::<Ident>().ok()?
+        }
+        _ => return None,
+    };
+    Some(FieldOffset {
+        field: field.into(),
+        offset,
+    })
+}
+
+fn macro_args(mac: &Macro) -> Option<Vec<Expr>> {
+    mac.parse_body_with(Punctuated::<Expr, Comma>::parse_terminated)
+        .ok()
+        .map(|args| args.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::take_field_offsets;
+    use syn::{parse_quote, Item};
+
+    #[test]
+    fn test_take_field_offsets() {
+        let mut items: Vec<Item> = vec![
+            parse_quote! {
+                #[repr(C, packed)]
+                pub struct Frame {
+                    pub tag: u8,
+                    pub value: u32,
+                }
+            },
+            parse_quote! {
+                #[test]
+                fn bindgen_test_layout_Frame() {
+                    const UNINIT: ::std::mem::MaybeUninit<Frame> =
+                        ::std::mem::MaybeUninit::uninit();
+                    let ptr = UNINIT.as_ptr();
+                    assert_eq!(
+                        ::std::mem::size_of::<Frame>(),
+                        5usize,
+                        concat!("Size of: ", stringify!(Frame))
+                    );
+                    assert_eq!(
+                        unsafe { ::std::ptr::addr_of!((*ptr).tag) as usize - ptr as usize },
+                        0usize,
+                        concat!("Offset of field: ", stringify!(Frame), "::", stringify!(tag))
+                    );
+                    assert_eq!(
+                        unsafe { ::std::ptr::addr_of!((*ptr).value) as usize - ptr as usize },
+                        1usize,
+                        concat!("Offset of field: ", stringify!(Frame), "::", stringify!(value))
+                    );
+                }
+            },
+            parse_quote! {
+                #[test]
+                fn __bindgen_test_layout_Holder_open0_int_close0_instantiation() {}
+            },
+        ];
+        let offsets = take_field_offsets(&mut items);
+        assert_eq!(items.len(), 1);
+        let frame = &offsets[&syn::parse_str::<syn::Ident>("Frame").unwrap()];
+        let frame: Vec<_> = frame
+            .iter()
+            .map(|f| (f.field.to_string(), f.offset))
+            .collect();
+        assert_eq!(
+            frame,
+            vec![("tag".to_string(), 0), ("value".to_string(), 1)]
+        );
+    }
+}
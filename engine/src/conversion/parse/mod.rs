@@ -6,10 +6,16 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod anonymous_types;
 mod bindgen_semantic_attributes;
+mod blocked_fields;
 mod extern_fun_signatures;
+mod generic_typedefs;
+mod layout_tests;
 mod parse_bindgen;
 mod parse_foreign_mod;
+mod unions;
 
 pub(crate) use bindgen_semantic_attributes::BindgenSemanticAttributes;
-pub(crate) use parse_bindgen::ParseBindgen;
+pub(crate) use blocked_fields::ALIGN_MARKER_PREFIX;
+pub(crate) use parse_bindgen::{find_nested_type_names, ParseBindgen};
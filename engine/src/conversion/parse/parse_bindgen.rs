@@ -11,40 +11,123 @@ use indexmap::set::IndexSet as HashSet;
 
 use crate::{
     conversion::{
-        api::{Api, ApiName, NullPhase, StructDetails, SubclassName, TypedefKind, UnanalyzedApi},
+        api::{
+            Api, ApiName, EnumAlias, FieldOffset, NullPhase, Provenance, StructDetails,
+            SubclassName, TypedefKind, UnanalyzedApi, UnionMember,
+        },
         apivec::ApiVec,
         convert_error::LocatedConvertErrorFromRust,
         ConvertError, ConvertErrorFromCpp,
     },
     types::Namespace,
-    types::QualifiedName,
+    types::{make_ident, QualifiedName, BITFIELD_UNIT},
 };
 use crate::{
     conversion::{
         convert_error::{ConvertErrorWithContext, ErrorContext},
         error_reporter::report_any_error,
     },
+    generated_types::ImportedType,
     types::validate_ident_ok_for_cxx,
 };
-use autocxx_parser::{IncludeCppConfig, RustPath};
-use syn::{parse_quote, Fields, Ident, Item, Type, TypePath, UseTree};
+use autocxx_parser::{ExternCppType, IncludeCppConfig, RustPath};
+use itertools::Itertools;
+use quote::ToTokens;
+use syn::{
+    parse_quote, Fields, Ident, ImplItem, ImplItemFn, Item, ItemMod, Type, TypePath, UseTree,
+};
 
 use super::{
     super::utilities::generate_utilities, bindgen_semantic_attributes::BindgenSemanticAttributes,
 };
 
+use super::anonymous_types::resolve_anonymous_types;
+use super::blocked_fields::block_fields;
+use super::generic_typedefs::resolve_generic_typedefs;
+use super::layout_tests::take_field_offsets;
 use super::parse_foreign_mod::ParseForeignMod;
+use super::unions::resolve_unions;
+use crate::conversion::doc_attr::translate_doxygen_in_items;
+
+/// Finds the nested types (`Outer::Inner`) in the bindgen output,
+/// mapping each such C++ name to the flattened name which bindgen gives
+/// it (`Outer_Inner`), so that directives can use either.
+pub(crate) fn find_nested_type_names(bindgen_mod: &ItemMod) -> HashMap<String, String> {
+    fn find_in_mod(items: &[Item], ns: &Namespace, found: &mut HashMap<String, String>) {
+        for item in items {
+            let (ident, attrs) = match item {
+                Item::Struct(s) => (&s.ident, &s.attrs),
+                Item::Enum(e) => (&e.ident, &e.attrs),
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        find_in_mod(items, &ns.push(m.ident.to_string()), found);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(original_name) = BindgenSemanticAttributes::new(attrs)
+                .get_original_name()
+                .filter(|original_name| original_name.contains("::"))
+            {
+                let qualify = |name: String| ns.iter().cloned().chain([name]).join("::");
+                found.insert(qualify(original_name), qualify(ident.to_string()));
+            }
+        }
+    }
+    let mut found = HashMap::new();
+    if let Some((_, items)) = &bindgen_mod.content {
+        for item in items {
+            if let Item::Mod(root_mod) = item {
+                if let Some((_, items)) = &root_mod.content {
+                    find_in_mod(items, &Namespace::new(), &mut found);
+                }
+            }
+        }
+    }
+    found
+}
 
 /// Parses a bindgen mod in order to understand the APIs within it.
 pub(crate) struct ParseBindgen<'a> {
     config: &'a IncludeCppConfig,
+    /// Types whose bindings were generated by other `include_cpp!`s,
+    /// which we should use rather than generating our own.
+    imported_types: &'a [ImportedType],
     apis: ApiVec<NullPhase>,
+    /// The selectors of `rename!` directives which matched a function,
+    /// once per match.
+    matched_overload_renames: Vec<String>,
+    /// bindgen's `__BindgenBitfieldUnit` type and its impls, if any struct
+    /// has bit-fields.
+    bitfield_unit_items: Vec<Item>,
+    /// bindgen's bit-field accessor methods for each struct.
+    bitfield_impls: HashMap<QualifiedName, Vec<Item>>,
+    /// The union members for which each struct needs accessors.
+    union_members: HashMap<QualifiedName, Vec<UnionMember>>,
+    /// The types and enums we dropped because they were on the blocklist.
+    blocked_names: Vec<String>,
 }
 
 fn api_name(ns: &Namespace, id: Ident, attrs: &BindgenSemanticAttributes) -> ApiName {
     ApiName::new_with_cpp_name(ns, id.into(), attrs.get_original_name())
 }
 
+/// Names an API which clashes with a constant, in terms of the C++.
+fn describe_clashing_api(api: &UnanalyzedApi) -> String {
+    match api {
+        Api::Const {
+            member_of: Some((ty, member)),
+            ..
+        } => format!("the constant {}::{member}", ty.to_cpp_name()),
+        _ => format!(
+            "the {} {}",
+            api.kind_description(),
+            api.name().to_cpp_name()
+        ),
+    }
+}
+
 pub(crate) fn api_name_qualified(
     ns: &Namespace,
     id: Ident,
@@ -63,10 +146,16 @@ pub(crate) fn api_name_qualified(
 }
 
 impl<'a> ParseBindgen<'a> {
-    pub(crate) fn new(config: &'a IncludeCppConfig) -> Self {
+    pub(crate) fn new(config: &'a IncludeCppConfig, imported_types: &'a [ImportedType]) -> Self {
         ParseBindgen {
             config,
+            imported_types,
             apis: ApiVec::new(),
+            matched_overload_renames: Vec::new(),
+            bitfield_unit_items: Vec::new(),
+            bitfield_impls: HashMap::new(),
+            union_members: HashMap::new(),
+            blocked_names: Vec::new(),
         }
     }
 
@@ -77,7 +166,13 @@ impl<'a> ParseBindgen<'a> {
         items: Vec<Item>,
         source_file_contents: &str,
     ) -> Result<ApiVec<NullPhase>, ConvertError> {
-        let items = Self::find_items_in_root(items).map_err(ConvertError::Cpp)?;
+        let mut items = Self::find_items_in_root(items).map_err(ConvertError::Cpp)?;
+        if !self.config.raw_doc_comments() {
+            translate_doxygen_in_items(&mut items);
+        }
+        resolve_anonymous_types(&mut items);
+        resolve_generic_typedefs(&mut items);
+        self.union_members = resolve_unions(&mut items);
         if !self.config.exclude_utilities() {
             generate_utilities(&mut self.apis, self.config);
         }
@@ -85,8 +180,11 @@ impl<'a> ParseBindgen<'a> {
             .map_err(ConvertError::Rust)?;
         let root_ns = Namespace::new();
         self.parse_mod_items(items, root_ns);
+        self.add_field_accessors();
         self.confirm_all_generate_directives_obeyed()
             .map_err(ConvertError::Cpp)?;
+        self.confirm_overload_renames_matched()
+            .map_err(ConvertError::Cpp)?;
         self.replace_extern_cpp_types();
         Ok(self.apis)
     }
@@ -97,11 +195,41 @@ impl<'a> ParseBindgen<'a> {
         &mut self,
         source_file_contents: &str,
     ) -> Result<(), LocatedConvertErrorFromRust> {
-        self.apis
-            .extend(self.config.subclasses.iter().map(|sc| Api::Subclass {
-                name: SubclassName::new(sc.subclass.clone().into()),
-                superclass: QualifiedName::new_from_cpp_name(&sc.superclass),
-            }));
+        self.apis.extend(self.config.subclasses.iter().map(|sc| {
+            let name = SubclassName::new(sc.subclass.clone().into());
+            // A template instantiation, whether named by its concrete! or
+            // spelled out, derives from the concrete type, whose members we
+            // found for ourselves since bindgen doesn't tell us about them.
+            let without_spaces = |s: &str| s.split_whitespace().collect::<String>();
+            let concrete = self
+                .config
+                .concretes
+                .0
+                .iter()
+                .zip(self.config.get_concretes())
+                .find(|((cpp_definition, rust_id), (expanded, _))| {
+                    *rust_id == &sc.superclass
+                        || [cpp_definition.as_str(), expanded.as_str()]
+                            .into_iter()
+                            .any(|cpp| without_spaces(cpp) == without_spaces(&sc.superclass))
+                })
+                .map(|((_, rust_id), _)| rust_id);
+            match concrete {
+                Some(rust_id) => Api::Subclass {
+                    name,
+                    superclass: QualifiedName::new(&Namespace::new(), rust_id.clone().into()),
+                },
+                None if sc.superclass.contains('<') => Api::IgnoredItem {
+                    ctx: Some(ErrorContext::new_for_item(name.id())),
+                    name: name.0,
+                    err: ConvertErrorFromCpp::SubclassOfTemplate(sc.superclass.clone()),
+                },
+                None => Api::Subclass {
+                    name,
+                    superclass: QualifiedName::new_from_cpp_name(&sc.superclass),
+                },
+            }
+        }));
         for fun in &self.config.extern_rust_funs {
             let id = fun.sig.ident.clone();
             self.apis.push(Api::RustFn {
@@ -121,16 +249,24 @@ impl<'a> ParseBindgen<'a> {
                 path: path.clone(),
             }
         }));
+        self.apis
+            .extend(
+                self.config
+                    .get_closure_params()
+                    .iter()
+                    .map(|closure| Api::ClosureHolder {
+                        name: ApiName::new_in_root_namespace(closure.holder().into()),
+                        details: Box::new(closure.clone()),
+                    }),
+            );
         self.apis.extend(
             self.config
-                .concretes
-                .0
-                .iter()
+                .get_concretes()
                 .map(|(cpp_definition, rust_id)| {
                     let name = ApiName::new_in_root_namespace(rust_id.clone().into());
                     Api::ConcreteType {
                         name,
-                        cpp_definition: cpp_definition.clone(),
+                        cpp_definition,
                         rs_definition: None,
                     }
                 }),
@@ -140,16 +276,17 @@ impl<'a> ParseBindgen<'a> {
 
     /// We do this last, _after_ we've parsed all the APIs, because we might want to actually
     /// replace some of the existing APIs (structs/enums/etc.) with replacements.
+    /// Imported types are treated as if they were named in `extern_cpp_type!`,
+    /// unless they actually were.
     fn replace_extern_cpp_types(&mut self) {
-        let pod_requests: HashSet<_> = self.config.get_pod_requests().iter().collect();
-        let replacements: HashMap<_, _> = self
+        let mut replacements: HashMap<_, _> = self
             .config
             .externs
             .0
             .iter()
             .map(|(cpp_definition, details)| {
                 let qn = QualifiedName::new_from_cpp_name(cpp_definition);
-                let pod = pod_requests.contains(&qn.to_cpp_name());
+                let pod = self.config.is_pod_request(&qn.to_cpp_name());
                 (
                     qn.clone(),
                     Api::ExternCppType {
@@ -160,11 +297,73 @@ impl<'a> ParseBindgen<'a> {
                 )
             })
             .collect();
+        // Only types which we'd otherwise have generated ourselves, since
+        // there may be many more in the other mod.
+        for imported in self.imported_types {
+            let qn = QualifiedName::new_from_cpp_name(&imported.cpp_name);
+            if replacements.contains_key(&qn) || !self.apis.contains_key(&qn) {
+                continue;
+            }
+            replacements.insert(
+                qn.clone(),
+                Api::ExternCppType {
+                    name: ApiName::new_from_qualified_name(qn),
+                    details: ExternCppType {
+                        rust_path: imported.rust_path.clone(),
+                        opaque: false,
+                    },
+                    pod: imported.pod,
+                },
+            );
+        }
         self.apis
             .retain(|api| !replacements.contains_key(api.name()));
         self.apis.extend(replacements.into_iter().map(|(_, v)| v));
     }
 
+    /// Bit-fields are represented by bindgen as a field of type
+    /// `__BindgenBitfieldUnit`, with accessor methods on the struct. We pass
+    /// both on as they are, since they're only of use to Rust. Union members
+    /// similarly need accessors, which we generate later.
+    fn add_field_accessors(&mut self) {
+        if !self.bitfield_unit_items.is_empty() {
+            self.apis.push(Api::BitfieldUnit {
+                name: ApiName::new_in_root_namespace(make_ident(BITFIELD_UNIT)),
+                items: std::mem::take(&mut self.bitfield_unit_items)
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            });
+        }
+        if self.bitfield_impls.is_empty() && self.union_members.is_empty() {
+            return;
+        }
+        let apis = std::mem::take(&mut self.apis);
+        self.apis = apis
+            .into_iter()
+            .map(|api| match api {
+                Api::Struct {
+                    name,
+                    mut details,
+                    analysis,
+                } => {
+                    if let Some(impls) = self.bitfield_impls.swap_remove(&name.name) {
+                        details.bitfield_impls = impls.into_iter().map(Into::into).collect();
+                    }
+                    if let Some(members) = self.union_members.swap_remove(&name.name) {
+                        details.union_members = members;
+                    }
+                    Api::Struct {
+                        name,
+                        details,
+                        analysis,
+                    }
+                }
+                _ => api,
+            })
+            .collect();
+    }
+
     fn find_items_in_root(items: Vec<Item>) -> Result<Vec<Item>, ConvertErrorFromCpp> {
         for item in items {
             match item {
@@ -185,18 +384,69 @@ impl<'a> ParseBindgen<'a> {
 
     /// Interpret the bindgen-generated .rs for a particular
     /// mod, which corresponds to a C++ namespace.
-    fn parse_mod_items(&mut self, items: Vec<Item>, ns: Namespace) {
+    fn parse_mod_items(&mut self, mut items: Vec<Item>, ns: Namespace) {
         // This object maintains some state specific to this namespace, i.e.
-        // this particular mod.
-        let mut mod_converter = ParseForeignMod::new(ns.clone());
+        // this particular mod. Functions in an anonymous namespace are
+        // callable (from any file which includes the header) by the names
+        // they'd have in the enclosing namespace, so that's where they go.
+        let mut mod_converter =
+            ParseForeignMod::new(ns.without_anonymous_namespaces(), self.config.str_params());
+        let types = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(s) => Some((
+                    s.ident.clone(),
+                    BindgenSemanticAttributes::new(&s.attrs)
+                        .get_original_name()
+                        .unwrap_or_else(|| s.ident.to_string()),
+                )),
+                _ => None,
+            })
+            .collect();
+        mod_converter.prepare_for_static_data(types, self.config.must_generate_list().collect());
+        mod_converter.request_const_globals(&items, |cpp_name| {
+            self.config.is_on_allowlist(cpp_name) && !self.config.is_on_blocklist(cpp_name)
+        });
+        mod_converter
+            .prepare_for_variadic_functions(self.config.get_variadic_requests().cloned().collect());
+        mod_converter.prepare_for_overload_renames(self.config.get_overload_renames().to_vec());
+        mod_converter.prepare_for_name_renames(self.config.get_name_renames().to_vec());
+        mod_converter.prepare_for_safety_overrides(self.config.get_safety_overrides().to_vec());
+        let enum_aliases = Self::find_enum_aliases(&items);
+        let mut field_offsets = take_field_offsets(&mut items);
         let mut more_apis = ApiVec::new();
         for item in items {
-            report_any_error(&ns, &mut more_apis, || {
-                self.parse_item(item, &mut mod_converter, &ns)
-            });
+            match item {
+                // The items within these are each parsed, and any panic
+                // contained, one by one.
+                Item::ForeignMod(fm) => mod_converter.convert_foreign_mod_items(fm.items),
+                Item::Mod(itm) => {
+                    if let Some((_, items)) = itm.content {
+                        let new_ns = ns.push(itm.ident.to_string());
+                        self.parse_mod_items(items, new_ns);
+                    }
+                }
+                item => match Self::item_ident(&item) {
+                    Some(id) => {
+                        let panic_ctx = ErrorContext::new_for_item(id.into());
+                        report_any_error(&ns, &mut more_apis, panic_ctx, || {
+                            self.parse_item(
+                                item,
+                                &mut mod_converter,
+                                &ns,
+                                &enum_aliases,
+                                &mut field_offsets,
+                            )
+                        });
+                    }
+                    None => eprintln!("Ignored item: {}", ConvertErrorFromCpp::UnexpectedItemInMod),
+                },
+            }
         }
         self.apis.append(&mut more_apis);
-        mod_converter.finished(&mut self.apis);
+        let mut matched_overload_renames = mod_converter.finished(&mut self.apis);
+        self.matched_overload_renames
+            .append(&mut matched_overload_renames);
     }
 
     fn parse_item(
@@ -204,16 +454,18 @@ impl<'a> ParseBindgen<'a> {
         item: Item,
         mod_converter: &mut ParseForeignMod,
         ns: &Namespace,
+        enum_aliases: &HashMap<Ident, Vec<EnumAlias>>,
+        field_offsets: &mut HashMap<Ident, Vec<FieldOffset>>,
     ) -> Result<(), ConvertErrorWithContext> {
         match item {
-            Item::ForeignMod(fm) => {
-                mod_converter.convert_foreign_mod_items(fm.items);
-                Ok(())
-            }
-            Item::Struct(s) => {
+            Item::Struct(mut s) => {
                 if s.ident.to_string().ends_with("__bindgen_vtable") {
                     return Ok(());
                 }
+                if ns.is_empty() && s.ident == BITFIELD_UNIT {
+                    self.bitfield_unit_items.push(Item::Struct(s));
+                    return Ok(());
+                }
                 let annotations = BindgenSemanticAttributes::new(&s.attrs);
                 // cxx::bridge can't cope with type aliases to generic
                 // types at the moment.
@@ -241,23 +493,46 @@ impl<'a> ParseBindgen<'a> {
                     }
                     Some(UnanalyzedApi::ForwardDeclaration { name, err })
                 } else {
+                    if let Some(excluded) =
+                        self.config.excluded_namespace_of(&name.name.to_cpp_name())
+                    {
+                        let reason = format!(
+                            " This type is opaque because namespace `{excluded}` was excluded from `generate_ns!`."
+                        );
+                        s.attrs.push(parse_quote! { #[doc = #reason] });
+                    }
+                    let layout = annotations.get_layout();
+                    let field_offsets = field_offsets.swap_remove(&s.ident).unwrap_or_default();
+                    let cpp_name = name.name.to_cpp_name();
+                    let blocked: Vec<_> = self.config.get_blocked_fields(&cpp_name).collect();
+                    block_fields(&mut s, &blocked, layout.as_ref(), &field_offsets).map_err(
+                        |e| {
+                            ConvertErrorWithContext(
+                                e,
+                                Some(ErrorContext::new_for_item(s.ident.clone().into())),
+                            )
+                        },
+                    )?;
                     let has_rvalue_reference_fields = s.fields.iter().any(|f| {
                         BindgenSemanticAttributes::new(&f.attrs).has_attr("rvalue_reference")
                     });
                     Some(UnanalyzedApi::Struct {
                         name,
                         details: Box::new(StructDetails {
-                            layout: annotations.get_layout(),
+                            layout,
                             item: s.into(),
                             has_rvalue_reference_fields,
+                            bitfield_impls: Vec::new(),
+                            union_members: Vec::new(),
+                            deprecated: annotations.get_deprecation(),
+                            is_final: annotations.is_final(),
+                            field_offsets,
                         }),
                         analysis: (),
                     })
                 };
                 if let Some(api) = api {
-                    if !self.config.is_on_blocklist(&api.name().to_cpp_name()) {
-                        self.apis.push(api);
-                    }
+                    self.push_unless_blocked(api);
                 }
                 Ok(())
             }
@@ -265,14 +540,40 @@ impl<'a> ParseBindgen<'a> {
                 let annotations = BindgenSemanticAttributes::new(&e.attrs);
                 let api = UnanalyzedApi::Enum {
                     name: api_name_qualified(ns, e.ident.clone(), &annotations)?,
+                    aliases: enum_aliases.get(&e.ident).cloned().unwrap_or_default(),
                     item: e.into(),
                 };
-                if !self.config.is_on_blocklist(&api.name().to_cpp_name()) {
-                    self.apis.push(api);
-                }
+                self.push_unless_blocked(api);
                 Ok(())
             }
-            Item::Impl(imp) => {
+            Item::Impl(mut imp) => {
+                let self_ty = match imp.self_ty.as_ref() {
+                    Type::Path(typ) if imp.trait_.is_none() => Some(QualifiedName::new(
+                        ns,
+                        typ.path.segments.last().unwrap().ident.clone().into(),
+                    )),
+                    _ => None,
+                };
+                if let Some(self_ty) = self_ty {
+                    if ns.is_empty() && self_ty.get_final_item() == BITFIELD_UNIT {
+                        self.bitfield_unit_items.push(Item::Impl(imp));
+                        return Ok(());
+                    }
+                    // Unlike other methods, bit-field accessors are implemented
+                    // by bindgen in Rust, so we keep them to emit later.
+                    let (accessors, others) = std::mem::take(&mut imp.items).into_iter().partition(
+                        |item| matches!(item, ImplItem::Fn(f) if Self::is_bitfield_accessor(f)),
+                    );
+                    imp.items = others;
+                    if !accessors.is_empty() {
+                        let mut accessor_impl = imp.clone();
+                        accessor_impl.items = accessors;
+                        self.bitfield_impls
+                            .entry(self_ty)
+                            .or_default()
+                            .push(Item::Impl(accessor_impl));
+                    }
+                }
                 // We *mostly* ignore all impl blocks generated by bindgen.
                 // Methods also appear in 'extern "C"' blocks which
                 // we will convert instead. At that time we'll also construct
@@ -283,13 +584,6 @@ impl<'a> ParseBindgen<'a> {
                 mod_converter.convert_impl_items(imp);
                 Ok(())
             }
-            Item::Mod(itm) => {
-                if let Some((_, items)) = itm.content {
-                    let new_ns = ns.push(itm.ident.to_string());
-                    self.parse_mod_items(items, new_ns);
-                }
-                Ok(())
-            }
             Item::Use(use_item) => {
                 let mut segs = Vec::new();
                 let mut tree = &use_item.tree;
@@ -364,10 +658,46 @@ impl<'a> ParseBindgen<'a> {
                     }
                 }
                 if enum_type_name_valid {
-                    self.apis.push(UnanalyzedApi::Const {
-                        name: api_name(ns, const_item.ident.clone(), &annotations),
-                        const_item: const_item.into(),
+                    // Static data members, and the values of anonymous
+                    // enums within a class, are also made available as
+                    // associated constants of their type.
+                    let owner = mod_converter
+                        .find_static_member_owner(&const_item.ident)
+                        .filter(|(_, _, member)| validate_ident_ok_for_cxx(member).is_ok());
+                    let member_cpp_name = owner.as_ref().map(|(_, cpp_name, member)| {
+                        QualifiedName::new(ns, make_ident(format!("{cpp_name}::{member}")))
+                            .to_cpp_name()
                     });
+                    let name = api_name(ns, const_item.ident.clone(), &annotations);
+                    // Such a constant may be blocked by either of its names.
+                    let blocked_name = member_cpp_name
+                        .into_iter()
+                        .chain(std::iter::once(name.name.to_cpp_name()))
+                        .find(|cpp_name| self.config.is_on_blocklist(cpp_name));
+                    if let Some(blocked_name) = blocked_name {
+                        self.blocked_names.push(blocked_name);
+                        return Ok(());
+                    }
+                    let member_of = owner.map(|(ty, _, member)| {
+                        (
+                            QualifiedName::new(ns, ty.clone().into()),
+                            make_ident(member),
+                        )
+                    });
+                    let api = UnanalyzedApi::Const {
+                        name,
+                        const_item: const_item.into(),
+                        member_of,
+                    };
+                    let clash = self
+                        .apis
+                        .get(api.name())
+                        .filter(|existing| !existing.discard_duplicates())
+                        .map(describe_clashing_api);
+                    match clash {
+                        Some(clash) => self.report_constant_clash(api, clash),
+                        None => self.apis.push(api),
+                    }
                 }
                 Ok(())
             }
@@ -390,6 +720,42 @@ impl<'a> ParseBindgen<'a> {
         }
     }
 
+    /// The name by which we'd report a problem with an item. Those without
+    /// one, such as macro invocations or glob imports, aren't generated by
+    /// bindgen.
+    fn item_ident(item: &Item) -> Option<Ident> {
+        match item {
+            Item::Const(i) => Some(i.ident.clone()),
+            Item::Enum(i) => Some(i.ident.clone()),
+            Item::ExternCrate(i) => Some(i.ident.clone()),
+            Item::Fn(i) => Some(i.sig.ident.clone()),
+            Item::Mod(i) => Some(i.ident.clone()),
+            Item::Static(i) => Some(i.ident.clone()),
+            Item::Struct(i) => Some(i.ident.clone()),
+            Item::Trait(i) => Some(i.ident.clone()),
+            Item::TraitAlias(i) => Some(i.ident.clone()),
+            Item::Type(i) => Some(i.ident.clone()),
+            Item::Union(i) => Some(i.ident.clone()),
+            Item::Macro(i) => i.ident.clone(),
+            Item::Impl(i) => match i.self_ty.as_ref() {
+                Type::Path(typ) => typ.path.segments.last().map(|seg| seg.ident.clone()),
+                _ => None,
+            },
+            Item::Use(i) => {
+                let mut tree = &i.tree;
+                loop {
+                    match tree {
+                        UseTree::Path(up) => tree = &up.tree,
+                        UseTree::Name(un) => return Some(un.ident.clone()),
+                        UseTree::Rename(urn) => return Some(urn.rename.clone()),
+                        UseTree::Glob(_) | UseTree::Group(_) => return None,
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn spot_forward_declaration(s: &Fields) -> bool {
         Self::spot_field(s, "_unused")
     }
@@ -398,17 +764,116 @@ impl<'a> ParseBindgen<'a> {
         Self::spot_field(s, "_address")
     }
 
+    /// Where a C++ enum has several enumerators with the same value, bindgen
+    /// makes a variant of the first, and associated constants of the others:
+    /// `impl Status { pub const SUCCESS: Status = Status::OK; }`.
+    fn find_enum_aliases(items: &[Item]) -> HashMap<Ident, Vec<EnumAlias>> {
+        let enums: HashSet<&Ident> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Enum(e) => Some(&e.ident),
+                _ => None,
+            })
+            .collect();
+        let mut aliases: HashMap<Ident, Vec<EnumAlias>> = HashMap::new();
+        for item in items {
+            let imp = match item {
+                Item::Impl(imp) if imp.trait_.is_none() => imp,
+                _ => continue,
+            };
+            let enum_id = match imp.self_ty.as_ref() {
+                Type::Path(typ) => match typ.path.get_ident() {
+                    Some(id) if enums.contains(id) => id,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            for imp_item in &imp.items {
+                if let ImplItem::Const(c) = imp_item {
+                    if let syn::Expr::Path(value) = &c.expr {
+                        if let Some(aliased) = value.path.segments.last() {
+                            aliases.entry(enum_id.clone()).or_default().push(EnumAlias {
+                                name: c.ident.clone().into(),
+                                aliased: aliased.ident.clone().into(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        aliases
+    }
+
+    fn is_bitfield_accessor(f: &ImplItemFn) -> bool {
+        f.sig.ident.to_string().starts_with("new_bitfield_")
+            || f.block
+                .to_token_stream()
+                .to_string()
+                .contains("self . _bitfield_")
+    }
+
     fn spot_field(s: &Fields, desired_id: &str) -> bool {
         s.iter()
             .filter_map(|f| f.ident.as_ref())
             .any(|id| id == desired_id)
     }
 
+    /// Each `rename!` directive must select exactly one overload, or it
+    /// wouldn't be clear which Rust name is which.
+    fn confirm_overload_renames_matched(&self) -> Result<(), ConvertErrorFromCpp> {
+        for rename in self.config.get_overload_renames() {
+            let matches = self
+                .matched_overload_renames
+                .iter()
+                .filter(|selector| **selector == rename.selector)
+                .count();
+            if matches != 1 {
+                return Err(ConvertErrorFromCpp::OverloadRenameMismatch(
+                    rename.selector.clone(),
+                    matches,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// bindgen names a constant within a class `Outer_NAME`, so it may
+    /// clash with something else of that name. Rather than the generic
+    /// error about duplicate names, say which two C++ items clash.
+    fn report_constant_clash(&mut self, api: UnanalyzedApi, clash: String) {
+        let err = ConvertErrorFromCpp::ConstantNameClash(
+            describe_clashing_api(&api),
+            clash,
+            api.name().get_final_item().to_string(),
+        );
+        let name = api.name().clone();
+        self.apis.replace(Api::IgnoredItem {
+            ctx: Some(ErrorContext::new_for_item(name.get_final_ident())),
+            name: ApiName::new_from_qualified_name(name),
+            err,
+        });
+    }
+
+    fn push_unless_blocked(&mut self, api: UnanalyzedApi) {
+        let cpp_name = api.name().to_cpp_name();
+        if self.config.is_on_blocklist(&cpp_name) {
+            self.blocked_names.push(cpp_name);
+        } else {
+            self.apis.push(api);
+        }
+    }
+
     fn confirm_all_generate_directives_obeyed(&self) -> Result<(), ConvertErrorFromCpp> {
         let api_names: HashSet<_> = self
             .apis
             .iter()
-            .map(|api| api.name().to_cpp_name())
+            .map(|api| match api {
+                Api::Function { fun, .. } => match &fun.provenance {
+                    Provenance::SynthesizedStaticDataAccessor(cpp_name) => cpp_name.clone(),
+                    _ => api.name().to_cpp_name(),
+                },
+                _ => api.name().to_cpp_name(),
+            })
             .collect();
         for generate_directive in self.config.must_generate_list() {
             if !api_names.contains(&generate_directive) {
@@ -417,6 +882,18 @@ impl<'a> ParseBindgen<'a> {
                 ));
             }
         }
+        let seen_names: Vec<_> = api_names
+            .into_iter()
+            .chain(self.blocked_names.iter().cloned())
+            .collect();
+        for keep_directive in self.config.must_keep_list() {
+            if !seen_names.contains(&keep_directive) {
+                return Err(ConvertErrorFromCpp::KeepDidNotMatchAnything(keep_directive));
+            }
+        }
+        for pattern in self.config.unmatched_name_patterns(&seen_names) {
+            log::warn!("The pattern \"{pattern}\" didn't match anything");
+        }
         Ok(())
     }
 }
@@ -6,7 +6,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::conversion::api::{ApiName, NullPhase, Provenance};
+use crate::conversion::analysis::fun::function_wrapper::{CppFunctionBody, CppFunctionKind};
+use crate::conversion::api::{
+    ApiName, CppVisibility, DeletedOrDefaulted, NullPhase, Provenance, References,
+    SpecialMemberKind, Virtualness,
+};
 use crate::conversion::apivec::ApiVec;
 use crate::conversion::doc_attr::get_doc_attrs;
 use crate::conversion::error_reporter::report_any_error;
@@ -15,13 +19,21 @@ use crate::conversion::{
     convert_error::ConvertErrorWithContext,
     convert_error::ErrorContext,
 };
-use crate::minisyn::{minisynize_punctuated, minisynize_vec};
+use crate::known_types::known_types;
+use crate::minisyn::{self, minisynize_punctuated, minisynize_vec};
 use crate::{
     conversion::ConvertErrorFromCpp,
-    types::{Namespace, QualifiedName},
+    types::{make_ident, Namespace, QualifiedName},
+};
+use autocxx_parser::{FnSafety, NameRename, OverloadRename, SafetyOverride};
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+use quote::ToTokens;
+use syn::{
+    parse_quote, punctuated::Punctuated, token::Comma, Block, Expr, ExprCall, FnArg, ForeignItem,
+    ForeignItemFn, ForeignItemStatic, Ident, ImplItem, Item, ItemImpl, Pat, ReturnType,
+    StaticMutability, Stmt, Type,
 };
-use std::collections::HashMap;
-use syn::{Block, Expr, ExprCall, ForeignItem, Ident, ImplItem, ItemImpl, Stmt, Type};
 
 use super::bindgen_semantic_attributes::BindgenSemanticAttributes;
 
@@ -39,26 +51,158 @@ pub(crate) struct ParseForeignMod {
     // Evidence from 'impl' blocks about which of these items
     // may actually be methods (static or otherwise). Mapping from
     // function name to type name.
-    method_receivers: HashMap<Ident, QualifiedName>,
+    method_receivers: HashMap<minisyn::Ident, QualifiedName>,
+    // For functions with trailing parameters that have straightforward
+    // default values, how many of those parameters may be omitted.
+    omittable_defaults: HashMap<minisyn::Ident, usize>,
+    // Whether to make variants of functions taking `const std::string&`
+    // parameters which accept Rust strings instead.
+    str_params: bool,
+    // Types declared in this mod, and their C++ names (which differ for
+    // nested types), so that we can recognize static data members.
+    types: HashMap<Ident, String>,
+    // Static data which the user asked for by name. We only make accessor
+    // functions for these, because the C++ wrappers might not compile for
+    // others: bindgen doesn't tell us whether static data members are
+    // public, nor whether global variables can be copied.
+    requested_static_data: HashSet<String>,
+    static_data_accessors: Vec<FuncToConvert>,
+    // C-style variadic functions which the user asked to be exposed
+    // as raw declarations, and the declarations themselves.
+    variadic_requests: HashSet<String>,
+    raw_functions: HashMap<minisyn::Ident, ForeignItemFn>,
+    // Overloads to which the user has given particular Rust names.
+    overload_renames: Vec<OverloadRename>,
+    // Functions and methods to which the user has given particular Rust
+    // names, whatever their parameters.
+    name_renames: Vec<NameRename>,
+    // Functions which the user has marked safe or unsafe, whatever
+    // the `safety!` policy.
+    safety_overrides: Vec<SafetyOverride>,
     ignored_apis: ApiVec<NullPhase>,
 }
 
 impl ParseForeignMod {
-    pub(crate) fn new(ns: Namespace) -> Self {
+    pub(crate) fn new(ns: Namespace, str_params: bool) -> Self {
         Self {
             ns,
             funcs_to_convert: Vec::new(),
             method_receivers: HashMap::new(),
+            omittable_defaults: HashMap::new(),
+            str_params,
+            types: HashMap::new(),
+            requested_static_data: HashSet::new(),
+            static_data_accessors: Vec::new(),
+            variadic_requests: HashSet::new(),
+            raw_functions: HashMap::new(),
+            overload_renames: Vec::new(),
+            name_renames: Vec::new(),
+            safety_overrides: Vec::new(),
             ignored_apis: ApiVec::new(),
         }
     }
 
+    /// Record the types declared in this mod (with their C++ names) and
+    /// the fully-qualified names of any static data for which the user
+    /// wants accessor functions.
+    pub(crate) fn prepare_for_static_data(
+        &mut self,
+        types: HashMap<Ident, String>,
+        requested_static_data: HashSet<String>,
+    ) {
+        self.types = types;
+        self.requested_static_data = requested_static_data;
+    }
+
+    /// Constant globals which would be generated due to `generate_all!` or
+    /// `generate_ns!`, rather than being named individually, also get
+    /// accessor functions, so long as they're of types which we know can
+    /// simply be copied (numbers, `bool`s and pointers).
+    pub(crate) fn request_const_globals(
+        &mut self,
+        items: &[Item],
+        is_allowlisted: impl Fn(&str) -> bool,
+    ) {
+        let ns_prefix: String = self.ns.iter().map(|seg| format!("{seg}::")).collect();
+        let const_globals: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::ForeignMod(fm) => Some(&fm.items),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                ForeignItem::Static(item)
+                    if matches!(item.mutability, StaticMutability::None)
+                        && is_copyable_global_type(&item.ty)
+                        && self.find_static_member_owner(&item.ident).is_none() =>
+                {
+                    Some(format!("{ns_prefix}{}", item.ident))
+                }
+                _ => None,
+            })
+            .filter(|cpp_name| is_allowlisted(cpp_name))
+            .collect();
+        self.requested_static_data.extend(const_globals);
+    }
+
+    /// Record the fully-qualified names of any variadic functions which
+    /// the user wants exposed as raw `extern "C"` declarations.
+    pub(crate) fn prepare_for_variadic_functions(&mut self, variadic_requests: HashSet<String>) {
+        self.variadic_requests = variadic_requests;
+    }
+
+    /// Record any `rename!` directives choosing Rust names for overloads.
+    pub(crate) fn prepare_for_overload_renames(&mut self, overload_renames: Vec<OverloadRename>) {
+        self.overload_renames = overload_renames;
+    }
+
+    /// Record any `rename!` directives choosing Rust names for functions
+    /// or methods by name alone.
+    pub(crate) fn prepare_for_name_renames(&mut self, name_renames: Vec<NameRename>) {
+        self.name_renames = name_renames;
+    }
+
+    /// Record any `safe_fn!` or `unsafe_fn!` directives.
+    pub(crate) fn prepare_for_safety_overrides(&mut self, safety_overrides: Vec<SafetyOverride>) {
+        self.safety_overrides = safety_overrides;
+    }
+
+    /// bindgen names static data members `Type_member`. If this is one
+    /// of those, returns the type, its C++ name, and the member's name.
+    pub(crate) fn find_static_member_owner(&self, id: &Ident) -> Option<(&Ident, &str, String)> {
+        let id = id.to_string();
+        self.types
+            .iter()
+            .filter_map(|(ty, cpp_name)| {
+                id.strip_prefix(&format!("{ty}_"))
+                    .filter(|member| !member.is_empty())
+                    .map(|member| (ty, cpp_name.as_str(), member.to_string()))
+            })
+            // Prefer Outer_Inner over Outer for Outer_Inner_member.
+            .max_by_key(|(ty, _, _)| ty.to_string().len())
+    }
+
     /// Record information from foreign mod items encountered
     /// in bindgen output.
     pub(crate) fn convert_foreign_mod_items(&mut self, foreign_mod_items: Vec<ForeignItem>) {
         let mut extra_apis = ApiVec::new();
         for i in foreign_mod_items {
-            report_any_error(&self.ns.clone(), &mut extra_apis, || {
+            let id = match &i {
+                ForeignItem::Fn(item) => &item.sig.ident,
+                ForeignItem::Static(item) => &item.ident,
+                ForeignItem::Type(item) => &item.ident,
+                // bindgen doesn't generate anything else.
+                _ => {
+                    eprintln!(
+                        "Ignored item: {}",
+                        ConvertErrorFromCpp::UnexpectedForeignItem
+                    );
+                    continue;
+                }
+            };
+            let panic_ctx = ErrorContext::new_for_item(id.clone().into());
+            report_any_error(&self.ns.clone(), &mut extra_apis, panic_ctx, || {
                 self.parse_foreign_item(i)
             });
         }
@@ -70,7 +214,17 @@ impl ParseForeignMod {
             ForeignItem::Fn(item) => {
                 let annotations = BindgenSemanticAttributes::new(&item.attrs);
                 let doc_attrs = get_doc_attrs(&item.attrs);
-                self.funcs_to_convert.push(FuncToConvert {
+                if item.sig.variadic.is_some() {
+                    let cpp_name = annotations
+                        .get_original_name()
+                        .unwrap_or_else(|| item.sig.ident.to_string());
+                    let cpp_name = QualifiedName::new(&self.ns, make_ident(cpp_name)).to_cpp_name();
+                    if self.variadic_requests.contains(&cpp_name) {
+                        self.raw_functions
+                            .insert(item.sig.ident.clone().into(), make_raw_function(&item));
+                    }
+                }
+                let mut fun = FuncToConvert {
                     provenance: Provenance::Bindgen,
                     self_ty: None,
                     ident: item.sig.ident.into(),
@@ -90,13 +244,27 @@ impl ParseForeignMod {
                     is_deleted: annotations.get_deleted_or_defaulted(),
                     synthetic_cpp: None,
                     variadic: item.sig.variadic.is_some(),
-                });
+                    noexcept: annotations.is_noexcept(),
+                    explicit: annotations.is_explicit(),
+                    pinned_rust_name: None,
+                    safety_override: None,
+                    deprecated: annotations.get_deprecation(),
+                    must_use: annotations.is_nodiscard(),
+                    is_final: annotations.is_final(),
+                };
+                if annotations.is_rvalue_ref_qualified() {
+                    make_consuming_method(&mut fun);
+                }
+                let defaults = annotations.get_default_arguments();
+                let omittable = count_omittable_defaults(&fun, &defaults);
+                note_required_defaults(&mut fun, &defaults, omittable);
+                if omittable > 0 {
+                    self.omittable_defaults.insert(fun.ident.clone(), omittable);
+                }
+                self.funcs_to_convert.push(fun);
                 Ok(())
             }
-            ForeignItem::Static(item) => Err(ConvertErrorWithContext(
-                ConvertErrorFromCpp::StaticData(item.ident.to_string()),
-                Some(ErrorContext::new_for_item(item.ident.into())),
-            )),
+            ForeignItem::Static(item) => self.add_static_data_accessors(item),
             _ => Err(ConvertErrorWithContext(
                 ConvertErrorFromCpp::UnexpectedForeignItem,
                 None,
@@ -104,6 +272,107 @@ impl ParseForeignMod {
         }
     }
 
+    /// bindgen gives us statics only where it couldn't work out a constant
+    /// value. cxx can't access those directly, so create functions to get
+    /// them (and, if they're mutable, set them).
+    fn add_static_data_accessors(
+        &mut self,
+        item: ForeignItemStatic,
+    ) -> Result<(), ConvertErrorWithContext> {
+        let ns_prefix: String = self.ns.iter().map(|seg| format!("{seg}::")).collect();
+        let global_cpp_name = format!("{ns_prefix}{}", item.ident);
+        let member = self
+            .find_static_member_owner(&item.ident)
+            .map(|(ty, ty_cpp_name, member)| {
+                let cpp_name = format!("{ns_prefix}{ty_cpp_name}::{member}");
+                (ty.clone(), cpp_name, member)
+            })
+            // A global which merely looks like a static data member.
+            .filter(|_| !self.requested_static_data.contains(&global_cpp_name));
+        let (self_ty, cpp_name, ident_prefix, data_name) = match member {
+            Some((ty, cpp_name, member)) => (
+                Some(QualifiedName::new(&self.ns, ty.clone().into())),
+                cpp_name,
+                format!("{ty}_"),
+                member,
+            ),
+            None => (None, global_cpp_name, String::new(), item.ident.to_string()),
+        };
+        if !self.requested_static_data.contains(&cpp_name) {
+            let ctx = match &self_ty {
+                Some(self_ty) => {
+                    ErrorContext::new_for_method(self_ty.get_final_ident(), make_ident(&data_name))
+                }
+                None => ErrorContext::new_for_item(item.ident.into()),
+            };
+            return Err(ConvertErrorWithContext(
+                ConvertErrorFromCpp::StaticData(cpp_name),
+                Some(ctx),
+            ));
+        }
+        if BindgenSemanticAttributes::new(&item.attrs).has_internal_linkage() {
+            return Err(ConvertErrorWithContext(
+                ConvertErrorFromCpp::InternalStaticData(cpp_name),
+                Some(ErrorContext::new_for_item(item.ident.into())),
+            ));
+        }
+        let ty = &item.ty;
+        let getter_doc = format!(" Returns the value of the C++ static data `{cpp_name}`.");
+        let mut accessors: Vec<(_, Punctuated<FnArg, Comma>, ReturnType, _, _)> = vec![(
+            "get",
+            Punctuated::new(),
+            parse_quote! { -> #ty },
+            CppFunctionBody::StaticDataGetter(cpp_name.clone()),
+            getter_doc,
+        )];
+        if matches!(item.mutability, StaticMutability::Mut(_)) {
+            let setter_doc = format!(" Sets the value of the C++ static data `{cpp_name}`.");
+            accessors.push((
+                "set",
+                parse_quote! { value: #ty },
+                ReturnType::Default,
+                CppFunctionBody::StaticDataSetter(cpp_name.clone()),
+                setter_doc,
+            ));
+        }
+        for (verb, inputs, output, body, doc) in accessors {
+            let accessor_name = format!("{verb}_{data_name}");
+            let mut doc_attrs = get_doc_attrs(&item.attrs);
+            if !doc_attrs.is_empty() {
+                doc_attrs.push(parse_quote! { #[doc = ""] });
+            }
+            doc_attrs.push(parse_quote! { #[doc = #doc] });
+            self.static_data_accessors.push(FuncToConvert {
+                provenance: Provenance::SynthesizedStaticDataAccessor(cpp_name.clone()),
+                self_ty: self_ty.clone(),
+                ident: make_ident(format!("{ident_prefix}{accessor_name}")),
+                doc_attrs: minisynize_vec(doc_attrs),
+                inputs: minisynize_punctuated(inputs),
+                output: output.into(),
+                vis: parse_quote! { pub },
+                virtualness: Virtualness::None,
+                cpp_vis: CppVisibility::Public,
+                special_member: None,
+                unused_template_param: false,
+                references: References::default(),
+                original_name: self_ty.as_ref().map(|_| accessor_name),
+                synthesized_this_type: None,
+                add_to_trait: None,
+                is_deleted: DeletedOrDefaulted::Neither,
+                synthetic_cpp: Some((body, CppFunctionKind::Function)),
+                variadic: false,
+                noexcept: false,
+                explicit: false,
+                pinned_rust_name: None,
+                safety_override: None,
+                deprecated: None,
+                must_use: false,
+                is_final: false,
+            });
+        }
+        Ok(())
+    }
+
     /// Record information from impl blocks encountered in bindgen
     /// output.
     pub(crate) fn convert_impl_items(&mut self, imp: ItemImpl) {
@@ -118,7 +387,7 @@ impl ParseForeignMod {
                     None => itm.sig.ident,
                 };
                 self.method_receivers.insert(
-                    effective_fun_name,
+                    effective_fun_name.into(),
                     QualifiedName::new(&self.ns, ty_id.clone().into()),
                 );
             }
@@ -128,11 +397,12 @@ impl ParseForeignMod {
     /// Indicate that all foreign mods and all impl blocks have been
     /// fed into us, and we should process that information to generate
     /// the resulting APIs.
-    pub(crate) fn finished(mut self, apis: &mut ApiVec<NullPhase>) {
+    /// Returns the selectors of any `rename!` directives which matched
+    /// a function in this mod.
+    pub(crate) fn finished(mut self, apis: &mut ApiVec<NullPhase>) -> Vec<String> {
+        let mut matched_overload_renames = Vec::new();
         apis.append(&mut self.ignored_apis);
-        while !self.funcs_to_convert.is_empty() {
-            let mut fun = self.funcs_to_convert.remove(0);
-            fun.self_ty = self.method_receivers.get(&fun.ident).cloned();
+        for fun in self.static_data_accessors.drain(..) {
             apis.push(UnanalyzedApi::Function {
                 name: ApiName::new_with_cpp_name(
                     &self.ns,
@@ -143,9 +413,527 @@ impl ParseForeignMod {
                 analysis: (),
             })
         }
+        while !self.funcs_to_convert.is_empty() {
+            let mut fun = self.funcs_to_convert.remove(0);
+            fun.self_ty = self.method_receivers.get(&fun.ident).cloned();
+            if let Some(rename) = self.find_overload_rename(&fun) {
+                fun.pinned_rust_name = Some(rename.rust_name.clone());
+                matched_overload_renames.push(rename.selector.clone());
+            } else if let Some(rename) = self.find_name_rename(&fun) {
+                fun.pinned_rust_name = Some(rename.rust_name.clone());
+            }
+            fun.safety_override = self.find_safety_override(&fun);
+            // We can only declare free functions raw; variadic methods
+            // will be rejected later.
+            if fun.self_ty.is_none() {
+                if let Some(item) = self.raw_functions.remove(&fun.ident) {
+                    apis.push(UnanalyzedApi::RawFunction {
+                        name: ApiName::new_with_cpp_name(
+                            &self.ns,
+                            fun.ident.clone(),
+                            fun.original_name.clone(),
+                        ),
+                        deps: raw_function_deps(&item),
+                        item: ForeignItem::Fn(item).into(),
+                    });
+                    continue;
+                }
+            }
+            let omittable = self
+                .omittable_defaults
+                .get(&fun.ident)
+                .cloned()
+                .unwrap_or_default();
+            let mut variants: Vec<_> = (1..=omittable)
+                .map(|omitted| make_default_arguments_variant(&fun, omitted))
+                .collect();
+            if self.str_params && has_str_params_variant(&fun) {
+                variants.push(make_str_params_variant(&fun));
+            }
+            for fun in std::iter::once(fun).chain(variants) {
+                apis.push(UnanalyzedApi::Function {
+                    name: ApiName::new_with_cpp_name(
+                        &self.ns,
+                        fun.ident.clone(),
+                        fun.original_name.clone(),
+                    ),
+                    fun: Box::new(fun),
+                    analysis: (),
+                })
+            }
+        }
+        matched_overload_renames
+    }
+
+    /// The `rename!` directive, if any, which names this function or
+    /// method without giving its parameter types.
+    fn find_name_rename(&self, fun: &FuncToConvert) -> Option<&NameRename> {
+        if self.name_renames.is_empty() {
+            return None;
+        }
+        let function = self.cpp_function_name(fun);
+        self.name_renames
+            .iter()
+            .find(|rename| rename.cpp_name == function)
+    }
+
+    /// The fully-qualified C++ name of this function or method, such as
+    /// `A::Widget::set`.
+    fn cpp_function_name(&self, fun: &FuncToConvert) -> String {
+        let name = fun
+            .original_name
+            .clone()
+            .unwrap_or_else(|| fun.ident.to_string());
+        let this_ty = fun.inputs.iter().find_map(|arg| match &**arg {
+            FnArg::Typed(pt) if param_name(arg).as_deref() == Some("this") => match &*pt.ty {
+                Type::Ptr(ptr) => match &*ptr.elem {
+                    Type::Path(typ) => Some(QualifiedName::from_type_path(typ)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        });
+        match fun.self_ty.clone().or(this_ty) {
+            Some(self_ty) => format!("{}::{name}", self.cpp_type_name(&self_ty)),
+            None => QualifiedName::new(&self.ns, make_ident(name)).to_cpp_name(),
+        }
+    }
+
+    /// The `rename!` directive, if any, which selects this function by
+    /// its C++ name and parameter types.
+    fn find_overload_rename(&self, fun: &FuncToConvert) -> Option<&OverloadRename> {
+        if self.overload_renames.is_empty() {
+            return None;
+        }
+        let function = self.cpp_function_name(fun);
+        let params = self.cpp_param_types(fun);
+        self.overload_renames
+            .iter()
+            .find(|rename| rename.matches(&function, &params))
+    }
+
+    /// Whether a `safe_fn!` or `unsafe_fn!` directive selects this
+    /// function.
+    fn find_safety_override(&self, fun: &FuncToConvert) -> Option<FnSafety> {
+        if self.safety_overrides.is_empty() {
+            return None;
+        }
+        let function = self.cpp_function_name(fun);
+        let params = self.cpp_param_types(fun);
+        self.safety_overrides
+            .iter()
+            .find(|safety_override| safety_override.matches(&function, &params))
+            .map(|safety_override| safety_override.safety)
+    }
+
+    /// The C++ parameter types of this function, each spelled as by
+    /// [`OverloadRename::normalize_cpp_type`].
+    fn cpp_param_types(&self, fun: &FuncToConvert) -> Vec<String> {
+        fun.inputs
+            .iter()
+            .filter_map(|arg| match &**arg {
+                FnArg::Typed(pt) if param_name(arg).as_deref() != Some("this") => Some(pt),
+                _ => None,
+            })
+            .map(|pt| {
+                let param = match &*pt.pat {
+                    Pat::Ident(pi) => Some(crate::minisyn::Ident::from(pi.ident.clone())),
+                    _ => None,
+                };
+                let spelling = match &*pt.ty {
+                    Type::Ptr(ptr)
+                        if param
+                            .as_ref()
+                            .is_some_and(|p| fun.references.rvalue_ref_params.contains(p)) =>
+                    {
+                        format!("{}&&", self.cpp_type_spelling(&ptr.elem))
+                    }
+                    Type::Ptr(ptr)
+                        if param
+                            .as_ref()
+                            .is_some_and(|p| fun.references.ref_params.contains(p)) =>
+                    {
+                        format!(
+                            "{}{}&",
+                            if ptr.mutability.is_some() {
+                                ""
+                            } else {
+                                "const "
+                            },
+                            self.cpp_type_spelling(&ptr.elem)
+                        )
+                    }
+                    ty => self.cpp_type_spelling(ty),
+                };
+                OverloadRename::normalize_cpp_type(&spelling)
+            })
+            .collect()
+    }
+
+    /// Roughly how a user would spell this bindgen type in C++.
+    fn cpp_type_spelling(&self, ty: &Type) -> String {
+        match ty {
+            Type::Ptr(ptr) => format!(
+                "{}{}*",
+                if ptr.mutability.is_some() {
+                    ""
+                } else {
+                    "const "
+                },
+                self.cpp_type_spelling(&ptr.elem)
+            ),
+            Type::Path(typ) => {
+                let name = QualifiedName::from_type_path(typ);
+                known_types()
+                    .special_cpp_name(&name)
+                    .unwrap_or_else(|| self.cpp_type_name(&name))
+            }
+            _ => ty.to_token_stream().to_string(),
+        }
+    }
+
+    /// The C++ name of a type, which differs from the bindgen name for
+    /// nested types declared in this mod.
+    fn cpp_type_name(&self, name: &QualifiedName) -> String {
+        match self.types.get(&Ident::from(name.get_final_ident())) {
+            Some(cpp_name) if name.get_namespace() == &self.ns => {
+                let ns_prefix: String = self.ns.iter().map(|seg| format!("{seg}::")).collect();
+                format!("{ns_prefix}{cpp_name}")
+            }
+            _ => name.to_cpp_name(),
+        }
+    }
+}
+
+/// A `&&`-qualified method can only be called on an object which is
+/// being moved from, so it becomes a static method taking that object by
+/// value, which Rust callers give up. C++ moves from it to call the method.
+fn make_consuming_method(fun: &mut FuncToConvert) {
+    let method = make_ident(
+        fun.original_name
+            .clone()
+            .unwrap_or_else(|| fun.ident.to_string()),
+    );
+    for arg in fun.inputs.iter_mut() {
+        if param_name(arg).as_deref() != Some("this") {
+            continue;
+        }
+        if let FnArg::Typed(pt) = &**arg {
+            if let Type::Ptr(ptr) = pt.ty.as_ref() {
+                let ty = ptr.elem.clone();
+                let by_value: FnArg = parse_quote! { this_: #ty };
+                *arg = by_value.into();
+                fun.synthetic_cpp = Some((
+                    CppFunctionBody::ConsumingMethodCall(method),
+                    CppFunctionKind::Function,
+                ));
+                return;
+            }
+        }
+    }
+}
+
+/// A copy of bindgen's declaration of a function, keeping only the
+/// attributes which mean something to rustc.
+fn make_raw_function(item: &ForeignItemFn) -> ForeignItemFn {
+    let mut item = item.clone();
+    item.attrs
+        .retain(|attr| attr.path().is_ident("doc") || attr.path().is_ident("link_name"));
+    item
+}
+
+/// The C++ types used by a raw function declaration, which must be
+/// generated too.
+fn raw_function_deps(item: &ForeignItemFn) -> Vec<QualifiedName> {
+    let mut deps = Vec::new();
+    for input in &item.sig.inputs {
+        if let FnArg::Typed(pt) = input {
+            add_cpp_type_deps(&pt.ty, &mut deps);
+        }
+    }
+    if let ReturnType::Type(_, ty) = &item.sig.output {
+        add_cpp_type_deps(ty, &mut deps);
+    }
+    deps
+}
+
+fn add_cpp_type_deps(ty: &Type, deps: &mut Vec<QualifiedName>) {
+    match ty {
+        Type::Ptr(ptr) => add_cpp_type_deps(&ptr.elem, deps),
+        Type::Reference(r) => add_cpp_type_deps(&r.elem, deps),
+        Type::Path(typ)
+            if typ
+                .path
+                .segments
+                .first()
+                .is_some_and(|seg| seg.ident == "root") =>
+        {
+            let name = QualifiedName::from_type_path(typ);
+            if !deps.contains(&name) {
+                deps.push(name);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The name of a parameter, if it's a simple identifier.
+fn param_name(arg: &FnArg) -> Option<String> {
+    match arg {
+        FnArg::Typed(pt) => match &*pt.pat {
+            Pat::Ident(pi) => Some(pi.ident.to_string()),
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    }
+}
+
+/// The final path segment of a parameter's type, looking through
+/// any pointers or references (which is how bindgen represents
+/// C++ references).
+fn param_type_name(arg: &FnArg) -> Option<String> {
+    let mut ty = match arg {
+        FnArg::Typed(pt) => &*pt.ty,
+        FnArg::Receiver(_) => return None,
+    };
+    loop {
+        match ty {
+            Type::Ptr(ptr) => ty = &ptr.elem,
+            Type::Reference(r) => ty = &r.elem,
+            Type::Path(tp) => return tp.path.segments.last().map(|seg| seg.ident.to_string()),
+            _ => return None,
+        }
+    }
+}
+
+/// Work out how many trailing parameters of this function could be
+/// omitted, because they have default values which we are confident
+/// don't depend on anything except constants. Any parameter with a
+/// more elaborate default value (or none at all) stops the count,
+/// since C++ only permits the trailing parameters to be omitted.
+fn count_omittable_defaults(fun: &FuncToConvert, defaults: &HashMap<String, String>) -> usize {
+    if defaults.is_empty()
+        || fun.variadic
+        || !matches!(fun.virtualness, Virtualness::None)
+        || matches!(
+            fun.special_member,
+            Some(
+                SpecialMemberKind::CopyConstructor
+                    | SpecialMemberKind::MoveConstructor
+                    | SpecialMemberKind::Destructor
+                    | SpecialMemberKind::AssignmentOperator
+            )
+        )
+    {
+        return 0;
+    }
+    let param_names: Vec<String> = fun.inputs.iter().filter_map(|i| param_name(i)).collect();
+    fun.inputs
+        .iter()
+        .rev()
+        .take_while(|arg| {
+            let default = param_name(arg)
+                .filter(|name| name != "this")
+                .and_then(|name| defaults.get(&name));
+            match default {
+                Some(default) => is_simple_default(
+                    default.trim(),
+                    &param_names,
+                    param_type_name(arg).as_deref(),
+                ),
+                None => false,
+            }
+        })
+        .count()
+}
+
+/// Add a note to the documentation of the function for each parameter
+/// which has a C++ default value, but which nevertheless must be passed
+/// from Rust, saying why.
+fn note_required_defaults(
+    fun: &mut FuncToConvert,
+    defaults: &HashMap<String, String>,
+    omittable: usize,
+) {
+    let param_names: Vec<String> = fun.inputs.iter().filter_map(|i| param_name(i)).collect();
+    let required = fun.inputs.len() - omittable;
+    let notes: Vec<String> = fun
+        .inputs
+        .iter()
+        .take(required)
+        .filter_map(|arg| param_name(arg).filter(|name| name != "this"))
+        .filter_map(|name| {
+            let default = defaults.get(&name)?.trim();
+            let refers_to_param = default
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .any(|token| param_names.iter().any(|param| param == token));
+            let reason = if refers_to_param {
+                "it refers to another parameter"
+            } else if is_simple_default(default, &param_names, None) {
+                "a later parameter must be passed"
+            } else {
+                "autocxx can only omit parameters whose defaults are literals, enumerators or simple constructions"
+            };
+            Some(format!(
+                " Parameter `{name}` has the C++ default value `{default}`, but must always be passed from Rust because {reason}."
+            ))
+        })
+        .collect();
+    if !notes.is_empty() {
+        let blank: syn::Attribute = parse_quote! { #[doc = ""] };
+        fun.doc_attrs.push(blank.into());
+        fun.doc_attrs.extend(notes.iter().map(|note| {
+            let attr: syn::Attribute = parse_quote! { #[doc = #note] };
+            attr.into()
+        }));
     }
 }
 
+/// Whether a C++ default argument expression is one we're happy to
+/// allow Rust callers to omit: a numeric, boolean or enumerator literal,
+/// or a construction of the parameter's own type from such literals.
+/// Anything else (for instance, a call to an arbitrary function) means
+/// the parameter must always be supplied.
+fn is_simple_default(expr: &str, param_names: &[String], param_type: Option<&str>) -> bool {
+    if expr == "true" || expr == "false" || is_numeric_literal(expr) {
+        return true;
+    }
+    if is_qualified_identifier(expr) {
+        // An enumerator or constant, so long as it's not referring
+        // to another parameter.
+        return !param_names.iter().any(|name| name == expr);
+    }
+    let (callee, args) = match expr
+        .strip_suffix(')')
+        .and_then(|e| e.split_once('('))
+        .or_else(|| expr.strip_suffix('}').and_then(|e| e.split_once('{')))
+    {
+        Some(split) => split,
+        None => return false,
+    };
+    let callee = callee.trim();
+    let callee_type = callee.rsplit("::").next().unwrap_or_default();
+    is_qualified_identifier(callee)
+        && param_type == Some(callee_type)
+        && (args.trim().is_empty()
+            || args
+                .split(',')
+                .map(str::trim)
+                .all(|arg| is_simple_default(arg, param_names, None)))
+}
+
+fn is_numeric_literal(expr: &str) -> bool {
+    let digits = expr.trim_start_matches(['-', '+']);
+    let mut prev = ' ';
+    digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && digits.chars().all(|c| {
+            let ok = c.is_ascii_alphanumeric()
+                || c == '.'
+                || c == '\''
+                || (matches!(c, '+' | '-') && matches!(prev, 'e' | 'E' | 'p' | 'P'));
+            prev = c;
+            ok
+        })
+}
+
+fn is_qualified_identifier(expr: &str) -> bool {
+    let expr = expr.strip_prefix("::").unwrap_or(expr);
+    !expr.is_empty()
+        && expr.split("::").all(|seg| {
+            seg.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && seg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Make a copy of a function with the last `omitted` parameters
+/// removed. The C++ wrapper we generate for it simply won't pass
+/// those parameters, so the C++ default expressions are evaluated
+/// by the C++ compiler just as they would be for a C++ caller.
+fn make_default_arguments_variant(fun: &FuncToConvert, omitted: usize) -> FuncToConvert {
+    let mut variant = fun.clone();
+    let kept = fun.inputs.len() - omitted;
+    let this_count = fun
+        .inputs
+        .iter()
+        .filter(|arg| param_name(arg).as_deref() == Some("this"))
+        .count();
+    let supplied = kept - this_count;
+    let omitted_names = fun
+        .inputs
+        .iter()
+        .skip(kept)
+        .filter_map(|arg| param_name(arg))
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let doc = format!(
+        " This variant omits the trailing parameter(s) {omitted_names}, so their C++ default values are used."
+    );
+    variant.inputs = fun.inputs.iter().take(kept).cloned().collect();
+    variant.ident = make_ident(format!("{}_autocxx_defaults{supplied}", fun.ident));
+    variant.original_name = Some(
+        fun.original_name
+            .clone()
+            .unwrap_or_else(|| fun.ident.to_string()),
+    );
+    variant.special_member = None;
+    let doc_attrs: [syn::Attribute; 2] =
+        [parse_quote! { #[doc = ""] }, parse_quote! { #[doc = #doc] }];
+    variant
+        .doc_attrs
+        .extend(doc_attrs.into_iter().map(Into::into));
+    variant.provenance = Provenance::SynthesizedDefaultArguments { supplied };
+    variant
+}
+
+/// Whether we can make a variant of this function which accepts Rust
+/// strings for its `const std::string&` parameters. We don't do so
+/// if the function returns a reference, since its lifetime might be
+/// tied to one of those parameters.
+fn has_str_params_variant(fun: &FuncToConvert) -> bool {
+    !fun.variadic
+        && matches!(fun.virtualness, Virtualness::None)
+        && !matches!(
+            fun.special_member,
+            Some(
+                SpecialMemberKind::CopyConstructor
+                    | SpecialMemberKind::MoveConstructor
+                    | SpecialMemberKind::Destructor
+                    | SpecialMemberKind::AssignmentOperator
+            )
+        )
+        && !fun.references.ref_return
+        && !fun.references.rvalue_ref_return
+        && fun
+            .inputs
+            .iter()
+            .any(|arg| fun.is_const_string_ref_param(arg))
+}
+
+/// Make a copy of a function which will accept `impl AsRef<str>` for
+/// its `const std::string&` parameters. The conversion itself happens
+/// during function analysis; here we just name the variant.
+fn make_str_params_variant(fun: &FuncToConvert) -> FuncToConvert {
+    let mut variant = fun.clone();
+    variant.ident = make_ident(format!("{}_autocxx_str", fun.ident));
+    variant.original_name = Some(
+        fun.original_name
+            .clone()
+            .unwrap_or_else(|| fun.ident.to_string()),
+    );
+    variant.special_member = None;
+    let doc_attrs: [syn::Attribute; 2] = [
+        parse_quote! { #[doc = ""] },
+        parse_quote! { #[doc = " This variant accepts any Rust string for each `const std::string&` parameter."] },
+    ];
+    variant
+        .doc_attrs
+        .extend(doc_attrs.into_iter().map(Into::into));
+    variant.provenance = Provenance::SynthesizedStrParameters;
+    variant
+}
+
 /// bindgen sometimes generates an impl fn called a which calls
 /// a function called a1(), if it's dealing with conflicting names.
 /// We actually care about the name a1, so we have to parse the
@@ -160,11 +948,29 @@ fn get_called_function(block: &Block) -> Option<&Ident> {
     }
 }
 
+/// Whether the C++ wrapper for a global of this type can return it by
+/// value. Types declared in the headers might not be copyable, so we
+/// leave those alone.
+fn is_copyable_global_type(ty: &Type) -> bool {
+    match ty {
+        Type::Ptr(_) => true,
+        Type::Path(typ) => known_types().is_c_abi_by_value(&QualifiedName::from_type_path(typ)),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::get_called_function;
+    use super::{get_called_function, is_simple_default, param_name, ParseForeignMod};
+    use crate::conversion::analysis::fun::function_wrapper::{CppFunctionBody, CppFunctionKind};
+    use crate::conversion::api::Api;
+    use crate::conversion::apivec::ApiVec;
+    use crate::conversion::ConvertErrorFromCpp;
+    use crate::types::{make_ident, Namespace};
+    use autocxx_parser::{FnSafety, IncludeCppConfig};
+    use quote::ToTokens;
     use syn::parse_quote;
-    use syn::Block;
+    use syn::{Block, ForeignItem, ItemForeignMod};
 
     #[test]
     fn test_get_called_function() {
@@ -175,4 +981,474 @@ mod test {
         };
         assert_eq!(get_called_function(&b).unwrap().to_string(), "call_foo");
     }
+
+    #[test]
+    fn test_is_simple_default() {
+        let params = ["x".to_string(), "y".to_string()];
+        for expr in [
+            "1",
+            "-3",
+            "0x1F",
+            "1.0f",
+            "2.5e-3",
+            "true",
+            "Color::Red",
+            "kBlue",
+            "Point(1, 2)",
+            "ns::Point{}",
+        ] {
+            assert!(is_simple_default(expr, &params, Some("Point")), "{expr}");
+        }
+        for expr in [
+            "x",
+            "get_scale()",
+            "Other(1)",
+            "Point(x, 2)",
+            "1 + y",
+            "\"str\"",
+        ] {
+            assert!(!is_simple_default(expr, &params, Some("Point")), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_default_arguments_variants() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(arg_default(c, "Color::Red"))]
+                #[cpp_semantics(arg_default(scale, "1.0"))]
+                pub fn draw(x: i32, y: i32, c: root::Color, scale: f32);
+                #[cpp_semantics(arg_default(y, "compute()"))]
+                #[cpp_semantics(arg_default(z, "0"))]
+                pub fn place(x: i32, y: i32, z: i32);
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let names: Vec<_> = apis.iter().map(|api| api.name().to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "draw",
+                "draw_autocxx_defaults3",
+                "draw_autocxx_defaults2",
+                "place",
+                "place_autocxx_defaults2"
+            ]
+        );
+        let place_docs: Vec<_> = match apis.iter().nth(3) {
+            Some(Api::Function { fun, .. }) => fun
+                .doc_attrs
+                .iter()
+                .map(|attr| attr.to_token_stream().to_string())
+                .collect(),
+            _ => panic!("Expected place"),
+        };
+        assert_eq!(place_docs.len(), 2);
+        assert!(place_docs[1].contains("Parameter `y`"));
+        assert!(place_docs[1].contains("simple constructions"));
+    }
+
+    #[test]
+    fn test_str_params_variants() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(arg_type_reference(a))]
+                pub fn take(a: *const root::std::string, n: u32);
+                #[cpp_semantics(arg_type_reference(a))]
+                pub fn take_mut(a: *mut root::std::string);
+                pub fn take_ptr(a: *const root::std::string);
+                #[cpp_semantics(arg_type_reference(a))]
+                #[cpp_semantics(ret_type_reference)]
+                pub fn give_back(a: *const root::std::string) -> *const root::std::string;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), true);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let names: Vec<_> = apis.iter().map(|api| api.name().to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "take",
+                "take_autocxx_str",
+                "take_mut",
+                "take_ptr",
+                "give_back"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_static_data_accessors() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                pub static mut counter: u32;
+                pub static limit: u32;
+                pub static mut Widget_count: u32;
+                pub static mut Widget_secret: u32;
+                #[cpp_semantics(internal_linkage)]
+                pub static mut scratch: u32;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.prepare_for_static_data(
+            [(make_ident("Widget").into(), "Widget".to_string())]
+                .into_iter()
+                .collect(),
+            ["counter", "limit", "Widget::count", "scratch"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        );
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let names: Vec<_> = apis.iter().map(|api| api.name().to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "Widget",
+                "scratch",
+                "get_counter",
+                "set_counter",
+                "get_limit",
+                "Widget_get_count",
+                "Widget_set_count"
+            ]
+        );
+        assert!(matches!(
+            apis.iter().next(),
+            Some(Api::IgnoredItem {
+                err: ConvertErrorFromCpp::StaticData(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            apis.iter().nth(1),
+            Some(Api::IgnoredItem {
+                err: ConvertErrorFromCpp::InternalStaticData(_),
+                ..
+            })
+        ));
+        match apis.iter().nth(5) {
+            Some(Api::Function { fun, .. }) => {
+                assert_eq!(fun.self_ty.as_ref().unwrap().to_cpp_name(), "Widget");
+                assert_eq!(fun.original_name.as_deref(), Some("get_count"));
+            }
+            _ => panic!("Expected Widget_get_count"),
+        };
+    }
+
+    #[test]
+    fn test_raw_variadic_functions() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(original_name("log_printf"))]
+                #[link_name = "\u{1}_ZN1A10log_printfEiPKcz"]
+                pub fn log_printf(level: c_int, fmt: *const c_char, ...);
+                pub fn other_printf(fmt: *const root::A::Fmt, ...);
+                pub fn unwanted_printf(fmt: *const c_char, ...);
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::from_user_input("A"), false);
+        pfm.prepare_for_variadic_functions(
+            ["A::log_printf", "A::other_printf"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        );
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let mut apis = apis.iter();
+        match apis.next() {
+            Some(Api::RawFunction { item, deps, .. }) => {
+                match &**item {
+                    ForeignItem::Fn(f) => {
+                        assert!(f.sig.variadic.is_some());
+                        assert_eq!(f.attrs.len(), 1);
+                        assert!(f.attrs[0].path().is_ident("link_name"));
+                    }
+                    _ => panic!("Expected a function"),
+                }
+                assert!(deps.is_empty());
+            }
+            _ => panic!("Expected raw log_printf"),
+        };
+        match apis.next() {
+            Some(Api::RawFunction { deps, .. }) => {
+                assert_eq!(deps.len(), 1);
+                assert_eq!(deps[0].to_cpp_name(), "A::Fmt");
+            }
+            _ => panic!("Expected raw other_printf"),
+        };
+        // Anything not requested is rejected in the usual way later.
+        assert!(matches!(
+            apis.next(),
+            Some(Api::Function { fun, .. }) if fun.variadic
+        ));
+    }
+
+    #[test]
+    fn test_noexcept() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(noexcept)]
+                pub fn get_count() -> u32;
+                pub fn compute_count() -> u32;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let noexcept: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun.noexcept,
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(noexcept, [true, false]);
+    }
+
+    #[test]
+    fn test_explicit() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(explicit)]
+                pub fn new_from_seconds(seconds: i64) -> u32;
+                pub fn new_from_millis(millis: i64) -> u32;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let explicit: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun.explicit,
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(explicit, [true, false]);
+    }
+
+    #[test]
+    fn test_deprecated_and_nodiscard() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(deprecated("use compute_count2"))]
+                #[cpp_semantics(nodiscard)]
+                pub fn compute_count() -> u32;
+                #[cpp_semantics(deprecated)]
+                pub fn get_count() -> u32;
+                pub fn compute_count2() -> u32;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let attrs: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => (
+                    fun.deprecated.as_ref().map(|d| d.message.clone()),
+                    fun.must_use,
+                ),
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(
+            attrs,
+            [
+                (Some(Some("use compute_count2".to_string())), true),
+                (Some(None), false),
+                (None, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rvalue_ref_qualified() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(original_name("close"))]
+                #[cpp_semantics(rvalue_ref_qualified)]
+                pub fn Connection_close(this: *mut root::Connection, code: u32) -> bool;
+                #[cpp_semantics(original_name("send"))]
+                pub fn Connection_send(this: *mut root::Connection, code: u32) -> bool;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let funs: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun,
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        let first_params: Vec<_> = funs
+            .iter()
+            .map(|fun| param_name(fun.inputs.first().unwrap()).unwrap())
+            .collect();
+        assert_eq!(first_params, ["this_", "this"]);
+        assert!(matches!(
+            &funs[0].synthetic_cpp,
+            Some((CppFunctionBody::ConsumingMethodCall(id), CppFunctionKind::Function)) if id == "close"
+        ));
+        assert!(funs[1].synthetic_cpp.is_none());
+    }
+
+    #[test]
+    fn test_const_span_params() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(arg_type_const_span(values))]
+                pub fn sum(values: root::std::span<f32>, out: root::std::span<f32>) -> f32;
+            }
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::new(), false);
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        match apis.iter().next() {
+            Some(Api::Function { fun, .. }) => {
+                let const_spans: Vec<_> = fun
+                    .references
+                    .const_span_params
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect();
+                assert_eq!(const_spans, ["values"]);
+            }
+            _ => panic!("Expected a function"),
+        };
+    }
+
+    #[test]
+    fn test_overload_renames() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(original_name("set"))]
+                pub fn Widget_set(this: *mut root::A::Widget, x: ::std::os::raw::c_int);
+                #[cpp_semantics(original_name("set"))]
+                pub fn Widget_set1(this: *mut root::A::Widget, x: f64);
+                #[cpp_semantics(original_name("set"))]
+                #[cpp_semantics(arg_type_reference(s))]
+                pub fn Widget_set2(this: *mut root::A::Widget, s: *const root::std::string);
+                #[cpp_semantics(original_name("Widget"))]
+                pub fn Widget_Widget(this: *mut root::A::Widget, x: f64);
+                pub fn reset(x: *mut root::A::Widget);
+                #[cpp_semantics(original_name("reset"))]
+                pub fn reset1();
+            }
+        };
+        let config: IncludeCppConfig = parse_quote! {
+            rename!("A::Widget::set(double)", "set_f64")
+            rename!("A::Widget::set(const std::string &)", "set_string")
+            rename!("A::Widget::Widget(double)", "new_f64")
+            rename!("A::reset()", "reset_all")
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::from_user_input("A"), false);
+        pfm.prepare_for_overload_renames(config.get_overload_renames().to_vec());
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        let matched = pfm.finished(&mut apis);
+        assert_eq!(matched.len(), 4);
+        let pinned: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun.pinned_rust_name.clone(),
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(
+            pinned,
+            [
+                None,
+                Some("set_f64".into()),
+                Some("set_string".into()),
+                Some("new_f64".into()),
+                None,
+                Some("reset_all".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_renames() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                #[cpp_semantics(original_name("move"))]
+                pub fn Widget_move(this: *mut root::A::Widget);
+                #[cpp_semantics(original_name("move"))]
+                pub fn move_(x: ::std::os::raw::c_int);
+                pub fn other();
+            }
+        };
+        let config: IncludeCppConfig = parse_quote! {
+            rename!("A::Widget::move", "relocate")
+            rename!("A::move", "shift")
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::from_user_input("A"), false);
+        pfm.prepare_for_name_renames(config.get_name_renames().to_vec());
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let pinned: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun.pinned_rust_name.clone(),
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(
+            pinned,
+            [Some("relocate".into()), Some("shift".into()), None]
+        );
+    }
+
+    #[test]
+    fn test_safety_overrides() {
+        let foreign_mod: ItemForeignMod = parse_quote! {
+            extern "C" {
+                pub fn risky(x: *mut ::std::os::raw::c_int);
+                #[cpp_semantics(original_name("audited"))]
+                pub fn audited(x: *mut f64);
+                #[cpp_semantics(original_name("audited"))]
+                pub fn audited1(x: *mut ::std::os::raw::c_int);
+                pub fn other();
+            }
+        };
+        let config: IncludeCppConfig = parse_quote! {
+            unsafe_fn!("A::risky")
+            safe_fn!("A::audited(double*)")
+        };
+        let mut pfm = ParseForeignMod::new(Namespace::from_user_input("A"), false);
+        pfm.prepare_for_safety_overrides(config.get_safety_overrides().to_vec());
+        pfm.convert_foreign_mod_items(foreign_mod.items);
+        let mut apis = ApiVec::new();
+        pfm.finished(&mut apis);
+        let safety: Vec<_> = apis
+            .iter()
+            .map(|api| match api {
+                Api::Function { fun, .. } => fun.safety_override,
+                _ => panic!("Expected only functions"),
+            })
+            .collect();
+        assert_eq!(
+            safety,
+            [Some(FnSafety::Unsafe), Some(FnSafety::Safe), None, None]
+        );
+    }
 }
@@ -0,0 +1,162 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen represents a C++ union as a struct with a zero-sized
+//! `__BindgenUnionField<T>` field for each member, plus a
+//! `bindgen_union_field` which has the right size and alignment. cxx can't
+//! cope with the former, so we rewrite each union to hold only its storage,
+//! and instead give Rust code accessor methods for the members. Anonymous
+//! unions within a struct become opaque storage within that struct, and the
+//! struct gets the accessors.
+
+use indexmap::map::IndexMap as HashMap;
+
+use syn::{Fields, GenericArgument, Ident, Item, ItemStruct, PathArguments, Type, Visibility};
+
+use crate::{
+    conversion::api::UnionMember,
+    types::{make_ident, Namespace, QualifiedName},
+};
+
+/// Rewrites all the unions found in the bindgen `root` mod, returning the
+/// members for which each struct should have accessors.
+pub(super) fn resolve_unions(items: &mut [Item]) -> HashMap<QualifiedName, Vec<UnionMember>> {
+    let mut unions = HashMap::new();
+    find_unions(items, &mut unions);
+    let mut accessors = HashMap::new();
+    rewrite_mod(items, &Namespace::new(), &unions, &mut accessors);
+    accessors
+}
+
+/// A union's storage, and its members.
+struct Union {
+    storage: Type,
+    members: Vec<(Ident, Type)>,
+}
+
+fn is_anonymous(id: &Ident) -> bool {
+    let id = id.to_string();
+    id.starts_with("_bindgen_ty_") || id.contains("__bindgen_ty_")
+}
+
+fn find_unions(items: &[Item], unions: &mut HashMap<Ident, Union>) {
+    for item in items {
+        match item {
+            Item::Mod(itm) => {
+                if let Some((_, items)) = &itm.content {
+                    find_unions(items, unions);
+                }
+            }
+            Item::Struct(s) => {
+                if let Some(union) = union_details(s) {
+                    unions.insert(s.ident.clone(), union);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn union_details(s: &ItemStruct) -> Option<Union> {
+    let mut storage = None;
+    let mut members = Vec::new();
+    for f in &s.fields {
+        let id = f.ident.as_ref()?;
+        if id == "bindgen_union_field" {
+            storage = Some(f.ty.clone());
+        } else if let Some(ty) = union_member_type(&f.ty) {
+            // Bit-fields within unions don't get accessors.
+            if !id.to_string().starts_with("_bitfield_") {
+                members.push((id.clone(), ty.clone()));
+            }
+        }
+    }
+    storage.map(|storage| Union { storage, members })
+}
+
+/// If this is `__BindgenUnionField<T>`, then `T`.
+fn union_member_type(ty: &Type) -> Option<&Type> {
+    let seg = match ty {
+        Type::Path(tp) => tp.path.segments.last()?,
+        _ => return None,
+    };
+    if seg.ident != "__BindgenUnionField" {
+        return None;
+    }
+    match &seg.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(ty)) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn rewrite_mod(
+    items: &mut [Item],
+    ns: &Namespace,
+    unions: &HashMap<Ident, Union>,
+    accessors: &mut HashMap<QualifiedName, Vec<UnionMember>>,
+) {
+    for item in items {
+        match item {
+            Item::Mod(itm) => {
+                if let Some((_, items)) = &mut itm.content {
+                    rewrite_mod(items, &ns.push(itm.ident.to_string()), unions, accessors);
+                }
+            }
+            Item::Struct(s) => {
+                let mut members = Vec::new();
+                if let Some(union) = unions.get(&s.ident) {
+                    let storage = &union.storage;
+                    s.fields = Fields::Named(syn::parse_quote! { {
+                        bindgen_union_field: #storage,
+                    } });
+                    members.extend(union.members.iter().map(|(id, ty)| UnionMember {
+                        accessor: id.clone().into(),
+                        storage_field: None,
+                        ty: ty.clone().into(),
+                    }));
+                }
+                for f in s.fields.iter_mut() {
+                    let union = match (&f.ident, &f.ty) {
+                        (Some(field_id), Type::Path(tp)) => tp
+                            .path
+                            .segments
+                            .last()
+                            .filter(|seg| is_anonymous(&seg.ident))
+                            .and_then(|seg| unions.get(&seg.ident))
+                            .map(|union| (field_id.clone(), union)),
+                        _ => None,
+                    };
+                    if let Some((field_id, union)) = union {
+                        f.ty = union.storage.clone();
+                        f.vis = Visibility::Inherited;
+                        // The members of a truly anonymous union are
+                        // accessed in C++ as if they were members of the
+                        // struct, so we name the accessors the same way.
+                        let prefix = if field_id.to_string().starts_with("__bindgen_anon_") {
+                            String::new()
+                        } else {
+                            format!("{field_id}_")
+                        };
+                        members.extend(union.members.iter().map(|(id, ty)| UnionMember {
+                            accessor: make_ident(format!("{prefix}{id}")),
+                            storage_field: Some(field_id.clone().into()),
+                            ty: ty.clone().into(),
+                        }));
+                    }
+                }
+                if !members.is_empty() && !is_anonymous(&s.ident) {
+                    accessors.insert(QualifiedName::new(ns, s.ident.clone().into()), members);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -7,8 +7,8 @@
 // except according to those terms.
 
 use syn::{
-    AngleBracketedGenericArguments, GenericArgument, PathArguments, PathSegment, Type, TypePath,
-    TypeReference,
+    AngleBracketedGenericArguments, GenericArgument, PathArguments, PathSegment, Type, TypeArray,
+    TypeBareFn, TypePath, TypeReference,
 };
 
 /// Looks in a `core::pin::Pin<&mut Something>` and returns the `Something`
@@ -39,14 +39,49 @@ pub(crate) fn extract_pinned_mutable_reference_type(tp: &TypePath) -> Option<&Ty
     None
 }
 
+/// Bindgen represents a C function pointer as an
+/// `Option<unsafe extern "C" fn(...)>`. Looks in such an `Option` and returns
+/// the `fn` if it's found.
+pub(crate) fn extract_function_pointer_signature(tp: &TypePath) -> Option<&TypeBareFn> {
+    if !has_segments(tp, &[&["std", "core"], &["option"], &["Option"]]) {
+        return None;
+    }
+    if let Some(PathSegment {
+        arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+        ..
+    }) = tp.path.segments.last()
+    {
+        if args.len() == 1 {
+            if let Some(GenericArgument::Type(Type::BareFn(bare_fn))) = args.first() {
+                return Some(bare_fn);
+            }
+        }
+    }
+    None
+}
+
+/// Returns the type of the elements of an array, looking through any
+/// nested arrays. Returns the type itself if it's not an array.
+pub(crate) fn array_element_type(mut ty: &Type) -> &Type {
+    while let Type::Array(TypeArray { elem, .. }) = ty {
+        ty = elem;
+    }
+    ty
+}
+
 /// Whether this type path is a `Pin`
 fn is_pin(tp: &TypePath) -> bool {
-    if tp.path.segments.len() != 3 {
+    has_segments(tp, &[&["std", "core"], &["pin"], &["Pin"]])
+}
+
+/// Whether this type path has the given segments, each of which may have
+/// one of several names.
+fn has_segments(tp: &TypePath, expected_segments: &[&[&str]]) -> bool {
+    if tp.path.segments.len() != expected_segments.len() {
         return false;
     }
-    static EXPECTED_SEGMENTS: &[&[&str]] = &[&["std", "core"], &["pin"], &["Pin"]];
 
-    for (seg, expected_name) in tp.path.segments.iter().zip(EXPECTED_SEGMENTS.iter()) {
+    for (seg, expected_name) in tp.path.segments.iter().zip(expected_segments.iter()) {
         if !expected_name
             .iter()
             .any(|expected_name| seg.ident == expected_name)
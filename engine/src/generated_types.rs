@@ -0,0 +1,97 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The types for which an `include_cpp!` generated bindings, so that
+//! another `include_cpp!` in the same file can use them (via
+//! `import_from!`) rather than generating its own, incompatible,
+//! bindings for the same C++ types.
+
+use syn::{Path, TypePath};
+
+/// A C++ type for which an `include_cpp!` generated bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GeneratedType {
+    /// The C++ name of the type, as bindgen spells it, e.g. `mylib::Engine`.
+    pub(crate) cpp_name: String,
+    /// Its path within the generated mod, e.g. `mylib::Engine`.
+    pub(crate) rust_path: String,
+    pub(crate) pod: bool,
+}
+
+impl GeneratedType {
+    /// This type, as used by an `include_cpp!` which imports it from the
+    /// generated mod at `mod_path`.
+    pub(crate) fn import_from(&self, mod_path: &Path) -> ImportedType {
+        let rust_path = syn::parse_str(&format!(
+            "{}::{}",
+            quote::quote! { #mod_path },
+            self.rust_path
+        ))
+        .expect("generated type paths should always be valid");
+        ImportedType {
+            cpp_name: self.cpp_name.clone(),
+            rust_path,
+            pod: self.pod,
+        }
+    }
+
+    /// How this is recorded in the cache: as a single line.
+    pub(crate) fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.cpp_name, self.rust_path, self.pod)
+    }
+
+    /// The reverse of [`Self::to_line`].
+    pub(crate) fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let generated_type = Self {
+            cpp_name: fields.next()?.to_string(),
+            rust_path: fields.next()?.to_string(),
+            pod: fields.next()?.parse().ok()?,
+        };
+        match fields.next() {
+            None => Some(generated_type),
+            Some(_) => None,
+        }
+    }
+}
+
+/// A C++ type whose bindings were generated by another `include_cpp!`,
+/// which this one should use just as if it were named in an
+/// `extern_cpp_type!` directive.
+#[derive(Debug, Clone)]
+pub(crate) struct ImportedType {
+    pub(crate) cpp_name: String,
+    pub(crate) rust_path: TypePath,
+    pub(crate) pod: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeneratedType;
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_generated_type_round_trip() {
+        let generated_type = GeneratedType {
+            cpp_name: "mylib::Engine".into(),
+            rust_path: "mylib::Engine".into(),
+            pod: true,
+        };
+        assert_eq!(
+            GeneratedType::from_line(&generated_type.to_line()),
+            Some(generated_type.clone())
+        );
+        let imported = generated_type.import_from(&parse_quote! { crate::ffi_a });
+        assert_eq!(
+            imported.rust_path.to_token_stream().to_string(),
+            "crate :: ffi_a :: mylib :: Engine"
+        );
+        assert!(imported.pod);
+    }
+}
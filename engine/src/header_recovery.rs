@@ -0,0 +1,150 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! If clang can't parse one of the headers listed in an `include_cpp!`,
+//! we'd rather generate bindings for the others than fail entirely.
+//! This works out which headers clang's errors came from.
+
+use indexmap::map::IndexMap as HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+/// A header which clang couldn't parse, and which was therefore left out
+/// of the bindings so that bindings could be generated for the other
+/// headers.
+#[derive(Debug, Clone)]
+pub struct DroppedHeader {
+    /// The header, as named in the `include_cpp!` macro.
+    pub header: String,
+    /// The errors which clang reported, one per line.
+    pub diagnostics: Vec<String>,
+}
+
+impl Display for DroppedHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was left out of the bindings because clang couldn't parse it: {}",
+            self.header,
+            self.diagnostics.join("; ")
+        )
+    }
+}
+
+/// The header which bindgen is asked to parse: some prelude, followed
+/// by an `#include` for each header in the `include_cpp!`. clang reports
+/// errors such as a missing header against the `#include` line.
+pub(crate) struct SynthesizedHeader<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) contents: String,
+    /// 1-based line number of the `#include` of the first header.
+    first_include_line: usize,
+}
+
+impl<'a> SynthesizedHeader<'a> {
+    pub(crate) fn new(name: &'a str, prelude: &str, includes: &str) -> Self {
+        let prelude_and_gap = format!("{prelude}\n\n");
+        Self {
+            name,
+            first_include_line: prelude_and_gap.matches('\n').count() + 1,
+            contents: format!("{prelude_and_gap}{includes}"),
+        }
+    }
+}
+
+/// Divides up the errors reported by clang according to which header
+/// (an index into `inclusions`) they occurred in. Errors which we can't
+/// attribute to any particular header are returned separately.
+pub(crate) fn attribute_errors(
+    message: &str,
+    inclusions: &[String],
+    synthesized_header: &SynthesizedHeader,
+) -> (HashMap<usize, Vec<String>>, Vec<String>) {
+    let mut attributed: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut unattributed = Vec::new();
+    for diagnostic in message.lines().filter(|line| !line.trim().is_empty()) {
+        match attribute_error(diagnostic, inclusions, synthesized_header) {
+            Some(idx) => attributed
+                .entry(idx)
+                .or_default()
+                .push(diagnostic.to_string()),
+            None => unattributed.push(diagnostic.to_string()),
+        }
+    }
+    (attributed, unattributed)
+}
+
+fn attribute_error(
+    diagnostic: &str,
+    inclusions: &[String],
+    synthesized_header: &SynthesizedHeader,
+) -> Option<usize> {
+    let (file, line) = diagnostic_location(diagnostic)?;
+    if Path::new(file).ends_with(synthesized_header.name) {
+        // The #include itself failed, e.g. because the file is missing.
+        let idx = line.checked_sub(synthesized_header.first_include_line)?;
+        return (idx < inclusions.len()).then_some(idx);
+    }
+    inclusions
+        .iter()
+        .position(|inclusion| Path::new(file).ends_with(inclusion))
+}
+
+/// clang formats diagnostics as `file:line:column: error: message`.
+/// Extracts the file and line.
+fn diagnostic_location(diagnostic: &str) -> Option<(&str, usize)> {
+    let location = diagnostic
+        .find(": error:")
+        .or_else(|| diagnostic.find(": fatal error:"))
+        .map(|pos| &diagnostic[..pos])?;
+    // Split from the right, since the file name may itself contain a colon
+    // (as in a Windows path).
+    let mut parts = location.rsplitn(3, ':');
+    let _column = parts.next()?.parse::<usize>().ok()?;
+    let line = parts.next()?.parse::<usize>().ok()?;
+    let file = parts.next()?;
+    Some((file, line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attribute_errors, SynthesizedHeader};
+
+    #[test]
+    fn test_attribute_errors() {
+        let inclusions = vec![
+            "good.h".to_string(),
+            "sub/broken.h".to_string(),
+            "missing.h".to_string(),
+        ];
+        let header = SynthesizedHeader::new(
+            "example.hpp",
+            "// prelude\n// more prelude",
+            "#include \"good.h\"\n#include \"sub/broken.h\"\n#include \"missing.h\"\n",
+        );
+        assert_eq!(
+            header.contents.lines().nth(5),
+            Some("#include \"missing.h\"")
+        );
+        let message = "/tmp/inc/sub/broken.h:3:1: error: unknown type name 'wat'\n\
+            /tmp/inc/sub/broken.h:4:1: error: expected ';' after top level declarator\n\
+            ./example.hpp:6:10: fatal error: 'missing.h' file not found\n\
+            /usr/include/nested.h:1:1: error: something else\n";
+        let (attributed, unattributed) = attribute_errors(message, &inclusions, &header);
+        assert_eq!(attributed.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(attributed[&1].len(), 2);
+        assert_eq!(
+            attributed[&2],
+            ["./example.hpp:6:10: fatal error: 'missing.h' file not found"]
+        );
+        assert_eq!(
+            unattributed,
+            ["/usr/include/nested.h:1:1: error: something else"]
+        );
+    }
+}
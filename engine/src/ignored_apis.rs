@@ -0,0 +1,89 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! APIs which the user explicitly asked for, but for which we couldn't
+//! generate bindings. The generated code documents why, but that's easy
+//! to miss, so builds also warn about them.
+
+use std::fmt::Display;
+
+/// An API which was requested with `generate!`, `generate_pod!` or
+/// `subclass!` (or which belongs to a type so requested) but for which
+/// bindings couldn't be generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredApi {
+    /// The name of the API, e.g. `ns::Widget::frob`.
+    pub name: String,
+    /// Why bindings couldn't be generated.
+    pub reason: String,
+    /// What the user might do about it, such as a directive to try.
+    pub suggestion: Option<String>,
+}
+
+impl Display for IgnoredApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} was ignored: {}", self.name, self.reason)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Beyond this many ignored APIs, we summarize rather than warning about
+/// each in turn.
+const MAX_WARNINGS: usize = 20;
+
+/// The warnings which a build should emit about these ignored APIs.
+pub(crate) fn ignored_api_warnings(ignored_apis: &[IgnoredApi]) -> Vec<String> {
+    let mut warnings: Vec<_> = ignored_apis
+        .iter()
+        .take(MAX_WARNINGS)
+        .map(|ignored| format!("autocxx: {ignored}"))
+        .collect();
+    if ignored_apis.len() > MAX_WARNINGS {
+        warnings.push(format!(
+            "autocxx: ... and {} more APIs were ignored. Use fail_on_ignored(true) to list them all.",
+            ignored_apis.len() - MAX_WARNINGS
+        ));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ignored_api_warnings, IgnoredApi, MAX_WARNINGS};
+
+    #[test]
+    fn test_ignored_api_warnings() {
+        let ignored = |n| IgnoredApi {
+            name: format!("Widget::frob{n}"),
+            reason: "it was too frobby".to_string(),
+            suggestion: None,
+        };
+        assert_eq!(
+            ignored_api_warnings(&[ignored(0)]),
+            ["autocxx: Widget::frob0 was ignored: it was too frobby"]
+        );
+        let suggested = IgnoredApi {
+            suggestion: Some("Try defrob!(\"Widget\").".to_string()),
+            ..ignored(0)
+        };
+        assert_eq!(
+            ignored_api_warnings(&[suggested]),
+            ["autocxx: Widget::frob0 was ignored: it was too frobby Try defrob!(\"Widget\")."]
+        );
+        let many: Vec<_> = (0..MAX_WARNINGS + 3).map(ignored).collect();
+        let warnings = ignored_api_warnings(&many);
+        assert_eq!(warnings.len(), MAX_WARNINGS + 1);
+        assert_eq!(
+            warnings.last().unwrap(),
+            "autocxx: ... and 3 more APIs were ignored. Use fail_on_ignored(true) to list them all."
+        );
+    }
+}
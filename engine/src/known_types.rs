@@ -294,6 +294,33 @@ impl TypeDatabase {
             .unwrap_or(false)
     }
 
+    /// Whether this type can be passed by value to or from a plain C
+    /// function, for instance the target of a C function pointer.
+    pub(crate) fn is_c_abi_by_value(&self, ty: &QualifiedName) -> bool {
+        self.get(ty)
+            .map(|td| {
+                matches!(
+                    td.behavior,
+                    Behavior::CByValue
+                        | Behavior::CByValueVecSafe
+                        | Behavior::CVariableLengthByValue
+                        | Behavior::CChar16
+                )
+            })
+            .unwrap_or(false)
+    }
+
+    /// Whether this is an integer type, of either fixed or variable length.
+    pub(crate) fn is_integer(&self, ty: &QualifiedName) -> bool {
+        self.get(ty)
+            .map(|td| match td.behavior {
+                Behavior::CVariableLengthByValue => true,
+                Behavior::CByValueVecSafe => !matches!(td.rs_name.as_str(), "f32" | "f64"),
+                _ => false,
+            })
+            .unwrap_or(false)
+    }
+
     /// Whether this is a generic type acceptable to cxx. Otherwise,
     /// if we encounter a generic, we'll replace it with a synthesized concrete
     /// type.
@@ -10,32 +10,51 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#![forbid(unsafe_code)]
+// The libclang module talks to libclang directly, and is the only exception.
+#![deny(unsafe_code)]
 #![cfg_attr(feature = "nightly", feature(doc_cfg))]
 
+mod api_filter;
+mod api_report;
 mod ast_discoverer;
+mod bindgen_runner;
+mod cache;
 mod conversion;
 mod cxxbridge;
+mod generated_types;
+mod header_recovery;
+mod ignored_apis;
 mod known_types;
+mod libclang;
 mod minisyn;
 mod output_generators;
 mod parse_callbacks;
 mod parse_file;
 mod rust_pretty_printer;
+mod source_locations;
+mod system_headers;
+#[cfg(any(test, feature = "build"))]
+mod target_args;
 mod types;
 
 #[cfg(any(test, feature = "build"))]
 mod builder;
 
+use api_report::cxx_bridge_items;
 use autocxx_bindgen::BindgenError;
 use autocxx_parser::{IncludeCppConfig, UnsafePolicy};
-use conversion::BridgeConverter;
+use bindgen_runner::{BindgenInputs, BindgenOutput};
+use cache::GenerationCache;
+use conversion::{find_nested_type_names, BridgeConverter};
+use generated_types::{GeneratedType, ImportedType};
+use ignored_apis::ignored_api_warnings;
+use indexmap::map::IndexMap as HashMap;
 use miette::{SourceOffset, SourceSpan};
-use parse_callbacks::AutocxxParseCallbacks;
 use parse_file::CppBuildable;
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{Delimiter, Group, TokenStream as TokenStream2, TokenTree};
 use regex::Regex;
-use std::cell::RefCell;
+use source_locations::SourceLocator;
+use std::cell::{Cell, RefCell};
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::{
@@ -44,6 +63,7 @@ use std::{
     path::Path,
     process::{Command, Stdio},
 };
+use system_headers::SystemHeaderFilter;
 use tempfile::NamedTempFile;
 
 use quote::ToTokens;
@@ -63,12 +83,23 @@ use miette::Diagnostic;
 /// We hope to unfork.
 use autocxx_bindgen as bindgen;
 
+pub use api_filter::{ApiDecision, ApiFilter, ApiSummary};
+pub use api_report::{write_api_report, ApiKind, ConversionSummary, ReportedApi};
 #[cfg(any(test, feature = "build"))]
 pub use builder::{
     Builder, BuilderBuild, BuilderContext, BuilderError, BuilderResult, BuilderSuccess,
 };
-pub use output_generators::{generate_rs_archive, generate_rs_single, RsOutput};
+pub use conversion::ConvertErrorFromCpp;
+pub use header_recovery::DroppedHeader;
+pub use ignored_apis::IgnoredApi;
+pub use output_generators::{
+    generate_rs_archive, generate_rs_single, generate_rs_single_pretty, RsOutput,
+};
 pub use parse_file::{parse_file, ParseError, ParsedFile};
+pub use source_locations::SourceLocation;
+#[cfg(any(test, feature = "build"))]
+pub use target_args::TargetArgsError;
+pub use types::QualifiedName;
 
 pub use cxx_gen::HEADER;
 
@@ -82,6 +113,10 @@ pub struct CppFilePair {
     /// The name which should be used for the header file
     /// (important as it may be `#include`d elsewhere)
     pub header_name: String,
+    /// Further implementations, if we were asked to split the C++ into
+    /// several files. When we hand out the generated C++, each of these
+    /// becomes its own `CppFilePair`, sharing the same header.
+    pub(crate) extra_implementations: Vec<Vec<u8>>,
 }
 
 /// All generated C++ content which should be written to disk.
@@ -137,9 +172,15 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 struct GenerationResults {
     item_mod: ItemMod,
     cpp: Option<CppFilePair>,
+    dropped_headers: Vec<DroppedHeader>,
+    ignored_apis: Vec<IgnoredApi>,
+    generated_types: Vec<GeneratedType>,
+    reported_apis: Vec<ReportedApi>,
     #[allow(dead_code)]
     inc_dirs: Vec<PathBuf>,
     cxxgen_header_name: String,
+    /// The Cargo features which may gate the bindings.
+    cfg_features: Vec<String>,
 }
 enum State {
     NotGenerated,
@@ -155,6 +196,52 @@ pub struct CodegenOptions<'a> {
     pub force_wrapper_gen: bool,
     /// Options about the C++ code generation.
     pub cpp_codegen_options: CppCodegenOptions<'a>,
+    /// Generate APIs in an order which depends only on their names,
+    /// rather than the order in which they were discovered, such that
+    /// the output is stable enough to be checked into source control.
+    pub stable_output: bool,
+    /// Items named in `generate!` (or similar) or `subclass!` directives
+    /// whose Rust bindings should be gated behind a Cargo feature, as
+    /// pairs of (item name, feature name). Anything needed by several such
+    /// items is gated behind any of their features; anything needed by
+    /// an ungated item is not gated at all. The C++ side of the bindings
+    /// is always generated in full.
+    pub cfg_features: Vec<(String, String)>,
+    /// Return C++ exceptions from all generated functions, methods and
+    /// constructors as a Rust `Result`, as if every item had been listed
+    /// in a `fallible!` directive.
+    pub fallible_by_default: bool,
+    /// A directory in which to cache generated bindings. If the `include_cpp!`
+    /// configuration, options and the contents of every header which
+    /// contributed to the bindings are unchanged since they were cached,
+    /// they're reused instead of running bindgen and the conversion again.
+    pub cache_dir: Option<PathBuf>,
+    /// How many threads may run bindgen at once, for the different
+    /// `include_cpp!`s in a file. Zero or one means one at a time.
+    pub bindgen_jobs: usize,
+    /// Don't pass on to the [`RebuildDependencyRecorder`] any headers
+    /// which are outside the include directories we were given, which
+    /// are assumed to be system headers.
+    pub skip_system_header_dependencies: bool,
+    /// Extra clang arguments for particular `include_cpp!`s, as pairs of
+    /// (mod name, arguments). These follow the arguments given for every
+    /// `include_cpp!`, so win where the two conflict.
+    pub block_clang_args: Vec<(String, Vec<String>)>,
+    /// Don't generate the deprecated stubs which otherwise stand in for
+    /// each function, method or type we couldn't generate, to say why
+    /// wherever they're used.
+    pub suppress_ignored_api_stubs: bool,
+    /// Fail the whole conversion if autocxx fails unexpectedly while
+    /// processing any one item, rather than ignoring that item.
+    pub strict: bool,
+    /// Decides whether to generate each API, and adjusts those which are
+    /// generated. Bindings aren't cached while this is set, since we can't
+    /// tell whether it would decide differently next time.
+    pub api_filter: Option<ApiFilter<'a>>,
+    #[doc(hidden)]
+    /// Counts the number of times the conversion is run. Only used by
+    /// the test suite to check the cache is effective.
+    pub conversion_counter: Option<Rc<Cell<usize>>>,
 }
 
 const AUTOCXX_CLANG_ARGS: &[&str; 4] = &["-x", "c++", "-std=c++14", "-DBINDGEN"];
@@ -269,7 +356,15 @@ pub trait RebuildDependencyRecorder: std::fmt::Debug {
 pub struct IncludeCppEngine {
     config: IncludeCppConfig,
     state: State,
+    /// Types whose bindings come from other `include_cpp!`s, as named in
+    /// `import_from!`.
+    imported_types: Vec<ImportedType>,
     source_code: Option<Rc<String>>, // so we can create diagnostics
+    /// Any `#[cfg]` attributes on the `include_cpp!`, which we apply to
+    /// the generated mod too.
+    cfg_attrs: Vec<syn::Attribute>,
+    /// The results of running bindgen, if that was done ahead of time.
+    prefetched_bindings: Option<std::result::Result<BindgenOutput, BindgenError>>,
 }
 
 impl Parse for IncludeCppEngine {
@@ -283,7 +378,10 @@ impl Parse for IncludeCppEngine {
         Ok(Self {
             config,
             state,
+            imported_types: Vec::new(),
             source_code: None,
+            cfg_attrs: Vec::new(),
+            prefetched_bindings: None,
         })
     }
 }
@@ -303,7 +401,10 @@ impl IncludeCppEngine {
         Self {
             config: IncludeCppConfig::default(),
             state: State::NotGenerated,
+            imported_types: Vec::new(),
             source_code: None,
+            cfg_attrs: Vec::new(),
+            prefetched_bindings: None,
         }
     }
 
@@ -315,67 +416,89 @@ impl IncludeCppEngine {
         &mut self.config
     }
 
-    fn build_header(&self) -> String {
+    pub(crate) fn build_header(inclusions: &[String]) -> String {
         join(
-            self.config
-                .inclusions
+            inclusions
                 .iter()
                 .map(|path| format!("#include \"{path}\"\n")),
             "",
         )
     }
 
-    fn make_bindgen_builder(
+    /// Everything bindgen needs to know about this `include_cpp!`.
+    fn bindgen_inputs(&self, inc_dirs: &[PathBuf], extra_clang_args: &[&str]) -> BindgenInputs {
+        BindgenInputs {
+            inclusions: self.config.inclusions.clone(),
+            clang_args: make_clang_args(inc_dirs, extra_clang_args).collect(),
+            keep_inline_namespaces: self.config.keep_inline_namespaces(),
+            allowlist: self
+                .config
+                .bindgen_allowlist()
+                .map(|allowlist| allowlist.collect()),
+            opaque_types: self.config.bindgen_opaque_types().collect(),
+            concretes: self
+                .config
+                .get_concretes()
+                .map(|(cpp_definition, rust_id)| (cpp_definition, rust_id.to_string()))
+                .collect(),
+        }
+    }
+
+    /// If bindgen will need to be run for this `include_cpp!`, returns
+    /// what it needs, so that it can be run ahead of time alongside
+    /// bindgen for the other `include_cpp!`s in the file. Bindgen won't
+    /// be needed if the cache has usable bindings; and if any types are
+    /// imported from other `include_cpp!`s we can't tell which cache entry
+    /// to look at until those have been generated, so we mustn't guess.
+    pub(crate) fn bindgen_inputs_to_prefetch(
         &self,
         inc_dirs: &[PathBuf],
         extra_clang_args: &[&str],
-    ) -> bindgen::Builder {
-        let mut builder = bindgen::builder()
-            .clang_args(make_clang_args(inc_dirs, extra_clang_args))
-            .derive_copy(false)
-            .derive_debug(false)
-            .default_enum_style(bindgen::EnumVariation::Rust {
-                non_exhaustive: false,
-            })
-            .formatter(if log::log_enabled!(log::Level::Info) {
-                bindgen::Formatter::Rustfmt
-            } else {
-                bindgen::Formatter::None
-            })
-            .size_t_is_usize(true)
-            .enable_cxx_namespaces()
-            .generate_inline_functions(true)
-            .respect_cxx_access_specs(true)
-            .use_specific_virtual_function_receiver(true)
-            .cpp_semantic_attributes(true)
-            .represent_cxx_operators(true)
-            .use_distinct_char16_t(true)
-            .layout_tests(false); // TODO revisit later
-        for item in known_types().get_initial_blocklist() {
-            builder = builder.blocklist_item(item);
+        codegen_options: &CodegenOptions,
+    ) -> Option<BindgenInputs> {
+        if !matches!(self.state, State::NotGenerated) {
+            return None;
         }
-
-        // 3. Passes allowlist and other options to the bindgen::Builder equivalent
-        //    to --output-style=cxx --allowlist=<as passed in>
-        if let Some(allowlist) = self.config.bindgen_allowlist() {
-            for a in allowlist {
-                // TODO - allowlist type/functions/separately
-                builder = builder
-                    .allowlist_type(&a)
-                    .allowlist_function(&a)
-                    .allowlist_var(&a);
-            }
+        if codegen_options.cache_dir.is_some()
+            && (!self.config.get_imports().is_empty()
+                || self
+                    .generation_cache(inc_dirs, extra_clang_args, codegen_options)
+                    .is_some_and(|cache| cache.is_fresh()))
+        {
+            return None;
         }
+        Some(self.bindgen_inputs(inc_dirs, extra_clang_args))
+    }
 
-        log::info!(
-            "Bindgen flags would be: {}",
-            builder
-                .command_line_flags()
-                .into_iter()
-                .map(|f| format!("\"{f}\""))
-                .join(" ")
-        );
-        builder
+    /// Supplies the results of running bindgen ahead of time, which
+    /// `generate` will then use instead of running it again.
+    pub(crate) fn set_prefetched_bindings(
+        &mut self,
+        output: std::result::Result<BindgenOutput, BindgenError>,
+    ) {
+        self.prefetched_bindings = Some(output);
+    }
+
+    fn generation_cache(
+        &self,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+        codegen_options: &CodegenOptions,
+    ) -> Option<GenerationCache> {
+        if codegen_options.api_filter.is_some() {
+            return None;
+        }
+        codegen_options.cache_dir.as_ref().map(|cache_dir| {
+            GenerationCache::new(
+                cache_dir,
+                &self.config,
+                &self.imported_types,
+                &Self::build_header(&self.config.inclusions),
+                inc_dirs,
+                extra_clang_args,
+                codegen_options,
+            )
+        })
     }
 
     pub fn get_rs_filename(&self) -> String {
@@ -388,23 +511,123 @@ impl IncludeCppEngine {
             config: &self.config,
             rs: match &self.state {
                 State::NotGenerated => panic!("Generate first"),
-                State::Generated(gen_results) => gen_results.item_mod.to_token_stream(),
+                State::Generated(gen_results) => {
+                    let cfg_attrs = &self.cfg_attrs;
+                    let item_mod = &gen_results.item_mod;
+                    quote::quote! {
+                        #(#cfg_attrs)*
+                        #item_mod
+                    }
+                }
                 State::ParseOnly => TokenStream2::new(),
             },
         }
     }
 
+    /// Headers which clang couldn't parse, and which were therefore left
+    /// out so that bindings could be generated for the others. Call
+    /// `generate` first.
+    pub fn dropped_headers(&self) -> &[DroppedHeader] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.dropped_headers,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// APIs which were explicitly requested, or which belong to types
+    /// which were, but for which bindings couldn't be generated. Call
+    /// `generate` first.
+    pub fn ignored_apis(&self) -> &[IgnoredApi] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.ignored_apis,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// The types for which bindings were generated, so that other
+    /// `include_cpp!`s can `import_from!` this one. Call `generate` first.
+    pub(crate) fn generated_types(&self) -> &[GeneratedType] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.generated_types,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// Every API which was discovered in the C++, for the API report.
+    /// Call `generate` first.
+    pub(crate) fn reported_apis(&self) -> &[ReportedApi] {
+        match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => &gen_results.reported_apis,
+            State::ParseOnly => &[],
+        }
+    }
+
+    /// A summary of what became of each API discovered in the C++, and of
+    /// what was generated. Call `generate` first.
+    pub fn conversion_summary(&self) -> ConversionSummary {
+        let (cpp_headers, cxx_bridge_items) = match &self.state {
+            State::NotGenerated => panic!("Generate first"),
+            State::Generated(gen_results) => (
+                std::iter::once(gen_results.cxxgen_header_name.clone())
+                    .chain(gen_results.cpp.iter().map(|cpp| cpp.header_name.clone()))
+                    .collect(),
+                cxx_bridge_items(&gen_results.item_mod),
+            ),
+            State::ParseOnly => (Vec::new(), Vec::new()),
+        };
+        ConversionSummary {
+            mod_name: self.get_mod_name(),
+            apis: self.reported_apis().to_vec(),
+            cpp_headers,
+            cxx_bridge_items,
+        }
+    }
+
+    /// Finds the types named by any `import_from!` directives amongst
+    /// those generated by the other `include_cpp!`s in this file, keyed
+    /// by mod name. Must be called before `generate`. If an `import_from!`
+    /// names a mod which isn't there, returns its path.
+    pub(crate) fn import_types(
+        &mut self,
+        generated_types: &HashMap<String, Vec<GeneratedType>>,
+    ) -> std::result::Result<(), String> {
+        for path in self.config.get_imports() {
+            let generated = path
+                .segments
+                .last()
+                .and_then(|segment| generated_types.get(&segment.ident.to_string()));
+            match generated {
+                Some(generated) => self.imported_types.extend(
+                    generated
+                        .iter()
+                        .map(|generated_type| generated_type.import_from(path)),
+                ),
+                None => return Err(path.to_token_stream().to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Warnings to emit about [`Self::ignored_apis`], summarized if there
+    /// are very many.
+    pub fn ignored_api_warnings(&self) -> Vec<String> {
+        ignored_api_warnings(self.ignored_apis())
+    }
+
     /// Returns the name of the mod which this `include_cpp!` will generate.
     /// Can and should be used to ensure multiple mods in a file don't conflict.
     pub fn get_mod_name(&self) -> String {
         self.config.get_mod_name().to_string()
     }
 
-    fn parse_bindings(&self, bindings: bindgen::Bindings) -> Result<ItemMod> {
-        // This bindings object is actually a TokenStream internally and we're wasting
-        // effort converting to and from string. We could enhance the bindgen API
-        // in future.
-        let bindings = bindings.to_string();
+    fn parse_bindings(&self, bindings: &str) -> Result<ItemMod> {
+        // Bindgen's bindings object is actually a TokenStream internally and
+        // we're wasting effort converting to and from string. We could enhance
+        // the bindgen API in future.
         // Manually add the mod ffi {} so that we can ask syn to parse
         // into a single construct.
         let bindings = format!("mod bindgen {{ {bindings} }}");
@@ -443,18 +666,95 @@ impl IncludeCppEngine {
         }
 
         let mod_name = self.config.get_mod_name();
-        let mut builder = self.make_bindgen_builder(&inc_dirs, extra_clang_args);
-        if let Some(dep_recorder) = dep_recorder {
-            builder = builder.parse_callbacks(Box::new(AutocxxParseCallbacks(dep_recorder)));
-        }
-        let header_contents = self.build_header();
+        let header_contents = Self::build_header(&self.config.inclusions);
         self.dump_header_if_so_configured(&header_contents, &inc_dirs, extra_clang_args);
-        let header_and_prelude = format!("{}\n\n{}", known_types().get_prelude(), header_contents);
-        log::info!("Header and prelude for bindgen:\n{}", header_and_prelude);
-        builder = builder.header_contents("example.hpp", &header_and_prelude);
 
-        let bindings = builder.generate().map_err(Error::Bindgen)?;
-        let bindings = self.parse_bindings(bindings)?;
+        let dep_recorder: Option<Box<dyn RebuildDependencyRecorder>> = match dep_recorder {
+            Some(dep_recorder) if codegen_options.skip_system_header_dependencies => {
+                Some(Box::new(SystemHeaderFilter::new(
+                    dep_recorder,
+                    &inc_dirs,
+                    extra_clang_args,
+                )))
+            }
+            dep_recorder => dep_recorder,
+        };
+        let cache = self.generation_cache(&inc_dirs, extra_clang_args, codegen_options);
+        let mut fallback_header_names = None;
+        if let Some(cached) = cache.as_ref().and_then(|cache| cache.load()) {
+            // Name the headers just as the conversion would have done, since
+            // the namers may depend upon the order in which they're called.
+            let cpp_codegen_options = &codegen_options.cpp_codegen_options;
+            let cxxgen_header_name = cpp_codegen_options.cxxgen_header_namer.name_header();
+            let autocxxgen_header_name = cached.cpp.as_ref().map(|_| {
+                cpp_codegen_options
+                    .autocxxgen_header_namer
+                    .name_header(mod_name.to_string())
+            });
+            if cxxgen_header_name == cached.cxxgen_header_name
+                && autocxxgen_header_name == cached.cpp.as_ref().map(|cpp| cpp.header_name.clone())
+            {
+                info!("Using cached bindings for {}", mod_name);
+                if let Some(dep_recorder) = &dep_recorder {
+                    for dependency in &cached.dependencies {
+                        dep_recorder.record_header_file_dependency(dependency);
+                    }
+                }
+                self.state = State::Generated(Box::new(GenerationResults {
+                    item_mod: cached.item_mod,
+                    cpp: cached.cpp,
+                    dropped_headers: Vec::new(),
+                    ignored_apis: cached.ignored_apis,
+                    generated_types: cached.generated_types,
+                    reported_apis: cached.reported_apis,
+                    inc_dirs,
+                    cxxgen_header_name: cached.cxxgen_header_name,
+                    cfg_features: gating_features(codegen_options),
+                }));
+                return Ok(());
+            }
+            fallback_header_names = Some((cxxgen_header_name, autocxxgen_header_name));
+        }
+        let renamed_codegen_options;
+        let codegen_options = match fallback_header_names {
+            Some((cxxgen_header_name, autocxxgen_header_name)) => {
+                renamed_codegen_options = cache::with_header_names(
+                    codegen_options,
+                    cxxgen_header_name,
+                    autocxxgen_header_name,
+                );
+                &renamed_codegen_options
+            }
+            None => codegen_options,
+        };
+
+        let BindgenOutput {
+            bindings,
+            inclusions,
+            dropped_headers,
+            dependencies,
+        } = match self.prefetched_bindings.take() {
+            Some(output) => output,
+            None => self.bindgen_inputs(&inc_dirs, extra_clang_args).run(),
+        }
+        .map_err(Error::Bindgen)?;
+        if let Some(dep_recorder) = &dep_recorder {
+            for dependency in &dependencies {
+                dep_recorder.record_header_file_dependency(dependency);
+            }
+        }
+        let source_locator = SourceLocator::new(&dependencies, &inc_dirs, extra_clang_args);
+        let parsed_bindings = self.parse_bindings(&bindings)?;
+        self.config
+            .resolve_nested_type_names(&find_nested_type_names(&parsed_bindings));
+        let inclusions: Vec<_> = inclusions
+            .iter()
+            .map(|inclusion| {
+                codegen_options
+                    .cpp_codegen_options
+                    .original_header_inclusion(inclusion)
+            })
+            .collect();
 
         // Source code contents just used for diagnostics - if we don't have it,
         // use a blank string and miette will not attempt to annotate it nicely.
@@ -464,15 +764,26 @@ impl IncludeCppEngine {
             .cloned()
             .unwrap_or_else(|| Rc::new("".to_string()));
 
-        let converter = BridgeConverter::new(&self.config.inclusions, &self.config);
+        let converter = BridgeConverter::new(&inclusions, &self.config, &self.imported_types);
+        if let Some(conversion_counter) = &codegen_options.conversion_counter {
+            conversion_counter.set(conversion_counter.get() + 1);
+        }
 
+        // Only if an item panics do we need the bindings again, so only
+        // then do we parse them again.
+        let mut parsed_bindings = Some(parsed_bindings);
         let conversion = converter
             .convert(
-                bindings,
+                || {
+                    parsed_bindings.take().unwrap_or_else(|| {
+                        self.parse_bindings(&bindings)
+                            .expect("bindings parsed before")
+                    })
+                },
                 self.config.unsafe_policy.clone(),
-                header_contents,
                 codegen_options,
                 &source_file_contents,
+                &source_locator,
             )
             .map_err(Error::Conversion)?;
         let mut items = conversion.rs;
@@ -485,15 +796,37 @@ impl IncludeCppEngine {
             }
         };
         new_bindings.content.as_mut().unwrap().1.append(&mut items);
+        for dropped in &dropped_headers {
+            let doc = format!(" {dropped}");
+            new_bindings.attrs.push(parse_quote! { #[doc = #doc] });
+        }
         info!(
             "New bindings:\n{}",
             rust_pretty_printer::pretty_print(&new_bindings)
         );
+        // If we had to drop headers, don't cache the bindings, so that
+        // we report the problem again next time.
+        if let Some(cache) = cache.as_ref().filter(|_| dropped_headers.is_empty()) {
+            cache.store(
+                &new_bindings,
+                conversion.cpp.as_ref(),
+                &conversion.cxxgen_header_name,
+                &conversion.ignored_apis,
+                &conversion.generated_types,
+                &conversion.reported_apis,
+                &dependencies,
+            );
+        }
         self.state = State::Generated(Box::new(GenerationResults {
             item_mod: new_bindings,
             cpp: conversion.cpp,
+            dropped_headers,
+            ignored_apis: conversion.ignored_apis,
+            generated_types: conversion.generated_types,
+            reported_apis: conversion.reported_apis,
             inc_dirs,
             cxxgen_header_name: conversion.cxxgen_header_name,
+            cfg_features: gating_features(codegen_options),
         }));
         Ok(())
     }
@@ -616,6 +949,7 @@ pub fn do_cxx_cpp_generation(
             cxx_generated.implementation,
             cpp_codegen_options.suppress_system_headers,
         )),
+        extra_implementations: Vec::new(),
     })
 }
 
@@ -637,6 +971,81 @@ fn strip_system_headers(input: Vec<u8>, suppress_system_headers: bool) -> Vec<u8
     }
 }
 
+fn gating_features(codegen_options: &CodegenOptions) -> Vec<String> {
+    codegen_options
+        .cfg_features
+        .iter()
+        .map(|(_, feature)| feature.clone())
+        .collect()
+}
+
+/// Remove any `#[cfg]` attributes we added to gate bindings behind Cargo
+/// features. cxx_gen can't evaluate them, and in any case we always
+/// generate all the C++ side of the bindings. Any other `#[cfg]`s are
+/// left for cxx_gen.
+fn strip_cfg_attrs(ts: TokenStream2, features: &[String]) -> TokenStream2 {
+    if features.is_empty() {
+        return ts;
+    }
+    let mut output = Vec::new();
+    let mut tokens = ts.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(ref p) if p.as_char() == '#' => {
+                if let Some(TokenTree::Group(attr)) = tokens.peek() {
+                    if attr.delimiter() == Delimiter::Bracket
+                        && is_feature_gate(attr.stream(), features)
+                    {
+                        tokens.next();
+                        continue;
+                    }
+                }
+                output.push(token);
+            }
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), strip_cfg_attrs(group.stream(), features));
+                new_group.set_span(group.span());
+                output.push(TokenTree::Group(new_group));
+            }
+            _ => output.push(token),
+        }
+    }
+    output.into_iter().collect()
+}
+
+/// Whether an attribute's contents are `cfg(feature = "a")` or
+/// `cfg(any(feature = "a", feature = "b"))`, naming only the given
+/// features, as we generate to gate bindings.
+fn is_feature_gate(attr: TokenStream2, features: &[String]) -> bool {
+    let is_gating_feature = |meta: &syn::Meta| match meta {
+        syn::Meta::NameValue(nv) if nv.path.is_ident("feature") => matches!(
+            &nv.value,
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(feature), .. })
+                if features.contains(&feature.value())
+        ),
+        _ => false,
+    };
+    let meta: syn::Meta = match syn::parse2(attr) {
+        Ok(meta) => meta,
+        Err(_) => return false,
+    };
+    let list = match meta {
+        syn::Meta::List(list) if list.path.is_ident("cfg") => list,
+        _ => return false,
+    };
+    match list.parse_args::<syn::Meta>() {
+        Ok(syn::Meta::List(any)) if any.path.is_ident("any") => any
+            .parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            )
+            .map(|metas| metas.iter().all(is_gating_feature))
+            .unwrap_or(false),
+        Ok(meta) => is_gating_feature(&meta),
+        Err(_) => false,
+    }
+}
+
 impl CppBuildable for IncludeCppEngine {
     /// Generate C++-side bindings for these APIs. Call `generate` first.
     fn generate_h_and_cxx(
@@ -648,14 +1057,28 @@ impl CppBuildable for IncludeCppEngine {
             State::ParseOnly => panic!("Cannot generate C++ in parse-only mode"),
             State::NotGenerated => panic!("Call generate() first"),
             State::Generated(gen_results) => {
-                let rs = gen_results.item_mod.to_token_stream();
+                let rs = strip_cfg_attrs(
+                    gen_results.item_mod.to_token_stream(),
+                    &gen_results.cfg_features,
+                );
                 files.push(do_cxx_cpp_generation(
                     rs,
                     cpp_codegen_options,
                     gen_results.cxxgen_header_name.clone(),
                 )?);
                 if let Some(cpp_file_pair) = &gen_results.cpp {
-                    files.push(cpp_file_pair.clone());
+                    files.push(CppFilePair {
+                        extra_implementations: Vec::new(),
+                        ..cpp_file_pair.clone()
+                    });
+                    files.extend(cpp_file_pair.extra_implementations.iter().map(
+                        |implementation| CppFilePair {
+                            header: cpp_file_pair.header.clone(),
+                            implementation: Some(implementation.clone()),
+                            header_name: cpp_file_pair.header_name.clone(),
+                            extra_implementations: Vec::new(),
+                        },
+                    ));
                 }
             }
         };
@@ -765,6 +1188,79 @@ impl CxxgenHeaderNamer<'_> {
     }
 }
 
+/// A version of the C++ standard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CppStandard {
+    Cpp14,
+    Cpp17,
+    Cpp20,
+}
+
+impl std::fmt::Display for CppStandard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Cpp14 => "C++14",
+            Self::Cpp17 => "C++17",
+            Self::Cpp20 => "C++20",
+        })
+    }
+}
+
+impl std::str::FromStr for CppStandard {
+    type Err = String;
+
+    /// Parses `c++14`, `c++17` or `c++20`, as given to `-std`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "c++14" => Ok(Self::Cpp14),
+            "c++17" => Ok(Self::Cpp17),
+            "c++20" => Ok(Self::Cpp20),
+            _ => Err(format!(
+                "{s} isn't a C++ standard which autocxx knows about: use c++14, c++17 or c++20"
+            )),
+        }
+    }
+}
+
+/// How the generated C++ should `#include` one of the headers listed
+/// in an `include_cpp!`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HeaderInclusion {
+    /// `#include "path"`
+    Quoted(String),
+    /// `#include <path>`
+    Angled(String),
+}
+
+impl HeaderInclusion {
+    fn include_stmt(&self) -> String {
+        match self {
+            Self::Quoted(path) => format!("#include \"{path}\""),
+            Self::Angled(path) => format!("#include <{path}>"),
+        }
+    }
+}
+
+/// Function to decide how the generated C++ should include a given one of
+/// the original headers, if not in the default way. It's passed the header
+/// as written in the `include_cpp!`.
+/// Newtype wrapper so we can give it a [`Default`].
+pub struct HeaderInclusionMapper<'a>(pub Box<HeaderInclusionMapperFn<'a>>);
+
+type HeaderInclusionMapperFn<'a> = dyn 'a + Fn(&str) -> Option<HeaderInclusion>;
+
+impl Default for HeaderInclusionMapper<'static> {
+    fn default() -> Self {
+        Self(Box::new(|_| None))
+    }
+}
+
+impl HeaderInclusionMapper<'_> {
+    fn map_header(&self, header: &str) -> Option<HeaderInclusion> {
+        self.0(header)
+    }
+}
+
 /// Options for C++ codegen
 #[derive(Default)]
 pub struct CppCodegenOptions<'a> {
@@ -785,9 +1281,61 @@ pub struct CppCodegenOptions<'a> {
     pub autocxxgen_header_namer: AutocxxgenHeaderNamer<'a>,
     /// A function to generate the name of the cxxgen.h header that should be output.
     pub cxxgen_header_namer: CxxgenHeaderNamer<'a>,
-    /// An annotation optionally to include on each C++ function.
-    /// For example to export the symbol from a library.
+    /// An annotation optionally to include on each C++ function, whether
+    /// generated by cxx or by autocxx, and on the classes generated for
+    /// `subclass!`. For example to export the symbol from a library, using
+    /// `__attribute__((visibility("default")))` or a macro defined in your
+    /// headers.
     pub cxx_impl_annotations: Option<String>,
+    /// The number of .cc files across which to split the C++ which we
+    /// (autocxx, rather than cxx) generate, so that they can be compiled
+    /// in parallel. Zero or one means a single file. If there's more than
+    /// one, our wrapper functions are no longer `inline`.
+    pub cpp_shards: usize,
+    /// Whether to precede each snippet of C++ which we generate with a
+    /// comment naming the C++ API for which it was generated, so that
+    /// compiler errors in it can be traced back to the original header.
+    pub annotate_cpp: bool,
+    /// Optionally, a prefix to go at the front of the path to each of the
+    /// headers listed in the `include_cpp!` within `#include` statements
+    /// in the generated C++. Must end in `/`.
+    pub path_to_original_headers: Option<String>,
+    /// Whether to include the headers listed in the `include_cpp!` using
+    /// `#include <...>` rather than `#include "..."` in the generated C++.
+    pub angle_bracket_includes: bool,
+    /// A function to override how the generated C++ includes particular
+    /// headers listed in the `include_cpp!`. Where it returns `None`,
+    /// [`CppCodegenOptions::path_to_original_headers`] and
+    /// [`CppCodegenOptions::angle_bracket_includes`] apply.
+    pub header_inclusion_mapper: HeaderInclusionMapper<'a>,
+    /// Optionally, a prefix to go at the front of the autocxxgen.h file
+    /// within `#include` statements. Must end in `/`.
+    pub path_to_autocxxgen_h: Option<String>,
+    /// The latest C++ standard which the generated C++ may rely on. Where
+    /// a function or method needs something newer (for instance, it takes
+    /// a `std::string_view`), no bindings are generated for it. By default,
+    /// anything which autocxx supports may be used.
+    pub max_cpp_standard: Option<CppStandard>,
+}
+
+impl CppCodegenOptions<'_> {
+    /// How the generated C++ should include this header from the
+    /// `include_cpp!`.
+    fn original_header_inclusion(&self, header: &str) -> HeaderInclusion {
+        self.header_inclusion_mapper
+            .map_header(header)
+            .unwrap_or_else(|| {
+                let path = format!(
+                    "{}{header}",
+                    self.path_to_original_headers.as_deref().unwrap_or_default()
+                );
+                if self.angle_bracket_includes {
+                    HeaderInclusion::Angled(path)
+                } else {
+                    HeaderInclusion::Quoted(path)
+                }
+            })
+    }
 }
 
 fn proc_macro_span_to_miette_span(span: &proc_macro2::Span) -> SourceSpan {
@@ -0,0 +1,889 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen annotates its output with much of what we need to know about
+//! each C++ function (`#[cpp_semantics(...)]`), but not everything: it
+//! doesn't tell us which parameters have default values, for instance,
+//! which fields are protected rather than private, or which variables
+//! have internal linkage. We add what libclang
+//! tells us about those things to bindgen's output as more annotations of
+//! the same form, so that the rest of autocxx needn't care where they came
+//! from. Functions and variables are matched up with bindgen's by their
+//! mangled names, and types by their paths within bindgen's modules.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+use std::{ffi::CStr, os::raw::c_uint, ptr};
+
+use clang_sys::*;
+use quote::ToTokens;
+use syn::{
+    parse_quote, Attribute, FnArg, ForeignItem, ForeignItemFn, ForeignItemStatic, Ident, Item, Lit,
+    Meta, Pat,
+};
+
+use super::{spelling, to_string, type_spelling, visit_children, TranslationUnit};
+
+/// What libclang tells us about a function which bindgen doesn't.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct FunctionFacts {
+    /// For each C++ parameter, its default value as C++ source, if any.
+    defaults: Vec<Option<String>>,
+    /// For each C++ parameter, whether it's a `std::span` of `const`
+    /// elements (which bindgen's type for it doesn't say).
+    const_spans: Vec<bool>,
+    /// For each C++ parameter, whether it's a pointer to a member
+    /// function, which bindgen represents just as it would a pointer to
+    /// a plain function.
+    member_function_pointers: Vec<bool>,
+    /// Whether the function can't throw.
+    noexcept: bool,
+    /// Whether this is a constructor declared `explicit`.
+    explicit: bool,
+    /// Whether this is a method which can only be called on an rvalue,
+    /// being declared `&&`.
+    rvalue_ref_qualified: bool,
+    /// If the function is `[[deprecated]]`, the reason, if one's given.
+    deprecated: Option<Option<String>>,
+    /// Whether the function is `[[nodiscard]]`.
+    nodiscard: bool,
+    /// Whether this is a virtual method declared `final`.
+    is_final: bool,
+}
+
+impl FunctionFacts {
+    fn is_interesting(&self) -> bool {
+        self.defaults.iter().any(Option::is_some)
+            || self.const_spans.iter().any(|is_const| *is_const)
+            || self.member_function_pointers.iter().any(|is_pmf| *is_pmf)
+            || self.noexcept
+            || self.explicit
+            || self.rvalue_ref_qualified
+            || self.deprecated.is_some()
+            || self.nodiscard
+            || self.is_final
+    }
+
+    /// Adds what another declaration of the same function tells us. Only
+    /// one need mention each default value, and only the declaration
+    /// within its class says that a constructor is `explicit`.
+    fn merge(&mut self, other: &Self) {
+        if self.defaults.len() < other.defaults.len() {
+            self.defaults.resize(other.defaults.len(), None);
+        }
+        for (default, other) in self.defaults.iter_mut().zip(&other.defaults) {
+            if default.is_none() {
+                default.clone_from(other);
+            }
+        }
+        if self.const_spans.len() < other.const_spans.len() {
+            self.const_spans.resize(other.const_spans.len(), false);
+        }
+        for (is_const, other) in self.const_spans.iter_mut().zip(&other.const_spans) {
+            *is_const |= other;
+        }
+        if self.member_function_pointers.len() < other.member_function_pointers.len() {
+            self.member_function_pointers
+                .resize(other.member_function_pointers.len(), false);
+        }
+        for (is_pmf, other) in self
+            .member_function_pointers
+            .iter_mut()
+            .zip(&other.member_function_pointers)
+        {
+            *is_pmf |= other;
+        }
+        self.noexcept |= other.noexcept;
+        self.explicit |= other.explicit;
+        self.rvalue_ref_qualified |= other.rvalue_ref_qualified;
+        if self.deprecated.is_none() {
+            self.deprecated.clone_from(&other.deprecated);
+        }
+        self.nodiscard |= other.nodiscard;
+        self.is_final |= other.is_final;
+    }
+
+    /// The annotations to add to bindgen's declaration of the function,
+    /// whose parameters are `params`. bindgen adds a `this` parameter to
+    /// methods, so there may be more of those than there are C++
+    /// parameters.
+    fn annotations(&self, params: &[Option<Ident>]) -> Vec<Attribute> {
+        let mut annotations = Vec::new();
+        if let Some(offset) = params.len().checked_sub(self.defaults.len()) {
+            for (default, param) in self.defaults.iter().zip(&params[offset..]) {
+                if let (Some(default), Some(param)) = (default, param) {
+                    annotations.push(parse_quote! {
+                        #[cpp_semantics(arg_default(#param, #default))]
+                    });
+                }
+            }
+        }
+        if let Some(offset) = params.len().checked_sub(self.const_spans.len()) {
+            for (is_const, param) in self.const_spans.iter().zip(&params[offset..]) {
+                if let (true, Some(param)) = (is_const, param) {
+                    annotations.push(parse_quote! {
+                        #[cpp_semantics(arg_type_const_span(#param))]
+                    });
+                }
+            }
+        }
+        if let Some(offset) = params
+            .len()
+            .checked_sub(self.member_function_pointers.len())
+        {
+            for (is_pmf, param) in self.member_function_pointers.iter().zip(&params[offset..]) {
+                if let (true, Some(param)) = (is_pmf, param) {
+                    annotations.push(parse_quote! {
+                        #[cpp_semantics(arg_type_member_function_pointer(#param))]
+                    });
+                }
+            }
+        }
+        if self.noexcept {
+            annotations.push(parse_quote! { #[cpp_semantics(noexcept)] });
+        }
+        if self.explicit {
+            annotations.push(parse_quote! { #[cpp_semantics(explicit)] });
+        }
+        if self.rvalue_ref_qualified {
+            annotations.push(parse_quote! { #[cpp_semantics(rvalue_ref_qualified)] });
+        }
+        if let Some(message) = &self.deprecated {
+            annotations.push(deprecation_annotation(message));
+        }
+        if self.nodiscard {
+            annotations.push(parse_quote! { #[cpp_semantics(nodiscard)] });
+        }
+        if self.is_final {
+            annotations.push(parse_quote! { #[cpp_semantics(final)] });
+        }
+        annotations
+    }
+}
+
+/// What libclang tells us about a protected field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldFacts {
+    cpp_name: String,
+    is_const: bool,
+}
+
+impl FieldFacts {
+    fn annotations(&self) -> Vec<Attribute> {
+        let cpp_name = &self.cpp_name;
+        let mut annotations = vec![
+            parse_quote! { #[cpp_semantics(visibility_protected)] },
+            parse_quote! { #[cpp_semantics(original_name(#cpp_name))] },
+        ];
+        if self.is_const {
+            annotations.push(parse_quote! { #[cpp_semantics(const_field)] });
+        }
+        annotations
+    }
+}
+
+/// What libclang told us about the functions and types in a header.
+#[derive(Debug, Default)]
+pub(super) struct Facts {
+    /// Keyed by every mangled name of the function.
+    functions: HashMap<String, FunctionFacts>,
+    /// The reason, if any, for which each `[[deprecated]]` type is
+    /// deprecated, keyed by the path by which bindgen's output names it.
+    deprecated_types: HashMap<String, Option<String>>,
+    /// The paths of the classes declared `final`.
+    final_types: HashSet<String>,
+    /// The protected fields of each type, keyed by the path by which
+    /// bindgen's output names the type (such as `ns::Outer_Inner`) and
+    /// then by the name bindgen gives the field.
+    protected_fields: HashMap<String, HashMap<String, FieldFacts>>,
+    /// The mangled names of mutable variables with internal linkage, of
+    /// which each C++ file has its own copy.
+    internal_variables: HashSet<String>,
+}
+
+/// Where we are within the header, as bindgen would name it: the
+/// namespaces, and then any classes (whose names bindgen joins with `_`).
+#[derive(Clone, Default)]
+struct Scope {
+    namespaces: Vec<String>,
+    classes: Vec<String>,
+}
+
+impl Scope {
+    fn path(&self) -> String {
+        self.namespaces
+            .iter()
+            .cloned()
+            .chain((!self.classes.is_empty()).then(|| self.classes.join("_")))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+}
+
+impl Facts {
+    pub(super) fn gather(tu: &TranslationUnit) -> Self {
+        let mut facts = Self::default();
+        facts.gather_from(tu, tu.cursor(), Some(&Scope::default()));
+        facts
+    }
+
+    /// Adds to `bindings` (bindgen's output for the header) the
+    /// annotations which bindgen doesn't make. If libclang can't tell us
+    /// anything more, returns `bindings` unchanged.
+    pub(super) fn annotate(&self, bindings: String) -> String {
+        if self.is_empty() {
+            return bindings;
+        }
+        let mut file: syn::File = match syn::parse_str(&bindings) {
+            Ok(file) => file,
+            // We'll report this properly when we parse the bindings ourselves.
+            Err(_) => return bindings,
+        };
+        // With namespaces enabled, bindgen puts everything in a mod called
+        // `root`.
+        for item in &mut file.items {
+            if let Item::Mod(itm) = item {
+                if let Some((_, items)) = &mut itm.content {
+                    self.annotate_items(items, &mut Vec::new());
+                }
+            }
+        }
+        file.into_token_stream().to_string()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+            && self.deprecated_types.is_empty()
+            && self.final_types.is_empty()
+            && self.protected_fields.is_empty()
+            && self.internal_variables.is_empty()
+    }
+
+    /// Looks for functions and types among the children of `cursor`,
+    /// which is within `scope` unless it's somewhere bindgen's names for
+    /// types wouldn't match ours, such as an anonymous namespace. We don't
+    /// look within templates, since bindgen doesn't generate their members.
+    fn gather_from(&mut self, tu: &TranslationUnit, cursor: CXCursor, scope: Option<&Scope>) {
+        visit_children(cursor, |child| {
+            #[allow(non_upper_case_globals)]
+            match unsafe { clang_getCursorKind(child) } {
+                CXCursor_LinkageSpec => self.gather_from(tu, child, scope),
+                CXCursor_Namespace => {
+                    let name = spelling(child);
+                    let scope = scope.filter(|_| !name.is_empty()).map(|scope| {
+                        let mut scope = scope.clone();
+                        scope.namespaces.push(name);
+                        scope
+                    });
+                    self.gather_from(tu, child, scope.as_ref())
+                }
+                CXCursor_StructDecl | CXCursor_ClassDecl | CXCursor_UnionDecl => {
+                    let name = spelling(child);
+                    let scope = scope.filter(|_| is_named(&name)).map(|scope| {
+                        let mut scope = scope.clone();
+                        scope.classes.push(name);
+                        scope
+                    });
+                    if let Some(scope) = &scope {
+                        self.gather_fields(child, scope);
+                        if let Some(message) = deprecation(child) {
+                            self.deprecated_types.insert(scope.path(), message);
+                        }
+                        if is_final_class(child) {
+                            self.final_types.insert(scope.path());
+                        }
+                    }
+                    self.gather_from(tu, child, scope.as_ref())
+                }
+                CXCursor_FunctionDecl
+                | CXCursor_CXXMethod
+                | CXCursor_Constructor
+                | CXCursor_ConversionFunction => {
+                    let facts = function_facts(tu, child);
+                    if facts.is_interesting() {
+                        for mangling in manglings(child) {
+                            self.functions.entry(mangling).or_default().merge(&facts);
+                        }
+                    }
+                }
+                CXCursor_VarDecl => unsafe {
+                    if clang_getCursorLinkage(child) == CXLinkage_Internal
+                        && clang_isConstQualifiedType(clang_getCursorType(child)) == 0
+                    {
+                        self.internal_variables.extend(manglings(child));
+                    }
+                },
+                _ => {}
+            }
+        });
+    }
+
+    /// Records the protected fields of the type at `cursor`.
+    fn gather_fields(&mut self, cursor: CXCursor, scope: &Scope) {
+        let mut fields = HashMap::new();
+        visit_children(cursor, |child| unsafe {
+            if clang_getCursorKind(child) == CXCursor_FieldDecl
+                && clang_getCXXAccessSpecifier(child) == CX_CXXProtected
+            {
+                let cpp_name = spelling(child);
+                let is_const = clang_isConstQualifiedType(clang_getCursorType(child)) != 0;
+                // bindgen appends `_` to fields named after Rust keywords.
+                let name = if syn::parse_str::<Ident>(&cpp_name).is_ok() {
+                    cpp_name.clone()
+                } else {
+                    format!("{cpp_name}_")
+                };
+                fields.insert(name, FieldFacts { cpp_name, is_const });
+            }
+        });
+        if !fields.is_empty() {
+            self.protected_fields.insert(scope.path(), fields);
+        }
+    }
+
+    /// Annotates bindgen's items within the mod at `path`.
+    fn annotate_items(&self, items: &mut [Item], path: &mut Vec<String>) {
+        for item in items {
+            match item {
+                Item::Mod(itm) => {
+                    if let Some((_, items)) = &mut itm.content {
+                        path.push(itm.ident.to_string());
+                        self.annotate_items(items, path);
+                        path.pop();
+                    }
+                }
+                Item::Struct(s) => {
+                    let type_path = path
+                        .iter()
+                        .cloned()
+                        .chain(std::iter::once(s.ident.to_string()))
+                        .collect::<Vec<_>>()
+                        .join("::");
+                    if let Some(message) = self.deprecated_types.get(&type_path) {
+                        s.attrs.push(deprecation_annotation(message));
+                    }
+                    if self.final_types.contains(&type_path) {
+                        s.attrs.push(parse_quote! { #[cpp_semantics(final)] });
+                    }
+                    if let Some(fields) = self.protected_fields.get(&type_path) {
+                        for field in s.fields.iter_mut() {
+                            if let Some(facts) = field
+                                .ident
+                                .as_ref()
+                                .and_then(|id| fields.get(&id.to_string()))
+                            {
+                                field.attrs.extend(facts.annotations());
+                            }
+                        }
+                    }
+                }
+                Item::ForeignMod(ifm) => {
+                    for item in &mut ifm.items {
+                        match item {
+                            ForeignItem::Fn(f) => self.annotate_function(f),
+                            ForeignItem::Static(s) => self.annotate_static(s),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn annotate_function(&self, f: &mut ForeignItemFn) {
+        let symbol = link_name(&f.attrs).unwrap_or_else(|| f.sig.ident.to_string());
+        if let Some(facts) = self.functions.get(&symbol) {
+            let params: Vec<_> = f
+                .sig
+                .inputs
+                .iter()
+                .map(|arg| match arg {
+                    FnArg::Typed(pt) => match pt.pat.as_ref() {
+                        Pat::Ident(pi) => Some(pi.ident.clone()),
+                        _ => None,
+                    },
+                    FnArg::Receiver(_) => None,
+                })
+                .collect();
+            f.attrs.extend(facts.annotations(&params));
+        }
+    }
+
+    fn annotate_static(&self, s: &mut ForeignItemStatic) {
+        let symbol = link_name(&s.attrs).unwrap_or_else(|| s.ident.to_string());
+        if self.internal_variables.contains(&symbol) {
+            s.attrs
+                .push(parse_quote! { #[cpp_semantics(internal_linkage)] });
+        }
+    }
+}
+
+/// Whether the class at `cursor` is declared `final`.
+fn is_final_class(cursor: CXCursor) -> bool {
+    let mut is_final = false;
+    visit_children(cursor, |child| {
+        is_final |= unsafe { clang_getCursorKind(child) } == CXCursor_CXXFinalAttr;
+    });
+    is_final
+}
+
+/// Whether a type has a name of its own, rather than being anonymous.
+/// libclang describes anonymous types as, for instance,
+/// `struct (unnamed at example.hpp:3:5)`.
+fn is_named(name: &str) -> bool {
+    !name.is_empty() && !name.contains(' ')
+}
+
+/// The symbol bindgen tells rustc to link against, without the leading
+/// `\u{1}` which tells rustc not to mangle it further.
+fn link_name(attrs: &[Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        Meta::NameValue(nv) if nv.path.is_ident("link_name") => match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(ls), ..
+            }) => Some(ls.value().trim_start_matches('\u{1}').to_string()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn function_facts(tu: &TranslationUnit, cursor: CXCursor) -> FunctionFacts {
+    let num_args = unsafe { clang_Cursor_getNumArguments(cursor) }.max(0) as c_uint;
+    let params: Vec<_> = (0..num_args)
+        .map(|idx| unsafe { clang_Cursor_getArgument(cursor, idx) })
+        .collect();
+    let defaults = params
+        .iter()
+        .map(|param| default_value(&tu.tokens(*param)))
+        .collect();
+    let const_spans = params
+        .iter()
+        .map(|param| is_const_span(unsafe { clang_getCursorType(*param) }))
+        .collect();
+    let member_function_pointers = params
+        .iter()
+        .map(|param| is_member_function_pointer(unsafe { clang_getCursorType(*param) }))
+        .collect();
+    // `noexcept(expression)` may turn out to be `noexcept(false)`, and
+    // libclang doesn't tell us which, so we only believe the plain forms.
+    #[allow(non_upper_case_globals)]
+    let noexcept = matches!(
+        unsafe { clang_getCursorExceptionSpecificationType(cursor) },
+        CXCursor_ExceptionSpecificationKind_DynamicNone
+            | CXCursor_ExceptionSpecificationKind_BasicNoexcept
+    );
+    // libclang can't tell us this directly until version 17, but the
+    // keyword can only come before the parameters.
+    let explicit = unsafe { clang_getCursorKind(cursor) } == CXCursor_Constructor
+        && tu
+            .tokens(cursor)
+            .iter()
+            .take_while(|(_, spelling)| spelling != "(")
+            .any(|(kind, spelling)| *kind == CXToken_Keyword && spelling == "explicit");
+    let rvalue_ref_qualified = unsafe {
+        clang_getCursorKind(cursor) == CXCursor_CXXMethod
+            && clang_Type_getCXXRefQualifier(clang_getCursorType(cursor)) == CXRefQualifier_RValue
+    };
+    let mut nodiscard = false;
+    let mut is_final = false;
+    visit_children(cursor, |child| {
+        let kind = unsafe { clang_getCursorKind(child) };
+        nodiscard |= kind == CXCursor_WarnUnusedResultAttr;
+        is_final |= kind == CXCursor_CXXFinalAttr;
+    });
+    FunctionFacts {
+        defaults,
+        const_spans,
+        member_function_pointers,
+        noexcept,
+        explicit,
+        rvalue_ref_qualified,
+        deprecated: deprecation(cursor),
+        nodiscard,
+        is_final,
+    }
+}
+
+/// If the entity at `cursor` is `[[deprecated]]`, the reason, if one's
+/// given.
+fn deprecation(cursor: CXCursor) -> Option<Option<String>> {
+    unsafe {
+        if clang_getCursorAvailability(cursor) != CXAvailability_Deprecated {
+            return None;
+        }
+        let mut message = CXString::default();
+        clang_getCursorPlatformAvailability(
+            cursor,
+            ptr::null_mut(),
+            &mut message,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+        );
+        let message = to_string(message);
+        Some((!message.is_empty()).then_some(message))
+    }
+}
+
+fn deprecation_annotation(message: &Option<String>) -> Attribute {
+    match message {
+        Some(message) => parse_quote! { #[cpp_semantics(deprecated(#message))] },
+        None => parse_quote! { #[cpp_semantics(deprecated)] },
+    }
+}
+
+/// Whether `ty` is a `std::span<const T>`, or a reference to one.
+fn is_const_span(ty: CXType) -> bool {
+    unsafe {
+        #[allow(non_upper_case_globals)]
+        let ty = match ty.kind {
+            CXType_LValueReference | CXType_RValueReference => clang_getPointeeType(ty),
+            _ => ty,
+        };
+        let ty = clang_getCanonicalType(ty);
+        // The canonical type names the namespace in which the standard
+        // library really defines it, such as `std::__1`.
+        spelling(clang_getTypeDeclaration(ty)) == "span"
+            && type_spelling(ty).starts_with("std::")
+            && clang_Type_getNumTemplateArguments(ty) > 0
+            && clang_isConstQualifiedType(clang_Type_getTemplateArgumentAsType(ty, 0)) != 0
+    }
+}
+
+/// Whether `ty` is a pointer to a member function, such as
+/// `void (Canvas::*)(int)`, perhaps behind a typedef.
+fn is_member_function_pointer(ty: CXType) -> bool {
+    unsafe {
+        let ty = clang_getCanonicalType(ty);
+        #[allow(non_upper_case_globals)]
+        let points_to_function = matches!(
+            clang_getPointeeType(ty).kind,
+            CXType_FunctionProto | CXType_FunctionNoProto
+        );
+        ty.kind == CXType_MemberPointer && points_to_function
+    }
+}
+
+/// Given the tokens of a parameter declaration, such as `int x = 3`,
+/// returns its default value, if it has one. In case libclang gives us a
+/// token beyond the end of the declaration, we stop at any `,` or
+/// unmatched closing bracket.
+fn default_value(tokens: &[(CXTokenKind, String)]) -> Option<String> {
+    let mut depth = 0;
+    let mut default: Option<Vec<&(CXTokenKind, String)>> = None;
+    for token in tokens {
+        match token.1.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" if depth == 0 => break,
+            ")" | "]" | "}" => depth -= 1,
+            "," if depth == 0 => break,
+            "=" if depth == 0 && default.is_none() => {
+                default = Some(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(default) = &mut default {
+            default.push(token);
+        }
+    }
+    let default = default.filter(|default| !default.is_empty())?;
+    let mut text = String::new();
+    let mut previous_was_word = false;
+    for (kind, spelling) in default {
+        let is_word = *kind != CXToken_Punctuation;
+        if is_word && previous_was_word {
+            text.push(' ');
+        }
+        text.push_str(spelling);
+        previous_was_word = is_word;
+    }
+    Some(text)
+}
+
+/// Every symbol name which clang might use for the function or variable:
+/// constructors and destructors have several.
+fn manglings(cursor: CXCursor) -> Vec<String> {
+    let mut results = Vec::new();
+    unsafe {
+        let set = clang_Cursor_getCXXManglings(cursor);
+        if !set.is_null() {
+            for idx in 0..(*set).Count as usize {
+                let s = clang_getCString(*(*set).Strings.add(idx));
+                if !s.is_null() {
+                    results.push(CStr::from_ptr(s).to_string_lossy().into_owned());
+                }
+            }
+            clang_disposeStringSet(set);
+        }
+        let mangling = to_string(clang_Cursor_getMangling(cursor));
+        if !mangling.is_empty() {
+            results.push(mangling);
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_value, Facts, FieldFacts, FunctionFacts};
+    use clang_sys::{CXToken_Identifier, CXToken_Keyword, CXToken_Literal, CXToken_Punctuation};
+    use quote::ToTokens;
+    use syn::{parse_quote, ItemMod};
+
+    #[test]
+    fn test_default_value() {
+        let tokens = |toks: &[(_, &str)]| -> Vec<_> {
+            toks.iter()
+                .map(|(kind, spelling)| (*kind, spelling.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            default_value(&tokens(&[
+                (CXToken_Keyword, "int"),
+                (CXToken_Identifier, "x"),
+            ])),
+            None
+        );
+        assert_eq!(
+            default_value(&tokens(&[
+                (CXToken_Identifier, "Color"),
+                (CXToken_Identifier, "c"),
+                (CXToken_Punctuation, "="),
+                (CXToken_Identifier, "Color"),
+                (CXToken_Punctuation, "::"),
+                (CXToken_Identifier, "Red"),
+                (CXToken_Punctuation, ")"),
+            ])),
+            Some("Color::Red".into())
+        );
+        assert_eq!(
+            default_value(&tokens(&[
+                (CXToken_Keyword, "unsigned"),
+                (CXToken_Keyword, "long"),
+                (CXToken_Identifier, "n"),
+                (CXToken_Punctuation, "="),
+                (CXToken_Identifier, "compute"),
+                (CXToken_Punctuation, "("),
+                (CXToken_Literal, "1"),
+                (CXToken_Punctuation, ","),
+                (CXToken_Keyword, "sizeof"),
+                (CXToken_Punctuation, "("),
+                (CXToken_Keyword, "int"),
+                (CXToken_Punctuation, ")"),
+                (CXToken_Punctuation, ")"),
+                (CXToken_Punctuation, ","),
+            ])),
+            Some("compute(1,sizeof(int))".into())
+        );
+    }
+
+    #[test]
+    fn test_merge_declarations() {
+        // A declaration with defaults, then a definition without them.
+        let mut facts = FunctionFacts::default();
+        facts.merge(&FunctionFacts {
+            defaults: vec![None, Some("0".into())],
+            const_spans: Vec::new(),
+            member_function_pointers: Vec::new(),
+            noexcept: true,
+            explicit: false,
+            rvalue_ref_qualified: false,
+            deprecated: None,
+            nodiscard: false,
+            is_final: false,
+        });
+        facts.merge(&FunctionFacts {
+            defaults: vec![None, None],
+            const_spans: Vec::new(),
+            member_function_pointers: Vec::new(),
+            noexcept: true,
+            explicit: false,
+            rvalue_ref_qualified: false,
+            deprecated: None,
+            nodiscard: false,
+            is_final: false,
+        });
+        assert_eq!(
+            facts,
+            FunctionFacts {
+                defaults: vec![None, Some("0".into())],
+                const_spans: Vec::new(),
+                member_function_pointers: Vec::new(),
+                noexcept: true,
+                explicit: false,
+                rvalue_ref_qualified: false,
+                deprecated: None,
+                nodiscard: false,
+                is_final: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_annotate_items() {
+        let mut facts = Facts::default();
+        facts.functions.insert(
+            "_ZN6Canvas4drawEiif".into(),
+            FunctionFacts {
+                defaults: vec![None, Some("0".into()), Some("1.0f".into())],
+                const_spans: Vec::new(),
+                member_function_pointers: Vec::new(),
+                noexcept: true,
+                explicit: false,
+                rvalue_ref_qualified: false,
+                deprecated: Some(Some("use paint".into())),
+                nodiscard: true,
+                is_final: false,
+            },
+        );
+        facts.functions.insert(
+            "_Z3sumSt4spanIKfLm18446744073709551615EE".into(),
+            FunctionFacts {
+                const_spans: vec![true],
+                ..Default::default()
+            },
+        );
+        facts.functions.insert(
+            "_ZN6Canvas8on_clickEMS_FviE".into(),
+            FunctionFacts {
+                member_function_pointers: vec![true],
+                ..Default::default()
+            },
+        );
+        facts.functions.insert(
+            "_ZNO6Canvas5closeEv".into(),
+            FunctionFacts {
+                rvalue_ref_qualified: true,
+                ..Default::default()
+            },
+        );
+        facts.functions.insert(
+            "_ZN6Canvas6redrawEv".into(),
+            FunctionFacts {
+                is_final: true,
+                ..Default::default()
+            },
+        );
+        facts.functions.insert(
+            "_ZN6CanvasC1Ei".into(),
+            FunctionFacts {
+                explicit: true,
+                ..Default::default()
+            },
+        );
+        facts
+            .deprecated_types
+            .insert("shapes::Canvas_Brush".into(), None);
+        facts.final_types.insert("shapes::Canvas_Brush".into());
+        facts.protected_fields.insert(
+            "shapes::Canvas_Brush".into(),
+            [
+                (
+                    "width_".into(),
+                    FieldFacts {
+                        cpp_name: "width_".into(),
+                        is_const: false,
+                    },
+                ),
+                (
+                    "type_".into(),
+                    FieldFacts {
+                        cpp_name: "type".into(),
+                        is_const: true,
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut bindings: ItemMod = parse_quote! {
+            pub mod root {
+                extern "C" {
+                    #[link_name = "\u{1}_ZN6Canvas4drawEiif"]
+                    pub fn Canvas_draw(this: *mut root::Canvas, x: c_int, y: c_int, scale: f32);
+                    #[link_name = "\u{1}_ZN6CanvasC1Ei"]
+                    pub fn Canvas_Canvas(this: *mut root::Canvas, size: c_int);
+                    #[link_name = "\u{1}_ZN6Canvas6redrawEv"]
+                    pub fn Canvas_redraw(this: *mut root::Canvas);
+                    #[link_name = "\u{1}_ZNO6Canvas5closeEv"]
+                    pub fn Canvas_close(this: *mut root::Canvas);
+                    #[link_name = "\u{1}_ZN6Canvas8on_clickEMS_FviE"]
+                    pub fn Canvas_on_click(this: *mut root::Canvas, handler: *mut c_void);
+                    pub fn unrelated(a: c_int);
+                    #[link_name = "\u{1}_Z3sumSt4spanIKfLm18446744073709551615EE"]
+                    pub fn sum(values: root::std::span<f32>) -> f32;
+                    #[link_name = "\u{1}_ZL7counter"]
+                    pub static mut counter: c_int;
+                }
+                pub mod shapes {
+                    pub struct Canvas_Brush {
+                        pub color: u32,
+                        width_: f32,
+                        type_: c_int,
+                    }
+                }
+            }
+        };
+        facts.internal_variables.insert("_ZL7counter".into());
+        facts.annotate_items(&mut bindings.content.as_mut().unwrap().1, &mut Vec::new());
+        let expected: ItemMod = parse_quote! {
+            pub mod root {
+                extern "C" {
+                    #[link_name = "\u{1}_ZN6Canvas4drawEiif"]
+                    #[cpp_semantics(arg_default(y, "0"))]
+                    #[cpp_semantics(arg_default(scale, "1.0f"))]
+                    #[cpp_semantics(noexcept)]
+                    #[cpp_semantics(deprecated("use paint"))]
+                    #[cpp_semantics(nodiscard)]
+                    pub fn Canvas_draw(this: *mut root::Canvas, x: c_int, y: c_int, scale: f32);
+                    #[link_name = "\u{1}_ZN6CanvasC1Ei"]
+                    #[cpp_semantics(explicit)]
+                    pub fn Canvas_Canvas(this: *mut root::Canvas, size: c_int);
+                    #[link_name = "\u{1}_ZN6Canvas6redrawEv"]
+                    #[cpp_semantics(final)]
+                    pub fn Canvas_redraw(this: *mut root::Canvas);
+                    #[link_name = "\u{1}_ZNO6Canvas5closeEv"]
+                    #[cpp_semantics(rvalue_ref_qualified)]
+                    pub fn Canvas_close(this: *mut root::Canvas);
+                    #[link_name = "\u{1}_ZN6Canvas8on_clickEMS_FviE"]
+                    #[cpp_semantics(arg_type_member_function_pointer(handler))]
+                    pub fn Canvas_on_click(this: *mut root::Canvas, handler: *mut c_void);
+                    pub fn unrelated(a: c_int);
+                    #[link_name = "\u{1}_Z3sumSt4spanIKfLm18446744073709551615EE"]
+                    #[cpp_semantics(arg_type_const_span(values))]
+                    pub fn sum(values: root::std::span<f32>) -> f32;
+                    #[link_name = "\u{1}_ZL7counter"]
+                    #[cpp_semantics(internal_linkage)]
+                    pub static mut counter: c_int;
+                }
+                pub mod shapes {
+                    #[cpp_semantics(deprecated)]
+                    #[cpp_semantics(final)]
+                    pub struct Canvas_Brush {
+                        pub color: u32,
+                        #[cpp_semantics(visibility_protected)]
+                        #[cpp_semantics(original_name("width_"))]
+                        width_: f32,
+                        #[cpp_semantics(visibility_protected)]
+                        #[cpp_semantics(original_name("type"))]
+                        #[cpp_semantics(const_field)]
+                        type_: c_int,
+                    }
+                }
+            }
+        };
+        assert_eq!(
+            bindings.into_token_stream().to_string(),
+            expected.into_token_stream().to_string()
+        );
+    }
+}
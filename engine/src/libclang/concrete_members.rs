@@ -0,0 +1,822 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen doesn't report the member functions of class templates, so the
+//! instantiations named in `concrete!` would otherwise have none. We find
+//! the public member functions of each such template with libclang, and
+//! declare for each one a function which takes the instantiation as its
+//! first parameter, spelling its signature with the template arguments
+//! substituted in. Those functions are never defined: we need only tell
+//! bindgen about them, in a header of their own, and the conversion turns
+//! them back into methods.
+
+use indexmap::map::IndexMap as HashMap;
+use indexmap::set::IndexSet as HashSet;
+
+use clang_sys::*;
+
+use super::{spelling, to_string, type_spelling, visit_children, TranslationUnit};
+
+/// The namespace, within that of each class template, in which we declare
+/// the functions standing for its members.
+pub(crate) const CONCRETE_MEMBERS_NAMESPACE: &str = "autocxx_concrete_members";
+
+/// The name of the parameter by which the functions standing for methods
+/// take the object.
+const THIS_PARAM: &str = "autocxx_this";
+
+/// What a function we declared stands for. Its name is the Rust name of
+/// the `concrete!` type, a marker for this, and the name of the member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConcreteMemberKind {
+    Method,
+    VirtualMethod,
+    PureVirtualMethod,
+    StaticMethod,
+    /// A constructor, which only a subclass can use, since the
+    /// instantiation may be abstract. Its member name is the template's.
+    Constructor,
+    /// The destructor, whether declared or implicit. Its member name is
+    /// the template's.
+    Destructor,
+    /// A member whose signature we couldn't spell for this instantiation.
+    Unsupported,
+}
+
+impl ConcreteMemberKind {
+    const ALL: [Self; 7] = [
+        Self::Method,
+        Self::VirtualMethod,
+        Self::PureVirtualMethod,
+        Self::StaticMethod,
+        Self::Constructor,
+        Self::Destructor,
+        Self::Unsupported,
+    ];
+
+    fn marker(&self) -> &'static str {
+        match self {
+            Self::Method => "_autocxx_member_",
+            Self::VirtualMethod => "_autocxx_virtual_",
+            Self::PureVirtualMethod => "_autocxx_pure_virtual_",
+            Self::StaticMethod => "_autocxx_static_",
+            Self::Constructor => "_autocxx_constructor_",
+            Self::Destructor => "_autocxx_destructor_",
+            Self::Unsupported => "_autocxx_unsupported_",
+        }
+    }
+
+    /// Given the name of one of the functions we declared, returns the
+    /// Rust name of the `concrete!` type, what the function stands for and
+    /// the name of the member.
+    pub(crate) fn parse_function_name(name: &str) -> Option<(&str, Self, &str)> {
+        Self::ALL.into_iter().find_map(|kind| {
+            name.split_once(kind.marker())
+                .map(|(concrete, member)| (concrete, kind, member))
+        })
+    }
+}
+
+/// A class template named in `concrete!`.
+struct Template {
+    /// The namespace in which it's declared.
+    namespace: Vec<String>,
+    /// Each public member function, and each constructor and the
+    /// destructor which a subclass could use, with its signature or
+    /// `None` if it has something we can't spell.
+    members: Vec<(String, ConcreteMemberKind, Option<Signature>)>,
+}
+
+/// What we need to know about a class template to tell which of the types
+/// within it depend on it.
+struct TemplateScope {
+    usr: String,
+    params: Vec<String>,
+}
+
+/// A member function's signature, as found within the template.
+#[derive(Debug)]
+struct Signature {
+    is_const: bool,
+    output: TemplateType,
+    inputs: Vec<(String, TemplateType)>,
+}
+
+/// A type in the signature of a member function of a class template, in a
+/// form we can spell for any instantiation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateType {
+    /// A type which doesn't depend on the template, spelled as it must be
+    /// outside it.
+    Fixed(String),
+    /// The template parameter with this index.
+    Param(usize),
+    /// The template itself, as its injected class name.
+    Instantiation,
+    /// A type declared within the template, by its path within it.
+    Member(String),
+    /// A specialization of a class template, by the fully-qualified name
+    /// of the template.
+    Specialization(String, Vec<TemplateType>),
+    Pointer(Box<TemplateType>),
+    LValueReference(Box<TemplateType>),
+    RValueReference(Box<TemplateType>),
+    Const(Box<TemplateType>),
+}
+
+/// One function we declare to stand for a member of an instantiation.
+struct Declaration {
+    namespace: Vec<String>,
+    concrete: String,
+    member: String,
+    /// The C++ declaration itself, unless it's unsupported.
+    text: Option<String>,
+}
+
+impl Declaration {
+    /// The declaration as a single line of C++.
+    fn line(&self) -> String {
+        let declaration = match &self.text {
+            Some(text) => text.clone(),
+            None => format!(
+                "void {}{}{}();",
+                self.concrete,
+                ConcreteMemberKind::Unsupported.marker(),
+                self.member
+            ),
+        };
+        let opening: String = self
+            .namespace
+            .iter()
+            .map(|ns| format!("namespace {ns} {{ "))
+            .collect();
+        let closing = " }".repeat(self.namespace.len());
+        format!("{opening}namespace {CONCRETE_MEMBERS_NAMESPACE} {{ {declaration} }}{closing}")
+    }
+}
+
+/// The functions standing for each public member function of the class
+/// templates instantiated in `concrete!`.
+#[derive(Default)]
+pub(super) struct ConcreteMembers(Vec<Declaration>);
+
+impl ConcreteMembers {
+    /// Finds the member functions of the class templates instantiated in
+    /// `concretes` (pairs of the C++ definition, such as `Store<int>`, and
+    /// the Rust name) within `tu`, which is the header alone.
+    pub(super) fn find(tu: &TranslationUnit, concretes: &[(String, String)]) -> Self {
+        let concretes: Vec<_> = concretes
+            .iter()
+            .filter_map(|(cpp_definition, rust_name)| {
+                let (template, args) = split_instantiation(cpp_definition)?;
+                // autocxx knows about the standard containers in other ways.
+                (!template.starts_with("std::")).then_some((
+                    cpp_definition.as_str(),
+                    template,
+                    args,
+                    rust_name.as_str(),
+                ))
+            })
+            .collect();
+        if concretes.is_empty() {
+            return Self::default();
+        }
+        let wanted: HashSet<&str> = concretes
+            .iter()
+            .map(|(_, template, _, _)| *template)
+            .collect();
+        let mut templates = HashMap::new();
+        find_templates_within(tu, tu.cursor(), &mut Vec::new(), 0, &wanted, &mut templates);
+        let mut declarations = Vec::new();
+        for (cpp_definition, template, args, rust_name) in &concretes {
+            if let Some(template) = templates.get(*template) {
+                declarations.extend(template.declarations(cpp_definition, args, rust_name));
+            }
+        }
+        Self(declarations)
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// A header declaring each function which we hope to offer, one per
+    /// line, for clang to check after the header it stands for.
+    pub(super) fn candidates(&self) -> String {
+        self.0
+            .iter()
+            .filter(|decl| decl.text.is_some())
+            .map(|decl| format!("{}\n", decl.line()))
+            .collect()
+    }
+
+    /// Given where clang reported errors in [`Self::candidates`], marks
+    /// the declarations at fault as unsupported. If we can't tell which
+    /// declaration is at fault for every error, we daren't offer any.
+    pub(super) fn reject(&mut self, error_lines: &[Option<usize>]) {
+        let mut candidates: Vec<_> = self
+            .0
+            .iter_mut()
+            .filter(|decl| decl.text.is_some())
+            .collect();
+        let culprits: Option<HashSet<usize>> = error_lines
+            .iter()
+            .map(|line| {
+                line.and_then(|line| line.checked_sub(1))
+                    .filter(|idx| *idx < candidates.len())
+            })
+            .collect();
+        for (idx, decl) in candidates.iter_mut().enumerate() {
+            if culprits
+                .as_ref()
+                .is_none_or(|culprits| culprits.contains(&idx))
+            {
+                decl.text = None;
+            }
+        }
+    }
+
+    /// Marks every declaration as unsupported, since we couldn't check
+    /// them.
+    pub(super) fn reject_all(&mut self) {
+        for decl in &mut self.0 {
+            decl.text = None;
+        }
+    }
+
+    /// The header to give bindgen. A member we couldn't declare need only
+    /// be reported if none of its overloads made it.
+    pub(super) fn header(&self) -> String {
+        let supported: HashSet<_> = self
+            .0
+            .iter()
+            .filter(|decl| decl.text.is_some())
+            .map(|decl| (&decl.concrete, &decl.member))
+            .collect();
+        let mut reported = HashSet::new();
+        self.0
+            .iter()
+            .filter(|decl| {
+                decl.text.is_some()
+                    || (!supported.contains(&(&decl.concrete, &decl.member))
+                        && reported.insert((&decl.concrete, &decl.member)))
+            })
+            .map(|decl| format!("{}\n", decl.line()))
+            .collect()
+    }
+}
+
+/// `scope` is the names of the namespaces and classes enclosing `cursor`,
+/// of which the first `namespace_depth` are namespaces. Finds the
+/// definitions of the class templates which we need, keyed by their
+/// fully-qualified names.
+fn find_templates_within(
+    tu: &TranslationUnit,
+    cursor: CXCursor,
+    scope: &mut Vec<String>,
+    namespace_depth: usize,
+    wanted: &HashSet<&str>,
+    templates: &mut HashMap<String, Template>,
+) {
+    visit_children(cursor, |child| {
+        #[allow(non_upper_case_globals)]
+        match unsafe { clang_getCursorKind(child) } {
+            CXCursor_LinkageSpec => {
+                find_templates_within(tu, child, scope, namespace_depth, wanted, templates)
+            }
+            kind @ (CXCursor_Namespace | CXCursor_StructDecl | CXCursor_ClassDecl) => {
+                let name = spelling(child);
+                if name.is_empty() {
+                    return;
+                }
+                let is_namespace = kind == CXCursor_Namespace;
+                if is_namespace && namespace_depth < scope.len() {
+                    return;
+                }
+                scope.push(name);
+                let depth = if is_namespace {
+                    namespace_depth + 1
+                } else {
+                    namespace_depth
+                };
+                find_templates_within(tu, child, scope, depth, wanted, templates);
+                scope.pop();
+            }
+            CXCursor_ClassTemplate if unsafe { clang_isCursorDefinition(child) } != 0 => {
+                let name = spelling(child);
+                let qualified = scope
+                    .iter()
+                    .chain(std::iter::once(&name))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("::");
+                if wanted.contains(qualified.as_str()) {
+                    let template = Template::new(tu, child, scope[..namespace_depth].to_vec());
+                    templates.insert(qualified, template);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+impl Template {
+    fn new(tu: &TranslationUnit, cursor: CXCursor, namespace: Vec<String>) -> Self {
+        let mut scope = TemplateScope {
+            usr: usr(cursor),
+            params: Vec::new(),
+        };
+        let template_name = spelling(cursor);
+        let mut methods = Vec::new();
+        let mut has_constructors = false;
+        let mut has_destructor = false;
+        visit_children(cursor, |child| unsafe {
+            let access = clang_getCXXAccessSpecifier(child);
+            #[allow(non_upper_case_globals)]
+            match clang_getCursorKind(child) {
+                CXCursor_TemplateTypeParameter
+                | CXCursor_NonTypeTemplateParameter
+                | CXCursor_TemplateTemplateParameter => scope.params.push(spelling(child)),
+                CXCursor_CXXMethod => {
+                    if access != CX_CXXPublic {
+                        return;
+                    }
+                    let name = spelling(child);
+                    // We leave operators to the existing means of offering
+                    // them.
+                    if name.starts_with("operator") || is_deleted(tu, child) {
+                        return;
+                    }
+                    let kind = if clang_CXXMethod_isStatic(child) != 0 {
+                        ConcreteMemberKind::StaticMethod
+                    } else if clang_CXXMethod_isPureVirtual(child) != 0 {
+                        ConcreteMemberKind::PureVirtualMethod
+                    } else if clang_CXXMethod_isVirtual(child) != 0 {
+                        ConcreteMemberKind::VirtualMethod
+                    } else {
+                        ConcreteMemberKind::Method
+                    };
+                    methods.push((name, kind, Some(child)));
+                }
+                CXCursor_Constructor => {
+                    has_constructors = true;
+                    // Copy and move constructors are no use to a subclass
+                    // made in Rust.
+                    if access != CX_CXXPrivate
+                        && !is_deleted(tu, child)
+                        && clang_CXXConstructor_isCopyConstructor(child) == 0
+                        && clang_CXXConstructor_isMoveConstructor(child) == 0
+                    {
+                        methods.push((
+                            template_name.clone(),
+                            ConcreteMemberKind::Constructor,
+                            Some(child),
+                        ));
+                    }
+                }
+                CXCursor_Destructor => {
+                    has_destructor = true;
+                    if access != CX_CXXPrivate && !is_deleted(tu, child) {
+                        methods.push((
+                            template_name.clone(),
+                            ConcreteMemberKind::Destructor,
+                            Some(child),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        });
+        // The implicit default constructor and destructor take nothing
+        // but the object.
+        if !has_constructors {
+            methods.push((template_name.clone(), ConcreteMemberKind::Constructor, None));
+        }
+        if !has_destructor {
+            methods.push((template_name, ConcreteMemberKind::Destructor, None));
+        }
+        let members = methods
+            .into_iter()
+            .map(|(name, kind, cursor)| {
+                let signature = match cursor {
+                    Some(cursor) => Signature::new(cursor, &scope),
+                    None => Some(Signature::implicit()),
+                };
+                (name, kind, signature)
+            })
+            .collect();
+        Self { namespace, members }
+    }
+
+    /// Declarations of functions standing for each member function of
+    /// the instantiation `cpp_definition`, which is this template with the
+    /// given arguments, whose Rust name is `rust_name`.
+    fn declarations(
+        &self,
+        cpp_definition: &str,
+        args: &[&str],
+        rust_name: &str,
+    ) -> Vec<Declaration> {
+        let instantiation = format!("::{cpp_definition}");
+        self.members
+            .iter()
+            .filter_map(|(member, kind, signature)| {
+                let text = signature.as_ref().and_then(|signature| {
+                    signature.declare(
+                        &format!("{rust_name}{}{member}", kind.marker()),
+                        *kind != ConcreteMemberKind::StaticMethod,
+                        args,
+                        &instantiation,
+                    )
+                });
+                // Constructors and destructors are only of interest to
+                // subclasses, which can do without those we can't spell.
+                if text.is_none()
+                    && matches!(
+                        kind,
+                        ConcreteMemberKind::Constructor | ConcreteMemberKind::Destructor
+                    )
+                {
+                    return None;
+                }
+                Some(Declaration {
+                    namespace: self.namespace.clone(),
+                    concrete: rust_name.to_string(),
+                    member: member.clone(),
+                    text,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Signature {
+    /// Returns `None` for methods we can't call on an ordinary object,
+    /// those which are `&&`-qualified or variadic, and for those whose
+    /// types we can't spell.
+    fn new(cursor: CXCursor, scope: &TemplateScope) -> Option<Self> {
+        unsafe {
+            let ty = clang_getCursorType(cursor);
+            if clang_Type_getCXXRefQualifier(ty) == CXRefQualifier_RValue
+                || clang_isFunctionTypeVariadic(ty) != 0
+            {
+                return None;
+            }
+            let num_args = clang_Cursor_getNumArguments(cursor).max(0) as u32;
+            Some(Self {
+                is_const: clang_CXXMethod_isConst(cursor) != 0,
+                output: TemplateType::new(clang_getCursorResultType(cursor), scope)?,
+                inputs: (0..num_args)
+                    .map(|idx| {
+                        let param = clang_Cursor_getArgument(cursor, idx);
+                        Some((
+                            spelling(param),
+                            TemplateType::new(clang_getCursorType(param), scope)?,
+                        ))
+                    })
+                    .collect::<Option<_>>()?,
+            })
+        }
+    }
+
+    /// The signature of an implicit constructor or destructor.
+    fn implicit() -> Self {
+        Self {
+            is_const: false,
+            output: TemplateType::Fixed("void".into()),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// A declaration of a function named `name` with this signature, for
+    /// the instantiation of the template with `args`, whose
+    /// fully-qualified spelling is `instantiation`. Methods take the object
+    /// as an extra first parameter.
+    fn declare(
+        &self,
+        name: &str,
+        is_method: bool,
+        args: &[&str],
+        instantiation: &str,
+    ) -> Option<String> {
+        let mut inputs = Vec::new();
+        if is_method {
+            let constness = if self.is_const { "const " } else { "" };
+            inputs.push(format!("{constness}{instantiation}& {THIS_PARAM}"));
+        }
+        for (idx, (param, ty)) in self.inputs.iter().enumerate() {
+            let ty = ty.spell(args, instantiation)?;
+            let param = if is_identifier(param) && param != THIS_PARAM {
+                param.clone()
+            } else {
+                format!("arg{idx}")
+            };
+            inputs.push(format!("{ty} {param}"));
+        }
+        let output = self.output.spell(args, instantiation)?;
+        Some(format!("{output} {name}({});", inputs.join(", ")))
+    }
+}
+
+impl TemplateType {
+    /// Works out how `ty`, found within the template, is made up.
+    /// Returns `None` for types which we can't spell before a parameter
+    /// name, such as function pointers, and for those which depend on the
+    /// template in ways we don't follow, such as `typename T::value_type`.
+    fn new(ty: CXType, scope: &TemplateScope) -> Option<Self> {
+        unsafe {
+            if clang_isVolatileQualifiedType(ty) != 0 {
+                return None;
+            }
+            let unqualified = Self::new_unqualified(ty, scope)?;
+            Some(
+                if clang_isConstQualifiedType(ty) != 0 && !matches!(unqualified, Self::Const(_)) {
+                    Self::Const(Box::new(unqualified))
+                } else {
+                    unqualified
+                },
+            )
+        }
+    }
+
+    unsafe fn new_unqualified(ty: CXType, scope: &TemplateScope) -> Option<Self> {
+        #[allow(non_upper_case_globals)]
+        match ty.kind {
+            CXType_Elaborated => Self::new(clang_Type_getNamedType(ty), scope),
+            CXType_Pointer => Some(Self::Pointer(Box::new(Self::new(
+                clang_getPointeeType(ty),
+                scope,
+            )?))),
+            CXType_LValueReference => Some(Self::LValueReference(Box::new(Self::new(
+                clang_getPointeeType(ty),
+                scope,
+            )?))),
+            CXType_RValueReference => Some(Self::RValueReference(Box::new(Self::new(
+                clang_getPointeeType(ty),
+                scope,
+            )?))),
+            kind if (CXType_Void..=CXType_NullPtr).contains(&kind) => {
+                let spelling = type_spelling(ty);
+                Some(Self::Fixed(
+                    spelling
+                        .strip_prefix("const ")
+                        .unwrap_or(&spelling)
+                        .to_string(),
+                ))
+            }
+            _ => {
+                let decl = clang_getTypeDeclaration(ty);
+                #[allow(non_upper_case_globals)]
+                match clang_getCursorKind(decl) {
+                    CXCursor_TemplateTypeParameter => scope.param(&spelling(decl)),
+                    // libclang may not tell us the declaration of a
+                    // template parameter, but it's spelled as its name.
+                    CXCursor_NoDeclFound => {
+                        let spelling = type_spelling(ty);
+                        scope.param(spelling.strip_prefix("const ").unwrap_or(&spelling))
+                    }
+                    CXCursor_StructDecl
+                    | CXCursor_ClassDecl
+                    | CXCursor_UnionDecl
+                    | CXCursor_EnumDecl
+                    | CXCursor_TypedefDecl
+                    | CXCursor_TypeAliasDecl
+                    | CXCursor_ClassTemplate => {
+                        let num_args = clang_Type_getNumTemplateArguments(ty);
+                        let args = (0..num_args.max(0) as u32)
+                            .map(|idx| {
+                                let arg = clang_Type_getTemplateArgumentAsType(ty, idx);
+                                (arg.kind != CXType_Invalid)
+                                    .then(|| Self::new(arg, scope))
+                                    .flatten()
+                            })
+                            .collect::<Option<Vec<_>>>()?;
+                        match scope.path_within(decl) {
+                            _ if usr(decl) == scope.usr && num_args <= 0 => {
+                                Some(Self::Instantiation)
+                            }
+                            // We leave alone the templates declared within
+                            // the template.
+                            Some(_) if num_args > 0 => None,
+                            Some(path) => Some(Self::Member(path)),
+                            None if num_args > 0 => {
+                                Some(Self::Specialization(qualified_name(decl), args))
+                            }
+                            None => Some(Self::Fixed(qualified_name(decl))),
+                        }
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Spells this type for the instantiation of the template with `args`,
+    /// whose fully-qualified spelling is `instantiation`. We put `const`
+    /// after what it qualifies, so that it means the same whatever we
+    /// substitute for a template parameter.
+    fn spell(&self, args: &[&str], instantiation: &str) -> Option<String> {
+        Some(match self {
+            Self::Fixed(spelling) => spelling.clone(),
+            Self::Param(idx) => args.get(*idx)?.to_string(),
+            Self::Instantiation => instantiation.to_string(),
+            Self::Member(path) => format!("{instantiation}::{path}"),
+            Self::Specialization(template, template_args) => format!(
+                "{template}<{}>",
+                template_args
+                    .iter()
+                    .map(|arg| arg.spell(args, instantiation))
+                    .collect::<Option<Vec<_>>>()?
+                    .join(", ")
+            ),
+            Self::Pointer(pointee) => format!("{} *", pointee.spell(args, instantiation)?),
+            Self::LValueReference(pointee) => {
+                format!("{} &", pointee.spell(args, instantiation)?)
+            }
+            Self::RValueReference(pointee) => {
+                format!("{} &&", pointee.spell(args, instantiation)?)
+            }
+            Self::Const(ty) => format!("{} const", ty.spell(args, instantiation)?),
+        })
+    }
+}
+
+impl TemplateScope {
+    fn param(&self, name: &str) -> Option<TemplateType> {
+        self.params
+            .iter()
+            .position(|param| param == name)
+            .map(TemplateType::Param)
+    }
+
+    /// If `decl` is declared within the template, its path within it.
+    fn path_within(&self, decl: CXCursor) -> Option<String> {
+        let mut path = vec![spelling(decl)];
+        let mut parent = unsafe { clang_getCursorSemanticParent(decl) };
+        while unsafe { clang_isDeclaration(clang_getCursorKind(parent)) } != 0 {
+            if usr(parent) == self.usr {
+                path.reverse();
+                return Some(path.join("::"));
+            }
+            path.push(spelling(parent));
+            parent = unsafe { clang_getCursorSemanticParent(parent) };
+        }
+        None
+    }
+}
+
+/// The Unified Symbol Resolution of a declaration, which is the same for
+/// a class template and the class declared within it.
+fn usr(cursor: CXCursor) -> String {
+    unsafe { to_string(clang_getCursorUSR(cursor)) }
+}
+
+/// The fully-qualified name of `decl`, skipping anonymous namespaces and
+/// any template arguments.
+fn qualified_name(decl: CXCursor) -> String {
+    let mut names = Vec::new();
+    let mut cursor = decl;
+    while unsafe { clang_isDeclaration(clang_getCursorKind(cursor)) } != 0 {
+        let name = spelling(cursor);
+        let name = name.split('<').next().unwrap_or_default();
+        if !name.is_empty() && unsafe { clang_getCursorKind(cursor) } != CXCursor_LinkageSpec {
+            names.push(name.to_string());
+        }
+        cursor = unsafe { clang_getCursorSemanticParent(cursor) };
+    }
+    names.iter().rev().map(|name| format!("::{name}")).collect()
+}
+
+/// Whether a method is declared `= delete`.
+fn is_deleted(tu: &TranslationUnit, cursor: CXCursor) -> bool {
+    tu.tokens(cursor)
+        .windows(2)
+        .any(|pair| pair[0].1 == "=" && pair[1].1 == "delete")
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a (canonically spelled) instantiation such as
+/// `ns::Store<std::pair<int, int>, Thing>` into the name of the template
+/// and its arguments.
+fn split_instantiation(cpp_definition: &str) -> Option<(&str, Vec<&str>)> {
+    let (template, args) = cpp_definition.split_once('<')?;
+    let args = args.strip_suffix('>')?;
+    let mut depth = 0;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (idx, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(args[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(args[start..].trim());
+    Some((template.trim(), split))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_instantiation, ConcreteMemberKind, Signature, TemplateType};
+
+    #[test]
+    fn test_split_instantiation() {
+        assert_eq!(
+            split_instantiation("ns::Store<std::pair<int, int>, Thing>"),
+            Some(("ns::Store", vec!["std::pair<int, int>", "Thing"]))
+        );
+        assert_eq!(split_instantiation("Thing"), None);
+    }
+
+    #[test]
+    fn test_declare() {
+        let signature = Signature {
+            is_const: true,
+            output: TemplateType::LValueReference(Box::new(TemplateType::Const(Box::new(
+                TemplateType::Param(0),
+            )))),
+            inputs: vec![
+                ("".into(), TemplateType::Member("value_type".into())),
+                (
+                    "other".into(),
+                    TemplateType::LValueReference(Box::new(TemplateType::Const(Box::new(
+                        TemplateType::Instantiation,
+                    )))),
+                ),
+                (
+                    "third".into(),
+                    TemplateType::Specialization(
+                        "::std::vector".into(),
+                        vec![TemplateType::Pointer(Box::new(TemplateType::Param(0)))],
+                    ),
+                ),
+                (
+                    "autocxx_this".into(),
+                    TemplateType::Const(Box::new(TemplateType::Fixed("int".into()))),
+                ),
+            ],
+        };
+        assert_eq!(
+            signature
+                .declare(
+                    "StoreOfThing_autocxx_member_get",
+                    true,
+                    &["ns::Thing *"],
+                    "::Store<ns::Thing *>",
+                )
+                .unwrap(),
+            "ns::Thing * const & StoreOfThing_autocxx_member_get(const ::Store<ns::Thing *>& autocxx_this, ::Store<ns::Thing *>::value_type arg0, ::Store<ns::Thing *> const & other, ::std::vector<ns::Thing * *> third, int const arg3);"
+        );
+        let two_params = Signature {
+            is_const: false,
+            output: TemplateType::Fixed("void".into()),
+            inputs: vec![("u".into(), TemplateType::Param(1))],
+        };
+        assert!(two_params
+            .declare(
+                "StoreOfThing_autocxx_static_apply",
+                false,
+                &["int"],
+                "::Store<int>"
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_function_name() {
+        assert_eq!(
+            ConcreteMemberKind::parse_function_name("Store_Of_Int_autocxx_member_get_value"),
+            Some(("Store_Of_Int", ConcreteMemberKind::Method, "get_value"))
+        );
+        assert_eq!(
+            ConcreteMemberKind::parse_function_name("StoreOfInt_autocxx_unsupported_apply"),
+            Some(("StoreOfInt", ConcreteMemberKind::Unsupported, "apply"))
+        );
+        assert_eq!(
+            ConcreteMemberKind::parse_function_name("NodeVisitor_autocxx_pure_virtual_visit"),
+            Some((
+                "NodeVisitor",
+                ConcreteMemberKind::PureVirtualMethod,
+                "visit"
+            ))
+        );
+        assert_eq!(
+            ConcreteMemberKind::parse_function_name("NodeVisitor_autocxx_virtual_visit"),
+            Some(("NodeVisitor", ConcreteMemberKind::VirtualMethod, "visit"))
+        );
+        assert_eq!(ConcreteMemberKind::parse_function_name("get"), None);
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! bindgen tells us much of what we need to know about the C++, but not
+//! everything. For the rest, we ask libclang ourselves, parsing the same
+//! header with the same arguments as bindgen does.
+
+#![allow(unsafe_code)]
+
+mod annotations;
+mod concrete_members;
+
+pub(crate) use concrete_members::{ConcreteMemberKind, CONCRETE_MEMBERS_NAMESPACE};
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_uint, c_ulong},
+    ptr,
+};
+
+use clang_sys::*;
+
+use crate::header_recovery::SynthesizedHeader;
+use annotations::Facts;
+use concrete_members::ConcreteMembers;
+
+/// The name of the header in which we declare the functions standing for
+/// the members of `concrete!` instantiations. bindgen parses it after
+/// (`-include`ing) the header proper, which is left as it is.
+pub(crate) const CONCRETE_MEMBERS_HEADER: &str = "autocxx-concrete-members.h";
+
+/// What libclang told us about a header, beyond what bindgen will.
+#[derive(Default)]
+pub(crate) struct HeaderFacts {
+    concrete_members: Option<String>,
+    annotations: Option<Facts>,
+}
+
+impl HeaderFacts {
+    /// The contents of [`CONCRETE_MEMBERS_HEADER`], if bindgen needs it.
+    pub(crate) fn concrete_members(&self) -> Option<&str> {
+        self.concrete_members.as_deref()
+    }
+
+    /// Adds to `bindings` (bindgen's output for the header) the
+    /// annotations which bindgen doesn't make.
+    pub(crate) fn annotate_bindings(&self, bindings: String) -> String {
+        match &self.annotations {
+            Some(facts) => facts.annotate(bindings),
+            None => bindings,
+        }
+    }
+}
+
+/// Parses `header` with libclang, with the same arguments as bindgen will,
+/// to declare functions standing for the members of the `concrete!`
+/// instantiations in `concretes` (pairs of the C++ definition and the Rust
+/// name) and to learn what bindgen won't tell us. We check every such
+/// declaration in a single further parse, which also serves for the rest.
+pub(crate) fn examine_header(
+    header: &SynthesizedHeader,
+    clang_args: &[String],
+    concretes: &[(String, String)],
+) -> HeaderFacts {
+    let clang_args = full_clang_args(clang_args);
+    let Some(tu) = TranslationUnit::parse(&[(header.name, &header.contents)], &clang_args) else {
+        return HeaderFacts::default();
+    };
+    // If the header itself has errors, bindgen will report them, and we
+    // should add nothing to confuse matters.
+    let mut members = if tu.error_lines().is_empty() {
+        ConcreteMembers::find(&tu, concretes)
+    } else {
+        ConcreteMembers::default()
+    };
+    if members.is_empty() {
+        return HeaderFacts {
+            concrete_members: None,
+            annotations: Some(Facts::gather(&tu)),
+        };
+    }
+    drop(tu);
+    let candidates = members.candidates();
+    let tu = TranslationUnit::parse(
+        &[
+            (CONCRETE_MEMBERS_HEADER, &candidates),
+            (header.name, &header.contents),
+        ],
+        &clang_args,
+    );
+    match &tu {
+        Some(tu) => members.reject(&tu.error_lines()),
+        // We can't check them, so we daren't offer them to bindgen.
+        None => members.reject_all(),
+    }
+    HeaderFacts {
+        concrete_members: Some(members.header()),
+        annotations: tu.as_ref().map(Facts::gather),
+    }
+}
+
+/// A header which libclang has parsed.
+struct TranslationUnit {
+    index: CXIndex,
+    tu: CXTranslationUnit,
+}
+
+impl TranslationUnit {
+    /// Parses `files`, each the name and contents of a header, passing
+    /// clang `clang_args` (as filled out by [`full_clang_args`]). As
+    /// bindgen does when given several headers, the first is the main
+    /// file and the others are `-include`d before it. Returns `None` if
+    /// libclang isn't available or can't parse them at all.
+    fn parse(files: &[(&str, &str)], clang_args: &[String]) -> Option<Self> {
+        // bindgen will have loaded libclang for this thread, if it's to be
+        // found at all.
+        #[cfg(feature = "runtime")]
+        if !clang_sys::is_loaded() {
+            return None;
+        }
+        // As bindgen does, pass clang absolute paths for the headers.
+        let current_dir = std::env::current_dir().ok()?;
+        let files: Vec<(CString, CString)> = files
+            .iter()
+            .map(|(name, contents)| {
+                Some((
+                    CString::new(current_dir.join(name).to_str()?).ok()?,
+                    CString::new(*contents).ok()?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let (main_file, included) = files.split_first()?;
+        let args: Vec<CString> = clang_args
+            .iter()
+            .map(|arg| CString::new(arg.as_str()))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?
+            .into_iter()
+            .chain(
+                included
+                    .iter()
+                    .flat_map(|(path, _)| [CString::new("-include").unwrap(), path.clone()]),
+            )
+            .chain(std::iter::once(main_file.0.clone()))
+            .collect();
+        let arg_ptrs: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        let mut unsaved: Vec<CXUnsavedFile> = files
+            .iter()
+            .map(|(path, contents)| CXUnsavedFile {
+                Filename: path.as_ptr(),
+                Contents: contents.as_ptr(),
+                Length: contents.as_bytes().len() as c_ulong,
+            })
+            .collect();
+        unsafe {
+            let index = clang_createIndex(0, 0);
+            let tu = clang_parseTranslationUnit(
+                index,
+                ptr::null(),
+                arg_ptrs.as_ptr(),
+                arg_ptrs.len() as c_int,
+                unsaved.as_mut_ptr(),
+                unsaved.len() as c_uint,
+                CXTranslationUnit_SkipFunctionBodies,
+            );
+            if tu.is_null() {
+                clang_disposeIndex(index);
+                None
+            } else {
+                Some(Self { index, tu })
+            }
+        }
+    }
+
+    fn cursor(&self) -> CXCursor {
+        unsafe { clang_getTranslationUnitCursor(self.tu) }
+    }
+
+    /// The kind and spelling of each token of the source which `cursor`
+    /// covers.
+    fn tokens(&self, cursor: CXCursor) -> Vec<(CXTokenKind, String)> {
+        let mut results = Vec::new();
+        unsafe {
+            let mut tokens = ptr::null_mut();
+            let mut num_tokens = 0;
+            clang_tokenize(
+                self.tu,
+                clang_getCursorExtent(cursor),
+                &mut tokens,
+                &mut num_tokens,
+            );
+            if !tokens.is_null() {
+                for idx in 0..num_tokens as usize {
+                    let token = *tokens.add(idx);
+                    results.push((
+                        clang_getTokenKind(token),
+                        to_string(clang_getTokenSpelling(self.tu, token)),
+                    ));
+                }
+                clang_disposeTokens(self.tu, tokens, num_tokens);
+            }
+        }
+        results
+    }
+
+    /// Where clang reported each error: the line of the main file, or
+    /// `None` if it was in some other file. An error which arose
+    /// elsewhere, such as while clang instantiated a template, is
+    /// attributed to the line of the main file mentioned by any of the
+    /// notes which follow it, such as "in instantiation of ... requested
+    /// here".
+    fn error_lines(&self) -> Vec<Option<usize>> {
+        let mut results = Vec::new();
+        unsafe {
+            for idx in 0..clang_getNumDiagnostics(self.tu) {
+                let diagnostic = clang_getDiagnostic(self.tu, idx);
+                if clang_getDiagnosticSeverity(diagnostic) >= CXDiagnostic_Error {
+                    let notes = clang_getChildDiagnostics(diagnostic);
+                    let line = main_file_line(diagnostic).or_else(|| {
+                        (0..clang_getNumDiagnosticsInSet(notes))
+                            .find_map(|idx| main_file_line(clang_getDiagnosticInSet(notes, idx)))
+                    });
+                    results.push(line);
+                }
+                clang_disposeDiagnostic(diagnostic);
+            }
+        }
+        results
+    }
+}
+
+/// The line of the main file at which `diagnostic` was reported, if it
+/// was reported there.
+unsafe fn main_file_line(diagnostic: CXDiagnostic) -> Option<usize> {
+    let location = clang_getDiagnosticLocation(diagnostic);
+    let mut line: c_uint = 0;
+    clang_getSpellingLocation(
+        location,
+        ptr::null_mut(),
+        &mut line,
+        ptr::null_mut(),
+        ptr::null_mut(),
+    );
+    (clang_Location_isFromMainFile(location) != 0).then_some(line as usize)
+}
+
+impl Drop for TranslationUnit {
+    fn drop(&mut self) {
+        unsafe {
+            clang_disposeTranslationUnit(self.tu);
+            clang_disposeIndex(self.index);
+        }
+    }
+}
+
+/// The arguments bindgen will pass to clang, bar the header itself: ours,
+/// any from the environment, and the C++ standard library include
+/// directories which bindgen finds for itself.
+fn full_clang_args(clang_args: &[String]) -> Vec<String> {
+    let mut args = clang_args.to_vec();
+    let extra_var = "BINDGEN_EXTRA_CLANG_ARGS";
+    let extra = std::env::var("TARGET")
+        .ok()
+        .and_then(|target| {
+            std::env::var(format!("{extra_var}_{target}"))
+                .or_else(|_| std::env::var(format!("{extra_var}_{}", target.replace('-', "_"))))
+                .ok()
+        })
+        .or_else(|| std::env::var(extra_var).ok());
+    if let Some(extra) = extra {
+        args.extend(shlex::split(&extra).unwrap_or_else(|| vec![extra]));
+    }
+    // Like bindgen, don't let clang-sys promote our include directories
+    // to system ones.
+    let mut without_includes = Vec::new();
+    let mut skip_next = false;
+    for arg in &args {
+        if std::mem::take(&mut skip_next) {
+            continue;
+        }
+        if arg == "-I" || arg == "--include-directory" {
+            skip_next = true;
+        } else if !arg.starts_with("-I") && !arg.starts_with("--include-directory=") {
+            without_includes.push(arg.clone());
+        }
+    }
+    if let Some(search_paths) = clang_sys::support::Clang::find(None, &without_includes)
+        .and_then(|clang| clang.cpp_search_paths)
+    {
+        for path in search_paths {
+            if let Some(path) = path.to_str() {
+                args.push("-isystem".into());
+                args.push(path.into());
+            }
+        }
+    }
+    args
+}
+
+/// The name of the entity which `cursor` refers to, or the spelling of a
+/// type.
+fn spelling(cursor: CXCursor) -> String {
+    unsafe { to_string(clang_getCursorSpelling(cursor)) }
+}
+
+fn type_spelling(ty: CXType) -> String {
+    unsafe { to_string(clang_getTypeSpelling(ty)) }
+}
+
+/// Converts, and disposes of, a string which libclang gave us.
+unsafe fn to_string(s: CXString) -> String {
+    let c_str = clang_getCString(s);
+    let result = if c_str.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(c_str).to_string_lossy().into_owned()
+    };
+    clang_disposeString(s);
+    result
+}
+
+fn visit_children<F: FnMut(CXCursor)>(cursor: CXCursor, mut f: F) {
+    extern "C" fn visit<F: FnMut(CXCursor)>(
+        cursor: CXCursor,
+        _parent: CXCursor,
+        data: CXClientData,
+    ) -> CXChildVisitResult {
+        let f = unsafe { &mut *(data as *mut F) };
+        f(cursor);
+        CXChildVisit_Continue
+    }
+    unsafe {
+        clang_visitChildren(cursor, visit::<F>, &mut f as *mut F as CXClientData);
+    }
+}
@@ -8,6 +8,9 @@
 
 use autocxx_parser::{IncludeCppConfig, MultiBindings};
 use proc_macro2::TokenStream;
+use syn::ItemMod;
+
+use crate::rust_pretty_printer::pretty_print;
 
 /// Opaque structure representing the Rust which needs to be generated
 /// for a given `include_cpp!` macro. You will want to pass this into
@@ -44,3 +47,17 @@ pub fn generate_rs_single(rs_output: RsOutput) -> RsInclude {
         filename: rs_output.config.get_rs_filename(),
     }
 }
+
+/// Like [`generate_rs_single`], but the code is pretty-printed such that
+/// its formatting is stable. This is slower, but more suitable if the
+/// resulting file is to be checked into source control.
+pub fn generate_rs_single_pretty(rs_output: RsOutput) -> RsInclude {
+    let code = match syn::parse2::<ItemMod>(rs_output.rs.clone()) {
+        Ok(item_mod) => pretty_print(&item_mod),
+        Err(_) => rs_output.rs.to_string(),
+    };
+    RsInclude {
+        code,
+        filename: rs_output.config.get_rs_filename(),
+    }
+}
@@ -7,12 +7,13 @@
 // except according to those terms.
 
 use std::panic::UnwindSafe;
+use std::rc::Rc;
 
 use crate::RebuildDependencyRecorder;
 use autocxx_bindgen::callbacks::ParseCallbacks;
 
 #[derive(Debug)]
-pub(crate) struct AutocxxParseCallbacks(pub(crate) Box<dyn RebuildDependencyRecorder>);
+pub(crate) struct AutocxxParseCallbacks(pub(crate) Rc<dyn RebuildDependencyRecorder>);
 
 impl UnwindSafe for AutocxxParseCallbacks {}
 
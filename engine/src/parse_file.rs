@@ -9,12 +9,13 @@
 use crate::ast_discoverer::{Discoveries, DiscoveryErr};
 use crate::output_generators::RsOutput;
 use crate::{
-    cxxbridge::CxxBridge, Error as EngineError, GeneratedCpp, IncludeCppEngine,
+    bindgen_runner, cxxbridge::CxxBridge, Error as EngineError, GeneratedCpp, IncludeCppEngine,
     RebuildDependencyRecorder,
 };
 use crate::{proc_macro_span_to_miette_span, CodegenOptions, CppCodegenOptions, LocatedSynError};
 use autocxx_parser::directive_names::SUBCLASS;
-use autocxx_parser::{AllowlistEntry, RustPath, Subclass, SubclassAttrs};
+use autocxx_parser::{is_cfg_enabled, AllowlistEntry, RustPath, Subclass, SubclassAttrs};
+use indexmap::map::IndexMap as HashMap;
 use indexmap::set::IndexSet as HashSet;
 use miette::{Diagnostic, SourceSpan};
 use quote::ToTokens;
@@ -55,6 +56,10 @@ pub enum ParseError {
     /// mod name.
     #[error("there are two or more include_cpp! mods with the same mod name")]
     ConflictingModNames,
+    /// An `import_from!` named something other than an `include_cpp!`
+    /// mod earlier in the same file.
+    #[error("import_from!({0}) doesn't name an include_cpp! mod earlier in the same file")]
+    UnknownImport(String),
     #[error("dynamic discovery was enabled but multiple mods were found")]
     MultipleModsForDynamicDiscovery,
     #[error("a problem occurred while discovering C++ APIs used within the Rust: {0}")]
@@ -104,12 +109,17 @@ fn parse_file_contents(
                         .segments
                         .last()
                         .map(|s| s.ident == "include_cpp")
-                        .unwrap_or(false) =>
+                        .unwrap_or(false)
+                        && is_cfg_enabled(&mac.attrs) =>
                 {
-                    Segment::Autocxx(
-                        crate::IncludeCppEngine::new_from_syn(mac.mac, file_contents)
-                            .map_err(ParseError::AutocxxCodegenError)?,
-                    )
+                    let mut engine = crate::IncludeCppEngine::new_from_syn(mac.mac, file_contents)
+                        .map_err(ParseError::AutocxxCodegenError)?;
+                    engine.cfg_attrs = mac
+                        .attrs
+                        .into_iter()
+                        .filter(|attr| attr.path().is_ident("cfg"))
+                        .collect();
+                    Segment::Autocxx(engine)
                 }
                 Item::Mod(itm)
                     if itm.attrs.iter().any(|attr| {
@@ -182,6 +192,7 @@ fn parse_file_contents(
                                 self.extra_superclasses.push(Subclass {
                                     superclass,
                                     subclass,
+                                    threadsafe: false,
                                 })
                             }
                         }
@@ -384,7 +395,11 @@ impl ParsedFile {
         dep_recorder: Option<Box<dyn RebuildDependencyRecorder>>,
         codegen_options: &CodegenOptions,
     ) -> Result<(), ParseError> {
+        if codegen_options.bindgen_jobs > 1 {
+            self.prefetch_bindings(&autocxx_inc, extra_clang_args, codegen_options);
+        }
         let mut mods_found = HashSet::new();
+        let mut generated_types = HashMap::new();
         let inner_dep_recorder: Option<Rc<dyn RebuildDependencyRecorder>> =
             dep_recorder.map(Rc::from);
         for include_cpp in self.get_autocxxes_mut() {
@@ -399,17 +414,80 @@ impl ParsedFile {
             if !mods_found.insert(include_cpp.get_mod_name()) {
                 return Err(ParseError::ConflictingModNames);
             }
+            include_cpp
+                .import_types(&generated_types)
+                .map_err(ParseError::UnknownImport)?;
+            let clang_args = clang_args_for_block(
+                &include_cpp.get_mod_name(),
+                extra_clang_args,
+                codegen_options,
+            );
             include_cpp
                 .generate(
                     autocxx_inc.clone(),
-                    extra_clang_args,
+                    &clang_args,
                     dep_recorder,
                     codegen_options,
                 )
-                .map_err(ParseError::AutocxxCodegenError)?
+                .map_err(ParseError::AutocxxCodegenError)?;
+            generated_types.insert(
+                include_cpp.get_mod_name(),
+                include_cpp.generated_types().to_vec(),
+            );
         }
         Ok(())
     }
+
+    /// Runs bindgen for each of the `include_cpp!`s which will need it,
+    /// in parallel, before they're each generated in turn. The conversion
+    /// which follows can't be done in parallel, since an `include_cpp!`
+    /// may depend on the results for those before it.
+    fn prefetch_bindings(
+        &mut self,
+        autocxx_inc: &[PathBuf],
+        extra_clang_args: &[&str],
+        codegen_options: &CodegenOptions,
+    ) {
+        let (include_cpps, inputs): (Vec<_>, Vec<_>) = self
+            .get_autocxxes_mut()
+            .filter_map(|include_cpp| {
+                let clang_args = clang_args_for_block(
+                    &include_cpp.get_mod_name(),
+                    extra_clang_args,
+                    codegen_options,
+                );
+                let inputs = include_cpp.bindgen_inputs_to_prefetch(
+                    autocxx_inc,
+                    &clang_args,
+                    codegen_options,
+                )?;
+                Some((include_cpp, inputs))
+            })
+            .unzip();
+        if inputs.len() < 2 {
+            return;
+        }
+        let outputs = bindgen_runner::run_in_parallel(inputs, codegen_options.bindgen_jobs);
+        for (include_cpp, output) in include_cpps.into_iter().zip(outputs) {
+            include_cpp.set_prefetched_bindings(output);
+        }
+    }
+}
+
+/// The clang arguments for the `include_cpp!` with the given mod name:
+/// those for every `include_cpp!`, then any just for this one.
+fn clang_args_for_block<'a>(
+    mod_name: &str,
+    extra_clang_args: &[&'a str],
+    codegen_options: &'a CodegenOptions,
+) -> Vec<&'a str> {
+    let mut clang_args = extra_clang_args.to_vec();
+    for (block, args) in &codegen_options.block_clang_args {
+        if block == mod_name {
+            clang_args.extend(args.iter().map(String::as_str));
+        }
+    }
+    clang_args
 }
 
 /// Shenanigans required to share the same RebuildDependencyRecorder
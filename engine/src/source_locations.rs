@@ -0,0 +1,240 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finding where in the user's headers an API was declared, so that we can
+//! say so when we can't generate bindings for it. bindgen doesn't tell us
+//! source locations, so we search the headers (other than system headers)
+//! which clang read for something which looks like the declaration. This
+//! is a best guess: it doesn't know about namespaces, for instance, so if
+//! two headers declare the same name we may pick the wrong one.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use indexmap::map::IndexMap as HashMap;
+use once_cell::unsync::OnceCell;
+use regex::Regex;
+
+use crate::system_headers::{canonicalize, user_header_dirs};
+
+/// Where something was declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The header, relative to the include directory in which it was
+    /// found.
+    pub file: String,
+    /// The line number, counting from one.
+    pub line: usize,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+struct Header {
+    /// The name to report.
+    name: String,
+    path: PathBuf,
+    contents: OnceCell<String>,
+}
+
+impl Header {
+    fn contents(&self) -> &str {
+        self.contents
+            .get_or_init(|| std::fs::read_to_string(&self.path).unwrap_or_default())
+    }
+}
+
+/// Where things seem to be declared, as the index of the header and the
+/// offset within it.
+#[derive(Default)]
+struct Declarations {
+    /// The first definition of each type (or typedef).
+    types: HashMap<String, (usize, usize)>,
+    /// Every declaration of each function or method, in order.
+    functions: HashMap<String, Vec<(usize, usize)>>,
+}
+
+/// Finds declarations in the user's headers. The headers are only read if
+/// we're asked to find something.
+pub(crate) struct SourceLocator {
+    headers: Vec<Header>,
+    declarations: OnceCell<Declarations>,
+}
+
+impl SourceLocator {
+    /// A locator searching those of the given headers which are within the
+    /// include directories (or given to clang with `-I`).
+    pub(crate) fn new(
+        dependencies: &[String],
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+    ) -> Self {
+        let user_dirs = user_header_dirs(inc_dirs, extra_clang_args);
+        let mut headers: Vec<Header> = Vec::new();
+        for dependency in dependencies {
+            let path = canonicalize(Path::new(dependency));
+            if headers.iter().any(|header| header.path == path) {
+                continue;
+            }
+            if let Some(name) = user_dirs
+                .iter()
+                .find_map(|dir| path.strip_prefix(dir).ok())
+                .and_then(|name| name.to_str())
+            {
+                headers.push(Header {
+                    name: name.replace('\\', "/"),
+                    path,
+                    contents: OnceCell::new(),
+                });
+            }
+        }
+        Self {
+            headers,
+            declarations: OnceCell::new(),
+        }
+    }
+
+    /// Reads all the headers, noting anything which looks like a
+    /// declaration.
+    fn declarations(&self) -> &Declarations {
+        self.declarations.get_or_init(|| {
+            let type_definition = Regex::new(
+                r"\b(?:(?:class|struct|union|enum(?:\s+class|\s+struct)?)\s+(?:\[\[[^\]]*\]\]\s*)?(?:\w+\s+)?(\w+)\s*(?:final\s*)?[:{]|using\s+(\w+)\s*=|typedef\b[^;{}]*?\b(\w+)\s*;)",
+            )
+            .unwrap();
+            let function_declaration = Regex::new(r"\b(\w+)\s*\(").unwrap();
+            let mut declarations = Declarations::default();
+            for (idx, header) in self.headers.iter().enumerate() {
+                let contents = header.contents();
+                for captures in type_definition.captures_iter(contents) {
+                    let name = (1..=3).find_map(|group| captures.get(group)).unwrap();
+                    declarations
+                        .types
+                        .entry(name.as_str().to_string())
+                        .or_insert((idx, captures.get(0).unwrap().start()));
+                }
+                for captures in function_declaration.captures_iter(contents) {
+                    let name = captures.get(1).unwrap();
+                    // Skip anything which looks like a call.
+                    let before = contents[..name.start()].trim_end();
+                    if [".", "->", "return", "=", "(", ","]
+                        .iter()
+                        .any(|call| before.ends_with(call))
+                    {
+                        continue;
+                    }
+                    declarations
+                        .functions
+                        .entry(name.as_str().to_string())
+                        .or_default()
+                        .push((idx, name.start()));
+                }
+            }
+            declarations
+        })
+    }
+
+    fn location(&self, (header, offset): (usize, usize)) -> SourceLocation {
+        let header = &self.headers[header];
+        SourceLocation {
+            file: header.name.clone(),
+            line: header.contents()[..offset].matches('\n').count() + 1,
+        }
+    }
+
+    /// Where the given type was defined.
+    pub(crate) fn locate_type(&self, name: &str) -> Option<SourceLocation> {
+        let found = *self.declarations().types.get(name)?;
+        Some(self.location(found))
+    }
+
+    /// Where the given function was declared.
+    pub(crate) fn locate_function(&self, name: &str) -> Option<SourceLocation> {
+        let found = *self.declarations().functions.get(name)?.first()?;
+        Some(self.location(found))
+    }
+
+    /// Where the given method was declared, or failing that (for instance,
+    /// because it's something we synthesized) where its type was defined.
+    pub(crate) fn locate_method(&self, self_ty: &str, method: &str) -> Option<SourceLocation> {
+        let declarations = self.declarations();
+        let (header, type_offset) = *declarations.types.get(self_ty)?;
+        let within_type = |method: &str| {
+            declarations
+                .functions
+                .get(method)?
+                .iter()
+                .find(|(idx, offset)| *idx == header && *offset >= type_offset)
+                .copied()
+        };
+        // Overloads get numbered, e.g. `frob1`.
+        let found = within_type(method)
+            .or_else(|| within_type(method.trim_end_matches(|c: char| c.is_ascii_digit())))
+            .unwrap_or((header, type_offset));
+        Some(self.location(found))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SourceLocation, SourceLocator};
+
+    #[test]
+    fn test_source_locator() {
+        let tdir = tempfile::tempdir().unwrap();
+        let header = tdir.path().join("mylib").join("widget.h");
+        std::fs::create_dir_all(header.parent().unwrap()).unwrap();
+        std::fs::write(
+            &header,
+            indoc::indoc! {"
+                #pragma once
+                struct Widget;
+                inline int helper() { return 3; }
+                class EXPORT Widget : public Base {
+                public:
+                    int count() const { return helper(); }
+                    void frob(int&& a);
+                    void frob(int a, int b);
+                };
+                typedef int Count;
+                inline void use_widget(const Widget& w) {}
+            "},
+        )
+        .unwrap();
+        let system_dir = tempfile::tempdir().unwrap();
+        let system_header = system_dir.path().join("vector");
+        std::fs::write(&system_header, "inline void use_widget();\n").unwrap();
+        let locator = SourceLocator::new(
+            &[
+                system_header.to_str().unwrap().to_string(),
+                header.to_str().unwrap().to_string(),
+            ],
+            &[tdir.path().to_path_buf()],
+            &[],
+        );
+        let at = |line| {
+            Some(SourceLocation {
+                file: "mylib/widget.h".to_string(),
+                line,
+            })
+        };
+        assert_eq!(locator.locate_type("Widget"), at(4));
+        assert_eq!(locator.locate_type("Count"), at(10));
+        assert_eq!(locator.locate_function("helper"), at(3));
+        assert_eq!(locator.locate_function("use_widget"), at(11));
+        assert_eq!(locator.locate_method("Widget", "frob"), at(7));
+        assert_eq!(locator.locate_method("Widget", "new"), at(4));
+        assert_eq!(locator.locate_type("Gadget"), None);
+        assert_eq!(at(7).unwrap().to_string(), "mylib/widget.h:7");
+    }
+}
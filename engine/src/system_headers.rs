@@ -0,0 +1,117 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Leaving system headers out of the header dependencies which we pass on
+//! to the build system. Clang reads thousands of them for a typical set
+//! of headers, and they rarely change, so watching them all just slows
+//! the build system down. We count as a system header any header which
+//! isn't within one of the directories we were given to search for
+//! headers: either the include directories passed to autocxx, or any
+//! given to clang with `-I` or `-iquote`.
+
+use std::path::{Path, PathBuf};
+
+use crate::RebuildDependencyRecorder;
+
+/// Passes on to another recorder those header dependencies which aren't
+/// system headers.
+#[derive(Debug)]
+pub(crate) struct SystemHeaderFilter {
+    inner: Box<dyn RebuildDependencyRecorder>,
+    user_dirs: Vec<PathBuf>,
+}
+
+impl SystemHeaderFilter {
+    pub(crate) fn new(
+        inner: Box<dyn RebuildDependencyRecorder>,
+        inc_dirs: &[PathBuf],
+        extra_clang_args: &[&str],
+    ) -> Self {
+        Self {
+            inner,
+            user_dirs: user_header_dirs(inc_dirs, extra_clang_args),
+        }
+    }
+}
+
+/// The canonical forms of the directories in which we were asked to search
+/// for headers, any headers within which aren't system headers.
+pub(crate) fn user_header_dirs(inc_dirs: &[PathBuf], extra_clang_args: &[&str]) -> Vec<PathBuf> {
+    let mut user_dirs: Vec<PathBuf> = inc_dirs.to_vec();
+    let mut args = extra_clang_args.iter();
+    while let Some(arg) = args.next() {
+        let dir = match ["-I", "-iquote"]
+            .iter()
+            .find_map(|flag| arg.strip_prefix(flag))
+        {
+            Some("") => args.next().copied(),
+            dir => dir,
+        };
+        user_dirs.extend(dir.map(PathBuf::from));
+    }
+    user_dirs.iter().map(|dir| canonicalize(dir)).collect()
+}
+
+/// The canonical form of a path, if there is one, so that we can tell
+/// whether one is within another.
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl RebuildDependencyRecorder for SystemHeaderFilter {
+    fn record_header_file_dependency(&self, filename: &str) {
+        let header = canonicalize(Path::new(filename));
+        if self.user_dirs.iter().any(|dir| header.starts_with(dir)) {
+            self.inner.record_header_file_dependency(filename);
+        } else {
+            log::info!("Not recording a dependency on system header {filename}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs, rc::Rc};
+
+    use super::SystemHeaderFilter;
+    use crate::RebuildDependencyRecorder;
+
+    #[derive(Debug, Default)]
+    struct Recorded(Rc<RefCell<Vec<String>>>);
+
+    impl RebuildDependencyRecorder for Recorded {
+        fn record_header_file_dependency(&self, filename: &str) {
+            self.0.borrow_mut().push(filename.to_string());
+        }
+    }
+
+    #[test]
+    fn test_system_header_filter() {
+        let tdir = tempfile::tempdir().unwrap();
+        let headers: Vec<_> = ["inc/a.h", "inc/nested/b.h", "extra/c.h", "sys/d.h"]
+            .into_iter()
+            .map(|header| {
+                let path = tdir.path().join(header);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, "#pragma once\n").unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let recorded = Rc::new(RefCell::new(Vec::new()));
+        let extra_dir = tdir.path().join("extra");
+        let filter = SystemHeaderFilter::new(
+            Box::new(Recorded(recorded.clone())),
+            &[tdir.path().join("inc")],
+            &["-std=c++17", "-I", extra_dir.to_str().unwrap()],
+        );
+        for header in &headers {
+            filter.record_header_file_dependency(header);
+        }
+        assert_eq!(*recorded.borrow(), headers[..3]);
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clang arguments derived from the Cargo environment, so that when we're
+//! cross-compiling, headers are parsed for the target rather than the host.
+//! Otherwise type sizes (`long`, `wchar_t`) and `#ifdef`s would silently come
+//! out as they are on the host.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Problems working out how to parse headers for the target.
+#[derive(Debug, Error)]
+pub enum TargetArgsError {
+    #[error("the sysroot {1} for target {0} doesn't exist")]
+    MissingSysroot(String, PathBuf),
+    #[error("target {0} needs the Android NDK's sysroot, but none was found: set ANDROID_NDK_HOME to the NDK, or SYSROOT_{0} to a sysroot, or pass --sysroot in the extra clang args")]
+    NoAndroidSysroot(String),
+}
+
+/// The clang target triple for a Rust target triple. These are mostly the
+/// same, but not always.
+pub(crate) fn rust_to_clang_target(rust_target: &str) -> String {
+    if let Some(rest) = rust_target.strip_prefix("aarch64-apple-") {
+        let rest = match rest.strip_suffix("-sim") {
+            Some(os) => format!("{os}-simulator"),
+            None => rest.to_string(),
+        };
+        return format!("arm64-apple-{rest}");
+    }
+    if let Some(os) = rust_target.strip_suffix("-espidf") {
+        let os = os.replacen("riscv32imc-", "riscv32-", 1);
+        return format!("{os}-elf");
+    }
+    // The RISC-V extensions (e.g. `gc` or `imac`) aren't part of the
+    // clang triple.
+    if let Some((arch, rest)) = rust_target.split_once('-') {
+        for base in ["riscv32", "riscv64"] {
+            if arch.starts_with(base) && arch != base {
+                return format!("{base}-{rest}");
+            }
+        }
+    }
+    rust_target.to_string()
+}
+
+/// Looks up an environment variable specific to the target, in the same
+/// way as the `cc` crate: `VAR_<target>`, then `VAR_<target_with_underscores>`,
+/// then `TARGET_VAR`.
+fn target_env_var(
+    env: &impl Fn(&str) -> Option<String>,
+    var: &str,
+    target: &str,
+) -> Option<String> {
+    env(&format!("{var}_{target}"))
+        .or_else(|| env(&format!("{var}_{}", target.replace('-', "_"))))
+        .or_else(|| env(&format!("TARGET_{var}")))
+}
+
+fn has_arg(args: &[String], flags: &[&str]) -> bool {
+    args.iter().any(|arg| {
+        flags
+            .iter()
+            .any(|flag| arg == flag || arg.starts_with(&format!("{flag}=")))
+    })
+}
+
+/// The sysroot within the Android NDK, which is the same whichever
+/// architecture the host is (the macOS one is called `darwin-x86_64` even
+/// on Apple silicon).
+fn android_ndk_sysroot(ndk: &Path) -> PathBuf {
+    let host_tag = match std::env::consts::OS {
+        "macos" => "darwin-x86_64",
+        "windows" => "windows-x86_64",
+        _ => "linux-x86_64",
+    };
+    ndk.join("toolchains/llvm/prebuilt")
+        .join(host_tag)
+        .join("sysroot")
+}
+
+/// The clang arguments needed to parse headers for the Cargo target, if
+/// it's not the host, given a way to read the environment and the extra
+/// clang arguments which the user gave us (which come after these and so
+/// override them). These are:
+/// * `BINDGEN_EXTRA_CLANG_ARGS` (or a target-specific version of it), as
+///   bindgen would use.
+/// * `--target`, unless the user gave one.
+/// * `--sysroot` from `SYSROOT_<target>` or the Android NDK, unless the user
+///   gave one.
+pub(crate) fn target_clang_args(
+    env: impl Fn(&str) -> Option<String>,
+    user_args: &[String],
+) -> Result<Vec<String>, TargetArgsError> {
+    let target = match env("TARGET") {
+        Some(target) => target,
+        None => return Ok(Vec::new()),
+    };
+    let mut args: Vec<String> = env(&format!("BINDGEN_EXTRA_CLANG_ARGS_{target}"))
+        .or_else(|| {
+            env(&format!(
+                "BINDGEN_EXTRA_CLANG_ARGS_{}",
+                target.replace('-', "_")
+            ))
+        })
+        .or_else(|| env("BINDGEN_EXTRA_CLANG_ARGS"))
+        .map(|args| args.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    if env("HOST").as_ref() == Some(&target) {
+        return Ok(args);
+    }
+    let all_args: Vec<_> = args.iter().chain(user_args).cloned().collect();
+    if has_arg(&all_args, &["--target", "-target"]) {
+        // They've set themselves up for cross-compilation already.
+        return Ok(args);
+    }
+    args.push(format!("--target={}", rust_to_clang_target(&target)));
+    if has_arg(&all_args, &["--sysroot", "-isysroot"]) {
+        return Ok(args);
+    }
+    let sysroot = match target_env_var(&env, "SYSROOT", &target) {
+        Some(sysroot) => Some(PathBuf::from(sysroot)),
+        None if target.contains("-android") => {
+            let ndk = ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "ANDROID_NDK"]
+                .into_iter()
+                .find_map(&env)
+                .ok_or_else(|| TargetArgsError::NoAndroidSysroot(target.clone()))?;
+            Some(android_ndk_sysroot(Path::new(&ndk)))
+        }
+        None => None,
+    };
+    if let Some(sysroot) = sysroot {
+        if !sysroot.is_dir() {
+            return Err(TargetArgsError::MissingSysroot(target, sysroot));
+        }
+        args.push(format!("--sysroot={}", sysroot.to_str().unwrap()));
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rust_to_clang_target, target_clang_args, TargetArgsError};
+
+    #[test]
+    fn test_rust_to_clang_target() {
+        assert_eq!(
+            rust_to_clang_target("aarch64-unknown-linux-gnu"),
+            "aarch64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            rust_to_clang_target("aarch64-linux-android"),
+            "aarch64-linux-android"
+        );
+        assert_eq!(rust_to_clang_target("aarch64-apple-ios"), "arm64-apple-ios");
+        assert_eq!(
+            rust_to_clang_target("aarch64-apple-ios-sim"),
+            "arm64-apple-ios-simulator"
+        );
+        assert_eq!(
+            rust_to_clang_target("riscv64gc-unknown-linux-gnu"),
+            "riscv64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            rust_to_clang_target("riscv32imac-unknown-none-elf"),
+            "riscv32-unknown-none-elf"
+        );
+        assert_eq!(
+            rust_to_clang_target("riscv32imc-esp-espidf"),
+            "riscv32-esp-elf"
+        );
+    }
+
+    #[test]
+    fn test_target_clang_args() {
+        let ndk = tempfile::tempdir().unwrap();
+        let sysroot = super::android_ndk_sysroot(ndk.path());
+        std::fs::create_dir_all(&sysroot).unwrap();
+        let vars = [
+            ("HOST", "x86_64-unknown-linux-gnu"),
+            ("ANDROID_NDK_HOME", ndk.path().to_str().unwrap()),
+            ("SYSROOT_aarch64_unknown_linux_gnu", "/no/such/sysroot"),
+            (
+                "BINDGEN_EXTRA_CLANG_ARGS_x86_64-pc-windows-msvc",
+                "-DFOO -DBAR",
+            ),
+        ];
+        let args = |target: &str, user_args: &[&str]| {
+            let env = |var: &str| match var {
+                "TARGET" => Some(target.to_string()),
+                _ => vars
+                    .iter()
+                    .find(|(name, _)| *name == var)
+                    .map(|(_, value)| value.to_string()),
+            };
+            let user_args: Vec<_> = user_args.iter().map(|arg| arg.to_string()).collect();
+            target_clang_args(env, &user_args)
+        };
+        assert!(args("x86_64-unknown-linux-gnu", &[]).unwrap().is_empty());
+        assert_eq!(
+            args("aarch64-linux-android", &[]).unwrap(),
+            [
+                "--target=aarch64-linux-android".to_string(),
+                format!("--sysroot={}", sysroot.to_str().unwrap())
+            ]
+        );
+        assert_eq!(
+            args("x86_64-pc-windows-msvc", &[]).unwrap(),
+            ["-DFOO", "-DBAR", "--target=x86_64-pc-windows-msvc"]
+        );
+        assert!(matches!(
+            args("aarch64-unknown-linux-gnu", &[]),
+            Err(TargetArgsError::MissingSysroot(..))
+        ));
+        assert_eq!(
+            args("aarch64-unknown-linux-gnu", &["--sysroot=/elsewhere"]).unwrap(),
+            ["--target=aarch64-unknown-linux-gnu"]
+        );
+        assert!(args(
+            "aarch64-unknown-linux-gnu",
+            &["-target", "aarch64-linux-gnu"]
+        )
+        .unwrap()
+        .is_empty());
+        std::fs::remove_dir_all(&sysroot).unwrap();
+        assert!(matches!(
+            args("aarch64-linux-android", &[]),
+            Err(TargetArgsError::MissingSysroot(..))
+        ));
+    }
+}
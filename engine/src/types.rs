@@ -22,7 +22,7 @@ pub(crate) fn make_ident<S: AsRef<str>>(id: S) -> Ident {
 }
 
 /// Newtype wrapper for a C++ namespace.
-#[derive(Debug, PartialEq, PartialOrd, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Hash, Clone)]
 #[allow(clippy::rc_buffer)]
 pub struct Namespace(Arc<Vec<String>>);
 
@@ -58,6 +58,22 @@ impl Namespace {
     pub(crate) fn to_cpp_path(&self) -> String {
         self.0.join("::")
     }
+
+    /// The namespace in which C++ can refer to things declared in this one.
+    /// That's different if this is, or is within, an anonymous namespace
+    /// (which bindgen calls `_bindgen_mod_...`), since the contents of an
+    /// anonymous namespace can be named as if they were in the enclosing
+    /// namespace.
+    #[must_use]
+    pub(crate) fn without_anonymous_namespaces(&self) -> Self {
+        Namespace(Arc::new(
+            self.0
+                .iter()
+                .filter(|segment| !segment.starts_with("_bindgen_mod"))
+                .cloned()
+                .collect(),
+        ))
+    }
 }
 
 impl Display for Namespace {
@@ -86,7 +102,7 @@ impl<'a> IntoIterator for &'a Namespace {
 /// either. It doesn't directly have functionality to convert
 /// from one to the other; `replace_type_path_without_arguments`
 /// does that.
-#[derive(PartialEq, PartialOrd, Eq, Hash, Clone)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Hash, Clone)]
 pub struct QualifiedName(Namespace, String);
 
 impl QualifiedName {
@@ -140,7 +156,7 @@ impl QualifiedName {
 
     /// Return the actual type name, without any namespace
     /// qualification. Avoid unless you have a good reason.
-    pub(crate) fn get_final_item(&self) -> &str {
+    pub fn get_final_item(&self) -> &str {
         &self.1
     }
 
@@ -170,7 +186,7 @@ impl QualifiedName {
     }
 
     /// Output the fully-qualified C++ name of this type.
-    pub(crate) fn to_cpp_name(&self) -> String {
+    pub fn to_cpp_name(&self) -> String {
         let special_cpp_name = known_types().special_cpp_name(self);
         match special_cpp_name {
             Some(name) => name,
@@ -204,7 +220,7 @@ impl QualifiedName {
     }
 
     /// Iterator over segments in the namespace of this name.
-    pub(crate) fn ns_segment_iter(&self) -> impl Iterator<Item = &String> {
+    pub fn ns_segment_iter(&self) -> impl Iterator<Item = &String> {
         self.0.iter()
     }
 
@@ -238,16 +254,19 @@ impl std::fmt::Debug for QualifiedName {
 pub enum InvalidIdentError {
     #[error("Union are not supported by autocxx (and their bindgen names have __ so are not acceptable to cxx)")]
     Union,
-    #[error("Bitfields are not supported by autocxx (and their bindgen names have __ so are not acceptable to cxx)")]
+    #[error("bindgen's storage type for bit-fields has __ in its name so is not acceptable to cxx. Bit-fields can only be used via the accessor methods of POD types.")]
     Bitfield,
     #[error("Names containing __ are reserved by C++ so not acceptable to cxx")]
     TooManyUnderscores,
-    #[error("bindgen decided to call this type _bindgen_ty_N because it couldn't deduce the correct name for it. That means we can't generate C++ bindings to it.")]
+    #[error("bindgen decided to call this type _bindgen_ty_N because it couldn't deduce the correct name for it. That means we can't generate C++ bindings to it. Giving it a name, or a typedef, in the C++ would fix this.")]
     BindgenTy,
     #[error("The item name '{0}' is a reserved word in Rust.")]
     ReservedName(String),
 }
 
+/// The type in which bindgen stores a struct's bit-fields.
+pub(crate) const BITFIELD_UNIT: &str = "__BindgenBitfieldUnit";
+
 const DOUBLE_UNDERSCORE_ALLOWLIST: [&str; 1] = ["pxrReserved"];
 
 /// cxx doesn't allow identifiers containing __. These are OK elsewhere
@@ -258,18 +277,18 @@ const DOUBLE_UNDERSCORE_ALLOWLIST: [&str; 1] = ["pxrReserved"];
 pub fn validate_ident_ok_for_cxx(id: &str) -> Result<(), InvalidIdentError> {
     validate_ident_ok_for_rust(id)?;
     // Provide a couple of more specific diagnostics if we can.
-    if id.starts_with("__BindgenBitfieldUnit") {
+    if id.starts_with(BITFIELD_UNIT) {
         Err(InvalidIdentError::Bitfield)
     } else if id.starts_with("__BindgenUnionField") {
         Err(InvalidIdentError::Union)
+    } else if id.starts_with("_bindgen_ty_") || id.contains("__bindgen_ty_") {
+        Err(InvalidIdentError::BindgenTy)
     } else if id.contains("__")
         && !DOUBLE_UNDERSCORE_ALLOWLIST
             .iter()
             .any(|allowlist| id.contains(allowlist))
     {
         Err(InvalidIdentError::TooManyUnderscores)
-    } else if id.starts_with("_bindgen_ty_") {
-        Err(InvalidIdentError::BindgenTy)
     } else {
         Ok(())
     }
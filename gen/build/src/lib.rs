@@ -13,6 +13,7 @@ use indexmap::set::IndexSet as HashSet;
 use std::{io::Write, sync::Mutex};
 
 pub type Builder = autocxx_engine::Builder<'static, CargoBuilderContext>;
+pub use autocxx_engine::{ApiDecision, ApiKind, ApiSummary, CppStandard, HeaderInclusion};
 
 #[doc(hidden)]
 pub struct CargoBuilderContext;
@@ -26,6 +27,9 @@ impl BuilderContext for CargoBuilderContext {
     fn get_dependency_recorder() -> Option<Box<dyn RebuildDependencyRecorder>> {
         Some(Box::new(CargoRebuildDependencyRecorder::new()))
     }
+    fn warn(message: &str) {
+        println!("cargo:warning={message}");
+    }
 }
 
 #[derive(Debug)]
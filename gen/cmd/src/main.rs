@@ -11,8 +11,8 @@
 mod depfile;
 
 use autocxx_engine::{
-    generate_rs_archive, generate_rs_single, get_cxx_header_bytes, parse_file,
-    AutocxxgenHeaderNamer, CxxgenHeaderNamer, RebuildDependencyRecorder,
+    generate_rs_archive, generate_rs_single, generate_rs_single_pretty, get_cxx_header_bytes,
+    parse_file, AutocxxgenHeaderNamer, CxxgenHeaderNamer, RebuildDependencyRecorder,
 };
 use clap::{crate_authors, crate_version, Arg, ArgGroup, Command};
 use depfile::Depfile;
@@ -82,6 +82,20 @@ opened will be used.
 
 This teaches rustc (and the autocxx macro) that all the different Rust bindings
 for multiple different autocxx macros have been archived into this single file.
+
+If you want to check the generated Rust bindings into source control, use
+  --stable-output
+which emits APIs in an order determined only by their names, and (with
+--gen-rs-include) pretty-prints the Rust so its formatting doesn't vary.
+You may additionally ask for parts of the Rust bindings to be gated behind
+Cargo features with
+  --cfg-feature ITEM=FEATURE
+where ITEM is named in a generate!, generate_pod! or subclass! directive.
+The Rust bindings needed for ITEM (and not for any ungated item) are then
+wrapped in #[cfg(feature = \"FEATURE\")]. If '=FEATURE' is omitted, the feature
+has the same name as the item. The C++ bindings are never gated, so for
+subclass! items the generated C++ must only be linked when the feature is
+enabled.
 ";
 
 fn main() -> miette::Result<()> {
@@ -176,6 +190,18 @@ fn main() -> miette::Result<()> {
                 .help("prefix for symbols to be exported from C++ bindings, e.g. __attribute__ ((visibility (\"default\")))")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("annotate-cpp")
+                .long("annotate-cpp")
+                .help("precede each piece of generated C++ with a comment naming the C++ API it's for, to help diagnose compile errors")
+        )
+        .arg(
+            Arg::new("cpp-shards")
+                .long("cpp-shards")
+                .value_name("N")
+                .help("split the C++ generated by autocxx for each include_cpp! into up to N .cc files, so they can be compiled in parallel. These count towards the number of files allowed by --generate-exact.")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("generate-cxx-h")
                 .long("generate-cxx-h")
@@ -195,6 +221,46 @@ fn main() -> miette::Result<()> {
                 .help("prefix for path to cxxgen.h (which we generate into the output directory) within #include statements. Must end in /")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("autocxxgen-h-path")
+                .long("autocxxgen-h-path")
+                .value_name("PREFIX")
+                .help("prefix for path to autocxxgen.h (which we generate into the output directory) within #include statements. Must end in /")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("original-headers-path")
+                .long("original-headers-path")
+                .value_name("PREFIX")
+                .help("prefix for path to the headers listed in include_cpp! within #include statements in generated C++. Must end in /")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("angle-bracket-includes")
+                .long("angle-bracket-includes")
+                .help("include the headers listed in include_cpp! from generated C++ using #include <...> rather than #include \"...\"")
+        )
+        .arg(
+            Arg::new("max-cpp-standard")
+                .long("max-cpp-standard")
+                .value_name("STANDARD")
+                .help("latest C++ standard (c++14, c++17 or c++20) which the generated C++ may rely upon. Functions which would need a later one are skipped.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("stable-output")
+                .long("stable-output")
+                .help("Generate APIs in a deterministic order and pretty-print the Rust, such that the output is suitable for checking into source control.")
+        )
+        .arg(
+            Arg::new("cfg-feature")
+                .long("cfg-feature")
+                .multiple_occurrences(true)
+                .number_of_values(1)
+                .value_name("ITEM[=FEATURE]")
+                .help("Gate the Rust bindings for an item from a generate! or subclass! directive behind a Cargo feature")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("depfile")
                 .long("depfile")
@@ -202,6 +268,43 @@ fn main() -> miette::Result<()> {
                 .help("A .d file to write")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("skip-system-header-deps")
+                .long("skip-system-header-deps")
+                .help("leave out of the .d file any headers which aren't within one of the --inc directories (or given to clang with -I or -iquote)")
+                .requires("depfile"),
+        )
+        .arg(
+            Arg::new("suppress-ignored-api-stubs")
+                .long("suppress-ignored-api-stubs")
+                .help("don't generate deprecated stubs for the functions, methods and types which couldn't be generated, explaining why"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("fail if autocxx fails unexpectedly while processing any one declaration, rather than ignoring that declaration"),
+        )
+        .arg(
+            Arg::new("bindgen-jobs")
+                .long("bindgen-jobs")
+                .value_name("N")
+                .help("run bindgen for up to N of the include_cpp!s in each input file at once")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .value_name("PATH")
+                .help("write a JSON report of every C++ API discovered, saying whether bindings were generated for it and if not, why not")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("cache-dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help("A directory in which to cache generated bindings, which are reused if no contributing header has changed")
+                .takes_value(true),
+        )
         .arg(
             Arg::new("clang-args")
                 .last(true)
@@ -251,9 +354,40 @@ fn main() -> miette::Result<()> {
         path_to_cxxgen_h: get_option_string("cxxgen-h-path", &matches),
         autocxxgen_header_namer,
         cxxgen_header_namer,
+        cpp_shards: matches
+            .value_of("cpp-shards")
+            .map(|s| s.parse::<usize>().unwrap())
+            .unwrap_or_default(),
+        annotate_cpp: matches.is_present("annotate-cpp"),
+        path_to_original_headers: get_option_string("original-headers-path", &matches),
+        angle_bracket_includes: matches.is_present("angle-bracket-includes"),
+        header_inclusion_mapper: Default::default(),
+        path_to_autocxxgen_h: get_option_string("autocxxgen-h-path", &matches),
+        max_cpp_standard: matches
+            .value_of("max-cpp-standard")
+            .map(|s| s.parse().unwrap()),
     };
+    let stable_output = matches.is_present("stable-output");
+    let cfg_features = matches
+        .values_of("cfg-feature")
+        .unwrap_or_default()
+        .map(|arg| match arg.split_once('=') {
+            Some((item, feature)) => (item.to_string(), feature.to_string()),
+            None => (arg.to_string(), arg.to_string()),
+        })
+        .collect();
     let codegen_options = autocxx_engine::CodegenOptions {
         cpp_codegen_options,
+        stable_output,
+        cfg_features,
+        cache_dir: matches.value_of_os("cache-dir").map(PathBuf::from),
+        bindgen_jobs: matches
+            .value_of("bindgen-jobs")
+            .map(|s| s.parse::<usize>().unwrap())
+            .unwrap_or_default(),
+        skip_system_header_dependencies: matches.is_present("skip-system-header-deps"),
+        suppress_ignored_api_stubs: matches.is_present("suppress-ignored-api-stubs"),
+        strict: matches.is_present("strict"),
         ..Default::default()
     };
     let depfile = match matches.value_of("depfile") {
@@ -290,6 +424,10 @@ fn main() -> miette::Result<()> {
         )?;
     }
 
+    if let Some(report) = matches.value_of_os("report") {
+        autocxx_engine::write_api_report(Path::new(report), &parsed_files).into_diagnostic()?;
+    }
+
     // Finally start to write the C++ and Rust out.
     let outdir: PathBuf = matches.value_of_os("outdir").unwrap().into();
 
@@ -356,7 +494,11 @@ fn main() -> miette::Result<()> {
             .iter()
             .flat_map(|parsed_file| parsed_file.get_rs_outputs());
         for include_cxx in rust_buildables {
-            let rs_code = generate_rs_single(include_cxx);
+            let rs_code = if stable_output {
+                generate_rs_single_pretty(include_cxx)
+            } else {
+                generate_rs_single(include_cxx)
+            };
             let fname = if matches.is_present("fix-rs-include-name") {
                 name_include_rs(counter)
             } else {
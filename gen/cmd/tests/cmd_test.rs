@@ -278,6 +278,38 @@ fn test_gen_fixed_num() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_depfile_lists_transitive_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_dir = tempdir()?;
+    let depfile = tmp_dir.path().join("test.d");
+    let mut files = HashMap::new();
+    files.insert("main.rs", MAIN_RS.as_bytes());
+    files.insert(
+        "input.h",
+        "#pragma once\n#include \"level2.h\"\n".as_bytes(),
+    );
+    files.insert(
+        "level2.h",
+        "#pragma once\n#include \"level3.h\"\n".as_bytes(),
+    );
+    files.insert("level3.h", INPUT_H.as_bytes());
+    base_test_ex(
+        &tmp_dir,
+        RsGenMode::Single,
+        |cmd| {
+            cmd.arg("--depfile")
+                .arg(&depfile)
+                .arg("--skip-system-header-deps");
+        },
+        files,
+        vec!["main.rs"],
+    )?;
+    let depfile = std::fs::read_to_string(depfile)?;
+    assert!(depfile.contains("level3.h"));
+    assert!(!depfile.contains("sstream"));
+    Ok(())
+}
+
 #[test]
 fn test_gen_preprocess() -> Result<(), Box<dyn std::error::Error>> {
     let tmp_dir = tempdir()?;
@@ -306,6 +338,28 @@ fn test_gen_repro() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_gen_stable_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let tmp_dir = tempdir()?;
+        base_test(&tmp_dir, RsGenMode::Single, |cmd| {
+            cmd.arg("--generate-exact")
+                .arg("1")
+                .arg("--fix-rs-include-name")
+                .arg("--stable-output")
+                .arg("--cfg-feature")
+                .arg("Goat=goats");
+        })?;
+        assert_contains(&tmp_dir, "gen0.include.rs", "#[cfg(feature = \"goats\")]");
+        outputs.push(std::fs::read_to_string(
+            tmp_dir.path().join("gen0.include.rs"),
+        )?);
+    }
+    assert_eq!(outputs[0], outputs[1]);
+    Ok(())
+}
+
 fn write_to_file(dir: &Path, filename: &str, content: &[u8]) {
     let path = dir.join(filename);
     let mut f = File::create(path).expect("Unable to create file");
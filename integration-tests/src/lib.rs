@@ -7,6 +7,7 @@
 // except according to those terms.
 
 use std::{
+    cell::RefCell,
     ffi::OsStr,
     fs::File,
     io::{Read, Write},
@@ -405,6 +406,11 @@ pub fn do_run_test(
     )
 }
 
+thread_local! {
+    /// Warnings reported by builds on this thread.
+    static BUILD_WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
 /// The [`BuilderContext`] used in autocxx's integration tests.
 pub struct TestBuilderContext;
 
@@ -412,6 +418,16 @@ impl BuilderContext for TestBuilderContext {
     fn get_dependency_recorder() -> Option<Box<dyn RebuildDependencyRecorder>> {
         None
     }
+
+    fn warn(message: &str) {
+        BUILD_WARNINGS.with(|warnings| warnings.borrow_mut().push(message.to_string()));
+    }
+}
+
+/// Returns the warnings reported by builds on this thread since this was
+/// last called.
+pub fn take_build_warnings() -> Vec<String> {
+    BUILD_WARNINGS.with(|warnings| std::mem::take(&mut *warnings.borrow_mut()))
 }
 
 pub fn do_run_test_manual(
@@ -447,8 +463,11 @@ pub fn do_run_test_manual(
     let rs_path = write_rust_to_file(&rust_code);
 
     info!("Path is {:?}", tdir.path());
+    // Run bindgen in parallel, so that tests with several `include_cpp!`s
+    // cover that too.
     let builder = Builder::<TestBuilderContext>::new(&rs_path, [tdir.path()])
-        .custom_gendir(target_dir.clone());
+        .custom_gendir(target_dir.clone())
+        .bindgen_jobs(4);
     let builder = if let Some(builder_modifier) = &builder_modifier {
         builder_modifier.modify_autocxx_builder(builder)
     } else {
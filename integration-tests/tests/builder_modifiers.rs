@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use autocxx_engine::Builder;
+use autocxx_engine::{ApiDecision, ApiSummary, Builder, CppStandard, HeaderInclusion};
 
 use autocxx_integration_tests::{BuilderModifier, BuilderModifierFns, TestBuilderContext};
 
@@ -70,3 +70,159 @@ impl BuilderModifierFns for EnableAutodiscover {
         builder.auto_allowlist(true)
     }
 }
+
+pub(crate) struct SetFallibleByDefault;
+
+impl BuilderModifierFns for SetFallibleByDefault {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.fallible_by_default(true)
+    }
+}
+
+pub(crate) struct SetFailOnIgnored;
+
+impl BuilderModifierFns for SetFailOnIgnored {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.fail_on_ignored(true)
+    }
+}
+
+pub(crate) struct SetSuppressIgnoredApiStubs;
+
+impl BuilderModifierFns for SetSuppressIgnoredApiStubs {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.suppress_ignored_api_stubs(true)
+    }
+}
+
+pub(crate) struct SetCppShards(pub(crate) usize);
+
+impl BuilderModifierFns for SetCppShards {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.cpp_shards(self.0)
+    }
+}
+
+pub(crate) struct SetAnnotateCpp;
+
+impl BuilderModifierFns for SetAnnotateCpp {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.annotate_generated_cpp(true)
+    }
+}
+
+pub(crate) struct SetAngleBracketIncludes(pub(crate) &'static str);
+
+impl BuilderModifierFns for SetAngleBracketIncludes {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder
+            .angle_bracket_includes(true)
+            .path_to_original_headers(self.0)
+    }
+}
+
+pub(crate) struct MapHeaderInclusions(pub(crate) fn(&str) -> Option<HeaderInclusion>);
+
+impl BuilderModifierFns for MapHeaderInclusions {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.map_header_inclusions(self.0)
+    }
+}
+
+pub(crate) struct SetApiFilter(pub(crate) fn(&ApiSummary) -> ApiDecision);
+
+impl BuilderModifierFns for SetApiFilter {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.api_filter(self.0)
+    }
+}
+
+pub(crate) struct SetCxxImplAnnotations(pub(crate) &'static str);
+
+impl BuilderModifierFns for SetCxxImplAnnotations {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.cxx_impl_annotations(Some(self.0.to_string()))
+    }
+}
+
+/// Parses the headers as C++17, but limits the generated C++ to the given
+/// standard.
+pub(crate) struct SetMaxCppStandard(pub(crate) CppStandard);
+
+impl BuilderModifierFns for SetMaxCppStandard {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder
+            .extra_clang_args(&["-std=c++17"])
+            .max_cpp_standard(self.0)
+    }
+}
+
+pub(crate) struct SetPrettyPrintRs;
+
+impl BuilderModifierFns for SetPrettyPrintRs {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder.pretty_print_rs(true)
+    }
+}
+
+/// Writes an extra header into its own directory, which is added to the
+/// include path used for parsing (but not for building) the C++.
+pub(crate) struct ExtraHeader {
+    dir: tempfile::TempDir,
+    fail_on_dropped_headers: bool,
+}
+
+impl ExtraHeader {
+    pub(crate) fn new(name: &str, contents: &str, fail_on_dropped_headers: bool) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(name), contents).unwrap();
+        Self {
+            dir,
+            fail_on_dropped_headers,
+        }
+    }
+}
+
+impl BuilderModifierFns for ExtraHeader {
+    fn modify_autocxx_builder<'a>(
+        &self,
+        builder: Builder<'a, TestBuilderContext>,
+    ) -> Builder<'a, TestBuilderContext> {
+        builder
+            .extra_clang_args(&[&format!("-I{}", self.dir.path().display())])
+            .fail_on_dropped_headers(self.fail_on_dropped_headers)
+    }
+}
@@ -17,7 +17,7 @@ use proc_macro2::TokenStream;
 use quote::ToTokens;
 use syn::Item;
 
-use autocxx_integration_tests::{CodeChecker, CodeCheckerFns, TestError};
+use autocxx_integration_tests::{take_build_warnings, CodeChecker, CodeCheckerFns, TestError};
 
 /// Generates a closure which can be used to ensure that the given symbol
 /// is mentioned in the output and has documentation attached.
@@ -100,6 +100,28 @@ pub(crate) fn make_string_finder(error_texts: Vec<String>) -> CodeChecker {
     Box::new(StringFinder(error_texts))
 }
 
+struct WarningFinder(Vec<String>);
+
+impl CodeCheckerFns for WarningFinder {
+    fn check_rust(&self, _rs: syn::File) -> Result<(), TestError> {
+        let warnings = take_build_warnings();
+        for msg in &self.0 {
+            if !warnings.iter().any(|warning| warning.contains(msg)) {
+                return Err(TestError::RsCodeExaminationFail(format!(
+                    "Couldn't find warning '{msg}' in {warnings:?}"
+                )));
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Returns a code checker which ensures that the build warned about each
+/// of the given strings
+pub(crate) fn make_warning_finder(warning_texts: Vec<String>) -> CodeChecker {
+    Box::new(WarningFinder(warning_texts))
+}
+
 struct RustCodeFinder(Vec<TokenStream>);
 
 impl CodeCheckerFns for RustCodeFinder {
@@ -159,6 +181,39 @@ impl<'a> CodeCheckerFns for CppMatcher<'a> {
     }
 }
 
+/// Checks that the C++ which autocxx generates has been split into at
+/// least `min_shards` .cxx files, and that each of the given snippets
+/// appears in exactly one of them.
+pub(crate) struct CppShardsChecker {
+    pub(crate) min_shards: usize,
+    pub(crate) snippets: Vec<String>,
+}
+
+impl CodeCheckerFns for CppShardsChecker {
+    fn check_cpp(&self, cpp: &[PathBuf]) -> Result<(), TestError> {
+        let shards: Vec<String> = cpp
+            .iter()
+            .filter(|filename| filename.extension().is_some_and(|ext| ext == "cxx"))
+            .map(|filename| std::fs::read_to_string(filename).unwrap())
+            .filter(|contents| contents.starts_with("#include \"autocxxgen_"))
+            .collect();
+        if shards.len() < self.min_shards {
+            return Err(TestError::CppCodeExaminationFail);
+        }
+        for snippet in &self.snippets {
+            if shards
+                .iter()
+                .filter(|shard| shard.contains(snippet))
+                .count()
+                != 1
+            {
+                return Err(TestError::CppCodeExaminationFail);
+            }
+        }
+        Ok(())
+    }
+}
+
 pub(crate) struct NoSystemHeadersChecker;
 
 impl CodeCheckerFns for NoSystemHeadersChecker {
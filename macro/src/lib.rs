@@ -64,6 +64,15 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
     } else {
         None
     };
+    let peer_destroyed_bit = if subclass_attrs.on_peer_destroyed {
+        Some(quote! {
+            fn notify_peer_destroyed(&mut self) {
+                autocxx::subclass::CppSubclassPeerDestroyed::<ffi::#cpp_ident>::on_peer_destroyed(self)
+            }
+        })
+    } else {
+        None
+    };
     let toks = quote! {
         #s
 
@@ -74,6 +83,7 @@ pub fn subclass(attr: TokenStream, item: TokenStream) -> TokenStream {
             fn peer_holder(&self) -> &autocxx::subclass::CppSubclassCppPeerHolder<ffi::#cpp_ident> {
                 &self.cpp_peer
             }
+            #peer_destroyed_bit
         }
 
         #self_owned_bit
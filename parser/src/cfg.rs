@@ -0,0 +1,145 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Evaluation of `#[cfg]` attributes on `include_cpp!` and its directives,
+//! so that we don't generate bindings which won't be built for this target.
+
+use syn::{punctuated::Punctuated, token::Comma, Attribute, Expr, Lit, Meta};
+
+/// Whether all the `#[cfg]` attributes in this list are satisfied for the
+/// target we're building. In a build script, cargo tells us about the
+/// target and enabled features by environment variables. Elsewhere (for
+/// instance when the `include_cpp!` macro itself runs, or when
+/// `autocxx-gen` is run by some other build system) we can't tell, so we
+/// assume everything is enabled, and rely on the same `#[cfg]` being
+/// applied to the Rust code.
+pub fn is_cfg_enabled(attrs: &[Attribute]) -> bool {
+    cfg_enabled_with(attrs, &cargo_cfg_lookup)
+}
+
+fn cfg_enabled_with(
+    attrs: &[Attribute],
+    lookup: &dyn Fn(&str, Option<&str>) -> Option<bool>,
+) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .all(|attr| match attr.parse_args::<Meta>() {
+            Ok(predicate) => evaluate(&predicate, lookup).unwrap_or(true),
+            Err(_) => true,
+        })
+}
+
+/// Evaluate a cfg predicate. `None` means we don't know.
+fn evaluate(predicate: &Meta, lookup: &dyn Fn(&str, Option<&str>) -> Option<bool>) -> Option<bool> {
+    match predicate {
+        Meta::Path(path) => lookup(&path.get_ident()?.to_string(), None),
+        Meta::NameValue(nv) => match &nv.value {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Str(value) => lookup(&nv.path.get_ident()?.to_string(), Some(&value.value())),
+                _ => None,
+            },
+            _ => None,
+        },
+        Meta::List(list) => {
+            let args: Vec<Option<bool>> = list
+                .parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)
+                .ok()?
+                .iter()
+                .map(|arg| evaluate(arg, lookup))
+                .collect();
+            if list.path.is_ident("all") {
+                if args.contains(&Some(false)) {
+                    Some(false)
+                } else if args.contains(&None) {
+                    None
+                } else {
+                    Some(true)
+                }
+            } else if list.path.is_ident("any") {
+                if args.contains(&Some(true)) {
+                    Some(true)
+                } else if args.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            } else if list.path.is_ident("not") {
+                match args.as_slice() {
+                    [arg] => arg.map(|arg| !arg),
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// cargo passes `CARGO_FEATURE_<NAME>` for each enabled feature, and
+/// `CARGO_CFG_<NAME>` (with comma-separated values, if any) for each
+/// cfg set for the target, to build scripts.
+fn cargo_cfg_lookup(name: &str, value: Option<&str>) -> Option<bool> {
+    std::env::var_os("CARGO_CFG_TARGET_OS")?;
+    let var_name =
+        |prefix: &str, name: &str| format!("{prefix}{}", name.to_uppercase().replace('-', "_"));
+    match (name, value) {
+        ("feature", Some(feature)) => {
+            Some(std::env::var_os(var_name("CARGO_FEATURE_", feature)).is_some())
+        }
+        (_, None) => Some(std::env::var_os(var_name("CARGO_CFG_", name)).is_some()),
+        (_, Some(value)) => Some(
+            std::env::var(var_name("CARGO_CFG_", name))
+                .map(|values| values.split(',').any(|v| v == value))
+                .unwrap_or(false),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cfg_enabled_with;
+    use syn::{parse_quote, Attribute};
+
+    fn linux_with_gpu(name: &str, value: Option<&str>) -> Option<bool> {
+        match (name, value) {
+            ("feature", Some(feature)) => Some(feature == "gpu"),
+            ("target_os", Some(os)) => Some(os == "linux"),
+            ("unix", None) => Some(true),
+            ("windows", None) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn enabled(attr: Attribute) -> bool {
+        cfg_enabled_with(&[attr], &linux_with_gpu)
+    }
+
+    #[test]
+    fn test_cfg_evaluation() {
+        assert!(enabled(parse_quote! { #[cfg(target_os = "linux")] }));
+        assert!(!enabled(parse_quote! { #[cfg(target_os = "windows")] }));
+        assert!(enabled(parse_quote! { #[cfg(unix)] }));
+        assert!(!enabled(parse_quote! { #[cfg(not(unix))] }));
+        assert!(enabled(parse_quote! { #[cfg(feature = "gpu")] }));
+        assert!(!enabled(
+            parse_quote! { #[cfg(all(unix, feature = "cuda"))] }
+        ));
+        assert!(enabled(
+            parse_quote! { #[cfg(any(windows, feature = "gpu"))] }
+        ));
+        // Things we can't evaluate don't stop us generating bindings.
+        assert!(enabled(parse_quote! { #[cfg(panic = "unwind")] }));
+        assert!(enabled(parse_quote! { #[cfg(not(panic = "unwind"))] }));
+        assert!(!enabled(
+            parse_quote! { #[cfg(all(windows, panic = "unwind"))] }
+        ));
+        // Other attributes are irrelevant.
+        assert!(enabled(parse_quote! { #[doc = "hello"] }));
+    }
+}
@@ -25,7 +25,9 @@ use syn::{
 use syn::{Ident, Result as ParseResult};
 use thiserror::Error;
 
-use crate::{directives::get_directives, RustPath};
+use crate::{
+    cfg::is_cfg_enabled, directives::get_directives, name_pattern, template_name, RustPath,
+};
 
 use quote::quote;
 
@@ -94,16 +96,74 @@ impl UnsafePolicy {
 #[derive(Hash, Debug)]
 pub enum AllowlistEntry {
     Item(String),
-    Namespace(String),
+    Namespace {
+        ns: String,
+        /// Nested namespaces which should not be generated.
+        exclude: Vec<String>,
+        /// Whether types in this namespace should be POD wherever
+        /// possible, as requested by `generate_ns_pod!`.
+        pod: bool,
+    },
 }
 
 impl AllowlistEntry {
     fn to_bindgen_item(&self) -> String {
         match self {
             AllowlistEntry::Item(i) => i.clone(),
-            AllowlistEntry::Namespace(ns) => format!("{ns}::.*"),
+            AllowlistEntry::Namespace { ns, .. } => format!("{ns}::.*"),
         }
     }
+
+    /// The excluded namespace which contains this item, if any.
+    fn excluded_namespace_of(&self, cpp_name: &str) -> Option<&str> {
+        match self {
+            AllowlistEntry::Item(_) => None,
+            AllowlistEntry::Namespace { exclude, .. } => exclude
+                .iter()
+                .find(|ex| is_in_namespace(cpp_name, ex))
+                .map(String::as_str),
+        }
+    }
+}
+
+/// bindgen names the nested type `A::B` as `A_B`, so it only allowlists
+/// it under that name. We can't tell which segments of a name are types
+/// rather than namespaces, so we ask for each possibility: for `A::B::C`,
+/// `A::B::C`, `A::B_C` and `A_B_C`.
+fn nested_type_spellings(name: &str) -> Vec<String> {
+    let segments: Vec<_> = name.split("::").collect();
+    (1..=segments.len())
+        .map(|flattened| {
+            let (namespaces, types) = segments.split_at(segments.len() - flattened);
+            namespaces
+                .iter()
+                .copied()
+                .chain(std::iter::once(types.join("_").as_str()))
+                .join("::")
+        })
+        .collect()
+}
+
+/// The names to give bindgen for this item from a `generate!` or
+/// `generate_pod!`, which (being a pattern) may need to become a
+/// regular expression.
+fn bindgen_spellings(name: &str) -> Vec<String> {
+    let spellings = nested_type_spellings(name);
+    if name_pattern::is_pattern(name) {
+        spellings
+            .iter()
+            .map(|spelling| name_pattern::to_regex(spelling))
+            .collect()
+    } else {
+        spellings
+    }
+}
+
+fn is_in_namespace(cpp_name: &str, ns: &str) -> bool {
+    cpp_name
+        .strip_prefix(ns)
+        .map(|rest| rest.starts_with("::"))
+        .unwrap_or_default()
 }
 
 /// Allowlist configuration.
@@ -159,6 +219,459 @@ impl Default for Allowlist {
 pub struct Subclass {
     pub superclass: String,
     pub subclass: Ident,
+    /// Whether the user asked for the subclass's C++ peer (and the holder
+    /// by which it refers to the Rust object) to be `Send`.
+    pub threadsafe: bool,
+}
+
+/// Verbatim C++ which the user has asked us to add to the C++ peer class
+/// of a subclass, using `subclass_extra_cpp!`.
+#[derive(Debug, Hash)]
+pub struct SubclassExtraCpp {
+    /// The name of the subclass, as given in its `subclass!` directive.
+    pub subclass: String,
+    /// Member declarations, emitted within the body of the class.
+    pub declarations: String,
+    /// Out-of-line definitions, emitted in the generated .cc file.
+    pub definitions: Option<String>,
+}
+
+/// A C++ operator which the user has told us a type supports, via the
+/// `operators!` directive, so that we can implement the corresponding
+/// Rust trait.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum CppOperator {
+    Eq,
+    Lt,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+    RemAssign,
+    /// `operator[]`, taking a `size_t`. We need to be told the type to
+    /// which it returns a reference.
+    Index,
+    /// Not really an operator: a specialization of `std::hash`, which we
+    /// use to implement `Hash`.
+    Hash,
+    /// `operator<<(std::ostream&, const T&)`, which we use to implement
+    /// `Display`. (Not a left shift.)
+    StreamOut,
+}
+
+impl CppOperator {
+    const ALL: [CppOperator; 15] = [
+        CppOperator::Eq,
+        CppOperator::Lt,
+        CppOperator::Add,
+        CppOperator::Sub,
+        CppOperator::Mul,
+        CppOperator::Div,
+        CppOperator::Rem,
+        CppOperator::AddAssign,
+        CppOperator::SubAssign,
+        CppOperator::MulAssign,
+        CppOperator::DivAssign,
+        CppOperator::RemAssign,
+        CppOperator::Index,
+        CppOperator::Hash,
+        CppOperator::StreamOut,
+    ];
+
+    /// The operator as it's spelled in C++, e.g. `+=`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CppOperator::Eq => "==",
+            CppOperator::Lt => "<",
+            CppOperator::Add => "+",
+            CppOperator::Sub => "-",
+            CppOperator::Mul => "*",
+            CppOperator::Div => "/",
+            CppOperator::Rem => "%",
+            CppOperator::AddAssign => "+=",
+            CppOperator::SubAssign => "-=",
+            CppOperator::MulAssign => "*=",
+            CppOperator::DivAssign => "/=",
+            CppOperator::RemAssign => "%=",
+            CppOperator::Index => "[]",
+            CppOperator::Hash => "std::hash",
+            CppOperator::StreamOut => "<<",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.symbol() == symbol)
+    }
+
+    /// Whether this operator modifies its left hand side in place,
+    /// rather than producing a new value.
+    pub fn is_assignment(&self) -> bool {
+        matches!(
+            self,
+            CppOperator::AddAssign
+                | CppOperator::SubAssign
+                | CppOperator::MulAssign
+                | CppOperator::DivAssign
+                | CppOperator::RemAssign
+        )
+    }
+
+    /// Whether this operator compares two values, returning a `bool`.
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, CppOperator::Eq | CppOperator::Lt)
+    }
+}
+
+/// The operators listed for one type in an `operators!` directive.
+#[derive(Debug, Hash)]
+pub struct TypeOperators {
+    pub ty: String,
+    pub operators: Vec<CppOperator>,
+    /// The C++ type to which `operator[]` returns a reference, if it's
+    /// listed.
+    pub index_output: Option<String>,
+}
+
+/// An overload given a particular Rust name by a `rename!` directive,
+/// selected by its parameter types.
+#[derive(Debug, Clone, Hash)]
+pub struct OverloadRename {
+    /// The selector as the user wrote it, e.g. `MyClass::set(double)`.
+    pub selector: String,
+    /// The C++ name of the function, e.g. `MyClass::set`.
+    pub function: String,
+    /// The C++ parameter types, each spelled as by
+    /// [`OverloadRename::normalize_cpp_type`].
+    pub params: Vec<String>,
+    pub rust_name: String,
+}
+
+impl OverloadRename {
+    /// Parses a selector such as `MyClass::set(const std::string&, int)`.
+    pub(crate) fn new(selector: &str, rust_name: String) -> Option<Self> {
+        let (function, params) = parse_fn_selector(selector)?;
+        Some(Self {
+            selector: selector.to_string(),
+            function,
+            params,
+            rust_name,
+        })
+    }
+
+    /// Spells a C++ type with whitespace only where it's needed, between
+    /// two words, e.g. `const std::string&` or `unsigned int`.
+    pub fn normalize_cpp_type(ty: &str) -> String {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut normalized = String::new();
+        let mut pending_space = false;
+        for c in ty.trim().chars() {
+            if c.is_whitespace() {
+                pending_space = true;
+                continue;
+            }
+            if pending_space && normalized.ends_with(is_word) && is_word(c) {
+                normalized.push(' ');
+            }
+            pending_space = false;
+            normalized.push(c);
+        }
+        normalized
+    }
+
+    /// Whether this selects the given function, whose parameter types
+    /// are spelled as by [`OverloadRename::normalize_cpp_type`].
+    pub fn matches(&self, function: &str, params: &[String]) -> bool {
+        self.function == function && self.params == params
+    }
+}
+
+/// Whether a `safe_fn!` or `unsafe_fn!` directive marks a function
+/// safe or unsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FnSafety {
+    Safe,
+    Unsafe,
+}
+
+/// A function, method or overload whose safety is chosen by a `safe_fn!`
+/// or `unsafe_fn!` directive, whatever the `safety!` policy.
+#[derive(Debug, Clone, Hash)]
+pub struct SafetyOverride {
+    /// The selector as the user wrote it, e.g. `mylib::risky` or
+    /// `MyClass::set(double*)`.
+    pub selector: String,
+    /// The C++ name of the function, e.g. `MyClass::set`.
+    pub function: String,
+    /// The C++ parameter types, if the selector picks out a particular
+    /// overload.
+    pub params: Option<Vec<String>>,
+    pub safety: FnSafety,
+}
+
+impl SafetyOverride {
+    pub(crate) fn new(selector: &str, safety: FnSafety) -> Option<Self> {
+        let (function, params) = if selector.contains('(') {
+            let (function, params) = parse_fn_selector(selector)?;
+            (function, Some(params))
+        } else {
+            (selector.trim().to_string(), None)
+        };
+        if function.is_empty() {
+            return None;
+        }
+        Some(Self {
+            selector: selector.to_string(),
+            function,
+            params,
+            safety,
+        })
+    }
+
+    /// Whether this applies to the given function, whose parameter types
+    /// are spelled as by [`OverloadRename::normalize_cpp_type`].
+    pub fn matches(&self, function: &str, params: &[String]) -> bool {
+        self.function == function
+            && match &self.params {
+                Some(selected) => selected == params,
+                None => true,
+            }
+    }
+
+    /// Whether some function could be selected by both this and `other`.
+    pub(crate) fn overlaps(&self, other: &SafetyOverride) -> bool {
+        self.function == other.function
+            && match (&self.params, &other.params) {
+                (Some(ours), Some(theirs)) => ours == theirs,
+                _ => true,
+            }
+    }
+}
+
+/// Splits a selector such as `MyClass::set(const std::string&, int)` into
+/// the function's C++ name and its parameter types, each spelled as by
+/// [`OverloadRename::normalize_cpp_type`].
+fn parse_fn_selector(selector: &str) -> Option<(String, Vec<String>)> {
+    let (function, params) = selector.trim().strip_suffix(')')?.split_once('(')?;
+    let function = function.trim();
+    if function.is_empty() {
+        return None;
+    }
+    let mut split_params = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in params.chars() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                split_params.push(OverloadRename::normalize_cpp_type(&current));
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    split_params.push(OverloadRename::normalize_cpp_type(&current));
+    // `f()` and `f(void)` take no parameters.
+    if matches!(split_params.as_slice(), [p] if p.is_empty() || p == "void") {
+        split_params.clear();
+    }
+    if split_params.iter().any(String::is_empty) {
+        return None;
+    }
+    Some((function.to_string(), split_params))
+}
+
+/// A type, function, method or namespace given a particular Rust name by a
+/// `rename!` directive.
+#[derive(Debug, Clone, Hash)]
+pub struct NameRename {
+    /// The C++ name, e.g. `mylib::Box` or `mylib::Widget::move`.
+    pub cpp_name: String,
+    pub rust_name: String,
+}
+
+/// A C++ namespace whose contents a `map_namespace!` directive puts in a
+/// different Rust mod.
+#[derive(Debug, Clone, Hash)]
+pub struct NamespaceMapping {
+    /// The C++ namespace, e.g. `company::product::module`.
+    pub cpp_namespace: String,
+    /// The path of the Rust mod within the generated mod, e.g. `pm`, or
+    /// empty for the generated mod itself.
+    pub rust_path: String,
+}
+
+/// The Rust name given to a type's constructors by a `constructor_name!`
+/// directive, in place of `new`.
+#[derive(Debug, Clone, Hash)]
+pub struct ConstructorRename {
+    /// The C++ name of the type, e.g. `mylib::Widget`.
+    pub ty: String,
+    pub rust_name: String,
+}
+
+/// A `std::function` parameter described by a `closure_param!` directive,
+/// so that Rust callers can instead pass a closure.
+#[derive(Debug, Clone, Hash)]
+pub struct ClosureParam {
+    /// The C++ name of the function, e.g. `Widget::on_progress`.
+    pub function: String,
+    pub param: Ident,
+    pub inputs: Vec<syn::Type>,
+    pub output: Option<syn::Type>,
+}
+
+/// A pointer and length parameter pair, described by a `slice_params!`
+/// directive, which Rust callers instead pass as a single slice.
+#[derive(Debug, Clone, Hash)]
+pub struct SliceParams {
+    /// The C++ name of the function, e.g. `Filter::process`.
+    pub function: String,
+    pub data: Ident,
+    pub len: Ident,
+}
+
+/// An array which a function returns through a pointer, described by an
+/// `out_array!` directive, along with its length, which it returns through
+/// a pointer parameter. Rust callers receive both as a single array.
+#[derive(Debug, Clone, Hash)]
+pub struct OutArray {
+    /// The C++ name of the function, e.g. `Catalog::get_items`.
+    pub function: String,
+    /// The pointer-to-pointer parameter through which the array is
+    /// returned, or `None` if the function returns the array.
+    pub data: Option<Ident>,
+    pub len: Ident,
+    /// The C++ function which frees the array, if the caller must do so.
+    pub free_with: Option<String>,
+}
+
+/// A function which bindgen doesn't tell us about, so the user has given
+/// its signature in a `friend_function!`, `literal_operator!`,
+/// `instantiate!` or `call_operator!` directive.
+#[derive(Debug, Clone, Hash)]
+pub struct DeclaredFunction {
+    /// For a friend function, the C++ name of the class followed by the
+    /// name of the function, e.g. `geo::Shape::merge`. For a literal
+    /// operator, the namespace followed by the suffix, e.g. `geo::_px`.
+    /// For an instantiation of a function template, its C++ name with the
+    /// template arguments, e.g. `Config::get<int>`. For a call operator,
+    /// the C++ name of the class.
+    pub name: String,
+    /// For an instantiation, the Rust name to give it.
+    pub rust_name: Option<String>,
+    pub inputs: Vec<syn::Type>,
+    pub output: Option<syn::Type>,
+}
+
+impl DeclaredFunction {
+    /// The namespace (or class) and final segment of the name: the class
+    /// and function for a friend function, the namespace and suffix for a
+    /// literal operator, or the scope and the function with its template
+    /// arguments for an instantiation.
+    pub fn split_name(&self) -> (&str, &str) {
+        let mut depth = 0;
+        let mut split = None;
+        for (i, c) in self.name.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                ':' if depth == 0 && self.name[i..].starts_with("::") => split = Some(i),
+                _ => {}
+            }
+        }
+        match split {
+            Some(i) => (&self.name[..i], &self.name[i + 2..]),
+            None => ("", &self.name),
+        }
+    }
+}
+
+impl ClosureParam {
+    /// The Rust type which owns the boxed closure, and which C++
+    /// holds in a `rust::Box`.
+    pub fn holder(&self) -> Ident {
+        let param = self.param.to_string();
+        let name: String = self
+            .function
+            .split("::")
+            .chain(std::iter::once(param.as_str()))
+            .flat_map(|segment| segment.split('_'))
+            .flat_map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars)
+            })
+            .collect();
+        Ident::new(&format!("{name}Closure"), Span::call_site())
+    }
+
+    /// The extern "Rust" function by which C++ calls the closure.
+    pub fn call_fn(&self) -> Ident {
+        Ident::new(&format!("{}_call", self.holder()), Span::call_site())
+    }
+
+    /// Whether cxx can pass this type to and from Rust as it is, which
+    /// is all we support for closure parameters and return values.
+    pub(crate) fn is_acceptable_type(ty: &syn::Type, allow_reference: bool) -> bool {
+        const PRIMITIVES: &[&str] = &[
+            "bool", "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize", "f32",
+            "f64",
+        ];
+        match ty {
+            syn::Type::Path(tp) => PRIMITIVES.iter().any(|p| tp.path.is_ident(p)),
+            syn::Type::Reference(tr) if allow_reference && tr.mutability.is_none() => {
+                matches!(tr.elem.as_ref(), syn::Type::Path(tp) if tp.path.is_ident("CxxString"))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A function which takes a pointer to a callback object, described by a
+/// `future_wrapper!` directive, so that Rust callers can instead await a
+/// future which resolves when the callback's completion method is called.
+#[derive(Debug, Clone, Hash)]
+pub struct FutureWrapper {
+    /// The C++ name of the function, e.g. `net::fetch`.
+    pub function: String,
+    pub callback_param: Ident,
+    /// The class of the callback object, e.g. `net::Callback`.
+    pub superclass: String,
+    pub method: Ident,
+}
+
+impl FutureWrapper {
+    /// The subclass of the callback class which we generate, and whose
+    /// override of the completion method resolves the future.
+    pub fn subclass(&self) -> Ident {
+        let param = self.callback_param.to_string();
+        let name: String = self
+            .function
+            .split("::")
+            .chain(std::iter::once(param.as_str()))
+            .flat_map(|segment| segment.split('_'))
+            .flat_map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars)
+            })
+            .collect();
+        Ident::new(&format!("{name}Completion"), Span::call_site())
+    }
 }
 
 #[derive(Clone, Hash)]
@@ -216,17 +729,56 @@ pub struct IncludeCppConfig {
     pub parse_only: bool,
     pub exclude_impls: bool,
     pub(crate) pod_requests: Vec<String>,
+    pub(crate) opaque_types: Vec<String>,
     pub allowlist: Allowlist,
     pub(crate) blocklist: Vec<String>,
+    pub(crate) blocked_namespaces: Vec<String>,
     pub(crate) constructor_blocklist: Vec<String>,
+    pub(crate) suppressed_constructors: Vec<String>,
+    pub(crate) constructor_names: Vec<ConstructorRename>,
+    pub(crate) implicit_conversion_blocklist: Vec<String>,
+    pub(crate) debug_blocklist: Vec<String>,
+    pub(crate) debug_requests: Vec<String>,
+    pub(crate) exposed_private_fields: Vec<String>,
+    pub(crate) blocked_fields: Vec<String>,
+    pub(crate) identity_eq_requests: Vec<String>,
+    pub(crate) layout_shims: Vec<String>,
+    pub(crate) operators: Vec<TypeOperators>,
+    pub(crate) closure_params: Vec<ClosureParam>,
+    pub(crate) future_wrappers: Vec<FutureWrapper>,
+    pub(crate) slice_params: Vec<SliceParams>,
+    pub(crate) out_arrays: Vec<OutArray>,
+    pub(crate) friend_functions: Vec<DeclaredFunction>,
+    pub(crate) literal_operators: Vec<DeclaredFunction>,
+    pub(crate) instantiations: Vec<DeclaredFunction>,
+    pub(crate) call_operators: Vec<DeclaredFunction>,
+    pub(crate) explicit_destructors: Vec<String>,
+    pub(crate) unique_ptr_exts: Vec<String>,
+    pub(crate) enums_as_constants: Vec<String>,
+    pub(crate) bitflags_enums: Vec<String>,
+    pub(crate) fallible_requests: Vec<String>,
+    pub(crate) variadic_requests: Vec<String>,
+    pub(crate) overload_renames: Vec<OverloadRename>,
+    pub(crate) name_renames: Vec<NameRename>,
+    pub(crate) namespace_mappings: Vec<NamespaceMapping>,
+    pub(crate) safety_overrides: Vec<SafetyOverride>,
     pub instantiable: Vec<String>,
+    pub(crate) keep_requests: Vec<String>,
+    pub(crate) keep_all: bool,
     pub(crate) exclude_utilities: bool,
+    pub(crate) raw_doc_comments: bool,
+    pub(crate) str_params: bool,
+    pub(crate) keep_inline_namespaces: bool,
+    pub(crate) rename_snake_case: bool,
+    pub(crate) catch_exceptions: bool,
     pub(crate) mod_name: Option<Ident>,
     pub rust_types: Vec<RustPath>,
     pub subclasses: Vec<Subclass>,
+    pub(crate) subclass_extra_cpp: Vec<SubclassExtraCpp>,
     pub extern_rust_funs: Vec<RustFun>,
     pub concretes: ConcretesMap,
     pub externs: ExternCppTypeMap,
+    pub(crate) imports: Vec<syn::Path>,
 }
 
 impl Parse for IncludeCppConfig {
@@ -234,6 +786,24 @@ impl Parse for IncludeCppConfig {
         let mut config = IncludeCppConfig::default();
 
         while !input.is_empty() {
+            // A directive may be given #[cfg] attributes. If they're not
+            // satisfied, we parse the directive but then ignore it.
+            let mut attrs = Vec::new();
+            while input.peek(syn::token::Pound) && input.peek2(syn::token::Bracket) {
+                let content;
+                attrs.push(syn::Attribute {
+                    pound_token: input.parse()?,
+                    style: syn::AttrStyle::Outer,
+                    bracket_token: syn::bracketed!(content in input),
+                    meta: content.parse()?,
+                });
+            }
+            let mut ignored_config = IncludeCppConfig::default();
+            let target_config = if is_cfg_enabled(&attrs) {
+                &mut config
+            } else {
+                &mut ignored_config
+            };
             let has_hexathorpe = input.parse::<Option<syn::token::Pound>>()?.is_some();
             let ident: syn::Ident = input.parse()?;
             let args;
@@ -253,7 +823,7 @@ impl Parse for IncludeCppConfig {
                         format!("expected {all_possible}"),
                     ));
                 }
-                Some(directive) => directive.parse(to_parse, &mut config, &ident.span())?,
+                Some(directive) => directive.parse(to_parse, target_config, &ident.span())?,
             }
             if parse_completely && !to_parse.is_empty() {
                 return Err(syn::Error::new(
@@ -274,6 +844,29 @@ impl IncludeCppConfig {
         &self.pod_requests
     }
 
+    /// The `generate_pod!` requests which name a single type, rather than
+    /// being patterns.
+    pub fn get_exact_pod_requests(&self) -> impl Iterator<Item = String> + '_ {
+        self.pod_requests
+            .iter()
+            .filter(|i| !name_pattern::is_pattern(i))
+            .map(|i| name_pattern::unescape(i))
+    }
+
+    /// Whether this type was requested by `generate_pod!`, either by name
+    /// or by a pattern which matches it.
+    pub fn is_pod_request(&self, cpp_name: &str) -> bool {
+        self.pod_requests
+            .iter()
+            .any(|i| name_pattern::matches(i, cpp_name))
+    }
+
+    /// Whether this type was named in an `opaque!` directive, so must
+    /// never be POD.
+    pub fn is_opaque(&self, cpp_name: &str) -> bool {
+        self.opaque_types.iter().any(|i| i == cpp_name)
+    }
+
     pub fn get_mod_name(&self) -> Ident {
         self.mod_name
             .as_ref()
@@ -287,8 +880,34 @@ impl IncludeCppConfig {
         self.exclude_utilities
     }
 
+    /// Whether to copy C++ comments into the Rust documentation exactly,
+    /// rather than translating Doxygen markup into Markdown.
+    pub fn raw_doc_comments(&self) -> bool {
+        self.raw_doc_comments
+    }
+
+    /// Whether to generate an extra variant of each function taking
+    /// `const std::string&` parameters, which accepts Rust strings
+    /// for them instead.
+    pub fn str_params(&self) -> bool {
+        self.str_params
+    }
+
+    /// Whether to keep C++ inline namespaces as Rust modules, rather
+    /// than treating them as transparent.
+    pub fn keep_inline_namespaces(&self) -> bool {
+        self.keep_inline_namespaces
+    }
+
+    /// Whether to give functions and methods snake_case Rust names,
+    /// rather than using their C++ names as they are.
+    pub fn rename_snake_case(&self) -> bool {
+        self.rename_snake_case
+    }
+
     /// Items which the user has explicitly asked us to generate;
-    /// we should raise an error if we weren't able to do so.
+    /// we should raise an error if we weren't able to do so. Patterns
+    /// aren't included: see [`Self::unmatched_name_patterns`].
     pub fn must_generate_list(&self) -> Box<dyn Iterator<Item = String> + '_> {
         if let Allowlist::Specific(items) = &self.allowlist {
             Box::new(
@@ -296,16 +915,61 @@ impl IncludeCppConfig {
                     .iter()
                     .filter_map(|i| match i {
                         AllowlistEntry::Item(i) => Some(i),
-                        AllowlistEntry::Namespace(_) => None,
+                        AllowlistEntry::Namespace { .. } => None,
                     })
                     .chain(self.pod_requests.iter())
-                    .cloned(),
+                    .filter(|i| !name_pattern::is_pattern(i))
+                    .map(|i| name_pattern::unescape(i)),
             )
         } else {
-            Box::new(self.pod_requests.iter().cloned())
+            Box::new(self.get_exact_pod_requests())
         }
     }
 
+    /// The items named in `keep!` directives, other than patterns, each of
+    /// which must match something in the C++.
+    pub fn must_keep_list(&self) -> impl Iterator<Item = String> + '_ {
+        self.keep_requests
+            .iter()
+            .filter(|i| !name_pattern::is_pattern(i))
+            .map(|i| name_pattern::unescape(i))
+    }
+
+    /// Whether garbage collection should keep this item, and whatever it
+    /// depends upon, even if nothing we were asked to generate needs it.
+    pub fn is_kept(&self, cpp_name: &str) -> bool {
+        self.keep_all
+            || self
+                .keep_requests
+                .iter()
+                .any(|item| name_pattern::matches(item, cpp_name))
+    }
+
+    /// The patterns in `generate!`, `generate_pod!`, `keep!` and `block!`
+    /// directives which don't match any of these names.
+    pub fn unmatched_name_patterns(&self, names: &[String]) -> Vec<&str> {
+        let items = match &self.allowlist {
+            Allowlist::Specific(items) => items.as_slice(),
+            _ => &[],
+        };
+        items
+            .iter()
+            .filter_map(|entry| match entry {
+                AllowlistEntry::Item(i) => Some(i),
+                AllowlistEntry::Namespace { .. } => None,
+            })
+            .chain(self.keep_requests.iter())
+            .chain(self.blocklist.iter())
+            .filter(|pattern| {
+                name_pattern::is_pattern(pattern)
+                    && !names
+                        .iter()
+                        .any(|name| name_pattern::matches(pattern, name))
+            })
+            .map(|pattern| pattern.as_str())
+            .collect()
+    }
+
     /// The allowlist of items to be passed into bindgen, if any.
     pub fn bindgen_allowlist(&self) -> Option<Box<dyn Iterator<Item = String> + '_>> {
         match &self.allowlist {
@@ -313,8 +977,12 @@ impl IncludeCppConfig {
             Allowlist::Specific(items) => Some(Box::new(
                 items
                     .iter()
-                    .map(AllowlistEntry::to_bindgen_item)
-                    .chain(self.pod_requests.iter().cloned())
+                    .flat_map(|entry| match entry {
+                        AllowlistEntry::Item(i) => bindgen_spellings(i),
+                        AllowlistEntry::Namespace { .. } => vec![entry.to_bindgen_item()],
+                    })
+                    .chain(self.pod_requests.iter().flat_map(|i| bindgen_spellings(i)))
+                    .chain(self.keep_requests.iter().flat_map(|i| bindgen_spellings(i)))
                     .chain(self.active_utilities())
                     .chain(self.subclasses.iter().flat_map(|sc| {
                         [
@@ -328,6 +996,68 @@ impl IncludeCppConfig {
         }
     }
 
+    /// Rewrites any directives which name a nested type as `Outer::Inner`
+    /// to use the name bindgen gives it, `Outer_Inner`. `nested_types` maps
+    /// the former to the latter, and is only known once bindgen has run.
+    pub fn resolve_nested_type_names(&mut self, nested_types: &HashMap<String, String>) {
+        let resolve = |name: &mut String| {
+            if let Some(flattened) = nested_types.get(name.as_str()) {
+                *name = flattened.clone();
+            }
+        };
+        if let Allowlist::Specific(items) = &mut self.allowlist {
+            for item in items.iter_mut() {
+                if let AllowlistEntry::Item(name) = item {
+                    resolve(name);
+                }
+            }
+        }
+        self.pod_requests.iter_mut().for_each(resolve);
+        self.opaque_types.iter_mut().for_each(resolve);
+    }
+
+    /// Items to be passed to bindgen as opaque types, because they're
+    /// in namespaces excluded from a `generate_ns!`. bindgen won't
+    /// generate the contents of these types, though they may still be
+    /// referred to by the APIs we do generate.
+    pub fn bindgen_opaque_types(&self) -> impl Iterator<Item = String> + '_ {
+        self.excluded_namespaces().map(|ns| format!("{ns}::.*"))
+    }
+
+    fn excluded_namespaces(&self) -> impl Iterator<Item = &String> {
+        let items = match &self.allowlist {
+            Allowlist::Specific(items) => items.as_slice(),
+            _ => &[],
+        };
+        items.iter().flat_map(|entry| match entry {
+            AllowlistEntry::Namespace { exclude, .. } => exclude.as_slice(),
+            AllowlistEntry::Item(_) => &[],
+        })
+    }
+
+    /// If this item is within a namespace excluded from a `generate_ns!`,
+    /// returns that namespace.
+    pub fn excluded_namespace_of(&self, cpp_name: &str) -> Option<&str> {
+        match &self.allowlist {
+            Allowlist::Specific(items) => items
+                .iter()
+                .find_map(|entry| entry.excluded_namespace_of(cpp_name)),
+            _ => None,
+        }
+    }
+
+    /// Whether this type is in a namespace requested by `generate_ns_pod!`,
+    /// such that it should be POD if at all possible.
+    pub fn is_pod_by_default(&self, cpp_name: &str) -> bool {
+        match &self.allowlist {
+            Allowlist::Specific(items) => items.iter().any(|entry| {
+                matches!(entry, AllowlistEntry::Namespace { ns, pod: true, .. } if is_in_namespace(cpp_name, ns))
+                    && entry.excluded_namespace_of(cpp_name).is_none()
+            }),
+            _ => false,
+        }
+    }
+
     fn active_utilities(&self) -> Vec<String> {
         if self.exclude_utilities {
             Vec::new()
@@ -364,32 +1094,396 @@ impl IncludeCppConfig {
             || self.is_rust_fun(cpp_name)
             || self.is_rust_type_name(cpp_name)
             || self.is_concrete_type(cpp_name)
+            || self.literal_operators.iter().any(|lo| lo.name == cpp_name)
+            || self.instantiations.iter().any(|i| i.name == cpp_name)
             || match &self.allowlist {
                 Allowlist::Unspecified(_) => panic!("Eek no allowlist yet"),
                 Allowlist::All => true,
                 Allowlist::Specific(items) => items.iter().any(|entry| match entry {
-                    AllowlistEntry::Item(i) => i == cpp_name,
-                    AllowlistEntry::Namespace(ns) => cpp_name.starts_with(ns),
+                    AllowlistEntry::Item(i) => name_pattern::matches(i, cpp_name),
+                    AllowlistEntry::Namespace { ns, .. } => {
+                        cpp_name.starts_with(ns) && entry.excluded_namespace_of(cpp_name).is_none()
+                    }
                 }),
             }
     }
 
+    /// Whether this item was named in a `generate!`, `generate_pod!` or
+    /// `subclass!` directive, rather than being generated because of
+    /// `generate_ns!` or `generate_all!`. Patterns don't count.
+    pub fn is_explicitly_requested(&self, cpp_name: &str) -> bool {
+        self.pod_requests
+            .iter()
+            .any(|item| name_pattern::is_exactly(item, cpp_name))
+            || self.superclasses().any(|sc| sc == cpp_name)
+            || match &self.allowlist {
+                Allowlist::Specific(items) => items.iter().any(|entry| {
+                    matches!(entry, AllowlistEntry::Item(i) if name_pattern::is_exactly(i, cpp_name))
+                }),
+                _ => false,
+            }
+    }
+
+    /// The directive which asked for bindings for this item, if any, as
+    /// it was written, e.g. `generate!("Sdl*")` or `generate_ns!("mylib")`.
+    /// Exact names are preferred over patterns and namespaces.
+    pub fn requested_by(&self, cpp_name: &str) -> Option<String> {
+        let items = match &self.allowlist {
+            Allowlist::Specific(items) => items.as_slice(),
+            Allowlist::All => return Some("generate_all!()".to_string()),
+            Allowlist::Unspecified(_) => &[],
+        };
+        let named_by = |exact: bool| {
+            let matches = |item: &str| match exact {
+                true => name_pattern::is_exactly(item, cpp_name),
+                false => name_pattern::matches(item, cpp_name),
+            };
+            self.pod_requests
+                .iter()
+                .find(|item| matches(item))
+                .map(|item| format!("generate_pod!(\"{item}\")"))
+                .or_else(|| {
+                    items.iter().find_map(|entry| match entry {
+                        AllowlistEntry::Item(item) if matches(item) => {
+                            Some(format!("generate!(\"{item}\")"))
+                        }
+                        _ => None,
+                    })
+                })
+        };
+        named_by(true)
+            .or_else(|| {
+                self.superclasses()
+                    .find(|sc| *sc == cpp_name)
+                    .map(|sc| format!("subclass!(\"{sc}\")"))
+            })
+            .or_else(|| named_by(false))
+            .or_else(|| {
+                items.iter().find_map(|entry| match entry {
+                    AllowlistEntry::Namespace { ns, pod, .. }
+                        if cpp_name.starts_with(&format!("{ns}::"))
+                            && entry.excluded_namespace_of(cpp_name).is_none() =>
+                    {
+                        let directive = if *pod {
+                            "generate_ns_pod"
+                        } else {
+                            "generate_ns"
+                        };
+                        Some(format!("{directive}!(\"{ns}\")"))
+                    }
+                    _ => None,
+                })
+            })
+    }
+
+    /// Whether this item was blocked by `block!` or `block_ns!`. This may
+    /// be a type, a function or (as `Type::method`) a method. Exact names
+    /// win over patterns and namespaces, so an item named exactly by a
+    /// `block!` is blocked even if it matches a `generate!` pattern, and an
+    /// item named exactly by a `generate!` isn't blocked by a `block!`
+    /// pattern or a `block_ns!`.
     pub fn is_on_blocklist(&self, cpp_name: &str) -> bool {
-        self.blocklist.contains(&cpp_name.to_string())
+        self.blocked_by(cpp_name).is_some()
+    }
+
+    /// The directive which blocked this item, if any, as it was written,
+    /// e.g. `block!("*_impl")` or `block_ns!("mylib::experimental")`.
+    pub fn blocked_by(&self, cpp_name: &str) -> Option<String> {
+        let exactly = self
+            .blocklist
+            .iter()
+            .find(|item| name_pattern::is_exactly(item, cpp_name));
+        if let Some(item) = exactly {
+            return Some(format!("block!(\"{item}\")"));
+        }
+        if self.is_explicitly_requested(cpp_name) {
+            return None;
+        }
+        self.blocklist
+            .iter()
+            .find(|item| name_pattern::matches(item, cpp_name))
+            .map(|item| format!("block!(\"{item}\")"))
+            .or_else(|| {
+                self.blocked_namespaces
+                    .iter()
+                    .find(|ns| is_in_namespace(cpp_name, ns))
+                    .map(|ns| format!("block_ns!(\"{ns}\")"))
+            })
     }
 
     pub fn is_on_constructor_blocklist(&self, cpp_name: &str) -> bool {
         self.constructor_blocklist.contains(&cpp_name.to_string())
     }
 
-    pub fn get_blocklist(&self) -> impl Iterator<Item = &String> {
-        self.blocklist.iter()
+    /// Whether the user has asked us not to offer any constructors for
+    /// this type, so that Rust code uses its factory functions instead.
+    pub fn is_constructor_suppressed(&self, cpp_name: &str) -> bool {
+        self.suppressed_constructors.contains(&cpp_name.to_string())
+    }
+
+    /// The Rust name which the user has chosen for this type's
+    /// constructors, if not `new`.
+    pub fn get_constructor_name(&self, cpp_name: &str) -> Option<&str> {
+        self.constructor_names
+            .iter()
+            .find(|name| name.ty == cpp_name)
+            .map(|name| name.rust_name.as_str())
+    }
+
+    /// Whether the user has asked us not to offer this type's non-explicit
+    /// single-argument constructors as conversions.
+    pub fn is_on_implicit_conversion_blocklist(&self, cpp_name: &str) -> bool {
+        self.implicit_conversion_blocklist
+            .contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked us not to implement `Debug` and `Display`
+    /// for this type, even though it has a suitable stringification method.
+    pub fn is_on_debug_blocklist(&self, cpp_name: &str) -> bool {
+        self.debug_blocklist.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked us to make the private and protected
+    /// fields of this POD type accessible from Rust.
+    pub fn exposes_private_fields(&self, cpp_name: &str) -> bool {
+        self.exposed_private_fields.contains(&cpp_name.to_string())
+    }
+
+    /// The fields of this type which the user has asked us to replace
+    /// with opaque storage.
+    pub fn get_blocked_fields<'a>(&'a self, cpp_name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.blocked_fields.iter().filter_map(move |blocked| {
+            blocked
+                .rsplit_once("::")
+                .filter(|(ty, _)| *ty == cpp_name)
+                .map(|(_, field)| field)
+        })
+    }
+
+    /// Whether the user has asked us to implement `PartialEq` and `Eq`
+    /// for this type by comparing the addresses of C++ objects.
+    pub fn is_identity_eq(&self, cpp_name: &str) -> bool {
+        self.identity_eq_requests.contains(&cpp_name.to_string())
+    }
+
+    /// The types for which the user has asked us to generate functions
+    /// which ask C++ for their size and alignment.
+    pub fn get_layout_shims(&self) -> &[String] {
+        &self.layout_shims
+    }
+
+    /// The types which the user has asked us to implement `Debug` for,
+    /// even though they don't have a stringification method.
+    pub fn get_debug_requests(&self) -> &[String] {
+        &self.debug_requests
+    }
+
+    /// The C++ operators which the user has told us each type supports.
+    pub fn get_operators(&self) -> &[TypeOperators] {
+        &self.operators
+    }
+
+    /// Whether the user has told us that this type supports this operator.
+    pub fn has_operator(&self, ty: &str, op: CppOperator) -> bool {
+        self.operators
+            .iter()
+            .any(|to| to.ty == ty && to.operators.contains(&op))
+    }
+
+    /// The `std::function` parameters which the user has told us to
+    /// accept as Rust closures.
+    pub fn get_closure_params(&self) -> &[ClosureParam] {
+        &self.closure_params
+    }
+
+    /// The pointer and length parameter pairs which the user has told us
+    /// to accept as Rust slices.
+    pub fn get_slice_params(&self) -> &[SliceParams] {
+        &self.slice_params
+    }
+
+    /// The arrays which the user has told us functions return through
+    /// pointer parameters.
+    pub fn get_out_arrays(&self) -> &[OutArray] {
+        &self.out_arrays
+    }
+
+    /// The friend functions, defined within classes, which the user has
+    /// told us about.
+    pub fn get_friend_functions(&self) -> &[DeclaredFunction] {
+        &self.friend_functions
+    }
+
+    /// The user-defined literal operators which the user has told us about.
+    pub fn get_literal_operators(&self) -> &[DeclaredFunction] {
+        &self.literal_operators
+    }
+
+    /// The instantiations of function templates which the user has asked
+    /// for.
+    pub fn get_instantiations(&self) -> &[DeclaredFunction] {
+        &self.instantiations
+    }
+
+    /// The overloads of `operator()` which the user has told us about.
+    pub fn get_call_operators(&self) -> &[DeclaredFunction] {
+        &self.call_operators
+    }
+
+    /// The overloads to which the user has given particular Rust names.
+    pub fn get_overload_renames(&self) -> &[OverloadRename] {
+        &self.overload_renames
+    }
+
+    /// The types, functions, methods and namespaces to which the user has
+    /// given particular Rust names.
+    pub fn get_name_renames(&self) -> &[NameRename] {
+        &self.name_renames
+    }
+
+    /// The C++ namespaces which the user has put in different Rust mods
+    /// using `map_namespace!`.
+    pub fn get_namespace_mappings(&self) -> &[NamespaceMapping] {
+        &self.namespace_mappings
+    }
+
+    /// The path of the Rust mod, within the generated mod, for the items
+    /// in this C++ namespace. That's the same as the namespace unless it,
+    /// or a namespace enclosing it, is named in `map_namespace!` or
+    /// `rename!`.
+    pub fn get_rust_mod_path(&self, cpp_namespace: &[String]) -> Vec<String> {
+        let mapping = self
+            .namespace_mappings
+            .iter()
+            .filter_map(|mapping| {
+                let segments: Vec<_> = mapping.cpp_namespace.split("::").collect();
+                (segments.len() <= cpp_namespace.len()
+                    && segments.iter().zip(cpp_namespace).all(|(a, b)| a == b))
+                .then_some((segments.len(), mapping))
+            })
+            .max_by_key(|(depth, _)| *depth);
+        let (mut path, mapped_depth) = match mapping {
+            Some((depth, mapping)) => (
+                mapping
+                    .rust_path
+                    .split("::")
+                    .filter(|segment| !segment.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                depth,
+            ),
+            None => (Vec::new(), 0),
+        };
+        for depth in mapped_depth..cpp_namespace.len() {
+            let cpp_name = cpp_namespace[..=depth].join("::");
+            path.push(
+                self.get_rust_rename(&cpp_name)
+                    .unwrap_or(&cpp_namespace[depth])
+                    .to_string(),
+            );
+        }
+        path
+    }
+
+    /// The mods generated by other `include_cpp!`s whose types this
+    /// one should use, as given to `import_from!`.
+    pub fn get_imports(&self) -> &[syn::Path] {
+        &self.imports
+    }
+
+    /// The functions whose safety the user has chosen using `safe_fn!`
+    /// or `unsafe_fn!`, overriding the `safety!` policy.
+    pub fn get_safety_overrides(&self) -> &[SafetyOverride] {
+        &self.safety_overrides
+    }
+
+    /// The Rust name the user has chosen for this type, function, method
+    /// (as `Type::method`) or namespace, if any.
+    pub fn get_rust_rename(&self, cpp_name: &str) -> Option<&str> {
+        self.name_renames
+            .iter()
+            .find(|rename| rename.cpp_name == cpp_name)
+            .map(|rename| rename.rust_name.as_str())
+    }
+
+    /// Whether the user has asked for this type to be destroyed only by
+    /// explicit calls, rather than implicitly whenever its `UniquePtr` is
+    /// dropped.
+    pub fn has_explicit_destructor(&self, cpp_name: &str) -> bool {
+        self.explicit_destructors.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked for this type's methods which take
+    /// `Pin<&mut Self>` to be callable directly on a `UniquePtr` of it.
+    pub fn has_unique_ptr_ext(&self, cpp_name: &str) -> bool {
+        self.unique_ptr_exts.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked for this enum to be represented as a
+    /// newtype over its underlying integer, with a constant for each
+    /// enumerator, rather than as a Rust enum.
+    pub fn is_enum_as_constants(&self, cpp_name: &str) -> bool {
+        self.enums_as_constants.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked for this enum to be treated as a set
+    /// of flags, which may be combined with the bitwise operators.
+    pub fn is_bitflags_enum(&self, cpp_name: &str) -> bool {
+        self.bitflags_enums.contains(&cpp_name.to_string())
+    }
+
+    /// Whether the user has asked for C++ exceptions thrown by this
+    /// function to be returned as a `Result`, either using
+    /// `catch_exceptions!` or by listing it. Any method or constructor
+    /// counts as fallible if its type was listed.
+    pub fn is_fallible(&self, cpp_name: &str, self_ty_cpp_name: Option<&str>) -> bool {
+        self.catch_exceptions
+            || self
+                .fallible_requests
+                .iter()
+                .any(|request| request == cpp_name || Some(request.as_str()) == self_ty_cpp_name)
+    }
+
+    /// The C-style variadic functions which the user has asked to be
+    /// exposed as raw `unsafe extern "C"` declarations.
+    pub fn get_variadic_requests(&self) -> impl Iterator<Item = &String> {
+        self.variadic_requests.iter()
+    }
+
+    /// The items named exactly by `block!`, rather than by patterns.
+    pub fn get_blocklist(&self) -> impl Iterator<Item = String> + '_ {
+        self.blocklist
+            .iter()
+            .filter(|i| !name_pattern::is_pattern(i))
+            .map(|i| name_pattern::unescape(i))
     }
 
     fn is_concrete_type(&self, cpp_name: &str) -> bool {
         self.concretes.0.values().any(|val| *val == cpp_name)
     }
 
+    /// The types named in `concrete!`, with the C++ definition of each
+    /// spelled out in full: any template arguments which are themselves
+    /// `concrete!` types are replaced by their definitions.
+    pub fn get_concretes(&self) -> impl Iterator<Item = (String, &Ident)> + '_ {
+        let aliases = |name: &str| {
+            self.concretes
+                .0
+                .iter()
+                .find(|(_, rust_id)| *rust_id == name)
+                .map(|(cpp_definition, _)| cpp_definition.as_str())
+        };
+        self.concretes
+            .0
+            .iter()
+            .map(move |(cpp_definition, rust_id)| {
+                (
+                    template_name::expand_aliases(cpp_definition, aliases, self.concretes.0.len()),
+                    rust_id,
+                )
+            })
+    }
+
     /// Get a hash of the contents of this `include_cpp!` block.
     pub fn get_hash(&self) -> u64 {
         let mut s = DefaultHasher::new();
@@ -438,6 +1532,36 @@ impl IncludeCppConfig {
             .any(|sc| format!("{}Holder", sc.subclass) == id)
     }
 
+    /// Whether this subclass was declared `threadsafe` in its `subclass!`
+    /// directive.
+    pub fn is_threadsafe_subclass(&self, id: &str) -> bool {
+        self.subclasses
+            .iter()
+            .any(|sc| sc.threadsafe && sc.subclass == id)
+    }
+
+    /// Whether this subclass is one which we generate for a
+    /// `future_wrapper!`, rather than one the user asked for.
+    pub fn is_future_wrapper_subclass(&self, id: &str) -> bool {
+        self.future_wrappers.iter().any(|fw| fw.subclass() == id)
+    }
+
+    /// The functions which the user has told us to wrap such that they
+    /// return a future instead of taking a callback object.
+    pub fn get_future_wrappers(&self) -> &[FutureWrapper] {
+        &self.future_wrappers
+    }
+
+    /// Any verbatim C++ to be added to the C++ peer of this subclass.
+    pub fn get_subclass_extra_cpp<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> impl Iterator<Item = &'a SubclassExtraCpp> + 'a {
+        self.subclass_extra_cpp
+            .iter()
+            .filter(move |extra| extra.subclass == id)
+    }
+
     fn is_subclass_cpp(&self, id: &str) -> bool {
         self.subclasses
             .iter()
@@ -495,7 +1619,8 @@ impl ToTokens for IncludeCppConfig {
 
 #[cfg(test)]
 mod parse_tests {
-    use crate::config::UnsafePolicy;
+    use crate::config::{FnSafety, OverloadRename, UnsafePolicy};
+    use crate::IncludeCppConfig;
     use syn::parse_quote;
     #[test]
     fn test_safety_unsafe() {
@@ -518,4 +1643,624 @@ mod parse_tests {
         let us: UnsafePolicy = parse_quote! {};
         assert_eq!(us, UnsafePolicy::AllFunctionsUnsafe)
     }
+
+    #[test]
+    fn test_keep() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("Host")
+            keep!("mylib::PluginHost")
+            keep!("mylib::plugin_*")
+        };
+        assert!(config.is_kept("mylib::PluginHost"));
+        assert!(config.is_kept("mylib::plugin_init"));
+        assert!(!config.is_kept("Host"));
+        assert!(!config.is_on_allowlist("mylib::PluginHost"));
+        assert_eq!(
+            config.must_keep_list().collect::<Vec<_>>(),
+            ["mylib::PluginHost"]
+        );
+        assert!(config
+            .bindgen_allowlist()
+            .unwrap()
+            .any(|item| item == "mylib::PluginHost"));
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("Host")
+            keep_all!()
+        };
+        assert!(config.is_kept("anything::at::All"));
+    }
+
+    #[test]
+    fn test_generate_ns_exclude() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate_ns!("mylib", exclude("mylib::detail", "mylib::testing"))
+        };
+        assert!(config.is_on_allowlist("mylib::Widget"));
+        assert!(config.is_on_allowlist("mylib::detail_helpers::Helper"));
+        assert!(!config.is_on_allowlist("mylib::detail::Impl"));
+        assert!(!config.is_on_allowlist("mylib::testing::nested::Fake"));
+        assert_eq!(
+            config.excluded_namespace_of("mylib::detail::Impl"),
+            Some("mylib::detail")
+        );
+        assert_eq!(config.excluded_namespace_of("mylib::Widget"), None);
+        assert_eq!(
+            config.bindgen_opaque_types().collect::<Vec<_>>(),
+            ["mylib::detail::.*", "mylib::testing::.*"]
+        );
+        assert!(!config.is_pod_by_default("mylib::Widget"));
+    }
+
+    #[test]
+    fn test_nested_type_names() {
+        let mut config: IncludeCppConfig = parse_quote! {
+            generate!("Connection::Options")
+            generate_pod!("ns::A::B::C")
+            generate!("Connection")
+        };
+        let allowlist: Vec<_> = config.bindgen_allowlist().unwrap().collect();
+        for spelling in [
+            "Connection::Options",
+            "Connection_Options",
+            "Connection",
+            "ns::A::B::C",
+            "ns::A::B_C",
+            "ns::A_B_C",
+            "ns_A_B_C",
+        ] {
+            assert!(allowlist.iter().any(|item| item == spelling));
+        }
+        config.resolve_nested_type_names(
+            &[
+                ("Connection::Options", "Connection_Options"),
+                ("ns::A::B::C", "ns::A_B_C"),
+            ]
+            .into_iter()
+            .map(|(nested, flattened)| (nested.to_string(), flattened.to_string()))
+            .collect(),
+        );
+        assert!(config.is_on_allowlist("Connection_Options"));
+        assert!(config.is_on_allowlist("Connection"));
+        assert_eq!(config.get_pod_requests(), ["ns::A_B_C"]);
+    }
+
+    #[test]
+    fn test_name_patterns() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("foo::*")
+            generate!("Sdl*")
+            generate!("Unused*")
+            generate_pod!("Point*")
+            block!("foo::Unstable")
+            block!("*_impl")
+        };
+        assert!(config.is_on_allowlist("foo::Bar"));
+        assert!(config.is_on_allowlist("SdlWindow"));
+        assert!(!config.is_on_allowlist("MySdlWindow"));
+        assert!(config.is_on_blocklist("foo::Unstable"));
+        assert!(config.is_on_blocklist("foo::widget_impl"));
+        assert!(!config.is_on_blocklist("foo::Bar"));
+        assert!(config.is_pod_request("Point3D"));
+        assert!(!config.is_explicitly_requested("foo::Bar"));
+        assert!(config.must_generate_list().next().is_none());
+        assert_eq!(
+            config.get_blocklist().collect::<Vec<_>>(),
+            ["foo::Unstable"]
+        );
+        let allowlist: Vec<_> = config.bindgen_allowlist().unwrap().collect();
+        assert!(allowlist.iter().any(|item| item == "foo::.*"));
+        assert!(allowlist.iter().any(|item| item == "foo_.*"));
+        assert!(allowlist.iter().any(|item| item == "Sdl.*"));
+        assert_eq!(
+            config.unmatched_name_patterns(&[
+                "foo::Bar".into(),
+                "SdlWindow".into(),
+                "Point".into()
+            ]),
+            ["Unused*", "*_impl"]
+        );
+    }
+
+    #[test]
+    fn test_requested_by() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("Sdl*")
+            generate!("SdlWindow")
+            generate_pod!("Point")
+            generate_ns!("mylib")
+            subclass!("Observer", MyObserver)
+        };
+        assert_eq!(
+            config.requested_by("SdlWindow").as_deref(),
+            Some("generate!(\"SdlWindow\")")
+        );
+        assert_eq!(
+            config.requested_by("SdlRenderer").as_deref(),
+            Some("generate!(\"Sdl*\")")
+        );
+        assert_eq!(
+            config.requested_by("Point").as_deref(),
+            Some("generate_pod!(\"Point\")")
+        );
+        assert_eq!(
+            config.requested_by("mylib::Engine").as_deref(),
+            Some("generate_ns!(\"mylib\")")
+        );
+        assert_eq!(
+            config.requested_by("Observer").as_deref(),
+            Some("subclass!(\"Observer\")")
+        );
+        assert_eq!(config.requested_by("mylibrary::Engine"), None);
+        let config: IncludeCppConfig = parse_quote! {
+            generate_all!()
+        };
+        assert_eq!(
+            config.requested_by("Anything").as_deref(),
+            Some("generate_all!()")
+        );
+    }
+
+    #[test]
+    fn test_name_patterns_exact_generate_wins() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate!("internal::detail::Kept")
+            block!("internal::detail::*")
+        };
+        assert!(!config.is_on_blocklist("internal::detail::Kept"));
+        assert!(config.is_on_blocklist("internal::detail::Other"));
+    }
+
+    #[test]
+    fn test_block_ns() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate_ns!("mylib")
+            generate!("mylib::experimental::Kept")
+            block_ns!("mylib::experimental")
+            block!("mylib::Widget::dangerous")
+        };
+        assert!(config.is_on_blocklist("mylib::experimental::Gadget"));
+        assert!(config.is_on_blocklist("mylib::experimental::inner::f"));
+        assert!(!config.is_on_blocklist("mylib::experimental::Kept"));
+        assert!(!config.is_on_blocklist("mylib::experimentalish"));
+        assert!(config.is_on_blocklist("mylib::Widget::dangerous"));
+        assert!(!config.is_on_blocklist("mylib::Widget"));
+        assert_eq!(
+            config.blocked_by("mylib::experimental::Gadget").as_deref(),
+            Some("block_ns!(\"mylib::experimental\")")
+        );
+        assert_eq!(
+            config.blocked_by("mylib::Widget::dangerous").as_deref(),
+            Some("block!(\"mylib::Widget::dangerous\")")
+        );
+        assert_eq!(config.blocked_by("mylib::experimental::Kept"), None);
+    }
+
+    #[test]
+    fn test_cfg_on_directives() {
+        let config: IncludeCppConfig = syn::parse_str(
+            r#"
+            #[cfg(all())]
+            #include "always.h"
+            #[cfg(any())]
+            #include "never.h"
+            #[cfg(all())]
+            generate!("Kept")
+            #[cfg(any())]
+            generate!("Skipped")
+            #[cfg(any())]
+            generate_pod!("AlsoSkipped")
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.inclusions, ["always.h"]);
+        assert!(config.is_on_allowlist("Kept"));
+        assert!(!config.is_on_allowlist("Skipped"));
+        assert!(config.get_pod_requests().is_empty());
+    }
+
+    #[test]
+    fn test_generate_ns_exclude_outside_namespace() {
+        let err = syn::parse2::<IncludeCppConfig>(quote::quote! {
+            generate_ns!("mylib", exclude("otherlib::detail"))
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_generate_ns_pod() {
+        let config: IncludeCppConfig = parse_quote! {
+            generate_ns_pod!("mylib", exclude("mylib::detail"))
+            generate_ns!("otherlib")
+        };
+        assert!(config.is_pod_by_default("mylib::Point"));
+        assert!(config.is_pod_by_default("mylib::geometry::Line"));
+        assert!(!config.is_pod_by_default("mylib::detail::Impl"));
+        assert!(!config.is_pod_by_default("otherlib::Point"));
+        assert!(config.is_on_allowlist("otherlib::Point"));
+    }
+
+    #[cfg(feature = "reproduction_case")]
+    #[test]
+    fn test_generate_ns_round_trip() {
+        use quote::ToTokens;
+        let config: IncludeCppConfig = parse_quote! {
+            generate_ns_pod!("mylib", exclude("mylib::detail"))
+            generate_ns!("otherlib")
+        };
+        let reparsed: IncludeCppConfig = syn::parse2(config.to_token_stream()).unwrap();
+        assert!(reparsed.is_pod_by_default("mylib::Point"));
+        assert!(!reparsed.is_pod_by_default("otherlib::Point"));
+        assert!(reparsed.is_on_allowlist("otherlib::Point"));
+        assert!(!reparsed.is_on_allowlist("mylib::detail::Impl"));
+    }
+
+    #[test]
+    fn test_subclass_threadsafe() {
+        let config: IncludeCppConfig = parse_quote! {
+            subclass!("Observer", SafeObserver, threadsafe)
+            subclass!("Observer", LocalObserver)
+        };
+        assert!(config.is_threadsafe_subclass("SafeObserver"));
+        assert!(!config.is_threadsafe_subclass("LocalObserver"));
+        let err = syn::parse2::<IncludeCppConfig>(quote::quote! {
+            subclass!("Observer", OtherObserver, sync)
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_subclass_extra_cpp() {
+        let config: IncludeCppConfig = parse_quote! {
+            subclass!("Task", MyTask)
+            subclass_extra_cpp!("MyTask", "friend class Scheduler;")
+            subclass_extra_cpp!("MyTask", "void run() override;", "void MyTaskCpp::run() {}")
+        };
+        let extras: Vec<_> = config.get_subclass_extra_cpp("MyTask").collect();
+        assert_eq!(extras.len(), 2);
+        assert_eq!(extras[0].declarations, "friend class Scheduler;");
+        assert!(extras[0].definitions.is_none());
+        assert_eq!(
+            extras[1].definitions.as_deref(),
+            Some("void MyTaskCpp::run() {}")
+        );
+        assert_eq!(config.get_subclass_extra_cpp("OtherTask").count(), 0);
+        let err = syn::parse2::<IncludeCppConfig>(quote::quote! {
+            subclass_extra_cpp!(MyTask, "void run() override;")
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_operators() {
+        use crate::config::CppOperator;
+        let config: IncludeCppConfig = parse_quote! {
+            operators!("Vec3", "==", "<", "+", "+=")
+            operators!("IntBuffer", "[]" -> "int")
+            operators!("Handle", "==", "std::hash")
+            operators!("Date", "<<")
+        };
+        let ops = config.get_operators();
+        assert_eq!(ops.len(), 4);
+        assert_eq!(ops[0].ty, "Vec3");
+        assert_eq!(ops[0].index_output, None);
+        assert_eq!(ops[1].operators, [CppOperator::Index]);
+        assert_eq!(ops[1].index_output.as_deref(), Some("int"));
+        assert_eq!(ops[2].operators, [CppOperator::Eq, CppOperator::Hash]);
+        assert_eq!(ops[3].operators, [CppOperator::StreamOut]);
+        assert!(config.has_operator("Date", CppOperator::StreamOut));
+        assert!(!config.has_operator("Vec3", CppOperator::StreamOut));
+        assert_eq!(
+            ops[0].operators,
+            [
+                CppOperator::Eq,
+                CppOperator::Lt,
+                CppOperator::Add,
+                CppOperator::AddAssign
+            ]
+        );
+        for bad in [
+            quote::quote! { operators!("Vec3", ">>") },
+            quote::quote! { operators!("Vec3", "<") },
+            quote::quote! { operators!("IntBuffer", "[]") },
+            quote::quote! { operators!("Handle", "std::hash") },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_slice_params() {
+        let config: IncludeCppConfig = parse_quote! {
+            slice_params!("process", "data", "len")
+            slice_params!("Filter::apply", samples, count)
+        };
+        let slice_params = config.get_slice_params();
+        assert_eq!(slice_params[0].function, "process");
+        assert_eq!(slice_params[0].data, "data");
+        assert_eq!(slice_params[0].len, "len");
+        assert_eq!(slice_params[1].function, "Filter::apply");
+        assert_eq!(slice_params[1].len, "count");
+        for bad in [
+            quote::quote! { slice_params!("process", "data") },
+            quote::quote! { slice_params!("process", "data", "not valid") },
+            quote::quote! { slice_params!("process", "data", "data") },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_out_arrays() {
+        let config: IncludeCppConfig = parse_quote! {
+            out_array!("get_items", out_items, out_count)
+            out_array!("list_names", return, count, free_with = "free_names")
+        };
+        let out_arrays = config.get_out_arrays();
+        assert_eq!(out_arrays[0].function, "get_items");
+        assert_eq!(out_arrays[0].data.as_ref().unwrap(), "out_items");
+        assert_eq!(out_arrays[0].len, "out_count");
+        assert_eq!(out_arrays[0].free_with, None);
+        assert_eq!(out_arrays[1].data, None);
+        assert_eq!(out_arrays[1].len, "count");
+        assert_eq!(out_arrays[1].free_with.as_deref(), Some("free_names"));
+        for bad in [
+            quote::quote! { out_array!("get_items", out_items) },
+            quote::quote! { out_array!("get_items", out_items, out_items) },
+            quote::quote! { out_array!("get_items", out_items, out_count, delete_with = "free") },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_declared_functions() {
+        let config: IncludeCppConfig = parse_quote! {
+            friend_function!("geo::Shape::merge", fn(&Shape, &Shape) -> Shape)
+            literal_operator!("geo::_px", fn(u64) -> Pixels)
+        };
+        let friend = &config.get_friend_functions()[0];
+        assert_eq!(friend.split_name(), ("geo::Shape", "merge"));
+        assert_eq!(friend.inputs.len(), 2);
+        assert!(friend.output.is_some());
+        let literal = &config.get_literal_operators()[0];
+        assert_eq!(literal.split_name(), ("geo", "_px"));
+        assert_eq!(literal.inputs.len(), 1);
+        let config: IncludeCppConfig = parse_quote! {
+            instantiate!("Config::get<std::map<int, ns::Widget>>", "get_widgets", fn(&Config) -> Widgets)
+            instantiate!("ns::make<int>", "make_int", fn() -> i32)
+        };
+        let instantiations = config.get_instantiations();
+        assert_eq!(
+            instantiations[0].split_name(),
+            ("Config", "get<std::map<int, ns::Widget>>")
+        );
+        assert_eq!(instantiations[0].rust_name.as_deref(), Some("get_widgets"));
+        assert_eq!(instantiations[1].split_name(), ("ns", "make<int>"));
+        assert!(config.is_on_allowlist("ns::make<int>"));
+        let config: IncludeCppConfig = parse_quote! {
+            call_operator!("Predicate", fn(&Predicate, &Item) -> bool)
+        };
+        let call = &config.get_call_operators()[0];
+        assert_eq!(call.split_name(), ("", "Predicate"));
+        assert_eq!(call.inputs.len(), 2);
+        for bad in [
+            quote::quote! { friend_function!("merge", fn(&Shape, &Shape) -> Shape) },
+            quote::quote! { friend_function!("Shape::merge", (&Shape, &Shape) -> Shape) },
+            quote::quote! { literal_operator!("geo::px", fn(u64) -> Pixels) },
+            quote::quote! { instantiate!("Config::get", "get", fn(&Config) -> i32) },
+            quote::quote! { instantiate!("Config::get<int>", "get int", fn(&Config) -> i32) },
+            quote::quote! { instantiate!("Config::get<int>", fn(&Config) -> i32) },
+            quote::quote! { call_operator!("Predicate", fn() -> bool) },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_closure_params() {
+        let config: IncludeCppConfig = parse_quote! {
+            closure_param!("Widget::on_progress", cb, FnMut(i32, &CxxString) -> bool)
+        };
+        let closure = &config.get_closure_params()[0];
+        assert_eq!(closure.function, "Widget::on_progress");
+        assert_eq!(closure.param, "cb");
+        assert_eq!(closure.inputs.len(), 2);
+        assert!(closure.output.is_some());
+        assert_eq!(closure.holder(), "WidgetOnProgressCbClosure");
+        for bad in [
+            quote::quote! { closure_param!("on_progress", cb, Fn(i32)) },
+            quote::quote! { closure_param!("on_progress", cb, FnMut(String)) },
+            quote::quote! { closure_param!("on_progress", cb, FnMut() -> &CxxString) },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_future_wrappers() {
+        let config: IncludeCppConfig = parse_quote! {
+            future_wrapper!("net::fetch", callback_param = "cb", method = "net::Callback::on_complete")
+        };
+        let wrapper = &config.get_future_wrappers()[0];
+        assert_eq!(wrapper.function, "net::fetch");
+        assert_eq!(wrapper.callback_param, "cb");
+        assert_eq!(wrapper.superclass, "net::Callback");
+        assert_eq!(wrapper.method, "on_complete");
+        assert_eq!(wrapper.subclass(), "NetFetchCbCompletion");
+        assert!(config.is_future_wrapper_subclass("NetFetchCbCompletion"));
+        assert!(config.is_threadsafe_subclass("NetFetchCbCompletion"));
+        for bad in [
+            quote::quote! { future_wrapper!("fetch", callback_param = "cb", method = "on_complete") },
+            quote::quote! { future_wrapper!("fetch", method = "Callback::on_complete") },
+            quote::quote! { future_wrapper!("fetch", callback = "cb", method = "Callback::on_complete") },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_overload_renames() {
+        let config: IncludeCppConfig = parse_quote! {
+            rename!("MyClass::set(const std::string &, std::map<int, int>)", "set_str")
+            rename!("reset( void )", "reset_all")
+        };
+        let renames = config.get_overload_renames();
+        assert_eq!(renames[0].function, "MyClass::set");
+        assert_eq!(
+            renames[0].params,
+            ["const std::string&", "std::map<int,int>"]
+        );
+        assert!(renames[1].matches("reset", &[]));
+        assert_eq!(
+            OverloadRename::normalize_cpp_type(" unsigned  long *"),
+            "unsigned long*"
+        );
+        for bad in [
+            quote::quote! { rename!("MyClass::set(double)", "set-f64") },
+            quote::quote! { rename!("MyClass::set(double,)", "set_f64") },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_name_renames() {
+        let config: IncludeCppConfig = parse_quote! {
+            rename!("mylib::Box", "CppBox")
+            rename!("mylib::Widget::move", "relocate")
+            rename!("MyClass::set(double)", "set_f64")
+        };
+        assert_eq!(config.get_rust_rename("mylib::Box"), Some("CppBox"));
+        assert_eq!(
+            config.get_rust_rename("mylib::Widget::move"),
+            Some("relocate")
+        );
+        assert_eq!(config.get_rust_rename("MyClass::set"), None);
+        assert_eq!(config.get_overload_renames().len(), 1);
+        assert!(syn::parse2::<IncludeCppConfig>(quote::quote! {
+            rename!("mylib::Box", "Cpp::Box")
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_constructor_names() {
+        let config: IncludeCppConfig = parse_quote! {
+            suppress_constructor!("Widget")
+            constructor_name!("mylib::Gadget", "new_unregistered")
+        };
+        assert!(config.is_constructor_suppressed("Widget"));
+        assert!(!config.is_constructor_suppressed("mylib::Gadget"));
+        assert_eq!(
+            config.get_constructor_name("mylib::Gadget"),
+            Some("new_unregistered")
+        );
+        assert_eq!(config.get_constructor_name("Widget"), None);
+        for invalid in [
+            quote::quote! { constructor_name!("Widget", "new-ish") },
+            quote::quote! {
+                constructor_name!("Widget", "make")
+                constructor_name!("Widget", "create")
+            },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_safety_overrides() {
+        let config: IncludeCppConfig = parse_quote! {
+            unsafe_fn!("mylib::risky")
+            safe_fn!("Widget::set(double*)")
+            safe_fn!("Widget::get")
+        };
+        let overrides = config.get_safety_overrides();
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(overrides[0].safety, FnSafety::Unsafe);
+        assert!(overrides[0].matches("mylib::risky", &["int*".into()]));
+        assert!(!overrides[0].matches("mylib::risky2", &[]));
+        assert!(overrides[1].matches("Widget::set", &["double*".into()]));
+        assert!(!overrides[1].matches("Widget::set", &["int*".into()]));
+        for conflicting in [
+            quote::quote! {
+                safe_fn!("risky")
+                unsafe_fn!("risky")
+            },
+            quote::quote! {
+                safe_fn!("Widget::set(double*)")
+                unsafe_fn!("Widget::set")
+            },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(conflicting).is_err());
+        }
+        let config: IncludeCppConfig = parse_quote! {
+            safe_fn!("Widget::set(double*)")
+            unsafe_fn!("Widget::set(int*)")
+        };
+        assert_eq!(config.get_safety_overrides().len(), 2);
+    }
+
+    #[test]
+    fn test_namespace_mappings() {
+        let config: IncludeCppConfig = parse_quote! {
+            map_namespace!("company::product::module", "pm")
+            map_namespace!("company::product::module::detail_public", "pm")
+            map_namespace!("company::util", "")
+            rename!("company::product::module::io", "input_output")
+        };
+        let path = |ns: &str| {
+            let ns: Vec<_> = ns.split("::").map(str::to_string).collect();
+            config.get_rust_mod_path(&ns).join("::")
+        };
+        assert_eq!(path("company::product::module"), "pm");
+        assert_eq!(path("company::product::module::detail_public"), "pm");
+        assert_eq!(
+            path("company::product::module::io::files"),
+            "pm::input_output::files"
+        );
+        assert_eq!(path("company::util"), "");
+        assert_eq!(path("company::utility"), "company::utility");
+        assert_eq!(path("company"), "company");
+        for invalid in [
+            quote::quote! { map_namespace!("", "pm") },
+            quote::quote! { map_namespace!("a::b", "pm::") },
+            quote::quote! { map_namespace!("a::b", "type") },
+            quote::quote! {
+                map_namespace!("a::b", "pm")
+                map_namespace!("a::b", "other")
+            },
+        ] {
+            assert!(syn::parse2::<IncludeCppConfig>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_concretes() {
+        let config: IncludeCppConfig = parse_quote! {
+            concrete!("std::map<std::string,NodePtrVec>", NodeMap)
+            concrete!("std::vector<std::shared_ptr<mylib::Node> >", NodePtrVec)
+        };
+        let concretes: Vec<_> = config
+            .get_concretes()
+            .map(|(cpp_definition, rust_id)| (cpp_definition, rust_id.to_string()))
+            .collect();
+        assert_eq!(
+            concretes,
+            vec![
+                (
+                    "std::map<std::string, std::vector<std::shared_ptr<mylib::Node>>>".to_string(),
+                    "NodeMap".to_string()
+                ),
+                (
+                    "std::vector<std::shared_ptr<mylib::Node>>".to_string(),
+                    "NodePtrVec".to_string()
+                ),
+            ]
+        );
+        let err = syn::parse2::<IncludeCppConfig>(quote::quote! {
+            concrete!("std::array<int, 4>", FourInts)
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("`4`"));
+    }
 }
@@ -14,8 +14,17 @@ use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::parse::ParseStream;
 
-use crate::config::{Allowlist, AllowlistErr};
+use crate::config::{
+    Allowlist, AllowlistErr, ConstructorRename, CppOperator, FnSafety, NameRename,
+    NamespaceMapping, OverloadRename, SafetyOverride, TypeOperators,
+};
 use crate::directive_names::{EXTERN_RUST_FUN, EXTERN_RUST_TYPE, SUBCLASS};
+use crate::template_name;
+use crate::ClosureParam as ClosureParamConfig;
+use crate::DeclaredFunction;
+use crate::FutureWrapper as FutureWrapperConfig;
+use crate::OutArray as OutArrayConfig;
+use crate::SliceParams as SliceParamsConfig;
 use crate::{AllowlistEntry, IncludeCppConfig};
 use crate::{ParseResult, RustFun, RustPath};
 
@@ -31,9 +40,12 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
         let mut need_hexathorpe: HashMap<String, Box<dyn Directive>> = HashMap::new();
         need_hexathorpe.insert("include".into(), Box::new(Inclusion));
         let mut need_exclamation: HashMap<String, Box<dyn Directive>> = HashMap::new();
-        need_exclamation.insert("generate".into(), Box::new(Generate(false)));
-        need_exclamation.insert("generate_pod".into(), Box::new(Generate(true)));
-        need_exclamation.insert("generate_ns".into(), Box::new(GenerateNs));
+        need_exclamation.insert("generate".into(), Box::new(Generate::Plain));
+        need_exclamation.insert("generate_pod".into(), Box::new(Generate::Pod));
+        need_exclamation.insert("generate_fallible".into(), Box::new(Generate::Fallible));
+        need_exclamation.insert("generate_variadic".into(), Box::new(Generate::Variadic));
+        need_exclamation.insert("generate_ns".into(), Box::new(GenerateNs { pod: false }));
+        need_exclamation.insert("generate_ns_pod".into(), Box::new(GenerateNs { pod: true }));
         need_exclamation.insert("generate_all".into(), Box::new(GenerateAll));
         need_exclamation.insert("safety".into(), Box::new(Safety));
         need_exclamation.insert(
@@ -43,6 +55,20 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.pod_requests,
             )),
         );
+        need_exclamation.insert(
+            "fallible".into(),
+            Box::new(StringList(
+                |config| &mut config.fallible_requests,
+                |config| &config.fallible_requests,
+            )),
+        );
+        need_exclamation.insert(
+            "variadic".into(),
+            Box::new(StringList(
+                |config| &mut config.variadic_requests,
+                |config| &config.variadic_requests,
+            )),
+        );
         need_exclamation.insert(
             "block".into(),
             Box::new(StringList(
@@ -50,6 +76,20 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.blocklist,
             )),
         );
+        need_exclamation.insert(
+            "block_ns".into(),
+            Box::new(StringList(
+                |config| &mut config.blocked_namespaces,
+                |config| &config.blocked_namespaces,
+            )),
+        );
+        need_exclamation.insert(
+            "opaque".into(),
+            Box::new(StringList(
+                |config| &mut config.opaque_types,
+                |config| &config.opaque_types,
+            )),
+        );
         need_exclamation.insert(
             "block_constructors".into(),
             Box::new(StringList(
@@ -57,6 +97,136 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.constructor_blocklist,
             )),
         );
+        need_exclamation.insert(
+            "suppress_constructor".into(),
+            Box::new(StringList(
+                |config| &mut config.suppressed_constructors,
+                |config| &config.suppressed_constructors,
+            )),
+        );
+        need_exclamation.insert("constructor_name".into(), Box::new(ConstructorName));
+        need_exclamation.insert(
+            "block_implicit_conversions".into(),
+            Box::new(StringList(
+                |config| &mut config.implicit_conversion_blocklist,
+                |config| &config.implicit_conversion_blocklist,
+            )),
+        );
+        need_exclamation.insert(
+            "block_debug".into(),
+            Box::new(StringList(
+                |config| &mut config.debug_blocklist,
+                |config| &config.debug_blocklist,
+            )),
+        );
+        need_exclamation.insert(
+            "derive_debug".into(),
+            Box::new(StringList(
+                |config| &mut config.debug_requests,
+                |config| &config.debug_requests,
+            )),
+        );
+        need_exclamation.insert(
+            "expose_private_fields".into(),
+            Box::new(StringList(
+                |config| &mut config.exposed_private_fields,
+                |config| &config.exposed_private_fields,
+            )),
+        );
+        need_exclamation.insert(
+            "block_field".into(),
+            Box::new(StringList(
+                |config| &mut config.blocked_fields,
+                |config| &config.blocked_fields,
+            )),
+        );
+        need_exclamation.insert(
+            "identity_eq".into(),
+            Box::new(StringList(
+                |config| &mut config.identity_eq_requests,
+                |config| &config.identity_eq_requests,
+            )),
+        );
+        need_exclamation.insert(
+            "layout_shims".into(),
+            Box::new(StringList(
+                |config| &mut config.layout_shims,
+                |config| &config.layout_shims,
+            )),
+        );
+        need_exclamation.insert("operators".into(), Box::new(Operators));
+        need_exclamation.insert("closure_param".into(), Box::new(ClosureParam));
+        need_exclamation.insert("future_wrapper".into(), Box::new(FutureWrapper));
+        need_exclamation.insert("slice_params".into(), Box::new(SliceParams));
+        need_exclamation.insert("out_array".into(), Box::new(OutArray));
+        need_exclamation.insert(
+            "friend_function".into(),
+            Box::new(DeclaredFunctions(
+                DeclaredFunctionKind::Friend,
+                |config| &mut config.friend_functions,
+                |config| &config.friend_functions,
+            )),
+        );
+        need_exclamation.insert(
+            "literal_operator".into(),
+            Box::new(DeclaredFunctions(
+                DeclaredFunctionKind::LiteralOperator,
+                |config| &mut config.literal_operators,
+                |config| &config.literal_operators,
+            )),
+        );
+        need_exclamation.insert(
+            "instantiate".into(),
+            Box::new(DeclaredFunctions(
+                DeclaredFunctionKind::Instantiation,
+                |config| &mut config.instantiations,
+                |config| &config.instantiations,
+            )),
+        );
+        need_exclamation.insert(
+            "call_operator".into(),
+            Box::new(DeclaredFunctions(
+                DeclaredFunctionKind::CallOperator,
+                |config| &mut config.call_operators,
+                |config| &config.call_operators,
+            )),
+        );
+        need_exclamation.insert("rename".into(), Box::new(Rename));
+        need_exclamation.insert("map_namespace".into(), Box::new(MapNamespace));
+        need_exclamation.insert("import_from".into(), Box::new(ImportFrom));
+        need_exclamation.insert("safe_fn".into(), Box::new(FnSafetyOverride(FnSafety::Safe)));
+        need_exclamation.insert(
+            "unsafe_fn".into(),
+            Box::new(FnSafetyOverride(FnSafety::Unsafe)),
+        );
+        need_exclamation.insert(
+            "explicit_destructor".into(),
+            Box::new(StringList(
+                |config| &mut config.explicit_destructors,
+                |config| &config.explicit_destructors,
+            )),
+        );
+        need_exclamation.insert(
+            "unique_ptr_ext".into(),
+            Box::new(StringList(
+                |config| &mut config.unique_ptr_exts,
+                |config| &config.unique_ptr_exts,
+            )),
+        );
+        need_exclamation.insert(
+            "enum_as_constants".into(),
+            Box::new(StringList(
+                |config| &mut config.enums_as_constants,
+                |config| &config.enums_as_constants,
+            )),
+        );
+        need_exclamation.insert(
+            "bitflags_enum".into(),
+            Box::new(StringList(
+                |config| &mut config.bitflags_enums,
+                |config| &config.bitflags_enums,
+            )),
+        );
         need_exclamation.insert(
             "instantiable".into(),
             Box::new(StringList(
@@ -64,6 +234,20 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.instantiable,
             )),
         );
+        need_exclamation.insert(
+            "keep".into(),
+            Box::new(StringList(
+                |config| &mut config.keep_requests,
+                |config| &config.keep_requests,
+            )),
+        );
+        need_exclamation.insert(
+            "keep_all".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.keep_all,
+                |config| &config.keep_all,
+            )),
+        );
         need_exclamation.insert(
             "parse_only".into(),
             Box::new(BoolFlag(
@@ -85,11 +269,47 @@ pub(crate) fn get_directives() -> &'static DirectivesMap {
                 |config| &config.exclude_utilities,
             )),
         );
+        need_exclamation.insert(
+            "raw_doc_comments".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.raw_doc_comments,
+                |config| &config.raw_doc_comments,
+            )),
+        );
+        need_exclamation.insert(
+            "str_params".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.str_params,
+                |config| &config.str_params,
+            )),
+        );
+        need_exclamation.insert(
+            "keep_inline_namespaces".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.keep_inline_namespaces,
+                |config| &config.keep_inline_namespaces,
+            )),
+        );
+        need_exclamation.insert(
+            "catch_exceptions".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.catch_exceptions,
+                |config| &config.catch_exceptions,
+            )),
+        );
+        need_exclamation.insert(
+            "rename_snake_case".into(),
+            Box::new(BoolFlag(
+                |config| &mut config.rename_snake_case,
+                |config| &config.rename_snake_case,
+            )),
+        );
         need_exclamation.insert("name".into(), Box::new(ModName));
         need_exclamation.insert("concrete".into(), Box::new(Concrete));
         need_exclamation.insert("rust_type".into(), Box::new(RustType { output: false }));
         need_exclamation.insert(EXTERN_RUST_TYPE.into(), Box::new(RustType { output: true }));
         need_exclamation.insert(SUBCLASS.into(), Box::new(Subclass));
+        need_exclamation.insert("subclass_extra_cpp".into(), Box::new(SubclassExtraCpp));
         need_exclamation.insert(EXTERN_RUST_FUN.into(), Box::new(ExternRustFun));
         need_exclamation.insert(
             "extern_cpp_type".into(),
@@ -143,8 +363,14 @@ impl Directive for Inclusion {
     }
 }
 
-/// Directive for either `generate!` (false) or `generate_pod!` (true).
-struct Generate(bool);
+/// Directive for `generate!`, `generate_pod!`, `generate_fallible!` or
+/// `generate_variadic!`.
+enum Generate {
+    Plain,
+    Pod,
+    Fallible,
+    Variadic,
+}
 
 impl Directive for Generate {
     fn parse(
@@ -158,8 +384,11 @@ impl Directive for Generate {
             .allowlist
             .push(AllowlistEntry::Item(generate.value()))
             .map_err(|e| allowlist_err_to_syn_err(e, span))?;
-        if self.0 {
-            config.pod_requests.push(generate.value());
+        match self {
+            Generate::Plain => {}
+            Generate::Pod => config.pod_requests.push(generate.value()),
+            Generate::Fallible => config.fallible_requests.push(generate.value()),
+            Generate::Variadic => config.variadic_requests.push(generate.value()),
         }
         Ok(())
     }
@@ -169,7 +398,7 @@ impl Directive for Generate {
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
         match &config.allowlist {
-            Allowlist::Specific(items) if !self.0 => Box::new(
+            Allowlist::Specific(items) if matches!(self, Generate::Plain) => Box::new(
                 items
                     .iter()
                     .flat_map(|i| match i {
@@ -184,7 +413,11 @@ impl Directive for Generate {
     }
 }
 
-struct GenerateNs;
+/// Directive for `generate_ns!` or `generate_ns_pod!`, optionally
+/// followed by `exclude("ns::a", "ns::b")`.
+struct GenerateNs {
+    pod: bool,
+}
 
 impl Directive for GenerateNs {
     fn parse(
@@ -194,9 +427,43 @@ impl Directive for GenerateNs {
         span: &Span,
     ) -> ParseResult<()> {
         let generate: syn::LitStr = args.parse()?;
+        let mut exclude = Vec::new();
+        if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let option: Ident = args.parse()?;
+            if option != "exclude" {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "expected exclude(\"namespace\", ...)",
+                ));
+            }
+            let content;
+            syn::parenthesized!(content in args);
+            let namespaces = content
+                .parse_terminated(<syn::LitStr as syn::parse::Parse>::parse, syn::Token![,])?;
+            for excluded in namespaces {
+                if !excluded
+                    .value()
+                    .starts_with(&format!("{}::", generate.value()))
+                {
+                    return Err(syn::Error::new(
+                        excluded.span(),
+                        format!(
+                            "excluded namespace {} isn't within {}",
+                            excluded.value(),
+                            generate.value()
+                        ),
+                    ));
+                }
+                exclude.push(excluded.value());
+            }
+        }
         config
             .allowlist
-            .push(AllowlistEntry::Namespace(generate.value()))
+            .push(AllowlistEntry::Namespace {
+                ns: generate.value(),
+                exclude,
+                pod: self.pod,
+            })
             .map_err(|e| allowlist_err_to_syn_err(e, span))?;
         Ok(())
     }
@@ -205,16 +472,18 @@ impl Directive for GenerateNs {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        let pod_needed = self.pod;
         match &config.allowlist {
-            Allowlist::Specific(items) => Box::new(
-                items
-                    .iter()
-                    .flat_map(|i| match i {
-                        AllowlistEntry::Namespace(s) => Some(s),
-                        _ => None,
+            Allowlist::Specific(items) => Box::new(items.iter().filter_map(move |i| match i {
+                AllowlistEntry::Namespace { ns, exclude, pod } if *pod == pod_needed => {
+                    Some(if exclude.is_empty() {
+                        quote! { #ns }
+                    } else {
+                        quote! { #ns, exclude(#(#exclude),*) }
                     })
-                    .map(|s| quote! { #s }),
-            ),
+                }
+                _ => None,
+            })),
             Allowlist::Unspecified(_) => panic!("Allowlist mode not yet determined"),
             _ => Box::new(std::iter::empty()),
         }
@@ -380,7 +649,9 @@ impl Directive for Concrete {
         let definition: syn::LitStr = args.parse()?;
         args.parse::<syn::token::Comma>()?;
         let rust_id: syn::Ident = args.parse()?;
-        config.concretes.0.insert(definition.value(), rust_id);
+        let canonical = template_name::canonicalize(&definition.value())
+            .map_err(|e| syn::Error::new(definition.span(), e))?;
+        config.concretes.0.insert(canonical, rust_id);
         Ok(())
     }
 
@@ -436,9 +707,201 @@ impl Directive for Subclass {
         let superclass: syn::LitStr = args.parse()?;
         args.parse::<syn::token::Comma>()?;
         let subclass: syn::Ident = args.parse()?;
+        let threadsafe = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let flag: syn::Ident = args.parse()?;
+            if flag != "threadsafe" {
+                return Err(syn::Error::new(flag.span(), "Expected threadsafe"));
+            }
+            true
+        } else {
+            false
+        };
         config.subclasses.push(crate::config::Subclass {
             superclass: superclass.value(),
             subclass,
+            threadsafe,
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .subclasses
+                .iter()
+                .filter(|sc| !config.is_future_wrapper_subclass(&sc.subclass.to_string()))
+                .map(|sc| {
+                    let superclass = &sc.superclass;
+                    let subclass = &sc.subclass;
+                    let threadsafe = sc.threadsafe.then(|| quote! { ,threadsafe });
+                    quote! {
+                        #superclass,#subclass #threadsafe
+                    }
+                }),
+        )
+    }
+}
+
+struct SubclassExtraCpp;
+
+impl Directive for SubclassExtraCpp {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let subclass: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let declarations: syn::LitStr = args.parse()?;
+        let definitions = if args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            Some(args.parse::<syn::LitStr>()?.value())
+        } else {
+            None
+        };
+        config
+            .subclass_extra_cpp
+            .push(crate::config::SubclassExtraCpp {
+                subclass: subclass.value(),
+                declarations: declarations.value(),
+                definitions,
+            });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.subclass_extra_cpp.iter().map(|extra| {
+            let subclass = &extra.subclass;
+            let declarations = &extra.declarations;
+            let definitions = extra.definitions.as_ref().map(|defs| quote! { ,#defs });
+            quote! {
+                #subclass, #declarations #definitions
+            }
+        }))
+    }
+}
+
+struct Operators;
+
+impl Directive for Operators {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        let mut operators = Vec::new();
+        let mut index_output = None;
+        while args.parse::<Option<syn::token::Comma>>()?.is_some() {
+            let symbol: syn::LitStr = args.parse()?;
+            let op = CppOperator::from_symbol(&symbol.value()).ok_or_else(|| {
+                syn::Error::new(
+                    symbol.span(),
+                    "Expected one of ==, <, +, -, *, /, %, +=, -=, *=, /=, %=, [], std::hash, <<",
+                )
+            })?;
+            if op == CppOperator::Index {
+                args.parse::<syn::token::RArrow>()?;
+                let output: syn::LitStr = args.parse()?;
+                index_output = Some(output.value());
+            }
+            if !operators.contains(&op) {
+                operators.push(op);
+            }
+        }
+        if operators.contains(&CppOperator::Lt) && !operators.contains(&CppOperator::Eq) {
+            return Err(syn::Error::new(
+                *ident_span,
+                "Operator < is mapped onto PartialOrd, which requires PartialEq, so operator == must be listed too",
+            ));
+        }
+        if operators.contains(&CppOperator::Hash) && !operators.contains(&CppOperator::Eq) {
+            return Err(syn::Error::new(
+                *ident_span,
+                "std::hash is mapped onto Hash, which requires Eq, so operator == must be listed too",
+            ));
+        }
+        config.operators.push(TypeOperators {
+            ty: ty.value(),
+            operators,
+            index_output,
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.operators.iter().map(|to| {
+            let ty = &to.ty;
+            let entries = to.operators.iter().map(|op| {
+                let symbol = op.symbol();
+                match (op, &to.index_output) {
+                    (CppOperator::Index, Some(output)) => quote! { #symbol -> #output },
+                    _ => quote! { #symbol },
+                }
+            });
+            quote! {
+                #ty #(,#entries)*
+            }
+        }))
+    }
+}
+
+struct ClosureParam;
+
+impl Directive for ClosureParam {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let param: syn::Ident = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let fn_trait: syn::Ident = args.parse()?;
+        if fn_trait != "FnMut" {
+            return Err(syn::Error::new(fn_trait.span(), "Expected FnMut"));
+        }
+        let signature: syn::ParenthesizedGenericArguments = args.parse()?;
+        let inputs: Vec<syn::Type> = signature.inputs.into_iter().collect();
+        let output = match signature.output {
+            syn::ReturnType::Default => None,
+            syn::ReturnType::Type(_, ty) => Some(*ty),
+        };
+        if let Some(bad) = inputs
+            .iter()
+            .find(|ty| !ClosureParamConfig::is_acceptable_type(ty, true))
+        {
+            return Err(syn::Error::new_spanned(
+                bad,
+                "Closure parameters must be primitive types or &CxxString",
+            ));
+        }
+        if let Some(bad) = output
+            .iter()
+            .find(|ty| !ClosureParamConfig::is_acceptable_type(ty, false))
+        {
+            return Err(syn::Error::new_spanned(
+                bad,
+                "Closures may only return primitive types",
+            ));
+        }
+        config.closure_params.push(ClosureParamConfig {
+            function: function.value(),
+            param,
+            inputs,
+            output,
         });
         Ok(())
     }
@@ -447,16 +910,567 @@ impl Directive for Subclass {
         &self,
         config: &'a IncludeCppConfig,
     ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
-        Box::new(config.subclasses.iter().map(|sc| {
-            let superclass = &sc.superclass;
-            let subclass = &sc.subclass;
+        Box::new(config.closure_params.iter().map(|cp| {
+            let function = &cp.function;
+            let param = &cp.param;
+            let inputs = &cp.inputs;
+            let output = cp.output.as_ref().map(|ty| quote! { -> #ty });
             quote! {
-                #superclass,#subclass
+                #function, #param, FnMut(#(#inputs),*) #output
             }
         }))
     }
 }
 
+struct FutureWrapper;
+
+impl FutureWrapper {
+    /// A `key = "value"` argument.
+    fn parse_named_arg(args: ParseStream, key: &str) -> ParseResult<syn::LitStr> {
+        args.parse::<syn::token::Comma>()?;
+        let ident: syn::Ident = args.parse()?;
+        if ident != key {
+            return Err(syn::Error::new(ident.span(), format!("Expected {key}")));
+        }
+        args.parse::<syn::token::Eq>()?;
+        args.parse()
+    }
+}
+
+impl Directive for FutureWrapper {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        let callback_param: syn::Ident = Self::parse_named_arg(args, "callback_param")?.parse()?;
+        let method = Self::parse_named_arg(args, "method")?;
+        let (superclass, method_name) = method.value().rsplit_once("::").map_or_else(
+            || {
+                Err(syn::Error::new(
+                    method.span(),
+                    "Expected the completion method qualified by its class, e.g. Callback::on_complete",
+                ))
+            },
+            |(superclass, method_name)| Ok((superclass.to_string(), method_name.to_string())),
+        )?;
+        let wrapper = FutureWrapperConfig {
+            function: function.value(),
+            callback_param,
+            superclass: superclass.clone(),
+            method: syn::parse_str(&method_name)?,
+        };
+        // The future is resolved by a subclass of the callback class,
+        // which the user has promised may be called from another thread.
+        config.subclasses.push(crate::config::Subclass {
+            superclass,
+            subclass: wrapper.subclass(),
+            threadsafe: true,
+        });
+        config.future_wrappers.push(wrapper);
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.future_wrappers.iter().map(|fw| {
+            let function = &fw.function;
+            let callback_param = fw.callback_param.to_string();
+            let method = format!("{}::{}", fw.superclass, fw.method);
+            quote! {
+                #function, callback_param = #callback_param, method = #method
+            }
+        }))
+    }
+}
+
+struct SliceParams;
+
+impl SliceParams {
+    /// A parameter name, given either as an identifier or a string.
+    fn parse_param(args: ParseStream) -> ParseResult<syn::Ident> {
+        if args.peek(syn::LitStr) {
+            let name: syn::LitStr = args.parse()?;
+            name.parse()
+                .map_err(|_| syn::Error::new(name.span(), "Expected a parameter name"))
+        } else {
+            args.parse()
+        }
+    }
+}
+
+impl Directive for SliceParams {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let data = Self::parse_param(args)?;
+        args.parse::<syn::token::Comma>()?;
+        let len = Self::parse_param(args)?;
+        if data == len {
+            return Err(syn::Error::new(
+                len.span(),
+                "The pointer and length must be different parameters",
+            ));
+        }
+        config.slice_params.push(SliceParamsConfig {
+            function: function.value(),
+            data,
+            len,
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.slice_params.iter().map(|sp| {
+            let function = &sp.function;
+            let data = &sp.data;
+            let len = &sp.len;
+            quote! {
+                #function, #data, #len
+            }
+        }))
+    }
+}
+
+struct OutArray;
+
+impl Directive for OutArray {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let function: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let data = if args.peek(syn::token::Return) {
+            args.parse::<syn::token::Return>()?;
+            None
+        } else {
+            Some(SliceParams::parse_param(args)?)
+        };
+        args.parse::<syn::token::Comma>()?;
+        let len = SliceParams::parse_param(args)?;
+        if data.as_ref() == Some(&len) {
+            return Err(syn::Error::new(
+                len.span(),
+                "The array and its length must be different parameters",
+            ));
+        }
+        let free_with = if args.peek(syn::token::Comma) {
+            args.parse::<syn::token::Comma>()?;
+            let key: syn::Ident = args.parse()?;
+            if key != "free_with" {
+                return Err(syn::Error::new(key.span(), "Expected free_with"));
+            }
+            args.parse::<syn::token::Eq>()?;
+            let free_with: syn::LitStr = args.parse()?;
+            Some(free_with.value())
+        } else {
+            None
+        };
+        config.out_arrays.push(OutArrayConfig {
+            function: function.value(),
+            data,
+            len,
+            free_with,
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.out_arrays.iter().map(|oa| {
+            let function = &oa.function;
+            let data = match &oa.data {
+                Some(data) => quote! { #data },
+                None => quote! { return },
+            };
+            let len = &oa.len;
+            let free_with = oa
+                .free_with
+                .as_ref()
+                .map(|free_with| quote! { , free_with = #free_with });
+            quote! {
+                #function, #data, #len #free_with
+            }
+        }))
+    }
+}
+
+enum DeclaredFunctionKind {
+    Friend,
+    LiteralOperator,
+    Instantiation,
+    CallOperator,
+}
+
+/// A directive giving the signature of some function which bindgen can't
+/// tell us about.
+struct DeclaredFunctions<SET, GET>(DeclaredFunctionKind, SET, GET)
+where
+    SET: Fn(&mut IncludeCppConfig) -> &mut Vec<DeclaredFunction>,
+    GET: Fn(&IncludeCppConfig) -> &Vec<DeclaredFunction>;
+
+impl<SET, GET> Directive for DeclaredFunctions<SET, GET>
+where
+    SET: Fn(&mut IncludeCppConfig) -> &mut Vec<DeclaredFunction> + Sync + Send,
+    GET: Fn(&IncludeCppConfig) -> &Vec<DeclaredFunction> + Sync + Send,
+{
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let name: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let rust_name = match self.0 {
+            DeclaredFunctionKind::Instantiation => {
+                let rust_name: syn::LitStr = args.parse()?;
+                args.parse::<syn::token::Comma>()?;
+                if syn::parse_str::<syn::Ident>(&rust_name.value()).is_err() {
+                    return Err(syn::Error::new(
+                        rust_name.span(),
+                        "Expected a valid Rust identifier",
+                    ));
+                }
+                Some(rust_name.value())
+            }
+            _ => None,
+        };
+        args.parse::<syn::token::Fn>()?;
+        let signature: syn::ParenthesizedGenericArguments = args.parse()?;
+        let function = DeclaredFunction {
+            name: name.value(),
+            rust_name,
+            inputs: signature.inputs.into_iter().collect(),
+            output: match signature.output {
+                syn::ReturnType::Default => None,
+                syn::ReturnType::Type(_, ty) => Some(*ty),
+            },
+        };
+        let (scope, item) = function.split_name();
+        match self.0 {
+            DeclaredFunctionKind::Friend if scope.is_empty() => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Expected the class followed by the function, e.g. \"Shape::merge\"",
+                ))
+            }
+            DeclaredFunctionKind::LiteralOperator if !item.starts_with('_') => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Expected a literal suffix beginning with an underscore, e.g. \"_px\"",
+                ))
+            }
+            DeclaredFunctionKind::Instantiation if !item.contains('<') || !item.ends_with('>') => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Expected a function template with its template arguments, e.g. \"Config::get<int>\"",
+                ))
+            }
+            DeclaredFunctionKind::CallOperator if function.inputs.is_empty() => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "Expected the object as the first parameter, e.g. fn(&Predicate, &Item) -> bool",
+                ))
+            }
+            _ => {}
+        }
+        self.1(config).push(function);
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(self.2(config).iter().map(|df| {
+            let name = &df.name;
+            let rust_name = df
+                .rust_name
+                .as_ref()
+                .map(|rust_name| quote! { #rust_name, });
+            let inputs = &df.inputs;
+            let output = df.output.as_ref().map(|ty| quote! { -> #ty });
+            quote! {
+                #name, #rust_name fn(#(#inputs),*) #output
+            }
+        }))
+    }
+}
+
+struct Rename;
+
+impl Directive for Rename {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let selector: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let rust_name: syn::LitStr = args.parse()?;
+        if syn::parse_str::<syn::Ident>(&rust_name.value()).is_err() {
+            return Err(syn::Error::new(
+                rust_name.span(),
+                "Expected a valid Rust identifier",
+            ));
+        }
+        if !selector.value().contains('(') {
+            config.name_renames.push(NameRename {
+                cpp_name: selector.value().trim().to_string(),
+                rust_name: rust_name.value(),
+            });
+            return Ok(());
+        }
+        let rename =
+            OverloadRename::new(&selector.value(), rust_name.value()).ok_or_else(|| {
+                syn::Error::new(
+                    selector.span(),
+                    "Expected a function and its parameter types, such as \"MyClass::set(double)\"",
+                )
+            })?;
+        config.overload_renames.push(rename);
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(
+            config
+                .overload_renames
+                .iter()
+                .map(|rename| (&rename.selector, &rename.rust_name))
+                .chain(
+                    config
+                        .name_renames
+                        .iter()
+                        .map(|rename| (&rename.cpp_name, &rename.rust_name)),
+                )
+                .map(|(selector, rust_name)| {
+                    quote! {
+                        #selector, #rust_name
+                    }
+                }),
+        )
+    }
+}
+
+struct ConstructorName;
+
+impl Directive for ConstructorName {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let ty: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let rust_name: syn::LitStr = args.parse()?;
+        if syn::parse_str::<syn::Ident>(&rust_name.value()).is_err() {
+            return Err(syn::Error::new(
+                rust_name.span(),
+                "Expected a valid Rust identifier",
+            ));
+        }
+        let ty_value = ty.value().trim().to_string();
+        if config.get_constructor_name(&ty_value).is_some() {
+            return Err(syn::Error::new(
+                ty.span(),
+                "This type was already named in another constructor_name! directive",
+            ));
+        }
+        config.constructor_names.push(ConstructorRename {
+            ty: ty_value,
+            rust_name: rust_name.value(),
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.constructor_names.iter().map(|name| {
+            let ty = &name.ty;
+            let rust_name = &name.rust_name;
+            quote! {
+                #ty, #rust_name
+            }
+        }))
+    }
+}
+
+struct MapNamespace;
+
+impl Directive for MapNamespace {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let cpp_namespace: syn::LitStr = args.parse()?;
+        args.parse::<syn::token::Comma>()?;
+        let rust_path: syn::LitStr = args.parse()?;
+        let cpp_namespace_value = cpp_namespace.value().trim().to_string();
+        if cpp_namespace_value.split("::").any(|segment| {
+            segment.is_empty()
+                || !segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }) {
+            return Err(syn::Error::new(
+                cpp_namespace.span(),
+                "Expected a C++ namespace, such as \"mylib::detail\"",
+            ));
+        }
+        let rust_path_value = rust_path.value().trim().to_string();
+        if !rust_path_value.is_empty()
+            && rust_path_value
+                .split("::")
+                .any(|segment| syn::parse_str::<syn::Ident>(segment).is_err())
+        {
+            return Err(syn::Error::new(
+                rust_path.span(),
+                "Expected a path of Rust mods, such as \"mylib::detail\", or \"\" for the generated mod itself",
+            ));
+        }
+        if config
+            .namespace_mappings
+            .iter()
+            .any(|mapping| mapping.cpp_namespace == cpp_namespace_value)
+        {
+            return Err(syn::Error::new(
+                cpp_namespace.span(),
+                "This namespace was already named in another map_namespace! directive",
+            ));
+        }
+        config.namespace_mappings.push(NamespaceMapping {
+            cpp_namespace: cpp_namespace_value,
+            rust_path: rust_path_value,
+        });
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.namespace_mappings.iter().map(|mapping| {
+            let cpp_namespace = &mapping.cpp_namespace;
+            let rust_path = &mapping.rust_path;
+            quote! {
+                #cpp_namespace, #rust_path
+            }
+        }))
+    }
+}
+
+struct ImportFrom;
+
+impl Directive for ImportFrom {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        config.imports.push(args.parse()?);
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        Box::new(config.imports.iter().map(|path| path.to_token_stream()))
+    }
+}
+
+struct FnSafetyOverride(FnSafety);
+
+impl Directive for FnSafetyOverride {
+    fn parse(
+        &self,
+        args: ParseStream,
+        config: &mut IncludeCppConfig,
+        _ident_span: &Span,
+    ) -> ParseResult<()> {
+        let selector: syn::LitStr = args.parse()?;
+        let safety_override = SafetyOverride::new(&selector.value(), self.0).ok_or_else(|| {
+            syn::Error::new(
+                selector.span(),
+                "Expected a function, optionally with its parameter types, such as \"MyClass::set\" or \"MyClass::set(double*)\"",
+            )
+        })?;
+        if let Some(conflict) = config
+            .safety_overrides
+            .iter()
+            .find(|existing| existing.safety != self.0 && existing.overlaps(&safety_override))
+        {
+            return Err(syn::Error::new(
+                selector.span(),
+                format!(
+                    "\"{}\" was already marked {} by \"{}\", so it can't also be marked {}",
+                    safety_override.function,
+                    fn_safety_directive(conflict.safety),
+                    conflict.selector,
+                    fn_safety_directive(self.0)
+                ),
+            ));
+        }
+        config.safety_overrides.push(safety_override);
+        Ok(())
+    }
+
+    fn output<'a>(
+        &self,
+        config: &'a IncludeCppConfig,
+    ) -> Box<dyn Iterator<Item = TokenStream> + 'a> {
+        let safety = self.0;
+        Box::new(
+            config
+                .safety_overrides
+                .iter()
+                .filter(move |safety_override| safety_override.safety == safety)
+                .map(|safety_override| {
+                    let selector = &safety_override.selector;
+                    quote! {
+                        #selector
+                    }
+                }),
+        )
+    }
+}
+
+fn fn_safety_directive(safety: FnSafety) -> &'static str {
+    match safety {
+        FnSafety::Safe => "safe_fn!",
+        FnSafety::Unsafe => "unsafe_fn!",
+    }
+}
+
 struct ExternRustFun;
 
 impl Directive for ExternRustFun {
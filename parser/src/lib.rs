@@ -8,15 +8,22 @@
 
 #![forbid(unsafe_code)]
 
+mod cfg;
 mod config;
 mod directives;
 pub mod file_locations;
 mod multi_bindings;
+mod name_pattern;
 mod path;
 mod subclass_attrs;
+mod template_name;
 
+pub use cfg::is_cfg_enabled;
 pub use config::{
-    AllowlistEntry, ExternCppType, IncludeCppConfig, RustFun, Subclass, UnsafePolicy,
+    AllowlistEntry, ClosureParam, ConstructorRename, CppOperator, DeclaredFunction, ExternCppType,
+    FnSafety, FutureWrapper, IncludeCppConfig, NameRename, NamespaceMapping, OutArray,
+    OverloadRename, RustFun, SafetyOverride, SliceParams, Subclass, SubclassExtraCpp,
+    TypeOperators, UnsafePolicy,
 };
 use file_locations::FileLocationStrategy;
 pub use multi_bindings::{MultiBindings, MultiBindingsErr};
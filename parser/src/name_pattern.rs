@@ -0,0 +1,123 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Wildcards in the names given to `generate!`, `generate_pod!` and
+//! `block!`. A `*` matches any sequence of characters, including `::`,
+//! so `generate!("Sdl*")` or `block!("internal::detail::*")`. To match a
+//! literal `*` (as in the name of an operator) write `\*`.
+
+/// Splits a name at its wildcards, unescaping any literal `*`s.
+fn segments(name: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'*') => {
+                chars.next();
+                segments.last_mut().unwrap().push('*');
+            }
+            '*' => segments.push(String::new()),
+            _ => segments.last_mut().unwrap().push(c),
+        }
+    }
+    segments
+}
+
+/// Whether this name contains any wildcards.
+pub(crate) fn is_pattern(name: &str) -> bool {
+    segments(name).len() > 1
+}
+
+/// Whether this name or pattern from a directive matches the given C++
+/// name.
+pub(crate) fn matches(name: &str, cpp_name: &str) -> bool {
+    match segments(name).as_slice() {
+        [first, middle @ .., last] => {
+            match cpp_name
+                .strip_prefix(first.as_str())
+                .and_then(|rest| rest.strip_suffix(last.as_str()))
+            {
+                Some(mut rest) => middle
+                    .iter()
+                    .all(|segment| match rest.find(segment.as_str()) {
+                        Some(idx) => {
+                            rest = &rest[idx + segment.len()..];
+                            true
+                        }
+                        None => false,
+                    }),
+                None => false,
+            }
+        }
+        [exact] => exact == cpp_name,
+        [] => unreachable!("there's always at least one segment"),
+    }
+}
+
+/// Whether this name from a directive is exactly the given C++ name,
+/// rather than a pattern which matches it.
+pub(crate) fn is_exactly(name: &str, cpp_name: &str) -> bool {
+    !is_pattern(name) && matches(name, cpp_name)
+}
+
+/// The name, with any escaped `*`s unescaped, if it isn't a pattern.
+pub(crate) fn unescape(name: &str) -> String {
+    segments(name).join("*")
+}
+
+/// The regular expression which bindgen should use for this pattern.
+pub(crate) fn to_regex(pattern: &str) -> String {
+    segments(pattern)
+        .iter()
+        .map(|segment| {
+            segment
+                .chars()
+                .map(|c| {
+                    if r"\.+*?()|[]{}^$".contains(c) {
+                        format!("\\{c}")
+                    } else {
+                        c.to_string()
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(".*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_exactly, is_pattern, matches, to_regex};
+
+    #[test]
+    fn test_name_patterns() {
+        assert!(!is_pattern("foo::Bar"));
+        assert!(is_pattern("Sdl*"));
+        assert!(!is_pattern("operator\\*"));
+        assert!(matches("Sdl*", "SdlWindow"));
+        assert!(matches("Sdl*", "Sdl"));
+        assert!(!matches("Sdl*", "MySdlWindow"));
+        assert!(matches("*_impl", "widget_impl"));
+        assert!(!matches("*_impl", "widget_impl2"));
+        assert!(matches("internal::detail::*", "internal::detail::a::B"));
+        assert!(!matches("internal::detail::*", "internal::Other"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxcyyb"));
+        assert!(!matches("ab*ba", "aba"));
+        assert!(matches("foo::Bar", "foo::Bar"));
+        assert!(!matches("foo::Bar", "foo::Bar2"));
+        assert!(matches("operator\\*", "operator*"));
+        assert!(!matches("operator\\*", "operator*="));
+        assert!(matches("operator\\**", "operator*="));
+        assert!(is_exactly("foo::Bar", "foo::Bar"));
+        assert!(!is_exactly("foo::*", "foo::Bar"));
+        assert_eq!(to_regex("ns::Sdl*"), "ns::Sdl.*");
+        assert_eq!(to_regex("operator\\**"), "operator\\*.*");
+    }
+}
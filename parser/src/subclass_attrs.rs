@@ -17,6 +17,7 @@ use syn::{
 #[derive(Default)]
 pub struct SubclassAttrs {
     pub self_owned: bool,
+    pub on_peer_destroyed: bool,
     pub superclass: Option<String>,
 }
 
@@ -27,6 +28,7 @@ impl Parse for SubclassAttrs {
         while id.is_some() {
             match id {
                 Some(id) if id == "self_owned" => me.self_owned = true,
+                Some(id) if id == "on_peer_destroyed" => me.on_peer_destroyed = true,
                 Some(id) if id == "superclass" => {
                     let args;
                     syn::parenthesized!(args in input);
@@ -42,7 +44,7 @@ impl Parse for SubclassAttrs {
                 Some(id) => {
                     return Err(syn::Error::new_spanned(
                         id.into_token_stream(),
-                        "Expected self_owned or superclass",
+                        "Expected self_owned, on_peer_destroyed or superclass",
                     ))
                 }
                 None => {}
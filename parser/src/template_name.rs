@@ -0,0 +1,196 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The template instantiations named in `concrete!`, such as
+//! `std::map<std::string, std::vector<mylib::Node>>`. We spell each of
+//! them in a canonical way, so that they can be matched against the
+//! instantiations we find in function signatures regardless of how the
+//! user happened to space them.
+
+/// A C++ type name and its template arguments, if any.
+#[derive(Debug, PartialEq, Eq)]
+struct TemplateName {
+    name: String,
+    args: Vec<TemplateName>,
+}
+
+impl TemplateName {
+    fn parse(definition: &str) -> Result<Self, String> {
+        let definition = definition.trim();
+        match definition.split_once('<') {
+            None => Ok(Self {
+                name: canonical_name(definition)?,
+                args: Vec::new(),
+            }),
+            Some((name, rest)) => {
+                let args = rest
+                    .strip_suffix('>')
+                    .ok_or_else(|| format!("`{definition}` doesn't end with `>`"))?;
+                Ok(Self {
+                    name: canonical_name(name)?,
+                    args: split_args(args, definition)?
+                        .into_iter()
+                        .map(Self::parse)
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+        }
+    }
+
+    /// Replaces any arguments which are the names of other `concrete!`
+    /// types with their definitions. `depth` guards against aliases which
+    /// refer to each other.
+    fn expand_aliases<'a>(&mut self, aliases: &impl Fn(&str) -> Option<&'a str>, depth: usize) {
+        for arg in &mut self.args {
+            if arg.args.is_empty() && depth > 0 {
+                if let Some(Ok(definition)) = aliases(&arg.name).map(Self::parse) {
+                    *arg = definition;
+                    arg.expand_aliases(aliases, depth - 1);
+                    continue;
+                }
+            }
+            arg.expand_aliases(aliases, depth);
+        }
+    }
+}
+
+impl std::fmt::Display for TemplateName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.args.is_empty() {
+            let args: Vec<_> = self.args.iter().map(|arg| arg.to_string()).collect();
+            write!(f, "<{}>", args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits template arguments on top-level commas only, since they may
+/// themselves be templates.
+fn split_args<'a>(args: &'a str, definition: &str) -> Result<Vec<&'a str>, String> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("the `<`s and `>`s in `{definition}` don't match"))?
+            }
+            ',' if depth == 0 => {
+                split.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("the `<`s and `>`s in `{definition}` don't match"));
+    }
+    split.push(&args[start..]);
+    Ok(split)
+}
+
+/// The name of a type, e.g. `mylib::Node` or `unsigned int`, without any
+/// leading `::` or redundant whitespace.
+fn canonical_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    let segments = name
+        .trim_start_matches("::")
+        .split("::")
+        .map(|segment| {
+            let words: Vec<_> = segment.split_whitespace().collect();
+            let valid = !words.is_empty()
+                && words.iter().all(|word| {
+                    !word.starts_with(|c: char| c.is_ascii_digit())
+                        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        && !matches!(*word, "true" | "false" | "nullptr")
+                });
+            match valid {
+                true => Ok(words.join(" ")),
+                false => Err(format!(
+                    "`{name}` isn't a type; concrete! only supports types as template arguments"
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(segments.join("::"))
+}
+
+/// The canonical spelling of a template instantiation named in
+/// `concrete!`, or a description of what's wrong with it.
+pub(crate) fn canonicalize(definition: &str) -> Result<String, String> {
+    TemplateName::parse(definition).map(|parsed| parsed.to_string())
+}
+
+/// The given (canonical) definition, with any template arguments which
+/// name other `concrete!` types replaced by their definitions.
+pub(crate) fn expand_aliases<'a>(
+    definition: &str,
+    aliases: impl Fn(&str) -> Option<&'a str>,
+    max_depth: usize,
+) -> String {
+    match TemplateName::parse(definition) {
+        Ok(mut parsed) => {
+            parsed.expand_aliases(&aliases, max_depth);
+            parsed.to_string()
+        }
+        Err(_) => definition.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonicalize, expand_aliases};
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(
+            canonicalize(" std::vector< std::shared_ptr<::mylib::Node> > ").unwrap(),
+            "std::vector<std::shared_ptr<mylib::Node>>"
+        );
+        assert_eq!(
+            canonicalize("std::map<std::string,mylib::Config>").unwrap(),
+            "std::map<std::string, mylib::Config>"
+        );
+        assert_eq!(
+            canonicalize("Pair<unsigned  int, Holder<A, B<C>>>").unwrap(),
+            "Pair<unsigned int, Holder<A, B<C>>>"
+        );
+        assert_eq!(canonicalize("Container<B>").unwrap(), "Container<B>");
+        assert!(canonicalize("std::array<int, 4>")
+            .unwrap_err()
+            .contains("`4`"));
+        assert!(canonicalize("Flag<true>").unwrap_err().contains("`true`"));
+        assert!(canonicalize("std::vector<int*>")
+            .unwrap_err()
+            .contains("`int*`"));
+        assert!(canonicalize("A<B<C>").is_err());
+        assert!(canonicalize("A<B>>").is_err());
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = |name: &str| match name {
+            "NodePtrVec" => Some("std::vector<std::shared_ptr<mylib::Node>>"),
+            "Loop" => Some("Holder<Loop>"),
+            _ => None,
+        };
+        assert_eq!(
+            expand_aliases("std::map<std::string, NodePtrVec>", aliases, 2),
+            "std::map<std::string, std::vector<std::shared_ptr<mylib::Node>>>"
+        );
+        assert_eq!(expand_aliases("NodePtrVec", aliases, 2), "NodePtrVec");
+        assert_eq!(
+            expand_aliases("Holder<Loop>", aliases, 2),
+            "Holder<Holder<Holder<Loop>>>"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2023 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::c_void;
+
+/// A Rust closure which C or C++ can call through a plain function pointer
+/// and a `void*` of "user data", for APIs such as
+/// `void set_handler(void (*cb)(int, void* user), void* user)`.
+///
+/// The function pointer is a trampoline which treats the user data as a
+/// pointer to the closure, and calls it:
+///
+/// ```rust,ignore
+/// let mut total = 0;
+/// let mut callback = autocxx::CCallback::new(|value: autocxx::c_int| total += value.0);
+/// unsafe { ffi::set_handler(callback.function(), callback.user_data()) };
+/// ffi::fire_handler(); // calls the closure
+/// ```
+///
+/// Use [`CCallback::function`] where the user data is the function pointer's
+/// last parameter, and [`CCallback::function_user_data_first`] where it's
+/// the first. The closure's parameters and return type must match those of
+/// the function pointer, other than the user data; closures of up to eight
+/// parameters are supported.
+///
+/// # Safety
+///
+/// Nothing ties the registered function pointer and user data to this
+/// object, so you must keep it alive (and not move the closure out of it)
+/// for as long as C++ may call the callback; if it should never be
+/// dropped, leak it with [`std::mem::forget`]. The closure mustn't be
+/// called re-entrantly, nor from several threads at once unless it's
+/// [`Sync`]. If the closure panics, the program aborts, since panics can't
+/// unwind into C++.
+pub struct CCallback<F> {
+    closure: Box<F>,
+}
+
+impl<F> CCallback<F> {
+    pub fn new(closure: F) -> Self {
+        Self {
+            closure: Box::new(closure),
+        }
+    }
+
+    /// The user data to pass along with the function pointer.
+    pub fn user_data(&mut self) -> *mut c_void {
+        &mut *self.closure as *mut F as *mut c_void
+    }
+
+    /// A function pointer which calls the closure, for APIs where the user
+    /// data is the last parameter of the function pointer.
+    pub fn function<Args>(&self) -> Option<<F as CCallbackFn<Args>>::UserDataLast>
+    where
+        F: CCallbackFn<Args>,
+    {
+        Some(F::user_data_last())
+    }
+
+    /// A function pointer which calls the closure, for APIs where the user
+    /// data is the first parameter of the function pointer.
+    pub fn function_user_data_first<Args>(&self) -> Option<<F as CCallbackFn<Args>>::UserDataFirst>
+    where
+        F: CCallbackFn<Args>,
+    {
+        Some(F::user_data_first())
+    }
+}
+
+/// Closures which [`CCallback`] can call from a C function pointer, where
+/// `Args` is the tuple of the closure's parameter types.
+pub trait CCallbackFn<Args> {
+    /// The function pointer type with the user data last.
+    type UserDataLast;
+    /// The function pointer type with the user data first.
+    type UserDataFirst;
+    /// A trampoline which calls this type of closure, passed as the last
+    /// parameter.
+    fn user_data_last() -> Self::UserDataLast;
+    /// A trampoline which calls this type of closure, passed as the first
+    /// parameter.
+    fn user_data_first() -> Self::UserDataFirst;
+}
+
+macro_rules! impl_c_callback_fn {
+    ($($arg:ident: $ty:ident),*) => {
+        impl<F, R, $($ty),*> CCallbackFn<($($ty,)*)> for F
+        where
+            F: FnMut($($ty),*) -> R,
+        {
+            type UserDataLast = unsafe extern "C" fn($($ty,)* *mut c_void) -> R;
+            type UserDataFirst = unsafe extern "C" fn(*mut c_void, $($ty),*) -> R;
+
+            fn user_data_last() -> Self::UserDataLast {
+                unsafe extern "C" fn trampoline<F: FnMut($($ty),*) -> R, R, $($ty),*>(
+                    $($arg: $ty,)*
+                    user_data: *mut c_void,
+                ) -> R {
+                    (*(user_data as *mut F))($($arg),*)
+                }
+                trampoline::<F, R, $($ty),*>
+            }
+
+            fn user_data_first() -> Self::UserDataFirst {
+                unsafe extern "C" fn trampoline<F: FnMut($($ty),*) -> R, R, $($ty),*>(
+                    user_data: *mut c_void,
+                    $($arg: $ty),*
+                ) -> R {
+                    (*(user_data as *mut F))($($arg),*)
+                }
+                trampoline::<F, R, $($ty),*>
+            }
+        }
+    };
+}
+
+impl_c_callback_fn!();
+impl_c_callback_fn!(a: A);
+impl_c_callback_fn!(a: A, b: B);
+impl_c_callback_fn!(a: A, b: B, c: C);
+impl_c_callback_fn!(a: A, b: B, c: C, d: D);
+impl_c_callback_fn!(a: A, b: B, c: C, d: D, e: E);
+impl_c_callback_fn!(a: A, b: B, c: C, d: D, e: E, f: G);
+impl_c_callback_fn!(a: A, b: B, c: C, d: D, e: E, f: G, g: H);
+impl_c_callback_fn!(a: A, b: B, c: C, d: D, e: E, f: G, g: H, h: I);
@@ -0,0 +1,121 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for passing `std::chrono` types between Rust and C++.
+//!
+//! A `std::chrono::duration` of any representation and period is
+//! represented in Rust as a [`std::time::Duration`]. A
+//! `std::chrono::time_point` of one of the standard clocks is represented
+//! as a [`TimePoint`] for the corresponding marker type here. Either way,
+//! the value crosses the language boundary as a signed 64-bit count of
+//! nanoseconds, so values which don't fit saturate (and panic in debug
+//! builds).
+
+use std::{fmt::Debug, marker::PhantomData, time::Duration};
+
+/// Marker type for `std::chrono::system_clock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemClock;
+
+/// Marker type for `std::chrono::steady_clock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SteadyClock;
+
+/// Marker type for `std::chrono::high_resolution_clock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HighResolutionClock;
+
+/// A `std::chrono::time_point` of the clock `C`, as a number of
+/// nanoseconds since that clock's epoch. This may be negative.
+pub struct TimePoint<C> {
+    nanos_since_epoch: i64,
+    clock: PhantomData<C>,
+}
+
+impl<C> TimePoint<C> {
+    /// Creates the time point this many nanoseconds after the clock's epoch.
+    pub fn from_nanos_since_epoch(nanos_since_epoch: i64) -> Self {
+        Self {
+            nanos_since_epoch,
+            clock: PhantomData,
+        }
+    }
+
+    /// The number of nanoseconds since the clock's epoch.
+    pub fn nanos_since_epoch(&self) -> i64 {
+        self.nanos_since_epoch
+    }
+
+    /// The time since the clock's epoch, or `None` if this time point is
+    /// before the epoch.
+    pub fn duration_since_epoch(&self) -> Option<Duration> {
+        u64::try_from(self.nanos_since_epoch)
+            .ok()
+            .map(Duration::from_nanos)
+    }
+}
+
+// Implemented by hand so as not to require anything of the marker type.
+impl<C> Clone for TimePoint<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for TimePoint<C> {}
+
+impl<C> PartialEq for TimePoint<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.nanos_since_epoch == other.nanos_since_epoch
+    }
+}
+
+impl<C> Eq for TimePoint<C> {}
+
+impl<C> PartialOrd for TimePoint<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C> Ord for TimePoint<C> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.nanos_since_epoch.cmp(&other.nanos_since_epoch)
+    }
+}
+
+impl<C> Debug for TimePoint<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimePoint")
+            .field("clock", &std::any::type_name::<C>())
+            .field("nanos_since_epoch", &self.nanos_since_epoch)
+            .finish()
+    }
+}
+
+#[doc(hidden)]
+pub fn duration_to_nanos(duration: Duration) -> i64 {
+    match i64::try_from(duration.as_nanos()) {
+        Ok(nanos) => nanos,
+        Err(_) => {
+            debug_assert!(false, "{duration:?} is too long to pass to C++");
+            i64::MAX
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn duration_from_nanos(nanos: i64) -> Duration {
+    match u64::try_from(nanos) {
+        Ok(nanos) => Duration::from_nanos(nanos),
+        Err(_) => {
+            debug_assert!(false, "C++ returned a negative duration of {nanos}ns");
+            Duration::ZERO
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Futures which resolve when C++ calls a completion method, for the
+//! wrappers generated by [`future_wrapper!`](crate::future_wrapper).
+//!
+//! The subclass of the C++ callback class which autocxx generates holds a
+//! [`Completer`], and its override of the completion method passes on its
+//! argument, which resolves the corresponding [`Completion`]. The C++
+//! library may do that on any thread.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+struct State<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Creates a linked [`Completer`] and [`Completion`].
+pub fn completion<T>() -> (Completer<T>, Completion<T>) {
+    let state = Arc::new(Mutex::new(State {
+        value: None,
+        waker: None,
+    }));
+    (Completer(state.clone()), Completion(state))
+}
+
+/// The means by which a completion method resolves its [`Completion`].
+pub struct Completer<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Completer<T> {
+    /// Resolves the [`Completion`] with this value. If the [`Completion`]
+    /// has already been dropped, the value is dropped too.
+    pub fn complete(self, value: T) {
+        let waker = {
+            let mut state = self.0.lock().unwrap();
+            state.value = Some(value);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// A future which resolves to the argument with which C++ calls a
+/// completion method.
+///
+/// If C++ never calls the completion method, this never resolves.
+pub struct Completion<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Future for Completion<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.0.lock().unwrap();
+        match state.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
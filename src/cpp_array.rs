@@ -0,0 +1,137 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for arrays which C++ functions return through pointers, as
+//! described by `out_array!` directives.
+
+use std::fmt::Debug;
+use std::ops::Deref;
+
+/// An array returned by a C++ function listed in an `out_array!`
+/// directive. It dereferences to a slice of the array's elements.
+///
+/// If the directive said `free_with`, this owns the array, and frees it
+/// using that C++ function when dropped. Otherwise the array still belongs
+/// to C++, and remains valid only for as long as C++ says: that's why such
+/// functions are `unsafe`.
+pub struct CppArray<T> {
+    ptr: *mut T,
+    len: usize,
+    free: Option<unsafe extern "C" fn(*mut crate::c_void)>,
+}
+
+impl<T> CppArray<T> {
+    /// Used by the code which autocxx generates.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or point to `len` initialized elements, which
+    /// remain valid for the lifetime of this object. `free` must be null or
+    /// a C++ function which frees the array.
+    #[doc(hidden)]
+    pub unsafe fn from_raw_parts(
+        ptr: *mut T,
+        len: impl ArrayLength,
+        free: *mut crate::c_void,
+    ) -> Self {
+        Self {
+            ptr,
+            len: len.to_array_length(),
+            free: std::mem::transmute::<
+                *mut crate::c_void,
+                Option<unsafe extern "C" fn(*mut crate::c_void)>,
+            >(free),
+        }
+    }
+
+    /// A pointer to the first element, which is null if C++ didn't return
+    /// an array at all.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+
+    /// Whether dropping this object frees the array.
+    pub fn is_owned(&self) -> bool {
+        self.free.is_some()
+    }
+}
+
+impl<T> Deref for CppArray<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        if self.ptr.is_null() {
+            &[]
+        } else {
+            // Safety: as promised to from_raw_parts.
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+    }
+}
+
+impl<T: Debug> Debug for CppArray<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Drop for CppArray<T> {
+    fn drop(&mut self) {
+        if let Some(free) = self.free {
+            if !self.ptr.is_null() {
+                // Safety: as promised to from_raw_parts.
+                unsafe { free(self.ptr as *mut crate::c_void) }
+            }
+        }
+    }
+}
+
+/// A type which C++ functions may use for the length of a [CppArray].
+/// A negative length is taken to mean that there's no array.
+#[doc(hidden)]
+pub trait ArrayLength {
+    fn to_array_length(self) -> usize;
+}
+
+macro_rules! array_length {
+    ($($t:ty),*) => {
+        $(
+            impl ArrayLength for $t {
+                fn to_array_length(self) -> usize {
+                    usize::try_from(self).unwrap_or(0)
+                }
+            }
+        )*
+    };
+}
+
+array_length!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! ctype_array_length {
+    ($($t:ident),*) => {
+        $(
+            impl ArrayLength for crate::$t {
+                fn to_array_length(self) -> usize {
+                    self.0.to_array_length()
+                }
+            }
+        )*
+    };
+}
+
+ctype_array_length!(
+    c_int,
+    c_uint,
+    c_long,
+    c_ulong,
+    c_longlong,
+    c_ulonglong,
+    c_short,
+    c_ushort,
+    c_uchar
+);
@@ -0,0 +1,62 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for returning C++ exceptions to Rust as errors.
+
+use std::fmt::Display;
+
+/// Separates the type name of a `std::exception` from its `what()`
+/// string in the messages thrown by autocxx's C++ wrapper functions.
+/// This must match the separator used by the autocxx engine.
+const TYPE_NAME_SEPARATOR: char = '\x1f';
+
+/// A C++ exception thrown by a function which was listed in a
+/// `generate_fallible!` or `fallible!` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CppException {
+    what: String,
+    type_name: Option<String>,
+}
+
+impl CppException {
+    /// The message from the exception. For a `std::exception`, this is
+    /// whatever `what()` returned.
+    pub fn what(&self) -> &str {
+        &self.what
+    }
+
+    /// The name of the C++ type of the exception, as given by
+    /// `typeid(...).name()`. With most compilers this is a mangled name.
+    /// This is `None` if the thrown object was not a `std::exception`.
+    pub fn type_name(&self) -> Option<&str> {
+        self.type_name.as_deref()
+    }
+}
+
+impl Display for CppException {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.what)
+    }
+}
+
+impl std::error::Error for CppException {}
+
+impl From<cxx::Exception> for CppException {
+    fn from(exception: cxx::Exception) -> Self {
+        match exception.what().split_once(TYPE_NAME_SEPARATOR) {
+            Some((type_name, what)) => Self {
+                what: what.to_string(),
+                type_name: Some(type_name.to_string()),
+            },
+            None => Self {
+                what: exception.what().to_string(),
+                type_name: None,
+            },
+        }
+    }
+}
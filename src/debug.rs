@@ -0,0 +1,56 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for the `Debug` implementations which we generate for POD types.
+//!
+//! Those print each field, but we can't tell from the bindings whether
+//! each field's type implements `Debug`: it might be a C++ type for which
+//! we don't generate an implementation. So the generated code calls
+//! `(&&DebugField(&self.field)).debug_field()`, which resolves to
+//! [`ViaDebug`] if the field's type implements `Debug`, or otherwise
+//! (because method resolution has to dereference the receiver once more)
+//! to [`ViaTypeName`], which prints just the name of the type.
+
+use std::fmt::{Debug, Formatter, Result};
+
+pub struct DebugField<'a, T>(pub &'a T);
+
+/// How to print a field.
+pub enum DebugFieldValue<'a> {
+    Debug(&'a dyn Debug),
+    TypeName(&'static str),
+}
+
+impl Debug for DebugFieldValue<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Debug(value) => value.fmt(f),
+            Self::TypeName(name) => write!(f, "<{name}>"),
+        }
+    }
+}
+
+pub trait ViaDebug {
+    fn debug_field(&self) -> DebugFieldValue<'_>;
+}
+
+impl<T: Debug> ViaDebug for &DebugField<'_, T> {
+    fn debug_field(&self) -> DebugFieldValue<'_> {
+        DebugFieldValue::Debug(self.0)
+    }
+}
+
+pub trait ViaTypeName {
+    fn debug_field(&self) -> DebugFieldValue<'_>;
+}
+
+impl<T> ViaTypeName for DebugField<'_, T> {
+    fn debug_field(&self) -> DebugFieldValue<'_> {
+        DebugFieldValue::TypeName(std::any::type_name::<T>())
+    }
+}
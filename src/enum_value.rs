@@ -0,0 +1,48 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for converting integers into C++ enums.
+
+use std::fmt::{Debug, Display};
+
+/// The error returned when converting an integer into a C++ enum using
+/// `TryFrom`, if the integer isn't the value of any of its enumerators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidEnumValue<T> {
+    value: T,
+    enum_name: &'static str,
+}
+
+impl<T> InvalidEnumValue<T> {
+    #[doc(hidden)]
+    pub fn new(value: T, enum_name: &'static str) -> Self {
+        Self { value, enum_name }
+    }
+
+    /// The integer which couldn't be converted.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// The C++ name of the enum.
+    pub fn enum_name(&self) -> &'static str {
+        self.enum_name
+    }
+}
+
+impl<T: Display> Display for InvalidEnumValue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not the value of any enumerator of {}",
+            self.value, self.enum_name
+        )
+    }
+}
+
+impl<T: Debug + Display> std::error::Error for InvalidEnumValue<T> {}
@@ -0,0 +1,111 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for types which must be destroyed explicitly, rather than
+//! whenever some owning pointer happens to be dropped.
+
+use moveit::{MakeCppStorage, New};
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::ptr::NonNull;
+
+/// A type which can be destroyed by an explicit call to its C++ destructor.
+/// This is implemented by autocxx for any type listed in an
+/// `explicit_destructor!` directive.
+///
+/// # Safety
+///
+/// Implementers must guarantee that `destroy_explicitly` runs the C++
+/// destructor of the object and then frees its storage as
+/// [`MakeCppStorage::free_uninitialized_cpp_storage`] would. (That
+/// storage needn't have come from `new`, so `delete p;` won't do.)
+pub unsafe trait ExplicitDestructor: MakeCppStorage {
+    /// Run the C++ destructor and free the storage.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to an initialized object in storage from
+    /// [`MakeCppStorage::allocate_uninitialized_cpp_storage`], and must
+    /// not be used again.
+    #[doc(hidden)]
+    unsafe fn destroy_explicitly(arg0: *mut Self);
+}
+
+/// An owning pointer to a C++ object which must be destroyed by an explicit
+/// call to [`ExplicitlyDestroyed::destroy`]. This allows the order of
+/// destruction of C++ objects to be controlled precisely.
+///
+/// Types listed in `explicit_destructor!` can't be held in a
+/// [`cxx::UniquePtr`] or [`cxx::SharedPtr`], which would destroy them
+/// whenever they were dropped, so this is how to own them. It derefs to
+/// the object itself, so its methods can be called directly; use
+/// [`ExplicitlyDestroyed::pin_mut`] for those which need it to be mutable.
+///
+/// Dropping this without calling [`ExplicitlyDestroyed::destroy`] never
+/// runs the C++ destructor. In debug builds it panics, so that such
+/// mistakes are noticed; in release builds the object is leaked.
+pub struct ExplicitlyDestroyed<T: ExplicitDestructor> {
+    ptr: NonNull<T>,
+    destroyed: bool,
+}
+
+impl<T: ExplicitDestructor> ExplicitlyDestroyed<T> {
+    /// Construct a C++ object, such as by `ffi::Session::new(...)`, such
+    /// that it can only be destroyed by calling
+    /// [`ExplicitlyDestroyed::destroy`].
+    pub fn new(n: impl New<Output = T>) -> Self {
+        // Safety: this is what moveit does to emplace into a UniquePtr.
+        // Like that, it leaks the storage if the constructor panics.
+        unsafe {
+            let storage = T::allocate_uninitialized_cpp_storage();
+            n.new(Pin::new_unchecked(&mut *(storage as *mut MaybeUninit<T>)));
+            Self {
+                ptr: NonNull::new_unchecked(storage),
+                destroyed: false,
+            }
+        }
+    }
+
+    /// Returns a mutable pinned reference to the object.
+    pub fn pin_mut(&mut self) -> Pin<&mut T> {
+        // Safety: we own the object, and never move it.
+        unsafe { Pin::new_unchecked(self.ptr.as_mut()) }
+    }
+
+    /// Returns a raw pointer to the object, which remains valid until
+    /// [`ExplicitlyDestroyed::destroy`] is called.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Run the C++ destructor for this object, now.
+    pub fn destroy(mut self) {
+        self.destroyed = true;
+        // Safety: the object was constructed in storage from
+        // allocate_uninitialized_cpp_storage, and since we consume self,
+        // it's never used again.
+        unsafe { T::destroy_explicitly(self.ptr.as_ptr()) }
+    }
+}
+
+impl<T: ExplicitDestructor> Deref for ExplicitlyDestroyed<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: the object remains alive until destroy() consumes self.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ExplicitDestructor> Drop for ExplicitlyDestroyed<T> {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && !self.destroyed && !std::thread::panicking() {
+            panic!("An object requiring explicit destruction was dropped without destroy() having been called");
+        }
+    }
+}
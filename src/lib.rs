@@ -16,11 +16,28 @@
 // do anything - all the magic is handled entirely by
 // autocxx_macro::include_cpp_impl.
 
+mod c_callback;
+pub mod chrono;
+pub mod completion;
+mod cpp_array;
+mod cpp_exception;
+#[doc(hidden)]
+pub mod debug;
+mod enum_value;
+mod explicit_destructor;
+pub mod path;
 mod reference_wrapper;
 mod rvalue_param;
 pub mod subclass;
 mod value_param;
 
+pub use c_callback::{CCallback, CCallbackFn};
+#[doc(hidden)]
+pub use cpp_array::ArrayLength;
+pub use cpp_array::CppArray;
+pub use cpp_exception::CppException;
+pub use enum_value::InvalidEnumValue;
+pub use explicit_destructor::{ExplicitDestructor, ExplicitlyDestroyed};
 pub use reference_wrapper::{AsCppMutRef, AsCppRef, CppMutRef, CppPin, CppRef, CppUniquePtrPin};
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
@@ -122,6 +139,9 @@ macro_rules! include {
 }
 
 /// Generate Rust bindings for the given C++ type or function.
+/// The name may contain `*` wildcards, such as `generate!("Sdl*")`,
+/// in which case bindings are generated for everything matching it
+/// (and a warning is given if nothing does). Use `\\*` for a literal `*`.
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 /// See also [generate_pod].
@@ -137,6 +157,7 @@ macro_rules! generate {
 /// destructor - you'll encounter a compile error otherwise.
 /// If your type doesn't match that description, use [generate]
 /// instead, and own the type using [UniquePtr][cxx::UniquePtr].
+/// As with [generate], the name may contain `*` wildcards.
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
@@ -144,8 +165,44 @@ macro_rules! generate_pod {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Generate Rust bindings for the given C++ function or type, such that
+/// any C++ exception thrown by the function (or by any method or
+/// constructor of the type) is returned as an `Err` containing a
+/// [CppException] rather than terminating the program. Functions return
+/// `Result<T, CppException>`, and constructors and functions returning
+/// values by placement return an implementation of
+/// [`moveit::new::TryNew`] instead of [`moveit::new::New`].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate].
+#[macro_export]
+macro_rules! generate_fallible {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Expose the given C-style variadic function, such as
+/// `void log_printf(int level, const char* fmt, ...)`, as a raw
+/// `unsafe extern "C"` declaration just as bindgen generates it.
+/// cxx can't call variadic functions, so they're otherwise skipped.
+/// This is an escape hatch: nothing checks the variadic arguments, and
+/// they must be types which C can pass through `...` (for example,
+/// `c_int`, `c_double` or raw pointers).
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+/// See also [generate].
+#[macro_export]
+macro_rules! generate_variadic {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Generate Rust bindings for all C++ types and functions
 /// in a given namespace.
+///
+/// Nested namespaces may be left out by listing them afterwards:
+/// `generate_ns!("mylib", exclude("mylib::detail", "mylib::testing"))`.
+/// Functions in those namespaces aren't generated at all, and their
+/// types are generated only if other APIs need them, and then only
+/// as opaque types.
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 /// See also [generate].
@@ -154,6 +211,57 @@ macro_rules! generate_ns {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Generate Rust bindings for all C++ types and functions
+/// in a given namespace, as [generate_ns] does, making each type
+/// "plain old data" as [generate_pod] would wherever that's possible.
+/// Types which can't be POD are generated as normal, rather than
+/// causing an error.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! generate_ns_pod {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Never make the given C++ type "plain old data", even where
+/// [generate_ns_pod] would. Use this for types whose layout you
+/// shouldn't rely on, because it may change. The type is used in Rust
+/// only by reference or through smart pointers such as
+/// [UniquePtr][cxx::UniquePtr], and so are any types containing it.
+/// This doesn't add the type to the allowlist. Code generation fails
+/// if a type requested by [generate_pod] needs this one to be POD.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! opaque {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make the private and protected fields of a type generated by
+/// [generate_pod] public in Rust. Normally they're private, so that only
+/// the type's own methods can touch them and they don't appear in its
+/// [Debug](core::fmt::Debug) output, just as in C++. Use this if you
+/// really must get at them, bearing in mind that you may break invariants
+/// which the C++ class relies upon.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! expose_private_fields {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Replace a field of a type, given as `"Type::field"`, with opaque
+/// storage of the same size, at the same offset. The field can then no
+/// longer be used from Rust, but if its type was all that kept the type
+/// from being [generate_pod], it now can be. The C++ type must still be
+/// trivially movable and destructible to be POD.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block_field {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Generate Rust bindings for all C++ types and functions
 /// found. Highly experimental and not recommended.
 /// A directive to be included inside
@@ -171,6 +279,31 @@ macro_rules! pod {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Return C++ exceptions as errors, as [generate_fallible] does, but
+/// without adding to the allowlist. For use with [generate_all]
+/// and [generate_ns].
+#[macro_export]
+macro_rules! fallible {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Return C++ exceptions as errors, as [generate_fallible] does, from
+/// every function and method in this `include_cpp!` which can throw.
+/// That's all of them except `noexcept` functions, destructors and copy
+/// and move constructors.
+#[macro_export]
+macro_rules! catch_exceptions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Expose a C-style variadic function, as [generate_variadic] does, but
+/// without adding to the allowlist. For use with [generate_all]
+/// and [generate_ns].
+#[macro_export]
+macro_rules! variadic {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Skip the normal generation of a `make_string` function
 /// and other utilities which we might generate normally.
 /// A directive to be included inside
@@ -180,12 +313,133 @@ macro_rules! exclude_utilities {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Copy C++ comments into the Rust documentation exactly as they are.
+/// Normally autocxx translates common Doxygen (and Javadoc) markup into
+/// Markdown: `\param` and `\return` become `# Arguments` and `# Returns`
+/// sections, `\code` blocks become fenced C++ blocks, and so on. Use this
+/// if your comments aren't Doxygen and that gets in the way.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! raw_doc_comments {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// For each function (or method) taking any `const std::string&`
+/// parameters, additionally generate a variant with a `_str` suffix
+/// which accepts any `impl AsRef<str>` for all those parameters.
+/// The C++ side constructs each `std::string` from the pointer and
+/// length of the Rust string, so embedded NUL bytes are preserved.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! str_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Keep a type or function, and whatever it depends upon, even if nothing
+/// named in a [generate] directive refers to it. autocxx normally leaves
+/// out anything which isn't needed, but such items may be needed by code
+/// elsewhere, for instance in another crate. The name must match
+/// something in the C++, unless it contains a `*` wildcard.
+///
+/// The syntax is:
+/// `keep!("mylib::PluginHost")`
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! keep {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Like [keep], but for everything that bindgen came across.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! keep_all {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Keep C++ inline namespaces visible as Rust modules.
+///
+/// By default, inline namespaces are transparent, as they are in C++:
+/// given `namespace lib { inline namespace v2 { struct Thing; } }` you'd
+/// write `generate!("lib::Thing")` and use `ffi::lib::Thing`. With this
+/// directive, you'd instead write `generate!("lib::v2::Thing")` and use
+/// `ffi::lib::v2::Thing`, which may be useful to distinguish versions.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! keep_inline_namespaces {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Represent a C++ enum as a newtype over its underlying integer, with an
+/// associated constant for each enumerator, rather than as a Rust enum.
+/// This happens anyway for enums in which several enumerators have the
+/// same value; use this directive if you'd like it for others, such as
+/// those which may hold values other than their enumerators.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! enum_as_constants {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Treat a C++ enum as a set of flags. As with [enum_as_constants], the
+/// enum becomes a newtype over its underlying integer with a constant for
+/// each enumerator, so it can hold any combination of them (or any other
+/// value). It also implements `BitOr`, `BitAnd`, `BitXor` and `Not`, and
+/// the assigning versions, and has `contains` and `is_empty` methods:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "file.h"
+///     generate!("Permissions")
+///     bitflags_enum!("Permissions")
+/// );
+/// ```
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! bitflags_enum {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Give functions and methods snake_case Rust names, so that
+/// `getFooBar()` becomes `get_foo_bar()` and `setValue1()` becomes
+/// `set_value1()`. Names which are already snake_case, and the names of
+/// types, are left alone. If two names would become the same (say,
+/// `getFoo` and `get_foo`), the one which is already snake_case keeps
+/// it and the other keeps its C++ name, with a warning.
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! rename_snake_case {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Entirely block some type from appearing in the generated
 /// code. This can be useful if there is a type which is not
 /// understood by bindgen or autocxx, and incorrect code is
 /// otherwise generated.
 /// This is 'greedy' in the sense that any functions/methods
 /// which take or return such a type will _also_ be blocked.
+/// A function may be blocked in the same way, as may a single
+/// method, as in `block!("mylib::Widget::dangerous")`, leaving the
+/// rest of the type alone. (All overloads of a function or method
+/// with that name are blocked.)
+/// The name may contain `*` wildcards, such as
+/// `block!("internal::detail::*")`. Exact names take precedence
+/// over patterns, so a type named in a [generate] isn't blocked by a
+/// pattern here, and a type named here is blocked even if it matches
+/// a [generate] pattern.
 ///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
@@ -194,6 +448,18 @@ macro_rules! block {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Block everything in the given namespace, including any nested
+/// namespaces, as if each item were named by a [block]. This is
+/// useful to leave some part of a namespace out of a [generate_ns].
+/// Items named exactly by a [generate] are still generated.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block_ns {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Avoid generating implicit constructors for this type.
 /// The rules for when to generate C++ implicit constructors
 /// are complex, and if autocxx gets it wrong, you can block
@@ -206,6 +472,519 @@ macro_rules! block_constructors {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Don't offer any of this type's constructors to Rust, whether written
+/// in the C++ or implicit, so that Rust code must use the type's own
+/// factory functions, such as `static std::unique_ptr<Widget> create()`.
+/// The copy and move constructors and the destructor are still available,
+/// since they're needed to deal with objects which C++ has constructed.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! suppress_constructor {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Give this type's constructors a different Rust name from `new`, for
+/// instance so that it's clear they aren't the usual way to make one.
+/// Overloads are numbered as usual, and conversions from single-argument
+/// constructors are still called `from`.
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "widget.h"
+///     generate!("Widget")
+///     constructor_name!("Widget", "new_unregistered")
+/// );
+/// ```
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! constructor_name {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Avoid offering this type's non-`explicit` single-argument constructors
+/// as conversions. Normally a constructor such as `Milliseconds(int64_t)`
+/// gives `impl From<i64> for Milliseconds` for POD types, or a
+/// `Milliseconds::from` function returning a [`cxx::UniquePtr`] otherwise.
+/// Use this where such implicit conversions are regarded as a mistake.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block_implicit_conversions {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// autocxx implements [Debug](core::fmt::Debug) and
+/// [Display](core::fmt::Display) for any type with a const
+/// `std::string to_string()` or `std::string str()` method, and
+/// [Debug](core::fmt::Debug) alone for POD types and enums.
+/// Use this directive to prevent that for a given type, for example
+/// because its stringification is expensive or not thread-safe, or
+/// because you want to implement these traits yourself.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! block_debug {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implement [Debug](core::fmt::Debug) and [Display](core::fmt::Display)
+/// for a type which has no `to_string()` or `str()` method, such as a
+/// non-POD type. They use `operator<<(std::ostream&, const T&)` if the
+/// type has one, or otherwise print the type's name and address.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! derive_debug {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Implement [PartialEq](core::cmp::PartialEq) and [Eq](core::cmp::Eq)
+/// for a non-POD type by identity: two references are equal if they
+/// refer to the same C++ object, as judged by its `cpp_addr()` method.
+/// This is ignored for types which have an `operator==`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! identity_eq {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate `cpp_size()` and `cpp_align()` static methods for a type,
+/// which return the C++ `sizeof` and `alignof` of the type. For a non-POD
+/// type, the generated C++ checks at compile time that these agree with
+/// the `CPP_SIZE` and `CPP_ALIGN` constants generated for it.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! layout_shims {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// bindgen doesn't tell autocxx about C++ operator overloads, so use
+/// this directive to list those which a type supports. autocxx will
+/// then implement the corresponding Rust traits, for example
+/// [PartialEq](core::cmp::PartialEq) for `==`,
+/// [PartialOrd](core::cmp::PartialOrd) for `<`,
+/// [AddAssign](core::ops::AddAssign) for `+=` and
+/// [Index](core::ops::Index) for `[]`. `std::hash` isn't an operator,
+/// but listing it implements [Hash](core::hash::Hash) (and
+/// [Eq](core::cmp::Eq)) using the type's `std::hash` specialization.
+/// `<<` means `operator<<(std::ostream&, const T&)`, which implements
+/// [Display](core::fmt::Display) and a `to_cpp_string` method.
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "vec3.h"
+///     generate_pod!("Vec3")
+///     operators!("Vec3", "==", "<", "+", "+=")
+///     operators!("IntBuffer", "[]" -> "int")
+///     operators!("Handle", "==", "std::hash")
+///     operators!("Date", "<<")
+/// );
+/// ```
+///
+/// Both operands must be the type itself, except for `[]`, which takes
+/// a `size_t` and must be followed by the type to which it returns a
+/// reference. Operators other than `==`, `<`, `[]`, `std::hash` and `<<`
+/// are only supported for POD types. Indexing isn't bounds checked unless
+/// the C++ checks.
+///
+/// Types listed in [generate_pod] which aren't given `==` here get a
+/// field-by-field [PartialEq](core::cmp::PartialEq) where possible, so
+/// list `==` if the C++ type defines its own notion of equality.
+///
+/// The operators may be members, free functions, or friend functions
+/// defined within the class, since autocxx applies them as C++ would.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! operators {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Choose the Rust name of a particular overload of a function, method
+/// or constructor, rather than relying on the numeric suffixes
+/// (`set`, `set1`, `set2`...) which otherwise depend on the order of
+/// declaration. The overload is selected by its fully-qualified C++ name
+/// and parameter types, spelled as in its declaration:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "widget.h"
+///     generate!("Widget")
+///     rename!("Widget::set(double)", "set_f64")
+///     rename!("Widget::Widget(const std::string&)", "new_from_name")
+/// );
+/// ```
+///
+/// Code generation fails unless the selector matches exactly one
+/// function. Any remaining overloads are numbered as usual.
+///
+/// Without the parameter types, `rename!` instead gives a Rust name to
+/// a type, a function (all of its overloads), a method or a namespace,
+/// for instance to avoid a clash with a Rust keyword or with one of your
+/// own items. The C++ side is unaffected.
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "mylib.h"
+///     generate_ns!("mylib")
+///     rename!("mylib::Box", "CppBox")
+///     rename!("mylib::Widget::move", "relocate")
+///     rename!("mylib::detail", "internals")
+/// );
+/// ```
+///
+/// Code generation fails if the new name is already used by something
+/// else in the same place, such as another function in the same
+/// namespace or another method of the same type.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! rename {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Put the contents of a C++ namespace in a different Rust mod within
+/// the generated mod, for instance to shorten deeply nested paths.
+/// Namespaces within it come along too. Give `""` to put them directly
+/// in the generated mod. The C++ side is unaffected.
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "mylib.h"
+///     generate_ns!("company::product::module")
+///     map_namespace!("company::product::module", "pm")
+///     map_namespace!("company::product::module::detail_public", "pm")
+/// );
+/// ```
+///
+/// Several namespaces may be put in the same mod, but code generation
+/// fails if two of the items in it would then have the same name.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! map_namespace {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accept a Rust closure in place of a `std::function` parameter.
+/// bindgen doesn't tell autocxx the signature of a `std::function`, so
+/// name the function and its parameter, and give the signature as an
+/// `FnMut`:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "widget.h"
+///     generate!("Widget")
+///     closure_param!("Widget::on_progress", cb, FnMut(i32, &CxxString))
+/// );
+/// ```
+///
+/// The closure's parameters may be primitives or `&CxxString`, and it
+/// may return a primitive. It must be `'static`, since C++ may keep
+/// the `std::function`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! closure_param {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Generate an `async`-friendly wrapper for a function which takes a
+/// pointer to a callback object, and calls a method on it once some work
+/// is complete:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "net.h"
+///     safety!(unsafe_ffi)
+///     generate!("fetch")
+///     future_wrapper!("fetch", callback_param = "cb", method = "Callback::on_complete")
+/// );
+/// ```
+///
+/// As well as `fetch`, this generates `fetch_async`, which takes the
+/// other parameters and returns a [`completion::Completion`]: a future
+/// which resolves to the argument of `on_complete`. Behind the scenes this
+/// is a [`subclass!`] of `Callback` which deletes itself once
+/// `on_complete` has been called.
+///
+/// The completion method must take a single parameter, and be the callback
+/// class's only pure virtual method, and the class must be constructible
+/// without arguments. C++ may call the completion method on any thread, so
+/// the type of its parameter must be `Send`.
+///
+/// If C++ never calls the completion method, the future never resolves and
+/// the callback object is leaked. If C++ calls it again, it's calling a
+/// deleted object, just as with any callback which deletes itself.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! future_wrapper {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Accept a Rust slice in place of a pointer and length parameter pair.
+/// Name the function, then its pointer parameter and the length parameter
+/// which immediately follows it:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "filter.h"
+///     generate!("Filter")
+///     slice_params!("Filter::process", samples, count)
+/// );
+/// ```
+///
+/// The pointer must point to a primitive type. A `const` pointer
+/// becomes `&[T]` and a non-`const` one `&mut [T]`, and the length
+/// parameter disappears from the Rust signature.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! slice_params {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Return an array which a C++ function gives back through pointer out
+/// parameters, as a [CppArray]. Name the function, then the pointer to
+/// pointer parameter through which it returns the array, then the
+/// pointer parameter through which it returns the length:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "catalog.h"
+///     generate!("Catalog")
+///     out_array!("Catalog::get_items", out_items, out_count)
+/// );
+/// ```
+///
+/// If the function returns the array itself, say `return` in place of the
+/// first parameter. Both out parameters disappear from the Rust
+/// signature. If the function otherwise returns nothing, Rust callers
+/// receive just the [CppArray]; if it returns a primitive, such as an
+/// error code, they receive that and the [CppArray] as a tuple.
+///
+/// By default the array still belongs to C++, so the function is `unsafe`:
+/// you must not use the [CppArray] after C++ frees or changes the array. If
+/// instead the caller must free the array, name the C++ function which
+/// does so, and the [CppArray] will call it when dropped:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "names.h"
+///     generate!("list_names")
+///     out_array!("list_names", return, count, free_with = "free_names")
+/// );
+/// ```
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! out_array {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// bindgen doesn't tell autocxx about friend functions defined within a
+/// class, which C++ can only find by argument-dependent lookup. Use this
+/// directive to give the signature of one, naming the class then the
+/// function, and autocxx will make it available as a function in the
+/// namespace enclosing the class:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "shape.h"
+///     generate!("geo::Shape")
+///     friend_function!("geo::Shape::merge", fn(&Shape, &Shape) -> Shape)
+/// );
+/// ```
+///
+/// Write each type as Rust would see it, such as `&Shape`, `i32` or
+/// `bool`. A plain name refers to a type in the namespace enclosing the
+/// class; give the full C++ name, such as `std::string`, of any other
+/// type. At least one parameter must be the class, or a reference or
+/// pointer to it. The function is wanted whenever the class is.
+///
+/// Use [operators] rather than this directive for a friend operator, such
+/// as `==`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! friend_function {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// bindgen doesn't tell autocxx about user-defined literal operators,
+/// such as `operator""_px`. Use this directive to give the signature of
+/// one, naming the namespace then the literal suffix:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "units.h"
+///     generate!("units::Pixels")
+///     literal_operator!("units::_px", fn(u64) -> Pixels)
+/// );
+/// ```
+///
+/// Types are written as for [friend_function]. Rust can't use the
+/// literal syntax, so the operator becomes a function named for the
+/// suffix, here `units::operator__px`. Choose another name using
+/// [rename], giving the operator's C++ name, for example
+/// `rename!("units::operator\"\"_px", "px")`.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! literal_operator {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// bindgen doesn't tell autocxx about function templates, so it can't
+/// generate bindings for them. Use this directive to ask for one
+/// instantiation of a function template, giving its C++ name including
+/// the template arguments, a Rust name, and its signature:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "config.h"
+///     generate!("Config")
+///     instantiate!("Config::get<int>", "get_int", fn(&Config, &std::string) -> i32)
+///     instantiate!("Config::get<double>", "get_double", fn(&Config, &std::string) -> f64)
+/// );
+/// ```
+///
+/// Types are written as for [friend_function]. For a member function
+/// template, the first parameter is the object, `&Config` if the method is
+/// `const` and `&mut Config` otherwise, and the instantiation becomes a
+/// method such as `Config::get_int`. Without such a parameter it becomes a
+/// static method, and a function template which isn't a member becomes a
+/// function in its namespace. The instantiation is generated whenever it's
+/// described.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! instantiate {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// bindgen doesn't tell autocxx about `operator()`, so function objects
+/// would be inert from Rust. Use this directive to give the signature of
+/// a call operator, naming the class:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "filters.h"
+///     generate!("Predicate")
+///     generate!("Accumulator")
+///     call_operator!("Predicate", fn(&Predicate, &Item) -> bool)
+///     call_operator!("Accumulator", fn(&mut Accumulator, i32))
+/// );
+/// ```
+///
+/// Types are written as for [friend_function]. The first parameter is the
+/// object: `&Predicate` if the operator is `const` and `&mut Accumulator`
+/// otherwise. The operator becomes a method called `call`, and further
+/// overloads are called `call1`, `call2` and so on. A call operator which
+/// is a template can't be described this way.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! call_operator {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Require that a type is destroyed explicitly, rather than whenever
+/// its `UniquePtr` happens to go out of scope. This is useful where
+/// C++ types must be torn down in a particular order. autocxx
+/// will implement [ExplicitDestructor] for the type, so that it can
+/// be held in an [ExplicitlyDestroyed] and destroyed using
+/// [ExplicitlyDestroyed::destroy]. The type can't then be put in a
+/// `UniquePtr` or `SharedPtr`. It must have a public, non-deleted
+/// destructor, and C++17 is needed, for `std::destroy_at`.
+///
+/// There's no `destroy(self: UniquePtr<T>)` instead, because dropping any
+/// `UniquePtr` would still destroy the object, wherever that happened.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! explicit_destructor {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make a type's methods which need `Pin<&mut Self>` callable directly on
+/// a `UniquePtr` holding it, without `pin_mut()`:
+///
+/// ```
+/// # use autocxx_macro::include_cpp_impl as include_cpp;
+/// include_cpp!(
+/// #   parse_only!()
+///     #include "counter.h"
+///     generate!("Counter")
+///     unique_ptr_ext!("Counter")
+/// );
+/// ```
+///
+/// autocxx generates a trait named after the type, here `CounterExt`,
+/// and implements it for `cxx::UniquePtr<Counter>`, so that with the
+/// trait in scope you can write `counter.add(1)` rather than
+/// `counter.pin_mut().add(1)`. Like `pin_mut()`, each method panics if
+/// the `UniquePtr` is null.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! unique_ptr_ext {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// The name of the mod to be generated with the FFI code.
 /// The default is `ffi`.
 ///
@@ -217,10 +996,15 @@ macro_rules! name {
 }
 
 /// A concrete type to make, for example
-/// `concrete!("Container<Contents>")`.
+/// `concrete!("Container<Contents>", ContainerOfContents)`.
 /// All types must already be on the allowlist by having used
 /// `generate!` or similar.
 ///
+/// The template arguments may themselves be templates, or the names
+/// of other `concrete!` types, for example
+/// `concrete!("std::map<std::string, ContainerOfContents>", ContentsMap)`.
+/// Functions which use the same instantiation use this type.
+///
 /// A directive to be included inside
 /// [include_cpp] - see [include_cpp] for general information.
 #[macro_export]
@@ -278,6 +1062,29 @@ macro_rules! safety {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Keep the given function `unsafe`, whatever the [safety] policy.
+/// The name may be that of a function or of a method (as `Type::method`),
+/// covering all of its overloads, or may select a single overload by its
+/// parameter types, as in `unsafe_fn!("Buffer::write(const uint8_t*, size_t)")`.
+/// It's an error to name a function in both this and [safe_fn].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! unsafe_fn {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
+/// Make the given function safe to call, whatever the [safety] policy,
+/// even if it takes raw pointers. You're promising that you've checked
+/// that it can't cause undefined behavior, whatever it's passed. The
+/// function is named as for [unsafe_fn].
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! safe_fn {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Whether to avoid generating [`cxx::UniquePtr`] and [`cxx::Vector`]
 /// implementations. This is primarily useful for reducing test cases and
 /// shouldn't be used in normal operation.
@@ -350,6 +1157,27 @@ macro_rules! extern_cpp_opaque_type {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Use the bindings generated by an earlier `include_cpp!` in the same
+/// file for any C++ types which this `include_cpp!` would otherwise
+/// generate itself. It's as if each of them were named in an
+/// [`extern_cpp_type!`] directive (and a [`pod!`] directive, if the earlier
+/// mod made it POD).
+///
+/// The syntax is:
+/// `import_from!(path::to::other::mod)`
+///
+/// The path is used from within the generated code, just like the paths in
+/// [`extern_cpp_type!`], so it's usually simplest to begin it with `crate::`.
+/// Its last segment must be the name of the other mod, which must come
+/// earlier in this file.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! import_from {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Deprecated - use [`extern_rust_type`] instead.
 #[macro_export]
 #[deprecated]
@@ -369,6 +1197,22 @@ macro_rules! subclass {
     ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
 }
 
+/// Adds C++, verbatim, to the C++ peer class generated for a [`subclass!`].
+///
+/// The syntax is
+/// `subclass_extra_cpp!("MySubclass", "member declarations", "definitions")`,
+/// where the final argument is optional. The declarations are placed at the
+/// end of the body of the peer class, `MySubclassCpp`, in a `public:`
+/// section, and the definitions are placed in the generated `.cc` file.
+/// This is an escape hatch: autocxx doesn't check the C++ you give it.
+///
+/// A directive to be included inside
+/// [include_cpp] - see [include_cpp] for general information.
+#[macro_export]
+macro_rules! subclass_extra_cpp {
+    ($($tt:tt)*) => { $crate::usage!{$($tt)*} };
+}
+
 /// Indicates that a C++ type can definitely be instantiated. This has effect
 /// only in a very specific case:
 /// * the type is a typedef to something else
@@ -494,7 +1338,7 @@ unsafe impl cxx::ExternType for c_char16_t {
 /// autocxx couldn't generate these bindings.
 /// If you come across a method, type or function which refers to this type,
 /// it indicates that autocxx couldn't generate that binding. A documentation
-/// comment should be attached indicating the reason.
+/// comment and a deprecation note should be attached indicating the reason.
 pub struct BindingGenerationFailure {
     _unallocatable: [*const u8; 0],
     _pinned: core::marker::PhantomData<core::marker::PhantomPinned>,
@@ -668,8 +1512,62 @@ where
     }
 }
 
+/// Provides a way to copy any C++ object into a new [`cxx::UniquePtr`],
+/// using its C++ copy constructor. Automatically imported by the autocxx
+/// prelude and implemented by any type which has an accessible,
+/// non-deleted copy constructor (that is, any type which is
+/// [`moveit::CopyNew`]).
+pub trait CloneUniquePtr: UniquePtrTarget + Sized {
+    /// Copy this item into a new [`cxx::UniquePtr`].
+    fn clone_in_unique_ptr(&self) -> cxx::UniquePtr<Self>;
+}
+
+impl<T> CloneUniquePtr for T
+where
+    T: CopyNew + UniquePtrTarget + MakeCppStorage,
+{
+    fn clone_in_unique_ptr(&self) -> cxx::UniquePtr<T> {
+        UniquePtr::emplace(moveit::new::copy(self))
+    }
+}
+
+/// Types which can be moved out of a [`cxx::UniquePtr`] into a new
+/// [`cxx::SharedPtr`], which then owns the object just as if it had been
+/// created with `std::make_shared`: in particular, classes derived from
+/// `std::enable_shared_from_this` can then call `shared_from_this`.
+/// autocxx implements this for any type which is used within a
+/// `std::shared_ptr` or `std::weak_ptr` in the APIs you generate.
+pub trait SharedPtrFromUniquePtr: UniquePtrTarget + SharedPtrTarget + Sized {
+    /// Transfer ownership of this object to a [`cxx::SharedPtr`].
+    fn shared_ptr_from_unique_ptr(ptr: cxx::UniquePtr<Self>) -> cxx::SharedPtr<Self>;
+}
+
+/// Provides utility functions to emplace any [`moveit::New`] into a
+/// [`cxx::SharedPtr`], such that any of a type's constructors can be used
+/// as the equivalent of `std::make_shared`. Automatically imported by the
+/// autocxx prelude and implemented by any (autocxx-related)
+/// [`moveit::New`] whose type implements [`SharedPtrFromUniquePtr`].
+pub trait WithinSharedPtr {
+    type Inner: SharedPtrFromUniquePtr + MakeCppStorage;
+    /// Create this item within a [`cxx::SharedPtr`].
+    fn within_shared_ptr(self) -> cxx::SharedPtr<Self::Inner>;
+}
+
+impl<N, T> WithinSharedPtr for N
+where
+    N: New<Output = T>,
+    T: SharedPtrFromUniquePtr + MakeCppStorage,
+{
+    type Inner = T;
+    fn within_shared_ptr(self) -> cxx::SharedPtr<T> {
+        T::shared_ptr_from_unique_ptr(UniquePtr::emplace(self))
+    }
+}
+
+use cxx::memory::SharedPtrTarget;
 use cxx::memory::UniquePtrTarget;
 use cxx::UniquePtr;
+use moveit::CopyNew;
 use moveit::New;
 pub use rvalue_param::RValueParam;
 pub use rvalue_param::RValueParamHandler;
@@ -698,15 +1596,20 @@ pub mod prelude {
     pub use crate::include_cpp;
     pub use crate::AsCppMutRef;
     pub use crate::AsCppRef;
+    pub use crate::CloneUniquePtr;
+    pub use crate::CppArray;
+    pub use crate::CppException;
     pub use crate::CppMutRef;
     pub use crate::CppPin;
     pub use crate::CppRef;
     pub use crate::CppUniquePtrPin;
+    pub use crate::ExplicitlyDestroyed;
     pub use crate::PinMut;
     pub use crate::RValueParam;
     pub use crate::ValueParam;
     pub use crate::WithinBox;
     pub use crate::WithinBoxTrivial;
+    pub use crate::WithinSharedPtr;
     pub use crate::WithinUniquePtr;
     pub use crate::WithinUniquePtrTrivial;
     pub use cxx::UniquePtr;
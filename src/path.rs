@@ -0,0 +1,46 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for passing `std::filesystem::path` between Rust and C++.
+//!
+//! Paths cross the language boundary as bytes. On Unix, those are the
+//! native bytes of the path, which both `OsStr` and `std::filesystem::path`
+//! hold, so any path survives the round trip. Elsewhere (notably on Windows,
+//! where both languages use UTF-16 natively) they are UTF-8, and any part of
+//! a path which isn't valid Unicode is replaced with U+FFFD.
+
+use std::{borrow::Cow, path::Path, path::PathBuf};
+
+#[doc(hidden)]
+#[cfg(unix)]
+pub fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[doc(hidden)]
+#[cfg(not(unix))]
+pub fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+#[doc(hidden)]
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::OsStr::from_bytes(bytes).into()
+}
+
+#[doc(hidden)]
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    String::from_utf8_lossy(bytes).into_owned().into()
+}
@@ -64,7 +64,8 @@ pub use autocxx_macro::subclass;
 pub mod prelude {
     pub use super::{
         is_subclass, subclass, CppPeerConstructor, CppSubclass, CppSubclassDefault,
-        CppSubclassRustPeerHolder, CppSubclassSelfOwned, CppSubclassSelfOwnedDefault,
+        CppSubclassPeerDestroyed, CppSubclassRustPeerHolder, CppSubclassSelfOwned,
+        CppSubclassSelfOwnedDefault,
     };
 }
 
@@ -97,6 +98,23 @@ impl<T> CppSubclassRustPeerHolder<T> {
             _ => self,
         }
     }
+    /// Called by the C++ peer's destructor. If C++ owned the Rust side
+    /// (see [`CppSubclass::new_cpp_owned`]) the Rust side is told about
+    /// the destruction. If Rust owned the C++ side, the Rust side is
+    /// already being dropped and there's nothing to do.
+    pub fn peer_destroyed<CppPeer>(&self)
+    where
+        CppPeer: CppSubclassCppPeer,
+        T: CppSubclass<CppPeer>,
+    {
+        if let Some(me) = self.get() {
+            let mut me = me.as_ref().try_borrow_mut().expect(
+                "C++ peer of a Rust subclass destroyed whilst the subclass was borrowed - likely the C++ object was deleted during a call into Rust",
+            );
+            *me.peer_holder_mut() = CppSubclassCppPeerHolder::Empty;
+            me.notify_peer_destroyed();
+        }
+    }
 }
 
 /// A type showing how the Rust side of a Rust/C++ subclass pair refers to
@@ -110,6 +128,10 @@ pub enum CppSubclassCppPeerHolder<CppPeer: CppSubclassCppPeer> {
     Unowned(*mut CppPeer),
 }
 
+// Only C++ peers of `threadsafe` subclasses are `Send`, and for them the
+// raw pointer is no worse than the `UniquePtr`.
+unsafe impl<CppPeer: CppSubclassCppPeer + Send> Send for CppSubclassCppPeerHolder<CppPeer> {}
+
 impl<CppPeer: CppSubclassCppPeer> CppSubclassCppPeerHolder<CppPeer> {
     fn pin_mut(&mut self) -> Pin<&mut CppPeer> {
         match self {
@@ -178,9 +200,14 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
     /// Create the C++ peer. This method will be automatically generated
     /// for you *except* in cases where the superclass has multiple constructors,
     /// or its only constructor takes parameters. In such a case you'll need
-    /// to implement this by calling a `make_unique` method on the
+    /// to implement this by calling one of the `new` functions on the
     /// `<my subclass name>Cpp` type, passing `peer_holder` as the first
-    /// argument.
+    /// argument. There's one such function for each public or protected
+    /// superclass constructor, named `new`, `new1`, `new2` and so on in the
+    /// order in which the constructors are declared, just as for any other
+    /// overloaded function. Their parameters are converted just as for any
+    /// other function, so for instance a `std::string` may be passed as a
+    /// `&str`. Default arguments aren't known to autocxx, so must be passed.
     fn make_peer(&mut self, peer_holder: CppSubclassRustPeerHolder<Self>) -> UniquePtr<CppPeer>;
 }
 
@@ -249,6 +276,9 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///    for cases where the subclass is listening for events, and needs to
 ///    stick around until a particular event occurs then delete itself.
 ///
+/// Only in the first case can C++ code destroy the object pair. If you need
+/// to know when that happens, implement [`CppSubclassPeerDestroyed`].
+///
 /// # Limitations
 ///
 /// * *Re-entrancy*. The main thing to look out for is re-entrancy. If a
@@ -257,6 +287,14 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   virtual method, we will try to create two mutable references to your
 ///   subclass which isn't allowed in Rust and will therefore panic.
 ///
+///   `const` virtual methods take `&self`, so they need only shared access
+///   and can be re-entered, or called during one another, freely. Bear in
+///   mind that borrowing a [`CppSubclass::new_rust_owned`] object mutably
+///   (for instance, to call `pin_mut()` and pass it to C++) counts too:
+///   any virtual call made while that borrow is alive will panic. Use
+///   [`CppSubclass::new_cpp_owned`] if C++ needs to call back into an
+///   object it's been passed mutably.
+///
 ///   A future version of autocxx may provide the option of treating all
 ///   non-const methods (in C++) as const methods on the Rust side, which will
 ///   give the option of using interior mutability ([`std::cell::RefCell`])
@@ -265,15 +303,50 @@ pub trait CppPeerConstructor<CppPeer: CppSubclassCppPeer>: Sized {
 ///   [this issue](https://github.com/google/autocxx/issues/622).
 ///
 /// * *Thread safety*. The subclass object is not thread-safe and shouldn't
-///   be passed to different threads in C++. A future version of this code
-///   will give the option to use `Arc` and `Mutex` internally rather than
-///   `Rc` and `RefCell`, solving this problem.
+///   be passed to different threads in C++. If you know that the object pair
+///   is only ever used by one thread at a time, you can declare the subclass
+///   with `subclass!("MyCppSuperclass",Bar,threadsafe)`. The C++ peer and its
+///   holder are then `Send`, so (for example) a [`UniquePtr`] from
+///   [`CppSubclass::new_cpp_owned`] can be moved to another thread. Your Rust
+///   struct must itself be `Send`. Nothing is made `Sync`, and the
+///   [`Rc`] returned by [`CppSubclass::new_rust_owned`] still can't be sent.
+///   This is a promise that autocxx can't check: if a C++ thread calls into
+///   the subclass while another thread is using it, behavior is undefined.
+///   That's true even if both threads call `const` methods, since the
+///   bookkeeping of the [`Rc`] and [`RefCell`] isn't atomic.
+///   A future version of this code may use `Arc` and `Mutex` internally
+///   rather than `Rc` and `RefCell`, solving this problem properly.
 ///
 /// * *Protected methods.* We don't do anything clever here - they're public.
+///   Protected data members are reached through `<field>_get` and
+///   `<field>_set` methods of the C++ peer, so a superclass field `state_`
+///   can be read by `self.peer().state_get()`.
 ///
-/// * *Non-trivial class hierarchies*. We don't yet consider virtual methods
-///   on base classes of base classes. This is a temporary limitation,
+/// * *Non-trivial class hierarchies*. Virtual methods declared on base classes
+///   of the superclass can be overridden, and appear in the superclass's
+///   `_methods` trait, but only if those base classes are themselves
+///   allowlisted (for example with `generate!`). Multiple inheritance
+///   isn't otherwise handled specially,
 ///   [see this issue](https://github.com/google/autocxx/issues/610).
+///
+/// * *Templates*. The superclass can be an instantiation of a C++ template
+///   if it's named in a `concrete!` directive. The `subclass!` directive may
+///   use either the `concrete!` name or the instantiation as spelled there,
+///   so `subclass!("NodeVisitor", MyVisitor)` and
+///   `subclass!("Visitor<Node>", MyVisitor)` are equivalent, and either way
+///   the trait is `NodeVisitor_methods`. Instantiations not named by
+///   `concrete!` can't be subclassed.
+///
+/// * *`final`*. A `final` class can't be subclassed, and nor can a class
+///   with a `final` virtual method, since the C++ peer overrides every
+///   virtual method. Either way the `subclass!` is rejected with an error
+///   naming the `final` class or method.
+///
+/// * *Destructors*. The superclass may have a protected destructor, as is
+///   common for reference-counted classes, since the C++ peer declares its
+///   own public destructor. A [`UniquePtr`] can't own the superclass itself
+///   in that case, so there's no `as_<superclass>_unique_ptr` function.
+///   A superclass with a private destructor can't be subclassed at all.
 pub trait CppSubclass<CppPeer: CppSubclassCppPeer>: CppPeerConstructor<CppPeer> {
     /// Return the field which holds the C++ peer object. This is normally
     /// implemented by the #[`is_subclass`] macro, but you're welcome to
@@ -297,6 +370,21 @@ pub trait CppSubclass<CppPeer: CppSubclassCppPeer>: CppPeerConstructor<CppPeer>
         self.peer_holder_mut().pin_mut()
     }
 
+    /// Return the address of the C++ part of this object pair. This is
+    /// the same as the `cpp_addr()` of any reference to it which C++ later
+    /// hands back, even as a reference to a superclass, so you can use it
+    /// to find which Rust object C++ means. See `cpp_addr()` for the
+    /// circumstances in which C++ can't tell us the address.
+    fn peer_addr(&self) -> usize {
+        self.peer() as *const CppPeer as usize
+    }
+
+    /// Called when the C++ peer is destroyed by C++. Implemented by the
+    /// #[`is_subclass`] macro to call
+    /// [`CppSubclassPeerDestroyed::on_peer_destroyed`] if you asked for that.
+    #[doc(hidden)]
+    fn notify_peer_destroyed(&mut self) {}
+
     /// Creates a new instance of this subclass. This instance is owned by the
     /// returned [`cxx::UniquePtr`] and thus would typically be returned immediately
     /// to C++ such that it can be owned on the C++ side.
@@ -348,6 +436,21 @@ pub trait CppSubclassSelfOwned<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer
     }
 }
 
+/// Trait to be implemented by subclasses which need to know when their C++
+/// peer has been destroyed by C++ code. That can only happen for instances
+/// created using [`CppSubclass::new_cpp_owned`], since otherwise it's Rust
+/// which owns the C++ peer. Ask the #[`is_subclass`] macro to call this by
+/// specifying `#[subclass(on_peer_destroyed)]`; if you implement
+/// [`CppSubclass`] yourself, instead override its `notify_peer_destroyed`
+/// method to call this.
+pub trait CppSubclassPeerDestroyed<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer> {
+    /// Called from the destructor of the C++ peer. By this time the C++
+    /// peer is no longer available, so [`CppSubclass::peer`] will panic.
+    /// This is not called when the C++ peer is destroyed because Rust
+    /// dropped this object.
+    fn on_peer_destroyed(&mut self);
+}
+
 /// Provides default constructors for subclasses which implement `Default`.
 pub trait CppSubclassDefault<CppPeer: CppSubclassCppPeer>: CppSubclass<CppPeer> + Default {
     /// Create a Rust-owned instance of this subclass, initializing with default values. See